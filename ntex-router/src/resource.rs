@@ -13,7 +13,10 @@ pub(super) struct Segments {
 
 /// ResourceDef describes an entry in resources table
 ///
-/// Resource definition can contain only 16 dynamic segments
+/// Resource definition can contain only 16 dynamic segments. Last segment
+/// could be catch-all segment, `{tail}*`, `{*tail}` or `{tail:.*}`, it
+/// matches remainder of the path including slashes. Catch-all values are
+/// not percent-decoded.
 #[derive(Clone, Debug)]
 pub struct ResourceDef {
     id: u16,
@@ -135,6 +138,11 @@ impl ResourceDef {
         ResourceDef::with_prefix(&insert_slash(path), true)
     }
 
+    /// Check if resource definition is a prefix match
+    pub fn is_prefix(&self) -> bool {
+        self.prefix
+    }
+
     /// Resource id
     pub fn id(&self) -> u16 {
         self.id
@@ -269,23 +277,36 @@ impl ResourceDef {
             let param = &p.0[1..p.0.len() - 1]; // Remove outer brackets
             tail = rem == "*"; // tail match (should match regardless of segments)
 
-            let (name, pat) = match param.find(':') {
-                Some(idx) => {
-                    if tail {
-                        panic!("Custom regex is not supported for remainder match");
+            let (name, pat) = if param.starts_with('*') {
+                // `{*name}` catch-all match
+                if !rem.is_empty() {
+                    panic!("Catch-all segment must be the last segment");
+                }
+                tail = true;
+                (&param[1..], DEFAULT_PATTERN_TAIL)
+            } else {
+                match param.find(':') {
+                    Some(idx) => {
+                        if tail {
+                            panic!("Custom regex is not supported for remainder match");
+                        }
+                        let (name, pattern) = param.split_at(idx);
+                        let pattern = &pattern[1..];
+
+                        // `{name:.*}` at the end of pattern is catch-all match
+                        tail = rem.is_empty() && pattern == DEFAULT_PATTERN_TAIL;
+                        (name, pattern)
                     }
-                    let (name, pattern) = param.split_at(idx);
-                    (name, &pattern[1..])
+                    None => (
+                        param,
+                        if tail {
+                            rem = &rem[1..];
+                            DEFAULT_PATTERN_TAIL
+                        } else {
+                            DEFAULT_PATTERN
+                        },
+                    ),
                 }
-                None => (
-                    param,
-                    if tail {
-                        rem = &rem[1..];
-                        DEFAULT_PATTERN_TAIL
-                    } else {
-                        DEFAULT_PATTERN
-                    },
-                ),
             };
 
             re.push_str(&format!(r"(?P<{}>{})", &escape(name), pat));
@@ -713,6 +734,38 @@ mod tests {
         assert_eq!(resource.get("id").unwrap(), "2345/sdg");
     }
 
+    #[test]
+    fn test_parse_catch_all() {
+        for pattern in &["/files/{*tail}", "/files/{tail:.*}", "/files/{tail}*"] {
+            let re = ResourceDef::new(*pattern);
+            let tree = Tree::new(&re, 1);
+
+            let mut resource = Path::new("/files/");
+            assert_eq!(tree.find(&mut resource), Some(1));
+            assert_eq!(resource.get("tail").unwrap(), "");
+
+            let mut resource = Path::new("/files/css/main.css");
+            assert_eq!(tree.find(&mut resource), Some(1));
+            assert_eq!(resource.get("tail").unwrap(), "css/main.css");
+
+            let mut resource = Path::new("/files/a%20b/c%2Fd");
+            assert_eq!(tree.find(&mut resource), Some(1));
+            assert_eq!(resource.get("tail").unwrap(), "a%20b/c%2Fd");
+
+            assert_eq!(tree.find(&mut Path::new("/other/css")), None);
+        }
+
+        let re = ResourceDef::new("/files/{name:.+}");
+        let tree = Tree::new(&re, 1);
+        assert_eq!(tree.find(&mut Path::new("/files/a/b")), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "Catch-all segment must be the last segment")]
+    fn test_parse_catch_all_not_last() {
+        ResourceDef::new("/files/{*tail}/index.html");
+    }
+
     #[test]
     fn test_static_tail() {
         let re = ResourceDef::new("/*");
@@ -0,0 +1,512 @@
+use std::cell::Cell;
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::rc::Rc;
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+
+use futures_util::future::{err, ok, Either, Ready};
+
+use crate::{Service, Transform};
+
+/// Circuit breaker state
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum CircuitState {
+    /// Requests are passed to the service, failures are counted
+    Closed,
+    /// Requests are rejected with `CircuitOpen` error
+    Open,
+    /// Limited number of probe requests are passed to the service
+    HalfOpen,
+}
+
+/// Service call is rejected because circuit is open
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct CircuitOpen;
+
+impl fmt::Display for CircuitOpen {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Circuit is open")
+    }
+}
+
+impl std::error::Error for CircuitOpen {}
+
+/// Circuit breaker transform.
+///
+/// Circuit starts in closed state, service responses are classified as
+/// success or failure. If failure rate within window reaches threshold,
+/// circuit opens and all calls are rejected with `CircuitOpen` error, it
+/// is converted to service error with `Into` trait. After probe interval
+/// circuit goes to half-open state and passes limited number of probe
+/// calls to the service. Circuit closes if all probes succeed and opens
+/// again if any probe fails.
+///
+/// State is tracked per service instance. By default any service error is
+/// failure, circuit opens if half of at least 10 requests within
+/// 10 seconds window fail, probe interval is 5 seconds.
+///
+/// ```rust
+/// use std::time::Duration;
+/// use ntex_service::{apply, fn_service, CircuitBreaker, CircuitOpen, ServiceFactory};
+///
+/// #[derive(Debug)]
+/// enum Error {
+///     Service,
+///     Unavailable,
+/// }
+///
+/// impl From<CircuitOpen> for Error {
+///     fn from(_: CircuitOpen) -> Self {
+///         Error::Unavailable
+///     }
+/// }
+///
+/// let factory = apply(
+///     CircuitBreaker::new()
+///         .failure_rate(25)
+///         .probe_interval(Duration::from_secs(1)),
+///     fn_service(|req: u32| async move {
+///         if req == 0 {
+///             Err(Error::Service)
+///         } else {
+///             Ok(req)
+///         }
+///     }),
+/// );
+/// let fut = factory.new_service(());
+/// ```
+pub struct CircuitBreaker<R, E> {
+    cfg: Rc<Config<R, E>>,
+}
+
+struct Config<R, E> {
+    failure_rate: usize,
+    min_requests: usize,
+    window: Duration,
+    probe_interval: Duration,
+    probes: usize,
+    failure: Box<dyn Fn(&Result<R, E>) -> bool>,
+}
+
+impl<R, E> Default for CircuitBreaker<R, E> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<R, E> CircuitBreaker<R, E> {
+    /// Construct circuit breaker with default settings
+    pub fn new() -> Self {
+        CircuitBreaker {
+            cfg: Rc::new(Config {
+                failure_rate: 50,
+                min_requests: 10,
+                window: Duration::from_secs(10),
+                probe_interval: Duration::from_secs(5),
+                probes: 1,
+                failure: Box::new(|res| res.is_err()),
+            }),
+        }
+    }
+
+    /// Set failure rate threshold in percents.
+    ///
+    /// By default threshold is 50%.
+    pub fn failure_rate(mut self, percent: u8) -> Self {
+        self.cfg_mut().failure_rate = std::cmp::min(percent, 100) as usize;
+        self
+    }
+
+    /// Set minimum number of requests within window required for
+    /// failure rate evaluation.
+    ///
+    /// By default it is 10 requests.
+    pub fn min_requests(mut self, num: usize) -> Self {
+        self.cfg_mut().min_requests = num;
+        self
+    }
+
+    /// Set failure rate window.
+    ///
+    /// Failures are counted for fixed time window, counters get reset
+    /// when window expires. By default window is 10 seconds.
+    pub fn window(mut self, window: Duration) -> Self {
+        self.cfg_mut().window = window;
+        self
+    }
+
+    /// Set time circuit stays open before probing the service.
+    ///
+    /// By default probe interval is 5 seconds.
+    pub fn probe_interval(mut self, interval: Duration) -> Self {
+        self.cfg_mut().probe_interval = interval;
+        self
+    }
+
+    /// Set number of successful probe calls required for closing circuit.
+    ///
+    /// Calls beyond this number are rejected while circuit is half-open.
+    /// By default one probe call is used.
+    pub fn probes(mut self, num: usize) -> Self {
+        self.cfg_mut().probes = std::cmp::max(num, 1);
+        self
+    }
+
+    /// Set function that classifies service result as failure.
+    ///
+    /// By default any service error is failure.
+    pub fn failure<F>(mut self, f: F) -> Self
+    where
+        F: Fn(&Result<R, E>) -> bool + 'static,
+    {
+        self.cfg_mut().failure = Box::new(f);
+        self
+    }
+
+    fn cfg_mut(&mut self) -> &mut Config<R, E> {
+        Rc::get_mut(&mut self.cfg).expect("Multiple copies exist")
+    }
+}
+
+impl<R, E> Clone for CircuitBreaker<R, E> {
+    fn clone(&self) -> Self {
+        CircuitBreaker {
+            cfg: self.cfg.clone(),
+        }
+    }
+}
+
+impl<S> Transform<S> for CircuitBreaker<S::Response, S::Error>
+where
+    S: Service,
+    CircuitOpen: Into<S::Error>,
+{
+    type Request = S::Request;
+    type Response = S::Response;
+    type Error = S::Error;
+    type InitError = ();
+    type Transform = CircuitBreakerService<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ok(CircuitBreakerService {
+            service,
+            state: Rc::new(State {
+                cfg: self.cfg.clone(),
+                state: Cell::new(CircuitState::Closed),
+                epoch: Cell::new(0),
+                since: Cell::new(Instant::now()),
+                requests: Cell::new(0),
+                failures: Cell::new(0),
+                probes: Cell::new(0),
+                successes: Cell::new(0),
+            }),
+        })
+    }
+}
+
+struct State<R, E> {
+    cfg: Rc<Config<R, E>>,
+    state: Cell<CircuitState>,
+    epoch: Cell<usize>,
+    since: Cell<Instant>,
+    requests: Cell<usize>,
+    failures: Cell<usize>,
+    probes: Cell<usize>,
+    successes: Cell<usize>,
+}
+
+impl<R, E> State<R, E> {
+    fn current(&self) -> CircuitState {
+        let state = self.state.get();
+        if state == CircuitState::Open
+            && self.since.get().elapsed() >= self.cfg.probe_interval
+        {
+            self.set(CircuitState::HalfOpen);
+            CircuitState::HalfOpen
+        } else {
+            state
+        }
+    }
+
+    fn set(&self, state: CircuitState) {
+        self.state.set(state);
+        self.epoch.set(self.epoch.get().wrapping_add(1));
+        self.reset();
+    }
+
+    fn reset(&self) {
+        self.since.set(Instant::now());
+        self.requests.set(0);
+        self.failures.set(0);
+        self.probes.set(0);
+        self.successes.set(0);
+    }
+
+    /// Check if call is allowed, returns current epoch
+    fn acquire(&self) -> Option<usize> {
+        match self.current() {
+            CircuitState::Closed => {
+                if self.since.get().elapsed() >= self.cfg.window {
+                    self.reset();
+                }
+                Some(self.epoch.get())
+            }
+            CircuitState::HalfOpen => {
+                if self.probes.get() < self.cfg.probes {
+                    self.probes.set(self.probes.get() + 1);
+                    Some(self.epoch.get())
+                } else {
+                    None
+                }
+            }
+            CircuitState::Open => None,
+        }
+    }
+
+    /// Record call result, `None` if call got canceled
+    fn release(&self, epoch: usize, failed: Option<bool>) {
+        // state changed since call started
+        if epoch != self.epoch.get() {
+            return;
+        }
+
+        match self.state.get() {
+            CircuitState::Closed => {
+                if let Some(failed) = failed {
+                    let requests = self.requests.get() + 1;
+                    self.requests.set(requests);
+                    if failed {
+                        let failures = self.failures.get() + 1;
+                        self.failures.set(failures);
+                        if requests >= self.cfg.min_requests
+                            && failures * 100 >= requests * self.cfg.failure_rate
+                        {
+                            self.set(CircuitState::Open);
+                        }
+                    }
+                }
+            }
+            CircuitState::HalfOpen => match failed {
+                Some(true) => self.set(CircuitState::Open),
+                Some(false) => {
+                    let successes = self.successes.get() + 1;
+                    self.successes.set(successes);
+                    if successes >= self.cfg.probes {
+                        self.set(CircuitState::Closed);
+                    }
+                }
+                // canceled probe does not count
+                None => self.probes.set(self.probes.get() - 1),
+            },
+            CircuitState::Open => (),
+        }
+    }
+}
+
+/// Circuit breaker service
+pub struct CircuitBreakerService<S: Service> {
+    service: S,
+    state: Rc<State<S::Response, S::Error>>,
+}
+
+impl<S: Service> CircuitBreakerService<S> {
+    /// Current circuit state
+    pub fn state(&self) -> CircuitState {
+        self.state.current()
+    }
+}
+
+impl<S> Service for CircuitBreakerService<S>
+where
+    S: Service,
+    CircuitOpen: Into<S::Error>,
+{
+    type Request = S::Request;
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future =
+        Either<CircuitBreakerResponse<S>, Ready<Result<S::Response, S::Error>>>;
+
+    #[inline]
+    fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(cx)
+    }
+
+    #[inline]
+    fn poll_shutdown(&self, cx: &mut Context<'_>, is_error: bool) -> Poll<()> {
+        self.service.poll_shutdown(cx, is_error)
+    }
+
+    fn call(&self, req: S::Request) -> Self::Future {
+        if let Some(epoch) = self.state.acquire() {
+            Either::Left(CircuitBreakerResponse {
+                fut: self.service.call(req),
+                guard: Guard {
+                    epoch,
+                    state: self.state.clone(),
+                    completed: false,
+                },
+            })
+        } else {
+            Either::Right(err(CircuitOpen.into()))
+        }
+    }
+}
+
+struct Guard<R, E> {
+    epoch: usize,
+    state: Rc<State<R, E>>,
+    completed: bool,
+}
+
+impl<R, E> Drop for Guard<R, E> {
+    fn drop(&mut self) {
+        if !self.completed {
+            self.state.release(self.epoch, None);
+        }
+    }
+}
+
+#[doc(hidden)]
+#[pin_project::pin_project]
+pub struct CircuitBreakerResponse<S: Service> {
+    #[pin]
+    fut: S::Future,
+    guard: Guard<S::Response, S::Error>,
+}
+
+impl<S: Service> Future for CircuitBreakerResponse<S> {
+    type Output = Result<S::Response, S::Error>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+
+        match this.fut.poll(cx) {
+            Poll::Ready(res) => {
+                let guard = this.guard;
+                guard.completed = true;
+                guard
+                    .state
+                    .release(guard.epoch, Some((guard.state.cfg.failure)(&res)));
+                Poll::Ready(res)
+            }
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures_util::future::{lazy, pending, FutureExt, LocalBoxFuture};
+
+    use super::*;
+    use crate::{fn_service, Service};
+
+    #[derive(Debug, PartialEq)]
+    enum Error {
+        Service,
+        Open,
+    }
+
+    impl From<CircuitOpen> for Error {
+        fn from(_: CircuitOpen) -> Self {
+            Error::Open
+        }
+    }
+
+    fn service() -> impl Service<
+        Request = u32,
+        Response = u32,
+        Error = Error,
+        Future = LocalBoxFuture<'static, Result<u32, Error>>,
+    > {
+        fn_service(|req: u32| {
+            async move {
+                match req {
+                    0 => Err(Error::Service),
+                    1 => pending().await,
+                    _ => Ok(req),
+                }
+            }
+            .boxed_local()
+        })
+    }
+
+    #[ntex_rt::test]
+    async fn test_open() {
+        let srv = CircuitBreaker::new()
+            .min_requests(4)
+            .failure_rate(50)
+            .probe_interval(Duration::from_millis(50))
+            .new_transform(service())
+            .await
+            .unwrap();
+        assert_eq!(srv.state(), CircuitState::Closed);
+        assert!(lazy(|cx| srv.poll_ready(cx)).await.is_ready());
+
+        assert_eq!(srv.call(2).await, Ok(2));
+        assert_eq!(srv.call(2).await, Ok(2));
+        assert_eq!(srv.call(0).await, Err(Error::Service));
+        assert_eq!(srv.state(), CircuitState::Closed);
+        assert_eq!(srv.call(0).await, Err(Error::Service));
+        assert_eq!(srv.state(), CircuitState::Open);
+        assert_eq!(srv.call(2).await, Err(Error::Open));
+
+        // failed probe
+        std::thread::sleep(Duration::from_millis(60));
+        assert_eq!(srv.state(), CircuitState::HalfOpen);
+        assert_eq!(srv.call(0).await, Err(Error::Service));
+        assert_eq!(srv.state(), CircuitState::Open);
+
+        // successful probe
+        std::thread::sleep(Duration::from_millis(60));
+        assert_eq!(srv.call(2).await, Ok(2));
+        assert_eq!(srv.state(), CircuitState::Closed);
+        assert_eq!(srv.call(0).await, Err(Error::Service));
+        assert_eq!(srv.state(), CircuitState::Closed);
+    }
+
+    #[ntex_rt::test]
+    async fn test_probes() {
+        let srv = CircuitBreaker::new()
+            .min_requests(1)
+            .probes(2)
+            .probe_interval(Duration::from_millis(10))
+            .new_transform(service())
+            .await
+            .unwrap();
+
+        assert_eq!(srv.call(0).await, Err(Error::Service));
+        assert_eq!(srv.state(), CircuitState::Open);
+        std::thread::sleep(Duration::from_millis(20));
+
+        // only two probes are allowed
+        let fut = srv.call(1);
+        assert_eq!(srv.call(2).await, Ok(2));
+        assert_eq!(srv.call(2).await, Err(Error::Open));
+        assert_eq!(srv.state(), CircuitState::HalfOpen);
+
+        // canceled probe releases slot
+        drop(fut);
+        assert_eq!(srv.call(2).await, Ok(2));
+        assert_eq!(srv.state(), CircuitState::Closed);
+    }
+
+    #[ntex_rt::test]
+    async fn test_failure() {
+        let srv = CircuitBreaker::new()
+            .min_requests(1)
+            .failure(|res: &Result<u32, Error>| res == &Ok(3))
+            .new_transform(service())
+            .await
+            .unwrap();
+
+        assert_eq!(srv.call(0).await, Err(Error::Service));
+        assert_eq!(srv.state(), CircuitState::Closed);
+        assert_eq!(srv.call(3).await, Ok(3));
+        assert_eq!(srv.state(), CircuitState::Open);
+    }
+}
@@ -0,0 +1,312 @@
+use std::future::Future;
+use std::marker::PhantomData;
+use std::pin::Pin;
+use std::rc::Rc;
+use std::task::{Context, Poll};
+
+use crate::{IntoServiceFactory, Service, ServiceFactory, Transform};
+
+/// Create `Transform` from async function.
+///
+/// Function receives inner service and constructs middleware service
+/// asynchronously, so middleware could perform async initialization,
+/// like fetching remote configuration, before it starts processing
+/// requests. Function's error type becomes transform's `InitError`,
+/// use `Transform::map_init_err()` to convert it to the init error type
+/// of the wrapped factory.
+///
+/// ```rust
+/// use std::task::{Context, Poll};
+/// use futures_util::future::ok;
+/// use ntex_service::{apply, fn_service, fn_transform, Service, ServiceFactory, Transform};
+///
+/// struct Prefix<S> {
+///     prefix: String,
+///     service: S,
+/// }
+///
+/// impl<S: Service<Request = String>> Service for Prefix<S> {
+///     type Request = String;
+///     type Response = S::Response;
+///     type Error = S::Error;
+///     type Future = S::Future;
+///
+///     fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<Result<(), S::Error>> {
+///         self.service.poll_ready(cx)
+///     }
+///
+///     fn call(&self, req: String) -> S::Future {
+///         self.service.call(format!("{}{}", self.prefix, req))
+///     }
+/// }
+///
+/// async fn fetch_prefix() -> Result<String, std::io::Error> {
+///     Ok("hello ".to_string())
+/// }
+///
+/// let factory = apply(
+///     fn_transform(|service| async move {
+///         let prefix = fetch_prefix().await?;
+///         Ok::<_, std::io::Error>(Prefix { prefix, service })
+///     })
+///     .map_init_err(|_| ()),
+///     fn_service(|req: String| ok::<_, ()>(req)),
+/// );
+/// let fut = factory.new_service(());
+/// ```
+pub fn fn_transform<F, S, R, T, E>(f: F) -> FnTransform<F, S, R, T, E>
+where
+    F: Fn(S) -> R,
+    R: Future<Output = Result<T, E>>,
+    T: Service,
+{
+    FnTransform { f, _t: PhantomData }
+}
+
+/// Transform that constructs middleware service with async function
+pub struct FnTransform<F, S, R, T, E> {
+    f: F,
+    _t: PhantomData<fn(S) -> (R, T, E)>,
+}
+
+impl<F, S, R, T, E> Clone for FnTransform<F, S, R, T, E>
+where
+    F: Clone,
+{
+    fn clone(&self) -> Self {
+        FnTransform {
+            f: self.f.clone(),
+            _t: PhantomData,
+        }
+    }
+}
+
+impl<F, S, R, T, E> Transform<S> for FnTransform<F, S, R, T, E>
+where
+    F: Fn(S) -> R,
+    R: Future<Output = Result<T, E>>,
+    T: Service,
+{
+    type Request = T::Request;
+    type Response = T::Response;
+    type Error = T::Error;
+    type Transform = T;
+    type InitError = E;
+    type Future = R;
+
+    #[inline]
+    fn new_transform(&self, service: S) -> Self::Future {
+        (self.f)(service)
+    }
+}
+
+/// Create service factory from async transform function that receives
+/// factory config.
+///
+/// `Transform` does not receive config, so `fn_transform()` could not
+/// use it for middleware construction. This variant constructs inner
+/// service with the config, then function receives the config and inner
+/// service and constructs middleware service. For example, for web
+/// application factory config is `AppConfig`. Function's error type must
+/// match init error of the inner factory.
+///
+/// ```rust
+/// use futures_util::future::ok;
+/// use ntex_service::{apply_fn, fn_service, fn_transform_with_config, Service, ServiceFactory};
+///
+/// let factory = fn_transform_with_config(
+///     |prefix: String, service| async move {
+///         // async initialization that depends on config
+///         Ok(apply_fn(service, move |req: String, srv| {
+///             srv.call(format!("{}{}", prefix, req))
+///         }))
+///     },
+///     fn_service(|req: String| ok::<_, ()>(req)),
+/// );
+/// let fut = factory.new_service("hello ".to_string());
+/// ```
+pub fn fn_transform_with_config<F, S, U, R, T>(
+    f: F,
+    factory: U,
+) -> FnTransformConfig<F, S, R, T>
+where
+    S: ServiceFactory,
+    S::Config: Clone,
+    U: IntoServiceFactory<S>,
+    F: Fn(S::Config, S::Service) -> R,
+    R: Future<Output = Result<T, S::InitError>>,
+    T: Service,
+{
+    FnTransformConfig {
+        store: Rc::new((f, factory.into_factory())),
+        _t: PhantomData,
+    }
+}
+
+/// Service factory that constructs middleware service with async function
+/// and factory config
+pub struct FnTransformConfig<F, S, R, T> {
+    store: Rc<(F, S)>,
+    _t: PhantomData<fn() -> (R, T)>,
+}
+
+impl<F, S, R, T> Clone for FnTransformConfig<F, S, R, T> {
+    fn clone(&self) -> Self {
+        FnTransformConfig {
+            store: self.store.clone(),
+            _t: PhantomData,
+        }
+    }
+}
+
+impl<F, S, R, T> ServiceFactory for FnTransformConfig<F, S, R, T>
+where
+    S: ServiceFactory,
+    S::Config: Clone,
+    F: Fn(S::Config, S::Service) -> R,
+    R: Future<Output = Result<T, S::InitError>>,
+    T: Service,
+{
+    type Request = T::Request;
+    type Response = T::Response;
+    type Error = T::Error;
+
+    type Config = S::Config;
+    type Service = T;
+    type InitError = S::InitError;
+    type Future = FnTransformConfigFuture<F, S, R, T>;
+
+    fn new_service(&self, cfg: S::Config) -> Self::Future {
+        FnTransformConfigFuture {
+            store: self.store.clone(),
+            state: FnTransformConfigState::A(
+                self.store.1.new_service(cfg.clone()),
+                Some(cfg),
+            ),
+        }
+    }
+}
+
+#[pin_project::pin_project]
+pub struct FnTransformConfigFuture<F, S, R, T>
+where
+    S: ServiceFactory,
+    F: Fn(S::Config, S::Service) -> R,
+    R: Future<Output = Result<T, S::InitError>>,
+{
+    store: Rc<(F, S)>,
+    #[pin]
+    state: FnTransformConfigState<S, R>,
+}
+
+#[pin_project::pin_project]
+pub enum FnTransformConfigState<S, R>
+where
+    S: ServiceFactory,
+{
+    A(#[pin] S::Future, Option<S::Config>),
+    B(#[pin] R),
+}
+
+impl<F, S, R, T> Future for FnTransformConfigFuture<F, S, R, T>
+where
+    S: ServiceFactory,
+    F: Fn(S::Config, S::Service) -> R,
+    R: Future<Output = Result<T, S::InitError>>,
+{
+    type Output = Result<T, S::InitError>;
+
+    #[pin_project::project]
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut this = self.as_mut().project();
+
+        #[project]
+        match this.state.as_mut().project() {
+            FnTransformConfigState::A(fut, cfg) => match fut.poll(cx)? {
+                Poll::Ready(srv) => {
+                    let cfg = cfg.take().unwrap();
+                    let fut = (this.store.0)(cfg, srv);
+                    this.state.set(FnTransformConfigState::B(fut));
+                    self.poll(cx)
+                }
+                Poll::Pending => Poll::Pending,
+            },
+            FnTransformConfigState::B(fut) => fut.poll(cx),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::task::{Context, Poll};
+
+    use futures_util::future::{err, ok};
+
+    use super::*;
+    use crate::{apply, fn_service, ServiceFactory};
+
+    struct Mul<S> {
+        mul: usize,
+        service: S,
+    }
+
+    impl<S: Service<Request = usize>> Service for Mul<S> {
+        type Request = usize;
+        type Response = S::Response;
+        type Error = S::Error;
+        type Future = S::Future;
+
+        fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<Result<(), S::Error>> {
+            self.service.poll_ready(cx)
+        }
+
+        fn call(&self, req: usize) -> S::Future {
+            self.service.call(req * self.mul)
+        }
+    }
+
+    #[ntex_rt::test]
+    async fn test_fn_transform() {
+        let factory = apply(
+            fn_transform(|service| async move {
+                let mul = ok::<_, ()>(2).await?;
+                Ok(Mul { mul, service })
+            }),
+            fn_service(|req: usize| ok::<_, ()>(req + 1)),
+        );
+        let srv = factory.new_service(()).await.unwrap();
+        assert_eq!(srv.call(10).await, Ok(21));
+    }
+
+    #[ntex_rt::test]
+    async fn test_init_err() {
+        let factory = apply(
+            fn_transform(|service| async move {
+                err::<(), _>("init error").await?;
+                Ok::<_, &'static str>(Mul { mul: 1, service })
+            })
+            .map_init_err(|_| ()),
+            fn_service(|req: usize| ok::<_, ()>(req)),
+        );
+        assert!(factory.new_service(()).await.is_err());
+    }
+
+    #[ntex_rt::test]
+    async fn test_fn_transform_with_config() {
+        let factory = fn_transform_with_config(
+            |mul: usize, service| async move {
+                if mul == 0 {
+                    Err(())
+                } else {
+                    Ok(Mul { mul, service })
+                }
+            },
+            crate::fn_factory_with_config(|add: usize| {
+                ok::<_, ()>(fn_service(move |req: usize| ok::<_, ()>(req + add)))
+            }),
+        );
+        let srv = factory.clone().new_service(3).await.unwrap();
+        assert_eq!(srv.call(10).await, Ok(33));
+        assert!(factory.new_service(0).await.is_err());
+    }
+}
@@ -0,0 +1,215 @@
+use std::cell::Cell;
+use std::rc::Rc;
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+
+use futures_util::future::{ok, Ready};
+
+use crate::{Service, Transform};
+
+/// Service readiness state transition
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Readiness {
+    /// Service is not ready to accept requests
+    Pending,
+    /// Service is ready again, contains time spent in pending state
+    Ready(Duration),
+    /// Readiness check returned error, contains time spent in
+    /// pending state
+    Failed(Duration),
+}
+
+/// Instrument service readiness.
+///
+/// Callback is called on every readiness state transition of the service,
+/// repeated `poll_ready` calls with the same result are not reported.
+/// Callback receives instrumentation name and state transition, it
+/// could be used for locating backpressure points in composed pipelines.
+///
+/// ```rust
+/// use ntex_service::{apply, fn_service, instrument, Readiness, ServiceFactory};
+///
+/// let factory = apply(
+///     instrument("db", |name: &str, state: Readiness| {
+///         if let Readiness::Ready(pending) = state {
+///             println!("{} was busy for {:?}", name, pending);
+///         }
+///     }),
+///     fn_service(|req: u32| async move { Ok::<_, ()>(req) }),
+/// );
+/// let fut = factory.new_service(());
+/// ```
+pub fn instrument<F>(name: &'static str, f: F) -> Instrument<F>
+where
+    F: Fn(&str, Readiness),
+{
+    Instrument {
+        name,
+        f: Rc::new(f),
+    }
+}
+
+/// Readiness instrumentation transform
+pub struct Instrument<F> {
+    name: &'static str,
+    f: Rc<F>,
+}
+
+impl<F> Clone for Instrument<F> {
+    fn clone(&self) -> Self {
+        Instrument {
+            name: self.name,
+            f: self.f.clone(),
+        }
+    }
+}
+
+impl<S, F> Transform<S> for Instrument<F>
+where
+    S: Service,
+    F: Fn(&str, Readiness),
+{
+    type Request = S::Request;
+    type Response = S::Response;
+    type Error = S::Error;
+    type InitError = ();
+    type Transform = InstrumentService<S, F>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ok(InstrumentService::new(self.name, service, self.f.clone()))
+    }
+}
+
+/// Readiness instrumentation service
+pub struct InstrumentService<S, F> {
+    name: &'static str,
+    service: S,
+    f: Rc<F>,
+    pending: Cell<Option<Instant>>,
+}
+
+impl<S, F> InstrumentService<S, F>
+where
+    S: Service,
+    F: Fn(&str, Readiness),
+{
+    fn new(name: &'static str, service: S, f: Rc<F>) -> Self {
+        InstrumentService {
+            name,
+            service,
+            f,
+            pending: Cell::new(None),
+        }
+    }
+}
+
+impl<S, F> Service for InstrumentService<S, F>
+where
+    S: Service,
+    F: Fn(&str, Readiness),
+{
+    type Request = S::Request;
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = S::Future;
+
+    fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        match self.service.poll_ready(cx) {
+            Poll::Ready(Ok(())) => {
+                if let Some(start) = self.pending.take() {
+                    (self.f)(self.name, Readiness::Ready(start.elapsed()));
+                }
+                Poll::Ready(Ok(()))
+            }
+            Poll::Ready(Err(e)) => {
+                let pending = self
+                    .pending
+                    .take()
+                    .map(|start| start.elapsed())
+                    .unwrap_or_default();
+                (self.f)(self.name, Readiness::Failed(pending));
+                Poll::Ready(Err(e))
+            }
+            Poll::Pending => {
+                if self.pending.get().is_none() {
+                    self.pending.set(Some(Instant::now()));
+                    (self.f)(self.name, Readiness::Pending);
+                }
+                Poll::Pending
+            }
+        }
+    }
+
+    #[inline]
+    fn poll_shutdown(&self, cx: &mut Context<'_>, is_error: bool) -> Poll<()> {
+        self.service.poll_shutdown(cx, is_error)
+    }
+
+    #[inline]
+    fn call(&self, req: S::Request) -> Self::Future {
+        self.service.call(req)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+
+    use futures_util::future::{lazy, Ready};
+
+    use super::*;
+
+    struct Srv(Rc<Cell<Poll<Result<(), ()>>>>);
+
+    impl Service for Srv {
+        type Request = ();
+        type Response = ();
+        type Error = ();
+        type Future = Ready<Result<(), ()>>;
+
+        fn poll_ready(&self, _: &mut Context<'_>) -> Poll<Result<(), ()>> {
+            self.0.get()
+        }
+
+        fn call(&self, _: ()) -> Self::Future {
+            ok(())
+        }
+    }
+
+    #[ntex_rt::test]
+    async fn test_transitions() {
+        let state = Rc::new(Cell::new(Poll::Ready(Ok(()))));
+        let events = Rc::new(RefCell::new(Vec::new()));
+        let events2 = events.clone();
+
+        let srv = instrument("srv", move |name: &str, state: Readiness| {
+            assert_eq!(name, "srv");
+            events2.borrow_mut().push(state);
+        })
+        .new_transform(Srv(state.clone()))
+        .await
+        .unwrap();
+
+        let _ = lazy(|cx| srv.poll_ready(cx)).await;
+        assert!(events.borrow().is_empty());
+
+        state.set(Poll::Pending);
+        let _ = lazy(|cx| srv.poll_ready(cx)).await;
+        let _ = lazy(|cx| srv.poll_ready(cx)).await;
+        assert_eq!(&*events.borrow(), &[Readiness::Pending]);
+
+        state.set(Poll::Ready(Ok(())));
+        let _ = lazy(|cx| srv.poll_ready(cx)).await;
+        assert_eq!(events.borrow().len(), 2);
+        assert!(matches!(events.borrow()[1], Readiness::Ready(_)));
+
+        state.set(Poll::Ready(Err(())));
+        let res = lazy(|cx| srv.poll_ready(cx)).await;
+        assert_eq!(res, Poll::Ready(Err(())));
+        assert_eq!(
+            events.borrow()[2],
+            Readiness::Failed(Duration::from_secs(0))
+        );
+    }
+}
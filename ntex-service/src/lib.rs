@@ -11,7 +11,10 @@ mod and_then_apply_fn;
 mod apply;
 mod apply_cfg;
 pub mod boxed;
+mod circuit;
 mod fn_service;
+mod fn_transform;
+mod instrument;
 mod map;
 mod map_config;
 mod map_err;
@@ -23,9 +26,14 @@ mod transform_err;
 
 pub use self::apply::{apply_fn, apply_fn_factory};
 pub use self::apply_cfg::{apply_cfg, apply_cfg_factory};
+pub use self::circuit::{
+    CircuitBreaker, CircuitBreakerService, CircuitOpen, CircuitState,
+};
 pub use self::fn_service::{
     fn_factory, fn_factory_with_config, fn_mut_service, fn_service,
 };
+pub use self::fn_transform::{fn_transform, fn_transform_with_config};
+pub use self::instrument::{instrument, Readiness};
 pub use self::map_config::{map_config, unit_config};
 pub use self::pipeline::{pipeline, pipeline_factory, Pipeline, PipelineFactory};
 pub use self::transform::{apply, Transform};
@@ -381,6 +389,8 @@ pub mod dev {
     pub use crate::fn_service::{
         FnMutService, FnService, FnServiceConfig, FnServiceFactory, FnServiceNoConfig,
     };
+    pub use crate::fn_transform::{FnTransform, FnTransformConfig};
+    pub use crate::instrument::{Instrument, InstrumentService};
     pub use crate::map::{Map, MapServiceFactory};
     pub use crate::map_config::{MapConfig, UnitConfig};
     pub use crate::map_err::{MapErr, MapErrServiceFactory};
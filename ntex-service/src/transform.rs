@@ -57,6 +57,10 @@ where
 /// Timeout service in above example is decoupled from underlying service implementation
 /// and could be applied to any service.
 ///
+/// `new_transform` returns future, so transform could perform async initialization
+/// before middleware service starts processing requests. Use `fn_transform` for
+/// constructing transforms from async functions.
+///
 /// The `Transform` trait defines the interface of a Service factory. `Transform`
 /// is often implemented for middleware, defining how to construct a
 /// middleware Service. A Service that is constructed by the factory takes
@@ -40,6 +40,8 @@ pub struct Connect<T> {
     pub(super) req: T,
     pub(super) port: u16,
     pub(super) addr: Option<Either<SocketAddr, VecDeque<SocketAddr>>>,
+    pub(super) server_name: Option<String>,
+    pub(super) sni: bool,
 }
 
 impl<T: Address> Connect<T> {
@@ -50,6 +52,8 @@ impl<T: Address> Connect<T> {
             req,
             port: port.unwrap_or(0),
             addr: None,
+            server_name: None,
+            sni: true,
         }
     }
 
@@ -59,6 +63,8 @@ impl<T: Address> Connect<T> {
             req,
             port: 0,
             addr: Some(Either::Left(addr)),
+            server_name: None,
+            sni: true,
         }
     }
 
@@ -92,6 +98,24 @@ impl<T: Address> Connect<T> {
         self
     }
 
+    /// Use tls server name.
+    ///
+    /// Server name is used for SNI and for certificate verification instead
+    /// of request's host name. It is useful when connecting to the server by
+    /// ip address.
+    pub fn set_server_name<S: Into<String>>(mut self, name: S) -> Self {
+        self.server_name = Some(name.into());
+        self
+    }
+
+    /// Enable or disable tls SNI extension.
+    ///
+    /// Certificate is still verified against server name. By default SNI is enabled.
+    pub fn set_sni(mut self, enabled: bool) -> Self {
+        self.sni = enabled;
+        self
+    }
+
     /// Host name
     pub fn host(&self) -> &str {
         self.req.host()
@@ -102,6 +126,20 @@ impl<T: Address> Connect<T> {
         self.req.port().unwrap_or(self.port)
     }
 
+    /// Tls server name of the request, defaults to host name
+    pub fn server_name(&self) -> &str {
+        if let Some(ref name) = self.server_name {
+            name
+        } else {
+            self.req.host()
+        }
+    }
+
+    /// Is tls SNI extension enabled
+    pub fn sni(&self) -> bool {
+        self.sni
+    }
+
     /// Preresolved addresses of the request.
     pub fn addrs(&self) -> ConnectAddrsIter<'_> {
         let inner = match self.addr {
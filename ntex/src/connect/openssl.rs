@@ -68,7 +68,8 @@ impl<T: Address + 'static> Service for OpensslConnector<T> {
     }
 
     fn call(&self, req: Connect<T>) -> Self::Future {
-        let host = req.host().to_string();
+        let host = req.server_name().to_string();
+        let sni = req.sni();
         let conn = self.connector.call(req);
         let openssl = self.openssl.clone();
 
@@ -78,17 +79,20 @@ impl<T: Address + 'static> Service for OpensslConnector<T> {
 
             match openssl.configure() {
                 Err(e) => Err(io::Error::new(io::ErrorKind::Other, e).into()),
-                Ok(config) => match tokio_openssl::connect(config, &host, io).await {
-                    Ok(io) => {
-                        trace!("SSL Handshake success: {:?}", host);
-                        Ok(io)
+                Ok(mut config) => {
+                    config.set_use_server_name_indication(sni);
+                    match tokio_openssl::connect(config, &host, io).await {
+                        Ok(io) => {
+                            trace!("SSL Handshake success: {:?}", host);
+                            Ok(io)
+                        }
+                        Err(e) => {
+                            trace!("SSL Handshake error: {:?}", e);
+                            Err(io::Error::new(io::ErrorKind::Other, format!("{}", e))
+                                .into())
+                        }
                     }
-                    Err(e) => {
-                        trace!("SSL Handshake error: {:?}", e);
-                        Err(io::Error::new(io::ErrorKind::Other, format!("{}", e))
-                            .into())
-                    }
-                },
+                }
             }
         }
         .boxed_local()
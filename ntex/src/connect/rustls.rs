@@ -72,9 +72,15 @@ impl<T: Address + 'static> Service for RustlsConnector<T> {
     }
 
     fn call(&self, req: Connect<T>) -> Self::Future {
-        let host = req.host().to_string();
+        let host = req.server_name().to_string();
+        let config = if req.sni() {
+            self.config.clone()
+        } else {
+            let mut config = ClientConfig::clone(&self.config);
+            config.enable_sni = false;
+            Arc::new(config)
+        };
         let conn = self.connector.call(req);
-        let config = self.config.clone();
 
         async move {
             let io = conn.await?;
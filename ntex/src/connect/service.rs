@@ -15,6 +15,14 @@ use super::{Address, AsyncResolver, Connect, ConnectError, Resolver};
 
 pub struct Connector<T> {
     resolver: Resolver<T>,
+    opts: ConnectOpts,
+}
+
+/// Tcp socket options
+#[derive(Debug, Default, Copy, Clone)]
+struct ConnectOpts {
+    fastopen: bool,
+    mptcp: bool,
 }
 
 impl<T> Connector<T> {
@@ -22,14 +30,36 @@ impl<T> Connector<T> {
     pub fn new(resolver: AsyncResolver) -> Self {
         Connector {
             resolver: Resolver::new(resolver),
+            opts: ConnectOpts::default(),
         }
     }
+
+    #[cfg(feature = "tcp-fastopen")]
+    /// Use TCP fast open for outgoing connections.
+    ///
+    /// If fast open is not supported by the platform or kernel, connection
+    /// is established with regular handshake. Only supported on linux.
+    pub fn tcp_fastopen(mut self, enabled: bool) -> Self {
+        self.opts.fastopen = enabled;
+        self
+    }
+
+    #[cfg(feature = "mptcp")]
+    /// Use multipath TCP for outgoing connections.
+    ///
+    /// If MPTCP is not supported by the platform or kernel, regular TCP
+    /// connection is used. Only supported on linux.
+    pub fn mptcp(mut self, enabled: bool) -> Self {
+        self.opts.mptcp = enabled;
+        self
+    }
 }
 
 impl<T> Default for Connector<T> {
     fn default() -> Self {
         Connector {
             resolver: Resolver::default(),
+            opts: ConnectOpts::default(),
         }
     }
 }
@@ -38,6 +68,7 @@ impl<T> Clone for Connector<T> {
     fn clone(&self) -> Self {
         Connector {
             resolver: self.resolver.clone(),
+            opts: self.opts,
         }
     }
 }
@@ -72,6 +103,7 @@ impl<T: Address> Service for Connector<T> {
     fn call(&self, req: Connect<T>) -> Self::Future {
         ConnectServiceResponse {
             state: ConnectState::Resolve(self.resolver.lookup(req)),
+            opts: self.opts,
         }
     }
 }
@@ -99,6 +131,7 @@ impl<T: Address> ConnectState<T> {
 
 pub struct ConnectServiceResponse<T: Address> {
     state: ConnectState<T>,
+    opts: ConnectOpts,
 }
 
 impl<T: Address> Future for ConnectServiceResponse<T> {
@@ -107,7 +140,8 @@ impl<T: Address> Future for ConnectServiceResponse<T> {
     fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
         let res = match self.state.poll(cx) {
             Either::Right(res) => {
-                self.state = ConnectState::Connect(connect(res));
+                let opts = self.opts;
+                self.state = ConnectState::Connect(connect(res, opts));
                 self.state.poll(cx)
             }
             Either::Left(res) => return res,
@@ -126,12 +160,12 @@ type ConnectFut<T> =
 /// Connect to remote host.
 ///
 /// Ip address must be resolved.
-fn connect<T: Address>(address: Connect<T>) -> ConnectFut<T> {
+fn connect<T: Address>(address: Connect<T>, opts: ConnectOpts) -> ConnectFut<T> {
     let port = address.port();
     let Connect { req, addr, .. } = address;
 
     if let Some(addr) = addr {
-        future::Either::Left(TcpConnectorResponse::new(req, port, addr))
+        future::Either::Left(TcpConnectorResponse::new(req, port, addr, opts))
     } else {
         error!("TCP connector: got unresolved address");
         future::Either::Right(err(ConnectError::Unresolverd))
@@ -144,6 +178,7 @@ struct TcpConnectorResponse<T> {
     port: u16,
     addrs: Option<VecDeque<SocketAddr>>,
     stream: Option<LocalBoxFuture<'static, Result<TcpStream, io::Error>>>,
+    opts: ConnectOpts,
 }
 
 impl<T: Address> TcpConnectorResponse<T> {
@@ -151,6 +186,7 @@ impl<T: Address> TcpConnectorResponse<T> {
         req: T,
         port: u16,
         addr: Either<SocketAddr, VecDeque<SocketAddr>>,
+        opts: ConnectOpts,
     ) -> TcpConnectorResponse<T> {
         trace!(
             "TCP connector - connecting to {:?} port:{}",
@@ -163,13 +199,15 @@ impl<T: Address> TcpConnectorResponse<T> {
                 req: Some(req),
                 port,
                 addrs: None,
-                stream: Some(TcpStream::connect(addr).boxed_local()),
+                stream: Some(tcp_connect(addr, opts)),
+                opts,
             },
             Either::Right(addrs) => TcpConnectorResponse {
                 req: Some(req),
                 port,
                 addrs: Some(addrs),
                 stream: None,
+                opts,
             },
         }
     }
@@ -211,7 +249,52 @@ impl<T: Address> Future for TcpConnectorResponse<T> {
 
             // try to connect
             let addr = this.addrs.as_mut().unwrap().pop_front().unwrap();
-            this.stream = Some(TcpStream::connect(addr).boxed());
+            this.stream = Some(tcp_connect(addr, this.opts));
+        }
+    }
+}
+
+/// Open tcp connection with configured socket options
+fn tcp_connect(
+    addr: SocketAddr,
+    opts: ConnectOpts,
+) -> LocalBoxFuture<'static, Result<TcpStream, io::Error>> {
+    if !opts.fastopen && !opts.mptcp {
+        return TcpStream::connect(addr).boxed_local();
+    }
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::io::{FromRawFd, IntoRawFd};
+        use tokio::io::PollEvented;
+
+        use crate::server::{set_fastopen_connect, tcp_socket};
+
+        async move {
+            let sock = tcp_socket(&addr, opts.mptcp)?;
+            if opts.fastopen {
+                set_fastopen_connect(&sock);
+            }
+
+            // wait for non-blocking connect completion
+            let io = PollEvented::new(mio::net::TcpStream::connect_stream(
+                sock.into_tcp_stream(),
+                &addr,
+            )?)?;
+            future::poll_fn(|cx| io.poll_write_ready(cx)).await?;
+            if let Some(e) = io.get_ref().take_error()? {
+                return Err(e);
+            }
+
+            let fd = io.into_inner()?.into_raw_fd();
+            TcpStream::from_std(unsafe { std::net::TcpStream::from_raw_fd(fd) })
         }
+        .boxed_local()
+    }
+
+    #[cfg(not(unix))]
+    {
+        log::warn!("Tcp socket options are not supported on this platform");
+        TcpStream::connect(addr).boxed_local()
     }
 }
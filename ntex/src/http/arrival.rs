@@ -0,0 +1,93 @@
+use std::time::{Duration, Instant};
+
+/// Request arrival time.
+///
+/// Dispatcher records arrival time when it starts reading request and
+/// stores it in request extensions. Time between arrival and start of
+/// request processing is the time request spent queued inside the server.
+///
+/// ```rust
+/// use ntex::http::Arrival;
+/// use ntex::web::HttpRequest;
+///
+/// async fn index(req: HttpRequest) -> String {
+///     let queued = req
+///         .extensions()
+///         .get::<Arrival>()
+///         .map(|a| a.elapsed())
+///         .unwrap_or_default();
+///     format!("queued for {:?}", queued)
+/// }
+/// # fn main() {}
+/// ```
+#[derive(Copy, Clone, Debug)]
+pub struct Arrival {
+    time: Instant,
+}
+
+impl Arrival {
+    pub(crate) fn new(time: Instant) -> Self {
+        Arrival { time }
+    }
+
+    /// Monotonic arrival timestamp
+    pub fn time(&self) -> Instant {
+        self.time
+    }
+
+    /// Time elapsed since request arrival
+    pub fn elapsed(&self) -> Duration {
+        self.time.elapsed()
+    }
+}
+
+/// Smoothed round trip time of tcp connection, derived from `TCP_INFO`.
+///
+/// Could be used in `on_connect` callback to make connection rtt available
+/// to request handlers.
+///
+/// ```rust,no_run
+/// use ntex::http::{tcp_rtt, HttpService, Response};
+/// use ntex::rt::net::TcpStream;
+///
+/// #[derive(Clone)]
+/// struct Rtt(Option<std::time::Duration>);
+///
+/// let srv = HttpService::build()
+///     .on_connect(|io: &TcpStream| Rtt(tcp_rtt(io)))
+///     .finish(|_| futures::future::ok::<_, std::io::Error>(Response::Ok().finish()));
+/// ```
+#[cfg(target_os = "linux")]
+pub fn tcp_rtt<T: std::os::unix::io::AsRawFd>(io: &T) -> Option<Duration> {
+    let mut info: libc::tcp_info = unsafe { std::mem::zeroed() };
+    let mut len = std::mem::size_of::<libc::tcp_info>() as libc::socklen_t;
+    let res = unsafe {
+        libc::getsockopt(
+            io.as_raw_fd(),
+            libc::IPPROTO_TCP,
+            libc::TCP_INFO,
+            &mut info as *mut _ as *mut libc::c_void,
+            &mut len,
+        )
+    };
+    if res == 0 {
+        Some(Duration::from_micros(u64::from(info.tcpi_rtt)))
+    } else {
+        None
+    }
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tcp_rtt() {
+        let lst = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let stream = std::net::TcpStream::connect(lst.local_addr().unwrap()).unwrap();
+        assert!(tcp_rtt(&stream).is_some());
+
+        let sock = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+        assert!(tcp_rtt(&sock).is_none());
+    }
+}
@@ -5,7 +5,8 @@ use std::task::{Context, Poll};
 use std::{fmt, mem};
 
 use bytes::{Bytes, BytesMut};
-use futures::{ready, Stream};
+use derive_more::Display;
+use futures::{ready, Sink, Stream};
 use pin_project::{pin_project, project};
 
 #[derive(Debug, PartialEq, Copy, Clone)]
@@ -279,6 +280,111 @@ impl From<serde_json::Value> for Body {
     }
 }
 
+#[cfg(feature = "send")]
+/// `Send` message body.
+///
+/// Body could be constructed on any thread, for example by a thread pool
+/// task, and converted to `Body` on the worker thread.
+pub enum SendBody {
+    /// Empty response. `Content-Length` header is not set.
+    None,
+    /// Zero sized response body. `Content-Length` header is set to `0`.
+    Empty,
+    /// Specific response body.
+    Bytes(Bytes),
+    /// Generic `Send` message body.
+    Message(Box<dyn MessageBody + Send>),
+}
+
+#[cfg(feature = "send")]
+impl SendBody {
+    /// Create body from generic `Send` message body.
+    pub fn from_message<B: MessageBody + Send + 'static>(body: B) -> SendBody {
+        SendBody::Message(Box::new(body))
+    }
+}
+
+#[cfg(feature = "send")]
+impl MessageBody for SendBody {
+    fn size(&self) -> BodySize {
+        match self {
+            SendBody::None => BodySize::None,
+            SendBody::Empty => BodySize::Empty,
+            SendBody::Bytes(ref bin) => BodySize::Sized(bin.len()),
+            SendBody::Message(ref body) => body.size(),
+        }
+    }
+
+    fn poll_next_chunk(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Bytes, Box<dyn Error>>>> {
+        match self {
+            SendBody::None | SendBody::Empty => Poll::Ready(None),
+            SendBody::Bytes(ref mut bin) => {
+                if bin.is_empty() {
+                    Poll::Ready(None)
+                } else {
+                    Poll::Ready(Some(Ok(mem::replace(bin, Bytes::new()))))
+                }
+            }
+            SendBody::Message(ref mut body) => body.poll_next_chunk(cx),
+        }
+    }
+}
+
+#[cfg(feature = "send")]
+impl fmt::Debug for SendBody {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            SendBody::None => write!(f, "SendBody::None"),
+            SendBody::Empty => write!(f, "SendBody::Empty"),
+            SendBody::Bytes(ref b) => write!(f, "SendBody::Bytes({:?})", b),
+            SendBody::Message(_) => write!(f, "SendBody::Message(_)"),
+        }
+    }
+}
+
+#[cfg(feature = "send")]
+impl From<SendBody> for Body {
+    fn from(body: SendBody) -> Body {
+        match body {
+            SendBody::None => Body::None,
+            SendBody::Empty => Body::Empty,
+            SendBody::Bytes(bin) => Body::Bytes(bin),
+            SendBody::Message(body) => Body::Message(body),
+        }
+    }
+}
+
+#[cfg(feature = "send")]
+impl From<&'static str> for SendBody {
+    fn from(s: &'static str) -> SendBody {
+        SendBody::Bytes(Bytes::from_static(s.as_ref()))
+    }
+}
+
+#[cfg(feature = "send")]
+impl From<Vec<u8>> for SendBody {
+    fn from(vec: Vec<u8>) -> SendBody {
+        SendBody::Bytes(Bytes::from(vec))
+    }
+}
+
+#[cfg(feature = "send")]
+impl From<String> for SendBody {
+    fn from(s: String) -> SendBody {
+        s.into_bytes().into()
+    }
+}
+
+#[cfg(feature = "send")]
+impl From<Bytes> for SendBody {
+    fn from(s: Bytes) -> SendBody {
+        SendBody::Bytes(s)
+    }
+}
+
 impl<S> From<SizedStream<S>> for Body
 where
     S: Stream<Item = Result<Bytes, Box<dyn Error>>> + Unpin + 'static,
@@ -497,6 +603,180 @@ where
     }
 }
 
+/// Body combinator that flushes each chunk.
+///
+/// After every chunk, body reports that it is not ready once, so
+/// dispatcher writes buffered data to the peer before polling next chunk.
+/// Useful for server-sent events and other long-living streams.
+pub struct FlushBody<B> {
+    body: B,
+    flush: bool,
+}
+
+impl<B: MessageBody> FlushBody<B> {
+    pub fn new(body: B) -> Self {
+        FlushBody { body, flush: false }
+    }
+}
+
+impl<B: MessageBody> MessageBody for FlushBody<B> {
+    fn size(&self) -> BodySize {
+        self.body.size()
+    }
+
+    fn poll_next_chunk(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Bytes, Box<dyn Error>>>> {
+        if self.flush {
+            self.flush = false;
+            cx.waker().wake_by_ref();
+            return Poll::Pending;
+        }
+
+        let item = ready!(self.body.poll_next_chunk(cx));
+        self.flush = matches!(item, Some(Ok(_)));
+        Poll::Ready(item)
+    }
+}
+
+/// Tee error, sent to the sink if tee is aborted
+#[derive(Debug, Display, Copy, Clone, PartialEq)]
+pub enum TeeError {
+    /// Body is larger than tee size limit
+    #[display(fmt = "Body is larger than tee size limit")]
+    Overflow,
+    /// Body stream returned error
+    #[display(fmt = "Body stream error")]
+    Body,
+}
+
+impl Error for TeeError {}
+
+/// Body combinator that copies streamed chunks to a sink.
+///
+/// Chunks are sent to the peer as is and copied to the sink, so response
+/// could be archived or stored to a cache without rendering it twice.
+/// Body stream completion is signaled by closing and dropping the sink.
+/// If body is larger than size limit or body stream fails, `TeeError` is
+/// sent to the sink and sink is dropped, body streaming continues.
+///
+/// Sink readiness applies backpressure to body streaming, use unbounded
+/// channel to avoid slowing down the peer. Sink errors are ignored, sink
+/// is dropped on error.
+///
+/// ```rust
+/// use ntex::channel::mpsc;
+/// use ntex::http::body::{Body, ResponseBody, TeeBody};
+/// use ntex::http::Response;
+///
+/// let (tx, rx) = mpsc::channel();
+/// let res = Response::Ok().body("body").map_body(|_, body| {
+///     ResponseBody::Body(Body::from_message(TeeBody::new(body, tx, 65_536)))
+/// });
+/// ```
+pub struct TeeBody<B, S> {
+    body: B,
+    sink: Option<S>,
+    limit: usize,
+    size: usize,
+    item: Option<Result<Bytes, TeeError>>,
+    eof: bool,
+}
+
+impl<B, S> TeeBody<B, S>
+where
+    B: MessageBody,
+    S: Sink<Result<Bytes, TeeError>> + Unpin,
+{
+    /// Create tee body with sink and body size limit
+    pub fn new(body: B, sink: S, limit: usize) -> Self {
+        TeeBody {
+            body,
+            limit,
+            sink: Some(sink),
+            size: 0,
+            item: None,
+            eof: false,
+        }
+    }
+
+    fn poll_send(&mut self, cx: &mut Context<'_>) -> Poll<()> {
+        if let Some(item) = self.item.take() {
+            if let Some(ref mut sink) = self.sink {
+                match Pin::new(&mut *sink).poll_ready(cx) {
+                    Poll::Pending => {
+                        self.item = Some(item);
+                        return Poll::Pending;
+                    }
+                    Poll::Ready(Ok(_)) => {
+                        let is_err = item.is_err();
+                        if Pin::new(sink).start_send(item).is_err() || is_err {
+                            self.sink = None;
+                        }
+                    }
+                    Poll::Ready(Err(_)) => self.sink = None,
+                }
+            }
+        }
+        Poll::Ready(())
+    }
+}
+
+impl<B, S> MessageBody for TeeBody<B, S>
+where
+    B: MessageBody,
+    S: Sink<Result<Bytes, TeeError>> + Unpin,
+{
+    fn size(&self) -> BodySize {
+        self.body.size()
+    }
+
+    fn poll_next_chunk(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Bytes, Box<dyn Error>>>> {
+        ready!(self.poll_send(cx));
+
+        if self.eof {
+            if let Some(ref mut sink) = self.sink {
+                if Pin::new(sink).poll_close(cx).is_pending() {
+                    return Poll::Pending;
+                }
+                self.sink = None;
+            }
+            return Poll::Ready(None);
+        }
+
+        match ready!(self.body.poll_next_chunk(cx)) {
+            Some(Ok(chunk)) => {
+                if self.sink.is_some() {
+                    self.size += chunk.len();
+                    self.item = Some(if self.size > self.limit {
+                        Err(TeeError::Overflow)
+                    } else {
+                        Ok(chunk.clone())
+                    });
+                }
+                Poll::Ready(Some(Ok(chunk)))
+            }
+            Some(Err(e)) => {
+                if self.sink.is_some() {
+                    self.item = Some(Err(TeeError::Body));
+                    if self.poll_send(cx).is_pending() {
+                        self.sink = None;
+                    }
+                }
+                Poll::Ready(Some(Err(e)))
+            }
+            None => {
+                self.eof = true;
+                self.poll_next_chunk(cx)
+            }
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use std::io;
@@ -658,6 +938,57 @@ mod tests {
         );
     }
 
+    #[ntex_rt::test]
+    async fn test_flush_body() {
+        let mut body = FlushBody::new(BodyStream::new(stream::iter(
+            ["1", "2"]
+                .iter()
+                .map(|&v| Ok(Bytes::from(v)) as Result<Bytes, io::Error>),
+        )));
+        assert_eq!(body.size(), BodySize::Stream);
+
+        let waker = futures::task::noop_waker();
+        let mut cx = Context::from_waker(&waker);
+        assert!(body.poll_next_chunk(&mut cx).is_ready());
+        assert!(body.poll_next_chunk(&mut cx).is_pending());
+        assert_eq!(
+            poll_fn(|cx| body.poll_next_chunk(cx)).await.unwrap().ok(),
+            Some(Bytes::from("2"))
+        );
+        assert!(poll_fn(|cx| body.poll_next_chunk(cx)).await.is_none());
+    }
+
+    #[cfg(feature = "send")]
+    #[ntex_rt::test]
+    async fn test_send_body() {
+        let mut body = std::thread::spawn(|| SendBody::from("test"))
+            .join()
+            .unwrap();
+        assert_eq!(body.size(), BodySize::Sized(4));
+        assert_eq!(
+            poll_fn(|cx| body.poll_next_chunk(cx)).await.unwrap().ok(),
+            Some(Bytes::from("test"))
+        );
+        assert!(poll_fn(|cx| body.poll_next_chunk(cx)).await.is_none());
+
+        let mut body = Body::from(
+            std::thread::spawn(|| SendBody::from_message(Bytes::from("12")))
+                .join()
+                .unwrap(),
+        );
+        assert_eq!(body.size(), BodySize::Sized(2));
+        assert_eq!(
+            poll_fn(|cx| body.poll_next_chunk(cx)).await.unwrap().ok(),
+            Some(Bytes::from("12"))
+        );
+
+        assert_eq!(Body::from(SendBody::Empty), Body::Empty);
+        assert_eq!(
+            Body::from(SendBody::from(String::from("test"))),
+            Body::Bytes(Bytes::from("test"))
+        );
+    }
+
     mod body_stream {
         use super::*;
 
@@ -698,4 +1029,59 @@ mod tests {
             );
         }
     }
+
+    mod tee_body {
+        use futures::StreamExt;
+
+        use super::*;
+        use crate::channel::mpsc;
+
+        #[ntex_rt::test]
+        async fn copies_chunks() {
+            let (tx, mut rx) = mpsc::channel();
+            let mut body = TeeBody::new(
+                SizedStream::new(
+                    4,
+                    stream::iter(["12", "34"].iter().map(|&v| Ok(Bytes::from(v)))),
+                ),
+                tx,
+                4,
+            );
+            assert_eq!(body.size(), BodySize::Sized64(4));
+            let mut data = BytesMut::new();
+            while let Some(item) = poll_fn(|cx| body.poll_next_chunk(cx)).await {
+                data.extend_from_slice(&item.unwrap());
+            }
+            assert_eq!(data, "1234");
+            drop(body);
+
+            assert_eq!(rx.next().await, Some(Ok(Bytes::from("12"))));
+            assert_eq!(rx.next().await, Some(Ok(Bytes::from("34"))));
+            assert_eq!(rx.next().await, None);
+        }
+
+        #[ntex_rt::test]
+        async fn overflow() {
+            let (tx, mut rx) = mpsc::channel();
+            let mut body = TeeBody::new(stream_body(&["12", "34", "56"]), tx, 3);
+            let mut data = BytesMut::new();
+            while let Some(item) = poll_fn(|cx| body.poll_next_chunk(cx)).await {
+                data.extend_from_slice(&item.unwrap());
+            }
+            assert_eq!(data, "123456");
+
+            assert_eq!(rx.next().await, Some(Ok(Bytes::from("12"))));
+            assert_eq!(rx.next().await, Some(Err(TeeError::Overflow)));
+            assert_eq!(rx.next().await, None);
+        }
+
+        fn stream_body(
+            items: &'static [&'static str],
+        ) -> BodyStream<impl Stream<Item = Result<Bytes, io::Error>> + Unpin, io::Error>
+        {
+            BodyStream::new(stream::iter(
+                items.iter().map(|&v| Ok::<_, io::Error>(Bytes::from(v))),
+            ))
+        }
+    }
 }
@@ -4,11 +4,12 @@ use std::rc::Rc;
 
 use crate::codec::Framed;
 use crate::http::body::MessageBody;
-use crate::http::config::{KeepAlive, ServiceConfig};
+use crate::http::config::{KeepAlive, Limits, ServiceConfig};
 use crate::http::error::ResponseError;
 use crate::http::h1::{Codec, ExpectHandler, H1Service, UpgradeHandler};
 use crate::http::h2::H2Service;
 use crate::http::helpers::{Data, DataFactory};
+use crate::http::pending::PendingLimits;
 use crate::http::request::Request;
 use crate::http::response::Response;
 use crate::http::service::HttpService;
@@ -23,6 +24,8 @@ pub struct HttpServiceBuilder<T, S, X = ExpectHandler, U = UpgradeHandler<T>> {
     client_timeout: u64,
     client_disconnect: u64,
     handshake_timeout: u64,
+    limits: Limits,
+    pending: PendingLimits,
     expect: X,
     upgrade: Option<U>,
     on_connect: Option<Rc<dyn Fn(&T) -> Box<dyn DataFactory>>>,
@@ -37,6 +40,8 @@ impl<T, S> HttpServiceBuilder<T, S, ExpectHandler, UpgradeHandler<T>> {
             client_timeout: 3000,
             client_disconnect: 3000,
             handshake_timeout: 5000,
+            limits: Limits::default(),
+            pending: PendingLimits::default(),
             expect: ExpectHandler,
             upgrade: None,
             on_connect: None,
@@ -106,6 +111,89 @@ where
         self
     }
 
+    /// Set max size of request uri in bytes.
+    ///
+    /// Requests with longer uri get terminated with
+    /// the 414 (URI Too Long) error.
+    ///
+    /// By default max uri size is set to 16Kb.
+    pub fn max_uri_size(mut self, val: usize) -> Self {
+        self.limits.max_uri_size = val;
+        self
+    }
+
+    /// Set max number of request headers.
+    ///
+    /// Requests with more headers get terminated with
+    /// the 431 (Request Header Fields Too Large) error.
+    /// Value can not be larger than 96.
+    ///
+    /// By default max number of headers is set to 96.
+    pub fn max_headers(mut self, val: usize) -> Self {
+        self.limits.max_headers = std::cmp::min(val, 96);
+        self
+    }
+
+    /// Set max size of all request headers in bytes.
+    ///
+    /// Requests with larger headers section get terminated with
+    /// the 431 (Request Header Fields Too Large) error.
+    ///
+    /// By default max headers size is set to 64Kb.
+    pub fn max_header_size(mut self, val: usize) -> Self {
+        self.limits.max_header_size = val;
+        self
+    }
+
+    /// Set max size of chunked payload trailer section in bytes.
+    ///
+    /// Requests with larger trailer section get terminated with
+    /// the 413 (Payload Too Large) error.
+    ///
+    /// By default max trailer size is set to 8Kb.
+    pub fn max_trailer_size(mut self, val: usize) -> Self {
+        self.limits.max_trailer_size = val;
+        self
+    }
+
+    /// Set max read rate of request payload per connection, in bytes per second.
+    ///
+    /// Dispatcher reads http/1 request payload from socket no faster than
+    /// this rate, so few fast uploads could not monopolize worker io and
+    /// buffers. Short bursts up to one second worth of data are allowed.
+    ///
+    /// By default read rate is not limited.
+    pub fn max_payload_rate(mut self, val: usize) -> Self {
+        self.limits.max_payload_rate = val;
+        self
+    }
+
+    /// Set max number of pending requests per worker.
+    ///
+    /// Request is pending from the moment dispatcher accepts it until
+    /// service returns response. New http/1 requests and http/2 streams
+    /// that arrive while worker has this many pending requests get
+    /// rejected with the 503 (Service Unavailable) response, service
+    /// does not get called for them.
+    ///
+    /// By default worker limit is disabled.
+    pub fn max_pending(mut self, val: usize) -> Self {
+        self.pending.worker = val;
+        self
+    }
+
+    /// Set max number of pending requests per connection.
+    ///
+    /// Limits number of pipelined http/1 requests or concurrent http/2
+    /// streams that are waiting for response. Requests over the limit get
+    /// rejected with the 503 (Service Unavailable) response.
+    ///
+    /// By default connection limit is disabled.
+    pub fn max_pending_per_connection(mut self, val: usize) -> Self {
+        self.pending.connection = val;
+        self
+    }
+
     /// Provide service for `EXPECT: 100-Continue` support.
     ///
     /// Service get called with request that contains `EXPECT` header.
@@ -124,6 +212,8 @@ where
             client_timeout: self.client_timeout,
             client_disconnect: self.client_disconnect,
             handshake_timeout: self.handshake_timeout,
+            limits: self.limits,
+            pending: self.pending,
             expect: expect.into_factory(),
             upgrade: self.upgrade,
             on_connect: self.on_connect,
@@ -152,6 +242,8 @@ where
             client_timeout: self.client_timeout,
             client_disconnect: self.client_disconnect,
             handshake_timeout: self.handshake_timeout,
+            limits: self.limits,
+            pending: self.pending,
             expect: self.expect,
             upgrade: Some(upgrade.into_factory()),
             on_connect: self.on_connect,
@@ -181,11 +273,13 @@ where
         S::InitError: fmt::Debug,
         S::Response: Into<Response<B>>,
     {
-        let cfg = ServiceConfig::new(
+        let cfg = ServiceConfig::with_limits(
             self.keep_alive,
             self.client_timeout,
             self.client_disconnect,
             self.handshake_timeout,
+            self.limits,
+            self.pending,
         );
         H1Service::with_config(cfg, service.into_factory())
             .expect(self.expect)
@@ -203,11 +297,13 @@ where
         S::Response: Into<Response<B>> + 'static,
         <S::Service as Service>::Future: 'static,
     {
-        let cfg = ServiceConfig::new(
+        let cfg = ServiceConfig::with_limits(
             self.keep_alive,
             self.client_timeout,
             self.client_disconnect,
             self.handshake_timeout,
+            self.limits,
+            self.pending,
         );
         H2Service::with_config(cfg, service.into_factory()).on_connect(self.on_connect)
     }
@@ -222,11 +318,13 @@ where
         S::Response: Into<Response<B>> + 'static,
         <S::Service as Service>::Future: 'static,
     {
-        let cfg = ServiceConfig::new(
+        let cfg = ServiceConfig::with_limits(
             self.keep_alive,
             self.client_timeout,
             self.client_disconnect,
             self.handshake_timeout,
+            self.limits,
+            self.pending,
         );
         HttpService::with_config(cfg, service.into_factory())
             .expect(self.expect)
@@ -38,6 +38,12 @@ impl ClientBuilder {
                 headers: HeaderMap::new(),
                 timeout: Some(Duration::from_secs(5)),
                 connector: Box::new(ConnectorWrapper(Connector::default().finish())),
+                #[cfg(feature = "signing")]
+                signers: Default::default(),
+                #[cfg(feature = "har")]
+                har: None,
+                #[cfg(feature = "dictionary")]
+                dictionary: None,
             },
         }
     }
@@ -136,6 +142,42 @@ impl ClientBuilder {
         self.header(header::AUTHORIZATION, format!("Bearer {}", token))
     }
 
+    /// Sign requests to specified origin, for example `https://example.com`.
+    ///
+    /// Requests are signed right before sending, websocket requests are
+    /// not signed.
+    #[cfg(feature = "signing")]
+    pub fn signer<S>(mut self, origin: &str, signer: S) -> Self
+    where
+        S: super::signing::Signer + 'static,
+    {
+        self.config.signers.add(origin, Box::new(signer));
+        self
+    }
+
+    /// Record all requests and responses to HTTP Archive log.
+    ///
+    /// Websocket requests are not recorded.
+    #[cfg(feature = "har")]
+    pub fn har(mut self, recorder: super::har::HarRecorder) -> Self {
+        self.config.har = Some(recorder);
+        self
+    }
+
+    /// Use shared compression dictionary.
+    ///
+    /// Client advertises dictionary with `Available-Dictionary` header and
+    /// decodes `dcb` encoded responses. Server must be configured with the
+    /// same dictionary.
+    #[cfg(feature = "dictionary")]
+    pub fn dictionary(
+        mut self,
+        dict: crate::http::encoding::dictionary::Dictionary,
+    ) -> Self {
+        self.config.dictionary = Some(dict);
+        self
+    }
+
     /// Finish build process and create `Client` instance.
     pub fn finish(self) -> Client {
         Client(Rc::new(self.config))
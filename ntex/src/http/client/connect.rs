@@ -82,10 +82,7 @@ where
         addr: Option<net::SocketAddr>,
     ) -> Pin<Box<dyn Future<Output = Result<ClientResponse, SendRequestError>>>> {
         // connect to the host
-        let fut = self.0.call(ClientConnect {
-            uri: head.uri.clone(),
-            addr,
-        });
+        let fut = self.0.call(ClientConnect::new(&head, addr));
 
         Box::pin(async move {
             let connection = fut.await?;
@@ -106,10 +103,7 @@ where
         addr: Option<net::SocketAddr>,
     ) -> Pin<Box<dyn Future<Output = Result<ClientResponse, SendRequestError>>>> {
         // connect to the host
-        let fut = self.0.call(ClientConnect {
-            uri: head.uri.clone(),
-            addr,
-        });
+        let fut = self.0.call(ClientConnect::new(&head, addr));
 
         Box::pin(async move {
             let connection = fut.await?;
@@ -138,10 +132,7 @@ where
         >,
     > {
         // connect to the host
-        let fut = self.0.call(ClientConnect {
-            uri: head.uri.clone(),
-            addr,
-        });
+        let fut = self.0.call(ClientConnect::new(&head, addr));
 
         Box::pin(async move {
             let connection = fut.await?;
@@ -171,10 +162,7 @@ where
         >,
     > {
         // connect to the host
-        let fut = self.0.call(ClientConnect {
-            uri: head.uri.clone(),
-            addr,
-        });
+        let fut = self.0.call(ClientConnect::new(&head, addr));
 
         Box::pin(async move {
             let connection = fut.await?;
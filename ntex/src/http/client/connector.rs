@@ -293,7 +293,11 @@ fn connector(
     TimeoutService::new(
         timeout,
         apply_fn(connector, |msg: Connect, srv| {
-            srv.call(TcpConnect::new(msg.uri).set_addr(msg.addr))
+            let mut req = TcpConnect::new(msg.uri).set_addr(msg.addr).set_sni(msg.sni);
+            if let Some(name) = msg.server_name {
+                req = req.set_server_name(name);
+            }
+            srv.call(req)
         })
         .map_err(ConnectError::from),
     )
@@ -0,0 +1,535 @@
+//! HTTP Archive recorder.
+//!
+//! Recorder captures request and response metadata and bodies of every
+//! request sent by the client into [HAR 1.2](http://www.softwareishard.com/blog/har-12-spec/)
+//! log, which could be inspected with browser developer tools or any
+//! other HAR viewer. Recording is meant for debugging integration
+//! problems and keeps everything in memory, do not enable it in
+//! production builds.
+//!
+//! Request body is captured only if it is already buffered, response
+//! body is captured as it is read by application, before decompression.
+//!
+//! ```rust,no_run
+//! use ntex::http::client::{har::HarRecorder, Client};
+//!
+//! #[ntex::main]
+//! async fn main() -> std::io::Result<()> {
+//!     let har = HarRecorder::new();
+//!     let client = Client::build().har(har.clone()).finish();
+//!
+//!     let mut res = client.get("http://www.rust-lang.org").send().await.unwrap();
+//!     let _ = res.body().await;
+//!
+//!     har.save("session.har")
+//! }
+//! ```
+use std::cell::RefCell;
+use std::path::Path;
+use std::pin::Pin;
+use std::rc::Rc;
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant, SystemTime};
+use std::{fs, io};
+
+use bytes::{Bytes, BytesMut};
+use futures::{Future, Stream};
+use serde_json::{json, Value};
+use time::OffsetDateTime;
+
+use crate::http::body::{Body, BodySize, MessageBody};
+use crate::http::error::PayloadError;
+use crate::http::header::{HeaderMap, CONTENT_TYPE};
+use crate::http::{Payload, PayloadStream, RequestHead, ResponseHead};
+
+use super::error::SendRequestError;
+use super::response::ClientResponse;
+
+const DEFAULT_BODY_LIMIT: usize = 1_048_576;
+
+/// HTTP Archive recorder.
+///
+/// Recorder is a handle to shared session log, clones record into
+/// the same log.
+#[derive(Clone)]
+pub struct HarRecorder(Rc<RefCell<Inner>>);
+
+struct Inner {
+    body_limit: usize,
+    entries: Vec<Rc<RefCell<Entry>>>,
+}
+
+struct Entry {
+    started: SystemTime,
+    start: Instant,
+    method: String,
+    url: String,
+    version: String,
+    headers: Vec<(String, String)>,
+    body: Option<Content>,
+    response: Option<ResponseEntry>,
+    wait: Option<Duration>,
+    receive: Option<Duration>,
+    error: Option<String>,
+}
+
+struct ResponseEntry {
+    status: u16,
+    reason: String,
+    version: String,
+    headers: Vec<(String, String)>,
+    body: Content,
+}
+
+#[derive(Default)]
+struct Content {
+    mime: String,
+    size: usize,
+    data: BytesMut,
+    truncated: bool,
+}
+
+impl Default for HarRecorder {
+    fn default() -> Self {
+        HarRecorder::new()
+    }
+}
+
+impl HarRecorder {
+    /// Create new empty session log
+    pub fn new() -> Self {
+        HarRecorder(Rc::new(RefCell::new(Inner {
+            body_limit: DEFAULT_BODY_LIMIT,
+            entries: Vec::new(),
+        })))
+    }
+
+    /// Set max size of captured request or response body.
+    ///
+    /// Body is truncated to this size, by default limit is 1Mb.
+    pub fn body_limit(self, limit: usize) -> Self {
+        self.0.borrow_mut().body_limit = limit;
+        self
+    }
+
+    /// Number of recorded entries
+    pub fn len(&self) -> usize {
+        self.0.borrow().entries.len()
+    }
+
+    /// Check if log is empty
+    pub fn is_empty(&self) -> bool {
+        self.0.borrow().entries.is_empty()
+    }
+
+    /// Remove all recorded entries
+    pub fn clear(&self) {
+        self.0.borrow_mut().entries.clear();
+    }
+
+    /// Render session log as HAR json document
+    pub fn to_json(&self) -> Value {
+        let entries: Vec<_> = self
+            .0
+            .borrow()
+            .entries
+            .iter()
+            .map(|e| e.borrow().to_json())
+            .collect();
+
+        json!({
+            "log": {
+                "version": "1.2",
+                "creator": {
+                    "name": "ntex",
+                    "version": env!("CARGO_PKG_VERSION"),
+                },
+                "pages": [],
+                "entries": entries,
+            }
+        })
+    }
+
+    /// Write session log to a writer
+    pub fn write<W: io::Write>(&self, writer: W) -> io::Result<()> {
+        serde_json::to_writer_pretty(writer, &self.to_json()).map_err(io::Error::from)
+    }
+
+    /// Write session log to a file
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        self.write(io::BufWriter::new(fs::File::create(path)?))
+    }
+
+    /// Start new entry, body is captured only if it is buffered
+    pub(crate) fn start(
+        &self,
+        head: &RequestHead,
+        extra_headers: Option<&HeaderMap>,
+        body: &Body,
+    ) -> HarEntry {
+        let mut inner = self.0.borrow_mut();
+
+        let mut headers = Vec::new();
+        for (key, value) in head.headers.iter() {
+            if !extra_headers.map(|h| h.contains_key(key)).unwrap_or(false) {
+                headers.push(header_pair(key.as_str(), value.as_bytes()));
+            }
+        }
+        if let Some(extra) = extra_headers {
+            for (key, value) in extra.iter() {
+                headers.push(header_pair(key.as_str(), value.as_bytes()));
+            }
+        }
+
+        let body = match body.size() {
+            BodySize::None | BodySize::Empty => None,
+            _ => {
+                let mut content = Content {
+                    mime: find(&headers, CONTENT_TYPE.as_str()),
+                    ..Default::default()
+                };
+                match body {
+                    Body::Bytes(ref bytes) => content.push(bytes, inner.body_limit),
+                    _ => content.truncated = true,
+                }
+                if let BodySize::Sized(size) = body.size() {
+                    content.size = size;
+                } else if let BodySize::Sized64(size) = body.size() {
+                    content.size = size as usize;
+                }
+                Some(content)
+            }
+        };
+
+        let entry = Rc::new(RefCell::new(Entry {
+            body,
+            headers,
+            started: SystemTime::now(),
+            start: Instant::now(),
+            method: head.method.to_string(),
+            url: head.uri.to_string(),
+            version: format!("{:?}", head.version),
+            response: None,
+            wait: None,
+            receive: None,
+            error: None,
+        }));
+        inner.entries.push(entry.clone());
+
+        HarEntry {
+            entry,
+            limit: inner.body_limit,
+        }
+    }
+}
+
+/// Handle to recorded entry
+pub(crate) struct HarEntry {
+    entry: Rc<RefCell<Entry>>,
+    limit: usize,
+}
+
+impl HarEntry {
+    /// Record response of the request future
+    pub(crate) fn wrap(
+        self,
+        fut: Pin<Box<dyn Future<Output = Result<ClientResponse, SendRequestError>>>>,
+    ) -> Pin<Box<dyn Future<Output = Result<ClientResponse, SendRequestError>>>> {
+        Box::pin(async move {
+            match fut.await {
+                Ok(res) => {
+                    self.response(&res.head);
+                    Ok(res.map_body(|_, payload| {
+                        let stream: PayloadStream = Box::pin(HarPayload {
+                            payload,
+                            entry: self,
+                        });
+                        Payload::Stream(stream)
+                    }))
+                }
+                Err(e) => {
+                    self.with(|entry, _| entry.error = Some(e.to_string()));
+                    Err(e)
+                }
+            }
+        })
+    }
+
+    fn with<F: FnOnce(&mut Entry, usize)>(&self, f: F) {
+        f(&mut self.entry.borrow_mut(), self.limit)
+    }
+
+    fn response(&self, head: &ResponseHead) {
+        self.with(|entry, _| {
+            let headers: Vec<_> = head
+                .headers
+                .iter()
+                .map(|(key, value)| header_pair(key.as_str(), value.as_bytes()))
+                .collect();
+
+            entry.wait = Some(entry.start.elapsed());
+            entry.response = Some(ResponseEntry {
+                status: head.status.as_u16(),
+                reason: head.reason().to_string(),
+                version: format!("{:?}", head.version),
+                body: Content {
+                    mime: find(&headers, CONTENT_TYPE.as_str()),
+                    ..Default::default()
+                },
+                headers,
+            });
+        })
+    }
+}
+
+/// Payload stream that copies response body to the log
+struct HarPayload {
+    payload: Payload,
+    entry: HarEntry,
+}
+
+impl Stream for HarPayload {
+    type Item = Result<Bytes, PayloadError>;
+
+    fn poll_next(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        let item = futures::ready!(Pin::new(&mut this.payload).poll_next(cx));
+
+        this.entry.with(|entry, limit| match item {
+            Some(Ok(ref chunk)) => {
+                if let Some(ref mut res) = entry.response {
+                    res.body.size += chunk.len();
+                    res.body.push(chunk, limit);
+                }
+            }
+            Some(Err(ref e)) => {
+                entry.receive =
+                    Some(entry.start.elapsed() - entry.wait.unwrap_or_default());
+                entry.error = Some(e.to_string());
+            }
+            None => {
+                entry.receive =
+                    Some(entry.start.elapsed() - entry.wait.unwrap_or_default());
+            }
+        });
+        Poll::Ready(item)
+    }
+}
+
+impl Content {
+    fn push(&mut self, chunk: &[u8], limit: usize) {
+        let rem = limit.saturating_sub(self.data.len());
+        if chunk.len() > rem {
+            self.truncated = true;
+        }
+        self.data
+            .extend_from_slice(&chunk[..std::cmp::min(rem, chunk.len())]);
+    }
+
+    fn to_json(&self) -> Value {
+        let mut content = json!({
+            "size": self.size,
+            "mimeType": self.mime,
+        });
+        match std::str::from_utf8(&self.data) {
+            Ok(text) => content["text"] = text.into(),
+            Err(_) => {
+                content["text"] = base64::encode(&self.data).into();
+                content["encoding"] = "base64".into();
+            }
+        }
+        if self.truncated {
+            content["comment"] = "body is not captured completely".into();
+        }
+        content
+    }
+}
+
+impl Entry {
+    fn to_json(&self) -> Value {
+        let started = OffsetDateTime::from(self.started);
+        let wait = millis(self.wait);
+        let receive = millis(self.receive);
+
+        let mut request = json!({
+            "method": self.method,
+            "url": self.url,
+            "httpVersion": self.version,
+            "cookies": [],
+            "headers": headers_json(&self.headers),
+            "queryString": query_json(&self.url),
+            "headersSize": -1,
+            "bodySize": self.body.as_ref().map(|b| b.size).unwrap_or(0),
+        });
+        if let Some(ref body) = self.body {
+            let mut data = body.to_json();
+            data["params"] = json!([]);
+            request["postData"] = data;
+        }
+
+        let response = match self.response {
+            Some(ref res) => json!({
+                "status": res.status,
+                "statusText": res.reason,
+                "httpVersion": res.version,
+                "cookies": [],
+                "headers": headers_json(&res.headers),
+                "content": res.body.to_json(),
+                "redirectURL": find(&res.headers, "location"),
+                "headersSize": -1,
+                "bodySize": if self.receive.is_some() { res.body.size as i64 } else { -1 },
+            }),
+            None => json!({
+                "status": 0,
+                "statusText": "",
+                "httpVersion": "",
+                "cookies": [],
+                "headers": [],
+                "content": {"size": 0, "mimeType": ""},
+                "redirectURL": "",
+                "headersSize": -1,
+                "bodySize": -1,
+            }),
+        };
+
+        let mut entry = json!({
+            "startedDateTime": format!(
+                "{}.{:03}Z",
+                started.format("%Y-%m-%dT%H:%M:%S"),
+                started.millisecond()
+            ),
+            "time": wait + receive,
+            "request": request,
+            "response": response,
+            "cache": {},
+            "timings": {
+                "blocked": -1,
+                "dns": -1,
+                "connect": -1,
+                "send": 0,
+                "wait": wait,
+                "receive": receive,
+            },
+        });
+        if let Some(ref error) = self.error {
+            entry["_error"] = error.as_str().into();
+        }
+        entry
+    }
+}
+
+fn millis(dur: Option<Duration>) -> f64 {
+    dur.map(|d| d.as_secs_f64() * 1000.0).unwrap_or(0.0)
+}
+
+fn header_pair(name: &str, value: &[u8]) -> (String, String) {
+    (
+        name.to_string(),
+        String::from_utf8_lossy(value).into_owned(),
+    )
+}
+
+fn find(headers: &[(String, String)], name: &str) -> String {
+    headers
+        .iter()
+        .find(|(key, _)| key == name)
+        .map(|(_, value)| value.clone())
+        .unwrap_or_default()
+}
+
+fn headers_json(headers: &[(String, String)]) -> Value {
+    headers
+        .iter()
+        .map(|(name, value)| json!({"name": name, "value": value}))
+        .collect()
+}
+
+fn query_json(url: &str) -> Value {
+    let query = url.splitn(2, '?').nth(1).unwrap_or("");
+    serde_urlencoded::from_str::<Vec<(String, String)>>(query)
+        .unwrap_or_default()
+        .into_iter()
+        .map(|(name, value)| json!({"name": name, "value": value}))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::future::{ok, poll_fn};
+    use futures::stream;
+
+    use super::*;
+    use crate::http::{Method, StatusCode, Uri};
+
+    #[ntex_rt::test]
+    async fn test_har_recorder() {
+        let har = HarRecorder::new().body_limit(8);
+        assert!(har.is_empty());
+
+        let mut head = RequestHead::default();
+        head.method = Method::POST;
+        head.uri = Uri::from_static("http://localhost/test?a=1&b=x%20y");
+        head.headers
+            .insert(CONTENT_TYPE, "text/plain".parse().unwrap());
+        let entry = har.start(&head, None, &Body::from("request body"));
+
+        let mut res = ResponseHead::new(StatusCode::OK);
+        res.headers
+            .insert(CONTENT_TYPE, "application/octet-stream".parse().unwrap());
+        let payload: PayloadStream = Box::pin(stream::iter(vec![
+            Ok(Bytes::from_static(b"\xff\xfe")),
+            Ok(Bytes::from_static(b"\x00")),
+        ]));
+        let fut = entry.wrap(Box::pin(ok(ClientResponse::new(res, payload.into()))));
+
+        let mut res = fut.await.unwrap();
+        while let Some(chunk) = poll_fn(|cx| Pin::new(&mut res).poll_next(cx)).await {
+            chunk.unwrap();
+        }
+        assert_eq!(har.len(), 1);
+
+        let log = har.to_json();
+        assert_eq!(log["log"]["version"], "1.2");
+        let entry = &log["log"]["entries"][0];
+        let req = &entry["request"];
+        assert_eq!(req["method"], "POST");
+        assert_eq!(req["url"], "http://localhost/test?a=1&b=x%20y");
+        assert_eq!(req["httpVersion"], "HTTP/1.1");
+        assert_eq!(req["headers"][0]["name"], "content-type");
+        assert_eq!(req["queryString"][1]["value"], "x y");
+        assert_eq!(req["bodySize"], 12);
+        assert_eq!(req["postData"]["mimeType"], "text/plain");
+        assert_eq!(req["postData"]["text"], "request ");
+        assert!(req["postData"]["comment"].is_string());
+
+        let res = &entry["response"];
+        assert_eq!(res["status"], 200);
+        assert_eq!(res["statusText"], "OK");
+        assert_eq!(res["bodySize"], 3);
+        assert_eq!(res["content"]["encoding"], "base64");
+        assert_eq!(res["content"]["text"], "//4A");
+        assert!(entry["startedDateTime"].as_str().unwrap().ends_with('Z'));
+
+        har.clear();
+        assert!(har.is_empty());
+    }
+
+    #[ntex_rt::test]
+    async fn test_har_error() {
+        let har = HarRecorder::new();
+        let entry = har.start(&RequestHead::default(), None, &Body::Empty);
+        let res = entry
+            .wrap(Box::pin(async { Err(SendRequestError::Timeout) }))
+            .await;
+        assert!(res.is_err());
+
+        let log = har.to_json();
+        let entry = &log["log"]["entries"][0];
+        assert_eq!(entry["response"]["status"], 0);
+        assert!(entry["request"].get("postData").is_none());
+        assert_eq!(entry["_error"], "Timeout out while waiting for response");
+    }
+}
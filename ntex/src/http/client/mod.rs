@@ -28,10 +28,14 @@ pub mod error;
 mod frozen;
 mod h1proto;
 mod h2proto;
+#[cfg(feature = "har")]
+pub mod har;
 mod pool;
 mod request;
 mod response;
 mod sender;
+#[cfg(feature = "signing")]
+pub mod signing;
 mod test;
 mod ws;
 
@@ -55,6 +59,50 @@ use self::connect::{Connect as InnerConnect, ConnectorWrapper};
 pub struct Connect {
     pub uri: Uri,
     pub addr: Option<std::net::SocketAddr>,
+    pub server_name: Option<String>,
+    pub sni: bool,
+}
+
+impl Connect {
+    pub(crate) fn new(head: &RequestHead, addr: Option<std::net::SocketAddr>) -> Self {
+        let (server_name, sni) = head
+            .extensions()
+            .get::<ServerName>()
+            .map(|name| (name.name.clone(), name.sni))
+            .unwrap_or((None, true));
+
+        Connect {
+            uri: head.uri.clone(),
+            addr,
+            server_name,
+            sni,
+        }
+    }
+}
+
+/// Tls server name settings of the request, stored in request extensions
+#[derive(Clone, Debug)]
+pub(crate) struct ServerName {
+    pub(crate) name: Option<String>,
+    pub(crate) sni: bool,
+}
+
+impl ServerName {
+    pub(crate) fn update<F: FnOnce(&mut ServerName)>(head: &RequestHead, f: F) {
+        let mut ext = head.extensions_mut();
+        let mut server_name = ext.remove::<ServerName>().unwrap_or_default();
+        f(&mut server_name);
+        ext.insert(server_name);
+    }
+}
+
+impl Default for ServerName {
+    fn default() -> Self {
+        ServerName {
+            name: None,
+            sni: true,
+        }
+    }
 }
 
 /// An HTTP Client
@@ -81,6 +129,12 @@ pub(crate) struct ClientConfig {
     pub(crate) connector: Box<dyn InnerConnect>,
     pub(crate) headers: HeaderMap,
     pub(crate) timeout: Option<Duration>,
+    #[cfg(feature = "signing")]
+    pub(crate) signers: signing::Signers,
+    #[cfg(feature = "har")]
+    pub(crate) har: Option<har::HarRecorder>,
+    #[cfg(feature = "dictionary")]
+    pub(crate) dictionary: Option<crate::http::encoding::dictionary::Dictionary>,
 }
 
 impl Default for Client {
@@ -89,6 +143,12 @@ impl Default for Client {
             connector: Box::new(ConnectorWrapper(Connector::default().finish())),
             headers: HeaderMap::new(),
             timeout: Some(Duration::from_secs(5)),
+            #[cfg(feature = "signing")]
+            signers: Default::default(),
+            #[cfg(feature = "har")]
+            har: None,
+            #[cfg(feature = "dictionary")]
+            dictionary: None,
         }))
     }
 }
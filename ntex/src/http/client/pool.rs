@@ -28,11 +28,17 @@ use super::Connect;
 #[derive(Hash, Eq, PartialEq, Clone, Debug)]
 pub(super) struct Key {
     authority: Authority,
+    server_name: Option<String>,
+    sni: bool,
 }
 
-impl From<Authority> for Key {
-    fn from(authority: Authority) -> Key {
-        Key { authority }
+impl Key {
+    fn new(authority: Authority, connect: &Connect) -> Key {
+        Key {
+            authority,
+            server_name: connect.server_name.clone(),
+            sni: connect.sni,
+        }
     }
 }
 
@@ -114,7 +120,7 @@ where
 
         let fut = async move {
             let key = if let Some(authority) = req.uri.authority() {
-                authority.clone().into()
+                Key::new(authority.clone(), &req)
             } else {
                 return Err(ConnectError::Unresolverd);
             };
@@ -304,7 +310,7 @@ where
     ) {
         let (tx, rx) = oneshot::channel();
 
-        let key: Key = connect.uri.authority().unwrap().clone().into();
+        let key = Key::new(connect.uri.authority().unwrap().clone(), &connect);
         let entry = self.waiters.vacant_entry();
         let token = entry.key();
         entry.insert(Some((connect, tx)));
@@ -19,12 +19,14 @@ use crate::http::{uri, ConnectionType, Method, RequestHead, Uri, Version};
 use super::error::{FreezeRequestError, InvalidUrl};
 use super::frozen::FrozenClientRequest;
 use super::sender::{PrepForSendingError, RequestSender, SendClientRequest};
-use super::ClientConfig;
+use super::{ClientConfig, ServerName};
 
 #[cfg(any(feature = "flate2-zlib", feature = "flate2-rust"))]
 const HTTPS_ENCODING: &str = "br, gzip, deflate";
 #[cfg(not(any(feature = "flate2-zlib", feature = "flate2-rust")))]
 const HTTPS_ENCODING: &str = "br";
+#[cfg(feature = "dictionary")]
+const DICTIONARY_ENCODING: &str = "dcb, br, gzip, deflate";
 
 /// An HTTP Client request builder
 ///
@@ -108,6 +110,24 @@ impl ClientRequest {
         self
     }
 
+    /// Set tls server name.
+    ///
+    /// Server name is used for SNI and certificate verification instead
+    /// of url's host name. Connections are not shared between requests
+    /// with different server names.
+    pub fn server_name(self, name: &str) -> Self {
+        ServerName::update(&self.head, |sn| sn.name = Some(name.to_string()));
+        self
+    }
+
+    /// Do not send tls SNI extension.
+    ///
+    /// Certificate is still verified against server name.
+    pub fn disable_sni(self) -> Self {
+        ServerName::update(&self.head, |sn| sn.sni = false);
+        self
+    }
+
     /// Set HTTP method of this request.
     #[inline]
     pub fn method(mut self, method: Method) -> Self {
@@ -535,6 +555,20 @@ impl ClientRequest {
         let mut slf = self;
 
         if slf.response_decompress {
+            #[cfg(feature = "dictionary")]
+            {
+                if let Some(value) =
+                    slf.config.dictionary.as_ref().map(|d| d.header_value())
+                {
+                    slf = slf
+                        .set_header_if_none(header::ACCEPT_ENCODING, DICTIONARY_ENCODING)
+                        .set_header_if_none(
+                            crate::http::encoding::dictionary::available_dictionary(),
+                            value,
+                        );
+                }
+            }
+
             let https = slf
                 .head
                 .uri
@@ -585,6 +619,22 @@ mod tests {
         assert!(repr.contains("x-test"));
     }
 
+    #[ntex_rt::test]
+    async fn test_server_name() {
+        let req = Client::new().get("https://127.0.0.1/");
+        let connect = crate::http::client::Connect::new(&req.head, None);
+        assert_eq!(connect.server_name, None);
+        assert!(connect.sni);
+
+        let req = Client::new()
+            .get("https://127.0.0.1/")
+            .server_name("example.com")
+            .disable_sni();
+        let connect = crate::http::client::Connect::new(&req.head, None);
+        assert_eq!(connect.server_name.as_deref(), Some("example.com"));
+        assert!(!connect.sni);
+    }
+
     #[ntex_rt::test]
     async fn test_basics() {
         let mut req = Client::new()
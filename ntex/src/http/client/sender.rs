@@ -16,6 +16,8 @@ use crate::http::header::{self, HeaderMap, HeaderName, IntoHeaderValue};
 use crate::http::RequestHead;
 use crate::rt::time::{delay_for, Delay};
 
+#[cfg(feature = "dictionary")]
+use crate::http::encoding::dictionary::Dictionary;
 #[cfg(feature = "compress")]
 use crate::http::encoding::Decoder;
 #[cfg(feature = "compress")]
@@ -158,6 +160,30 @@ impl From<PrepForSendingError> for SendClientRequest {
     }
 }
 
+#[cfg(feature = "dictionary")]
+/// Decode `dcb` encoded response with shared dictionary
+fn dictionary_decoder(
+    fut: Pin<Box<dyn Future<Output = Result<ClientResponse, SendRequestError>>>>,
+    dict: Dictionary,
+) -> Pin<Box<dyn Future<Output = Result<ClientResponse, SendRequestError>>>> {
+    Box::pin(async move {
+        Ok(fut.await?.map_body(|head, payload| {
+            let dcb = head
+                .headers
+                .get(&header::CONTENT_ENCODING)
+                .map(|v| v == "dcb")
+                .unwrap_or(false);
+            if dcb {
+                head.headers.remove(&header::CONTENT_ENCODING);
+                let stream: PayloadStream = Box::pin(Decoder::dictionary(payload, dict));
+                Payload::Stream(stream)
+            } else {
+                payload
+            }
+        }))
+    })
+}
+
 #[derive(Debug)]
 pub(crate) enum RequestSender {
     Owned(RequestHead),
@@ -176,13 +202,45 @@ impl RequestSender {
     where
         B: Into<Body>,
     {
-        let fut = match self {
+        let body = body.into();
+
+        #[cfg(feature = "signing")]
+        let sender = match self.sign(config, &body) {
+            Ok(sender) => sender,
+            Err(e) => return e.into(),
+        };
+        #[cfg(not(feature = "signing"))]
+        let sender = self;
+
+        #[cfg(feature = "har")]
+        let entry = config.har.as_ref().map(|har| match sender {
+            RequestSender::Owned(ref head) => har.start(head, None, &body),
+            RequestSender::Rc(ref head, ref extra_headers) => {
+                har.start(head, extra_headers.as_ref(), &body)
+            }
+        });
+
+        let fut = match sender {
             RequestSender::Owned(head) => {
-                config.connector.send_request(head, body.into(), addr)
+                config.connector.send_request(head, body, addr)
             }
             RequestSender::Rc(head, extra_headers) => config
                 .connector
-                .send_request_extra(head, extra_headers, body.into(), addr),
+                .send_request_extra(head, extra_headers, body, addr),
+        };
+
+        #[cfg(feature = "har")]
+        let fut = match entry {
+            Some(entry) => entry.wrap(fut),
+            None => fut,
+        };
+
+        #[cfg(feature = "dictionary")]
+        let fut = match config.dictionary {
+            Some(ref dict) if response_decompress => {
+                dictionary_decoder(fut, dict.clone())
+            }
+            _ => fut,
         };
 
         SendClientRequest::new(
@@ -280,6 +338,35 @@ impl RequestSender {
         self.send_body(addr, response_decompress, timeout, config, Body::Empty)
     }
 
+    #[cfg(feature = "signing")]
+    fn sign(
+        mut self,
+        config: &ClientConfig,
+        body: &Body,
+    ) -> Result<Self, SendRequestError> {
+        let headers = match self {
+            RequestSender::Owned(ref head) => config.signers.sign(head, None, body),
+            RequestSender::Rc(ref head, ref extra_headers) => {
+                config.signers.sign(head, extra_headers.as_ref(), body)
+            }
+        }
+        .map_err(|e| SendRequestError::Error(Box::new(e)))?;
+
+        if let Some(headers) = headers {
+            for (key, value) in headers.iter() {
+                match self {
+                    RequestSender::Owned(ref mut head) => {
+                        head.headers.insert(key.clone(), value.clone())
+                    }
+                    RequestSender::Rc(_, ref mut extra_headers) => extra_headers
+                        .get_or_insert(HeaderMap::new())
+                        .insert(key.clone(), value.clone()),
+                }
+            }
+        }
+        Ok(self)
+    }
+
     fn set_header_if_none<V>(
         &mut self,
         key: HeaderName,
@@ -0,0 +1,696 @@
+//! Request signing.
+//!
+//! Signers are configured per origin with `ClientBuilder::signer()` and
+//! are applied to every request to that origin right before it is sent.
+//! Request body is available to signer only if it is already buffered,
+//! streaming bodies are signed as unsigned payload.
+//!
+//! Supported schemes are [AWS Signature Version 4](https://docs.aws.amazon.com/general/latest/gr/signature-version-4.html)
+//! and [HTTP Message Signatures](https://www.rfc-editor.org/rfc/rfc9421) with
+//! `hmac-sha256` algorithm.
+//!
+//! ```rust
+//! use ntex::http::client::Client;
+//! use ntex::http::client::signing::{HttpSignature, SigV4};
+//!
+//! let client = Client::build()
+//!     .signer(
+//!         "https://s3.us-east-1.amazonaws.com",
+//!         SigV4::new("AKIDEXAMPLE", "secret", "us-east-1", "s3"),
+//!     )
+//!     .signer(
+//!         "https://api.example.com",
+//!         HttpSignature::hmac_sha256("key-1", b"secret"),
+//!     )
+//!     .finish();
+//! ```
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use derive_more::Display;
+use percent_encoding::{
+    percent_decode_str, utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC,
+};
+use sha2::{Digest, Sha256};
+use time::OffsetDateTime;
+
+use crate::http::body::Body;
+use crate::http::header::{self, HeaderMap, HeaderName, HeaderValue};
+use crate::http::{Method, RequestHead, Uri};
+
+/// Unreserved characters are not encoded
+const AWS_ENCODE_SET: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'_')
+    .remove(b'.')
+    .remove(b'~');
+const AWS_PATH_ENCODE_SET: &AsciiSet = &AWS_ENCODE_SET.remove(b'/');
+
+/// Errors which can occur during request signing
+#[derive(Debug, Display)]
+pub enum SignError {
+    /// Signed component is not present in request
+    #[display(fmt = "Signed component is missing: {}", _0)]
+    MissingComponent(String),
+    /// Signed component is not supported
+    #[display(fmt = "Signed component is not supported: {}", _0)]
+    UnknownComponent(String),
+    /// Generated header value is invalid
+    #[display(fmt = "Invalid header value")]
+    InvalidHeader,
+}
+
+impl std::error::Error for SignError {}
+
+/// Request parts available to signer
+#[derive(Debug)]
+pub struct SignRequest<'a> {
+    method: &'a Method,
+    uri: &'a Uri,
+    headers: &'a HeaderMap,
+    body: Option<&'a [u8]>,
+}
+
+impl<'a> SignRequest<'a> {
+    /// Request method
+    pub fn method(&self) -> &Method {
+        self.method
+    }
+
+    /// Request uri
+    pub fn uri(&self) -> &Uri {
+        self.uri
+    }
+
+    /// Request headers
+    pub fn headers(&self) -> &HeaderMap {
+        self.headers
+    }
+
+    /// Request body, `None` for streaming bodies
+    pub fn body(&self) -> Option<&[u8]> {
+        self.body
+    }
+
+    /// Host of the request, from `Host` header or uri authority
+    fn host(&self) -> Option<&str> {
+        self.headers
+            .get(header::HOST)
+            .and_then(|v| v.to_str().ok())
+            .or_else(|| self.uri.authority().map(|a| a.as_str()))
+    }
+}
+
+/// Request signer
+pub trait Signer {
+    /// Sign request, returns headers that must be set on the request
+    fn sign(&self, req: &SignRequest<'_>) -> Result<HeaderMap, SignError>;
+}
+
+/// Signers configured for origins
+#[derive(Default)]
+pub(crate) struct Signers(Vec<(String, Box<dyn Signer>)>);
+
+impl Signers {
+    pub(crate) fn add(&mut self, origin: &str, signer: Box<dyn Signer>) {
+        self.0
+            .push((origin.trim_end_matches('/').to_ascii_lowercase(), signer));
+    }
+
+    /// Sign request with signer configured for request's origin
+    pub(crate) fn sign(
+        &self,
+        head: &RequestHead,
+        extra_headers: Option<&HeaderMap>,
+        body: &Body,
+    ) -> Result<Option<HeaderMap>, SignError> {
+        if self.0.is_empty() {
+            return Ok(None);
+        }
+        let origin = match (head.uri.scheme_str(), head.uri.authority()) {
+            (Some(scheme), Some(authority)) => {
+                format!("{}://{}", scheme, authority).to_ascii_lowercase()
+            }
+            _ => return Ok(None),
+        };
+        let signer = match self.0.iter().find(|(o, _)| *o == origin) {
+            Some((_, signer)) => signer,
+            None => return Ok(None),
+        };
+
+        let merged;
+        let headers = if let Some(extra) = extra_headers {
+            let mut headers = head.headers.clone();
+            for name in extra.keys() {
+                headers.remove(name);
+            }
+            for (name, value) in extra.iter() {
+                headers.append(name.clone(), value.clone());
+            }
+            merged = headers;
+            &merged
+        } else {
+            &head.headers
+        };
+        let body = match body {
+            Body::None | Body::Empty => Some(&b""[..]),
+            Body::Bytes(ref bytes) => Some(&bytes[..]),
+            Body::Message(_) => None,
+        };
+
+        signer
+            .sign(&SignRequest {
+                method: &head.method,
+                uri: &head.uri,
+                headers,
+                body,
+            })
+            .map(Some)
+    }
+}
+
+/// AWS Signature Version 4 signer
+#[derive(Debug, Clone)]
+pub struct SigV4 {
+    access_key: String,
+    secret_key: String,
+    region: String,
+    service: String,
+    session_token: Option<String>,
+    content_sha256: bool,
+    headers: Vec<HeaderName>,
+}
+
+impl SigV4 {
+    /// Create signer for specified credentials, region and service
+    ///
+    /// `Host`, `Content-Type` and all `x-amz-*` headers are signed.
+    pub fn new(access_key: &str, secret_key: &str, region: &str, service: &str) -> Self {
+        SigV4 {
+            access_key: access_key.to_string(),
+            secret_key: secret_key.to_string(),
+            region: region.to_string(),
+            service: service.to_string(),
+            session_token: None,
+            content_sha256: service == "s3",
+            headers: Vec::new(),
+        }
+    }
+
+    /// Set session token for temporary credentials
+    pub fn session_token(mut self, token: &str) -> Self {
+        self.session_token = Some(token.to_string());
+        self
+    }
+
+    /// Send payload hash in `x-amz-content-sha256` header.
+    ///
+    /// By default header is sent only for `s3` service.
+    pub fn content_sha256(mut self, enabled: bool) -> Self {
+        self.content_sha256 = enabled;
+        self
+    }
+
+    /// Sign additional header
+    pub fn sign_header(mut self, name: HeaderName) -> Self {
+        self.headers.push(name);
+        self
+    }
+
+    fn sign_at(
+        &self,
+        req: &SignRequest<'_>,
+        time: SystemTime,
+    ) -> Result<HeaderMap, SignError> {
+        let time = OffsetDateTime::from(time);
+        let amz_date = time.format("%Y%m%dT%H%M%SZ");
+        let date = time.format("%Y%m%d");
+        let payload_hash = match req.body {
+            Some(body) => hex(&Sha256::digest(body)),
+            None => "UNSIGNED-PAYLOAD".to_string(),
+        };
+
+        let mut result = HeaderMap::new();
+        result.insert(
+            HeaderName::from_static("x-amz-date"),
+            header_value(&amz_date)?,
+        );
+        if let Some(ref token) = self.session_token {
+            result.insert(
+                HeaderName::from_static("x-amz-security-token"),
+                header_value(token)?,
+            );
+        }
+        if self.content_sha256 {
+            result.insert(
+                HeaderName::from_static("x-amz-content-sha256"),
+                header_value(&payload_hash)?,
+            );
+        }
+
+        // canonical headers
+        let host = req
+            .host()
+            .ok_or_else(|| SignError::MissingComponent("host".to_string()))?;
+        let mut headers = BTreeMap::new();
+        headers.insert("host".to_string(), host.to_string());
+        for map in &[req.headers, &result] {
+            for name in map.keys() {
+                let signed = name == header::CONTENT_TYPE
+                    || name.as_str().starts_with("x-amz-")
+                    || self.headers.contains(name);
+                if signed {
+                    let value = map
+                        .get_all(name)
+                        .map(|v| canonical_value(v.as_bytes()))
+                        .collect::<Vec<_>>()
+                        .join(",");
+                    headers.insert(name.as_str().to_string(), value);
+                }
+            }
+        }
+
+        let mut canonical_headers = String::new();
+        for (name, value) in &headers {
+            let _ = writeln!(canonical_headers, "{}:{}", name, value);
+        }
+        let signed_headers = headers
+            .keys()
+            .map(|name| name.as_str())
+            .collect::<Vec<_>>()
+            .join(";");
+
+        // canonical request
+        let path = if req.uri.path().is_empty() {
+            "/"
+        } else {
+            req.uri.path()
+        };
+        let path = if self.service == "s3" {
+            path.to_string()
+        } else {
+            utf8_percent_encode(path, AWS_PATH_ENCODE_SET).to_string()
+        };
+        let canonical_request = format!(
+            "{}\n{}\n{}\n{}\n{}\n{}",
+            req.method.as_str(),
+            path,
+            canonical_query(req.uri.query().unwrap_or("")),
+            canonical_headers,
+            signed_headers,
+            payload_hash
+        );
+
+        let scope = format!("{}/{}/{}/aws4_request", date, self.region, self.service);
+        let string_to_sign = format!(
+            "AWS4-HMAC-SHA256\n{}\n{}\n{}",
+            amz_date,
+            scope,
+            hex(&Sha256::digest(canonical_request.as_bytes()))
+        );
+
+        let key = hmac_sha256(
+            format!("AWS4{}", self.secret_key).as_bytes(),
+            date.as_bytes(),
+        );
+        let key = hmac_sha256(&key, self.region.as_bytes());
+        let key = hmac_sha256(&key, self.service.as_bytes());
+        let key = hmac_sha256(&key, b"aws4_request");
+        let signature = hex(&hmac_sha256(&key, string_to_sign.as_bytes()));
+
+        result.insert(
+            header::AUTHORIZATION,
+            header_value(&format!(
+                "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}",
+                self.access_key, scope, signed_headers, signature
+            ))?,
+        );
+        Ok(result)
+    }
+}
+
+impl Signer for SigV4 {
+    fn sign(&self, req: &SignRequest<'_>) -> Result<HeaderMap, SignError> {
+        self.sign_at(req, SystemTime::now())
+    }
+}
+
+/// HTTP Message Signatures signer, `hmac-sha256` algorithm
+#[derive(Debug, Clone)]
+pub struct HttpSignature {
+    key_id: String,
+    key: Vec<u8>,
+    label: String,
+    components: Vec<String>,
+    content_digest: bool,
+}
+
+impl HttpSignature {
+    /// Create signer with shared secret key.
+    ///
+    /// By default `@method`, `@authority` and `@path` components are
+    /// signed, `Content-Digest` header is generated and signed for
+    /// buffered bodies.
+    pub fn hmac_sha256(key_id: &str, key: &[u8]) -> Self {
+        HttpSignature {
+            key_id: key_id.to_string(),
+            key: key.to_vec(),
+            label: "sig1".to_string(),
+            components: vec![
+                "@method".to_string(),
+                "@authority".to_string(),
+                "@path".to_string(),
+            ],
+            content_digest: true,
+        }
+    }
+
+    /// Set signature label. By default label is `sig1`
+    pub fn label(mut self, label: &str) -> Self {
+        self.label = label.to_string();
+        self
+    }
+
+    /// Set covered components.
+    ///
+    /// Supported derived components are `@method`, `@authority`, `@scheme`,
+    /// `@target-uri`, `@request-target`, `@path` and `@query`, other
+    /// components are header names.
+    pub fn components(mut self, components: &[&str]) -> Self {
+        self.components = components.iter().map(|c| c.to_ascii_lowercase()).collect();
+        self
+    }
+
+    /// Generate and sign `Content-Digest` header. By default it is enabled.
+    pub fn content_digest(mut self, enabled: bool) -> Self {
+        self.content_digest = enabled;
+        self
+    }
+
+    fn sign_at(
+        &self,
+        req: &SignRequest<'_>,
+        created: u64,
+    ) -> Result<HeaderMap, SignError> {
+        let mut result = HeaderMap::new();
+        let mut components = self.components.clone();
+
+        if self.content_digest {
+            if let Some(body) = req.body {
+                let digest =
+                    format!("sha-256=:{}:", base64::encode(Sha256::digest(body)));
+                result.insert(
+                    HeaderName::from_static("content-digest"),
+                    header_value(&digest)?,
+                );
+                if !components.iter().any(|c| c == "content-digest") {
+                    components.push("content-digest".to_string());
+                }
+            }
+        }
+
+        let mut base = String::new();
+        for name in &components {
+            let value = match name.as_str() {
+                "@method" => req.method.as_str().to_string(),
+                "@authority" => req
+                    .uri
+                    .authority()
+                    .map(|a| a.as_str().to_ascii_lowercase())
+                    .ok_or_else(|| SignError::MissingComponent(name.clone()))?,
+                "@scheme" => req
+                    .uri
+                    .scheme_str()
+                    .map(|s| s.to_ascii_lowercase())
+                    .ok_or_else(|| SignError::MissingComponent(name.clone()))?,
+                "@target-uri" => req.uri.to_string(),
+                "@request-target" => req
+                    .uri
+                    .path_and_query()
+                    .map(|p| p.as_str().to_string())
+                    .unwrap_or_else(|| "/".to_string()),
+                "@path" => {
+                    if req.uri.path().is_empty() {
+                        "/".to_string()
+                    } else {
+                        req.uri.path().to_string()
+                    }
+                }
+                "@query" => format!("?{}", req.uri.query().unwrap_or("")),
+                _ if name.starts_with('@') => {
+                    return Err(SignError::UnknownComponent(name.clone()))
+                }
+                _ => {
+                    let map = if result.contains_key(name.as_str()) {
+                        &result
+                    } else {
+                        req.headers
+                    };
+                    let values: Vec<_> = map
+                        .get_all(name.as_str())
+                        .map(|v| {
+                            String::from_utf8_lossy(v.as_bytes()).trim().to_string()
+                        })
+                        .collect();
+                    if values.is_empty() {
+                        return Err(SignError::MissingComponent(name.clone()));
+                    }
+                    values.join(", ")
+                }
+            };
+            let _ = writeln!(base, "\"{}\": {}", name, value);
+        }
+
+        let params = format!(
+            "({});created={};keyid=\"{}\"",
+            components
+                .iter()
+                .map(|c| format!("\"{}\"", c))
+                .collect::<Vec<_>>()
+                .join(" "),
+            created,
+            self.key_id
+        );
+        let _ = write!(base, "\"@signature-params\": {}", params);
+        let signature = base64::encode(hmac_sha256(&self.key, base.as_bytes()));
+
+        result.insert(
+            HeaderName::from_static("signature-input"),
+            header_value(&format!("{}={}", self.label, params))?,
+        );
+        result.insert(
+            HeaderName::from_static("signature"),
+            header_value(&format!("{}=:{}:", self.label, signature))?,
+        );
+        Ok(result)
+    }
+}
+
+impl Signer for HttpSignature {
+    fn sign(&self, req: &SignRequest<'_>) -> Result<HeaderMap, SignError> {
+        let created = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        self.sign_at(req, created)
+    }
+}
+
+fn header_value(s: &str) -> Result<HeaderValue, SignError> {
+    HeaderValue::from_str(s).map_err(|_| SignError::InvalidHeader)
+}
+
+fn hex(data: &[u8]) -> String {
+    let mut s = String::with_capacity(data.len() * 2);
+    for b in data {
+        let _ = write!(s, "{:02x}", b);
+    }
+    s
+}
+
+fn hmac_sha256(key: &[u8], data: &[u8]) -> Vec<u8> {
+    let mut block = [0u8; 64];
+    if key.len() > block.len() {
+        block[..32].copy_from_slice(&Sha256::digest(key));
+    } else {
+        block[..key.len()].copy_from_slice(key);
+    }
+
+    let mut inner = Sha256::new();
+    inner.input(block.iter().map(|b| b ^ 0x36).collect::<Vec<_>>());
+    inner.input(data);
+    let mut outer = Sha256::new();
+    outer.input(block.iter().map(|b| b ^ 0x5c).collect::<Vec<_>>());
+    outer.input(inner.result());
+    outer.result().to_vec()
+}
+
+/// Trim value and collapse sequential spaces
+fn canonical_value(value: &[u8]) -> String {
+    String::from_utf8_lossy(value)
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn canonical_query(query: &str) -> String {
+    let mut params: Vec<_> = query
+        .split('&')
+        .filter(|p| !p.is_empty())
+        .map(|p| {
+            let mut parts = p.splitn(2, '=');
+            let encode = |s: &str| {
+                let s = percent_decode_str(s).decode_utf8_lossy();
+                utf8_percent_encode(&s, AWS_ENCODE_SET).to_string()
+            };
+            let name = encode(parts.next().unwrap());
+            let value = encode(parts.next().unwrap_or(""));
+            (name, value)
+        })
+        .collect();
+    params.sort();
+    params
+        .iter()
+        .map(|(name, value)| format!("{}={}", name, value))
+        .collect::<Vec<_>>()
+        .join("&")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    fn request<'a>(
+        method: &'a Method,
+        uri: &'a Uri,
+        headers: &'a HeaderMap,
+        body: Option<&'a [u8]>,
+    ) -> SignRequest<'a> {
+        SignRequest {
+            method,
+            uri,
+            headers,
+            body,
+        }
+    }
+
+    #[test]
+    fn test_hmac() {
+        // rfc 4231, test case 2
+        assert_eq!(
+            hex(&hmac_sha256(b"Jefe", b"what do ya want for nothing?")),
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+    }
+
+    #[test]
+    fn test_sigv4() {
+        // aws sigv4 test suite, get-vanilla
+        let uri = Uri::from_static("https://example.amazonaws.com/");
+        let headers = HeaderMap::new();
+        let time = UNIX_EPOCH + Duration::from_secs(1_440_938_160);
+        let signer = SigV4::new(
+            "AKIDEXAMPLE",
+            "wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY",
+            "us-east-1",
+            "service",
+        );
+
+        let res = signer
+            .sign_at(&request(&Method::GET, &uri, &headers, Some(b"")), time)
+            .unwrap();
+        assert_eq!(res.get("x-amz-date").unwrap(), "20150830T123600Z");
+        assert_eq!(
+            res.get(header::AUTHORIZATION).unwrap(),
+            "AWS4-HMAC-SHA256 Credential=AKIDEXAMPLE/20150830/us-east-1/service/aws4_request, \
+             SignedHeaders=host;x-amz-date, \
+             Signature=5fa00fa31553b73ebf1942676e86291e8372ff2a2260956d9b8aae1d763fbf31"
+        );
+
+        let signer = signer.session_token("token").content_sha256(true);
+        let res = signer
+            .sign_at(&request(&Method::PUT, &uri, &headers, None), time)
+            .unwrap();
+        assert_eq!(res.get("x-amz-security-token").unwrap(), "token");
+        assert_eq!(res.get("x-amz-content-sha256").unwrap(), "UNSIGNED-PAYLOAD");
+        assert!(res
+            .get(header::AUTHORIZATION)
+            .unwrap()
+            .to_str()
+            .unwrap()
+            .contains(
+            "SignedHeaders=host;x-amz-content-sha256;x-amz-date;x-amz-security-token,"
+        ));
+    }
+
+    #[test]
+    fn test_canonical_query() {
+        assert_eq!(canonical_query(""), "");
+        assert_eq!(canonical_query("b=2&a=%20x&c&a=1"), "a=%20x&a=1&b=2&c=");
+    }
+
+    #[test]
+    fn test_http_signature() {
+        // rfc 9421, appendix B.2.5
+        let key = base64::decode(
+            "uzvJfB4u3N0Jy4T7NZ75MDVcr8zSTInedJtkgcu46YW4XByzNJjxBdtjUkdJPBtbmHhIDi6pcl8jsasjlTMtDQ==",
+        )
+        .unwrap();
+        let uri = Uri::from_static("https://example.com/foo?param=Value&Pet=dog");
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            header::DATE,
+            HeaderValue::from_static("Tue, 20 Apr 2021 02:07:55 GMT"),
+        );
+        headers.insert(
+            header::CONTENT_TYPE,
+            HeaderValue::from_static("application/json"),
+        );
+        let body = br#"{"hello": "world"}"#;
+
+        let signer = HttpSignature::hmac_sha256("test-shared-secret", &key)
+            .label("sig-b25")
+            .components(&["date", "@authority", "content-type"])
+            .content_digest(false);
+        let res = signer
+            .sign_at(
+                &request(&Method::POST, &uri, &headers, Some(body)),
+                1_618_884_473,
+            )
+            .unwrap();
+        assert_eq!(
+            res.get("signature-input").unwrap(),
+            "sig-b25=(\"date\" \"@authority\" \"content-type\");created=1618884473;keyid=\"test-shared-secret\""
+        );
+        assert_eq!(
+            res.get("signature").unwrap(),
+            "sig-b25=:pxcQw6G3AjtMBQjwo8XzkZf/bws5LelbaMk5rGIGtE8=:"
+        );
+        assert!(res.get("content-digest").is_none());
+
+        let signer = HttpSignature::hmac_sha256("key", b"secret");
+        let res = signer
+            .sign_at(
+                &request(&Method::POST, &uri, &headers, Some(body)),
+                1_618_884_473,
+            )
+            .unwrap();
+        assert_eq!(
+            res.get("content-digest").unwrap(),
+            "sha-256=:X48E9qOokqqrvdts8nOJRJN3OWDUoyWxBf7kbu9DBPE=:"
+        );
+        assert_eq!(
+            res.get("signature-input").unwrap(),
+            "sig1=(\"@method\" \"@authority\" \"@path\" \"content-digest\");created=1618884473;keyid=\"key\""
+        );
+
+        let signer =
+            HttpSignature::hmac_sha256("key", b"secret").components(&["x-missing"]);
+        assert!(signer
+            .sign_at(&request(&Method::GET, &uri, &headers, None), 0)
+            .is_err());
+    }
+}
@@ -20,7 +20,7 @@ use crate::ws;
 use super::connect::BoxedSocket;
 use super::error::{InvalidUrl, SendRequestError, WsClientError};
 use super::response::ClientResponse;
-use super::ClientConfig;
+use super::{ClientConfig, ServerName};
 
 /// `WebSocket` connection
 pub struct WebsocketsRequest {
@@ -76,6 +76,21 @@ impl WebsocketsRequest {
         self
     }
 
+    /// Set tls server name.
+    ///
+    /// Server name is used for SNI and certificate verification instead
+    /// of url's host name.
+    pub fn server_name(self, name: &str) -> Self {
+        ServerName::update(&self.head, |sn| sn.name = Some(name.to_string()));
+        self
+    }
+
+    /// Do not send tls SNI extension.
+    pub fn disable_sni(self) -> Self {
+        ServerName::update(&self.head, |sn| sn.sni = false);
+        self
+    }
+
     /// Set supported websocket protocols
     pub fn protocols<U, V>(mut self, protos: U) -> Self
     where
@@ -0,0 +1,147 @@
+use std::cell::RefCell;
+use std::future::Future;
+use std::rc::Rc;
+
+use futures::future::{FutureExt, LocalBoxFuture};
+
+type Hook = Box<dyn FnOnce(Delivery) -> LocalBoxFuture<'static, ()>>;
+
+/// Response delivery status
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Delivery {
+    /// Response head and body are written to the peer
+    Written,
+    /// Response is not completely written, connection is closed or failed
+    Failed,
+}
+
+/// Response delivery hooks.
+///
+/// Hooks run after response head and body are written to the connection,
+/// or after dispatcher fails to write response. Hooks could be used for
+/// side effects that must be tied to actual delivery, like analytics events
+/// or message queue publishes. Each hook runs as a separate task.
+///
+/// Dispatcher takes hooks from response extensions. Web application moves
+/// hooks from request extensions to response extensions, so hooks could be
+/// registered from handlers and middlewares with `ResponseCommitted`
+/// extractor. For *HTTP/1* response is written once data is written to
+/// the socket, for *HTTP/2* once data is passed to the connection.
+///
+/// If connection fails or gets closed before response is written, hooks
+/// run with `Delivery::Failed` status. Hooks that never reach dispatcher,
+/// for example if server is shutting down, are dropped without running.
+///
+/// ```rust
+/// use ntex::http::{Delivery, ResponseCommitted};
+/// use ntex::web::{self, App, HttpResponse};
+///
+/// async fn index(committed: ResponseCommitted) -> HttpResponse {
+///     committed.on_commit(|delivery| async move {
+///         if delivery == Delivery::Written {
+///             println!("Response is delivered");
+///         }
+///     });
+///     HttpResponse::Ok().finish()
+/// }
+///
+/// fn main() {
+///     let app = App::new().service(web::resource("/").to(index));
+/// }
+/// ```
+#[derive(Clone, Default)]
+pub struct ResponseCommitted(Rc<RefCell<Vec<Hook>>>);
+
+impl ResponseCommitted {
+    /// Create empty hooks set
+    pub fn new() -> Self {
+        ResponseCommitted::default()
+    }
+
+    /// Register hook
+    pub fn on_commit<F, R>(&self, f: F)
+    where
+        F: FnOnce(Delivery) -> R + 'static,
+        R: Future<Output = ()> + 'static,
+    {
+        self.0
+            .borrow_mut()
+            .push(Box::new(move |d| f(d).boxed_local()));
+    }
+
+    /// Number of registered hooks
+    pub fn len(&self) -> usize {
+        self.0.borrow().len()
+    }
+
+    /// Check if no hooks are registered
+    pub fn is_empty(&self) -> bool {
+        self.0.borrow().is_empty()
+    }
+
+    /// Spawn registered hooks, must be called within running system
+    pub(crate) fn commit(&self, delivery: Delivery) {
+        let hooks = std::mem::take(&mut *self.0.borrow_mut());
+        for hook in hooks {
+            crate::rt::spawn(hook(delivery));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+    use std::time::Duration;
+
+    use super::*;
+
+    #[ntex_rt::test]
+    async fn test_committed() {
+        let status = Rc::new(Cell::new(None));
+
+        let hooks = ResponseCommitted::new();
+        assert!(hooks.is_empty());
+        let st = status.clone();
+        hooks.on_commit(move |d| async move { st.set(Some(d)) });
+        assert_eq!(hooks.len(), 1);
+
+        hooks.commit(Delivery::Written);
+        assert!(hooks.is_empty());
+        crate::rt::time::delay_for(Duration::from_millis(10)).await;
+        assert_eq!(status.get(), Some(Delivery::Written));
+
+        // hooks run once
+        hooks.commit(Delivery::Failed);
+        crate::rt::time::delay_for(Duration::from_millis(10)).await;
+        assert_eq!(status.get(), Some(Delivery::Written));
+    }
+
+    #[ntex_rt::test]
+    async fn test_web() {
+        use std::sync::{Arc, Mutex};
+
+        use crate::web::{self, test, App, HttpResponse};
+
+        let status = Arc::new(Mutex::new(Vec::new()));
+        let status2 = status.clone();
+        let srv = test::server(move || {
+            let status = status2.clone();
+            App::new().service(web::resource("/").to(move |c: ResponseCommitted| {
+                let status = status.clone();
+                c.on_commit(move |d| async move { status.lock().unwrap().push(d) });
+                async { HttpResponse::Ok().body("data") }
+            }))
+        });
+
+        let response = srv.get("/").send().await.unwrap();
+        assert!(response.status().is_success());
+
+        for _ in 0..50 {
+            if !status.lock().unwrap().is_empty() {
+                break;
+            }
+            crate::rt::time::delay_for(Duration::from_millis(10)).await;
+        }
+        assert_eq!(*status.lock().unwrap(), vec![Delivery::Written]);
+    }
+}
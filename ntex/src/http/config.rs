@@ -10,6 +10,8 @@ use time::OffsetDateTime;
 
 use crate::rt::time::{delay_for, delay_until, Delay, Instant};
 
+use super::pending::PendingLimits;
+
 // "Sun, 06 Nov 1994 08:49:37 GMT".len()
 const DATE_VALUE_LENGTH: usize = 29;
 
@@ -40,6 +42,33 @@ impl From<Option<usize>> for KeepAlive {
     }
 }
 
+#[derive(Debug, PartialEq, Clone, Copy)]
+/// Http/1 request limits
+pub(super) struct Limits {
+    /// Max size of request uri
+    pub(super) max_uri_size: usize,
+    /// Max number of request headers
+    pub(super) max_headers: usize,
+    /// Max size of all request headers
+    pub(super) max_header_size: usize,
+    /// Max size of chunked payload trailer section
+    pub(super) max_trailer_size: usize,
+    /// Max request payload read rate in bytes per second, zero means no limit
+    pub(super) max_payload_rate: usize,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Limits {
+            max_uri_size: 16_384,
+            max_headers: 96,
+            max_header_size: 65_536,
+            max_trailer_size: 8_192,
+            max_payload_rate: 0,
+        }
+    }
+}
+
 /// Http service configuration
 pub struct ServiceConfig(pub(super) Rc<Inner>);
 
@@ -50,6 +79,8 @@ pub(super) struct Inner {
     pub(super) ka_enabled: bool,
     pub(super) timer: DateService,
     pub(super) ssl_handshake_timeout: u64,
+    pub(super) limits: Limits,
+    pub(super) pending: PendingLimits,
 }
 
 impl Clone for ServiceConfig {
@@ -71,6 +102,24 @@ impl ServiceConfig {
         client_timeout: u64,
         client_disconnect: u64,
         ssl_handshake_timeout: u64,
+    ) -> ServiceConfig {
+        ServiceConfig::with_limits(
+            keep_alive,
+            client_timeout,
+            client_disconnect,
+            ssl_handshake_timeout,
+            Limits::default(),
+            PendingLimits::default(),
+        )
+    }
+
+    pub(super) fn with_limits(
+        keep_alive: KeepAlive,
+        client_timeout: u64,
+        client_disconnect: u64,
+        ssl_handshake_timeout: u64,
+        limits: Limits,
+        pending: PendingLimits,
     ) -> ServiceConfig {
         let (keep_alive, ka_enabled) = match keep_alive {
             KeepAlive::Timeout(val) => (val as u64, true),
@@ -89,6 +138,8 @@ impl ServiceConfig {
             client_timeout,
             client_disconnect,
             ssl_handshake_timeout,
+            limits,
+            pending,
             timer: DateService::new(),
         }))
     }
@@ -103,6 +154,8 @@ pub(super) struct DispatcherConfig<S, X, U> {
     pub(super) client_disconnect: u64,
     pub(super) ka_enabled: bool,
     pub(super) timer: DateService,
+    pub(super) limits: Limits,
+    pub(super) pending: PendingLimits,
 }
 
 impl<S, X, U> DispatcherConfig<S, X, U> {
@@ -121,6 +174,8 @@ impl<S, X, U> DispatcherConfig<S, X, U> {
             client_disconnect: cfg.0.client_disconnect,
             ka_enabled: cfg.0.ka_enabled,
             timer: cfg.0.timer.clone(),
+            limits: cfg.0.limits,
+            pending: cfg.0.pending,
         }
     }
 
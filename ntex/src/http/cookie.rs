@@ -0,0 +1,358 @@
+//! Cookie utilities
+use std::convert::TryFrom;
+use std::fmt;
+
+use coo_kie::{Cookie, ParseError};
+#[cfg(feature = "secure-cookies")]
+use coo_kie::{CookieJar, Key};
+use derive_more::Display;
+
+use super::header::{HeaderValue, IntoHeaderValue, InvalidHeaderValue};
+#[cfg(feature = "secure-cookies")]
+use super::HttpMessage;
+
+/// Cookie name prefix
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum CookiePrefix {
+    /// `__Secure-` prefix, cookie must have `Secure` attribute
+    Secure,
+    /// `__Host-` prefix, cookie must have `Secure` attribute and `/` path,
+    /// and must not have `Domain` attribute
+    Host,
+}
+
+impl CookiePrefix {
+    /// Get prefix of cookie name
+    pub fn from_name(name: &str) -> Option<CookiePrefix> {
+        let starts_with = |prefix: &str| {
+            name.len() >= prefix.len()
+                && name.as_bytes()[..prefix.len()]
+                    .eq_ignore_ascii_case(prefix.as_bytes())
+        };
+
+        if starts_with(CookiePrefix::Secure.as_str()) {
+            Some(CookiePrefix::Secure)
+        } else if starts_with(CookiePrefix::Host.as_str()) {
+            Some(CookiePrefix::Host)
+        } else {
+            None
+        }
+    }
+
+    /// Prefix string
+    pub fn as_str(self) -> &'static str {
+        match self {
+            CookiePrefix::Secure => "__Secure-",
+            CookiePrefix::Host => "__Host-",
+        }
+    }
+
+    /// Check if cookie attributes satisfy prefix requirements
+    pub fn validate(self, cookie: &Cookie<'_>) -> Result<(), CookiePrefixError> {
+        if cookie.secure() != Some(true) {
+            return Err(CookiePrefixError::NotSecure);
+        }
+        if self == CookiePrefix::Host {
+            if cookie.path() != Some("/") {
+                return Err(CookiePrefixError::Path);
+            }
+            if cookie.domain().is_some() {
+                return Err(CookiePrefixError::Domain);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Check if cookie satisfies requirements of its name prefix.
+///
+/// Cookies without `__Secure-` or `__Host-` prefix are always valid.
+pub fn validate_prefix(cookie: &Cookie<'_>) -> Result<(), CookiePrefixError> {
+    if let Some(prefix) = CookiePrefix::from_name(cookie.name()) {
+        prefix.validate(cookie)
+    } else {
+        Ok(())
+    }
+}
+
+/// Cookie prefix validation error
+#[derive(Copy, Clone, Debug, Display, PartialEq, Eq)]
+pub enum CookiePrefixError {
+    /// Prefixed cookie does not have `Secure` attribute
+    #[display(fmt = "Prefixed cookie must have Secure attribute")]
+    NotSecure,
+    /// `__Host-` cookie path is not `/`
+    #[display(fmt = "__Host- cookie must have / path")]
+    Path,
+    /// `__Host-` cookie has `Domain` attribute
+    #[display(fmt = "__Host- cookie must not have Domain attribute")]
+    Domain,
+}
+
+impl std::error::Error for CookiePrefixError {}
+
+/// `Set-Cookie` header value.
+///
+/// Supports `Partitioned` attribute (CHIPS) in addition to `Cookie`
+/// attributes. Partitioned cookie is always secure.
+///
+/// ```rust
+/// use coo_kie::Cookie;
+/// use ntex::http::{cookie::SetCookie, header, Response};
+///
+/// let res = Response::Ok()
+///     .header(
+///         header::SET_COOKIE,
+///         SetCookie::new(Cookie::new("name", "value")).partitioned(true),
+///     )
+///     .finish();
+/// assert_eq!(
+///     res.headers().get(header::SET_COOKIE).unwrap(),
+///     "name=value; Secure; Partitioned"
+/// );
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct SetCookie<'c> {
+    cookie: Cookie<'c>,
+    partitioned: bool,
+}
+
+impl<'c> SetCookie<'c> {
+    /// Create `Set-Cookie` value for cookie
+    pub fn new(cookie: Cookie<'c>) -> Self {
+        SetCookie {
+            cookie,
+            partitioned: false,
+        }
+    }
+
+    /// Parse `Set-Cookie` header value
+    pub fn parse(s: &'c str) -> Result<Self, ParseError> {
+        let partitioned = s
+            .split(';')
+            .skip(1)
+            .any(|attr| attr.trim().eq_ignore_ascii_case("partitioned"));
+        Ok(SetCookie {
+            partitioned,
+            cookie: Cookie::parse(s)?,
+        })
+    }
+
+    /// Set `Partitioned` attribute
+    pub fn partitioned(mut self, value: bool) -> Self {
+        self.set_partitioned(value);
+        self
+    }
+
+    /// Set `Partitioned` attribute
+    pub fn set_partitioned(&mut self, value: bool) {
+        self.partitioned = value;
+        if value {
+            self.cookie.set_secure(true);
+        }
+    }
+
+    /// Check if `Partitioned` attribute is set
+    pub fn is_partitioned(&self) -> bool {
+        self.partitioned
+    }
+
+    /// Get reference to cookie
+    pub fn cookie(&self) -> &Cookie<'c> {
+        &self.cookie
+    }
+
+    /// Get mutable reference to cookie
+    pub fn cookie_mut(&mut self) -> &mut Cookie<'c> {
+        &mut self.cookie
+    }
+
+    /// Convert into cookie, `Partitioned` attribute is lost
+    pub fn into_cookie(self) -> Cookie<'c> {
+        self.cookie
+    }
+}
+
+impl<'c> From<Cookie<'c>> for SetCookie<'c> {
+    fn from(cookie: Cookie<'c>) -> Self {
+        SetCookie::new(cookie)
+    }
+}
+
+impl<'c> fmt::Display for SetCookie<'c> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.cookie.fmt(f)?;
+        if self.partitioned {
+            f.write_str("; Partitioned")?;
+        }
+        Ok(())
+    }
+}
+
+impl<'c> IntoHeaderValue for SetCookie<'c> {
+    type Error = InvalidHeaderValue;
+
+    fn try_into(self) -> Result<HeaderValue, Self::Error> {
+        HeaderValue::try_from(self.to_string())
+    }
+}
+
+#[cfg(feature = "secure-cookies")]
+/// Signed or private cookie jar.
+///
+/// Signed cookie values are authenticated, private cookie values are
+/// encrypted and authenticated. Cookies that fail verification are
+/// ignored.
+///
+/// ```rust
+/// use coo_kie::Cookie;
+/// use ntex::http::cookie::SecureJar;
+///
+/// let jar = SecureJar::private(&[0; 32]);
+/// let sealed = jar.seal(Cookie::new("name", "value"));
+/// assert_ne!(sealed.value(), "value");
+/// assert_eq!(jar.open(sealed).unwrap().value(), "value");
+/// ```
+#[derive(Clone)]
+pub struct SecureJar {
+    key: Key,
+    private: bool,
+}
+
+#[cfg(feature = "secure-cookies")]
+impl SecureJar {
+    /// Construct jar with signed cookies.
+    ///
+    /// Key must be at least 32 bytes long.
+    pub fn signed(key: &[u8]) -> Self {
+        SecureJar {
+            key: Key::from_master(key),
+            private: false,
+        }
+    }
+
+    /// Construct jar with private cookies, cookie value is encrypted.
+    ///
+    /// Key must be at least 32 bytes long.
+    pub fn private(key: &[u8]) -> Self {
+        SecureJar {
+            key: Key::from_master(key),
+            private: true,
+        }
+    }
+
+    /// Check if jar encrypts cookies
+    pub fn is_private(&self) -> bool {
+        self.private
+    }
+
+    /// Sign or encrypt cookie value
+    pub fn seal(&self, cookie: Cookie<'static>) -> Cookie<'static> {
+        let name = cookie.name().to_string();
+        let mut jar = CookieJar::new();
+        if self.private {
+            jar.private(&self.key).add(cookie);
+        } else {
+            jar.signed(&self.key).add(cookie);
+        }
+        jar.get(&name).cloned().unwrap()
+    }
+
+    /// Verify or decrypt cookie value.
+    ///
+    /// Returns `None` if cookie verification failed.
+    pub fn open(&self, cookie: Cookie<'static>) -> Option<Cookie<'static>> {
+        let name = cookie.name().to_string();
+        let mut jar = CookieJar::new();
+        jar.add_original(cookie);
+        if self.private {
+            jar.private(&self.key).get(&name)
+        } else {
+            jar.signed(&self.key).get(&name)
+        }
+    }
+
+    /// Get verified cookie of the message
+    pub fn get<T: HttpMessage>(&self, msg: &T, name: &str) -> Option<Cookie<'static>> {
+        msg.cookie(name).and_then(|cookie| self.open(cookie))
+    }
+}
+
+#[cfg(feature = "secure-cookies")]
+impl fmt::Debug for SecureJar {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SecureJar")
+            .field("private", &self.private)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_prefix() {
+        assert_eq!(CookiePrefix::from_name("name"), None);
+        assert_eq!(
+            CookiePrefix::from_name("__Secure-name"),
+            Some(CookiePrefix::Secure)
+        );
+        assert_eq!(
+            CookiePrefix::from_name("__host-name"),
+            Some(CookiePrefix::Host)
+        );
+
+        assert!(validate_prefix(&Cookie::new("name", "value")).is_ok());
+        assert_eq!(
+            validate_prefix(&Cookie::new("__Secure-name", "value")),
+            Err(CookiePrefixError::NotSecure)
+        );
+        let cookie = Cookie::build("__Secure-name", "value")
+            .secure(true)
+            .domain("example.com")
+            .finish();
+        assert!(validate_prefix(&cookie).is_ok());
+
+        let cookie = Cookie::build("__Host-name", "value").secure(true).finish();
+        assert_eq!(validate_prefix(&cookie), Err(CookiePrefixError::Path));
+        let cookie = Cookie::build("__Host-name", "value")
+            .secure(true)
+            .path("/")
+            .domain("example.com")
+            .finish();
+        assert_eq!(validate_prefix(&cookie), Err(CookiePrefixError::Domain));
+        let cookie = Cookie::build("__Host-name", "value")
+            .secure(true)
+            .path("/")
+            .finish();
+        assert!(validate_prefix(&cookie).is_ok());
+    }
+
+    #[test]
+    fn test_set_cookie() {
+        let c = SetCookie::new(Cookie::new("name", "value")).partitioned(true);
+        assert!(c.is_partitioned());
+        assert_eq!(c.to_string(), "name=value; Secure; Partitioned");
+
+        let c = SetCookie::parse("name=value; Path=/; partitioned; Secure").unwrap();
+        assert!(c.is_partitioned());
+        assert_eq!(c.cookie().path(), Some("/"));
+        assert_eq!(c.to_string(), "name=value; Secure; Path=/; Partitioned");
+
+        let c = SetCookie::parse("partitioned=value").unwrap();
+        assert!(!c.is_partitioned());
+    }
+
+    #[cfg(feature = "secure-cookies")]
+    #[test]
+    fn test_secure_jar() {
+        for jar in &[SecureJar::signed(&[0; 32]), SecureJar::private(&[0; 32])] {
+            let sealed = jar.seal(Cookie::new("name", "value"));
+            assert_ne!(sealed.value(), "value");
+            assert_eq!(jar.open(sealed.clone()).unwrap().value(), "value");
+            assert!(jar.open(Cookie::new("name", "value")).is_none());
+            assert!(SecureJar::private(&[1; 32]).open(sealed).is_none());
+        }
+    }
+}
@@ -1,3 +1,4 @@
+use std::error::Error;
 use std::future::Future;
 use std::io::{self, Write};
 use std::pin::Pin;
@@ -9,12 +10,20 @@ use bytes::Bytes;
 use flate2::write::{GzDecoder, ZlibDecoder};
 use futures::{ready, Stream};
 
+#[cfg(feature = "dictionary")]
+use super::dictionary::{DcbDecoder, Dictionary};
 use super::Writer;
+use crate::http::body::{BodySize, MessageBody};
 use crate::http::error::PayloadError;
 use crate::http::header::{ContentEncoding, HeaderMap, CONTENT_ENCODING};
 
 const INPLACE: usize = 2049;
 
+/// Streaming payload decoder.
+///
+/// Decoder decompresses chunks of any payload stream, large chunks are
+/// decompressed in thread pool. Decoder implements `MessageBody`, so
+/// decoded payload could be used as response body.
 pub struct Decoder<S> {
     decoder: Option<ContentDecoder>,
     stream: S,
@@ -49,6 +58,17 @@ where
         }
     }
 
+    /// Construct `dcb` shared dictionary decoder.
+    #[cfg(feature = "dictionary")]
+    pub fn dictionary(stream: S, dict: Dictionary) -> Decoder<S> {
+        Decoder {
+            stream,
+            decoder: Some(ContentDecoder::Dcb(Box::new(DcbDecoder::new(dict)))),
+            fut: None,
+            eof: false,
+        }
+    }
+
     /// Construct decoder based on headers.
     #[inline]
     pub fn from_headers(stream: S, headers: &HeaderMap) -> Decoder<S> {
@@ -134,10 +154,32 @@ where
     }
 }
 
+impl<S> MessageBody for Decoder<S>
+where
+    S: Stream<Item = Result<Bytes, PayloadError>> + Unpin,
+{
+    fn size(&self) -> BodySize {
+        BodySize::Stream
+    }
+
+    fn poll_next_chunk(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Bytes, Box<dyn Error>>>> {
+        match ready!(Pin::new(self).poll_next(cx)) {
+            Some(Ok(chunk)) => Poll::Ready(Some(Ok(chunk))),
+            Some(Err(err)) => Poll::Ready(Some(Err(Box::new(err)))),
+            None => Poll::Ready(None),
+        }
+    }
+}
+
 enum ContentDecoder {
     Deflate(Box<ZlibDecoder<Writer>>),
     Gzip(Box<GzDecoder<Writer>>),
     Br(Box<BrotliDecoder<Writer>>),
+    #[cfg(feature = "dictionary")]
+    Dcb(Box<DcbDecoder>),
 }
 
 impl ContentDecoder {
@@ -176,6 +218,8 @@ impl ContentDecoder {
                 }
                 Err(e) => Err(e),
             },
+            #[cfg(feature = "dictionary")]
+            ContentDecoder::Dcb(ref mut decoder) => decoder.feed_eof(),
         }
     }
 
@@ -217,6 +261,8 @@ impl ContentDecoder {
                 }
                 Err(e) => Err(e),
             },
+            #[cfg(feature = "dictionary")]
+            ContentDecoder::Dcb(ref mut decoder) => decoder.feed_data(&data),
         }
     }
 }
@@ -0,0 +1,412 @@
+//! Shared dictionary compression.
+//!
+//! Implements `dcb` content encoding from
+//! [Compression Dictionary Transport](https://www.rfc-editor.org/rfc/rfc9842),
+//! brotli stream compressed with pre-shared dictionary. Client advertises
+//! dictionary it has with `Available-Dictionary` header, server compresses
+//! response with the same dictionary if it knows it.
+//!
+//! Dictionaries are distributed out of band, so both sides must be
+//! configured with the same dictionary content.
+use std::ptr;
+use std::{fmt, io, slice};
+
+use brotli_sys as sys;
+use bytes::{Bytes, BytesMut};
+use sha2::{Digest, Sha256};
+
+use crate::http::header::{HeaderMap, HeaderName, HeaderValue, ACCEPT_ENCODING};
+
+/// `dcb` stream magic number
+const DCB_MAGIC: [u8; 4] = [0xff, 0x44, 0x43, 0x42];
+const DCB_HEADER: usize = 36;
+const QUALITY: u32 = 5;
+
+/// `Available-Dictionary` header name
+pub fn available_dictionary() -> HeaderName {
+    HeaderName::from_static("available-dictionary")
+}
+
+/// Compression dictionary
+#[derive(Clone)]
+pub struct Dictionary {
+    data: Bytes,
+    hash: [u8; 32],
+}
+
+impl Dictionary {
+    /// Create dictionary from its content
+    pub fn new<T: Into<Bytes>>(data: T) -> Self {
+        let data = data.into();
+        let mut hash = [0; 32];
+        hash.copy_from_slice(&Sha256::digest(&data));
+        Dictionary { data, hash }
+    }
+
+    /// Dictionary content
+    pub fn data(&self) -> &Bytes {
+        &self.data
+    }
+
+    /// SHA-256 hash of dictionary content
+    pub fn hash(&self) -> &[u8; 32] {
+        &self.hash
+    }
+
+    /// Value for `Available-Dictionary` header
+    pub fn header_value(&self) -> HeaderValue {
+        HeaderValue::from_str(&format!(":{}:", base64::encode(self.hash))).unwrap()
+    }
+
+    /// Check if request headers advertise this dictionary and accept
+    /// `dcb` encoding
+    pub fn is_available(&self, headers: &HeaderMap) -> bool {
+        let accepted = headers
+            .get(&ACCEPT_ENCODING)
+            .and_then(|v| v.to_str().ok())
+            .map(|v| {
+                v.split(',').any(|enc| {
+                    let mut parts = enc.split(';');
+                    parts
+                        .next()
+                        .map(|enc| enc.trim().eq_ignore_ascii_case("dcb"))
+                        .unwrap_or(false)
+                        && parts.all(|p| {
+                            let p = p.trim();
+                            p != "q=0" && p != "q=0.0"
+                        })
+                })
+            })
+            .unwrap_or(false);
+
+        accepted
+            && headers
+                .get(available_dictionary())
+                .and_then(|v| v.to_str().ok())
+                .map(|v| {
+                    let v = v.trim();
+                    v.len() > 2
+                        && v.starts_with(':')
+                        && v.ends_with(':')
+                        && base64::decode(&v[1..v.len() - 1])
+                            .map(|hash| hash[..] == self.hash[..])
+                            .unwrap_or(false)
+                })
+                .unwrap_or(false)
+    }
+
+    fn lgwin(&self) -> u32 {
+        // window must cover dictionary and some data
+        let mut lgwin = 22;
+        while lgwin < 24 && (1 << lgwin) < self.data.len() * 2 {
+            lgwin += 1;
+        }
+        lgwin
+    }
+}
+
+impl fmt::Debug for Dictionary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Dictionary")
+            .field("size", &self.data.len())
+            .field("hash", &base64::encode(self.hash))
+            .finish()
+    }
+}
+
+/// `dcb` stream encoder
+pub(super) struct DcbEncoder {
+    state: *mut sys::BrotliEncoderState,
+    buf: BytesMut,
+}
+
+// encoder state is exclusively owned
+unsafe impl Send for DcbEncoder {}
+
+impl DcbEncoder {
+    pub(super) fn new(dict: &Dictionary) -> Self {
+        let state = unsafe {
+            let state = sys::BrotliEncoderCreateInstance(None, None, ptr::null_mut());
+            assert!(!state.is_null());
+            sys::BrotliEncoderSetParameter(state, sys::BROTLI_PARAM_QUALITY, QUALITY);
+            sys::BrotliEncoderSetParameter(state, sys::BROTLI_PARAM_LGWIN, dict.lgwin());
+            sys::BrotliEncoderSetCustomDictionary(
+                state,
+                dict.data.len(),
+                dict.data.as_ptr(),
+            );
+            state
+        };
+
+        let mut buf = BytesMut::with_capacity(8192);
+        buf.extend_from_slice(&DCB_MAGIC);
+        buf.extend_from_slice(&dict.hash);
+        DcbEncoder { state, buf }
+    }
+
+    pub(super) fn write(&mut self, data: &[u8]) -> io::Result<()> {
+        self.compress(sys::BROTLI_OPERATION_PROCESS, data)
+    }
+
+    pub(super) fn take(&mut self) -> Bytes {
+        self.buf.split().freeze()
+    }
+
+    pub(super) fn finish(mut self) -> io::Result<Bytes> {
+        self.compress(sys::BROTLI_OPERATION_FINISH, &[])?;
+        Ok(self.take())
+    }
+
+    fn compress(
+        &mut self,
+        op: sys::BrotliEncoderOperation,
+        data: &[u8],
+    ) -> io::Result<()> {
+        let mut available_in = data.len();
+        let mut next_in = data.as_ptr();
+
+        loop {
+            let mut available_out = 0;
+            let res = unsafe {
+                sys::BrotliEncoderCompressStream(
+                    self.state,
+                    op,
+                    &mut available_in,
+                    &mut next_in,
+                    &mut available_out,
+                    ptr::null_mut(),
+                    ptr::null_mut(),
+                )
+            };
+            if res == 0 {
+                return Err(io::Error::new(io::ErrorKind::Other, "dcb encoding error"));
+            }
+            self.take_output();
+
+            let done = available_in == 0
+                && unsafe { sys::BrotliEncoderHasMoreOutput(self.state) } == 0
+                && (op != sys::BROTLI_OPERATION_FINISH
+                    || unsafe { sys::BrotliEncoderIsFinished(self.state) } != 0);
+            if done {
+                return Ok(());
+            }
+        }
+    }
+
+    fn take_output(&mut self) {
+        loop {
+            let mut size = 0;
+            unsafe {
+                let ptr = sys::BrotliEncoderTakeOutput(self.state, &mut size);
+                if size == 0 {
+                    return;
+                }
+                self.buf.extend_from_slice(slice::from_raw_parts(ptr, size));
+            }
+        }
+    }
+}
+
+impl Drop for DcbEncoder {
+    fn drop(&mut self) {
+        unsafe { sys::BrotliEncoderDestroyInstance(self.state) }
+    }
+}
+
+/// `dcb` stream decoder
+pub(super) struct DcbDecoder {
+    state: *mut sys::BrotliDecoderState,
+    // decoder references dictionary content, it must outlive decoder state
+    dict: Dictionary,
+    header: BytesMut,
+    buf: BytesMut,
+    finished: bool,
+}
+
+// decoder state is exclusively owned
+unsafe impl Send for DcbDecoder {}
+
+impl DcbDecoder {
+    pub(super) fn new(dict: Dictionary) -> Self {
+        let state = unsafe {
+            let state = sys::BrotliDecoderCreateInstance(None, None, ptr::null_mut());
+            assert!(!state.is_null());
+            sys::BrotliDecoderSetCustomDictionary(
+                state,
+                dict.data.len(),
+                dict.data.as_ptr(),
+            );
+            state
+        };
+
+        DcbDecoder {
+            state,
+            dict,
+            header: BytesMut::with_capacity(DCB_HEADER),
+            buf: BytesMut::new(),
+            finished: false,
+        }
+    }
+
+    pub(super) fn feed_data(&mut self, mut data: &[u8]) -> io::Result<Option<Bytes>> {
+        if self.header.len() < DCB_HEADER {
+            let n = std::cmp::min(DCB_HEADER - self.header.len(), data.len());
+            self.header.extend_from_slice(&data[..n]);
+            data = &data[n..];
+
+            if self.header.len() == DCB_HEADER
+                && (self.header[..4] != DCB_MAGIC || self.header[4..] != self.dict.hash)
+            {
+                return Err(invalid_data("dcb stream dictionary mismatch"));
+            }
+        }
+
+        if !data.is_empty() {
+            self.decompress(data)?;
+        }
+
+        if self.buf.is_empty() {
+            Ok(None)
+        } else {
+            Ok(Some(self.buf.split().freeze()))
+        }
+    }
+
+    pub(super) fn feed_eof(&mut self) -> io::Result<Option<Bytes>> {
+        if !self.finished {
+            Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "dcb stream is incomplete",
+            ))
+        } else if self.buf.is_empty() {
+            Ok(None)
+        } else {
+            Ok(Some(self.buf.split().freeze()))
+        }
+    }
+
+    fn decompress(&mut self, data: &[u8]) -> io::Result<()> {
+        if self.finished {
+            return Err(invalid_data("unexpected data after dcb stream"));
+        }
+
+        let mut available_in = data.len();
+        let mut next_in = data.as_ptr();
+
+        loop {
+            let mut available_out = 0;
+            let res = unsafe {
+                sys::BrotliDecoderDecompressStream(
+                    self.state,
+                    &mut available_in,
+                    &mut next_in,
+                    &mut available_out,
+                    ptr::null_mut(),
+                    ptr::null_mut(),
+                )
+            };
+
+            loop {
+                let mut size = 0;
+                unsafe {
+                    let ptr = sys::BrotliDecoderTakeOutput(self.state, &mut size);
+                    if size == 0 {
+                        break;
+                    }
+                    self.buf.extend_from_slice(slice::from_raw_parts(ptr, size));
+                }
+            }
+
+            match res {
+                sys::BROTLI_DECODER_RESULT_NEEDS_MORE_OUTPUT => continue,
+                sys::BROTLI_DECODER_RESULT_NEEDS_MORE_INPUT => return Ok(()),
+                sys::BROTLI_DECODER_RESULT_SUCCESS => {
+                    self.finished = true;
+                    return if available_in != 0 {
+                        Err(invalid_data("unexpected data after dcb stream"))
+                    } else {
+                        Ok(())
+                    };
+                }
+                _ => return Err(invalid_data("dcb decoding error")),
+            }
+        }
+    }
+}
+
+impl Drop for DcbDecoder {
+    fn drop(&mut self) {
+        unsafe { sys::BrotliDecoderDestroyInstance(self.state) }
+    }
+}
+
+fn invalid_data(msg: &'static str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DICT: &[u8] =
+        b"{\"id\":0,\"name\":\"\",\"email\":\"\",\"roles\":[\"admin\",\"user\"]}";
+    const DATA: &[u8] =
+        b"{\"id\":1,\"name\":\"alice\",\"email\":\"alice@example.com\",\"roles\":[\"admin\",\"user\"]}";
+
+    fn encode(dict: &Dictionary, data: &[u8]) -> Bytes {
+        let mut enc = DcbEncoder::new(dict);
+        enc.write(data).unwrap();
+        enc.finish().unwrap()
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let dict = Dictionary::new(DICT);
+        let encoded = encode(&dict, DATA);
+        assert_eq!(&encoded[..4], &DCB_MAGIC);
+        assert_eq!(&encoded[4..36], dict.hash());
+
+        // dictionary helps
+        let plain = Dictionary::new(&b"-"[..]);
+        assert!(encoded.len() < encode(&plain, DATA).len());
+
+        // decode byte by byte
+        let mut dec = DcbDecoder::new(dict.clone());
+        let mut out = BytesMut::new();
+        for b in encoded.iter() {
+            if let Some(chunk) = dec.feed_data(&[*b]).unwrap() {
+                out.extend_from_slice(&chunk);
+            }
+        }
+        assert!(dec.feed_eof().unwrap().is_none());
+        assert_eq!(&out[..], DATA);
+
+        // wrong dictionary
+        let mut dec = DcbDecoder::new(plain);
+        assert!(dec.feed_data(&encoded).is_err());
+
+        // incomplete stream
+        let mut dec = DcbDecoder::new(dict);
+        let _ = dec.feed_data(&encoded[..encoded.len() - 2]).unwrap();
+        assert!(dec.feed_eof().is_err());
+    }
+
+    #[test]
+    fn test_available() {
+        let dict = Dictionary::new(DICT);
+        let mut headers = HeaderMap::new();
+        headers.insert(available_dictionary(), dict.header_value());
+        assert!(!dict.is_available(&headers));
+
+        headers.insert(ACCEPT_ENCODING, HeaderValue::from_static("gzip, dcb"));
+        assert!(dict.is_available(&headers));
+        assert!(!Dictionary::new(&b"other"[..]).is_available(&headers));
+
+        headers.insert(ACCEPT_ENCODING, HeaderValue::from_static("dcb;q=0, br"));
+        assert!(!dict.is_available(&headers));
+
+        headers.insert(ACCEPT_ENCODING, HeaderValue::from_static("dcb"));
+        headers.insert(available_dictionary(), HeaderValue::from_static(":bad:"));
+        assert!(!dict.is_available(&headers));
+    }
+}
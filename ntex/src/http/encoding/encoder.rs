@@ -15,10 +15,32 @@ use crate::http::body::{Body, BodySize, MessageBody, ResponseBody};
 use crate::http::header::{ContentEncoding, HeaderValue, CONTENT_ENCODING};
 use crate::http::{ResponseHead, StatusCode};
 
+#[cfg(feature = "dictionary")]
+use super::dictionary::{DcbEncoder, Dictionary};
 use super::Writer;
+#[cfg(feature = "dictionary")]
+use crate::http::header::VARY;
 
 const INPLACE: usize = 1024;
 
+/// Streaming body encoder.
+///
+/// Encoder compresses chunks of the wrapped body, large chunks are
+/// compressed in thread pool. `Encoder::new()` wraps any `MessageBody`,
+/// so bodies could be re-compressed, for example decoded upstream
+/// payload could be encoded with different encoding.
+///
+/// ```rust
+/// use ntex::http::body::Body;
+/// use ntex::http::encoding::{Decoder, Encoder};
+/// use ntex::http::header::ContentEncoding;
+/// use ntex::http::Payload;
+///
+/// fn transcode(payload: Payload) -> Body {
+///     let decoded = Decoder::new(payload, ContentEncoding::Gzip);
+///     Body::from_message(Encoder::new(decoded, ContentEncoding::Br))
+/// }
+/// ```
 pub struct Encoder<B> {
     eof: bool,
     body: EncoderBody<B>,
@@ -27,17 +49,71 @@ pub struct Encoder<B> {
 }
 
 impl<B: MessageBody> Encoder<B> {
+    /// Construct encoder for body.
+    ///
+    /// Body is passed through as is for `Identity` and `Auto` encodings.
+    pub fn new(body: B, encoding: ContentEncoding) -> Encoder<B> {
+        Encoder {
+            body: EncoderBody::Stream(body),
+            eof: false,
+            fut: None,
+            encoder: ContentEncoder::encoder(encoding),
+        }
+    }
+
+    /// Encode response body and update response head.
+    ///
+    /// Response is not encoded if it already has *Content-Encoding*
+    /// header or has no body.
     pub fn response(
         encoding: ContentEncoding,
         head: &mut ResponseHead,
         body: ResponseBody<B>,
     ) -> ResponseBody<Encoder<B>> {
-        let can_encode = !(head.headers().contains_key(&CONTENT_ENCODING)
-            || head.status == StatusCode::SWITCHING_PROTOCOLS
-            || head.status == StatusCode::NO_CONTENT
-            || encoding == ContentEncoding::Identity
+        let can_encode = !(encoding == ContentEncoding::Identity
             || encoding == ContentEncoding::Auto);
 
+        Encoder::build(head, body, can_encode, encoding.as_str(), || {
+            ContentEncoder::encoder(encoding)
+        })
+    }
+
+    #[cfg(feature = "dictionary")]
+    /// Encode response with `dcb` shared dictionary encoding.
+    pub fn dictionary_response(
+        dict: &Dictionary,
+        head: &mut ResponseHead,
+        body: ResponseBody<B>,
+    ) -> ResponseBody<Encoder<B>> {
+        let body = Encoder::build(head, body, true, "dcb", || {
+            Some(ContentEncoder::Dcb(Box::new(DcbEncoder::new(dict))))
+        });
+        if head.headers().get(&CONTENT_ENCODING)
+            == Some(&HeaderValue::from_static("dcb"))
+        {
+            head.headers_mut().append(
+                VARY,
+                HeaderValue::from_static("accept-encoding, available-dictionary"),
+            );
+        }
+        body
+    }
+
+    fn build<F>(
+        head: &mut ResponseHead,
+        body: ResponseBody<B>,
+        can_encode: bool,
+        name: &'static str,
+        f: F,
+    ) -> ResponseBody<Encoder<B>>
+    where
+        F: FnOnce() -> Option<ContentEncoder>,
+    {
+        let can_encode = can_encode
+            && !(head.headers().contains_key(&CONTENT_ENCODING)
+                || head.status == StatusCode::SWITCHING_PROTOCOLS
+                || head.status == StatusCode::NO_CONTENT);
+
         let body = match body {
             ResponseBody::Other(b) => match b {
                 Body::None => return ResponseBody::Other(Body::None),
@@ -56,8 +132,8 @@ impl<B: MessageBody> Encoder<B> {
 
         if can_encode {
             // Modify response body only if encoder is not None
-            if let Some(enc) = ContentEncoder::encoder(encoding) {
-                update_head(encoding, head);
+            if let Some(enc) = f() {
+                update_head(name, head);
                 head.no_chunking(false);
                 return ResponseBody::Body(Encoder {
                     body,
@@ -175,17 +251,17 @@ impl<B: MessageBody> MessageBody for Encoder<B> {
     }
 }
 
-fn update_head(encoding: ContentEncoding, head: &mut ResponseHead) {
-    head.headers_mut().insert(
-        CONTENT_ENCODING,
-        HeaderValue::from_static(encoding.as_str()),
-    );
+fn update_head(name: &'static str, head: &mut ResponseHead) {
+    head.headers_mut()
+        .insert(CONTENT_ENCODING, HeaderValue::from_static(name));
 }
 
 enum ContentEncoder {
     Deflate(ZlibEncoder<Writer>),
     Gzip(GzEncoder<Writer>),
     Br(BrotliEncoder<Writer>),
+    #[cfg(feature = "dictionary")]
+    Dcb(Box<DcbEncoder>),
 }
 
 impl ContentEncoder {
@@ -212,6 +288,8 @@ impl ContentEncoder {
             ContentEncoder::Br(ref mut encoder) => encoder.get_mut().take(),
             ContentEncoder::Deflate(ref mut encoder) => encoder.get_mut().take(),
             ContentEncoder::Gzip(ref mut encoder) => encoder.get_mut().take(),
+            #[cfg(feature = "dictionary")]
+            ContentEncoder::Dcb(ref mut encoder) => encoder.take(),
         }
     }
 
@@ -229,6 +307,8 @@ impl ContentEncoder {
                 Ok(writer) => Ok(writer.buf.freeze()),
                 Err(err) => Err(err),
             },
+            #[cfg(feature = "dictionary")]
+            ContentEncoder::Dcb(encoder) => encoder.finish(),
         }
     }
 
@@ -255,6 +335,78 @@ impl ContentEncoder {
                     Err(err)
                 }
             },
+            #[cfg(feature = "dictionary")]
+            ContentEncoder::Dcb(ref mut encoder) => match encoder.write(data) {
+                Ok(_) => Ok(()),
+                Err(err) => {
+                    trace!("Error decoding dcb encoding: {}", err);
+                    Err(err)
+                }
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::BytesMut;
+    use futures::future::poll_fn;
+    use futures::stream::{self, StreamExt};
+
+    use super::*;
+    use crate::http::encoding::Decoder;
+    use crate::http::error::PayloadError;
+
+    async fn read<B: MessageBody>(mut body: B) -> Vec<Bytes> {
+        let mut chunks = Vec::new();
+        while let Some(chunk) = poll_fn(|cx| body.poll_next_chunk(cx)).await {
+            chunks.push(chunk.unwrap());
         }
+        chunks
+    }
+
+    async fn decode(chunks: Vec<Bytes>, encoding: ContentEncoding) -> Bytes {
+        let stream = stream::iter(chunks.into_iter().map(Ok::<_, PayloadError>));
+        let mut decoder = Decoder::new(stream, encoding);
+        let mut buf = BytesMut::new();
+        while let Some(chunk) = decoder.next().await {
+            buf.extend_from_slice(&chunk.unwrap());
+        }
+        buf.freeze()
+    }
+
+    #[ntex_rt::test]
+    async fn test_encoder() {
+        let data: Bytes = (0..10_000u32)
+            .map(|i| format!("line {}\n", i))
+            .collect::<String>()
+            .into();
+
+        for enc in &[
+            ContentEncoding::Gzip,
+            ContentEncoding::Deflate,
+            ContentEncoding::Br,
+        ] {
+            let chunks = read(Encoder::new(data.clone(), *enc)).await;
+            let size: usize = chunks.iter().map(|c| c.len()).sum();
+            assert!(size < data.len());
+            assert_eq!(decode(chunks, *enc).await, data);
+        }
+
+        // identity encoding passes body through
+        let body = Encoder::new(data.clone(), ContentEncoding::Identity);
+        assert_eq!(body.size(), BodySize::Sized(data.len()));
+        assert_eq!(read(body).await, vec![data.clone()]);
+    }
+
+    #[ntex_rt::test]
+    async fn test_transcode() {
+        let data = Bytes::from_static(b"transcoded payload");
+        let gzip = read(Encoder::new(data.clone(), ContentEncoding::Gzip)).await;
+
+        let stream = stream::iter(gzip.into_iter().map(Ok::<_, PayloadError>));
+        let decoded = Decoder::new(stream, ContentEncoding::Gzip);
+        let br = read(Encoder::new(decoded, ContentEncoding::Br)).await;
+        assert_eq!(decode(br, ContentEncoding::Br).await, data);
     }
 }
@@ -4,6 +4,8 @@ use std::io;
 use bytes::{Bytes, BytesMut};
 
 mod decoder;
+#[cfg(feature = "dictionary")]
+pub mod dictionary;
 mod encoder;
 
 pub use self::decoder::Decoder;
@@ -84,6 +84,18 @@ pub enum ParseError {
     /// A message head is too large to be reasonable.
     #[display(fmt = "Message head is too large")]
     TooLarge,
+    /// A request uri is larger than configured limit.
+    #[display(fmt = "Request uri is too long")]
+    UriTooLong,
+    /// A message contains more headers than configured limit.
+    #[display(fmt = "Too many headers")]
+    TooManyHeaders,
+    /// A message headers are larger than configured limit.
+    #[display(fmt = "Message headers are too large")]
+    HeadersTooLarge,
+    /// A chunked payload trailer is larger than configured limit.
+    #[display(fmt = "Payload trailer is too large")]
+    TrailersTooLarge,
     /// A message reached EOF, but is not complete.
     #[display(fmt = "Message is incomplete")]
     Incomplete,
@@ -163,6 +175,9 @@ pub enum PayloadError {
     /// A payload length is unknown.
     #[display(fmt = "A payload length is unknown.")]
     UnknownLength,
+    /// A payload chunk is not received in time.
+    #[display(fmt = "A payload chunk is not received in time.")]
+    Timeout,
     /// Http2 payload error
     #[display(fmt = "{}", _0)]
     Http2Payload(h2::Error),
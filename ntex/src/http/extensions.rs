@@ -31,6 +31,16 @@ impl Extensions {
         self.map.contains_key(&TypeId::of::<T>())
     }
 
+    /// Check if container contains value of a type
+    pub(crate) fn contains_type(&self, id: TypeId) -> bool {
+        self.map.contains_key(&id)
+    }
+
+    /// Iterate over type ids of stored values
+    pub(crate) fn type_ids(&self) -> impl Iterator<Item = TypeId> + '_ {
+        self.map.keys().cloned()
+    }
+
     /// Get a reference to a type previously inserted on this `Extensions`.
     pub fn get<T: 'static>(&self) -> Option<&T> {
         self.map
@@ -6,7 +6,7 @@ use http::{Method, Version};
 
 use crate::codec::{Decoder, Encoder};
 use crate::http::body::BodySize;
-use crate::http::config::DateService;
+use crate::http::config::{DateService, Limits};
 use crate::http::error::ParseError;
 use crate::http::message::ConnectionType;
 use crate::http::request::Request;
@@ -71,6 +71,11 @@ impl Codec {
         }
     }
 
+    /// Set request parsing limits
+    pub(super) fn set_limits(&mut self, limits: Limits) {
+        self.decoder = decoder::MessageDecoder::new(limits);
+    }
+
     #[inline]
     /// Check if request is upgrade
     pub fn upgrade(&self) -> bool {
@@ -9,6 +9,7 @@ use http::{header, Method, StatusCode, Uri, Version};
 use log::{debug, error, trace};
 
 use crate::codec::Decoder;
+use crate::http::config::Limits;
 use crate::http::error::ParseError;
 use crate::http::header::HeaderMap;
 use crate::http::message::{ConnectionType, ResponseHead};
@@ -19,7 +20,10 @@ use super::MAX_BUFFER_SIZE;
 const MAX_HEADERS: usize = 96;
 
 /// Incoming messagd decoder
-pub(super) struct MessageDecoder<T: MessageType>(PhantomData<T>);
+pub(super) struct MessageDecoder<T: MessageType> {
+    limits: Limits,
+    _t: PhantomData<T>,
+}
 
 #[derive(Debug)]
 /// Incoming request type
@@ -31,7 +35,16 @@ pub(super) enum PayloadType {
 
 impl<T: MessageType> Default for MessageDecoder<T> {
     fn default() -> Self {
-        MessageDecoder(PhantomData)
+        MessageDecoder::new(Limits::default())
+    }
+}
+
+impl<T: MessageType> MessageDecoder<T> {
+    pub(super) fn new(limits: Limits) -> Self {
+        MessageDecoder {
+            limits,
+            _t: PhantomData,
+        }
     }
 }
 
@@ -40,7 +53,7 @@ impl<T: MessageType> Decoder for MessageDecoder<T> {
     type Error = ParseError;
 
     fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
-        T::decode(src)
+        T::decode(src, &self.limits)
     }
 }
 
@@ -57,12 +70,16 @@ pub(super) trait MessageType: Sized {
 
     fn headers_mut(&mut self) -> &mut HeaderMap;
 
-    fn decode(src: &mut BytesMut) -> Result<Option<(Self, PayloadType)>, ParseError>;
+    fn decode(
+        src: &mut BytesMut,
+        limits: &Limits,
+    ) -> Result<Option<(Self, PayloadType)>, ParseError>;
 
     fn set_headers(
         &mut self,
         slice: &Bytes,
         raw_headers: &[HeaderIndex],
+        limits: &Limits,
     ) -> Result<PayloadLength, ParseError> {
         let mut ka = None;
         let mut has_upgrade = false;
@@ -154,7 +171,7 @@ pub(super) trait MessageType: Sized {
         if chunked {
             // Chunked encoding
             Ok(PayloadLength::Payload(PayloadType::Payload(
-                PayloadDecoder::chunked(),
+                PayloadDecoder::chunked(limits.max_trailer_size as u64),
             )))
         } else if let Some(len) = content_length {
             // Content-Length
@@ -185,7 +202,10 @@ impl MessageType for Request {
     }
 
     #[allow(clippy::uninit_assumed_init)]
-    fn decode(src: &mut BytesMut) -> Result<Option<(Self, PayloadType)>, ParseError> {
+    fn decode(
+        src: &mut BytesMut,
+        limits: &Limits,
+    ) -> Result<Option<(Self, PayloadType)>, ParseError> {
         // Unsafe: we read this data only after httparse parses headers into.
         // performance bump for pipeline benchmarks.
         let mut headers: [HeaderIndex; MAX_HEADERS] =
@@ -196,11 +216,33 @@ impl MessageType for Request {
                 unsafe { MaybeUninit::uninit().assume_init() };
 
             let mut req = httparse::Request::new(&mut parsed);
-            match req.parse(src)? {
+            let status = match req.parse(src) {
+                Ok(status) => status,
+                Err(httparse::Error::TooManyHeaders) => {
+                    trace!("Max number of headers reached, closing");
+                    return Err(ParseError::TooManyHeaders);
+                }
+                Err(e) => return Err(e.into()),
+            };
+            match status {
                 httparse::Status::Complete(len) => {
+                    let path = req.path.unwrap();
+                    if path.len() > limits.max_uri_size {
+                        trace!("Max uri size reached, closing");
+                        return Err(ParseError::UriTooLong);
+                    }
+                    if req.headers.len() > limits.max_headers {
+                        trace!("Max number of headers reached, closing");
+                        return Err(ParseError::TooManyHeaders);
+                    }
+                    if len - request_line_len(src) > limits.max_header_size {
+                        trace!("Max headers size reached, closing");
+                        return Err(ParseError::HeadersTooLarge);
+                    }
+
                     let method = Method::from_bytes(req.method.unwrap().as_bytes())
                         .map_err(|_| ParseError::Method)?;
-                    let uri = Uri::try_from(req.path.unwrap())?;
+                    let uri = Uri::try_from(path)?;
                     let version = if req.version.unwrap() == 1 {
                         Version::HTTP_11
                     } else {
@@ -211,6 +253,17 @@ impl MessageType for Request {
                     (len, method, uri, version, req.headers.len())
                 }
                 httparse::Status::Partial => {
+                    if req.path.is_none() {
+                        // request line is not complete, check uri size so far
+                        if partial_uri_len(src) > limits.max_uri_size {
+                            trace!("Max uri size reached, closing");
+                            return Err(ParseError::UriTooLong);
+                        }
+                    } else if src.len() - request_line_len(src) > limits.max_header_size
+                    {
+                        trace!("Max headers size reached, closing");
+                        return Err(ParseError::HeadersTooLarge);
+                    }
                     if src.len() >= MAX_BUFFER_SIZE {
                         trace!("MAX_BUFFER_SIZE unprocessed data reached, closing");
                         return Err(ParseError::TooLarge);
@@ -223,7 +276,8 @@ impl MessageType for Request {
         let mut msg = Request::new();
 
         // convert headers
-        let length = msg.set_headers(&src.split_to(len).freeze(), &headers[..h_len])?;
+        let length =
+            msg.set_headers(&src.split_to(len).freeze(), &headers[..h_len], limits)?;
 
         // payload decoder
         let decoder = match length {
@@ -264,7 +318,10 @@ impl MessageType for ResponseHead {
     }
 
     #[allow(clippy::uninit_assumed_init)]
-    fn decode(src: &mut BytesMut) -> Result<Option<(Self, PayloadType)>, ParseError> {
+    fn decode(
+        src: &mut BytesMut,
+        limits: &Limits,
+    ) -> Result<Option<(Self, PayloadType)>, ParseError> {
         // Unsafe: we read this data only after httparse parses headers into.
         // performance bump for pipeline benchmarks.
         let mut headers: [HeaderIndex; MAX_HEADERS] =
@@ -303,7 +360,8 @@ impl MessageType for ResponseHead {
         msg.version = ver;
 
         // convert headers
-        let length = msg.set_headers(&src.split_to(len).freeze(), &headers[..h_len])?;
+        let length =
+            msg.set_headers(&src.split_to(len).freeze(), &headers[..h_len], limits)?;
 
         // message payload
         let decoder = if let PayloadLength::Payload(pl) = length {
@@ -325,6 +383,26 @@ impl MessageType for ResponseHead {
     }
 }
 
+/// Length of the request line, including CRLF
+fn request_line_len(src: &[u8]) -> usize {
+    src.iter()
+        .position(|b| *b == b'\n')
+        .map(|pos| pos + 1)
+        .unwrap_or_else(|| src.len())
+}
+
+/// Length of the uri part of incomplete request line
+fn partial_uri_len(src: &[u8]) -> usize {
+    if let Some(start) = src.iter().position(|b| *b == b' ') {
+        let uri = &src[start + 1..];
+        uri.iter()
+            .position(|b| *b == b' ' || *b == b'\r' || *b == b'\n')
+            .unwrap_or_else(|| uri.len())
+    } else {
+        0
+    }
+}
+
 #[derive(Clone, Copy)]
 pub(super) struct HeaderIndex {
     pub(super) name: (usize, usize),
@@ -372,9 +450,9 @@ impl PayloadDecoder {
         }
     }
 
-    pub(super) fn chunked() -> PayloadDecoder {
+    pub(super) fn chunked(max_trailer_size: u64) -> PayloadDecoder {
         PayloadDecoder {
-            kind: Kind::Chunked(ChunkedState::Size, 0),
+            kind: Kind::Chunked(ChunkedState::Size, 0, max_trailer_size),
         }
     }
 
@@ -389,7 +467,10 @@ enum Kind {
     /// integer.
     Length(u64),
     /// A Reader used when Transfer-Encoding is `chunked`.
-    Chunked(ChunkedState, u64),
+    ///
+    /// Holds current state, remaining size of current chunk
+    /// (or size of trailer section) and max trailer section size.
+    Chunked(ChunkedState, u64, u64),
     /// A Reader used for responses that don't indicate a length or chunked.
     ///
     /// Note: This should only used for `Response`s. It is illegal for a
@@ -418,6 +499,7 @@ enum ChunkedState {
     BodyLf,
     EndCr,
     EndLf,
+    Trailer,
     End,
 }
 
@@ -447,11 +529,11 @@ impl Decoder for PayloadDecoder {
                     Ok(Some(PayloadItem::Chunk(buf)))
                 }
             }
-            Kind::Chunked(ref mut state, ref mut size) => {
+            Kind::Chunked(ref mut state, ref mut size, max_trailer) => {
                 loop {
                     let mut buf = None;
                     // advances the chunked state
-                    *state = match state.step(src, size, &mut buf) {
+                    *state = match state.step(src, size, max_trailer, &mut buf) {
                         Poll::Pending => return Ok(None),
                         Poll::Ready(Ok(state)) => state,
                         Poll::Ready(Err(e)) => return Err(e),
@@ -496,6 +578,7 @@ impl ChunkedState {
         &self,
         body: &mut BytesMut,
         size: &mut u64,
+        max_trailer: u64,
         buf: &mut Option<Bytes>,
     ) -> Poll<Result<ChunkedState, ParseError>> {
         use self::ChunkedState::*;
@@ -507,8 +590,9 @@ impl ChunkedState {
             Body => ChunkedState::read_body(body, size, buf),
             BodyCr => ChunkedState::read_body_cr(body),
             BodyLf => ChunkedState::read_body_lf(body),
-            EndCr => ChunkedState::read_end_cr(body),
+            EndCr => ChunkedState::read_end_cr(body, size, max_trailer),
             EndLf => ChunkedState::read_end_lf(body),
+            Trailer => ChunkedState::read_trailer(body, size, max_trailer),
             End => Poll::Ready(Ok(ChunkedState::End)),
         }
     }
@@ -612,10 +696,39 @@ impl ChunkedState {
             _ => Poll::Ready(Err(ParseError::InvalidInput("Invalid chunk body LF"))),
         }
     }
-    fn read_end_cr(rdr: &mut BytesMut) -> Poll<Result<ChunkedState, ParseError>> {
+    fn read_end_cr(
+        rdr: &mut BytesMut,
+        size: &mut u64,
+        max_trailer: u64,
+    ) -> Poll<Result<ChunkedState, ParseError>> {
         match byte!(rdr) {
             b'\r' => Poll::Ready(Ok(ChunkedState::EndLf)),
-            _ => Poll::Ready(Err(ParseError::InvalidInput("Invalid chunk end CR"))),
+            b'\n' => Poll::Ready(Err(ParseError::InvalidInput("Invalid chunk end CR"))),
+            // trailer field, size is zero after last chunk so we use it
+            // for tracking size of trailer section
+            _ => ChunkedState::trailer_byte(size, max_trailer),
+        }
+    }
+    fn read_trailer(
+        rdr: &mut BytesMut,
+        size: &mut u64,
+        max_trailer: u64,
+    ) -> Poll<Result<ChunkedState, ParseError>> {
+        match byte!(rdr) {
+            b'\n' => Poll::Ready(Ok(ChunkedState::EndCr)),
+            _ => ChunkedState::trailer_byte(size, max_trailer),
+        }
+    }
+    fn trailer_byte(
+        size: &mut u64,
+        max_trailer: u64,
+    ) -> Poll<Result<ChunkedState, ParseError>> {
+        *size += 1;
+        if *size > max_trailer {
+            trace!("Max trailer size reached, closing");
+            Poll::Ready(Err(ParseError::TrailersTooLarge))
+        } else {
+            Poll::Ready(Ok(ChunkedState::Trailer))
         }
     }
     fn read_end_lf(rdr: &mut BytesMut) -> Poll<Result<ChunkedState, ParseError>> {
@@ -1209,4 +1322,109 @@ mod tests {
         let chunk = pl.decode(&mut buf).unwrap().unwrap();
         assert_eq!(chunk, PayloadItem::Chunk(Bytes::from_static(b"test data")));
     }
+
+    #[test]
+    fn test_parse_chunked_payload_trailer() {
+        let mut buf = BytesMut::from(
+            &"GET /test HTTP/1.1\r\n\
+              transfer-encoding: chunked\r\n\r\n"[..],
+        );
+
+        let mut reader = MessageDecoder::<Request>::default();
+        let (_, pl) = reader.decode(&mut buf).unwrap().unwrap();
+        let mut pl = pl.unwrap();
+
+        buf.extend(b"4\r\ndata\r\n0\r\nx-trailer: test\r\nx-other: test\r\n\r\n");
+        let chunk = pl.decode(&mut buf).unwrap().unwrap().chunk();
+        assert_eq!(chunk, Bytes::from_static(b"data"));
+        assert!(pl.decode(&mut buf).unwrap().unwrap().eof());
+        assert!(buf.is_empty());
+    }
+
+    #[test]
+    fn test_parse_chunked_payload_trailer_too_large() {
+        let mut buf = BytesMut::from(
+            &"GET /test HTTP/1.1\r\n\
+              transfer-encoding: chunked\r\n\r\n"[..],
+        );
+
+        let mut reader = MessageDecoder::<Request>::new(Limits {
+            max_trailer_size: 10,
+            ..Limits::default()
+        });
+        let (_, pl) = reader.decode(&mut buf).unwrap().unwrap();
+        let mut pl = pl.unwrap();
+
+        buf.extend(b"0\r\nx-trailer: test\r\n\r\n");
+        match pl.decode(&mut buf) {
+            Err(ParseError::TrailersTooLarge) => (),
+            _ => panic!("error expected"),
+        }
+    }
+
+    #[test]
+    fn test_parse_uri_too_long() {
+        let mut reader = MessageDecoder::<Request>::new(Limits {
+            max_uri_size: 8,
+            ..Limits::default()
+        });
+
+        let mut buf = BytesMut::from("GET /test HTTP/1.1\r\n\r\n");
+        assert!(reader.decode(&mut buf).unwrap().is_some());
+
+        let mut buf = BytesMut::from("GET /test/long HTTP/1.1\r\n\r\n");
+        match reader.decode(&mut buf) {
+            Err(ParseError::UriTooLong) => (),
+            _ => panic!("error expected"),
+        }
+
+        // incomplete request line
+        let mut buf = BytesMut::from("GET /test/long");
+        match reader.decode(&mut buf) {
+            Err(ParseError::UriTooLong) => (),
+            _ => panic!("error expected"),
+        }
+    }
+
+    #[test]
+    fn test_parse_too_many_headers() {
+        let mut reader = MessageDecoder::<Request>::new(Limits {
+            max_headers: 2,
+            ..Limits::default()
+        });
+
+        let mut buf = BytesMut::from("GET /test HTTP/1.1\r\na: 1\r\nb: 2\r\n\r\n");
+        assert!(reader.decode(&mut buf).unwrap().is_some());
+
+        let mut buf =
+            BytesMut::from("GET /test HTTP/1.1\r\na: 1\r\nb: 2\r\nc: 3\r\n\r\n");
+        match reader.decode(&mut buf) {
+            Err(ParseError::TooManyHeaders) => (),
+            _ => panic!("error expected"),
+        }
+    }
+
+    #[test]
+    fn test_parse_headers_too_large() {
+        let mut reader = MessageDecoder::<Request>::new(Limits {
+            max_header_size: 16,
+            ..Limits::default()
+        });
+
+        let mut buf = BytesMut::from("GET /test HTTP/1.1\r\nx-test: 1\r\n\r\n");
+        assert!(reader.decode(&mut buf).unwrap().is_some());
+
+        let mut buf = BytesMut::from("GET /test HTTP/1.1\r\nx-test: 123456789\r\n\r\n");
+        match reader.decode(&mut buf) {
+            Err(ParseError::HeadersTooLarge) => (),
+            _ => panic!("error expected"),
+        }
+
+        // incomplete headers section
+        let mut buf = BytesMut::from("GET /test HTTP/1.1\r\nx-test: 123456789");
+        match reader.decode(&mut buf) {
+            Err(ParseError::HeadersTooLarge) => (),
+            _ => panic!("error expected"),
+        }
+    }
 }
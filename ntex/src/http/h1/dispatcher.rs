@@ -3,20 +3,25 @@ use std::future::Future;
 use std::pin::Pin;
 use std::rc::Rc;
 use std::task::{Context, Poll};
-use std::{fmt, io, mem, net};
+use std::{fmt, io, mem, net, time};
 
 use bitflags::bitflags;
 use bytes::{Buf, BytesMut};
 use futures::ready;
 use pin_project::{pin_project, project};
+use tokio::io::AsyncReadExt;
 
 use crate::codec::{AsyncRead, AsyncWrite, Decoder, Encoder, Framed, FramedParts};
+use crate::http::arrival::Arrival;
 use crate::http::body::{Body, BodySize, MessageBody, ResponseBody};
+use crate::http::committed::{Delivery, ResponseCommitted};
 use crate::http::config::DispatcherConfig;
-use crate::http::error::{DispatchError, PayloadError, ResponseError};
+use crate::http::error::{DispatchError, ParseError, PayloadError, ResponseError};
 use crate::http::helpers::DataFactory;
+use crate::http::pending::{PendingCounter, PendingGuard};
 use crate::http::request::Request;
 use crate::http::response::Response;
+use crate::http::throttle::Throttle;
 use crate::rt::time::{delay_until, Delay, Instant};
 use crate::Service;
 
@@ -105,7 +110,16 @@ where
 
     send_payload: Option<ResponseBody<B>>,
     payload: Option<PayloadSender>,
+    /// hooks of response that is being encoded
+    payload_commit: Option<ResponseCommitted>,
+    /// hooks of encoded responses and connection offsets of their ends
+    commits: VecDeque<(u64, ResponseCommitted)>,
+    /// total amount of written bytes
+    written: u64,
+    throttle: Option<Throttle>,
     messages: VecDeque<DispatcherMessage>,
+    pending: PendingCounter,
+    inflight: Option<PendingGuard>,
 
     ka_expire: Instant,
     ka_timer: Option<Delay>,
@@ -114,11 +128,12 @@ where
     read_buf: BytesMut,
     write_buf: BytesMut,
     codec: Codec,
+    arrival: Option<time::Instant>,
 }
 
 enum DispatcherMessage {
-    Request(Request),
-    Upgrade(Request),
+    Request(Request, PendingGuard),
+    Upgrade(Request, PendingGuard),
     Error(Response<()>),
 }
 
@@ -167,7 +182,8 @@ where
         peer_addr: Option<net::SocketAddr>,
         on_connect: Option<Box<dyn DataFactory>>,
     ) -> Self {
-        let codec = Codec::new(config.timer.clone(), config.keep_alive_enabled());
+        let mut codec = Codec::new(config.timer.clone(), config.keep_alive_enabled());
+        codec.set_limits(config.limits);
         // slow request timer
         let timeout = config.client_timer();
 
@@ -208,15 +224,34 @@ where
             (config.now(), None)
         };
 
+        // request payload read rate
+        let throttle = match config.limits.max_payload_rate {
+            0 => None,
+            rate => Some(Throttle::new(rate, config.now())),
+        };
+
+        // data could be already read during protocol detection
+        let arrival = if read_buf.is_empty() {
+            None
+        } else {
+            Some(time::Instant::now())
+        };
+
         Dispatcher {
             call: CallState::Io,
             upgrade: None,
             inner: InnerDispatcher {
                 write_buf: BytesMut::with_capacity(WRITE_HW_BUFFER_SIZE),
                 payload: None,
+                throttle,
                 send_payload: None,
+                payload_commit: None,
+                commits: VecDeque::new(),
+                written: 0,
                 error: None,
                 messages: VecDeque::new(),
+                pending: PendingCounter::default(),
+                inflight: None,
                 io: Some(io),
                 config,
                 codec,
@@ -226,6 +261,7 @@ where
                 on_connect,
                 ka_expire,
                 ka_timer,
+                arrival,
             },
         }
     }
@@ -245,8 +281,33 @@ where
 {
     type Output = Result<(), DispatchError>;
 
-    #[project]
     fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let result = self.as_mut().poll_dispatch(cx);
+        if result.is_ready() {
+            // responses that are not written yet, could not be written anymore
+            self.project().inner.fail_commits();
+        }
+        result
+    }
+}
+
+impl<T, S, B, X, U> Dispatcher<T, S, B, X, U>
+where
+    T: AsyncRead + AsyncWrite + Unpin,
+    S: Service<Request = Request>,
+    S::Error: ResponseError,
+    S::Response: Into<Response<B>>,
+    B: MessageBody,
+    X: Service<Request = Request, Response = Request>,
+    X::Error: ResponseError,
+    U: Service<Request = (Request, Framed<T, Codec>), Response = ()>,
+    U::Error: fmt::Display,
+{
+    #[project]
+    fn poll_dispatch(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<(), DispatchError>> {
         let mut this = self.as_mut().project();
 
         // upgrade
@@ -360,7 +421,7 @@ where
                             }
                             CallProcess::Upgrade(fut) => {
                                 this.upgrade.set(Some(fut));
-                                return self.poll(cx);
+                                return self.poll_dispatch(cx);
                             }
                             CallProcess::Io => (),
                             CallProcess::Pending => unreachable!(),
@@ -370,7 +431,7 @@ where
                 }
                 CallProcess::Upgrade(fut) => {
                     this.upgrade.set(Some(fut));
-                    return self.poll(cx);
+                    return self.poll_dispatch(cx);
                 }
             };
 
@@ -526,15 +587,48 @@ where
         } else {
             self.write_buf.advance(written);
         }
+
+        // run hooks of written responses
+        self.written += written as u64;
+        while let Some((end, _)) = self.commits.front() {
+            if *end > self.written {
+                break;
+            }
+            if let Some((_, commit)) = self.commits.pop_front() {
+                commit.commit(Delivery::Written);
+            }
+        }
         Ok(())
     }
 
+    /// Register hooks of completely encoded response
+    fn push_commit(&mut self, commit: Option<ResponseCommitted>) {
+        if let Some(commit) = commit {
+            let end = self.written + self.write_buf.len() as u64;
+            self.commits.push_back((end, commit));
+        }
+    }
+
+    /// Run hooks of responses that are not written
+    fn fail_commits(&mut self) {
+        for commit in self
+            .payload_commit
+            .take()
+            .into_iter()
+            .chain(self.commits.drain(..).map(|(_, commit)| commit))
+        {
+            commit.commit(Delivery::Failed);
+        }
+    }
+
     fn send_response(
         &mut self,
-        msg: Response<()>,
+        mut msg: Response<()>,
         body: ResponseBody<B>,
     ) -> Result<bool, DispatchError> {
         trace!("Sending response: {:?}", msg);
+        let commit = msg.extensions_mut().remove::<ResponseCommitted>();
+
         // we dont need to process responses if socket is disconnected
         // but we still want to handle requests with app service
         if !self.flags.contains(Flags::DISCONNECT) {
@@ -550,13 +644,20 @@ where
             self.flags.set(Flags::KEEPALIVE, self.codec.keepalive());
 
             match body.size() {
-                BodySize::None | BodySize::Empty => Ok(true),
+                BodySize::None | BodySize::Empty => {
+                    self.push_commit(commit);
+                    Ok(true)
+                }
                 _ => {
                     self.send_payload = Some(body);
+                    self.payload_commit = commit;
                     Ok(false)
                 }
             }
         } else {
+            if let Some(commit) = commit {
+                commit.commit(Delivery::Failed);
+            }
             Ok(false)
         }
     }
@@ -585,6 +686,8 @@ where
                         self.codec
                             .encode(Message::Chunk(None), &mut self.write_buf)?;
                         self.send_payload = None;
+                        let commit = self.payload_commit.take();
+                        self.push_commit(commit);
                         break;
                     }
                     Poll::Ready(Some(Err(e))) => {
@@ -643,18 +746,41 @@ where
                 return Ok(PollRead::NoUpdates);
             }
 
+            // limit request payload read rate
+            let mut throttle = if self.payload.is_some() {
+                self.throttle.as_mut()
+            } else {
+                None
+            };
+            let allowed = match throttle {
+                Some(ref mut throttle) => match throttle.poll_ready(cx) {
+                    Poll::Ready(n) => n,
+                    Poll::Pending => return Ok(PollRead::NoUpdates),
+                },
+                None => std::usize::MAX,
+            };
+
             // read data from socket
             let io = self.io.as_mut().unwrap();
             let buf = &mut self.read_buf;
+            let empty = buf.is_empty();
+            let initial = buf.len();
             let mut updated = false;
-            while buf.len() < MAX_BUFFER_SIZE {
+            while buf.len() < MAX_BUFFER_SIZE && buf.len() - initial < allowed {
                 // increase read buffer size
                 let remaining = buf.capacity() - buf.len();
                 if remaining < READ_LW_BUFFER_SIZE {
                     buf.reserve(BUFFER_SIZE);
                 }
 
-                match read(cx, io, buf) {
+                let res = if allowed == std::usize::MAX {
+                    read(cx, io, buf)
+                } else {
+                    let limit = allowed - (buf.len() - initial);
+                    read(cx, &mut AsyncReadExt::take(&mut *io, limit as u64), buf)
+                };
+
+                match res {
                     Poll::Pending => break,
                     Poll::Ready(Ok(n)) => {
                         if n == 0 {
@@ -677,9 +803,16 @@ where
                 }
             }
 
+            if let Some(throttle) = throttle {
+                throttle.consume(buf.len() - initial);
+            }
+
             if !updated {
                 return Ok(PollRead::NoUpdates);
             }
+            if empty {
+                self.arrival = Some(time::Instant::now());
+            }
         }
 
         if self.read_buf.is_empty() {
@@ -711,16 +844,46 @@ where
                             let pl = self.codec.message_type();
                             req.head_mut().peer_addr = self.peer_addr;
 
+                            // rest of the buffer arrived at the same time
+                            let arrival =
+                                self.arrival.take().unwrap_or_else(time::Instant::now);
+                            if !self.read_buf.is_empty() {
+                                self.arrival = Some(arrival);
+                            }
+                            req.extensions_mut().insert(Arrival::new(arrival));
+
                             // set on_connect data
                             if let Some(ref on_connect) = self.on_connect {
                                 on_connect.set(&mut req.extensions_mut());
                             }
 
+                            // reject request if too many requests are pending,
+                            // connection with unread payload could not be reused
+                            if self.config.pending.exceeded(&self.pending) {
+                                trace!("Too many pending requests, reject request");
+                                let mut res = Response::ServiceUnavailable();
+                                if pl != MessageType::None {
+                                    res.force_close();
+                                }
+                                self.messages.push_back(DispatcherMessage::Error(
+                                    res.finish().drop_body(),
+                                ));
+                                if pl != MessageType::None {
+                                    self.flags.insert(Flags::STOP_READING);
+                                    self.read_buf.clear();
+                                    break;
+                                }
+                                continue;
+                            }
+                            let (guard, pending) = self.pending.acquire();
+                            req.extensions_mut().insert(pending);
+
                             // handle upgrade request
                             if pl == MessageType::Stream && self.config.upgrade.is_some()
                             {
                                 self.flags.insert(Flags::STOP_READING);
-                                self.messages.push_back(DispatcherMessage::Upgrade(req));
+                                self.messages
+                                    .push_back(DispatcherMessage::Upgrade(req, guard));
                                 break;
                             }
 
@@ -733,7 +896,8 @@ where
                                 self.payload = Some(ps);
                             }
 
-                            self.messages.push_back(DispatcherMessage::Request(req));
+                            self.messages
+                                .push_back(DispatcherMessage::Request(req, guard));
                         }
                         Message::Chunk(Some(chunk)) => {
                             if let Some(ref mut payload) = self.payload {
@@ -772,10 +936,18 @@ where
                         payload.set_error(PayloadError::EncodingCorrupted);
                     }
 
-                    // Malformed requests should be responded with 400
-                    self.messages.push_back(DispatcherMessage::Error(
-                        Response::BadRequest().finish().drop_body(),
-                    ));
+                    // Requests that exceed configured limits get distinct status,
+                    // other malformed requests should be responded with 400
+                    let mut res = match e {
+                        ParseError::UriTooLong => Response::UriTooLong(),
+                        ParseError::TooManyHeaders | ParseError::HeadersTooLarge => {
+                            Response::RequestHeaderFieldsTooLarge()
+                        }
+                        ParseError::TrailersTooLarge => Response::PayloadTooLarge(),
+                        _ => Response::BadRequest(),
+                    };
+                    self.messages
+                        .push_back(DispatcherMessage::Error(res.finish().drop_body()));
                     self.flags.insert(Flags::STOP_READING);
                     self.read_buf.clear();
                     self.error = Some(e.into());
@@ -852,6 +1024,9 @@ where
         &mut self,
         res: Response<B>,
     ) -> Result<CallProcess<S, X, U>, DispatchError> {
+        // request is not pending anymore
+        self.inflight = None;
+
         let (res, body) = res.replace_body(());
         if self.send_response(res, body)? {
             // response does not have body, so we can process next request
@@ -867,7 +1042,9 @@ where
     ) -> Result<CallProcess<S, X, U>, DispatchError> {
         while let Some(msg) = self.messages.pop_front() {
             return match msg {
-                DispatcherMessage::Request(req) => {
+                DispatcherMessage::Request(req, guard) => {
+                    self.inflight = Some(guard);
+
                     // Handle `EXPECT: 100-Continue` header
                     Ok(CallProcess::Next(if req.head().expect() {
                         CallState::Expect(self.config.expect.call(req))
@@ -876,7 +1053,7 @@ where
                     }))
                 }
                 // switch to upgrade handler
-                DispatcherMessage::Upgrade(req) => {
+                DispatcherMessage::Upgrade(req, _) => {
                     self.flags.insert(Flags::UPGRADE);
                     let mut parts = FramedParts::with_read_buf(
                         self.io.take().unwrap(),
@@ -927,8 +1104,9 @@ mod tests {
     use std::time::Duration;
 
     use super::*;
-    use crate::http::config::{DispatcherConfig, ServiceConfig};
+    use crate::http::config::{DispatcherConfig, KeepAlive, Limits, ServiceConfig};
     use crate::http::h1::{ClientCodec, ExpectHandler, UpgradeHandler};
+    use crate::http::pending::PendingLimits;
     use crate::http::{body, Request, ResponseHead, StatusCode};
     use crate::rt::time::delay_for;
     use crate::service::IntoService;
@@ -1094,6 +1272,60 @@ mod tests {
         assert_eq!(num.load(Ordering::Relaxed), 3);
     }
 
+    #[ntex_rt::test]
+    async fn test_response_commit() {
+        let status = Rc::new(std::cell::RefCell::new(Vec::new()));
+        let status2 = status.clone();
+
+        let (client, server) = Io::create();
+        client.remote_buffer_cap(1024);
+        spawn_h1(server, move |_| {
+            let status = status2.clone();
+            let commit = ResponseCommitted::new();
+            commit.on_commit(move |d| async move { status.borrow_mut().push(d) });
+            let mut res = Response::Ok().body("data");
+            res.extensions_mut().insert(commit);
+            ok::<_, io::Error>(res)
+        });
+
+        client.write("GET /test HTTP/1.1\r\n\r\n");
+        let mut buf = client.read().await.unwrap();
+        assert!(load(&mut ClientCodec::default(), &mut buf)
+            .status
+            .is_success());
+        delay_for(Duration::from_millis(50)).await;
+        assert_eq!(*status.borrow(), vec![Delivery::Written]);
+
+        // connection is closed before response is written
+        client.write("GET /test HTTP/1.1\r\n\r\n");
+        client.close().await;
+        assert!(client.is_server_dropped());
+        delay_for(Duration::from_millis(50)).await;
+        assert_eq!(*status.borrow(), vec![Delivery::Written, Delivery::Failed]);
+    }
+
+    #[ntex_rt::test]
+    async fn test_req_uri_too_long() {
+        let (client, server) = Io::create();
+        client.remote_buffer_cap(1024);
+        let data = rand::thread_rng()
+            .sample_iter(&rand::distributions::Alphanumeric)
+            .take(20_000)
+            .collect::<String>();
+        client.write(format!("GET /{} HTTP/1.1\r\n\r\n", data));
+
+        let mut h1 = h1(server, |_| ok::<_, io::Error>(Response::Ok().finish()));
+        let mut decoder = ClientCodec::default();
+        assert!(lazy(|cx| Pin::new(&mut h1).poll(cx)).await.is_pending());
+        assert!(h1.inner.flags.contains(Flags::SHUTDOWN));
+
+        let mut buf = client.read().await.unwrap();
+        assert_eq!(
+            load(&mut decoder, &mut buf).status,
+            StatusCode::URI_TOO_LONG
+        );
+    }
+
     #[ntex_rt::test]
     async fn test_read_large_message() {
         let (client, server) = Io::create();
@@ -1113,7 +1345,10 @@ mod tests {
         assert!(h1.inner.flags.contains(Flags::SHUTDOWN));
 
         let mut buf = client.read().await.unwrap();
-        assert_eq!(load(&mut decoder, &mut buf).status, StatusCode::BAD_REQUEST);
+        assert_eq!(
+            load(&mut decoder, &mut buf).status,
+            StatusCode::REQUEST_HEADER_FIELDS_TOO_LARGE
+        );
     }
 
     #[ntex_rt::test]
@@ -1151,6 +1386,66 @@ mod tests {
         assert!(mark.load(Ordering::Relaxed));
     }
 
+    #[ntex_rt::test]
+    async fn test_payload_rate() {
+        // virtual clock, timers fire in order once dispatcher is idle
+        tokio::time::pause();
+
+        let received = Arc::new(AtomicUsize::new(0));
+        let received2 = received.clone();
+
+        let (client, server) = Io::create();
+        client.remote_buffer_cap(1024);
+        let config = ServiceConfig::with_limits(
+            KeepAlive::Timeout(5),
+            0,
+            0,
+            5000,
+            Limits {
+                max_payload_rate: 2000,
+                ..Limits::default()
+            },
+            PendingLimits::default(),
+        );
+        crate::rt::spawn(
+            Dispatcher::<_, _, _, ExpectHandler, UpgradeHandler<Io>>::new(
+                Rc::new(DispatcherConfig::new(
+                    config,
+                    (move |mut req: Request| {
+                        let received = received2.clone();
+                        async move {
+                            let mut pl = req.take_payload();
+                            while let Some(chunk) = pl.next().await {
+                                received
+                                    .fetch_add(chunk.unwrap().len(), Ordering::Relaxed);
+                            }
+                            Ok::<_, io::Error>(Response::Ok().finish())
+                        }
+                    })
+                    .into_service(),
+                    ExpectHandler,
+                    None,
+                )),
+                server,
+                None,
+                None,
+            )
+            .map(|_| ()),
+        );
+
+        client.write("GET /test HTTP/1.1\r\nContent-Length: 3000\r\n\r\n");
+        delay_for(Duration::from_millis(50)).await;
+        client.write(vec![b'x'; 3000]);
+        delay_for(Duration::from_millis(250)).await;
+
+        // burst and 250ms worth of data
+        let n = received.load(Ordering::Relaxed);
+        assert!((2000..=2600).contains(&n), "{}", n);
+
+        delay_for(Duration::from_millis(500)).await;
+        assert_eq!(received.load(Ordering::Relaxed), 3000);
+    }
+
     #[ntex_rt::test]
     async fn test_write_backpressure() {
         let num = Arc::new(AtomicUsize::new(0));
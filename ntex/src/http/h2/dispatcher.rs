@@ -13,12 +13,15 @@ use http::header::{HeaderValue, CONNECTION, CONTENT_LENGTH, DATE, TRANSFER_ENCOD
 use log::{error, trace};
 
 use crate::codec::{AsyncRead, AsyncWrite};
+use crate::http::arrival::Arrival;
 use crate::http::body::{BodySize, MessageBody, ResponseBody};
+use crate::http::committed::{Delivery, ResponseCommitted};
 use crate::http::config::{DateService, DispatcherConfig};
 use crate::http::error::{DispatchError, ResponseError};
 use crate::http::helpers::DataFactory;
 use crate::http::message::ResponseHead;
 use crate::http::payload::Payload;
+use crate::http::pending::{PendingCounter, PendingGuard};
 use crate::http::request::Request;
 use crate::http::response::Response;
 use crate::rt::time::{Delay, Instant};
@@ -38,6 +41,7 @@ where
     peer_addr: Option<net::SocketAddr>,
     ka_expire: Instant,
     ka_timer: Option<Delay>,
+    pending: PendingCounter,
     _t: PhantomData<B>,
 }
 
@@ -72,6 +76,7 @@ where
             on_connect,
             ka_expire,
             ka_timer,
+            pending: PendingCounter::default(),
             _t: PhantomData,
         }
     }
@@ -96,7 +101,7 @@ where
             match Pin::new(&mut this.connection).poll_accept(cx) {
                 Poll::Ready(None) => return Poll::Ready(Ok(())),
                 Poll::Ready(Some(Err(err))) => return Poll::Ready(Err(err.into())),
-                Poll::Ready(Some(Ok((req, res)))) => {
+                Poll::Ready(Some(Ok((req, mut res)))) => {
                     // update keep-alive expire
                     if this.ka_timer.is_some() {
                         if let Some(expire) = this.config.keep_alive_expire() {
@@ -104,6 +109,20 @@ where
                         }
                     }
 
+                    // reject stream if too many requests are pending
+                    if this.config.pending.exceeded(&this.pending) {
+                        trace!("Too many pending requests, reject stream");
+                        let mut h2_res = http::Response::new(());
+                        *h2_res.status_mut() = http::StatusCode::SERVICE_UNAVAILABLE;
+                        *h2_res.version_mut() = http::Version::HTTP_2;
+                        if let Err(e) = res.send_response(h2_res, true) {
+                            trace!("Error sending h2 response: {:?}", e);
+                        }
+                        continue;
+                    }
+                    let (guard, pending) = this.pending.acquire();
+
+                    let arrival = Arrival::new(std::time::Instant::now());
                     let (parts, body) = req.into_parts();
                     let mut req = Request::with_payload(Payload::<
                         crate::http::payload::PayloadStream,
@@ -117,6 +136,8 @@ where
                     head.version = parts.version;
                     head.headers = parts.headers.into();
                     head.peer_addr = this.peer_addr;
+                    req.extensions_mut().insert(arrival);
+                    req.extensions_mut().insert(pending);
 
                     // set on_connect data
                     if let Some(ref on_connect) = this.on_connect {
@@ -135,6 +156,8 @@ where
                         ),
                         timer: this.config.timer.clone(),
                         buffer: None,
+                        pending: Some(guard),
+                        commit: None,
                         _t: PhantomData,
                     });
                 }
@@ -150,6 +173,8 @@ struct ServiceResponse<F, I, E, B> {
     state: ServiceResponseState<F, B>,
     timer: DateService,
     buffer: Option<Bytes>,
+    pending: Option<PendingGuard>,
+    commit: Option<ResponseCommitted>,
     _t: PhantomData<(I, E)>,
 }
 
@@ -238,20 +263,42 @@ where
 {
     type Output = ();
 
-    #[pin_project::project]
     fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let result = self.as_mut().poll_response(cx);
+        if result.is_ready() {
+            // hooks are not taken, response is not written
+            if let Some(commit) = self.project().commit.take() {
+                commit.commit(Delivery::Failed);
+            }
+        }
+        result
+    }
+}
+
+impl<F, I, E, B> ServiceResponse<F, I, E, B>
+where
+    F: Future<Output = Result<I, E>>,
+    E: ResponseError,
+    I: Into<Response<B>>,
+    B: MessageBody,
+{
+    #[pin_project::project]
+    fn poll_response(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
         let mut this = self.as_mut().project();
 
         #[project]
         match this.state.project() {
             ServiceResponseState::ServiceCall(call, send) => match call.poll(cx) {
                 Poll::Ready(Ok(res)) => {
-                    let (res, body) = res.into().replace_body(());
+                    let (mut res, body) = res.into().replace_body(());
+                    let commit = res.extensions_mut().remove::<ResponseCommitted>();
 
                     let mut send = send.take().unwrap();
                     let mut size = body.size();
                     let h2_res = self.as_mut().prepare_response(res.head(), &mut size);
                     this = self.as_mut().project();
+                    this.pending.take();
+                    *this.commit = commit;
 
                     let stream = match send.send_response(h2_res, size.is_eof()) {
                         Err(e) => {
@@ -262,11 +309,14 @@ where
                     };
 
                     if size.is_eof() {
+                        if let Some(commit) = this.commit.take() {
+                            commit.commit(Delivery::Written);
+                        }
                         Poll::Ready(())
                     } else {
                         this.state
                             .set(ServiceResponseState::SendPayload(stream, body));
-                        self.poll(cx)
+                        self.poll_response(cx)
                     }
                 }
                 Poll::Pending => Poll::Pending,
@@ -278,6 +328,7 @@ where
                     let mut size = body.size();
                     let h2_res = self.as_mut().prepare_response(res.head(), &mut size);
                     this = self.as_mut().project();
+                    this.pending.take();
 
                     let stream = match send.send_response(h2_res, size.is_eof()) {
                         Err(e) => {
@@ -294,7 +345,7 @@ where
                             stream,
                             body.into_body(),
                         ));
-                        self.poll(cx)
+                        self.poll_response(cx)
                     }
                 }
             },
@@ -329,6 +380,8 @@ where
                             Poll::Ready(None) => {
                                 if let Err(e) = stream.send_data(Bytes::new(), true) {
                                     warn!("{:?}", e);
+                                } else if let Some(commit) = this.commit.take() {
+                                    commit.commit(Delivery::Written);
                                 }
                                 return Poll::Ready(());
                             }
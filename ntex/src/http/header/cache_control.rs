@@ -0,0 +1,299 @@
+use std::fmt::{self, Write};
+use std::str::FromStr;
+
+use super::{HeaderValue, IntoHeaderValue, InvalidHeaderValue};
+use crate::http::error::ParseError;
+
+/// Typed `Cache-Control` header.
+///
+/// ```rust
+/// use ntex::http::{header::CacheControl, Response};
+///
+/// let res = Response::Ok()
+///     .cache_control(
+///         CacheControl::new()
+///             .public()
+///             .max_age(3600)
+///             .stale_while_revalidate(60),
+///     )
+///     .finish();
+/// assert_eq!(
+///     res.headers().get("cache-control").unwrap(),
+///     "public, max-age=3600, stale-while-revalidate=60"
+/// );
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CacheControl {
+    public: bool,
+    private: bool,
+    no_cache: bool,
+    no_store: bool,
+    no_transform: bool,
+    must_revalidate: bool,
+    proxy_revalidate: bool,
+    immutable: bool,
+    max_age: Option<u64>,
+    s_maxage: Option<u64>,
+    stale_while_revalidate: Option<u64>,
+    stale_if_error: Option<u64>,
+}
+
+impl CacheControl {
+    /// Create empty `Cache-Control` header.
+    pub fn new() -> Self {
+        CacheControl::default()
+    }
+
+    /// Response could be stored by any cache.
+    ///
+    /// Unsets `private` directive.
+    pub fn public(mut self) -> Self {
+        self.public = true;
+        self.private = false;
+        self
+    }
+
+    /// Response could be stored only by browser cache.
+    ///
+    /// Unsets `public` directive.
+    pub fn private(mut self) -> Self {
+        self.private = true;
+        self.public = false;
+        self
+    }
+
+    /// Response must be validated with origin server before each reuse.
+    pub fn no_cache(mut self) -> Self {
+        self.no_cache = true;
+        self
+    }
+
+    /// Response must not be stored by any cache.
+    pub fn no_store(mut self) -> Self {
+        self.no_store = true;
+        self
+    }
+
+    /// Intermediaries must not transform response body.
+    pub fn no_transform(mut self) -> Self {
+        self.no_transform = true;
+        self
+    }
+
+    /// Stale response must not be reused without validation.
+    pub fn must_revalidate(mut self) -> Self {
+        self.must_revalidate = true;
+        self
+    }
+
+    /// Same as `must_revalidate` but only for shared caches.
+    pub fn proxy_revalidate(mut self) -> Self {
+        self.proxy_revalidate = true;
+        self
+    }
+
+    /// Response will not be updated while it is fresh.
+    pub fn immutable(mut self) -> Self {
+        self.immutable = true;
+        self
+    }
+
+    /// Response remains fresh for specified number of seconds.
+    pub fn max_age(mut self, secs: u64) -> Self {
+        self.max_age = Some(secs);
+        self
+    }
+
+    /// Same as `max_age` but only for shared caches.
+    pub fn s_maxage(mut self, secs: u64) -> Self {
+        self.s_maxage = Some(secs);
+        self
+    }
+
+    /// Stale response could be reused for specified number of seconds
+    /// while cache revalidates it in background.
+    pub fn stale_while_revalidate(mut self, secs: u64) -> Self {
+        self.stale_while_revalidate = Some(secs);
+        self
+    }
+
+    /// Stale response could be reused for specified number of seconds
+    /// if origin server responds with an error.
+    pub fn stale_if_error(mut self, secs: u64) -> Self {
+        self.stale_if_error = Some(secs);
+        self
+    }
+
+    /// Check if `public` directive is set
+    pub fn is_public(&self) -> bool {
+        self.public
+    }
+
+    /// Check if `private` directive is set
+    pub fn is_private(&self) -> bool {
+        self.private
+    }
+
+    /// Check if `no-cache` directive is set
+    pub fn is_no_cache(&self) -> bool {
+        self.no_cache
+    }
+
+    /// Check if `no-store` directive is set
+    pub fn is_no_store(&self) -> bool {
+        self.no_store
+    }
+
+    /// Check if `immutable` directive is set
+    pub fn is_immutable(&self) -> bool {
+        self.immutable
+    }
+
+    /// Get `max-age` directive value
+    pub fn get_max_age(&self) -> Option<u64> {
+        self.max_age
+    }
+
+    /// Get `s-maxage` directive value
+    pub fn get_s_maxage(&self) -> Option<u64> {
+        self.s_maxage
+    }
+
+    /// Get `stale-while-revalidate` directive value
+    pub fn get_stale_while_revalidate(&self) -> Option<u64> {
+        self.stale_while_revalidate
+    }
+}
+
+impl fmt::Display for CacheControl {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let flags = [
+            (self.public, "public"),
+            (self.private, "private"),
+            (self.no_cache, "no-cache"),
+            (self.no_store, "no-store"),
+            (self.no_transform, "no-transform"),
+            (self.must_revalidate, "must-revalidate"),
+            (self.proxy_revalidate, "proxy-revalidate"),
+            (self.immutable, "immutable"),
+        ];
+        let values = [
+            (self.max_age, "max-age"),
+            (self.s_maxage, "s-maxage"),
+            (self.stale_while_revalidate, "stale-while-revalidate"),
+            (self.stale_if_error, "stale-if-error"),
+        ];
+
+        let mut sep = "";
+        for (_, name) in flags.iter().filter(|item| item.0) {
+            write!(f, "{}{}", sep, name)?;
+            sep = ", ";
+        }
+        for (val, name) in values.iter() {
+            if let Some(val) = val {
+                write!(f, "{}{}={}", sep, name, val)?;
+                sep = ", ";
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Parse `Cache-Control` header value, unknown directives are ignored.
+impl FromStr for CacheControl {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut cc = CacheControl::new();
+        for item in s.split(',').map(|item| item.trim()) {
+            if item.is_empty() {
+                continue;
+            }
+            let mut parts = item.splitn(2, '=');
+            let name = parts.next().unwrap().trim().to_ascii_lowercase();
+            let value = parts.next().map(|val| val.trim().trim_matches('"'));
+            let secs = || {
+                value
+                    .and_then(|val| val.parse::<u64>().ok())
+                    .ok_or(ParseError::Header)
+            };
+
+            match name.as_str() {
+                "public" => cc.public = true,
+                "private" => cc.private = true,
+                "no-cache" => cc.no_cache = true,
+                "no-store" => cc.no_store = true,
+                "no-transform" => cc.no_transform = true,
+                "must-revalidate" => cc.must_revalidate = true,
+                "proxy-revalidate" => cc.proxy_revalidate = true,
+                "immutable" => cc.immutable = true,
+                "max-age" => cc.max_age = Some(secs()?),
+                "s-maxage" => cc.s_maxage = Some(secs()?),
+                "stale-while-revalidate" => cc.stale_while_revalidate = Some(secs()?),
+                "stale-if-error" => cc.stale_if_error = Some(secs()?),
+                _ => (),
+            }
+        }
+        Ok(cc)
+    }
+}
+
+impl IntoHeaderValue for CacheControl {
+    type Error = InvalidHeaderValue;
+
+    #[inline]
+    fn try_into(self) -> Result<HeaderValue, Self::Error> {
+        let mut s = String::with_capacity(64);
+        let _ = write!(s, "{}", self);
+        HeaderValue::from_str(&s)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_display() {
+        assert_eq!(CacheControl::new().to_string(), "");
+        assert_eq!(CacheControl::new().no_store().to_string(), "no-store");
+        assert_eq!(
+            CacheControl::new()
+                .public()
+                .private()
+                .max_age(60)
+                .s_maxage(120)
+                .to_string(),
+            "private, max-age=60, s-maxage=120"
+        );
+        assert_eq!(
+            CacheControl::new()
+                .public()
+                .immutable()
+                .max_age(31_536_000)
+                .to_string(),
+            "public, immutable, max-age=31536000"
+        );
+    }
+
+    #[test]
+    fn test_parse() {
+        let cc: CacheControl = "Public, max-age=\"60\", stale-while-revalidate=30, foo"
+            .parse()
+            .unwrap();
+        assert!(cc.is_public());
+        assert!(!cc.is_private());
+        assert_eq!(cc.get_max_age(), Some(60));
+        assert_eq!(cc.get_stale_while_revalidate(), Some(30));
+        assert_eq!(
+            cc.to_string().parse::<CacheControl>().unwrap(),
+            CacheControl::new()
+                .public()
+                .max_age(60)
+                .stale_while_revalidate(30)
+        );
+
+        assert!("max-age".parse::<CacheControl>().is_err());
+        assert!("max-age=abc".parse::<CacheControl>().is_err());
+    }
+}
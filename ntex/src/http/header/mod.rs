@@ -8,8 +8,10 @@ use mime::Mime;
 
 pub use http::header::{HeaderName, HeaderValue, InvalidHeaderValue};
 
+mod cache_control;
 pub(crate) mod map;
 
+pub use self::cache_control::CacheControl;
 #[doc(hidden)]
 pub use self::map::GetAll;
 pub use self::map::HeaderMap;
@@ -13,6 +13,11 @@ macro_rules! STATIC_RESP {
 }
 
 impl Response {
+    STATIC_RESP!(Continue, StatusCode::CONTINUE);
+    STATIC_RESP!(SwitchingProtocols, StatusCode::SWITCHING_PROTOCOLS);
+    STATIC_RESP!(Processing, StatusCode::PROCESSING);
+    STATIC_RESP!(EarlyHints, StatusCode::from_u16(103).unwrap());
+
     STATIC_RESP!(Ok, StatusCode::OK);
     STATIC_RESP!(Created, StatusCode::CREATED);
     STATIC_RESP!(Accepted, StatusCode::ACCEPTED);
@@ -26,6 +31,7 @@ impl Response {
     STATIC_RESP!(PartialContent, StatusCode::PARTIAL_CONTENT);
     STATIC_RESP!(MultiStatus, StatusCode::MULTI_STATUS);
     STATIC_RESP!(AlreadyReported, StatusCode::ALREADY_REPORTED);
+    STATIC_RESP!(ImUsed, StatusCode::IM_USED);
 
     STATIC_RESP!(MultipleChoices, StatusCode::MULTIPLE_CHOICES);
     STATIC_RESP!(MovedPermanently, StatusCode::MOVED_PERMANENTLY);
@@ -58,8 +64,22 @@ impl Response {
     STATIC_RESP!(UnsupportedMediaType, StatusCode::UNSUPPORTED_MEDIA_TYPE);
     STATIC_RESP!(RangeNotSatisfiable, StatusCode::RANGE_NOT_SATISFIABLE);
     STATIC_RESP!(ExpectationFailed, StatusCode::EXPECTATION_FAILED);
+    STATIC_RESP!(ImATeapot, StatusCode::IM_A_TEAPOT);
+    STATIC_RESP!(MisdirectedRequest, StatusCode::MISDIRECTED_REQUEST);
     STATIC_RESP!(UnprocessableEntity, StatusCode::UNPROCESSABLE_ENTITY);
+    STATIC_RESP!(Locked, StatusCode::LOCKED);
+    STATIC_RESP!(FailedDependency, StatusCode::FAILED_DEPENDENCY);
+    STATIC_RESP!(TooEarly, StatusCode::from_u16(425).unwrap());
+    STATIC_RESP!(UpgradeRequired, StatusCode::UPGRADE_REQUIRED);
     STATIC_RESP!(TooManyRequests, StatusCode::TOO_MANY_REQUESTS);
+    STATIC_RESP!(
+        RequestHeaderFieldsTooLarge,
+        StatusCode::REQUEST_HEADER_FIELDS_TOO_LARGE
+    );
+    STATIC_RESP!(
+        UnavailableForLegalReasons,
+        StatusCode::UNAVAILABLE_FOR_LEGAL_REASONS
+    );
 
     STATIC_RESP!(InternalServerError, StatusCode::INTERNAL_SERVER_ERROR);
     STATIC_RESP!(NotImplemented, StatusCode::NOT_IMPLEMENTED);
@@ -70,6 +90,11 @@ impl Response {
     STATIC_RESP!(VariantAlsoNegotiates, StatusCode::VARIANT_ALSO_NEGOTIATES);
     STATIC_RESP!(InsufficientStorage, StatusCode::INSUFFICIENT_STORAGE);
     STATIC_RESP!(LoopDetected, StatusCode::LOOP_DETECTED);
+    STATIC_RESP!(NotExtended, StatusCode::NOT_EXTENDED);
+    STATIC_RESP!(
+        NetworkAuthenticationRequired,
+        StatusCode::NETWORK_AUTHENTICATION_REQUIRED
+    );
 }
 
 #[cfg(test)]
@@ -82,5 +107,12 @@ mod tests {
     fn test_build() {
         let resp = Response::Ok().body(Body::Empty);
         assert_eq!(resp.status(), StatusCode::OK);
+
+        let resp = Response::EarlyHints().finish();
+        assert_eq!(resp.status().as_u16(), 103);
+        let resp = Response::TooEarly().finish();
+        assert_eq!(resp.status().as_u16(), 425);
+        let resp = Response::UnavailableForLegalReasons().finish();
+        assert_eq!(resp.status(), StatusCode::UNAVAILABLE_FOR_LEGAL_REASONS);
     }
 }
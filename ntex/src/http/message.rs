@@ -413,19 +413,20 @@ impl Drop for BoxedResponseHead {
 
 #[doc(hidden)]
 /// Request's objects pool
-pub(crate) struct MessagePool<T: Head>(RefCell<Vec<Rc<T>>>);
+pub(crate) struct MessagePool<T: Head>(RefCell<Vec<Rc<T>>>, usize);
 
 #[doc(hidden)]
 #[allow(clippy::vec_box)]
 /// Request's objects pool
-pub(super) struct BoxedResponsePool(RefCell<Vec<Box<ResponseHead>>>);
+pub(super) struct BoxedResponsePool(RefCell<Vec<Box<ResponseHead>>>, usize);
 
 thread_local!(static REQUEST_POOL: &'static MessagePool<RequestHead> = MessagePool::<RequestHead>::create());
 thread_local!(static RESPONSE_POOL: &'static BoxedResponsePool = BoxedResponsePool::create());
 
 impl<T: Head> MessagePool<T> {
     fn create() -> &'static MessagePool<T> {
-        let pool = MessagePool(RefCell::new(Vec::with_capacity(128)));
+        let size = crate::server::pool_size();
+        let pool = MessagePool(RefCell::new(Vec::with_capacity(size)), size);
         Box::leak(Box::new(pool))
     }
 
@@ -448,7 +449,7 @@ impl<T: Head> MessagePool<T> {
     /// Release request instance
     fn release(&self, msg: Rc<T>) {
         let v = &mut self.0.borrow_mut();
-        if v.len() < 128 {
+        if v.len() < self.1 {
             v.push(msg);
         }
     }
@@ -456,7 +457,8 @@ impl<T: Head> MessagePool<T> {
 
 impl BoxedResponsePool {
     fn create() -> &'static BoxedResponsePool {
-        let pool = BoxedResponsePool(RefCell::new(Vec::with_capacity(128)));
+        let size = crate::server::pool_size();
+        let pool = BoxedResponsePool(RefCell::new(Vec::with_capacity(size)), size);
         Box::leak(Box::new(pool))
     }
 
@@ -480,7 +482,7 @@ impl BoxedResponsePool {
     /// Release request instance
     fn release(&self, msg: Box<ResponseHead>) {
         let v = &mut self.0.borrow_mut();
-        if v.len() < 128 {
+        if v.len() < self.1 {
             msg.extensions.borrow_mut().clear();
             v.push(msg);
         }
@@ -1,8 +1,12 @@
 //! Http protocol support.
+mod arrival;
 pub mod body;
 mod builder;
 pub mod client;
+mod committed;
 mod config;
+#[cfg(feature = "cookie")]
+pub mod cookie;
 #[cfg(feature = "compress")]
 pub mod encoding;
 mod extensions;
@@ -10,10 +14,12 @@ pub(crate) mod helpers;
 mod httpcodes;
 mod httpmessage;
 mod message;
-mod payload;
+pub mod payload;
+mod pending;
 mod request;
 mod response;
 mod service;
+pub(crate) mod throttle;
 
 pub mod error;
 pub mod h1;
@@ -24,15 +30,22 @@ pub mod ws;
 
 pub(crate) use self::message::Message;
 
+#[cfg(target_os = "linux")]
+pub use self::arrival::tcp_rtt;
+pub use self::arrival::Arrival;
 pub use self::builder::HttpServiceBuilder;
+pub use self::committed::{Delivery, ResponseCommitted};
 pub use self::config::{DateService, KeepAlive, ServiceConfig};
 pub use self::error::ResponseError;
 pub use self::extensions::Extensions;
 pub use self::header::HeaderMap;
 pub use self::httpmessage::HttpMessage;
 pub use self::message::{ConnectionType, RequestHead, RequestHeadType, ResponseHead};
-pub use self::payload::{Payload, PayloadStream};
+pub use self::payload::{Payload, PayloadExt, PayloadStream};
+pub use self::pending::Pending;
 pub use self::request::Request;
+#[cfg(feature = "send")]
+pub use self::response::SendResponse;
 pub use self::response::{Response, ResponseBuilder};
 pub use self::service::HttpService;
 
@@ -1,11 +1,16 @@
+//! Request payload stream and payload combinators
+use std::future::Future;
 use std::pin::Pin;
 use std::task::{Context, Poll};
+use std::time::Duration;
 
-use bytes::Bytes;
-use futures::Stream;
+use bytes::{Bytes, BytesMut};
+use futures::{ready, Stream};
 use h2::RecvStream;
+use pin_project::pin_project;
 
 use super::error::PayloadError;
+use crate::rt::time::{delay_until, Delay, Instant};
 
 /// Type represent boxed payload
 pub type PayloadStream = Pin<Box<dyn Stream<Item = Result<Bytes, PayloadError>>>>;
@@ -68,3 +73,395 @@ where
         }
     }
 }
+
+/// Payload stream combinators.
+///
+/// Combinators are implemented for all payload streams, combined streams
+/// could be converted back to `Payload` with `into_payload()`.
+///
+/// ```rust
+/// use std::time::Duration;
+/// use ntex::http::PayloadExt;
+/// use ntex::web::dev::WebRequest;
+/// use ntex::web::DefaultError;
+///
+/// fn limit_payload(req: &mut WebRequest<DefaultError>) {
+///     let payload = req
+///         .take_payload()
+///         .limit(65_536)
+///         .chunk_timeout(Duration::from_secs(5))
+///         .into_payload();
+///     req.set_payload(payload);
+/// }
+/// ```
+pub trait PayloadExt: Stream<Item = Result<Bytes, PayloadError>> + Sized {
+    /// Limit payload size, `PayloadError::Overflow` is returned if payload
+    /// is larger than `max` bytes.
+    ///
+    /// Default error renderer renders overflow error as
+    /// *413 Payload Too Large* response.
+    fn limit(self, max: usize) -> Limit<Self> {
+        Limit {
+            max,
+            stream: self,
+            size: 0,
+            done: false,
+        }
+    }
+
+    /// Call function for each payload chunk.
+    fn inspect_chunks<F>(self, f: F) -> InspectChunks<Self, F>
+    where
+        F: FnMut(&Bytes),
+    {
+        InspectChunks { f, stream: self }
+    }
+
+    /// Transform each payload chunk, function could fail payload stream.
+    fn map_chunks<F>(self, f: F) -> MapChunks<Self, F>
+    where
+        F: FnMut(Bytes) -> Result<Bytes, PayloadError>,
+    {
+        MapChunks { f, stream: self }
+    }
+
+    /// Limit time between payload chunks, `PayloadError::Timeout` is
+    /// returned if next chunk is not received in time.
+    ///
+    /// Default error renderer renders timeout error as
+    /// *408 Request Timeout* response.
+    fn chunk_timeout(self, timeout: Duration) -> ChunkTimeout<Self> {
+        ChunkTimeout {
+            timeout,
+            stream: self,
+            delay: None,
+            done: false,
+        }
+    }
+
+    /// Merge payload chunks to chunks of at least `min` bytes, last chunk
+    /// could be smaller.
+    fn coalesce(self, min: usize) -> Coalesce<Self> {
+        Coalesce {
+            min,
+            stream: self,
+            buf: BytesMut::new(),
+            done: false,
+        }
+    }
+
+    /// Convert stream to `Payload`
+    fn into_payload(self) -> Payload
+    where
+        Self: 'static,
+    {
+        Payload::Stream(Box::pin(self))
+    }
+}
+
+impl<S> PayloadExt for S where S: Stream<Item = Result<Bytes, PayloadError>> {}
+
+/// Payload stream with size limit, check
+/// [`PayloadExt::limit()`](trait.PayloadExt.html#method.limit)
+#[pin_project]
+pub struct Limit<S> {
+    #[pin]
+    stream: S,
+    max: usize,
+    size: usize,
+    done: bool,
+}
+
+impl<S> Stream for Limit<S>
+where
+    S: Stream<Item = Result<Bytes, PayloadError>>,
+{
+    type Item = Result<Bytes, PayloadError>;
+
+    fn poll_next(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Self::Item>> {
+        let this = self.project();
+        if *this.done {
+            return Poll::Ready(None);
+        }
+
+        match ready!(this.stream.poll_next(cx)) {
+            Some(Ok(chunk)) => {
+                *this.size += chunk.len();
+                if *this.size > *this.max {
+                    *this.done = true;
+                    Poll::Ready(Some(Err(PayloadError::Overflow)))
+                } else {
+                    Poll::Ready(Some(Ok(chunk)))
+                }
+            }
+            item => Poll::Ready(item),
+        }
+    }
+}
+
+/// Payload stream that inspects chunks, check
+/// [`PayloadExt::inspect_chunks()`](trait.PayloadExt.html#method.inspect_chunks)
+#[pin_project]
+pub struct InspectChunks<S, F> {
+    #[pin]
+    stream: S,
+    f: F,
+}
+
+impl<S, F> Stream for InspectChunks<S, F>
+where
+    S: Stream<Item = Result<Bytes, PayloadError>>,
+    F: FnMut(&Bytes),
+{
+    type Item = Result<Bytes, PayloadError>;
+
+    fn poll_next(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Self::Item>> {
+        let this = self.project();
+        let item = ready!(this.stream.poll_next(cx));
+        if let Some(Ok(ref chunk)) = item {
+            (this.f)(chunk);
+        }
+        Poll::Ready(item)
+    }
+}
+
+/// Payload stream that transforms chunks, check
+/// [`PayloadExt::map_chunks()`](trait.PayloadExt.html#method.map_chunks)
+#[pin_project]
+pub struct MapChunks<S, F> {
+    #[pin]
+    stream: S,
+    f: F,
+}
+
+impl<S, F> Stream for MapChunks<S, F>
+where
+    S: Stream<Item = Result<Bytes, PayloadError>>,
+    F: FnMut(Bytes) -> Result<Bytes, PayloadError>,
+{
+    type Item = Result<Bytes, PayloadError>;
+
+    fn poll_next(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Self::Item>> {
+        let this = self.project();
+        match ready!(this.stream.poll_next(cx)) {
+            Some(Ok(chunk)) => Poll::Ready(Some((this.f)(chunk))),
+            item => Poll::Ready(item),
+        }
+    }
+}
+
+/// Payload stream with timeout between chunks, check
+/// [`PayloadExt::chunk_timeout()`](trait.PayloadExt.html#method.chunk_timeout)
+#[pin_project]
+pub struct ChunkTimeout<S> {
+    #[pin]
+    stream: S,
+    timeout: Duration,
+    delay: Option<Delay>,
+    done: bool,
+}
+
+impl<S> Stream for ChunkTimeout<S>
+where
+    S: Stream<Item = Result<Bytes, PayloadError>>,
+{
+    type Item = Result<Bytes, PayloadError>;
+
+    fn poll_next(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Self::Item>> {
+        let this = self.project();
+        if *this.done {
+            return Poll::Ready(None);
+        }
+
+        match this.stream.poll_next(cx) {
+            Poll::Ready(item) => {
+                // next chunk gets full timeout
+                *this.delay = None;
+                Poll::Ready(item)
+            }
+            Poll::Pending => {
+                let timeout = *this.timeout;
+                let delay = this
+                    .delay
+                    .get_or_insert_with(|| delay_until(Instant::now() + timeout));
+                match Pin::new(delay).poll(cx) {
+                    Poll::Ready(_) => {
+                        *this.done = true;
+                        Poll::Ready(Some(Err(PayloadError::Timeout)))
+                    }
+                    Poll::Pending => Poll::Pending,
+                }
+            }
+        }
+    }
+}
+
+/// Payload stream that merges small chunks, check
+/// [`PayloadExt::coalesce()`](trait.PayloadExt.html#method.coalesce)
+#[pin_project]
+pub struct Coalesce<S> {
+    #[pin]
+    stream: S,
+    min: usize,
+    buf: BytesMut,
+    done: bool,
+}
+
+impl<S> Stream for Coalesce<S>
+where
+    S: Stream<Item = Result<Bytes, PayloadError>>,
+{
+    type Item = Result<Bytes, PayloadError>;
+
+    fn poll_next(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Self::Item>> {
+        let mut this = self.project();
+        if *this.done {
+            return Poll::Ready(None);
+        }
+
+        loop {
+            match ready!(this.stream.as_mut().poll_next(cx)) {
+                Some(Ok(chunk)) => {
+                    if this.buf.is_empty() && chunk.len() >= *this.min {
+                        return Poll::Ready(Some(Ok(chunk)));
+                    }
+                    this.buf.extend_from_slice(&chunk);
+                    if this.buf.len() >= *this.min {
+                        return Poll::Ready(Some(Ok(this.buf.split().freeze())));
+                    }
+                }
+                Some(Err(err)) => {
+                    *this.done = true;
+                    return Poll::Ready(Some(Err(err)));
+                }
+                None => {
+                    *this.done = true;
+                    return if this.buf.is_empty() {
+                        Poll::Ready(None)
+                    } else {
+                        Poll::Ready(Some(Ok(this.buf.split().freeze())))
+                    };
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    use futures::stream::{self, StreamExt};
+
+    use super::*;
+    use crate::rt::time::delay_for;
+
+    fn chunks(
+        items: &[&'static [u8]],
+    ) -> impl Stream<Item = Result<Bytes, PayloadError>> {
+        stream::iter(
+            items
+                .iter()
+                .map(|item| Ok(Bytes::from_static(item)))
+                .collect::<Vec<_>>(),
+        )
+    }
+
+    #[ntex_rt::test]
+    async fn test_limit() {
+        let items: Vec<_> = chunks(&[b"abc", b"def"]).limit(6).collect().await;
+        assert_eq!(items.len(), 2);
+        assert!(items.iter().all(|item| item.is_ok()));
+
+        let mut pl = chunks(&[b"abc", b"def", b"g"]).limit(5).into_payload();
+        assert_eq!(
+            pl.next().await.unwrap().unwrap(),
+            Bytes::from_static(b"abc")
+        );
+        assert!(matches!(
+            pl.next().await.unwrap(),
+            Err(PayloadError::Overflow)
+        ));
+        assert!(pl.next().await.is_none());
+    }
+
+    #[ntex_rt::test]
+    async fn test_inspect_map() {
+        let size = Rc::new(Cell::new(0));
+        let size2 = size.clone();
+        let items: Vec<_> = chunks(&[b"abc", b"de"])
+            .inspect_chunks(move |chunk| size2.set(size2.get() + chunk.len()))
+            .map_chunks(|chunk| {
+                if chunk.len() > 2 {
+                    Ok(chunk.slice(..2))
+                } else {
+                    Err(PayloadError::EncodingCorrupted)
+                }
+            })
+            .collect()
+            .await;
+        assert_eq!(size.get(), 5);
+        assert_eq!(items[0].as_ref().unwrap(), &Bytes::from_static(b"ab"));
+        assert!(matches!(items[1], Err(PayloadError::EncodingCorrupted)));
+    }
+
+    #[ntex_rt::test]
+    async fn test_chunk_timeout() {
+        let items: Vec<_> = chunks(&[b"abc", b"def"])
+            .chunk_timeout(Duration::from_millis(50))
+            .collect()
+            .await;
+        assert_eq!(items.len(), 2);
+
+        let slow = chunks(&[b"abc", b"def"]).then(|item| async move {
+            delay_for(Duration::from_millis(100)).await;
+            item
+        });
+        let mut pl = Box::pin(slow.chunk_timeout(Duration::from_millis(20)));
+        assert!(matches!(
+            pl.next().await.unwrap(),
+            Err(PayloadError::Timeout)
+        ));
+        assert!(pl.next().await.is_none());
+    }
+
+    #[ntex_rt::test]
+    async fn test_coalesce() {
+        let items: Vec<_> = chunks(&[b"a", b"bc", b"defg", b"h", b"ij"])
+            .coalesce(3)
+            .map(|item| item.unwrap())
+            .collect()
+            .await;
+        assert_eq!(
+            items,
+            vec![
+                Bytes::from_static(b"abc"),
+                Bytes::from_static(b"defg"),
+                Bytes::from_static(b"hij")
+            ]
+        );
+
+        let items: Vec<_> = chunks(&[b"a", b"b"])
+            .coalesce(3)
+            .map(|item| item.unwrap())
+            .collect()
+            .await;
+        assert_eq!(items, vec![Bytes::from_static(b"ab")]);
+    }
+}
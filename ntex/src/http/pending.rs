@@ -0,0 +1,125 @@
+use std::cell::Cell;
+use std::rc::Rc;
+
+thread_local!(static WORKER: Cell<usize> = Cell::new(0));
+
+/// Pending requests state.
+///
+/// Dispatcher counts requests that are accepted but not yet responded,
+/// per connection and per worker thread. Snapshot taken at the moment
+/// request got accepted is stored in request extensions, snapshot includes
+/// request itself.
+///
+/// ```rust
+/// use ntex::http::Pending;
+/// use ntex::web::HttpRequest;
+///
+/// async fn index(req: HttpRequest) -> String {
+///     let pending = req.extensions().get::<Pending>().copied();
+///     format!("{:?}, now: {}", pending, Pending::worker_current())
+/// }
+/// # fn main() {}
+/// ```
+#[derive(Copy, Clone, Debug)]
+pub struct Pending {
+    connection: usize,
+    worker: usize,
+}
+
+impl Pending {
+    /// Number of pending requests on request's connection
+    pub fn connection(&self) -> usize {
+        self.connection
+    }
+
+    /// Number of pending requests in request's worker
+    pub fn worker(&self) -> usize {
+        self.worker
+    }
+
+    /// Number of requests currently pending in current worker
+    pub fn worker_current() -> usize {
+        WORKER.with(|w| w.get())
+    }
+}
+
+#[derive(Debug, Default, PartialEq, Clone, Copy)]
+/// Pending requests limits, zero means no limit
+pub(super) struct PendingLimits {
+    pub(super) worker: usize,
+    pub(super) connection: usize,
+}
+
+impl PendingLimits {
+    /// Check if new request on connection must be rejected
+    pub(super) fn exceeded(&self, connection: &PendingCounter) -> bool {
+        (self.worker != 0 && Pending::worker_current() >= self.worker)
+            || (self.connection != 0 && connection.0.get() >= self.connection)
+    }
+}
+
+/// Per-connection pending requests counter
+#[derive(Clone, Default)]
+pub(super) struct PendingCounter(Rc<Cell<usize>>);
+
+impl PendingCounter {
+    /// Track new pending request
+    pub(super) fn acquire(&self) -> (PendingGuard, Pending) {
+        self.0.set(self.0.get() + 1);
+        let worker = WORKER.with(|w| {
+            w.set(w.get() + 1);
+            w.get()
+        });
+        let pending = Pending {
+            worker,
+            connection: self.0.get(),
+        };
+        (PendingGuard(self.clone()), pending)
+    }
+}
+
+/// Pending request, counters get decremented on drop
+pub(super) struct PendingGuard(PendingCounter);
+
+impl Drop for PendingGuard {
+    fn drop(&mut self) {
+        let cnt = &(self.0).0;
+        cnt.set(cnt.get() - 1);
+        WORKER.with(|w| w.set(w.get() - 1));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pending() {
+        let limits = PendingLimits {
+            worker: 3,
+            connection: 2,
+        };
+        let c1 = PendingCounter::default();
+        let c2 = PendingCounter::default();
+        assert!(!limits.exceeded(&c1));
+
+        let (g1, p) = c1.acquire();
+        assert_eq!((p.connection(), p.worker()), (1, 1));
+        let (g2, p) = c1.acquire();
+        assert_eq!((p.connection(), p.worker()), (2, 2));
+        assert!(limits.exceeded(&c1));
+        assert!(!limits.exceeded(&c2));
+
+        let (g3, p) = c2.acquire();
+        assert_eq!((p.connection(), p.worker()), (1, 3));
+        assert!(limits.exceeded(&c2));
+        assert_eq!(Pending::worker_current(), 3);
+
+        drop(g1);
+        assert!(!limits.exceeded(&c1));
+        drop(g2);
+        drop(g3);
+        assert_eq!(Pending::worker_current(), 0);
+        assert!(!PendingLimits::default().exceeded(&c1));
+    }
+}
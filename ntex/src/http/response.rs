@@ -11,11 +11,15 @@ use serde::Serialize;
 #[cfg(feature = "cookie")]
 use coo_kie::{Cookie, CookieJar};
 
-use crate::http::body::{Body, BodyStream, MessageBody, ResponseBody};
+#[cfg(feature = "send")]
+use crate::http::body::SendBody;
+use crate::http::body::{Body, BodyStream, FlushBody, MessageBody, ResponseBody};
 use crate::http::error::{HttpError, ResponseError};
 use crate::http::extensions::Extensions;
 use crate::http::header::{self};
-use crate::http::header::{HeaderMap, HeaderName, HeaderValue, IntoHeaderValue};
+use crate::http::header::{
+    CacheControl, HeaderMap, HeaderName, HeaderValue, IntoHeaderValue,
+};
 use crate::http::message::{BoxedResponseHead, ConnectionType, ResponseHead};
 use crate::http::StatusCode;
 
@@ -414,8 +418,12 @@ impl ResponseBuilder {
     }
 
     /// Disable chunked transfer encoding for HTTP/1.1 streaming responses.
+    ///
+    /// Sets *Content-Length* header to `len`, streaming body must produce
+    /// exactly `len` bytes.
     #[inline]
-    pub fn no_chunking(&mut self) -> &mut Self {
+    pub fn no_chunking(&mut self, len: u64) -> &mut Self {
+        self.header(header::CONTENT_LENGTH, len);
         if let Some(parts) = parts(&mut self.head, &self.err) {
             parts.no_chunking(true);
         }
@@ -446,6 +454,22 @@ impl ResponseBuilder {
         self.header(header::CONTENT_LENGTH, len)
     }
 
+    /// Set *CACHE-CONTROL* header
+    ///
+    /// ```rust
+    /// use ntex::http::{header::CacheControl, Response};
+    ///
+    /// fn index() -> Response {
+    ///     Response::Ok()
+    ///         .cache_control(CacheControl::new().private().no_cache())
+    ///         .finish()
+    /// }
+    /// ```
+    #[inline]
+    pub fn cache_control(&mut self, value: CacheControl) -> &mut Self {
+        self.set_header(header::CACHE_CONTROL, value)
+    }
+
     #[cfg(feature = "cookie")]
     /// Set a cookie
     ///
@@ -591,6 +615,37 @@ impl ResponseBuilder {
         self.body(Body::from_message(BodyStream::new(stream)))
     }
 
+    /// Set a streaming body and generate `Response`, each chunk is flushed
+    /// to the peer as soon as it is produced.
+    ///
+    /// Suitable for server-sent events and other long-living streams.
+    /// `ResponseBuilder` can not be used after this call.
+    ///
+    /// ```rust
+    /// use bytes::Bytes;
+    /// use futures::stream;
+    /// use ntex::http::Response;
+    ///
+    /// fn events() -> Response {
+    ///     let events = stream::iter(vec![
+    ///         Ok::<_, std::io::Error>(Bytes::from_static(b"data: 1\n\n")),
+    ///         Ok(Bytes::from_static(b"data: 2\n\n")),
+    ///     ]);
+    ///
+    ///     Response::Ok()
+    ///         .content_type("text/event-stream")
+    ///         .keep_alive()
+    ///         .streaming_with_flush(events)
+    /// }
+    /// ```
+    pub fn streaming_with_flush<S, E>(&mut self, stream: S) -> Response
+    where
+        S: Stream<Item = Result<Bytes, E>> + Unpin + 'static,
+        E: Error + 'static,
+    {
+        self.body(Body::from_message(FlushBody::new(BodyStream::new(stream))))
+    }
+
     /// Set a json body and generate `Response`
     ///
     /// `ResponseBuilder` can not be used after this call.
@@ -812,11 +867,90 @@ impl From<BytesMut> for Response {
     }
 }
 
+#[cfg(feature = "send")]
+/// `Send` response parts.
+///
+/// `Response` uses thread local resources and could not be moved between
+/// threads. `SendResponse` could be constructed on any thread and
+/// converted to `Response` on the worker thread.
+///
+/// ```rust
+/// use ntex::http::{SendResponse, StatusCode};
+/// use ntex::web::{self, App};
+///
+/// async fn index() -> SendResponse {
+///     let (tx, rx) = futures::channel::oneshot::channel();
+///     std::thread::spawn(move || {
+///         let _ = tx.send(SendResponse::new(StatusCode::OK).body("computed"));
+///     });
+///     rx.await
+///         .unwrap_or_else(|_| SendResponse::new(StatusCode::INTERNAL_SERVER_ERROR))
+/// }
+///
+/// fn main() {
+///     let app = App::new().service(web::resource("/").to(index));
+/// }
+/// ```
+#[derive(Debug)]
+pub struct SendResponse {
+    status: StatusCode,
+    headers: HeaderMap,
+    body: SendBody,
+}
+
+#[cfg(feature = "send")]
+impl SendResponse {
+    /// Create response parts with specific status and empty body
+    pub fn new(status: StatusCode) -> SendResponse {
+        SendResponse {
+            status,
+            headers: HeaderMap::new(),
+            body: SendBody::Empty,
+        }
+    }
+
+    /// Response status
+    pub fn status(&self) -> StatusCode {
+        self.status
+    }
+
+    /// Response headers
+    pub fn headers(&self) -> &HeaderMap {
+        &self.headers
+    }
+
+    /// Mutable reference to response headers
+    pub fn headers_mut(&mut self) -> &mut HeaderMap {
+        &mut self.headers
+    }
+
+    /// Append a header
+    pub fn header(mut self, key: HeaderName, value: HeaderValue) -> Self {
+        self.headers.append(key, value);
+        self
+    }
+
+    /// Set response body
+    pub fn body<B: Into<SendBody>>(mut self, body: B) -> Self {
+        self.body = body.into();
+        self
+    }
+}
+
+#[cfg(feature = "send")]
+impl From<SendResponse> for Response {
+    fn from(res: SendResponse) -> Response {
+        let mut response = Response::with_body(res.status, Body::from(res.body));
+        *response.headers_mut() = res.headers;
+        response
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::http::body::Body;
-    use crate::http::header::{HeaderValue, CONTENT_TYPE, COOKIE};
+    use crate::http::header::{HeaderValue, CONTENT_LENGTH, CONTENT_TYPE, COOKIE};
 
     #[test]
     fn test_debug() {
@@ -834,6 +968,18 @@ mod tests {
         assert!(dbg.contains("ResponseBuilder"));
     }
 
+    #[test]
+    fn test_cache_control() {
+        let resp = Response::Ok()
+            .header(header::CACHE_CONTROL, "no-cache")
+            .cache_control(CacheControl::new().public().max_age(60))
+            .finish();
+        assert_eq!(
+            resp.headers().get(header::CACHE_CONTROL).unwrap(),
+            "public, max-age=60"
+        );
+    }
+
     #[cfg(feature = "cookie")]
     #[test]
     fn test_response_cookies() {
@@ -901,6 +1047,15 @@ mod tests {
         assert_eq!(resp.status(), StatusCode::OK);
     }
 
+    #[test]
+    fn test_no_chunking() {
+        let resp = Response::Ok()
+            .no_chunking(10)
+            .streaming(futures::stream::empty::<Result<_, std::io::Error>>());
+        assert!(!resp.head().chunked());
+        assert_eq!(resp.headers().get(CONTENT_LENGTH).unwrap(), "10");
+    }
+
     #[test]
     fn test_upgrade() {
         let resp = Response::build(StatusCode::OK)
@@ -1054,4 +1209,22 @@ mod tests {
             assert_eq!((cookie.name(), cookie.value()), ("cookie1", "val100"));
         }
     }
+
+    #[cfg(feature = "send")]
+    #[test]
+    fn test_send_response() {
+        let res = std::thread::spawn(|| {
+            SendResponse::new(StatusCode::CREATED)
+                .header(CONTENT_TYPE, HeaderValue::from_static("text/plain"))
+                .body("created")
+        })
+        .join()
+        .unwrap();
+        assert_eq!(res.status(), StatusCode::CREATED);
+
+        let resp = Response::from(res);
+        assert_eq!(resp.status(), StatusCode::CREATED);
+        assert_eq!(resp.headers().get(CONTENT_TYPE).unwrap(), "text/plain");
+        assert_eq!(resp.body().get_ref(), b"created");
+    }
 }
@@ -0,0 +1,118 @@
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use crate::rt::time::{delay_until, Delay, Instant};
+
+/// Token bucket for io rate limiting.
+///
+/// Bucket holds up to one second worth of tokens, each token allows to
+/// transfer one byte. Transfers could overdraw the bucket, in that case
+/// transfer resumes after debt is paid off.
+pub(crate) struct TokenBucket {
+    rate: f64,
+    tokens: f64,
+    updated: Instant,
+}
+
+impl TokenBucket {
+    /// Create full bucket, `rate` is number of bytes per second
+    pub(crate) fn new(rate: usize, now: Instant) -> Self {
+        TokenBucket {
+            rate: rate as f64,
+            tokens: rate as f64,
+            updated: now,
+        }
+    }
+
+    /// Number of bytes allowed to transfer.
+    ///
+    /// If bucket is empty, `delay` is set to the moment when transfer could
+    /// be resumed and waker is registered.
+    pub(crate) fn poll_ready(
+        &mut self,
+        delay: &mut Option<Delay>,
+        cx: &mut Context<'_>,
+    ) -> Poll<usize> {
+        loop {
+            let now = Instant::now();
+            let elapsed = now.saturating_duration_since(self.updated).as_secs_f64();
+            self.tokens = (self.tokens + elapsed * self.rate).min(self.rate);
+            self.updated = now;
+
+            if self.tokens >= 1.0 {
+                *delay = None;
+                return Poll::Ready(self.tokens as usize);
+            }
+
+            // wait for 100ms worth of tokens to avoid frequent wakeups
+            let wait = (self.rate / 10.0 - self.tokens) / self.rate;
+            let deadline = now + Duration::from_secs_f64(wait);
+            match delay {
+                Some(ref mut delay) => delay.reset(deadline),
+                None => *delay = Some(delay_until(deadline)),
+            }
+            if Pin::new(delay.as_mut().unwrap()).poll(cx).is_pending() {
+                return Poll::Pending;
+            }
+        }
+    }
+
+    /// Consume tokens for transferred bytes
+    pub(crate) fn consume(&mut self, size: usize) {
+        self.tokens -= size as f64;
+    }
+}
+
+/// Token bucket with own timer
+pub(crate) struct Throttle {
+    bucket: TokenBucket,
+    delay: Option<Delay>,
+}
+
+impl Throttle {
+    /// Create throttle, `rate` is number of bytes per second
+    pub(crate) fn new(rate: usize, now: Instant) -> Self {
+        Throttle {
+            bucket: TokenBucket::new(rate, now),
+            delay: None,
+        }
+    }
+
+    /// Number of bytes allowed to transfer.
+    ///
+    /// Registers waker if bucket is empty.
+    pub(crate) fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<usize> {
+        self.bucket.poll_ready(&mut self.delay, cx)
+    }
+
+    /// Consume tokens for transferred bytes
+    pub(crate) fn consume(&mut self, size: usize) {
+        self.bucket.consume(size)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::future::poll_fn;
+
+    use super::*;
+
+    #[ntex_rt::test]
+    async fn test_throttle() {
+        tokio::time::pause();
+
+        let mut throttle = Throttle::new(1000, Instant::now());
+        let n = poll_fn(|cx| throttle.poll_ready(cx)).await;
+        assert!(n >= 999);
+
+        throttle.consume(1500);
+        let start = Instant::now();
+        let n = poll_fn(|cx| throttle.poll_ready(cx)).await;
+        let elapsed = start.elapsed();
+        assert!(n > 0);
+        assert!(elapsed >= Duration::from_millis(550), "{:?}", elapsed);
+        assert!(elapsed < Duration::from_millis(1000), "{:?}", elapsed);
+    }
+}
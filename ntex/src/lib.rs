@@ -6,6 +6,10 @@
 //! * `rustls` - enables ssl support via `rustls` crate
 //! * `compress` - enables compression support in http and web modules
 //! * `cookie` - enables cookie support in http and web modules
+//! * `signing` - enables client request signing
+//! * `har` - enables client http archive recorder
+//! * `dictionary` - enables shared dictionary compression
+//! * `jwt` - enables jwt validation middleware
 
 #![warn(
     rust_2018_idioms,
@@ -8,6 +8,7 @@ use slab::Slab;
 use crate::rt::time::{delay_until, Instant};
 use crate::rt::System;
 
+use super::balance::{Balance, WorkerLoad};
 use super::socket::{SocketAddr, SocketListener, StdListener};
 use super::worker::{Conn, WorkerClient};
 use super::{Server, Token};
@@ -85,6 +86,7 @@ impl AcceptLoop {
         &mut self,
         socks: Vec<(Token, StdListener)>,
         workers: Vec<WorkerClient>,
+        balance: Box<dyn Balance>,
     ) {
         let srv = self.srv.take().expect("Can not re-use AcceptInfo");
 
@@ -95,6 +97,7 @@ impl AcceptLoop {
             socks,
             srv,
             workers,
+            balance,
         );
     }
 }
@@ -106,7 +109,9 @@ struct Accept {
     workers: Vec<WorkerClient>,
     srv: Server,
     timer: (mio::Registration, mio::SetReadiness),
-    next: usize,
+    balance: Box<dyn Balance>,
+    loads: Vec<WorkerLoad>,
+    positions: Vec<usize>,
     backpressure: bool,
 }
 
@@ -122,7 +127,7 @@ const NOTIFY: mio::Token = mio::Token(2);
 /// All other errors will incur a timeout before next `accept()` is performed.
 /// The timeout is useful to handle resource exhaustion errors like ENFILE
 /// and EMFILE. Otherwise, could enter into tight loop.
-fn connection_error(e: &io::Error) -> bool {
+pub(super) fn connection_error(e: &io::Error) -> bool {
     e.kind() == io::ErrorKind::ConnectionRefused
         || e.kind() == io::ErrorKind::ConnectionAborted
         || e.kind() == io::ErrorKind::ConnectionReset
@@ -137,6 +142,7 @@ impl Accept {
         socks: Vec<(Token, StdListener)>,
         srv: Server,
         workers: Vec<WorkerClient>,
+        balance: Box<dyn Balance>,
     ) {
         let sys = System::current();
 
@@ -145,7 +151,7 @@ impl Accept {
             .name("actix-server accept loop".to_owned())
             .spawn(move || {
                 System::set_current(sys);
-                let mut accept = Accept::new(rx, socks, workers, srv);
+                let mut accept = Accept::new(rx, socks, workers, srv, balance);
 
                 // Start listening for incoming commands
                 if let Err(err) = accept.poll.register(
@@ -176,6 +182,7 @@ impl Accept {
         socks: Vec<(Token, StdListener)>,
         workers: Vec<WorkerClient>,
         srv: Server,
+        balance: Box<dyn Balance>,
     ) -> Accept {
         // Create a poll instance
         let poll = match mio::Poll::new() {
@@ -224,7 +231,9 @@ impl Accept {
             sockets,
             workers,
             srv,
-            next: 0,
+            balance,
+            loads: Vec::new(),
+            positions: Vec::new(),
             timer: (tm, tmr),
             backpressure: false,
         }
@@ -364,56 +373,39 @@ impl Accept {
     }
 
     fn accept_one(&mut self, mut msg: Conn) {
-        if self.backpressure {
-            while !self.workers.is_empty() {
-                match self.workers[self.next].send(msg) {
-                    Ok(_) => (),
-                    Err(tmp) => {
-                        self.srv.worker_faulted(self.workers[self.next].idx);
-                        msg = tmp;
-                        self.workers.swap_remove(self.next);
-                        if self.workers.is_empty() {
-                            error!("No workers");
-                            return;
-                        } else if self.workers.len() <= self.next {
-                            self.next = 0;
-                        }
-                        continue;
-                    }
+        loop {
+            if self.workers.is_empty() {
+                error!("No workers");
+                self.backpressure(true);
+                return;
+            }
+
+            // collect workers that could accept connection,
+            // all workers are used if backpressure is enabled
+            self.loads.clear();
+            self.positions.clear();
+            for (pos, worker) in self.workers.iter().enumerate() {
+                if self.backpressure || worker.available() {
+                    self.loads.push(worker.load());
+                    self.positions.push(pos);
                 }
-                self.next = (self.next + 1) % self.workers.len();
-                break;
             }
-        } else {
-            let mut idx = 0;
-            while idx < self.workers.len() {
-                idx += 1;
-                if self.workers[self.next].available() {
-                    match self.workers[self.next].send(msg) {
-                        Ok(_) => {
-                            self.next = (self.next + 1) % self.workers.len();
-                            return;
-                        }
-                        Err(tmp) => {
-                            self.srv.worker_faulted(self.workers[self.next].idx);
-                            msg = tmp;
-                            self.workers.swap_remove(self.next);
-                            if self.workers.is_empty() {
-                                error!("No workers");
-                                self.backpressure(true);
-                                return;
-                            } else if self.workers.len() <= self.next {
-                                self.next = 0;
-                            }
-                            continue;
-                        }
-                    }
+            if self.loads.is_empty() {
+                // enable backpressure
+                self.backpressure(true);
+                continue;
+            }
+
+            let selected = self.balance.select(&self.loads);
+            let pos = self.positions[selected % self.positions.len()];
+            match self.workers[pos].send(msg) {
+                Ok(_) => return,
+                Err(tmp) => {
+                    self.srv.worker_faulted(self.workers[pos].idx);
+                    msg = tmp;
+                    self.workers.swap_remove(pos);
                 }
-                self.next = (self.next + 1) % self.workers.len();
             }
-            // enable backpressure
-            self.backpressure(true);
-            self.accept_one(msg);
         }
     }
 
@@ -0,0 +1,167 @@
+//! Connection balancing strategies
+use rand::Rng;
+
+/// Strategy for distributing accepted connections between workers.
+///
+/// Accept loop calls strategy for every accepted connection. Only workers
+/// that are able to process new connection are passed to the strategy,
+/// if all workers are busy accept loop stops accepting new connections.
+///
+/// ```rust
+/// use ntex::server::{Server, balance::{Balance, WorkerLoad}};
+///
+/// /// Always use first worker
+/// struct First;
+///
+/// impl Balance for First {
+///     fn select(&mut self, workers: &[WorkerLoad]) -> usize {
+///         0
+///     }
+/// }
+///
+/// let builder = Server::build().balance(First);
+/// ```
+pub trait Balance: Send {
+    /// Select worker for accepted connection.
+    ///
+    /// Returns position of the selected worker in `workers` slice,
+    /// slice is never empty.
+    fn select(&mut self, workers: &[WorkerLoad]) -> usize;
+}
+
+/// Worker load information
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct WorkerLoad {
+    idx: usize,
+    connections: usize,
+}
+
+impl WorkerLoad {
+    pub(super) fn new(idx: usize, connections: usize) -> Self {
+        WorkerLoad { idx, connections }
+    }
+
+    /// Worker index
+    pub fn idx(&self) -> usize {
+        self.idx
+    }
+
+    /// Number of connections that are currently processed by worker
+    pub fn connections(&self) -> usize {
+        self.connections
+    }
+}
+
+/// Select workers in round-robin order.
+///
+/// This is default strategy.
+#[derive(Debug, Default)]
+pub struct RoundRobin {
+    next: usize,
+}
+
+impl Balance for RoundRobin {
+    fn select(&mut self, workers: &[WorkerLoad]) -> usize {
+        let pos = workers
+            .iter()
+            .enumerate()
+            .filter(|(_, w)| w.idx >= self.next)
+            .min_by_key(|(_, w)| w.idx)
+            .or_else(|| workers.iter().enumerate().min_by_key(|(_, w)| w.idx))
+            .map(|(pos, _)| pos)
+            .unwrap_or(0);
+        self.next = workers[pos].idx + 1;
+        pos
+    }
+}
+
+/// Select worker with least number of connections.
+///
+/// Useful for long-lived connections, like websockets, where round-robin
+/// distribution leads to imbalance over time.
+#[derive(Debug, Default)]
+pub struct LeastConnections;
+
+impl Balance for LeastConnections {
+    fn select(&mut self, workers: &[WorkerLoad]) -> usize {
+        workers
+            .iter()
+            .enumerate()
+            .min_by_key(|(_, w)| w.connections)
+            .map(|(pos, _)| pos)
+            .unwrap_or(0)
+    }
+}
+
+/// Select two random workers and use one with less connections.
+///
+/// Provides distribution close to `LeastConnections` strategy, but
+/// avoids herding on the least loaded worker.
+#[derive(Debug, Default)]
+pub struct RandomTwoChoices;
+
+impl Balance for RandomTwoChoices {
+    fn select(&mut self, workers: &[WorkerLoad]) -> usize {
+        if workers.len() < 2 {
+            return 0;
+        }
+
+        let mut rng = rand::thread_rng();
+        let first = rng.gen_range(0, workers.len());
+        let mut second = rng.gen_range(0, workers.len() - 1);
+        if second >= first {
+            second += 1;
+        }
+
+        if workers[second].connections < workers[first].connections {
+            second
+        } else {
+            first
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn loads(conns: &[usize]) -> Vec<WorkerLoad> {
+        conns
+            .iter()
+            .enumerate()
+            .map(|(idx, c)| WorkerLoad::new(idx, *c))
+            .collect()
+    }
+
+    #[test]
+    fn test_round_robin() {
+        let mut b = RoundRobin::default();
+        let workers = loads(&[0, 0, 0]);
+        assert_eq!(b.select(&workers), 0);
+        assert_eq!(b.select(&workers), 1);
+        assert_eq!(b.select(&workers), 2);
+        assert_eq!(b.select(&workers), 0);
+
+        // unavailable workers are skipped
+        let workers = vec![WorkerLoad::new(0, 0), WorkerLoad::new(2, 0)];
+        assert_eq!(b.select(&workers), 1);
+        assert_eq!(b.select(&workers), 0);
+    }
+
+    #[test]
+    fn test_least_connections() {
+        let mut b = LeastConnections;
+        assert_eq!(b.select(&loads(&[5, 2, 3])), 1);
+        assert_eq!(b.select(&loads(&[1, 2, 1])), 0);
+    }
+
+    #[test]
+    fn test_random_two_choices() {
+        let mut b = RandomTwoChoices;
+        assert_eq!(b.select(&loads(&[5])), 0);
+        for _ in 0..100 {
+            assert_eq!(b.select(&loads(&[5, 1])), 1);
+            assert_ne!(b.select(&loads(&[1, 1, 10])), 2);
+        }
+    }
+}
@@ -1,40 +1,170 @@
+use std::collections::VecDeque;
+#[cfg(unix)]
+use std::ffi::OsString;
+#[cfg(unix)]
+use std::fs::File;
+#[cfg(unix)]
+use std::os::unix::io::AsRawFd;
 use std::pin::Pin;
 use std::task::{Context, Poll};
 use std::time::Duration;
-use std::{io, mem, net};
+use std::{io, mem, net, thread};
 
 use futures::channel::mpsc::{unbounded, UnboundedReceiver};
 use futures::channel::oneshot;
-use futures::future::ready;
+use futures::future::{join_all, ready, select, Either};
 use futures::stream::FuturesUnordered;
 use futures::{ready, Future, FutureExt, Stream, StreamExt};
 use log::{error, info};
 use socket2::{Domain, SockAddr, Socket, Type};
 
 use crate::rt::net::TcpStream;
-use crate::rt::time::{delay_until, Instant};
+use crate::rt::time::{delay_for, delay_until, Instant};
 use crate::rt::{spawn, System};
 
 use super::accept::{AcceptLoop, AcceptNotify, Command};
+use super::balance::{Balance, RoundRobin};
 use super::config::{ConfiguredService, ServiceConfig};
+#[cfg(unix)]
+use super::process::{self, Process};
 use super::service::{Factory, InternalServiceFactory, StreamServiceFactory};
 use super::signals::{Signal, Signals};
 use super::socket::StdListener;
-use super::worker::{self, Worker, WorkerAvailability, WorkerClient};
+use super::sockopt::{set_fastopen, tcp_socket, SocketOpts};
+use super::udp::{DatagramServiceFactory, InternalUdpFactory, UdpFactory};
+use super::worker::{self, Worker, WorkerAvailability, WorkerClient, WorkerWarmup};
 use super::{Server, ServerCommand, Token};
 
+/// Server behavior in case of worker warm-up failure
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum WarmupPolicy {
+    /// Stop server
+    Stop,
+    /// Log error and start accepting connections
+    Continue,
+}
+
+/// Worker restart policy.
+///
+/// Worker is restarted if it panics. Restart is delayed with exponential
+/// backoff, delay is doubled for each restart within restart window. If
+/// number of restarts within window exceeds limit, faulted worker is not
+/// restarted. By default workers are restarted immediately without limit.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct RestartPolicy {
+    max_restarts: Option<usize>,
+    window: Duration,
+    backoff: Duration,
+    max_backoff: Duration,
+}
+
+impl Default for RestartPolicy {
+    fn default() -> Self {
+        RestartPolicy {
+            max_restarts: None,
+            window: Duration::from_secs(60),
+            backoff: Duration::from_secs(0),
+            max_backoff: Duration::from_secs(30),
+        }
+    }
+}
+
+impl RestartPolicy {
+    /// Create default restart policy
+    pub fn new() -> Self {
+        RestartPolicy::default()
+    }
+
+    /// Do not restart faulted workers
+    pub fn never() -> Self {
+        RestartPolicy::default().max_restarts(0)
+    }
+
+    /// Set maximum number of restarts within restart window
+    pub fn max_restarts(mut self, num: usize) -> Self {
+        self.max_restarts = Some(num);
+        self
+    }
+
+    /// Set restart window in seconds, by default 60 seconds
+    pub fn window(mut self, sec: u64) -> Self {
+        self.window = Duration::from_secs(sec);
+        self
+    }
+
+    /// Set initial and maximum restart delay in milliseconds.
+    ///
+    /// By default workers are restarted without delay.
+    pub fn backoff(mut self, initial: u64, max: u64) -> Self {
+        self.backoff = Duration::from_millis(initial);
+        self.max_backoff = Duration::from_millis(max);
+        self
+    }
+
+    /// Restart delay, `None` if worker should not be restarted
+    fn delay(&self, restarts: usize) -> Option<Duration> {
+        if let Some(max) = self.max_restarts {
+            if restarts >= max {
+                return None;
+            }
+        }
+        let mut delay = self.backoff;
+        for _ in 0..restarts {
+            if delay >= self.max_backoff {
+                break;
+            }
+            delay *= 2;
+        }
+        Some(std::cmp::min(delay, self.max_backoff))
+    }
+}
+
+/// Listener that is bound with `SO_REUSEPORT` option and
+/// replicated in each worker
+struct WorkerSocket {
+    token: Token,
+    name: String,
+    addr: net::SocketAddr,
+    lst: Option<net::TcpListener>,
+}
+
+/// Udp socket that is bound with `SO_REUSEPORT` option and
+/// replicated in each worker
+struct WorkerUdpSocket {
+    name: String,
+    addr: net::SocketAddr,
+    factory: Box<dyn InternalUdpFactory>,
+    sock: Option<net::UdpSocket>,
+}
+
 /// Server builder
 pub struct ServerBuilder {
     threads: usize,
+    processes: usize,
+    #[cfg(unix)]
+    children: Vec<Process>,
+    #[cfg(unix)]
+    process_args: Option<Vec<OsString>>,
     token: Token,
     backlog: i32,
+    opts: SocketOpts,
+    cpu_affinity: bool,
     workers: Vec<(usize, WorkerClient)>,
     services: Vec<Box<dyn InternalServiceFactory>>,
     sockets: Vec<(Token, String, StdListener)>,
+    worker_sockets: Vec<WorkerSocket>,
+    udp_sockets: Vec<WorkerUdpSocket>,
     accept: AcceptLoop,
+    balance: Option<Box<dyn Balance>>,
     exit: bool,
     shutdown_timeout: Duration,
     no_signals: bool,
+    warmup: Option<Box<dyn WorkerWarmup>>,
+    warmup_timeout: Duration,
+    warmup_policy: WarmupPolicy,
+    restart_policy: RestartPolicy,
+    restarts: VecDeque<Instant>,
+    stopping: bool,
     cmd: UnboundedReceiver<ServerCommand>,
     server: Server,
     notify: Vec<oneshot::Sender<()>>,
@@ -53,16 +183,32 @@ impl ServerBuilder {
         let server = Server::new(tx);
 
         ServerBuilder {
-            threads: num_cpus::get(),
+            threads: super::available_cpus(),
+            processes: 0,
+            #[cfg(unix)]
+            children: Vec::new(),
+            #[cfg(unix)]
+            process_args: None,
             token: Token(0),
             workers: Vec::new(),
             services: Vec::new(),
             sockets: Vec::new(),
+            worker_sockets: Vec::new(),
+            udp_sockets: Vec::new(),
             accept: AcceptLoop::new(server.clone()),
+            balance: None,
             backlog: 2048,
+            opts: SocketOpts::default(),
+            cpu_affinity: false,
             exit: false,
             shutdown_timeout: Duration::from_secs(30),
             no_signals: false,
+            warmup: None,
+            warmup_timeout: Duration::from_secs(30),
+            warmup_policy: WarmupPolicy::Stop,
+            restart_policy: RestartPolicy::default(),
+            restarts: VecDeque::new(),
+            stopping: false,
             cmd: rx,
             notify: Vec::new(),
             server,
@@ -72,12 +218,54 @@ impl ServerBuilder {
     /// Set number of workers to start.
     ///
     /// By default server uses number of available logical cpu as workers
-    /// count.
+    /// count, cpu quota of linux cgroup is taken into account.
     pub fn workers(mut self, num: usize) -> Self {
         self.threads = num;
         self
     }
 
+    /// Number of workers to start
+    pub(crate) fn workers_count(&self) -> usize {
+        self.threads
+    }
+
+    /// Set number of worker processes.
+    ///
+    /// Enables pre-fork mode. Master process binds listeners and starts
+    /// worker processes, each worker process inherits listeners and
+    /// starts `workers` worker threads. Master process supervises worker
+    /// processes and restarts them according to restart policy, so memory
+    /// corruption in one worker process does not affect others and each
+    /// process could be placed in separate cgroup. Signals are handled by
+    /// master process.
+    ///
+    /// Worker process re-executes current executable with the same
+    /// arguments, so application must build the same server in the same
+    /// order. In worker process listeners for addresses bound by the
+    /// builder are taken from master process and `run()` never returns,
+    /// process exits once server stops. Listeners passed with `listen()`
+    /// methods are not inherited.
+    ///
+    /// By default pre-fork mode is disabled.
+    #[cfg(unix)]
+    pub fn processes(mut self, num: usize) -> Self {
+        self.processes = num;
+        self
+    }
+
+    /// Set command line arguments for worker processes.
+    ///
+    /// By default worker processes use arguments of current process.
+    #[cfg(unix)]
+    pub fn process_args<I, S>(mut self, args: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<OsString>,
+    {
+        self.process_args = Some(args.into_iter().map(|s| s.into()).collect());
+        self
+    }
+
     /// Set the maximum number of pending connections.
     ///
     /// This refers to the number of clients that can be waiting to be served.
@@ -93,6 +281,69 @@ impl ServerBuilder {
         self
     }
 
+    /// Accept connections in workers instead of accept loop.
+    ///
+    /// Each worker binds its own listener with `SO_REUSEPORT` option, so
+    /// kernel distributes incoming connections between per-worker accept
+    /// queues and connection is accepted and processed by the same thread.
+    /// This improves cache locality on machines with many cores. Only
+    /// supported on unix platforms.
+    ///
+    /// Listeners passed to `listen()` method must have `SO_REUSEPORT` option
+    /// set. Services added with `configure()` method are served by accept
+    /// loop. Worker listeners are not affected by `Server::pause()`.
+    ///
+    /// This method should be called before `bind()` method call.
+    pub fn reuse_port(mut self, enabled: bool) -> Self {
+        self.opts.reuse_port = enabled;
+        self
+    }
+
+    #[cfg(feature = "tcp-fastopen")]
+    /// Enable TCP fast open for listeners.
+    ///
+    /// `qlen` is the maximum length of pending fast open requests queue.
+    /// If fast open is not supported by the platform or kernel, listeners
+    /// work with regular handshake. Only supported on linux.
+    ///
+    /// This method should be called before `bind()` method call.
+    pub fn tcp_fastopen(mut self, qlen: u32) -> Self {
+        self.opts.fastopen = Some(qlen);
+        self
+    }
+
+    #[cfg(feature = "mptcp")]
+    /// Use multipath TCP for listeners.
+    ///
+    /// If MPTCP is not supported by the platform or kernel, regular TCP
+    /// listeners are used. Only supported on linux.
+    ///
+    /// This method should be called before `bind()` method call.
+    pub fn mptcp(mut self, enabled: bool) -> Self {
+        self.opts.mptcp = enabled;
+        self
+    }
+
+    /// Pin worker threads to cpu cores.
+    ///
+    /// Each worker thread is pinned to one of the cores available to the
+    /// process, workers are distributed between cores in round-robin order.
+    /// Only supported on linux, on other platforms this option is ignored.
+    ///
+    /// By default workers are not pinned.
+    pub fn cpu_affinity(mut self, enabled: bool) -> Self {
+        self.cpu_affinity = enabled;
+        self
+    }
+
+    /// Set strategy for distributing accepted connections between workers.
+    ///
+    /// By default workers are selected in round-robin order.
+    pub fn balance<B: Balance + 'static>(mut self, balance: B) -> Self {
+        self.balance = Some(Box::new(balance));
+        self
+    }
+
     /// Sets the maximum per-worker number of concurrent connections.
     ///
     /// All socket listeners will stop accepting connections when this limit is
@@ -128,6 +379,51 @@ impl ServerBuilder {
         self
     }
 
+    /// Set worker warm-up function.
+    ///
+    /// Function get called once in each worker thread after worker
+    /// services get constructed. Server starts accepting connections
+    /// only after all workers complete warm-up. Restarted workers run
+    /// warm-up as well, worker gets connections after its warm-up is
+    /// completed.
+    pub fn warmup<F, R, E>(mut self, f: F) -> Self
+    where
+        F: Fn() -> R + Send + Clone + 'static,
+        R: Future<Output = Result<(), E>> + 'static,
+        E: std::fmt::Debug + 'static,
+    {
+        self.warmup = Some(Box::new(f));
+        self
+    }
+
+    /// Timeout for workers warm-up in seconds.
+    ///
+    /// If workers do not complete warm-up within this time, warm-up
+    /// is considered failed.
+    ///
+    /// By default warm-up timeout sets to 30 seconds.
+    pub fn warmup_timeout(mut self, sec: u64) -> Self {
+        self.warmup_timeout = Duration::from_secs(sec);
+        self
+    }
+
+    /// Set server behavior in case of worker warm-up failure.
+    ///
+    /// By default server stops.
+    pub fn warmup_policy(mut self, policy: WarmupPolicy) -> Self {
+        self.warmup_policy = policy;
+        self
+    }
+
+    /// Set worker restart policy.
+    ///
+    /// Restart events are reported with `ServerMetrics`, check
+    /// `Server::metrics()` method.
+    pub fn restart_policy(mut self, policy: RestartPolicy) -> Self {
+        self.restart_policy = policy;
+        self
+    }
+
     /// Execute external configuration as part of the server building
     /// process.
     ///
@@ -166,18 +462,10 @@ impl ServerBuilder {
         F: StreamServiceFactory<TcpStream>,
         U: net::ToSocketAddrs,
     {
-        let sockets = bind_addr(addr, self.backlog)?;
+        let sockets = bind_addr(addr, self.backlog, self.opts)?;
 
         for lst in sockets {
-            let token = self.token.next();
-            self.services.push(Factory::create(
-                name.as_ref().to_string(),
-                token,
-                factory.clone(),
-                lst.local_addr()?,
-            ));
-            self.sockets
-                .push((token, name.as_ref().to_string(), StdListener::Tcp(lst)));
+            self = self.listen(name.as_ref(), lst, factory.clone())?;
         }
         Ok(self)
     }
@@ -192,6 +480,11 @@ impl ServerBuilder {
     {
         use std::os::unix::net::UnixListener;
 
+        // worker process uses listener bound by master process
+        if let Some(lst) = process::inherited_uds(addr.as_ref()) {
+            return self.listen_uds(name, lst, factory);
+        }
+
         // The path must not exist when we try to bind.
         // Try to remove it to avoid bind error.
         if let Err(e) = std::fs::remove_file(addr.as_ref()) {
@@ -243,14 +536,96 @@ impl ServerBuilder {
         F: StreamServiceFactory<TcpStream>,
     {
         let token = self.token.next();
+        let addr = lst.local_addr()?;
         self.services.push(Factory::create(
             name.as_ref().to_string(),
             token,
             factory,
-            lst.local_addr()?,
+            addr,
         ));
-        self.sockets
-            .push((token, name.as_ref().to_string(), StdListener::Tcp(lst)));
+        if self.opts.reuse_port {
+            self.worker_sockets.push(WorkerSocket {
+                token,
+                addr,
+                name: name.as_ref().to_string(),
+                lst: Some(lst),
+            });
+        } else {
+            self.sockets
+                .push((token, name.as_ref().to_string(), StdListener::Tcp(lst)));
+        }
+        Ok(self)
+    }
+
+    /// Add new udp service to the server.
+    ///
+    /// On unix platforms each worker binds its own socket with `SO_REUSEPORT`
+    /// option and kernel distributes datagrams between workers by peer
+    /// address. On other platforms datagrams are processed by first worker.
+    pub fn bind_udp<F, U, N: AsRef<str>>(
+        mut self,
+        name: N,
+        addr: U,
+        factory: F,
+    ) -> io::Result<Self>
+    where
+        F: DatagramServiceFactory,
+        U: net::ToSocketAddrs,
+    {
+        let mut err = None;
+        let mut succ = false;
+        for addr in addr.to_socket_addrs()? {
+            #[cfg(unix)]
+            let res = match process::inherited_udp(addr) {
+                Some(sock) => Ok(sock),
+                None => create_udp_socket(addr, true),
+            };
+            #[cfg(not(unix))]
+            let res = create_udp_socket(addr, false);
+
+            match res {
+                Ok(sock) => {
+                    succ = true;
+                    self = self.listen_udp(name.as_ref(), sock, factory.clone())?;
+                }
+                Err(e) => err = Some(e),
+            }
+        }
+
+        if !succ {
+            if let Some(e) = err.take() {
+                Err(e)
+            } else {
+                Err(io::Error::new(
+                    io::ErrorKind::Other,
+                    "Can not bind to address.",
+                ))
+            }
+        } else {
+            Ok(self)
+        }
+    }
+
+    /// Add new udp service to the server.
+    ///
+    /// On unix platforms socket must have `SO_REUSEPORT` option set,
+    /// otherwise only first worker receives datagrams.
+    pub fn listen_udp<F, N: AsRef<str>>(
+        mut self,
+        name: N,
+        sock: net::UdpSocket,
+        factory: F,
+    ) -> io::Result<Self>
+    where
+        F: DatagramServiceFactory,
+    {
+        let name = name.as_ref().to_string();
+        self.udp_sockets.push(WorkerUdpSocket {
+            addr: sock.local_addr()?,
+            factory: UdpFactory::create(name.clone(), factory),
+            sock: Some(sock),
+            name,
+        });
         Ok(self)
     }
 
@@ -261,30 +636,48 @@ impl ServerBuilder {
 
     /// Starts processing incoming connections and return server controller.
     pub fn run(mut self) -> Server {
-        if self.sockets.is_empty() {
+        if self.sockets.is_empty()
+            && self.worker_sockets.is_empty()
+            && self.udp_sockets.is_empty()
+        {
             panic!("Server should have at least one bound socket");
         } else {
+            #[cfg(unix)]
+            {
+                if self.processes > 0 {
+                    if let Some((idx, ctl)) = process::worker() {
+                        self.run_worker_process(idx, ctl);
+                    }
+                    return self.run_processes();
+                }
+            }
+
             info!("Starting {} workers", self.threads);
 
             // start workers
-            let mut workers = Vec::new();
+            let mut warmup = Vec::new();
             for idx in 0..self.threads {
-                let worker = self.start_worker(idx, self.accept.get_notify());
-                workers.push(worker.clone());
+                let worker = if self.warmup.is_some() {
+                    let (tx, rx) = oneshot::channel();
+                    warmup.push(rx);
+                    self.start_worker(idx, self.accept.get_notify(), Some(tx))
+                } else {
+                    self.start_worker(idx, self.accept.get_notify(), None)
+                };
                 self.workers.push((idx, worker));
             }
+            self.server.2.set_workers(self.workers.len());
 
-            // start accept thread
-            for sock in &self.sockets {
-                info!("Starting \"{}\" service on {}", sock.1, sock.2);
+            if self.warmup.is_some() {
+                // start accept thread after all workers complete warm-up
+                let srv = self.server.clone();
+                let fut = wait_warmup(warmup, self.warmup_timeout);
+                spawn(async move {
+                    srv.warmup_completed(fut.await);
+                });
+            } else {
+                self.start_accept();
             }
-            self.accept.start(
-                mem::replace(&mut self.sockets, Vec::new())
-                    .into_iter()
-                    .map(|t| (t.0, t.2))
-                    .collect(),
-                workers,
-            );
 
             // handle signals
             if !self.no_signals {
@@ -298,22 +691,119 @@ impl ServerBuilder {
         }
     }
 
-    fn start_worker(&self, idx: usize, notify: AcceptNotify) -> WorkerClient {
+    fn start_worker(
+        &mut self,
+        idx: usize,
+        notify: AcceptNotify,
+        ready: Option<oneshot::Sender<bool>>,
+    ) -> WorkerClient {
         let avail = WorkerAvailability::new(notify);
         let services: Vec<Box<dyn InternalServiceFactory>> =
             self.services.iter().map(|v| v.clone_factory()).collect();
+        let warmup = self.warmup.as_ref().map(|w| (w.as_ref().clone(), ready));
 
-        Worker::start(idx, services, avail, self.shutdown_timeout)
+        // first worker uses bound listener, others bind their own
+        let backlog = self.backlog;
+        let opts = self.opts;
+        let listeners = self
+            .worker_sockets
+            .iter_mut()
+            .filter_map(|sock| {
+                let lst = match sock.lst.take() {
+                    Some(lst) => Ok(lst),
+                    None => create_tcp_listener(sock.addr, backlog, opts),
+                };
+                match lst {
+                    Ok(lst) => Some((sock.token, lst)),
+                    Err(e) => {
+                        error!(
+                            "Can not bind \"{}\" service on {} for worker {}: {}",
+                            sock.name, sock.addr, idx, e
+                        );
+                        None
+                    }
+                }
+            })
+            .collect();
+
+        let udp_sockets = self
+            .udp_sockets
+            .iter_mut()
+            .filter_map(|sock| {
+                let res = match sock.sock.take() {
+                    Some(s) => Ok(s),
+                    #[cfg(unix)]
+                    None => create_udp_socket(sock.addr, true),
+                    #[cfg(not(unix))]
+                    None => return None,
+                };
+                match res {
+                    Ok(s) => Some((sock.factory.clone_factory(), s)),
+                    Err(e) => {
+                        error!(
+                            "Can not bind \"{}\" udp service on {} for worker {}: {}",
+                            sock.name, sock.addr, idx, e
+                        );
+                        None
+                    }
+                }
+            })
+            .collect();
+
+        Worker::start(
+            idx,
+            services,
+            avail,
+            self.shutdown_timeout,
+            warmup,
+            listeners,
+            udp_sockets,
+            self.cpu_affinity,
+            self.server.clone(),
+        )
+    }
+
+    fn start_accept(&mut self) {
+        for sock in &self.sockets {
+            info!("Starting \"{}\" service on {}", sock.1, sock.2);
+        }
+        for sock in &self.worker_sockets {
+            info!(
+                "Starting \"{}\" service on {} with per-worker listeners",
+                sock.name, sock.addr
+            );
+        }
+        for sock in &self.udp_sockets {
+            info!("Starting \"{}\" udp service on {}", sock.name, sock.addr);
+        }
+        self.accept.start(
+            mem::replace(&mut self.sockets, Vec::new())
+                .into_iter()
+                .map(|t| (t.0, t.2))
+                .collect(),
+            self.workers.iter().map(|w| w.1.clone()).collect(),
+            self.balance
+                .take()
+                .unwrap_or_else(|| Box::new(RoundRobin::default())),
+        );
     }
 
     fn handle_cmd(&mut self, item: ServerCommand) {
         match item {
             ServerCommand::Pause(tx) => {
                 self.accept.send(Command::Pause);
+                #[cfg(unix)]
+                for child in &mut self.children {
+                    child.pause();
+                }
                 let _ = tx.send(());
             }
             ServerCommand::Resume(tx) => {
                 self.accept.send(Command::Resume);
+                #[cfg(unix)]
+                for child in &mut self.children {
+                    child.resume();
+                }
                 let _ = tx.send(());
             }
             ServerCommand::Signal(sig) => {
@@ -350,11 +840,61 @@ impl ServerBuilder {
             ServerCommand::Notify(tx) => {
                 self.notify.push(tx);
             }
+            ServerCommand::Warmup(res) => {
+                if self.stopping {
+                    // server is stopped during warm-up, accept loop must not start
+                    return;
+                }
+                if res {
+                    info!("Workers warm-up completed");
+                    self.start_accept();
+                } else if self.warmup_policy == WarmupPolicy::Continue {
+                    error!("Workers warm-up failed, starting anyway");
+                    self.start_accept();
+                } else {
+                    error!("Workers warm-up failed, stopping");
+                    self.handle_cmd(ServerCommand::Stop {
+                        graceful: false,
+                        completion: None,
+                    })
+                }
+            }
+            ServerCommand::WorkerWarmup(idx, res) => {
+                if self.stopping {
+                    return;
+                }
+                let pos = match self.workers.iter().position(|w| w.0 == idx) {
+                    Some(pos) => pos,
+                    None => return,
+                };
+                if res || self.warmup_policy == WarmupPolicy::Continue {
+                    if !res {
+                        error!("Worker {:?} warm-up failed, starting anyway", idx);
+                    }
+                    self.accept
+                        .send(Command::Worker(self.workers[pos].1.clone()));
+                } else {
+                    error!("Worker {:?} warm-up failed, stopping worker", idx);
+                    let (_, worker) = self.workers.swap_remove(pos);
+                    drop(worker.stop(false));
+                    self.server.2.set_workers(self.workers.len());
+                    self.worker_faulted(idx);
+                }
+            }
             ServerCommand::Stop {
                 graceful,
                 completion,
             } => {
                 let exit = self.exit;
+                self.stopping = true;
+
+                #[cfg(unix)]
+                {
+                    if self.processes > 0 {
+                        self.stop_processes(graceful, completion);
+                        return;
+                    }
+                }
 
                 // stop accept thread
                 self.accept.send(Command::Stop);
@@ -421,24 +961,254 @@ impl ServerBuilder {
                 }
 
                 if found {
+                    self.server.2.set_workers(self.workers.len());
+                    self.worker_faulted(idx);
+                }
+            }
+            #[cfg(unix)]
+            ServerCommand::ProcessExited(idx) => {
+                if let Some(pos) = self.children.iter().position(|p| p.idx() == idx) {
+                    self.children.swap_remove(pos);
+                    self.server.2.set_workers(self.children.len());
+                    if !self.stopping {
+                        self.worker_faulted(idx);
+                    } else if self.children.is_empty() {
+                        self.processes_stopped();
+                    }
+                }
+            }
+            #[cfg(unix)]
+            ServerCommand::ProcessKill => {
+                for child in &self.children {
+                    child.kill();
+                }
+            }
+            ServerCommand::WorkerRestart => {
+                if !self.stopping {
+                    self.restart_worker();
+                }
+            }
+        }
+    }
+
+    fn worker_faulted(&mut self, idx: usize) {
+        let metrics = &self.server.2;
+        metrics.fault();
+        if self.stopping {
+            return;
+        }
+
+        // restarts within restart window
+        let now = Instant::now();
+        while let Some(time) = self.restarts.front() {
+            if now.duration_since(*time) > self.restart_policy.window {
+                self.restarts.pop_front();
+            } else {
+                break;
+            }
+        }
+
+        match self.restart_policy.delay(self.restarts.len()) {
+            None => {
+                error!("Worker has died {:?}, restart limit is reached", idx);
+                metrics.abandon();
+            }
+            Some(delay) => {
+                self.restarts.push_back(now);
+                if delay == Duration::from_secs(0) {
                     error!("Worker has died {:?}, restarting", idx);
+                    self.restart_worker();
+                } else {
+                    error!("Worker has died {:?}, restarting in {:?}", idx, delay);
+                    let srv = self.server.clone();
+                    spawn(async move {
+                        delay_for(delay).await;
+                        srv.worker_restart();
+                    });
+                }
+            }
+        }
+    }
 
-                    let mut new_idx = self.workers.len();
-                    'found: loop {
-                        for i in 0..self.workers.len() {
-                            if self.workers[i].0 == new_idx {
-                                new_idx += 1;
-                                continue 'found;
-                            }
-                        }
-                        break;
-                    }
+    fn restart_worker(&mut self) {
+        #[cfg(unix)]
+        {
+            if self.processes > 0 {
+                let idx = (0..)
+                    .find(|idx| self.children.iter().all(|p| p.idx() != *idx))
+                    .unwrap();
+                if self.start_process(idx) {
+                    self.server.2.restart();
+                }
+                self.server.2.set_workers(self.children.len());
+                return;
+            }
+        }
 
-                    let worker = self.start_worker(new_idx, self.accept.get_notify());
-                    self.workers.push((new_idx, worker.clone()));
-                    self.accept.send(Command::Worker(worker));
+        let mut new_idx = self.workers.len();
+        'found: loop {
+            for i in 0..self.workers.len() {
+                if self.workers[i].0 == new_idx {
+                    new_idx += 1;
+                    continue 'found;
                 }
             }
+            break;
+        }
+
+        if self.warmup.is_some() {
+            // worker is added to accept loop after warm-up
+            let (tx, rx) = oneshot::channel();
+            let worker = self.start_worker(new_idx, self.accept.get_notify(), Some(tx));
+            self.workers.push((new_idx, worker));
+
+            let srv = self.server.clone();
+            let fut = wait_warmup(vec![rx], self.warmup_timeout);
+            spawn(async move {
+                srv.worker_warmup_completed(new_idx, fut.await);
+            });
+        } else {
+            let worker = self.start_worker(new_idx, self.accept.get_notify(), None);
+            self.workers.push((new_idx, worker.clone()));
+            self.accept.send(Command::Worker(worker));
+        }
+
+        self.server.2.restart();
+        self.server.2.set_workers(self.workers.len());
+    }
+}
+
+/// Wait for workers warm-up, returns `false` if any worker fails
+async fn wait_warmup(rx: Vec<oneshot::Receiver<bool>>, timeout: Duration) -> bool {
+    match select(join_all(rx), delay_for(timeout)).await {
+        Either::Left((res, _)) => res.into_iter().all(|res| res.unwrap_or(false)),
+        Either::Right(_) => {
+            error!("Workers warm-up timeout");
+            false
+        }
+    }
+}
+
+#[cfg(unix)]
+impl ServerBuilder {
+    fn run_processes(mut self) -> Server {
+        info!("Starting {} worker processes", self.processes);
+
+        for idx in 0..self.processes {
+            self.start_process(idx);
+        }
+        self.server.2.set_workers(self.children.len());
+
+        // handle signals
+        if !self.no_signals {
+            Signals::start(self.server.clone()).unwrap();
+        }
+
+        // start master process actor
+        let server = self.server.clone();
+        spawn(self);
+        server
+    }
+
+    fn start_process(&mut self, idx: usize) -> bool {
+        let listeners: Vec<_> = self
+            .sockets
+            .iter()
+            .map(|(_, _, lst)| process::Listener::from(lst))
+            .chain(self.worker_sockets.iter().filter_map(|sock| {
+                sock.lst
+                    .as_ref()
+                    .map(|lst| process::Listener::Tcp(lst.as_raw_fd()))
+            }))
+            .chain(self.udp_sockets.iter().filter_map(|sock| {
+                sock.sock
+                    .as_ref()
+                    .map(|sock| process::Listener::Udp(sock.as_raw_fd()))
+            }))
+            .collect();
+        let args = self.process_args.as_deref();
+
+        match process::spawn(idx, self.server.clone(), args, &listeners) {
+            Ok(child) => {
+                info!("Worker process {} started", idx);
+                self.children.push(child);
+                true
+            }
+            Err(e) => {
+                error!("Can not start worker process {}: {}", idx, e);
+                false
+            }
+        }
+    }
+
+    /// Run regular server in worker process, exit once server stops
+    fn run_worker_process(mut self, idx: usize, ctl: File) -> ! {
+        self.processes = 0;
+        self.children.clear();
+        self.restarts.clear();
+        self.exit = true;
+        self.no_signals = true;
+
+        // current thread may run application's system, server runs
+        // in new system
+        let name = format!("ntex-process:{}", idx);
+        let res = thread::Builder::new().name(name.clone()).spawn(move || {
+            System::builder().name(name).run(move || {
+                let srv = self.run();
+                if let Err(e) = process::init_child(ctl, srv) {
+                    error!("Can not start worker process control: {}", e);
+                    System::current().stop_with_code(1);
+                }
+            })
+        });
+        let code = match res.map(|h| h.join()) {
+            Ok(Ok(Ok(_))) => 0,
+            _ => 1,
+        };
+        std::process::exit(code)
+    }
+
+    fn stop_processes(
+        &mut self,
+        graceful: bool,
+        completion: Option<oneshot::Sender<()>>,
+    ) {
+        if let Some(tx) = completion {
+            self.notify.push(tx);
+        }
+        if self.children.is_empty() {
+            self.processes_stopped();
+            return;
+        }
+
+        for child in &mut self.children {
+            child.stop(graceful);
+        }
+
+        // kill worker processes that did not stop in time
+        let timeout = if graceful {
+            self.shutdown_timeout + Duration::from_secs(1)
+        } else {
+            Duration::from_secs(1)
+        };
+        let srv = self.server.clone();
+        spawn(async move {
+            delay_for(timeout).await;
+            srv.process_kill();
+        });
+    }
+
+    fn processes_stopped(&mut self) {
+        for tx in mem::take(&mut self.notify) {
+            let _ = tx.send(());
+        }
+        if self.exit {
+            spawn(
+                delay_until(Instant::now() + Duration::from_millis(300)).then(|_| {
+                    System::current().stop();
+                    ready(())
+                }),
+            );
         }
     }
 }
@@ -458,15 +1228,25 @@ impl Future for ServerBuilder {
     }
 }
 
-pub(super) fn bind_addr<S: net::ToSocketAddrs>(
+pub(crate) fn bind_addr<S: net::ToSocketAddrs>(
     addr: S,
     backlog: i32,
+    opts: SocketOpts,
 ) -> io::Result<Vec<net::TcpListener>> {
     let mut err = None;
     let mut succ = false;
     let mut sockets = Vec::new();
     for addr in addr.to_socket_addrs()? {
-        match create_tcp_listener(addr, backlog) {
+        // worker process uses listeners bound by master process
+        #[cfg(unix)]
+        let res = match process::inherited_tcp(addr) {
+            Some(lst) => Ok(lst),
+            None => create_tcp_listener(addr, backlog, opts),
+        };
+        #[cfg(not(unix))]
+        let res = create_tcp_listener(addr, backlog, opts);
+
+        match res {
             Ok(lst) => {
                 succ = true;
                 sockets.push(lst);
@@ -492,13 +1272,45 @@ pub(super) fn bind_addr<S: net::ToSocketAddrs>(
 pub(crate) fn create_tcp_listener(
     addr: net::SocketAddr,
     backlog: i32,
+    opts: SocketOpts,
 ) -> io::Result<net::TcpListener> {
-    let builder = match addr {
-        net::SocketAddr::V4(_) => Socket::new(Domain::ipv4(), Type::stream(), None)?,
-        net::SocketAddr::V6(_) => Socket::new(Domain::ipv6(), Type::stream(), None)?,
-    };
+    let builder = tcp_socket(&addr, opts.mptcp)?;
     builder.set_reuse_address(true)?;
+    if opts.reuse_port {
+        #[cfg(unix)]
+        builder.set_reuse_port(true)?;
+        #[cfg(not(unix))]
+        return Err(io::Error::new(
+            io::ErrorKind::Other,
+            "SO_REUSEPORT is not supported",
+        ));
+    }
     builder.bind(&SockAddr::from(addr))?;
     builder.listen(backlog)?;
+    if let Some(qlen) = opts.fastopen {
+        set_fastopen(&builder, qlen);
+    }
     Ok(builder.into_tcp_listener())
 }
+
+pub(super) fn create_udp_socket(
+    addr: net::SocketAddr,
+    reuse_port: bool,
+) -> io::Result<net::UdpSocket> {
+    let builder = match addr {
+        net::SocketAddr::V4(_) => Socket::new(Domain::ipv4(), Type::dgram(), None)?,
+        net::SocketAddr::V6(_) => Socket::new(Domain::ipv6(), Type::dgram(), None)?,
+    };
+    builder.set_reuse_address(true)?;
+    if reuse_port {
+        #[cfg(unix)]
+        builder.set_reuse_port(true)?;
+        #[cfg(not(unix))]
+        return Err(io::Error::new(
+            io::ErrorKind::Other,
+            "SO_REUSEPORT is not supported",
+        ));
+    }
+    builder.bind(&SockAddr::from(addr))?;
+    Ok(builder.into_udp_socket())
+}
@@ -0,0 +1,210 @@
+//! Container resource limits
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Number of cpus available to the process.
+///
+/// On linux cgroup v1 and v2 cpu quota is taken into account, quota is
+/// rounded up to whole cpus. Otherwise it is number of logical cpus.
+pub fn available_cpus() -> usize {
+    let cpus = num_cpus::get();
+    match cpu_quota() {
+        Some(quota) => std::cmp::max(1, std::cmp::min(cpus, quota.ceil() as usize)),
+        None => cpus,
+    }
+}
+
+/// Memory limit of the process in bytes.
+///
+/// Returns limit set by linux cgroup v1 or v2, `None` if memory is not
+/// limited.
+pub fn memory_limit() -> Option<u64> {
+    #[cfg(target_os = "linux")]
+    {
+        if let Some(limit) =
+            linux::read("memory", "memory.limit_in_bytes", parse_memory_max)
+        {
+            return limit;
+        }
+        linux::read_v2("memory.max", parse_memory_max).and_then(|v| v)
+    }
+    #[cfg(not(target_os = "linux"))]
+    {
+        None
+    }
+}
+
+const POOL_SIZE: usize = 128;
+const POOL_MIN_SIZE: usize = 16;
+const POOL_ITEM_MEMORY: u64 = 4 * 1024 * 1024;
+
+static POOL: AtomicUsize = AtomicUsize::new(0);
+
+/// Per-thread capacity of message pools.
+///
+/// Pools are reduced if process memory is limited, full size is used
+/// with at least 512Mb of memory.
+pub(crate) fn pool_size() -> usize {
+    let size = POOL.load(Ordering::Relaxed);
+    if size != 0 {
+        return size;
+    }
+
+    let size = match memory_limit() {
+        Some(limit) => std::cmp::max(
+            POOL_MIN_SIZE,
+            std::cmp::min(POOL_SIZE as u64, limit / POOL_ITEM_MEMORY) as usize,
+        ),
+        None => POOL_SIZE,
+    };
+    POOL.store(size, Ordering::Relaxed);
+    size
+}
+
+fn cpu_quota() -> Option<f64> {
+    #[cfg(target_os = "linux")]
+    {
+        if let Some(quota) =
+            linux::read("cpu", "cpu.cfs_quota_us", |s| Some(s.to_string()))
+        {
+            let period =
+                linux::read("cpu", "cpu.cfs_period_us", |s| Some(s.to_string()));
+            return match (quota, period) {
+                (Some(quota), Some(Some(period))) => parse_cfs_quota(&quota, &period),
+                _ => None,
+            };
+        }
+        linux::read_v2("cpu.max", parse_cpu_max).and_then(|v| v)
+    }
+    #[cfg(not(target_os = "linux"))]
+    {
+        None
+    }
+}
+
+/// Parse cgroup v2 `cpu.max`, "$MAX $PERIOD"
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+fn parse_cpu_max(s: &str) -> Option<f64> {
+    let mut parts = s.split_whitespace();
+    let quota = parts.next()?;
+    let period = parts.next().unwrap_or("100000");
+    if quota == "max" {
+        None
+    } else {
+        parse_cfs_quota(quota, period)
+    }
+}
+
+/// Parse cgroup v1 `cpu.cfs_quota_us` and `cpu.cfs_period_us`
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+fn parse_cfs_quota(quota: &str, period: &str) -> Option<f64> {
+    let quota = quota.trim().parse::<i64>().ok()?;
+    let period = period.trim().parse::<i64>().ok()?;
+    if quota <= 0 || period <= 0 {
+        None
+    } else {
+        Some(quota as f64 / period as f64)
+    }
+}
+
+/// Parse cgroup v2 `memory.max` or v1 `memory.limit_in_bytes`
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+fn parse_memory_max(s: &str) -> Option<u64> {
+    let s = s.trim();
+    if s == "max" {
+        return None;
+    }
+    match s.parse::<u64>() {
+        // v1 reports unlimited memory as huge page aligned i64::MAX
+        Ok(limit) if limit < (1 << 60) => Some(limit),
+        _ => None,
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod linux {
+    use std::fs;
+
+    const ROOT: &str = "/sys/fs/cgroup";
+
+    /// Read cgroup v1 controller file, returns `None` if process is not
+    /// in v1 hierarchy for the controller
+    pub(super) fn read<F, R>(controller: &str, file: &str, f: F) -> Option<Option<R>>
+    where
+        F: Fn(&str) -> Option<R>,
+    {
+        let cgroups = fs::read_to_string("/proc/self/cgroup").ok()?;
+        for line in cgroups.lines() {
+            let mut parts = line.splitn(3, ':');
+            let (id, controllers, path) = (parts.next()?, parts.next()?, parts.next()?);
+            if id == "0" || !controllers.split(',').any(|c| c == controller) {
+                continue;
+            }
+
+            // path is relative to namespace root inside containers
+            for dir in &[controllers, controller] {
+                for path in &[path, "/"] {
+                    let name = format!("{}/{}{}/{}", ROOT, dir, path, file);
+                    if let Ok(content) = fs::read_to_string(&name) {
+                        return Some(f(&content));
+                    }
+                }
+            }
+            return Some(None);
+        }
+        None
+    }
+
+    /// Read cgroup v2 unified hierarchy file
+    pub(super) fn read_v2<F, R>(file: &str, f: F) -> Option<Option<R>>
+    where
+        F: Fn(&str) -> Option<R>,
+    {
+        let cgroups = fs::read_to_string("/proc/self/cgroup").ok()?;
+        let path = cgroups
+            .lines()
+            .find(|line| line.starts_with("0::"))
+            .map(|line| &line[3..])?;
+
+        for path in &[path, "/"] {
+            let name = format!("{}{}/{}", ROOT, path, file);
+            if let Ok(content) = fs::read_to_string(&name) {
+                return Some(f(&content));
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cpu_max() {
+        assert_eq!(parse_cpu_max("max 100000\n"), None);
+        assert_eq!(parse_cpu_max("200000 100000\n"), Some(2.0));
+        assert_eq!(parse_cpu_max("50000 100000"), Some(0.5));
+        assert_eq!(parse_cpu_max(""), None);
+        assert_eq!(parse_cfs_quota("-1\n", "100000\n"), None);
+        assert_eq!(parse_cfs_quota("150000\n", "100000\n"), Some(1.5));
+        assert_eq!(parse_cfs_quota("abc", "100000"), None);
+    }
+
+    #[test]
+    fn test_memory_max() {
+        assert_eq!(parse_memory_max("max\n"), None);
+        assert_eq!(parse_memory_max("536870912\n"), Some(536_870_912));
+        assert_eq!(parse_memory_max("9223372036854771712\n"), None);
+        assert_eq!(parse_memory_max("-"), None);
+    }
+
+    #[test]
+    fn test_available() {
+        let cpus = available_cpus();
+        assert!((1..=num_cpus::get()).contains(&cpus));
+
+        let size = pool_size();
+        assert!((POOL_MIN_SIZE..=POOL_SIZE).contains(&size));
+        assert_eq!(pool_size(), size);
+    }
+}
@@ -12,6 +12,7 @@ use super::builder::bind_addr;
 use super::service::{
     BoxedServerService, InternalServiceFactory, ServerMessage, StreamService,
 };
+use super::sockopt::SocketOpts;
 use super::Token;
 
 pub struct ServiceConfig {
@@ -44,7 +45,7 @@ impl ServiceConfig {
     where
         U: net::ToSocketAddrs,
     {
-        let sockets = bind_addr(addr, self.backlog)?;
+        let sockets = bind_addr(addr, self.backlog, SocketOpts::default())?;
 
         for lst in sockets {
             self.listen(name.as_ref(), lst);
@@ -0,0 +1,106 @@
+//! TLS key material logging
+use std::fmt::Write as _;
+use std::fs::OpenOptions;
+use std::io::{self, Write};
+use std::sync::{Arc, Mutex};
+use std::{env, fmt};
+
+/// `SSLKEYLOGFILE` compatible key log sink.
+///
+/// Sink writes TLS secrets in NSS key log format, so captured traffic
+/// could be decrypted with Wireshark. Use `set_keylog()` function for
+/// openssl acceptor and set `ServerConfig::key_log` for rustls acceptor.
+///
+/// **Key log allows to decrypt all logged sessions, do not use it in
+/// production.**
+#[derive(Clone)]
+pub struct KeyLogSink(Arc<Mutex<Box<dyn Write + Send>>>);
+
+impl KeyLogSink {
+    /// Create sink that writes key log to `writer`
+    pub fn new<W: Write + Send + 'static>(writer: W) -> Self {
+        KeyLogSink(Arc::new(Mutex::new(Box::new(writer))))
+    }
+
+    /// Create sink for file that is set with `SSLKEYLOGFILE` env variable.
+    ///
+    /// Returns `None` if variable is not set.
+    pub fn from_env() -> io::Result<Option<Self>> {
+        match env::var_os("SSLKEYLOGFILE") {
+            Some(path) => Ok(Some(Self::file(path)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Create sink that appends key log to file
+    pub fn file<P: AsRef<std::path::Path>>(path: P) -> io::Result<Self> {
+        let file = OpenOptions::new().append(true).create(true).open(path)?;
+        Ok(Self::new(file))
+    }
+
+    /// Write formatted key log line
+    pub fn write_line(&self, line: &str) {
+        let mut writer = self.0.lock().unwrap();
+        if let Err(e) = writer
+            .write_all(line.as_bytes())
+            .and_then(|_| writer.write_all(b"\n"))
+            .and_then(|_| writer.flush())
+        {
+            log::warn!("Can not write TLS key log: {}", e);
+        }
+    }
+
+    /// Write secret in NSS key log format
+    pub fn log(&self, label: &str, client_random: &[u8], secret: &[u8]) {
+        let mut line = String::with_capacity(
+            label.len() + (client_random.len() + secret.len()) * 2 + 2,
+        );
+        line.push_str(label);
+        line.push(' ');
+        for b in client_random {
+            let _ = write!(line, "{:02x}", b);
+        }
+        line.push(' ');
+        for b in secret {
+            let _ = write!(line, "{:02x}", b);
+        }
+        self.write_line(&line)
+    }
+}
+
+impl fmt::Debug for KeyLogSink {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("KeyLogSink").finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Clone, Default)]
+    struct Buffer(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Buffer {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_keylog() {
+        let buf = Buffer::default();
+        let sink = KeyLogSink::new(buf.clone());
+        sink.log("CLIENT_RANDOM", &[0x01, 0xab], &[0xff, 0x00, 0x10]);
+        sink.write_line("SERVER_TRAFFIC_SECRET_0 00 11");
+
+        assert_eq!(
+            String::from_utf8(buf.0.lock().unwrap().clone()).unwrap(),
+            "CLIENT_RANDOM 01ab ff0010\nSERVER_TRAFFIC_SECRET_0 00 11\n"
+        );
+    }
+}
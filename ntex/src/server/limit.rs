@@ -0,0 +1,86 @@
+use std::cell::{Cell, RefCell};
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use crate::rt::time::{delay_until, Delay, Instant};
+
+/// Handshake rate limiter.
+///
+/// Allows up to `rate` handshakes per second, handshakes could be started
+/// in bursts of up to `rate` handshakes.
+pub(super) struct RateLimiter {
+    interval: Duration,
+    tolerance: Duration,
+    tat: Cell<Instant>,
+    delay: RefCell<Option<Delay>>,
+}
+
+impl RateLimiter {
+    pub(super) fn new(rate: u32) -> Self {
+        let interval = Duration::from_secs(1) / rate.max(1);
+        RateLimiter {
+            interval,
+            tolerance: Duration::from_secs(1) - interval,
+            tat: Cell::new(Instant::now()),
+            delay: RefCell::new(None),
+        }
+    }
+
+    /// Check if new handshake could be started. If rate is exceeded
+    /// it registers notification for current task.
+    pub(super) fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<()> {
+        let tat = self.tat.get();
+        if tat <= Instant::now() + self.tolerance {
+            return Poll::Ready(());
+        }
+        let deadline = tat - self.tolerance;
+
+        let mut delay = self.delay.borrow_mut();
+        match *delay {
+            Some(ref mut d) if d.deadline() == deadline => (),
+            _ => *delay = Some(delay_until(deadline)),
+        }
+        match Pin::new(delay.as_mut().unwrap()).poll(cx) {
+            Poll::Ready(_) => {
+                *delay = None;
+                Poll::Ready(())
+            }
+            Poll::Pending => Poll::Pending,
+        }
+    }
+
+    /// Register started handshake
+    pub(super) fn acquire(&self) {
+        let now = Instant::now();
+        let tat = self.tat.get();
+        self.tat
+            .set(if tat > now { tat } else { now } + self.interval);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::future::poll_fn;
+
+    #[ntex_rt::test]
+    async fn test_rate_limiter() {
+        let limiter = RateLimiter::new(10);
+
+        for _ in 0..10 {
+            assert!(poll_fn(|cx| Poll::Ready(limiter.poll_ready(cx)))
+                .await
+                .is_ready());
+            limiter.acquire();
+        }
+        assert!(poll_fn(|cx| Poll::Ready(limiter.poll_ready(cx)))
+            .await
+            .is_pending());
+
+        let start = Instant::now();
+        poll_fn(|cx| limiter.poll_ready(cx)).await;
+        assert!(Instant::now() - start >= Duration::from_millis(50));
+    }
+}
@@ -1,10 +1,11 @@
-//! General purpose tcp server
+//! General purpose tcp and udp server
 #![allow(clippy::type_complexity)]
 use std::error::Error;
 use std::future::Future;
 use std::io;
 use std::pin::Pin;
 use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
 use std::task::{Context, Poll};
 
 use futures::channel::mpsc::UnboundedSender;
@@ -14,12 +15,25 @@ use futures::FutureExt;
 use crate::util::counter::Counter;
 
 mod accept;
+pub mod balance;
 mod builder;
+mod cgroup;
 mod config;
+#[cfg(any(feature = "openssl", feature = "rustls"))]
+mod keylog;
+#[cfg(any(feature = "openssl", feature = "rustls"))]
+mod limit;
+#[cfg(any(feature = "openssl", feature = "rustls"))]
+mod ocsp;
+#[cfg(unix)]
+mod process;
+pub mod proxy;
 mod service;
 mod signals;
 mod socket;
+mod sockopt;
 mod test;
+pub mod udp;
 mod worker;
 
 #[cfg(feature = "openssl")]
@@ -28,10 +42,17 @@ pub mod openssl;
 #[cfg(feature = "rustls")]
 pub mod rustls;
 
-pub(crate) use self::builder::create_tcp_listener;
-pub use self::builder::ServerBuilder;
+pub(crate) use self::builder::bind_addr;
+pub use self::builder::{RestartPolicy, ServerBuilder, WarmupPolicy};
+pub(crate) use self::cgroup::pool_size;
+pub use self::cgroup::{available_cpus, memory_limit};
 pub use self::config::{ServiceConfig, ServiceRuntime};
+#[cfg(any(feature = "openssl", feature = "rustls"))]
+pub use self::keylog::KeyLogSink;
+#[cfg(any(feature = "openssl", feature = "rustls"))]
+pub use self::ocsp::OcspResponse;
 pub use self::service::StreamServiceFactory;
+pub(crate) use self::sockopt::{set_fastopen_connect, tcp_socket, SocketOpts};
 pub use self::test::{build_test_server, test_server, TestServer};
 
 #[doc(hidden)]
@@ -68,6 +89,10 @@ pub fn max_concurrent_ssl_accept(num: usize) {
 pub(self) const ZERO: std::time::Duration = std::time::Duration::from_millis(0);
 pub(self) static MAX_CONN: AtomicUsize = AtomicUsize::new(256);
 
+/// Handshake failure hook
+#[cfg(any(feature = "openssl", feature = "rustls"))]
+pub(self) type HandshakeErrorHook = std::sync::Arc<dyn Fn(&dyn Error) + Send + Sync>;
+
 thread_local! {
     static MAX_CONN_COUNTER: Counter = Counter::new(MAX_CONN.load(Ordering::Relaxed));
 }
@@ -82,6 +107,18 @@ pub enum SslError<E> {
 #[derive(Debug)]
 enum ServerCommand {
     WorkerFaulted(usize),
+    /// Restart faulted worker after backoff delay
+    WorkerRestart,
+    /// Worker process exited
+    #[cfg(unix)]
+    ProcessExited(usize),
+    /// Kill worker processes that did not stop within shutdown timeout
+    #[cfg(unix)]
+    ProcessKill,
+    /// Workers warm-up is completed
+    Warmup(bool),
+    /// Restarted worker warm-up is completed
+    WorkerWarmup(usize, bool),
     Pause(oneshot::Sender<()>),
     Resume(oneshot::Sender<()>),
     Signal(signals::Signal),
@@ -94,16 +131,70 @@ enum ServerCommand {
     Notify(oneshot::Sender<()>),
 }
 
+/// Server metrics.
+///
+/// Metrics handle could be obtained with `Server::metrics()` method.
+#[derive(Clone, Debug, Default)]
+pub struct ServerMetrics(Arc<ServerMetricsInner>);
+
+#[derive(Debug, Default)]
+struct ServerMetricsInner {
+    workers: AtomicUsize,
+    faults: AtomicUsize,
+    restarts: AtomicUsize,
+    abandoned: AtomicUsize,
+}
+
+impl ServerMetrics {
+    /// Number of running workers
+    pub fn workers(&self) -> usize {
+        self.0.workers.load(Ordering::Relaxed)
+    }
+
+    /// Number of faulted workers
+    pub fn faults(&self) -> usize {
+        self.0.faults.load(Ordering::Relaxed)
+    }
+
+    /// Number of restarted workers
+    pub fn restarts(&self) -> usize {
+        self.0.restarts.load(Ordering::Relaxed)
+    }
+
+    /// Number of faulted workers that were not restarted because of
+    /// restart policy
+    pub fn abandoned(&self) -> usize {
+        self.0.abandoned.load(Ordering::Relaxed)
+    }
+
+    fn set_workers(&self, num: usize) {
+        self.0.workers.store(num, Ordering::Relaxed);
+    }
+
+    fn fault(&self) {
+        self.0.faults.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn restart(&self) {
+        self.0.restarts.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn abandon(&self) {
+        self.0.abandoned.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
 /// Server controller
 #[derive(Debug)]
 pub struct Server(
     UnboundedSender<ServerCommand>,
     Option<oneshot::Receiver<()>>,
+    ServerMetrics,
 );
 
 impl Server {
     fn new(tx: UnboundedSender<ServerCommand>) -> Self {
-        Server(tx, None)
+        Server(tx, None, ServerMetrics::default())
     }
 
     /// Start server building process
@@ -119,6 +210,35 @@ impl Server {
         let _ = self.0.unbounded_send(ServerCommand::WorkerFaulted(idx));
     }
 
+    fn worker_restart(&self) {
+        let _ = self.0.unbounded_send(ServerCommand::WorkerRestart);
+    }
+
+    #[cfg(unix)]
+    fn process_exited(&self, idx: usize) {
+        let _ = self.0.unbounded_send(ServerCommand::ProcessExited(idx));
+    }
+
+    #[cfg(unix)]
+    fn process_kill(&self) {
+        let _ = self.0.unbounded_send(ServerCommand::ProcessKill);
+    }
+
+    fn warmup_completed(&self, result: bool) {
+        let _ = self.0.unbounded_send(ServerCommand::Warmup(result));
+    }
+
+    fn worker_warmup_completed(&self, idx: usize, result: bool) {
+        let _ = self
+            .0
+            .unbounded_send(ServerCommand::WorkerWarmup(idx, result));
+    }
+
+    /// Server metrics handle
+    pub fn metrics(&self) -> ServerMetrics {
+        self.2.clone()
+    }
+
     /// Pause accepting incoming connections
     ///
     /// If socket contains some pending connection, they might be dropped.
@@ -151,7 +271,7 @@ impl Server {
 
 impl Clone for Server {
     fn clone(&self) -> Self {
-        Self(self.0.clone(), None)
+        Self(self.0.clone(), None, self.2.clone())
     }
 }
 
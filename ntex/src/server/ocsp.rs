@@ -0,0 +1,169 @@
+//! OCSP stapling support
+use std::fmt;
+use std::future::Future;
+use std::sync::{Arc, RwLock, Weak};
+use std::time::Duration;
+
+use crate::rt::{spawn, time::delay_for};
+
+/// Shared OCSP response.
+///
+/// Response is shared between all workers and could be updated at any
+/// time, new handshakes use updated response. Use `set_ocsp_stapling()`
+/// function for openssl acceptor and `OcspCertResolver` for rustls acceptor.
+///
+/// ```rust,ignore
+/// let ocsp = OcspResponse::new();
+/// ocsp.refresh(Duration::from_secs(3600), || async {
+///     fetch_ocsp_response().await
+/// });
+/// ```
+#[derive(Clone, Default)]
+pub struct OcspResponse(Arc<RwLock<Option<Vec<u8>>>>);
+
+impl OcspResponse {
+    /// Create empty OCSP response
+    pub fn new() -> Self {
+        OcspResponse::default()
+    }
+
+    /// Set DER encoded OCSP response
+    pub fn set(&self, der: Vec<u8>) {
+        *self.0.write().unwrap() = Some(der);
+    }
+
+    /// Remove OCSP response, handshakes continue without stapling
+    pub fn clear(&self) {
+        *self.0.write().unwrap() = None;
+    }
+
+    /// Get DER encoded OCSP response
+    pub fn get(&self) -> Option<Vec<u8>> {
+        self.0.read().unwrap().clone()
+    }
+
+    /// Refresh OCSP response in background.
+    ///
+    /// Function `f` get called immediately and then every `interval`.
+    /// If refresh fails, previous response is kept and refresh is retried
+    /// after one tenth of the interval. Refresh task stops when all copies
+    /// of the response are dropped.
+    ///
+    /// This method must be called within ntex runtime.
+    pub fn refresh<F, R, E>(&self, interval: Duration, f: F)
+    where
+        F: Fn() -> R + 'static,
+        R: Future<Output = Result<Vec<u8>, E>> + 'static,
+        E: fmt::Debug + 'static,
+    {
+        self.refresh_with(interval, f, delay_for)
+    }
+
+    /// Refresh OCSP response, `timer` waits for next refresh
+    fn refresh_with<F, R, E, T, D>(&self, interval: Duration, f: F, timer: T)
+    where
+        F: Fn() -> R + 'static,
+        R: Future<Output = Result<Vec<u8>, E>> + 'static,
+        E: fmt::Debug + 'static,
+        T: Fn(Duration) -> D + 'static,
+        D: Future<Output = ()> + 'static,
+    {
+        let response = Arc::downgrade(&self.0);
+        spawn(async move {
+            loop {
+                let res = f().await;
+                let inner = match Weak::upgrade(&response) {
+                    Some(inner) => inner,
+                    None => return,
+                };
+                let delay = match res {
+                    Ok(der) => {
+                        log::trace!("OCSP response has been refreshed");
+                        *inner.write().unwrap() = Some(der);
+                        interval
+                    }
+                    Err(e) => {
+                        log::error!("Can not refresh OCSP response: {:?}", e);
+                        interval / 10
+                    }
+                };
+                drop(inner);
+                timer(delay).await;
+            }
+        });
+    }
+}
+
+impl fmt::Debug for OcspResponse {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("OcspResponse")
+            .field("stapled", &self.0.read().unwrap().is_some())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::channel::{mpsc, oneshot};
+    use futures::StreamExt;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[ntex_rt::test]
+    async fn test_refresh() {
+        let ocsp = OcspResponse::new();
+        assert!(ocsp.get().is_none());
+
+        // refresh task requests next tick after each refresh
+        let (tx, mut ticks) = mpsc::unbounded();
+        let timer = move |delay| {
+            let (tick, rx) = oneshot::channel();
+            let _ = tx.unbounded_send((delay, tick));
+            async move {
+                let _ = rx.await;
+            }
+        };
+
+        let counter = Arc::new(AtomicUsize::new(0));
+        let counter2 = counter.clone();
+        ocsp.refresh_with(
+            Duration::from_millis(500),
+            move || {
+                let n = counter2.fetch_add(1, Ordering::Relaxed);
+                async move {
+                    if n == 1 {
+                        Err("responder is not available")
+                    } else {
+                        Ok(vec![n as u8])
+                    }
+                }
+            },
+            timer,
+        );
+
+        let (delay, tick) = ticks.next().await.unwrap();
+        assert_eq!(delay, Duration::from_millis(500));
+        assert_eq!(ocsp.get(), Some(vec![0]));
+
+        // failed refresh keeps previous response, retry is scheduled sooner
+        let _ = tick.send(());
+        let (delay, tick) = ticks.next().await.unwrap();
+        assert_eq!(delay, Duration::from_millis(50));
+        assert_eq!(counter.load(Ordering::Relaxed), 2);
+        assert_eq!(ocsp.get(), Some(vec![0]));
+
+        let _ = tick.send(());
+        let (delay, tick) = ticks.next().await.unwrap();
+        assert_eq!(delay, Duration::from_millis(500));
+        assert_eq!(ocsp.get(), Some(vec![2]));
+
+        ocsp.clear();
+        assert!(ocsp.get().is_none());
+
+        // refresh task stops when response is dropped
+        drop(ocsp);
+        let _ = tick.send(());
+        assert!(ticks.next().await.is_none());
+        assert_eq!(counter.load(Ordering::Relaxed), 4);
+    }
+}
@@ -2,10 +2,12 @@ use std::error::Error;
 use std::future::Future;
 use std::marker::PhantomData;
 use std::pin::Pin;
+use std::sync::Arc;
 use std::task::{Context, Poll};
 use std::time::Duration;
 use std::{fmt, io};
 
+pub use open_ssl::error::ErrorStack;
 pub use open_ssl::ssl::{AlpnError, SslAcceptor, SslAcceptorBuilder};
 pub use tokio_openssl::SslStream;
 
@@ -16,7 +18,34 @@ use crate::rt::time::{delay_for, Delay};
 use crate::service::{Service, ServiceFactory};
 use crate::util::counter::{Counter, CounterGuard};
 
-use super::{MAX_CONN_COUNTER, ZERO};
+use super::limit::RateLimiter;
+use super::{HandshakeErrorHook, KeyLogSink, OcspResponse, MAX_CONN_COUNTER, ZERO};
+
+/// Write TLS key material to key log sink.
+///
+/// **Key log allows to decrypt all logged sessions, do not use it in
+/// production.**
+pub fn set_keylog(builder: &mut SslAcceptorBuilder, sink: KeyLogSink) {
+    builder.set_keylog_callback(move |_, line| sink.write_line(line))
+}
+
+/// Configure OCSP stapling for openssl acceptor.
+///
+/// Stapled response is taken from `ocsp` for each handshake, handshake
+/// continues without stapling if response is not available.
+pub fn set_ocsp_stapling(
+    builder: &mut SslAcceptorBuilder,
+    ocsp: OcspResponse,
+) -> Result<(), ErrorStack> {
+    builder.set_status_callback(move |ssl| {
+        if let Some(der) = ocsp.get() {
+            ssl.set_ocsp_status(&der)?;
+            Ok(true)
+        } else {
+            Ok(false)
+        }
+    })
+}
 
 /// Support `TLS` server connections via openssl package
 ///
@@ -24,6 +53,9 @@ use super::{MAX_CONN_COUNTER, ZERO};
 pub struct Acceptor<T: AsyncRead + AsyncWrite> {
     acceptor: SslAcceptor,
     timeout: Duration,
+    max_handshakes: Option<usize>,
+    rate: Option<u32>,
+    on_error: Option<HandshakeErrorHook>,
     io: PhantomData<T>,
 }
 
@@ -33,6 +65,9 @@ impl<T: AsyncRead + AsyncWrite> Acceptor<T> {
         Acceptor {
             acceptor,
             timeout: Duration::from_secs(5),
+            max_handshakes: None,
+            rate: None,
+            on_error: None,
             io: PhantomData,
         }
     }
@@ -44,6 +79,36 @@ impl<T: AsyncRead + AsyncWrite> Acceptor<T> {
         self.timeout = Duration::from_millis(time);
         self
     }
+
+    /// Set max number of concurrent handshakes per worker.
+    ///
+    /// Worker stops accepting new connections for this acceptor when limit
+    /// is reached. By default limit is shared between all acceptors and is
+    /// set with `max_concurrent_ssl_accept()` function.
+    pub fn max_handshakes(mut self, num: usize) -> Self {
+        self.max_handshakes = Some(num);
+        self
+    }
+
+    /// Set max number of new handshakes per second per worker.
+    ///
+    /// Handshakes are started in bursts of up to `num` handshakes, after
+    /// that worker delays new connections. By default rate is not limited.
+    pub fn handshake_rate(mut self, num: u32) -> Self {
+        self.rate = Some(num);
+        self
+    }
+
+    /// Set handshake failure hook.
+    ///
+    /// Hook get called with the reason of failed or timed out handshake.
+    pub fn on_handshake_error<F>(mut self, f: F) -> Self
+    where
+        F: Fn(&dyn Error) + Send + Sync + 'static,
+    {
+        self.on_error = Some(Arc::new(f));
+        self
+    }
 }
 
 impl<T: AsyncRead + AsyncWrite> Clone for Acceptor<T> {
@@ -51,6 +116,9 @@ impl<T: AsyncRead + AsyncWrite> Clone for Acceptor<T> {
         Self {
             acceptor: self.acceptor.clone(),
             timeout: self.timeout,
+            max_handshakes: self.max_handshakes,
+            rate: self.rate,
+            on_error: self.on_error.clone(),
             io: PhantomData,
         }
     }
@@ -69,13 +137,18 @@ where
     type Future = Ready<Result<Self::Service, Self::InitError>>;
 
     fn new_service(&self, _: ()) -> Self::Future {
-        MAX_CONN_COUNTER.with(|conns| {
-            ok(AcceptorService {
-                acceptor: self.acceptor.clone(),
-                conns: conns.clone(),
-                timeout: self.timeout,
-                io: PhantomData,
-            })
+        let conns = match self.max_handshakes {
+            Some(num) => Counter::new(num),
+            None => MAX_CONN_COUNTER.with(|conns| conns.clone()),
+        };
+
+        ok(AcceptorService {
+            conns,
+            acceptor: self.acceptor.clone(),
+            timeout: self.timeout,
+            rate: self.rate.map(RateLimiter::new),
+            on_error: self.on_error.clone(),
+            io: PhantomData,
         })
     }
 }
@@ -84,6 +157,8 @@ pub struct AcceptorService<T> {
     acceptor: SslAcceptor,
     conns: Counter,
     timeout: Duration,
+    rate: Option<RateLimiter>,
+    on_error: Option<HandshakeErrorHook>,
     io: PhantomData<T>,
 }
 
@@ -98,6 +173,11 @@ where
 
     #[inline]
     fn poll_ready(&self, ctx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        if let Some(ref rate) = self.rate {
+            if rate.poll_ready(ctx).is_pending() {
+                return Poll::Pending;
+            }
+        }
         if self.conns.available(ctx) {
             Poll::Ready(Ok(()))
         } else {
@@ -107,9 +187,13 @@ where
 
     #[inline]
     fn call(&self, req: Self::Request) -> Self::Future {
+        if let Some(ref rate) = self.rate {
+            rate.acquire();
+        }
         let acc = self.acceptor.clone();
         AcceptorServiceResponse {
             _guard: self.conns.get(),
+            on_error: self.on_error.clone(),
             delay: if self.timeout == ZERO {
                 None
             } else {
@@ -133,26 +217,40 @@ where
 {
     fut: LocalBoxFuture<'static, Result<SslStream<T>, Box<dyn Error>>>,
     delay: Option<Delay>,
+    on_error: Option<HandshakeErrorHook>,
     _guard: CounterGuard,
 }
 
 impl<T: AsyncRead + AsyncWrite + Unpin> Future for AcceptorServiceResponse<T> {
     type Output = Result<SslStream<T>, Box<dyn Error>>;
 
-    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
-        if let Some(ref mut delay) = self.delay {
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.get_mut();
+
+        if let Some(ref mut delay) = this.delay {
             match Pin::new(delay).poll(cx) {
                 Poll::Pending => (),
                 Poll::Ready(_) => {
-                    return Poll::Ready(Err(Box::new(io::Error::new(
+                    let err: Box<dyn Error> = Box::new(io::Error::new(
                         io::ErrorKind::TimedOut,
                         "ssl handshake timeout",
-                    ))))
+                    ));
+                    if let Some(ref on_error) = this.on_error {
+                        on_error(err.as_ref());
+                    }
+                    return Poll::Ready(Err(err));
                 }
             }
         }
 
-        let io = futures::ready!(Pin::new(&mut self.fut).poll(cx))?;
-        Poll::Ready(Ok(io))
+        match futures::ready!(Pin::new(&mut this.fut).poll(cx)) {
+            Ok(io) => Poll::Ready(Ok(io)),
+            Err(e) => {
+                if let Some(ref on_error) = this.on_error {
+                    on_error(e.as_ref());
+                }
+                Poll::Ready(Err(e))
+            }
+        }
     }
 }
@@ -0,0 +1,368 @@
+//! Pre-fork worker processes
+use std::ffi::OsString;
+use std::fs::File;
+use std::io::{self, Read, Write};
+use std::mem::ManuallyDrop;
+use std::os::unix::io::{AsRawFd, FromRawFd, RawFd};
+use std::os::unix::net::UnixListener;
+use std::os::unix::process::{CommandExt, ExitStatusExt};
+use std::process::{Child, Command};
+use std::sync::Mutex;
+use std::{env, net, path::Path, thread};
+
+use super::socket::StdListener;
+use super::Server;
+
+const PAUSE: u8 = b'p';
+const RESUME: u8 = b'r';
+const STOP: u8 = b's';
+const STOP_GRACEFUL: u8 = b'g';
+
+/// Worker process index and control pipe
+const ENV_PROCESS: &str = "NTEX_PROCESS";
+/// Listeners inherited by worker process
+const ENV_LISTENERS: &str = "NTEX_PROCESS_LISTENERS";
+
+lazy_static::lazy_static! {
+    static ref INHERITED: Mutex<Inherited> = Mutex::new(Inherited::from_env());
+}
+
+/// Worker process handle.
+///
+/// Master process controls worker process with pipe, worker process
+/// stops gracefully if pipe gets closed.
+pub(super) struct Process {
+    idx: usize,
+    pid: libc::pid_t,
+    ctl: Option<File>,
+}
+
+impl Process {
+    pub(super) fn idx(&self) -> usize {
+        self.idx
+    }
+
+    pub(super) fn pause(&mut self) {
+        self.send(PAUSE)
+    }
+
+    pub(super) fn resume(&mut self) {
+        self.send(RESUME)
+    }
+
+    pub(super) fn stop(&mut self, graceful: bool) {
+        self.send(if graceful { STOP_GRACEFUL } else { STOP });
+        self.ctl.take();
+    }
+
+    pub(super) fn kill(&self) {
+        log::error!("Killing worker process {}, pid: {}", self.idx, self.pid);
+        unsafe {
+            libc::kill(self.pid, libc::SIGKILL);
+        }
+    }
+
+    fn send(&mut self, cmd: u8) {
+        if let Some(ref mut ctl) = self.ctl {
+            if let Err(e) = ctl.write_all(&[cmd]) {
+                log::error!(
+                    "Can not send command to worker process {}: {}",
+                    self.idx,
+                    e
+                );
+            }
+        }
+    }
+}
+
+/// Listener passed from master process to worker process
+#[derive(Copy, Clone)]
+pub(super) enum Listener {
+    Tcp(RawFd),
+    Uds(RawFd),
+    Udp(RawFd),
+}
+
+impl Listener {
+    fn fd(self) -> RawFd {
+        match self {
+            Listener::Tcp(fd) | Listener::Uds(fd) | Listener::Udp(fd) => fd,
+        }
+    }
+
+    fn encode(self) -> String {
+        match self {
+            Listener::Tcp(fd) => format!("t{}", fd),
+            Listener::Uds(fd) => format!("u{}", fd),
+            Listener::Udp(fd) => format!("d{}", fd),
+        }
+    }
+
+    fn decode(s: &str) -> Option<Self> {
+        if s.len() < 2 {
+            return None;
+        }
+        let fd = s[1..].parse().ok()?;
+        match &s[..1] {
+            "t" => Some(Listener::Tcp(fd)),
+            "u" => Some(Listener::Uds(fd)),
+            "d" => Some(Listener::Udp(fd)),
+            _ => None,
+        }
+    }
+}
+
+impl<'a> From<&'a StdListener> for Listener {
+    fn from(lst: &'a StdListener) -> Self {
+        match lst {
+            StdListener::Tcp(lst) => Listener::Tcp(lst.as_raw_fd()),
+            StdListener::Uds(lst) => Listener::Uds(lst.as_raw_fd()),
+        }
+    }
+}
+
+/// State passed from master process to worker process
+struct Inherited {
+    process: Option<(usize, RawFd)>,
+    listeners: Vec<Listener>,
+}
+
+impl Inherited {
+    fn from_env() -> Self {
+        let process = env::var(ENV_PROCESS).ok().and_then(|val| {
+            let mut parts = val.splitn(2, ':');
+            let idx = parts.next()?.parse().ok()?;
+            let ctl = parts.next()?.parse().ok()?;
+            Some((idx, ctl))
+        });
+        let listeners = if process.is_some() {
+            env::var(ENV_LISTENERS)
+                .unwrap_or_default()
+                .split(',')
+                .filter_map(Listener::decode)
+                .collect()
+        } else {
+            Vec::new()
+        };
+
+        // processes started by worker process are not workers
+        env::remove_var(ENV_PROCESS);
+        env::remove_var(ENV_LISTENERS);
+
+        Inherited { process, listeners }
+    }
+
+    fn take<F>(&mut self, f: F) -> Option<RawFd>
+    where
+        F: Fn(Listener) -> bool,
+    {
+        let pos = self.listeners.iter().position(|lst| f(*lst))?;
+        Some(self.listeners.remove(pos).fd())
+    }
+}
+
+/// Index and control pipe, if current process is worker process
+pub(super) fn worker() -> Option<(usize, File)> {
+    INHERITED
+        .lock()
+        .unwrap()
+        .process
+        .take()
+        .map(|(idx, ctl)| (idx, unsafe { File::from_raw_fd(ctl) }))
+}
+
+/// Take tcp listener inherited from master process.
+///
+/// Port `0` matches any port, master binds the same addresses in the
+/// same order.
+pub(super) fn inherited_tcp(addr: net::SocketAddr) -> Option<net::TcpListener> {
+    INHERITED
+        .lock()
+        .unwrap()
+        .take(|lst| match lst {
+            Listener::Tcp(fd) => {
+                let lst =
+                    ManuallyDrop::new(unsafe { net::TcpListener::from_raw_fd(fd) });
+                lst.local_addr()
+                    .map(|a| same_addr(a, addr))
+                    .unwrap_or(false)
+            }
+            _ => false,
+        })
+        .map(|fd| unsafe { net::TcpListener::from_raw_fd(fd) })
+}
+
+/// Take udp socket inherited from master process
+pub(super) fn inherited_udp(addr: net::SocketAddr) -> Option<net::UdpSocket> {
+    INHERITED
+        .lock()
+        .unwrap()
+        .take(|lst| match lst {
+            Listener::Udp(fd) => {
+                let sock = ManuallyDrop::new(unsafe { net::UdpSocket::from_raw_fd(fd) });
+                sock.local_addr()
+                    .map(|a| same_addr(a, addr))
+                    .unwrap_or(false)
+            }
+            _ => false,
+        })
+        .map(|fd| unsafe { net::UdpSocket::from_raw_fd(fd) })
+}
+
+/// Take unix domain listener inherited from master process
+pub(super) fn inherited_uds(path: &Path) -> Option<UnixListener> {
+    INHERITED
+        .lock()
+        .unwrap()
+        .take(|lst| match lst {
+            Listener::Uds(fd) => {
+                let lst = ManuallyDrop::new(unsafe { UnixListener::from_raw_fd(fd) });
+                lst.local_addr()
+                    .ok()
+                    .and_then(|a| a.as_pathname().map(|p| p == path))
+                    .unwrap_or(false)
+            }
+            _ => false,
+        })
+        .map(|fd| unsafe { UnixListener::from_raw_fd(fd) })
+}
+
+fn same_addr(bound: net::SocketAddr, addr: net::SocketAddr) -> bool {
+    bound.ip() == addr.ip() && (addr.port() == 0 || bound.port() == addr.port())
+}
+
+/// Start worker process.
+///
+/// Worker process re-executes current executable, so it does not share
+/// runtime state or threads with master process. Listeners and control
+/// pipe are inherited by worker process. Master is notified when worker
+/// process exits.
+pub(super) fn spawn(
+    idx: usize,
+    srv: Server,
+    args: Option<&[OsString]>,
+    listeners: &[Listener],
+) -> io::Result<Process> {
+    let (rd, wr) = pipe()?;
+
+    let mut cmd = Command::new(env::current_exe()?);
+    if let Some(args) = args {
+        cmd.args(args);
+    } else {
+        cmd.args(env::args_os().skip(1));
+    }
+    let encoded: Vec<_> = listeners.iter().map(|lst| lst.encode()).collect();
+    cmd.env(ENV_PROCESS, format!("{}:{}", idx, rd.as_raw_fd()))
+        .env(ENV_LISTENERS, encoded.join(","));
+
+    // inherited descriptors are marked close-on-exec in master process,
+    // flag is cleared in child process right before exec
+    let mut fds: Vec<RawFd> = listeners.iter().map(|lst| lst.fd()).collect();
+    fds.push(rd.as_raw_fd());
+    unsafe {
+        cmd.pre_exec(move || {
+            for fd in &fds {
+                if libc::fcntl(*fd, libc::F_SETFD, 0) == -1 {
+                    return Err(io::Error::last_os_error());
+                }
+            }
+            Ok(())
+        });
+    }
+
+    let mut child = cmd.spawn()?;
+    drop(rd);
+
+    let pid = child.id() as libc::pid_t;
+    thread::Builder::new()
+        .name(format!("ntex-process-waiter:{}", idx))
+        .spawn(move || {
+            wait(idx, &mut child);
+            srv.process_exited(idx);
+        })?;
+    Ok(Process {
+        idx,
+        pid,
+        ctl: Some(wr),
+    })
+}
+/// Prepare worker process for running server.
+///
+/// `SIGINT` is handled by master process, other signals are reset to
+/// default disposition. Commands from master are translated to server
+/// commands.
+pub(super) fn init_child(ctl: File, srv: Server) -> io::Result<()> {
+    unsafe {
+        libc::signal(libc::SIGINT, libc::SIG_IGN);
+        libc::signal(libc::SIGTERM, libc::SIG_DFL);
+        libc::signal(libc::SIGQUIT, libc::SIG_DFL);
+        libc::signal(libc::SIGHUP, libc::SIG_DFL);
+    }
+
+    thread::Builder::new()
+        .name("ntex-process-control".to_string())
+        .spawn(move || {
+            let mut ctl = ctl;
+            let mut buf = [0u8; 1];
+            loop {
+                match ctl.read(&mut buf) {
+                    Ok(1) => match buf[0] {
+                        PAUSE => {
+                            drop(srv.pause());
+                        }
+                        RESUME => {
+                            drop(srv.resume());
+                        }
+                        STOP => {
+                            drop(srv.stop(false));
+                            return;
+                        }
+                        STOP_GRACEFUL => {
+                            drop(srv.stop(true));
+                            return;
+                        }
+                        _ => (),
+                    },
+                    Err(ref e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                    _ => {
+                        // master process is gone
+                        drop(srv.stop(true));
+                        return;
+                    }
+                }
+            }
+        })?;
+    Ok(())
+}
+
+fn pipe() -> io::Result<(File, File)> {
+    let mut fds = [0; 2];
+    unsafe {
+        if libc::pipe(fds.as_mut_ptr()) != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        for fd in &fds {
+            libc::fcntl(*fd, libc::F_SETFD, libc::FD_CLOEXEC);
+        }
+        Ok((File::from_raw_fd(fds[0]), File::from_raw_fd(fds[1])))
+    }
+}
+
+fn wait(idx: usize, child: &mut Child) {
+    match child.wait() {
+        Ok(status) => {
+            if let Some(signal) = status.signal() {
+                log::error!("Worker process {} is terminated by signal {}", idx, signal);
+            } else if !status.success() {
+                log::error!(
+                    "Worker process {} exited with code {}",
+                    idx,
+                    status.code().unwrap_or(-1)
+                );
+            } else {
+                log::info!("Worker process {} exited", idx);
+            }
+        }
+        Err(e) => log::error!("Can not wait for worker process {}: {}", idx, e),
+    }
+}
@@ -0,0 +1,481 @@
+//! Byte-stream proxy service
+use std::future::Future;
+use std::marker::PhantomData;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::Duration;
+use std::{fmt, io};
+
+use derive_more::Display;
+use futures::future::LocalBoxFuture;
+use futures::{ready, FutureExt};
+
+use crate::codec::{AsyncRead, AsyncWrite};
+use crate::connect::{Address, Connect, ConnectError, Connector};
+use crate::rt::time::{delay_until, Delay, Instant};
+use crate::service::{Service, ServiceFactory};
+
+const BUF_SIZE: usize = 8 * 1024;
+
+/// Proxy service error
+#[derive(Debug, Display)]
+pub enum ProxyError {
+    /// Can not connect to upstream
+    #[display(fmt = "Can not connect to upstream: {}", _0)]
+    Connect(ConnectError),
+    /// Io error
+    #[display(fmt = "{}", _0)]
+    Io(io::Error),
+    /// Connection was idle for too long
+    #[display(fmt = "Idle timeout")]
+    Timeout,
+}
+
+impl std::error::Error for ProxyError {}
+
+/// Number of bytes transferred by proxied connection
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct Transferred {
+    /// Bytes sent from client to upstream
+    pub upstream: u64,
+    /// Bytes sent from upstream to client
+    pub downstream: u64,
+}
+
+/// Proxy counters.
+///
+/// Counters could be shared between workers.
+#[derive(Clone, Default)]
+pub struct ProxyCounters(Arc<CountersInner>);
+
+#[derive(Default)]
+struct CountersInner {
+    connections: AtomicUsize,
+    active: AtomicUsize,
+    upstream: AtomicUsize,
+    downstream: AtomicUsize,
+}
+
+impl ProxyCounters {
+    /// Create new counters
+    pub fn new() -> Self {
+        ProxyCounters::default()
+    }
+
+    /// Total number of proxied connections
+    pub fn connections(&self) -> usize {
+        self.0.connections.load(Ordering::Relaxed)
+    }
+
+    /// Number of currently proxied connections
+    pub fn active(&self) -> usize {
+        self.0.active.load(Ordering::Relaxed)
+    }
+
+    /// Total number of bytes sent from clients to upstream.
+    ///
+    /// Byte counters wrap around on overflow.
+    pub fn upstream_bytes(&self) -> usize {
+        self.0.upstream.load(Ordering::Relaxed)
+    }
+
+    /// Total number of bytes sent from upstream to clients.
+    ///
+    /// Byte counters wrap around on overflow.
+    pub fn downstream_bytes(&self) -> usize {
+        self.0.downstream.load(Ordering::Relaxed)
+    }
+}
+
+impl fmt::Debug for ProxyCounters {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ProxyCounters")
+            .field("connections", &self.connections())
+            .field("active", &self.active())
+            .field("upstream_bytes", &self.upstream_bytes())
+            .field("downstream_bytes", &self.downstream_bytes())
+            .finish()
+    }
+}
+
+/// Byte-stream proxy service factory.
+///
+/// Service connects to upstream for each incoming stream and copies data
+/// in both directions until both sides close connection. Tls could be used
+/// on the client side by combining service with tls acceptor, and on the
+/// upstream side by using tls connector.
+///
+/// ```rust,no_run
+/// use ntex::rt::net::TcpStream;
+/// use ntex::server::{self, proxy::Proxy};
+///
+/// #[ntex::main]
+/// async fn main() -> std::io::Result<()> {
+///     server::build()
+///         .bind("proxy", "127.0.0.1:8080", || {
+///             Proxy::<_, _, TcpStream>::new("127.0.0.1:8081")
+///         })?
+///         .run()
+///         .await
+/// }
+/// ```
+pub struct Proxy<T, C, Io> {
+    target: T,
+    connector: C,
+    idle: Duration,
+    counters: ProxyCounters,
+    _t: PhantomData<Io>,
+}
+
+impl<T: Address + Clone, Io> Proxy<T, Connector<T>, Io> {
+    /// Create proxy service factory for specified upstream address
+    pub fn new(target: T) -> Self {
+        Proxy {
+            target,
+            connector: Connector::default(),
+            idle: Duration::from_secs(300),
+            counters: ProxyCounters::default(),
+            _t: PhantomData,
+        }
+    }
+}
+
+impl<T, C, Io> Proxy<T, C, Io> {
+    /// Use custom upstream connector
+    pub fn connector<U>(self, connector: U) -> Proxy<T, U, Io>
+    where
+        U: ServiceFactory<Config = (), Request = Connect<T>, Error = ConnectError>,
+    {
+        Proxy {
+            connector,
+            target: self.target,
+            idle: self.idle,
+            counters: self.counters,
+            _t: PhantomData,
+        }
+    }
+
+    /// Set idle timeout.
+    ///
+    /// Connection is closed if no data is transferred in any direction
+    /// for this time. To disable timeout set value to 0.
+    ///
+    /// By default idle timeout is set to 300 seconds.
+    pub fn idle_timeout(mut self, timeout: Duration) -> Self {
+        self.idle = timeout;
+        self
+    }
+
+    /// Use shared counters
+    pub fn counters(mut self, counters: ProxyCounters) -> Self {
+        self.counters = counters;
+        self
+    }
+}
+
+impl<T, C, Io> ServiceFactory for Proxy<T, C, Io>
+where
+    T: Address + Clone + 'static,
+    C: ServiceFactory<Config = (), Request = Connect<T>, Error = ConnectError>,
+    C::Response: AsyncRead + AsyncWrite + Unpin,
+    C::Future: 'static,
+    <C::Service as Service>::Future: 'static,
+    Io: AsyncRead + AsyncWrite + Unpin + 'static,
+{
+    type Config = ();
+    type Request = Io;
+    type Response = Transferred;
+    type Error = ProxyError;
+    type InitError = C::InitError;
+    type Service = ProxyService<T, C::Service, Io>;
+    type Future = LocalBoxFuture<'static, Result<Self::Service, Self::InitError>>;
+
+    fn new_service(&self, _: ()) -> Self::Future {
+        let target = self.target.clone();
+        let idle = self.idle;
+        let counters = self.counters.clone();
+
+        self.connector
+            .new_service(())
+            .map(move |res| {
+                res.map(|connector| ProxyService {
+                    target,
+                    idle,
+                    counters,
+                    connector,
+                    _t: PhantomData,
+                })
+            })
+            .boxed_local()
+    }
+}
+
+/// Byte-stream proxy service
+pub struct ProxyService<T, C, Io> {
+    target: T,
+    connector: C,
+    idle: Duration,
+    counters: ProxyCounters,
+    _t: PhantomData<Io>,
+}
+
+impl<T, C, Io> Service for ProxyService<T, C, Io>
+where
+    T: Address + Clone + 'static,
+    C: Service<Request = Connect<T>, Error = ConnectError>,
+    C::Response: AsyncRead + AsyncWrite + Unpin,
+    C::Future: 'static,
+    Io: AsyncRead + AsyncWrite + Unpin + 'static,
+{
+    type Request = Io;
+    type Response = Transferred;
+    type Error = ProxyError;
+    type Future = LocalBoxFuture<'static, Result<Transferred, ProxyError>>;
+
+    #[inline]
+    fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.connector.poll_ready(cx).map_err(ProxyError::Connect)
+    }
+
+    fn call(&self, io: Io) -> Self::Future {
+        let fut = self.connector.call(Connect::new(self.target.clone()));
+        let idle = self.idle;
+        let counters = self.counters.clone();
+
+        async move {
+            let upstream = fut.await.map_err(|e| {
+                log::trace!("Can not connect to upstream: {}", e);
+                ProxyError::Connect(e)
+            })?;
+
+            counters.0.connections.fetch_add(1, Ordering::Relaxed);
+            counters.0.active.fetch_add(1, Ordering::Relaxed);
+            let res = CopyBidirectional::new(io, upstream, idle, counters.clone()).await;
+            counters.0.active.fetch_sub(1, Ordering::Relaxed);
+            res
+        }
+        .boxed_local()
+    }
+}
+
+/// Copy data in both directions until both sides are closed
+struct CopyBidirectional<A, B> {
+    a: A,
+    b: B,
+    a_to_b: Transfer,
+    b_to_a: Transfer,
+    idle: Duration,
+    delay: Option<Delay>,
+    counters: ProxyCounters,
+}
+
+impl<A, B> CopyBidirectional<A, B> {
+    fn new(a: A, b: B, idle: Duration, counters: ProxyCounters) -> Self {
+        let delay = if idle != Duration::from_secs(0) {
+            Some(delay_until(Instant::now() + idle))
+        } else {
+            None
+        };
+        CopyBidirectional {
+            a,
+            b,
+            idle,
+            delay,
+            counters,
+            a_to_b: Transfer::new(),
+            b_to_a: Transfer::new(),
+        }
+    }
+}
+
+impl<A, B> Future for CopyBidirectional<A, B>
+where
+    A: AsyncRead + AsyncWrite + Unpin,
+    B: AsyncRead + AsyncWrite + Unpin,
+{
+    type Output = Result<Transferred, ProxyError>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.get_mut();
+        let inner = &this.counters.0;
+
+        let up = this
+            .a_to_b
+            .poll_copy(cx, &mut this.a, &mut this.b, &inner.upstream)
+            .map_err(ProxyError::Io)?;
+        let down = this
+            .b_to_a
+            .poll_copy(cx, &mut this.b, &mut this.a, &inner.downstream)
+            .map_err(ProxyError::Io)?;
+
+        if up.is_ready() && down.is_ready() {
+            return Poll::Ready(Ok(Transferred {
+                upstream: this.a_to_b.amt,
+                downstream: this.b_to_a.amt,
+            }));
+        }
+
+        if let Some(ref mut delay) = this.delay {
+            if this.a_to_b.active || this.b_to_a.active {
+                this.a_to_b.active = false;
+                this.b_to_a.active = false;
+                delay.reset(Instant::now() + this.idle);
+            }
+            if Pin::new(delay).poll(cx).is_ready() {
+                log::trace!("Proxied connection is idle, closing");
+                return Poll::Ready(Err(ProxyError::Timeout));
+            }
+        }
+        Poll::Pending
+    }
+}
+
+/// One direction of the copy
+struct Transfer {
+    buf: Box<[u8]>,
+    pos: usize,
+    cap: usize,
+    amt: u64,
+    read_done: bool,
+    need_flush: bool,
+    active: bool,
+}
+
+impl Transfer {
+    fn new() -> Self {
+        Transfer {
+            buf: vec![0; BUF_SIZE].into_boxed_slice(),
+            pos: 0,
+            cap: 0,
+            amt: 0,
+            read_done: false,
+            need_flush: false,
+            active: false,
+        }
+    }
+
+    fn poll_copy<R, W>(
+        &mut self,
+        cx: &mut Context<'_>,
+        reader: &mut R,
+        writer: &mut W,
+        counter: &AtomicUsize,
+    ) -> Poll<io::Result<()>>
+    where
+        R: AsyncRead + Unpin,
+        W: AsyncWrite + Unpin,
+    {
+        loop {
+            // read more data if buffer is empty
+            if self.pos == self.cap && !self.read_done {
+                match Pin::new(&mut *reader).poll_read(cx, &mut self.buf) {
+                    Poll::Ready(Ok(0)) => self.read_done = true,
+                    Poll::Ready(Ok(n)) => {
+                        self.pos = 0;
+                        self.cap = n;
+                        self.active = true;
+                    }
+                    Poll::Ready(Err(e)) => return Poll::Ready(Err(e)),
+                    Poll::Pending => {
+                        if self.need_flush {
+                            ready!(Pin::new(&mut *writer).poll_flush(cx))?;
+                            self.need_flush = false;
+                        }
+                        return Poll::Pending;
+                    }
+                }
+            }
+
+            // write buffered data
+            while self.pos < self.cap {
+                let n =
+                    ready!(Pin::new(&mut *writer)
+                        .poll_write(cx, &self.buf[self.pos..self.cap]))?;
+                if n == 0 {
+                    return Poll::Ready(Err(io::Error::new(
+                        io::ErrorKind::WriteZero,
+                        "write zero byte into writer",
+                    )));
+                }
+                self.pos += n;
+                self.amt += n as u64;
+                self.need_flush = true;
+                self.active = true;
+                counter.fetch_add(n, Ordering::Relaxed);
+            }
+
+            // peer closed connection, propagate close to the other side
+            if self.pos == self.cap && self.read_done {
+                if self.need_flush {
+                    ready!(Pin::new(&mut *writer).poll_flush(cx))?;
+                    self.need_flush = false;
+                }
+                ready!(Pin::new(&mut *writer).poll_shutdown(cx))?;
+                return Poll::Ready(Ok(()));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+    use futures::{SinkExt, StreamExt};
+
+    use super::*;
+    use crate::codec::{BytesCodec, Framed};
+    use crate::rt::net::TcpStream;
+    use crate::server::test_server;
+    use crate::service::fn_service;
+
+    /// Upstream that sends received data back
+    async fn echo(io: TcpStream) -> Result<(), io::Error> {
+        let mut framed = Framed::new(io, BytesCodec);
+        while let Some(data) = framed.next().await {
+            framed.send(data?.freeze()).await?;
+        }
+        Ok(())
+    }
+
+    #[ntex_rt::test]
+    async fn test_echo_proxy() {
+        let echo = test_server(|| fn_service(echo));
+        let target = format!("127.0.0.1:{}", echo.addr().port());
+
+        let counters = ProxyCounters::new();
+        let counters2 = counters.clone();
+        let proxy = test_server(move || {
+            Proxy::<_, _, TcpStream>::new(target.clone()).counters(counters2.clone())
+        });
+
+        let io = proxy.connect().unwrap();
+        let mut framed = Framed::new(io, BytesCodec);
+        framed.send(Bytes::from_static(b"test")).await.unwrap();
+        let item = framed.next().await.unwrap().unwrap();
+        assert_eq!(&item[..], b"test");
+        drop(framed);
+
+        crate::rt::time::delay_for(Duration::from_millis(100)).await;
+        assert_eq!(counters.connections(), 1);
+        assert_eq!(counters.active(), 0);
+        assert_eq!(counters.upstream_bytes(), 4);
+        assert_eq!(counters.downstream_bytes(), 4);
+    }
+
+    #[ntex_rt::test]
+    async fn test_idle_timeout() {
+        let echo = test_server(|| fn_service(echo));
+        let target = format!("127.0.0.1:{}", echo.addr().port());
+        let proxy = test_server(move || {
+            Proxy::<_, _, TcpStream>::new(target.clone())
+                .idle_timeout(Duration::from_millis(50))
+        });
+
+        let io = proxy.connect().unwrap();
+        let mut framed = Framed::new(io, BytesCodec);
+        crate::rt::time::delay_for(Duration::from_millis(150)).await;
+        assert!(framed.next().await.is_none());
+    }
+}
@@ -3,11 +3,13 @@ use std::future::Future;
 use std::io;
 use std::marker::PhantomData;
 use std::pin::Pin;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 use std::task::{Context, Poll};
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 use futures::future::{ok, Ready};
+use rust_tls::sign::CertifiedKey;
+use rust_tls::{ClientHello, KeyLog, ProducesTickets, ResolvesServerCert, Ticketer};
 use tokio_rustls::{Accept, TlsAcceptor};
 
 pub use rust_tls::{ServerConfig, Session};
@@ -19,7 +21,131 @@ use crate::rt::time::{delay_for, Delay};
 use crate::service::{Service, ServiceFactory};
 use crate::util::counter::{Counter, CounterGuard};
 
-use super::{MAX_CONN_COUNTER, ZERO};
+use super::limit::RateLimiter;
+use super::{HandshakeErrorHook, KeyLogSink, OcspResponse, MAX_CONN_COUNTER, ZERO};
+
+impl KeyLog for KeyLogSink {
+    fn log(&self, label: &str, client_random: &[u8], secret: &[u8]) {
+        KeyLogSink::log(self, label, client_random, secret)
+    }
+}
+
+/// Certificate resolver with OCSP stapling.
+///
+/// Stapled response is taken from `ocsp` for each handshake.
+///
+/// ```rust,ignore
+/// config.cert_resolver = Arc::new(OcspCertResolver::new(certified_key, ocsp));
+/// ```
+pub struct OcspCertResolver {
+    key: CertifiedKey,
+    ocsp: OcspResponse,
+}
+
+impl OcspCertResolver {
+    /// Create resolver for certificate chain and key
+    pub fn new(key: CertifiedKey, ocsp: OcspResponse) -> Self {
+        OcspCertResolver { key, ocsp }
+    }
+}
+
+impl ResolvesServerCert for OcspCertResolver {
+    fn resolve(&self, _: ClientHello<'_>) -> Option<CertifiedKey> {
+        let mut key = self.key.clone();
+        key.ocsp = self.ocsp.get();
+        Some(key)
+    }
+}
+
+/// Session tickets producer with scheduled key rotation.
+///
+/// Ticket key is replaced with new random key every `interval`, tickets
+/// encrypted with previous key are accepted until next rotation. Ticketer
+/// is shared by all workers that use same `ServerConfig`.
+///
+/// ```rust,ignore
+/// let ticketer = SessionTicketer::new(Duration::from_secs(3600));
+/// config.ticketer = ticketer.clone();
+/// ```
+pub struct SessionTicketer {
+    interval: Duration,
+    state: Mutex<TicketerState>,
+}
+
+struct TicketerState {
+    current: Arc<dyn ProducesTickets>,
+    previous: Option<Arc<dyn ProducesTickets>>,
+    next_rotation: Instant,
+}
+
+/// Max rotation interval, rustls ticketer rotates its own keys every 6 hours
+const MAX_TICKET_INTERVAL: Duration = Duration::from_secs(6 * 60 * 60);
+
+impl SessionTicketer {
+    /// Create ticketer with key rotation interval.
+    ///
+    /// Interval is limited to 6 hours.
+    pub fn new(interval: Duration) -> Arc<Self> {
+        let interval = if interval > MAX_TICKET_INTERVAL {
+            MAX_TICKET_INTERVAL
+        } else {
+            interval
+        };
+
+        Arc::new(SessionTicketer {
+            interval,
+            state: Mutex::new(TicketerState {
+                current: Ticketer::new(),
+                previous: None,
+                next_rotation: Instant::now() + interval,
+            }),
+        })
+    }
+
+    /// Rotate ticket key immediately
+    pub fn rotate(&self) {
+        let mut state = self.state.lock().unwrap();
+        self.rotate_state(&mut state);
+    }
+
+    fn rotate_state(&self, state: &mut TicketerState) {
+        let current = std::mem::replace(&mut state.current, Ticketer::new());
+        state.previous = Some(current);
+        state.next_rotation = Instant::now() + self.interval;
+    }
+
+    fn state(&self) -> std::sync::MutexGuard<'_, TicketerState> {
+        let mut state = self.state.lock().unwrap();
+        if Instant::now() >= state.next_rotation {
+            self.rotate_state(&mut state);
+        }
+        state
+    }
+}
+
+impl ProducesTickets for SessionTicketer {
+    fn enabled(&self) -> bool {
+        true
+    }
+
+    fn get_lifetime(&self) -> u32 {
+        (self.interval.as_secs() * 2) as u32
+    }
+
+    fn encrypt(&self, plain: &[u8]) -> Option<Vec<u8>> {
+        self.state().current.encrypt(plain)
+    }
+
+    fn decrypt(&self, cipher: &[u8]) -> Option<Vec<u8>> {
+        let state = self.state();
+        state.current.decrypt(cipher).or_else(|| {
+            state
+                .previous
+                .as_ref()
+                .and_then(|previous| previous.decrypt(cipher))
+        })
+    }
+}
 
 /// Support `SSL` connections via rustls package
 ///
@@ -27,6 +153,9 @@ use super::{MAX_CONN_COUNTER, ZERO};
 pub struct Acceptor<T> {
     timeout: Duration,
     config: Arc<ServerConfig>,
+    max_handshakes: Option<usize>,
+    rate: Option<u32>,
+    on_error: Option<HandshakeErrorHook>,
     io: PhantomData<T>,
 }
 
@@ -36,6 +165,9 @@ impl<T: AsyncRead + AsyncWrite> Acceptor<T> {
         Acceptor {
             config: Arc::new(config),
             timeout: Duration::from_secs(5),
+            max_handshakes: None,
+            rate: None,
+            on_error: None,
             io: PhantomData,
         }
     }
@@ -47,6 +179,36 @@ impl<T: AsyncRead + AsyncWrite> Acceptor<T> {
         self.timeout = Duration::from_millis(time);
         self
     }
+
+    /// Set max number of concurrent handshakes per worker.
+    ///
+    /// Worker stops accepting new connections for this acceptor when limit
+    /// is reached. By default limit is shared between all acceptors and is
+    /// set with `max_concurrent_ssl_accept()` function.
+    pub fn max_handshakes(mut self, num: usize) -> Self {
+        self.max_handshakes = Some(num);
+        self
+    }
+
+    /// Set max number of new handshakes per second per worker.
+    ///
+    /// Handshakes are started in bursts of up to `num` handshakes, after
+    /// that worker delays new connections. By default rate is not limited.
+    pub fn handshake_rate(mut self, num: u32) -> Self {
+        self.rate = Some(num);
+        self
+    }
+
+    /// Set handshake failure hook.
+    ///
+    /// Hook get called with the reason of failed or timed out handshake.
+    pub fn on_handshake_error<F>(mut self, f: F) -> Self
+    where
+        F: Fn(&dyn Error) + Send + Sync + 'static,
+    {
+        self.on_error = Some(Arc::new(f));
+        self
+    }
 }
 
 impl<T> Clone for Acceptor<T> {
@@ -54,6 +216,9 @@ impl<T> Clone for Acceptor<T> {
         Self {
             config: self.config.clone(),
             timeout: self.timeout,
+            max_handshakes: self.max_handshakes,
+            rate: self.rate,
+            on_error: self.on_error.clone(),
             io: PhantomData,
         }
     }
@@ -70,13 +235,18 @@ impl<T: AsyncRead + AsyncWrite + Unpin> ServiceFactory for Acceptor<T> {
     type Future = Ready<Result<Self::Service, Self::InitError>>;
 
     fn new_service(&self, _: ()) -> Self::Future {
-        MAX_CONN_COUNTER.with(|conns| {
-            ok(AcceptorService {
-                acceptor: self.config.clone().into(),
-                conns: conns.clone(),
-                timeout: self.timeout,
-                io: PhantomData,
-            })
+        let conns = match self.max_handshakes {
+            Some(num) => Counter::new(num),
+            None => MAX_CONN_COUNTER.with(|conns| conns.clone()),
+        };
+
+        ok(AcceptorService {
+            conns,
+            acceptor: self.config.clone().into(),
+            timeout: self.timeout,
+            rate: self.rate.map(RateLimiter::new),
+            on_error: self.on_error.clone(),
+            io: PhantomData,
         })
     }
 }
@@ -87,6 +257,8 @@ pub struct AcceptorService<T> {
     io: PhantomData<T>,
     conns: Counter,
     timeout: Duration,
+    rate: Option<RateLimiter>,
+    on_error: Option<HandshakeErrorHook>,
 }
 
 impl<T: AsyncRead + AsyncWrite + Unpin> Service for AcceptorService<T> {
@@ -97,6 +269,11 @@ impl<T: AsyncRead + AsyncWrite + Unpin> Service for AcceptorService<T> {
 
     #[inline]
     fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        if let Some(ref rate) = self.rate {
+            if rate.poll_ready(cx).is_pending() {
+                return Poll::Pending;
+            }
+        }
         if self.conns.available(cx) {
             Poll::Ready(Ok(()))
         } else {
@@ -106,8 +283,12 @@ impl<T: AsyncRead + AsyncWrite + Unpin> Service for AcceptorService<T> {
 
     #[inline]
     fn call(&self, req: Self::Request) -> Self::Future {
+        if let Some(ref rate) = self.rate {
+            rate.acquire();
+        }
         AcceptorServiceFut {
             _guard: self.conns.get(),
+            on_error: self.on_error.clone(),
             fut: self.acceptor.accept(req),
             delay: if self.timeout == ZERO {
                 None
@@ -124,6 +305,7 @@ where
 {
     fut: Accept<T>,
     delay: Option<Delay>,
+    on_error: Option<HandshakeErrorHook>,
     _guard: CounterGuard,
 }
 
@@ -137,10 +319,14 @@ impl<T: AsyncRead + AsyncWrite + Unpin> Future for AcceptorServiceFut<T> {
             match Pin::new(delay).poll(cx) {
                 Poll::Pending => (),
                 Poll::Ready(_) => {
-                    return Poll::Ready(Err(Box::new(io::Error::new(
+                    let err: Box<dyn Error> = Box::new(io::Error::new(
                         io::ErrorKind::TimedOut,
                         "ssl handshake timeout",
-                    ))))
+                    ));
+                    if let Some(ref on_error) = this.on_error {
+                        on_error(err.as_ref());
+                    }
+                    return Poll::Ready(Err(err));
                 }
             }
         }
@@ -148,7 +334,12 @@ impl<T: AsyncRead + AsyncWrite + Unpin> Future for AcceptorServiceFut<T> {
         let res = futures::ready!(Pin::new(&mut this.fut).poll(cx));
         match res {
             Ok(io) => Poll::Ready(Ok(io)),
-            Err(e) => Poll::Ready(Err(Box::new(e))),
+            Err(e) => {
+                if let Some(ref on_error) = this.on_error {
+                    on_error(&e);
+                }
+                Poll::Ready(Err(Box::new(e)))
+            }
         }
     }
 }
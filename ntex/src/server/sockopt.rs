@@ -0,0 +1,130 @@
+//! Tcp socket options
+use std::{io, net};
+
+use socket2::{Domain, Socket, Type};
+
+/// `IPPROTO_MPTCP` protocol number
+#[cfg(target_os = "linux")]
+const IPPROTO_MPTCP: i32 = 262;
+
+/// `TCP_FASTOPEN_CONNECT` socket option
+#[cfg(target_os = "linux")]
+const TCP_FASTOPEN_CONNECT: libc::c_int = 30;
+
+/// Tcp listener options
+#[derive(Debug, Default, Copy, Clone)]
+pub(crate) struct SocketOpts {
+    /// Set `SO_REUSEPORT` option
+    pub(crate) reuse_port: bool,
+    /// Enable tcp fast open with specified queue length
+    pub(crate) fastopen: Option<u32>,
+    /// Use multipath tcp
+    pub(crate) mptcp: bool,
+}
+
+/// Create tcp socket.
+///
+/// If multipath tcp is requested but not supported by the kernel, regular
+/// tcp socket is created.
+pub(crate) fn tcp_socket(addr: &net::SocketAddr, mptcp: bool) -> io::Result<Socket> {
+    let domain = match addr {
+        net::SocketAddr::V4(_) => Domain::ipv4(),
+        net::SocketAddr::V6(_) => Domain::ipv6(),
+    };
+
+    if mptcp {
+        #[cfg(target_os = "linux")]
+        match Socket::new(domain, Type::stream(), Some(IPPROTO_MPTCP.into())) {
+            Ok(sock) => return Ok(sock),
+            Err(e) => log::warn!("MPTCP is not available, fallback to TCP: {}", e),
+        }
+        #[cfg(not(target_os = "linux"))]
+        log::warn!("MPTCP is not supported on this platform, fallback to TCP");
+    }
+    Socket::new(domain, Type::stream(), None)
+}
+
+/// Enable tcp fast open for listener socket.
+///
+/// Failure is not fatal, listener continues to work without fast open.
+pub(crate) fn set_fastopen(sock: &Socket, qlen: u32) {
+    #[cfg(target_os = "linux")]
+    {
+        if let Err(e) = setsockopt(sock, libc::TCP_FASTOPEN, qlen as libc::c_int) {
+            log::warn!("Can not enable TCP fast open: {}", e);
+        }
+    }
+    #[cfg(not(target_os = "linux"))]
+    {
+        let _ = (sock, qlen);
+        log::warn!("TCP fast open is not supported on this platform");
+    }
+}
+
+/// Enable tcp fast open for client socket.
+///
+/// Failure is not fatal, connection is established with regular handshake.
+pub(crate) fn set_fastopen_connect(sock: &Socket) {
+    #[cfg(target_os = "linux")]
+    {
+        if let Err(e) = setsockopt(sock, TCP_FASTOPEN_CONNECT, 1) {
+            log::trace!("Can not enable TCP fast open: {}", e);
+        }
+    }
+    #[cfg(not(target_os = "linux"))]
+    {
+        let _ = sock;
+        log::trace!("TCP fast open is not supported on this platform");
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn setsockopt(sock: &Socket, opt: libc::c_int, val: libc::c_int) -> io::Result<()> {
+    use std::os::unix::io::AsRawFd;
+
+    let res = unsafe {
+        libc::setsockopt(
+            sock.as_raw_fd(),
+            libc::IPPROTO_TCP,
+            opt,
+            &val as *const libc::c_int as *const libc::c_void,
+            std::mem::size_of::<libc::c_int>() as libc::socklen_t,
+        )
+    };
+    if res == -1 {
+        Err(io::Error::last_os_error())
+    } else {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Read, Write};
+
+    use super::*;
+    use crate::server::builder::create_tcp_listener;
+
+    #[test]
+    fn test_listener_opts() {
+        let opts = SocketOpts {
+            reuse_port: false,
+            fastopen: Some(16),
+            mptcp: true,
+        };
+        let addr = "127.0.0.1:0".parse().unwrap();
+        let lst = create_tcp_listener(addr, 16, opts).unwrap();
+        let addr = lst.local_addr().unwrap();
+
+        let sock = tcp_socket(&addr, true).unwrap();
+        set_fastopen_connect(&sock);
+        sock.connect(&addr.into()).unwrap();
+        let mut client = sock.into_tcp_stream();
+        client.write_all(b"test").unwrap();
+
+        let (mut io, _) = lst.accept().unwrap();
+        let mut buf = [0; 4];
+        io.read_exact(&mut buf).unwrap();
+        assert_eq!(&buf, b"test");
+    }
+}
@@ -0,0 +1,489 @@
+//! UDP server support
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::rc::Rc;
+use std::task::{Context, Poll};
+use std::time::Duration;
+use std::{fmt, io};
+
+use bytes::Bytes;
+use futures::channel::oneshot;
+use futures::future::{ok, poll_fn, LocalBoxFuture, Ready, Shared};
+use futures::{Future, FutureExt, TryFutureExt};
+use log::{error, trace};
+
+use crate::rt::net::UdpSocket;
+use crate::rt::spawn;
+use crate::rt::time::Instant;
+use crate::service::{Service, ServiceFactory};
+use crate::util::counter::Counter;
+
+/// Max size of received datagram
+const MAX_DATAGRAM_SIZE: usize = 65536;
+
+/// Received datagram
+pub struct Datagram {
+    data: Bytes,
+    peer: SocketAddr,
+    sender: UdpSender,
+}
+
+impl Datagram {
+    /// Datagram payload
+    pub fn data(&self) -> &Bytes {
+        &self.data
+    }
+
+    /// Consume datagram and return payload
+    pub fn into_data(self) -> Bytes {
+        self.data
+    }
+
+    /// Address of the peer that sent datagram
+    pub fn peer(&self) -> SocketAddr {
+        self.peer
+    }
+
+    /// Sender for the socket that received datagram.
+    ///
+    /// Sender could be used for sending additional datagrams
+    /// to the peer.
+    pub fn sender(&self) -> &UdpSender {
+        &self.sender
+    }
+}
+
+impl fmt::Debug for Datagram {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Datagram")
+            .field("peer", &self.peer)
+            .field("data", &self.data)
+            .finish()
+    }
+}
+
+/// Send half of the worker's udp socket
+#[derive(Clone)]
+pub struct UdpSender(Rc<UdpSocket>);
+
+impl UdpSender {
+    /// Send datagram to the specified address
+    pub async fn send_to(&self, data: &[u8], target: SocketAddr) -> io::Result<usize> {
+        poll_fn(|cx| self.0.poll_send_to(cx, data, &target)).await
+    }
+
+    /// Local address of the socket
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.0.local_addr()
+    }
+}
+
+impl fmt::Debug for UdpSender {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("UdpSender")
+            .field("local_addr", &self.0.local_addr().ok())
+            .finish()
+    }
+}
+
+/// Factory of datagram services.
+///
+/// Service receives `Datagram` and could return response payload,
+/// response is sent back to the peer.
+pub trait DatagramServiceFactory: Send + Clone + 'static {
+    type Factory: ServiceFactory<
+        Config = (),
+        Request = Datagram,
+        Response = Option<Bytes>,
+    >;
+
+    fn create(&self) -> Self::Factory;
+}
+
+impl<F, T> DatagramServiceFactory for F
+where
+    F: Fn() -> T + Send + Clone + 'static,
+    T: ServiceFactory<Config = (), Request = Datagram, Response = Option<Bytes>>,
+{
+    type Factory = T;
+
+    #[inline]
+    fn create(&self) -> T {
+        (self)()
+    }
+}
+
+pub(super) trait InternalUdpFactory: Send {
+    fn name(&self) -> &str;
+
+    fn clone_factory(&self) -> Box<dyn InternalUdpFactory>;
+
+    fn create(&self) -> LocalBoxFuture<'static, Result<BoxedUdpService, ()>>;
+}
+
+pub(super) type BoxedUdpService = Box<
+    dyn Service<
+        Request = Datagram,
+        Response = Option<Bytes>,
+        Error = (),
+        Future = LocalBoxFuture<'static, Result<Option<Bytes>, ()>>,
+    >,
+>;
+
+struct UdpService<T> {
+    service: T,
+}
+
+impl<T> Service for UdpService<T>
+where
+    T: Service<Request = Datagram, Response = Option<Bytes>>,
+    T::Future: 'static,
+    T::Error: 'static,
+{
+    type Request = Datagram;
+    type Response = Option<Bytes>;
+    type Error = ();
+    type Future = LocalBoxFuture<'static, Result<Option<Bytes>, ()>>;
+
+    #[inline]
+    fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(cx).map_err(|_| ())
+    }
+
+    #[inline]
+    fn poll_shutdown(&self, cx: &mut Context<'_>, is_error: bool) -> Poll<()> {
+        self.service.poll_shutdown(cx, is_error)
+    }
+
+    fn call(&self, req: Datagram) -> Self::Future {
+        self.service.call(req).map_err(|_| ()).boxed_local()
+    }
+}
+
+pub(super) struct UdpFactory<F> {
+    name: String,
+    inner: F,
+}
+
+impl<F: DatagramServiceFactory> UdpFactory<F> {
+    pub(super) fn create(name: String, inner: F) -> Box<dyn InternalUdpFactory> {
+        Box::new(Self { name, inner })
+    }
+}
+
+impl<F: DatagramServiceFactory> InternalUdpFactory for UdpFactory<F> {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn clone_factory(&self) -> Box<dyn InternalUdpFactory> {
+        Box::new(Self {
+            name: self.name.clone(),
+            inner: self.inner.clone(),
+        })
+    }
+
+    fn create(&self) -> LocalBoxFuture<'static, Result<BoxedUdpService, ()>> {
+        self.inner
+            .create()
+            .new_service(())
+            .map_err(|_| ())
+            .map_ok(|service| {
+                let service: BoxedUdpService = Box::new(UdpService { service });
+                service
+            })
+            .boxed_local()
+    }
+}
+
+/// Receive datagrams from the socket and pass them to the service.
+///
+/// Loop stops when `stop` channel get dropped. Every in-flight datagram
+/// holds worker's counter guard, so graceful shutdown waits for
+/// responses.
+pub(super) async fn run(
+    name: String,
+    socket: UdpSocket,
+    service: BoxedUdpService,
+    conns: Counter,
+    mut stop: oneshot::Receiver<()>,
+) {
+    let sender = UdpSender(Rc::new(socket));
+    let mut buf = vec![0; MAX_DATAGRAM_SIZE];
+
+    loop {
+        let item = poll_fn(|cx| {
+            if Pin::new(&mut stop).poll(cx).is_ready() {
+                return Poll::Ready(None);
+            }
+            match service.poll_ready(cx) {
+                Poll::Ready(Ok(_)) => (),
+                Poll::Ready(Err(_)) => {
+                    error!("Service {:?} readiness check returned error", name);
+                    return Poll::Ready(None);
+                }
+                Poll::Pending => return Poll::Pending,
+            }
+            sender.0.poll_recv_from(cx, &mut buf).map(Some)
+        })
+        .await;
+
+        match item {
+            Some(Ok((size, peer))) => {
+                let guard = conns.get();
+                let sender = sender.clone();
+                let fut = service.call(Datagram {
+                    peer,
+                    data: Bytes::copy_from_slice(&buf[..size]),
+                    sender: sender.clone(),
+                });
+                spawn(async move {
+                    if let Ok(Some(data)) = fut.await {
+                        if let Err(e) = sender.send_to(&data, peer).await {
+                            trace!("Can not send datagram to {}: {}", peer, e);
+                        }
+                    }
+                    drop(guard);
+                });
+            }
+            // icmp errors are reported on some platforms, ignore them
+            Some(Err(e)) => trace!("Error receiving datagram: {}", e),
+            None => {
+                trace!("Stopping {:?} udp service", name);
+                return;
+            }
+        }
+    }
+}
+
+/// Create per-peer datagram service factory.
+///
+/// New service is created for each peer, datagrams from the same peer are
+/// processed by the same service. Services that did not receive datagrams
+/// for idle timeout are dropped. On unix platforms each worker binds its
+/// own socket and kernel distributes datagrams by peer address, so all
+/// datagrams from one peer are processed by the same worker.
+///
+/// ```rust
+/// use bytes::Bytes;
+/// use futures::future::ok;
+/// use ntex::server::{self, udp::{self, Datagram}};
+/// use ntex::fn_service;
+///
+/// #[ntex::main]
+/// async fn main() -> std::io::Result<()> {
+///     server::build()
+///         .bind_udp("echo", "127.0.0.1:0", || {
+///             udp::demux(|_peer| {
+///                 fn_service(|dg: Datagram| {
+///                     ok::<_, ()>(Some(dg.into_data()))
+///                 })
+///             })
+///         })?
+///         .run();
+///     # Ok(())
+/// }
+/// ```
+pub fn demux<F, S>(factory: F) -> Demux<F>
+where
+    F: Fn(&SocketAddr) -> S + 'static,
+    S: ServiceFactory<Config = (), Request = Datagram, Response = Option<Bytes>>,
+{
+    Demux {
+        factory: Rc::new(factory),
+        idle: Duration::from_secs(60),
+    }
+}
+
+/// Per-peer datagram service factory
+pub struct Demux<F> {
+    factory: Rc<F>,
+    idle: Duration,
+}
+
+impl<F> Demux<F> {
+    /// Set peer idle timeout.
+    ///
+    /// By default idle timeout is set to 60 seconds.
+    pub fn idle_timeout(mut self, timeout: Duration) -> Self {
+        self.idle = timeout;
+        self
+    }
+}
+
+impl<F, S> ServiceFactory for Demux<F>
+where
+    F: Fn(&SocketAddr) -> S + 'static,
+    S: ServiceFactory<Config = (), Request = Datagram, Response = Option<Bytes>>,
+    S::Service: 'static,
+    S::Future: 'static,
+    S::InitError: fmt::Debug,
+{
+    type Config = ();
+    type Request = Datagram;
+    type Response = Option<Bytes>;
+    type Error = S::Error;
+    type InitError = ();
+    type Service = DemuxService<F, S::Service>;
+    type Future = Ready<Result<Self::Service, ()>>;
+
+    fn new_service(&self, _: ()) -> Self::Future {
+        ok(DemuxService {
+            factory: self.factory.clone(),
+            idle: self.idle,
+            peers: Rc::new(RefCell::new(HashMap::new())),
+            last_sweep: Cell::new(Instant::now()),
+        })
+    }
+}
+
+type PeerFuture<S> = Shared<LocalBoxFuture<'static, Result<Rc<S>, ()>>>;
+
+struct Peer<S> {
+    service: PeerFuture<S>,
+    last: Instant,
+}
+
+/// Per-peer datagram service
+pub struct DemuxService<F, S> {
+    factory: Rc<F>,
+    idle: Duration,
+    peers: Rc<RefCell<HashMap<SocketAddr, Peer<S>>>>,
+    last_sweep: Cell<Instant>,
+}
+
+impl<F, S> DemuxService<F, S> {
+    /// Drop services of idle peers
+    fn sweep(&self, now: Instant) {
+        if now - self.last_sweep.get() >= self.idle {
+            let idle = self.idle;
+            self.last_sweep.set(now);
+            self.peers
+                .borrow_mut()
+                .retain(|_, peer| now - peer.last < idle);
+        }
+    }
+}
+
+impl<F, T, S> Service for DemuxService<F, S>
+where
+    F: Fn(&SocketAddr) -> T + 'static,
+    T: ServiceFactory<
+        Config = (),
+        Request = Datagram,
+        Response = Option<Bytes>,
+        Service = S,
+    >,
+    T::Future: 'static,
+    T::InitError: fmt::Debug,
+    S: Service<Request = Datagram, Response = Option<Bytes>, Error = T::Error> + 'static,
+{
+    type Request = Datagram;
+    type Response = Option<Bytes>;
+    type Error = S::Error;
+    type Future = LocalBoxFuture<'static, Result<Option<Bytes>, S::Error>>;
+
+    #[inline]
+    fn poll_ready(&self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&self, req: Datagram) -> Self::Future {
+        let now = Instant::now();
+        self.sweep(now);
+
+        let addr = req.peer;
+        let service = {
+            let mut peers = self.peers.borrow_mut();
+            let factory = &self.factory;
+            let peer = peers.entry(addr).or_insert_with(|| {
+                trace!("Creating service for new peer {}", addr);
+                Peer {
+                    service: factory(&addr)
+                        .new_service(())
+                        .map(move |res| {
+                            res.map(Rc::new).map_err(|e| {
+                                error!("Can not create service for {}: {:?}", addr, e)
+                            })
+                        })
+                        .boxed_local()
+                        .shared(),
+                    last: now,
+                }
+            });
+            peer.last = now;
+            peer.service.clone()
+        };
+
+        let peers = self.peers.clone();
+        async move {
+            match service.await {
+                Ok(srv) => {
+                    poll_fn(|cx| srv.poll_ready(cx)).await?;
+                    srv.call(req).await
+                }
+                Err(_) => {
+                    // datagram is dropped, service would be created again
+                    // for the next datagram
+                    peers.borrow_mut().remove(&addr);
+                    Ok(None)
+                }
+            }
+        }
+        .boxed_local()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fn_service;
+    use std::cell::Cell;
+
+    fn socket() -> UdpSender {
+        let sock = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+        UdpSender(Rc::new(UdpSocket::from_std(sock).unwrap()))
+    }
+
+    fn datagram(sender: &UdpSender, peer: &str, data: &'static [u8]) -> Datagram {
+        Datagram {
+            peer: peer.parse().unwrap(),
+            data: Bytes::from_static(data),
+            sender: sender.clone(),
+        }
+    }
+
+    #[ntex_rt::test]
+    async fn test_demux() {
+        let created = Rc::new(Cell::new(0));
+        let created2 = created.clone();
+        let factory = demux(move |_| {
+            created2.set(created2.get() + 1);
+            let count = Rc::new(Cell::new(0));
+            fn_service(move |_: Datagram| {
+                count.set(count.get() + 1);
+                ok::<_, ()>(Some(Bytes::from(count.get().to_string())))
+            })
+        })
+        .idle_timeout(Duration::from_millis(100));
+        let srv = factory.new_service(()).await.unwrap();
+        let sender = socket();
+
+        let res = srv.call(datagram(&sender, "127.0.0.1:1000", b"1")).await;
+        assert_eq!(res, Ok(Some(Bytes::from_static(b"1"))));
+        let res = srv.call(datagram(&sender, "127.0.0.1:1000", b"2")).await;
+        assert_eq!(res, Ok(Some(Bytes::from_static(b"2"))));
+        let res = srv.call(datagram(&sender, "127.0.0.1:1001", b"1")).await;
+        assert_eq!(res, Ok(Some(Bytes::from_static(b"1"))));
+        assert_eq!(created.get(), 2);
+
+        // idle peers get dropped
+        crate::rt::time::delay_for(Duration::from_millis(150)).await;
+        let res = srv.call(datagram(&sender, "127.0.0.1:1000", b"3")).await;
+        assert_eq!(res, Ok(Some(Bytes::from_static(b"1"))));
+        assert_eq!(created.get(), 3);
+        assert_eq!(srv.peers.borrow().len(), 1);
+    }
+}
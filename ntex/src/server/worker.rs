@@ -1,23 +1,27 @@
+use std::panic::AssertUnwindSafe;
 use std::pin::Pin;
 use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::sync::Arc;
 use std::task::{Context, Poll};
-use std::time;
+use std::{fmt, io, net, time};
 
 use futures::channel::mpsc::{unbounded, UnboundedReceiver, UnboundedSender};
 use futures::channel::oneshot;
 use futures::future::{join_all, LocalBoxFuture, MapOk};
 use futures::{Future, FutureExt, Stream, TryFutureExt};
 use log::{error, info, trace};
+use tokio::io::PollEvented;
 
 use crate::rt::time::{delay_until, Delay, Instant};
 use crate::rt::{spawn, Arbiter};
 use crate::util::counter::Counter;
 
-use super::accept::AcceptNotify;
+use super::accept::{connection_error, AcceptNotify};
+use super::balance::WorkerLoad;
 use super::service::{BoxedServerService, InternalServiceFactory, ServerMessage};
-use super::socket::{SocketAddr, StdStream};
-use super::Token;
+use super::socket::{SocketAddr, SocketListener, StdListener, StdStream};
+use super::udp::{self, InternalUdpFactory};
+use super::{Server, Token};
 
 pub(super) struct WorkerCommand(Conn);
 
@@ -47,13 +51,27 @@ pub(super) fn max_concurrent_connections(num: usize) {
     MAX_CONNS.store(num, Ordering::Relaxed);
 }
 
-pub(super) fn num_connections() -> usize {
-    MAX_CONNS_COUNTER.with(|conns| conns.total())
+pub(super) trait WorkerWarmup: Send {
+    fn clone(&self) -> Box<dyn WorkerWarmup>;
+
+    fn warmup(&self) -> LocalBoxFuture<'static, Result<(), ()>>;
 }
 
-thread_local! {
-    static MAX_CONNS_COUNTER: Counter =
-        Counter::new(MAX_CONNS.load(Ordering::Relaxed));
+impl<F, R, E> WorkerWarmup for F
+where
+    F: Fn() -> R + Send + Clone + 'static,
+    R: Future<Output = Result<(), E>> + 'static,
+    E: fmt::Debug + 'static,
+{
+    fn clone(&self) -> Box<dyn WorkerWarmup> {
+        Box::new(self.clone())
+    }
+
+    fn warmup(&self) -> LocalBoxFuture<'static, Result<(), ()>> {
+        (self)()
+            .map_err(|e| error!("Worker warm-up failed: {:?}", e))
+            .boxed_local()
+    }
 }
 
 #[derive(Clone)]
@@ -62,6 +80,7 @@ pub(super) struct WorkerClient {
     tx1: UnboundedSender<WorkerCommand>,
     tx2: UnboundedSender<StopCommand>,
     avail: WorkerAvailability,
+    conns: Arc<AtomicUsize>,
 }
 
 impl WorkerClient {
@@ -70,12 +89,14 @@ impl WorkerClient {
         tx1: UnboundedSender<WorkerCommand>,
         tx2: UnboundedSender<StopCommand>,
         avail: WorkerAvailability,
+        conns: Arc<AtomicUsize>,
     ) -> Self {
         WorkerClient {
             idx,
             tx1,
             tx2,
             avail,
+            conns,
         }
     }
 
@@ -89,6 +110,10 @@ impl WorkerClient {
         self.avail.available()
     }
 
+    pub(super) fn load(&self) -> WorkerLoad {
+        WorkerLoad::new(self.idx, self.conns.load(Ordering::Relaxed))
+    }
+
     pub(super) fn stop(&self, graceful: bool) -> oneshot::Receiver<bool> {
         let (result, rx) = oneshot::channel();
         let _ = self.tx2.unbounded_send(StopCommand { graceful, result });
@@ -122,13 +147,60 @@ impl WorkerAvailability {
     }
 }
 
+/// Pin current thread to one of the cpus available to the process
+#[cfg(target_os = "linux")]
+fn set_cpu_affinity(idx: usize) {
+    use std::mem;
+
+    unsafe {
+        let mut set: libc::cpu_set_t = mem::zeroed();
+        let size = mem::size_of::<libc::cpu_set_t>();
+        if libc::sched_getaffinity(0, size, &mut set) != 0 {
+            error!("Can not get cpu affinity: {}", io::Error::last_os_error());
+            return;
+        }
+
+        let cpus: Vec<usize> = (0..libc::CPU_SETSIZE as usize)
+            .filter(|cpu| libc::CPU_ISSET(*cpu, &set))
+            .collect();
+        if cpus.is_empty() {
+            return;
+        }
+        let cpu = cpus[idx % cpus.len()];
+
+        libc::CPU_ZERO(&mut set);
+        libc::CPU_SET(cpu, &mut set);
+        if libc::sched_setaffinity(0, size, &set) != 0 {
+            error!(
+                "Can not pin worker {} to cpu {}: {}",
+                idx,
+                cpu,
+                io::Error::last_os_error()
+            );
+        } else {
+            trace!("Worker {} is pinned to cpu {}", idx, cpu);
+        }
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn set_cpu_affinity(idx: usize) {
+    log::warn!(
+        "Cpu affinity is not supported on this platform, worker {} is not pinned",
+        idx
+    );
+}
+
 /// Service worker
 ///
 /// Worker accepts Socket objects via unbounded channel and starts stream
-/// processing.
+/// processing. Worker could also accept connections from its own
+/// listeners and receive datagrams from its own udp sockets.
 pub(super) struct Worker {
     rx: UnboundedReceiver<WorkerCommand>,
     rx2: UnboundedReceiver<StopCommand>,
+    listeners: Vec<(Token, PollEvented<SocketListener>)>,
+    udp: Vec<oneshot::Sender<()>>,
     services: Vec<WorkerService>,
     availability: WorkerAvailability,
     conns: Counter,
@@ -161,29 +233,59 @@ enum WorkerServiceStatus {
 }
 
 impl Worker {
+    #[allow(clippy::too_many_arguments)]
     pub(super) fn start(
         idx: usize,
         factories: Vec<Box<dyn InternalServiceFactory>>,
         availability: WorkerAvailability,
         shutdown_timeout: time::Duration,
+        warmup: Option<(Box<dyn WorkerWarmup>, Option<oneshot::Sender<bool>>)>,
+        listeners: Vec<(Token, net::TcpListener)>,
+        udp_sockets: Vec<(Box<dyn InternalUdpFactory>, net::UdpSocket)>,
+        cpu_affinity: bool,
+        srv: Server,
     ) -> WorkerClient {
         let (tx1, rx) = unbounded();
         let (tx2, rx2) = unbounded();
         let avail = availability.clone();
+        let conns = Arc::new(AtomicUsize::new(0));
+        let shared = conns.clone();
 
         Arbiter::new().send(
             async move {
                 availability.set(false);
-                let mut wrk = MAX_CONNS_COUNTER.with(move |conns| Worker {
+                if cpu_affinity {
+                    set_cpu_affinity(idx);
+                }
+
+                let listeners = listeners
+                    .into_iter()
+                    .filter_map(|(token, lst)| {
+                        match PollEvented::new(StdListener::Tcp(lst).into_listener()) {
+                            Ok(lst) => Some((token, lst)),
+                            Err(e) => {
+                                error!("Can not register listener: {}", e);
+                                None
+                            }
+                        }
+                    })
+                    .collect();
+
+                let mut wrk = Worker {
                     rx,
                     rx2,
+                    listeners,
                     availability,
+                    udp: Vec::new(),
                     factories,
                     shutdown_timeout,
                     services: Vec::new(),
-                    conns: conns.clone(),
+                    conns: Counter::with_shared(
+                        MAX_CONNS.load(Ordering::Relaxed),
+                        shared,
+                    ),
                     state: WorkerState::Unavailable(Vec::new()),
-                });
+                };
 
                 let mut fut: Vec<MapOk<LocalBoxFuture<'static, _>, _>> = Vec::new();
                 for (idx, factory) in wrk.factories.iter().enumerate() {
@@ -194,10 +296,15 @@ impl Worker {
                     }));
                 }
 
+                let udp_fut =
+                    join_all(udp_sockets.into_iter().map(|(factory, sock)| {
+                        factory.create().map(move |res| (factory, sock, res))
+                    }));
+
                 spawn(async move {
                     let res = join_all(fut).await;
                     let res: Result<Vec<_>, _> = res.into_iter().collect();
-                    match res {
+                    let started = match res {
                         Ok(services) => {
                             for item in services {
                                 for (factory, token, service) in item {
@@ -209,19 +316,74 @@ impl Worker {
                                     });
                                 }
                             }
+                            true
                         }
                         Err(e) => {
                             error!("Can not start worker: {:?}", e);
                             Arbiter::current().stop();
+                            false
+                        }
+                    };
+
+                    let mut udp_services = Vec::new();
+                    let started = started
+                        && udp_fut.await.into_iter().all(
+                            |(factory, sock, res)| match res {
+                                Ok(service) => {
+                                    udp_services.push((factory, sock, service));
+                                    true
+                                }
+                                Err(_) => {
+                                    error!(
+                                        "Can not start {:?} udp service",
+                                        factory.name()
+                                    );
+                                    Arbiter::current().stop();
+                                    false
+                                }
+                            },
+                        );
+
+                    // run warm-up tasks before worker starts accepting connections
+                    if let Some((warmup, ready)) = warmup {
+                        let res = started && warmup.warmup().await.is_ok();
+                        if let Some(ready) = ready {
+                            let _ = ready.send(res);
                         }
                     }
-                    wrk.await
+
+                    // start receiving datagrams
+                    for (factory, sock, service) in udp_services {
+                        let sock = match crate::rt::net::UdpSocket::from_std(sock) {
+                            Ok(sock) => sock,
+                            Err(e) => {
+                                error!("Can not register udp socket: {}", e);
+                                continue;
+                            }
+                        };
+                        let (tx, rx) = oneshot::channel();
+                        wrk.udp.push(tx);
+                        spawn(udp::run(
+                            factory.name().to_string(),
+                            sock,
+                            service,
+                            wrk.conns.clone(),
+                            rx,
+                        ));
+                    }
+
+                    // report worker panic to server, so it could be restarted
+                    if AssertUnwindSafe(wrk).catch_unwind().await.is_err() {
+                        error!("Worker {:?} panicked", idx);
+                        srv.worker_faulted(idx);
+                        Arbiter::current().stop();
+                    }
                 });
             }
             .boxed(),
         );
 
-        WorkerClient::new(idx, tx1, tx2, avail)
+        WorkerClient::new(idx, tx1, tx2, avail, conns)
     }
 
     fn shutdown(&mut self, force: bool) {
@@ -251,6 +413,44 @@ impl Worker {
         }
     }
 
+    /// Accept connection from worker's own listeners
+    fn accept(&mut self, cx: &mut Context<'_>) -> Option<Conn> {
+        for (token, lst) in &self.listeners {
+            loop {
+                match lst.poll_read_ready(cx, mio::Ready::readable()) {
+                    Poll::Ready(Ok(_)) => (),
+                    Poll::Ready(Err(e)) => {
+                        error!("Error polling listener: {}", e);
+                        break;
+                    }
+                    Poll::Pending => break,
+                }
+
+                match lst.get_ref().accept() {
+                    Ok(Some((io, addr))) => {
+                        return Some(Conn {
+                            io,
+                            token: *token,
+                            peer: Some(addr),
+                        })
+                    }
+                    Ok(None) => continue,
+                    Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => {
+                        let _ = lst.clear_read_ready(cx, mio::Ready::readable());
+                        break;
+                    }
+                    Err(ref e) if connection_error(e) => continue,
+                    Err(e) => {
+                        error!("Error accepting connection: {}", e);
+                        let _ = lst.clear_read_ready(cx, mio::Ready::readable());
+                        break;
+                    }
+                }
+            }
+        }
+        None
+    }
+
     fn check_readiness(&mut self, cx: &mut Context<'_>) -> Result<bool, (Token, usize)> {
         let mut ready = self.conns.available(cx);
         let mut failed = None;
@@ -322,14 +522,16 @@ impl Future for Worker {
             Pin::new(&mut self.rx2).poll_next(cx)
         {
             self.availability.set(false);
-            let num = num_connections();
+            self.listeners.clear();
+            self.udp.clear();
+            let num = self.conns.total();
             if num == 0 {
                 info!("Shutting down worker, 0 connections");
                 let _ = result.send(true);
                 return Poll::Ready(());
             } else if graceful {
                 self.shutdown(false);
-                let num = num_connections();
+                let num = self.conns.total();
                 if num != 0 {
                     info!("Graceful worker shutdown, {} connections", num);
                     self.state = WorkerState::Shutdown(
@@ -351,6 +553,7 @@ impl Future for Worker {
             }
         }
 
+        let num = self.conns.total();
         match self.state {
             WorkerState::Unavailable(ref mut conns) => {
                 let conn = conns.pop();
@@ -419,7 +622,6 @@ impl Future for Worker {
                 self.poll(cx)
             }
             WorkerState::Shutdown(ref mut t1, ref mut t2, ref mut tx) => {
-                let num = num_connections();
                 if num == 0 {
                     let _ = tx.take().unwrap().send(true);
                     Arbiter::current().stop();
@@ -451,9 +653,15 @@ impl Future for Worker {
             }
             WorkerState::Available => {
                 loop {
-                    match Pin::new(&mut self.rx).poll_next(cx) {
+                    let msg = match Pin::new(&mut self.rx).poll_next(cx) {
+                        Poll::Ready(Some(WorkerCommand(msg))) => Some(msg),
+                        Poll::Pending => self.accept(cx),
+                        Poll::Ready(None) => return Poll::Ready(()),
+                    };
+
+                    match msg {
                         // handle incoming io stream
-                        Poll::Ready(Some(WorkerCommand(msg))) => {
+                        Some(msg) => {
                             match self.check_readiness(cx) {
                                 Ok(true) => {
                                     let guard = self.conns.get();
@@ -485,11 +693,10 @@ impl Future for Worker {
                             }
                             return self.poll(cx);
                         }
-                        Poll::Pending => {
+                        None => {
                             self.state = WorkerState::Available;
                             return Poll::Pending;
                         }
-                        Poll::Ready(None) => return Poll::Ready(()),
                     }
                 }
             }
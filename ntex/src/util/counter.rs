@@ -1,5 +1,7 @@
 use std::cell::Cell;
 use std::rc::Rc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
 use std::task;
 
 use crate::task::LocalWaker;
@@ -14,6 +16,7 @@ struct CounterInner {
     count: Cell<usize>,
     capacity: usize,
     task: LocalWaker,
+    shared: Option<Arc<AtomicUsize>>,
 }
 
 impl Counter {
@@ -23,6 +26,18 @@ impl Counter {
             capacity,
             count: Cell::new(0),
             task: LocalWaker::new(),
+            shared: None,
+        }))
+    }
+
+    /// Create `Counter` instance that mirrors total number of acquired
+    /// counts to atomic counter, so it could be read from other threads.
+    pub(crate) fn with_shared(capacity: usize, shared: Arc<AtomicUsize>) -> Self {
+        Counter(Rc::new(CounterInner {
+            capacity,
+            count: Cell::new(0),
+            task: LocalWaker::new(),
+            shared: Some(shared),
         }))
     }
 
@@ -63,11 +78,17 @@ impl Drop for CounterGuard {
 impl CounterInner {
     fn inc(&self) {
         self.count.set(self.count.get() + 1);
+        if let Some(ref shared) = self.shared {
+            shared.fetch_add(1, Ordering::Relaxed);
+        }
     }
 
     fn dec(&self) {
         let num = self.count.get();
         self.count.set(num - 1);
+        if let Some(ref shared) = self.shared {
+            shared.fetch_sub(1, Ordering::Relaxed);
+        }
         if num == self.capacity {
             self.task.wake();
         }
@@ -4,6 +4,7 @@ pub mod framed;
 pub mod inflight;
 pub mod keepalive;
 pub mod order;
+pub mod shutdown;
 pub mod stream;
 pub mod time;
 pub mod timeout;
@@ -0,0 +1,342 @@
+//! Structured service shutdown
+use std::cell::RefCell;
+use std::convert::Infallible;
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use futures::future::{ok, Ready};
+
+use crate::rt::time::{delay_until, Delay, Instant};
+use crate::service::{Service, Transform};
+
+/// Shutdown order of middleware and its inner service
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ShutdownOrder {
+    /// Shutdown middleware and inner service concurrently
+    Concurrent,
+    /// Shutdown middleware first, then inner service
+    OuterFirst,
+    /// Shutdown inner service first, then middleware.
+    ///
+    /// Useful for stateful middlewares that must flush state after all
+    /// in-flight requests are completed, like sessions stores.
+    InnerFirst,
+}
+
+/// Structured shutdown protocol for middleware services.
+///
+/// Shutdown is performed in two stages, middleware stage and inner service
+/// stage, stages are executed in specified order. Each stage could have
+/// timeout, if stage does not complete within timeout it is considered
+/// completed.
+///
+/// `SessionManager` middleware uses `ShutdownOrder::InnerFirst` order,
+/// session store is flushed after inner service completes shutdown.
+///
+/// ```rust,ignore
+/// impl<S: Service> Service for SessionManagerMiddleware<S> {
+///     fn poll_shutdown(&self, cx: &mut Context<'_>, is_error: bool) -> Poll<()> {
+///         self.shutdown.poll_shutdown(cx, is_error, &*self.service, |cx, is_error| {
+///             self.store.poll_shutdown(cx, is_error)
+///         })
+///     }
+/// }
+/// ```
+pub struct Shutdown {
+    order: ShutdownOrder,
+    outer_timeout: Option<Duration>,
+    inner_timeout: Option<Duration>,
+    state: RefCell<(Stage, Stage)>,
+}
+
+enum Stage {
+    Idle,
+    Running(Option<Delay>),
+    Done,
+}
+
+impl Shutdown {
+    /// Create shutdown protocol with specified order and without timeouts
+    pub fn new(order: ShutdownOrder) -> Self {
+        Shutdown {
+            order,
+            outer_timeout: None,
+            inner_timeout: None,
+            state: RefCell::new((Stage::Idle, Stage::Idle)),
+        }
+    }
+
+    /// Set timeout for middleware shutdown stage
+    pub fn outer_timeout(mut self, timeout: Duration) -> Self {
+        self.outer_timeout = Some(timeout);
+        self
+    }
+
+    /// Set timeout for inner service shutdown stage
+    pub fn inner_timeout(mut self, timeout: Duration) -> Self {
+        self.inner_timeout = Some(timeout);
+        self
+    }
+
+    /// Shutdown order
+    pub fn order(&self) -> ShutdownOrder {
+        self.order
+    }
+
+    /// Poll shutdown stages.
+    ///
+    /// `outer` is middleware's own shutdown function.
+    pub fn poll_shutdown<S, F>(
+        &self,
+        cx: &mut Context<'_>,
+        is_error: bool,
+        inner: &S,
+        mut outer: F,
+    ) -> Poll<()>
+    where
+        S: Service,
+        F: FnMut(&mut Context<'_>, bool) -> Poll<()>,
+    {
+        let mut state = self.state.borrow_mut();
+        let (ref mut outer_st, ref mut inner_st) = *state;
+        let mut outer = |cx: &mut Context<'_>| {
+            outer_st.poll(cx, self.outer_timeout, "Middleware", |cx| {
+                outer(cx, is_error)
+            })
+        };
+        let mut inner = |cx: &mut Context<'_>| {
+            inner_st.poll(cx, self.inner_timeout, "Inner service", |cx| {
+                inner.poll_shutdown(cx, is_error)
+            })
+        };
+
+        match self.order {
+            ShutdownOrder::Concurrent => {
+                let outer = outer(cx);
+                let inner = inner(cx);
+                if outer.is_ready() && inner.is_ready() {
+                    Poll::Ready(())
+                } else {
+                    Poll::Pending
+                }
+            }
+            ShutdownOrder::OuterFirst => {
+                if outer(cx).is_ready() {
+                    inner(cx)
+                } else {
+                    Poll::Pending
+                }
+            }
+            ShutdownOrder::InnerFirst => {
+                if inner(cx).is_ready() {
+                    outer(cx)
+                } else {
+                    Poll::Pending
+                }
+            }
+        }
+    }
+}
+
+impl Stage {
+    fn poll<F>(
+        &mut self,
+        cx: &mut Context<'_>,
+        timeout: Option<Duration>,
+        name: &str,
+        f: F,
+    ) -> Poll<()>
+    where
+        F: FnOnce(&mut Context<'_>) -> Poll<()>,
+    {
+        if let Stage::Idle = self {
+            *self = Stage::Running(timeout.map(|t| delay_until(Instant::now() + t)));
+        }
+
+        match self {
+            Stage::Done => Poll::Ready(()),
+            Stage::Running(ref mut delay) => {
+                if f(cx).is_ready() {
+                    *self = Stage::Done;
+                    return Poll::Ready(());
+                }
+                if let Some(ref mut delay) = delay {
+                    if Pin::new(delay).poll(cx).is_ready() {
+                        log::warn!("{} shutdown timeout, continue", name);
+                        *self = Stage::Done;
+                        return Poll::Ready(());
+                    }
+                }
+                Poll::Pending
+            }
+            Stage::Idle => unreachable!(),
+        }
+    }
+}
+
+/// Limit service shutdown time.
+///
+/// If inner service does not complete shutdown within timeout, shutdown
+/// is considered completed.
+#[derive(Debug, Clone)]
+pub struct ShutdownTimeout {
+    timeout: Duration,
+}
+
+impl ShutdownTimeout {
+    /// Create shutdown timeout transform
+    pub fn new(timeout: Duration) -> Self {
+        ShutdownTimeout { timeout }
+    }
+}
+
+impl<S: Service> Transform<S> for ShutdownTimeout {
+    type Request = S::Request;
+    type Response = S::Response;
+    type Error = S::Error;
+    type InitError = Infallible;
+    type Transform = ShutdownTimeoutService<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ok(ShutdownTimeoutService {
+            service,
+            timeout: self.timeout,
+            stage: RefCell::new(Stage::Idle),
+        })
+    }
+}
+
+/// Service with limited shutdown time
+pub struct ShutdownTimeoutService<S> {
+    service: S,
+    timeout: Duration,
+    stage: RefCell<Stage>,
+}
+
+impl<S: Service> Service for ShutdownTimeoutService<S> {
+    type Request = S::Request;
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = S::Future;
+
+    #[inline]
+    fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(cx)
+    }
+
+    fn poll_shutdown(&self, cx: &mut Context<'_>, is_error: bool) -> Poll<()> {
+        let service = &self.service;
+        self.stage
+            .borrow_mut()
+            .poll(cx, Some(self.timeout), "Service", |cx| {
+                service.poll_shutdown(cx, is_error)
+            })
+    }
+
+    #[inline]
+    fn call(&self, req: S::Request) -> Self::Future {
+        self.service.call(req)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    use futures::future::{lazy, poll_fn};
+
+    use super::*;
+
+    struct Srv {
+        log: Rc<RefCell<Vec<&'static str>>>,
+        ready: Rc<Cell<bool>>,
+    }
+
+    impl Service for Srv {
+        type Request = ();
+        type Response = ();
+        type Error = ();
+        type Future = Ready<Result<(), ()>>;
+
+        fn poll_ready(&self, _: &mut Context<'_>) -> Poll<Result<(), ()>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn poll_shutdown(&self, _: &mut Context<'_>, _: bool) -> Poll<()> {
+            if self.ready.get() {
+                self.log.borrow_mut().push("inner");
+                Poll::Ready(())
+            } else {
+                Poll::Pending
+            }
+        }
+
+        fn call(&self, _: ()) -> Self::Future {
+            ok(())
+        }
+    }
+
+    #[ntex_rt::test]
+    async fn test_inner_first() {
+        let log = Rc::new(RefCell::new(Vec::new()));
+        let ready = Rc::new(Cell::new(false));
+        let srv = Srv {
+            log: log.clone(),
+            ready: ready.clone(),
+        };
+        let shutdown = Shutdown::new(ShutdownOrder::InnerFirst);
+        let poll = |cx: &mut Context<'_>| {
+            shutdown.poll_shutdown(cx, false, &srv, |_, _| {
+                log.borrow_mut().push("outer");
+                Poll::Ready(())
+            })
+        };
+
+        assert!(lazy(|cx| poll(cx)).await.is_pending());
+        assert!(log.borrow().is_empty());
+
+        ready.set(true);
+        assert!(lazy(|cx| poll(cx)).await.is_ready());
+        assert_eq!(&*log.borrow(), &["inner", "outer"]);
+
+        // completed stages are not polled again
+        assert!(lazy(|cx| poll(cx)).await.is_ready());
+        assert_eq!(log.borrow().len(), 2);
+    }
+
+    #[ntex_rt::test]
+    async fn test_outer_first_timeout() {
+        let log = Rc::new(RefCell::new(Vec::new()));
+        let srv = Srv {
+            log: log.clone(),
+            ready: Rc::new(Cell::new(true)),
+        };
+        let shutdown = Shutdown::new(ShutdownOrder::OuterFirst)
+            .outer_timeout(Duration::from_millis(50));
+
+        let start = Instant::now();
+        poll_fn(|cx| shutdown.poll_shutdown(cx, false, &srv, |_, _| Poll::Pending))
+            .await;
+        assert!(Instant::now() - start >= Duration::from_millis(50));
+        assert_eq!(&*log.borrow(), &["inner"]);
+    }
+
+    #[ntex_rt::test]
+    async fn test_shutdown_timeout() {
+        let srv = ShutdownTimeout::new(Duration::from_millis(50))
+            .new_transform(Srv {
+                log: Rc::new(RefCell::new(Vec::new())),
+                ready: Rc::new(Cell::new(false)),
+            })
+            .await
+            .unwrap();
+
+        let start = Instant::now();
+        poll_fn(|cx| srv.poll_shutdown(cx, false)).await;
+        assert!(Instant::now() - start >= Duration::from_millis(50));
+    }
+}
@@ -1,4 +1,6 @@
+use std::any::TypeId;
 use std::cell::RefCell;
+use std::collections::HashMap;
 use std::fmt;
 use std::future::Future;
 use std::marker::PhantomData;
@@ -7,22 +9,26 @@ use std::rc::Rc;
 use futures::future::{FutureExt, LocalBoxFuture};
 
 use crate::http::body::{Body, MessageBody};
-use crate::http::Extensions;
+use crate::http::{Extensions, StatusCode};
 use crate::router::ResourceDef;
 use crate::service::boxed::{self, BoxServiceFactory};
-use crate::service::{
-    apply, apply_fn_factory, IntoServiceFactory, ServiceFactory, Transform,
-};
+use crate::service::{apply, IntoServiceFactory, Service, ServiceFactory, Transform};
 
 use super::app_service::{AppEntry, AppFactory, AppRoutingFactory};
 use super::config::ServiceConfig;
+use super::dynamic::DynamicRouter;
+use super::error::DefaultErrorBody;
+use super::handler::StrictContentType;
+use super::httprequest::HttpRequest;
+use super::middleware::{from_fn, Next};
+use super::report::ErrorReporter;
 use super::request::WebRequest;
 use super::resource::Resource;
 use super::response::WebResponse;
 use super::route::Route;
 use super::service::{AppServiceFactory, ServiceFactoryWrapper, WebServiceFactory};
 use super::types::data::{Data, DataFactory};
-use super::{DefaultError, ErrorRenderer};
+use super::{DefaultError, ErrorRenderer, HttpResponse};
 
 type HttpNewService<Err: ErrorRenderer> =
     BoxServiceFactory<(), WebRequest<Err>, WebResponse, Err::Container, ()>;
@@ -42,6 +48,11 @@ pub struct App<T, B, Err: ErrorRenderer = DefaultError> {
     extensions: Extensions,
     error_renderer: Err,
     case_insensitive: bool,
+    reporter: Option<Rc<dyn ErrorReporter>>,
+    dynamic: Option<DynamicRouter<Err>>,
+    patterns: HashMap<String, String>,
+    validate: bool,
+    data_types: Vec<TypeId>,
     _t: PhantomData<B>,
 }
 
@@ -60,6 +71,11 @@ impl App<AppEntry<DefaultError>, Body, DefaultError> {
             extensions: Extensions::new(),
             error_renderer: DefaultError,
             case_insensitive: false,
+            reporter: None,
+            dynamic: None,
+            patterns: HashMap::new(),
+            validate: false,
+            data_types: Vec::new(),
             _t: PhantomData,
         }
     }
@@ -80,6 +96,11 @@ impl<Err: ErrorRenderer> App<AppEntry<Err>, Body, Err> {
             extensions: Extensions::new(),
             error_renderer: err,
             case_insensitive: false,
+            reporter: None,
+            dynamic: None,
+            patterns: HashMap::new(),
+            validate: false,
+            data_types: Vec::new(),
             _t: PhantomData,
         }
     }
@@ -142,6 +163,7 @@ where
         D: 'static,
         E: std::fmt::Debug,
     {
+        self.data_types.push(TypeId::of::<Data<D>>());
         self.data_factories.push(Box::new(move || {
             {
                 let fut = data();
@@ -310,6 +332,33 @@ where
         self
     }
 
+    /// Set custom response for framework generated *404 Not Found* and
+    /// *405 Method Not Allowed* responses.
+    ///
+    /// By default these responses are rendered by application's error
+    /// renderer, check `ErrorRenderer::default_response()`. Function
+    /// is not used for requests handled by the default service.
+    ///
+    /// ```rust
+    /// use ntex::web::{self, App, HttpResponse};
+    ///
+    /// fn main() {
+    ///     let app = App::new()
+    ///         .default_error_body(|_, status| {
+    ///             HttpResponse::build(status).body(format!("error: {}", status))
+    ///         })
+    ///         .service(web::resource("/index.html").to(|| async { HttpResponse::Ok() }));
+    /// }
+    /// ```
+    pub fn default_error_body<F>(mut self, f: F) -> Self
+    where
+        F: Fn(&HttpRequest, StatusCode) -> HttpResponse + 'static,
+    {
+        self.data
+            .push(Box::new(Data::new(DefaultErrorBody(Box::new(f)))));
+        self
+    }
+
     /// Register an external resource.
     ///
     /// External resources are useful for URL generation purposes only
@@ -407,6 +456,11 @@ where
             extensions: self.extensions,
             error_renderer: self.error_renderer,
             case_insensitive: self.case_insensitive,
+            reporter: self.reporter,
+            dynamic: self.dynamic,
+            patterns: self.patterns,
+            validate: self.validate,
+            data_types: self.data_types,
             _t: PhantomData,
         }
     }
@@ -418,8 +472,11 @@ where
     ///
     /// Use middleware when you need to read or modify *every* request or response in some way.
     ///
+    /// Closure receives request and `next` handle of the inner service,
+    /// handle could be moved into async block. Check
+    /// [`middleware::from_fn()`](middleware/fn.from_fn.html).
+    ///
     /// ```rust
-    /// use ntex::service::Service;
     /// use ntex::web;
     /// use ntex::http::header::{CONTENT_TYPE, HeaderValue};
     ///
@@ -429,15 +486,12 @@ where
     ///
     /// fn main() {
     ///     let app = web::App::new()
-    ///         .wrap_fn(|req, srv| {
-    ///             let fut = srv.call(req);
-    ///             async {
-    ///                 let mut res = fut.await?;
-    ///                 res.headers_mut().insert(
-    ///                    CONTENT_TYPE, HeaderValue::from_static("text/plain"),
-    ///                 );
-    ///                 Ok(res)
-    ///             }
+    ///         .wrap_fn(|req, next| async move {
+    ///             let mut res = next.call(req).await?;
+    ///             res.headers_mut().insert(
+    ///                CONTENT_TYPE, HeaderValue::from_static("text/plain"),
+    ///             );
+    ///             Ok(res)
     ///         })
     ///         .route("/index.html", web::get().to(index));
     /// }
@@ -458,11 +512,13 @@ where
     >
     where
         B1: MessageBody,
-        F: Fn(WebRequest<Err>, &T::Service) -> R + Clone,
+        F: Fn(WebRequest<Err>, Next<Err, B>) -> R,
         R: Future<Output = Result<WebResponse<B1>, Err::Container>>,
+        T::Service: 'static,
+        <T::Service as Service>::Future: 'static,
     {
         App {
-            endpoint: apply_fn_factory(self.endpoint, mw),
+            endpoint: apply(from_fn(mw), self.endpoint),
             data: self.data,
             data_factories: self.data_factories,
             services: self.services,
@@ -472,10 +528,28 @@ where
             extensions: self.extensions,
             error_renderer: self.error_renderer,
             case_insensitive: self.case_insensitive,
+            reporter: self.reporter,
+            dynamic: self.dynamic,
+            patterns: self.patterns,
+            validate: self.validate,
+            data_types: self.data_types,
             _t: PhantomData,
         }
     }
 
+    /// Set error reporter.
+    ///
+    /// Reporter get called for all *5xx* responses and for panics with
+    /// request metadata. Check [`ErrorReporter`](report/trait.ErrorReporter.html)
+    /// for details.
+    pub fn error_reporter<R>(mut self, reporter: R) -> Self
+    where
+        R: ErrorReporter + 'static,
+    {
+        self.reporter = Some(Rc::new(reporter));
+        self
+    }
+
     /// Use ascii case-insensitive routing.
     ///
     /// Only static segments could be case-insensitive.
@@ -483,6 +557,102 @@ where
         self.case_insensitive = true;
         self
     }
+
+    /// Use routing table that could be changed at runtime.
+    ///
+    /// Dynamic routes are checked after all statically registered services,
+    /// check [`DynamicRouter`](struct.DynamicRouter.html) for details.
+    pub fn dynamic_routes(mut self, router: DynamicRouter<Err>) -> Self {
+        self.dynamic = Some(router);
+        self
+    }
+
+    /// Validate application data during application construction.
+    ///
+    /// Application checks that `Data<T>` objects required by resource
+    /// handlers are registered with the application, scope or resource.
+    /// Missing objects get logged and application construction fails,
+    /// so misconfiguration is detected at startup rather than with
+    /// *500 Internal Server Error* responses at request time. Extractor
+    /// configs, like `JsonConfig`, are not validated, extractors fall back
+    /// to default configuration. Services registered with `web::service()`
+    /// are not validated.
+    ///
+    /// ```rust
+    /// use ntex::web::{self, App, HttpResponse};
+    ///
+    /// async fn index(data: web::types::Data<usize>) -> HttpResponse {
+    ///     HttpResponse::Ok().body(format!("{}", data.get_ref()))
+    /// }
+    ///
+    /// fn main() {
+    ///     let app = App::new()
+    ///         .validate()
+    ///         .data(10usize)
+    ///         .route("/", web::get().to(index));
+    /// }
+    /// ```
+    pub fn validate(mut self) -> Self {
+        self.validate = true;
+        self
+    }
+
+    /// Register named segment pattern.
+    ///
+    /// Dynamic segments of resources and scopes could reference registered
+    /// pattern by its name, i.e. `{user_id:id}`. Reference gets replaced
+    /// with pattern's regular expression during application construction,
+    /// so segment constraints are defined in one place and requests
+    /// with non-matching segments are rejected by router. Segments that
+    /// reference unknown names are treated as regular expressions.
+    /// Resources registered with `DynamicRouter` are not affected.
+    ///
+    /// ```rust
+    /// use ntex::web::{self, App, HttpResponse};
+    ///
+    /// fn main() {
+    ///     let app = App::new()
+    ///         .segment_pattern("id", r"\d+")
+    ///         .segment_pattern("uuid", "[0-9a-fA-F]{8}(-[0-9a-fA-F]{4}){3}-[0-9a-fA-F]{12}")
+    ///         .route("/users/{user_id:id}", web::get().to(|| async { HttpResponse::Ok() }))
+    ///         .route("/orders/{order:uuid}", web::get().to(|| async { HttpResponse::Ok() }));
+    /// }
+    /// ```
+    pub fn segment_pattern<N, P>(mut self, name: N, pattern: P) -> Self
+    where
+        N: Into<String>,
+        P: Into<String>,
+    {
+        self.patterns.insert(name.into(), pattern.into());
+        self
+    }
+
+    /// Enable strict content-type enforcement.
+    ///
+    /// Requests with payload get rejected with *415 Unsupported Media Type*
+    /// response if content type is not accepted by route's extractors, i.e.
+    /// `Json<T>` or `Form<T>`. Request is rejected before extractors read
+    /// payload. Scopes could override this setting.
+    ///
+    /// ```rust
+    /// use ntex::web::{self, App, HttpResponse};
+    ///
+    /// #[derive(serde_derive::Deserialize)]
+    /// struct Info {
+    ///     name: String,
+    /// }
+    ///
+    /// fn main() {
+    ///     let app = App::new()
+    ///         .strict_content_type(true)
+    ///         .route("/", web::post().to(|info: web::types::Json<Info>| async move {
+    ///             HttpResponse::Ok().body(format!("{}", info.name))
+    ///         }));
+    /// }
+    /// ```
+    pub fn strict_content_type(self, enabled: bool) -> Self {
+        self.app_data(StrictContentType(enabled))
+    }
 }
 
 impl<T, B, Err> IntoServiceFactory<AppFactory<T, B, Err>> for App<T, B, Err>
@@ -508,6 +678,11 @@ where
             factory_ref: self.factory_ref,
             extensions: RefCell::new(Some(self.extensions)),
             case_insensitive: self.case_insensitive,
+            reporter: self.reporter,
+            dynamic: self.dynamic,
+            patterns: Rc::new(self.patterns),
+            validate: self.validate,
+            data_types: self.data_types,
         }
     }
 }
@@ -518,8 +693,10 @@ mod tests {
     use futures::future::ok;
 
     use super::*;
+    use crate::http::error::ResponseError;
     use crate::http::header::{self, HeaderValue};
     use crate::http::{Method, StatusCode};
+    use crate::web::config::AppConfig;
     use crate::web::middleware::DefaultHeaders;
     use crate::web::request::WebRequest;
     use crate::web::test::{call_service, init_service, read_body, TestRequest};
@@ -572,6 +749,155 @@ mod tests {
         assert_eq!(resp.status(), StatusCode::CREATED);
     }
 
+    #[ntex_rt::test]
+    async fn test_error_reporter() {
+        use crate::web::report::{ErrorReport, ReportKind};
+        use futures::FutureExt;
+        use std::panic::AssertUnwindSafe;
+
+        let reports = Rc::new(RefCell::new(Vec::new()));
+        let reports2 = reports.clone();
+
+        let srv = init_service(
+            App::new()
+                .error_reporter(move |report: &ErrorReport| {
+                    reports2.borrow_mut().push((
+                        report.kind(),
+                        report.status(),
+                        report.route().map(|s| s.to_string()),
+                        report.request_id().map(|s| s.to_string()),
+                        report.headers().len(),
+                        report.message().map(|s| s.to_string()),
+                    ))
+                })
+                .service(web::resource("/ok").to(|| async { HttpResponse::Ok() }))
+                .service(
+                    web::resource("/fail/{id}")
+                        .to(|| async { HttpResponse::InternalServerError() }),
+                )
+                .service(web::resource("/panic").to(|| async {
+                    if true {
+                        panic!("test panic");
+                    }
+                    HttpResponse::Ok()
+                })),
+        )
+        .await;
+
+        let req = TestRequest::with_uri("/ok").to_request();
+        let resp = srv.call(req).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert!(reports.borrow().is_empty());
+
+        let req = TestRequest::with_uri("/fail/1")
+            .header("x-request-id", "req-1")
+            .header(header::USER_AGENT, "test")
+            .header(header::COOKIE, "secret=1")
+            .to_request();
+        let resp = srv.call(req).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(
+            reports.borrow_mut().pop().unwrap(),
+            (
+                ReportKind::Response,
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Some("/fail/{id}".to_string()),
+                Some("req-1".to_string()),
+                1,
+                None
+            )
+        );
+
+        let req = TestRequest::with_uri("/panic").to_request();
+        let res = AssertUnwindSafe(srv.call(req)).catch_unwind().await;
+        assert!(res.is_err());
+        assert_eq!(
+            reports.borrow_mut().pop().unwrap(),
+            (
+                ReportKind::Panic,
+                StatusCode::INTERNAL_SERVER_ERROR,
+                None,
+                None,
+                0,
+                Some("test panic".to_string())
+            )
+        );
+    }
+
+    #[ntex_rt::test]
+    async fn test_default_error_body() {
+        let srv = init_service(App::new().service(
+            web::resource("/test").route(web::get().to(|| async { HttpResponse::Ok() })),
+        ))
+        .await;
+
+        let req = TestRequest::with_uri("/blah").to_request();
+        let resp = srv.call(req).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+        assert_eq!(read_body(resp).await, Bytes::new());
+
+        let req = TestRequest::with_uri("/blah")
+            .header(header::ACCEPT, "application/json, text/html;q=0.9")
+            .to_request();
+        let resp = srv.call(req).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+        assert_eq!(
+            resp.headers().get(header::CONTENT_TYPE).unwrap(),
+            "application/json"
+        );
+        assert_eq!(
+            read_body(resp).await,
+            Bytes::from_static(b"{\"status\":404,\"error\":\"Not Found\"}")
+        );
+
+        let req = TestRequest::with_uri("/test")
+            .method(Method::POST)
+            .header(header::ACCEPT, "text/html")
+            .to_request();
+        let resp = srv.call(req).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::METHOD_NOT_ALLOWED);
+        assert_eq!(
+            resp.headers().get(header::CONTENT_TYPE).unwrap(),
+            "text/html; charset=utf-8"
+        );
+
+        let srv = init_service(
+            App::new()
+                .default_error_body(|req, status| {
+                    HttpResponse::build(status).body(format!(
+                        "{} {}",
+                        status,
+                        req.path()
+                    ))
+                })
+                .service(
+                    web::scope("/app").data(1usize).service(
+                        web::resource("/test")
+                            .route(web::get().to(|| async { HttpResponse::Ok() })),
+                    ),
+                ),
+        )
+        .await;
+
+        let req = TestRequest::with_uri("/blah").to_request();
+        let resp = srv.call(req).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+        assert_eq!(
+            read_body(resp).await,
+            Bytes::from_static(b"404 Not Found /blah")
+        );
+
+        let req = TestRequest::with_uri("/app/test")
+            .method(Method::POST)
+            .to_request();
+        let resp = srv.call(req).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::METHOD_NOT_ALLOWED);
+        assert_eq!(
+            read_body(resp).await,
+            Bytes::from_static(b"405 Method Not Allowed /app/test")
+        );
+    }
+
     #[ntex_rt::test]
     async fn test_data_factory() {
         let srv = init_service(
@@ -631,6 +957,41 @@ mod tests {
         );
     }
 
+    #[ntex_rt::test]
+    async fn test_wrap_circuit_breaker() {
+        let srv = init_service(
+            App::new()
+                .wrap(
+                    crate::service::CircuitBreaker::new()
+                        .min_requests(2)
+                        .failure(|res: &Result<WebResponse, web::Error>| match res {
+                            Ok(res) => res.status().is_server_error(),
+                            Err(_) => true,
+                        }),
+                )
+                .route("/test", web::get().to(|| async { HttpResponse::Ok() }))
+                .route(
+                    "/fail",
+                    web::get().to(|| async { HttpResponse::InternalServerError() }),
+                ),
+        )
+        .await;
+
+        let req = TestRequest::with_uri("/test").to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let req = TestRequest::with_uri("/fail").to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::INTERNAL_SERVER_ERROR);
+
+        let req = TestRequest::with_uri("/test").to_request();
+        let err = srv.call(req).await.err().unwrap();
+        assert_eq!(
+            err.error_response().status(),
+            StatusCode::SERVICE_UNAVAILABLE
+        );
+    }
+
     #[ntex_rt::test]
     async fn test_router_wrap() {
         let srv = init_service(
@@ -678,6 +1039,37 @@ mod tests {
         );
     }
 
+    #[ntex_rt::test]
+    async fn test_wrap_fn_next() {
+        let srv = init_service(
+            App::new()
+                .wrap_fn(|req, next| async move {
+                    if req.path() == "/skip" {
+                        return Ok(req.into_response(HttpResponse::NoContent().finish()));
+                    }
+                    crate::rt::time::delay_for(std::time::Duration::from_millis(10))
+                        .await;
+                    let mut res = next.call(req).await?;
+                    res.headers_mut()
+                        .insert(header::CONTENT_TYPE, HeaderValue::from_static("0001"));
+                    Ok(res)
+                })
+                .service(web::resource("/test").to(|| async { HttpResponse::Ok() })),
+        )
+        .await;
+        let req = TestRequest::with_uri("/test").to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(
+            resp.headers().get(header::CONTENT_TYPE).unwrap(),
+            HeaderValue::from_static("0001")
+        );
+
+        let req = TestRequest::with_uri("/skip").to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+    }
+
     #[ntex_rt::test]
     async fn test_router_wrap_fn() {
         let srv = init_service(
@@ -744,4 +1136,202 @@ mod tests {
         let body = read_body(resp).await;
         assert_eq!(body, Bytes::from_static(b"https://youtube.com/watch/12345"));
     }
+
+    #[ntex_rt::test]
+    async fn test_strict_content_type() {
+        #[derive(serde_derive::Deserialize)]
+        struct Info {
+            name: String,
+        }
+
+        let srv = init_service(
+            App::new()
+                .strict_content_type(true)
+                .route(
+                    "/json",
+                    web::post().to(|info: web::types::Json<Info>| async move {
+                        HttpResponse::Ok().body(info.into_inner().name)
+                    }),
+                )
+                .route(
+                    "/form",
+                    web::post().to(
+                        |_: web::types::Path<()>, info: web::types::Form<Info>| async move {
+                            HttpResponse::Ok().body(info.into_inner().name)
+                        },
+                    ),
+                )
+                .route("/any", web::post().to(|body: String| async move { body }))
+                .service(web::scope("/lax").strict_content_type(false).route(
+                    "/json",
+                    web::post().to(|info: web::types::Json<Info>| async move {
+                        HttpResponse::Ok().body(info.into_inner().name)
+                    }),
+                )),
+        )
+        .await;
+
+        let req = TestRequest::post()
+            .uri("/json")
+            .header(header::CONTENT_TYPE, "application/json")
+            .set_payload(r#"{"name": "test"}"#)
+            .to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(read_body(resp).await, Bytes::from_static(b"test"));
+
+        let req = TestRequest::post()
+            .uri("/json")
+            .header(header::CONTENT_TYPE, "text/plain")
+            .set_payload(r#"{"name": "test"}"#)
+            .to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+
+        let req = TestRequest::post()
+            .uri("/form")
+            .set_payload(r#"{"name": "test"}"#)
+            .to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+
+        let req = TestRequest::post()
+            .uri("/form")
+            .header(header::CONTENT_TYPE, "Application/X-WWW-Form-Urlencoded")
+            .set_payload("name=test")
+            .to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let req = TestRequest::post()
+            .uri("/any")
+            .header(header::CONTENT_TYPE, "text/plain")
+            .set_payload("test")
+            .to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        // requests without payload are not checked
+        let req = TestRequest::post()
+            .uri("/json")
+            .header(header::CONTENT_LENGTH, "0")
+            .to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+
+        let req = TestRequest::post()
+            .uri("/lax/json")
+            .header(header::CONTENT_TYPE, "text/plain")
+            .set_payload(r#"{"name": "test"}"#)
+            .to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[ntex_rt::test]
+    async fn test_segment_pattern() {
+        let srv = init_service(
+            App::new()
+                .segment_pattern("id", r"\d+")
+                .segment_pattern("lang", "[a-z]{2}")
+                .route(
+                    "/users/{user_id:id}",
+                    web::get().to(|p: web::types::Path<u32>| async move {
+                        format!("user {}", p)
+                    }),
+                )
+                .service(web::scope("/{lang:lang}").route(
+                    "/items/{item:id}/{name:[a-z]+}",
+                    web::get().to(|| async { HttpResponse::Ok() }),
+                )),
+        )
+        .await;
+
+        let req = TestRequest::with_uri("/users/10").to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(read_body(resp).await, Bytes::from_static(b"user 10"));
+
+        let req = TestRequest::with_uri("/users/abc").to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+
+        let req = TestRequest::with_uri("/en/items/1/test").to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let req = TestRequest::with_uri("/eng/items/1/test").to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+
+        let req = TestRequest::with_uri("/en/items/a/test").to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[ntex_rt::test]
+    async fn test_validate() {
+        async fn index(
+            _: web::types::Data<usize>,
+            _: web::types::Data<u32>,
+        ) -> HttpResponse {
+            HttpResponse::Ok().finish()
+        }
+
+        let srv = init_service(
+            App::new()
+                .validate()
+                .data(10usize)
+                .data_factory(|| ok::<_, ()>(1u32))
+                .route("/", web::get().to(index))
+                .service(
+                    web::scope("/scope")
+                        .data(1u64)
+                        .route("/", web::get().to(|_: web::types::Data<u64>| async {
+                            HttpResponse::Ok()
+                        }))
+                        .service(web::resource("/res").data('c').to(
+                            |_: web::types::Data<char>, _: web::types::Data<usize>| async {
+                                HttpResponse::Ok()
+                            },
+                        )),
+                ),
+        )
+        .await;
+        let req = TestRequest::with_uri("/").to_request();
+        let resp = srv.call(req).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        let req = TestRequest::with_uri("/scope/res").to_request();
+        let resp = srv.call(req).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let app = App::new()
+            .validate()
+            .data(10usize)
+            .route("/", web::get().to(index));
+        assert!(app
+            .into_factory()
+            .new_service(AppConfig::default())
+            .await
+            .is_err());
+
+        let app = App::new().validate().data(10u32).service(
+            web::scope("/scope").data(1u64).route(
+                "/",
+                web::get().to(|_: web::types::Data<usize>| async { HttpResponse::Ok() }),
+            ),
+        );
+        assert!(app
+            .into_factory()
+            .new_service(AppConfig::default())
+            .await
+            .is_err());
+
+        // validation is disabled
+        let app = App::new().route("/", web::get().to(index));
+        assert!(app
+            .into_factory()
+            .new_service(AppConfig::default())
+            .await
+            .is_ok());
+    }
 }
@@ -1,25 +1,31 @@
+use std::any::TypeId;
 use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
 use std::future::Future;
 use std::marker::PhantomData;
+use std::panic::{self, AssertUnwindSafe};
 use std::pin::Pin;
 use std::rc::Rc;
 use std::task::{Context, Poll};
 
 use futures::future::{ok, FutureExt, LocalBoxFuture};
 
-use crate::http::{Extensions, Request, Response};
+use crate::http::error::ResponseError;
+use crate::http::{Extensions, Request, StatusCode};
 use crate::router::{Path, ResourceDef, ResourceInfo, Router};
 use crate::service::boxed::{self, BoxService, BoxServiceFactory};
 use crate::{fn_service, Service, ServiceFactory};
 
 use super::config::AppConfig;
-use super::error::ErrorRenderer;
+use super::dynamic::{DynamicRouter, DynamicRouting};
+use super::error::{default_response, ErrorRenderer};
 use super::guard::Guard;
 use super::httprequest::{HttpRequest, HttpRequestPool};
+use super::report::{panic_message, ErrorReporter, ReportKind, RequestMeta};
 use super::request::WebRequest;
 use super::response::WebResponse;
 use super::rmap::ResourceMap;
-use super::service::{AppServiceFactory, WebServiceConfig};
+use super::service::{AppServiceFactory, DataValidation, WebServiceConfig};
 use super::types::data::DataFactory;
 
 type Guards = Vec<Box<dyn Guard>>;
@@ -54,6 +60,11 @@ where
     pub(super) factory_ref: Rc<RefCell<Option<AppRoutingFactory<Err>>>>,
     pub(super) external: RefCell<Vec<ResourceDef>>,
     pub(super) case_insensitive: bool,
+    pub(super) reporter: Option<Rc<dyn ErrorReporter>>,
+    pub(super) dynamic: Option<DynamicRouter<Err>>,
+    pub(super) patterns: Rc<HashMap<String, String>>,
+    pub(super) validate: bool,
+    pub(super) data_types: Vec<TypeId>,
 }
 
 impl<T, B, Err> ServiceFactory for AppFactory<T, B, Err>
@@ -79,13 +90,33 @@ where
         // update resource default service
         let default = self.default.clone().unwrap_or_else(|| {
             Rc::new(boxed::factory(fn_service(|req: WebRequest<Err>| {
-                ok(req.into_response(Response::NotFound().finish()))
+                let req = req.into_parts().0;
+                let res = default_response::<Err>(&req, StatusCode::NOT_FOUND);
+                ok(WebResponse::new(req, res))
             })))
         });
 
+        // data validation
+        let validation = if self.validate {
+            let data: HashSet<_> = self.data.iter().map(|f| f.data_type()).collect();
+            let mut app = data.clone();
+            app.extend(self.data_types.iter().cloned());
+            if let Some(ref ext) = *self.extensions.borrow() {
+                app.extend(ext.type_ids());
+            }
+            Some(Rc::new(DataValidation::new(app, data)))
+        } else {
+            None
+        };
+
         // App config
-        let mut config =
-            WebServiceConfig::new(config, default.clone(), self.data.clone());
+        let mut config = WebServiceConfig::new(
+            config,
+            default.clone(),
+            self.data.clone(),
+            self.patterns.clone(),
+            validation.clone(),
+        );
 
         // register services
         std::mem::replace(&mut *self.services.borrow_mut(), Vec::new())
@@ -96,6 +127,17 @@ where
 
         let (config, services) = config.into_services();
 
+        // report missing data
+        let valid = if let Some(validation) = validation {
+            let errors = validation.errors();
+            for err in &errors {
+                log::error!("Application data validation failed: {}", err);
+            }
+            errors.is_empty()
+        } else {
+            true
+        };
+
         // complete pipeline creation
         *self.factory_ref.borrow_mut() = Some(AppRoutingFactory {
             default,
@@ -109,6 +151,7 @@ where
                     .collect(),
             ),
             case_insensitive: self.case_insensitive,
+            dynamic: self.dynamic.clone(),
         });
 
         // external resources
@@ -120,6 +163,11 @@ where
         let rmap = Rc::new(rmap);
         rmap.finish(rmap.clone());
 
+        // startup report
+        if let Some(reporter) = config.reporter() {
+            reporter.report(&rmap);
+        }
+
         AppFactoryResult {
             endpoint: None,
             endpoint_fut: self.endpoint.new_service(()),
@@ -135,6 +183,8 @@ where
             ),
             config,
             rmap,
+            reporter: self.reporter.clone(),
+            valid,
             _t: PhantomData,
         }
     }
@@ -155,6 +205,8 @@ where
     data_factories_fut: Vec<LocalBoxFuture<'static, Result<Box<dyn DataFactory>, ()>>>,
     case_insensitive: bool,
     extensions: Option<Extensions>,
+    reporter: Option<Rc<dyn ErrorReporter>>,
+    valid: bool,
     _t: PhantomData<(B, Err)>,
 }
 
@@ -174,6 +226,10 @@ where
     fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
         let this = self.project();
 
+        if !*this.valid {
+            return Poll::Ready(Err(()));
+        }
+
         // async data factories
         let mut idx = 0;
         while idx < this.data_factories_fut.len() {
@@ -209,6 +265,7 @@ where
                 config: this.config.clone(),
                 data: Rc::new(data),
                 pool: HttpRequestPool::create(),
+                reporter: this.reporter.clone(),
                 _t: PhantomData,
             }))
         } else {
@@ -232,6 +289,7 @@ where
     config: AppConfig,
     data: Rc<Extensions>,
     pool: &'static HttpRequestPool,
+    reporter: Option<Rc<dyn ErrorReporter>>,
     _t: PhantomData<Err>,
 }
 
@@ -247,7 +305,7 @@ where
     type Request = Request;
     type Response = WebResponse<B>;
     type Error = T::Error;
-    type Future = T::Future;
+    type Future = AppFactoryResponse<T::Future, B, T::Error>;
 
     #[inline]
     fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
@@ -265,6 +323,9 @@ where
         let req = if let Some(mut req) = self.pool.get_request() {
             let inner = Rc::get_mut(&mut req.0).unwrap();
             inner.path.set(head.uri.clone());
+            inner.resources.clear();
+            inner.matched = false;
+            inner.prefix = 0;
             inner.head = head;
             inner.payload = payload;
             inner.app_data = self.data.clone();
@@ -280,7 +341,85 @@ where
                 self.pool,
             )
         };
-        self.service.call(WebRequest::new(req))
+        let req = WebRequest::new(req);
+        let report = self
+            .reporter
+            .as_ref()
+            .map(|reporter| (RequestMeta::new(&req, &**reporter), reporter.clone()));
+
+        AppFactoryResponse {
+            report,
+            fut: self.service.call(req),
+            _t: PhantomData,
+        }
+    }
+}
+
+#[doc(hidden)]
+#[pin_project::pin_project]
+pub struct AppFactoryResponse<F, B, E> {
+    #[pin]
+    fut: F,
+    report: Option<(RequestMeta, Rc<dyn ErrorReporter>)>,
+    _t: PhantomData<(B, E)>,
+}
+
+impl<F, B, E> Future for AppFactoryResponse<F, B, E>
+where
+    F: Future<Output = Result<WebResponse<B>, E>>,
+    E: ResponseError,
+{
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut this = self.project();
+
+        if this.report.is_none() {
+            return this.fut.poll(cx);
+        }
+
+        // catch panics to report them, panic is resumed after reporting
+        let res =
+            match panic::catch_unwind(AssertUnwindSafe(|| this.fut.as_mut().poll(cx))) {
+                Ok(Poll::Ready(res)) => res,
+                Ok(Poll::Pending) => return Poll::Pending,
+                Err(panic) => {
+                    let (meta, reporter) = this.report.take().unwrap();
+                    reporter.report(&meta.report(
+                        ReportKind::Panic,
+                        StatusCode::INTERNAL_SERVER_ERROR,
+                        None,
+                        panic_message(&*panic),
+                    ));
+                    panic::resume_unwind(panic)
+                }
+            };
+
+        let (meta, reporter) = this.report.take().unwrap();
+        match res {
+            Ok(ref res) => {
+                if res.status().is_server_error() {
+                    reporter.report(&meta.report(
+                        ReportKind::Response,
+                        res.status(),
+                        res.match_pattern(),
+                        None,
+                    ));
+                }
+            }
+            Err(ref e) => {
+                let status = e.error_response().status();
+                if status.is_server_error() {
+                    reporter.report(&meta.report(
+                        ReportKind::Error,
+                        status,
+                        None,
+                        Some(e.to_string()),
+                    ));
+                }
+            }
+        }
+        Poll::Ready(res)
     }
 }
 
@@ -302,6 +441,7 @@ pub struct AppRoutingFactory<Err: ErrorRenderer> {
     services: Rc<Vec<(ResourceDef, HttpNewService<Err>, RefCell<Option<Guards>>)>>,
     default: Rc<HttpNewService<Err>>,
     case_insensitive: bool,
+    dynamic: Option<DynamicRouter<Err>>,
 }
 
 impl<Err: ErrorRenderer> ServiceFactory for AppRoutingFactory<Err> {
@@ -329,6 +469,7 @@ impl<Err: ErrorRenderer> ServiceFactory for AppRoutingFactory<Err> {
             default: None,
             default_fut: Some(self.default.new_service(())),
             case_insensitive: self.case_insensitive,
+            dynamic: self.dynamic.clone(),
         }
     }
 }
@@ -342,6 +483,7 @@ pub struct AppRoutingFactoryResponse<Err: ErrorRenderer> {
     default: Option<HttpService<Err>>,
     default_fut: Option<LocalBoxFuture<'static, Result<HttpService<Err>, ()>>>,
     case_insensitive: bool,
+    dynamic: Option<DynamicRouter<Err>>,
 }
 
 enum CreateAppRoutingItem<Err: ErrorRenderer> {
@@ -394,7 +536,8 @@ impl<Err: ErrorRenderer> Future for AppRoutingFactoryResponse<Err> {
                     .fold(Router::build(), |mut router, item| {
                         match item {
                             CreateAppRoutingItem::Service(path, guards, service) => {
-                                router.rdef(path, service).2 = guards;
+                                router.rdef(path.clone(), (service, Rc::new(path))).2 =
+                                    guards;
                             }
                             CreateAppRoutingItem::Future(_, _, _) => unreachable!(),
                         }
@@ -409,6 +552,7 @@ impl<Err: ErrorRenderer> Future for AppRoutingFactoryResponse<Err> {
                 ready: None,
                 router: router.finish(),
                 default: self.default.take(),
+                dynamic: self.dynamic.take().map(DynamicRouting::new),
             }))
         } else {
             Poll::Pending
@@ -417,9 +561,10 @@ impl<Err: ErrorRenderer> Future for AppRoutingFactoryResponse<Err> {
 }
 
 pub struct AppRouting<Err: ErrorRenderer> {
-    router: Router<HttpService<Err>, Guards>,
+    router: Router<(HttpService<Err>, Rc<ResourceDef>), Guards>,
     ready: Option<(WebRequest<Err>, ResourceInfo)>,
     default: Option<HttpService<Err>>,
+    dynamic: Option<DynamicRouting<Err>>,
 }
 
 impl<Err: ErrorRenderer> Service for AppRouting<Err> {
@@ -449,13 +594,24 @@ impl<Err: ErrorRenderer> Service for AppRouting<Err> {
             true
         });
 
-        if let Some((srv, _info)) = res {
-            srv.call(req)
-        } else if let Some(ref default) = self.default {
+        if let Some(((srv, rdef), _info)) = res {
+            req.add_resource(rdef.clone());
+            return srv.call(req);
+        }
+
+        if let Some(ref dynamic) = self.dynamic {
+            req = match dynamic.call(req) {
+                Ok(fut) => return fut,
+                Err(req) => req,
+            };
+        }
+
+        if let Some(ref default) = self.default {
             default.call(req)
         } else {
             let req = req.into_parts().0;
-            ok(WebResponse::new(req, Response::NotFound().finish())).boxed_local()
+            let res = default_response::<Err>(&req, StatusCode::NOT_FOUND);
+            ok(WebResponse::new(req, res)).boxed_local()
         }
     }
 }
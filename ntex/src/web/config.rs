@@ -1,10 +1,12 @@
 use std::net::SocketAddr;
 use std::rc::Rc;
+use std::sync::Arc;
 
 use crate::router::ResourceDef;
 
 use super::resource::Resource;
 use super::route::Route;
+use super::server::StartupReporter;
 use super::service::{AppServiceFactory, ServiceFactoryWrapper, WebServiceFactory};
 use super::types::data::{Data, DataFactory};
 use super::{DefaultError, ErrorRenderer};
@@ -17,11 +19,28 @@ struct AppConfigInner {
     secure: bool,
     host: String,
     addr: SocketAddr,
+    reporter: Option<Arc<StartupReporter>>,
 }
 
 impl AppConfig {
     pub(crate) fn new(secure: bool, addr: SocketAddr, host: String) -> Self {
-        AppConfig(Rc::new(AppConfigInner { secure, addr, host }))
+        AppConfig(Rc::new(AppConfigInner {
+            secure,
+            addr,
+            host,
+            reporter: None,
+        }))
+    }
+
+    pub(crate) fn with_reporter(mut self, reporter: Arc<StartupReporter>) -> Self {
+        if let Some(inner) = Rc::get_mut(&mut self.0) {
+            inner.reporter = Some(reporter);
+        }
+        self
+    }
+
+    pub(crate) fn reporter(&self) -> Option<&Arc<StartupReporter>> {
+        self.0.reporter.as_ref()
     }
 
     /// Server host name.
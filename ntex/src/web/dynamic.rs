@@ -0,0 +1,311 @@
+//! Runtime route registration
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::rc::Rc;
+use std::sync::{Arc, RwLock};
+
+use futures::future::{FutureExt, LocalBoxFuture};
+
+use crate::http::StatusCode;
+use crate::router::{ResourceDef, Router};
+use crate::service::boxed::{BoxService, BoxServiceFactory};
+use crate::service::{Service, ServiceFactory};
+
+use super::error::{default_response, DefaultError, ErrorRenderer};
+use super::guard::Guard;
+use super::request::WebRequest;
+use super::resource::Resource;
+use super::response::WebResponse;
+
+type Guards = Vec<Box<dyn Guard>>;
+type HttpService<Err: ErrorRenderer> =
+    BoxService<WebRequest<Err>, WebResponse, Err::Container>;
+type HttpNewService<Err: ErrorRenderer> =
+    BoxServiceFactory<(), WebRequest<Err>, WebResponse, Err::Container, ()>;
+type ResourceFactory<Err> =
+    Arc<dyn Fn() -> (ResourceDef, Guards, HttpNewService<Err>) + Send + Sync>;
+
+/// Identifier of dynamically registered resource
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct DynamicRouteId(usize);
+
+/// Handle to routing table that could be changed at runtime.
+///
+/// Resources are registered with a resource factory, factory gets called
+/// once per worker thread on first match. Dynamic routes are checked only
+/// if none of statically registered services match the request, each match
+/// takes read lock of the shared table. Dynamic resources are not part of
+/// the application's resource map, `url_for()` does not see them.
+///
+/// Handle is cheap to clone and could be shared between threads.
+///
+/// ```rust
+/// use ntex::web::{self, App, DynamicRouter, HttpResponse};
+///
+/// fn main() {
+///     let router = DynamicRouter::new();
+///
+///     let handle = router.clone();
+///     let app = App::new()
+///         .dynamic_routes(router)
+///         .route("/hooks", web::post().to(move || {
+///             let id = handle.add(|| {
+///                 web::resource("/hooks/test").to(|| async { HttpResponse::Ok() })
+///             });
+///             async move { format!("{:?}", id) }
+///         }));
+/// }
+/// ```
+pub struct DynamicRouter<Err: ErrorRenderer = DefaultError> {
+    table: Arc<RwLock<Table<Err>>>,
+}
+
+struct Table<Err: ErrorRenderer> {
+    version: usize,
+    next_id: usize,
+    resources: Vec<(DynamicRouteId, ResourceDef, ResourceFactory<Err>)>,
+    router: Router<DynamicRouteId, DynamicRouteId>,
+}
+
+impl<Err: ErrorRenderer> Table<Err> {
+    fn rebuild(&mut self) {
+        let mut router = Router::build();
+        for (id, rdef, _) in &self.resources {
+            router.rdef(rdef.clone(), *id).2 = Some(*id);
+        }
+        self.router = router.finish();
+        self.version += 1;
+    }
+}
+
+impl<Err: ErrorRenderer> DynamicRouter<Err> {
+    /// Create empty routing table.
+    pub fn new() -> Self {
+        DynamicRouter {
+            table: Arc::new(RwLock::new(Table {
+                version: 0,
+                next_id: 0,
+                resources: Vec::new(),
+                router: Router::build().finish(),
+            })),
+        }
+    }
+
+    /// Register resource.
+    ///
+    /// Resource path is always relative to application root. Resources
+    /// are matched in registration order.
+    pub fn add<F, T>(&self, factory: F) -> DynamicRouteId
+    where
+        F: Fn() -> Resource<Err, T> + Send + Sync + 'static,
+        T: ServiceFactory<
+                Config = (),
+                Request = WebRequest<Err>,
+                Response = WebResponse,
+                Error = Err::Container,
+                InitError = (),
+            > + 'static,
+    {
+        let rdef = factory().into_parts().0;
+        let factory: ResourceFactory<Err> = Arc::new(move || factory().into_parts());
+
+        let mut table = self.table.write().unwrap();
+        let id = DynamicRouteId(table.next_id);
+        table.next_id += 1;
+        table.resources.push((id, rdef, factory));
+        table.rebuild();
+        id
+    }
+
+    /// Unregister resource.
+    ///
+    /// Returns `false` if resource is not registered. Requests that are
+    /// already in progress are not affected.
+    pub fn remove(&self, id: DynamicRouteId) -> bool {
+        let mut table = self.table.write().unwrap();
+        let len = table.resources.len();
+        table.resources.retain(|item| item.0 != id);
+        if table.resources.len() != len {
+            table.rebuild();
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Number of registered resources.
+    pub fn len(&self) -> usize {
+        self.table.read().unwrap().resources.len()
+    }
+
+    /// Check if routing table is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl<Err: ErrorRenderer> Default for DynamicRouter<Err> {
+    fn default() -> Self {
+        DynamicRouter::new()
+    }
+}
+
+impl<Err: ErrorRenderer> Clone for DynamicRouter<Err> {
+    fn clone(&self) -> Self {
+        DynamicRouter {
+            table: self.table.clone(),
+        }
+    }
+}
+
+/// Per-worker state of dynamic routing table
+pub(super) struct DynamicRouting<Err: ErrorRenderer> {
+    router: DynamicRouter<Err>,
+    version: Cell<usize>,
+    resources: RefCell<HashMap<DynamicRouteId, Rc<DynamicResource<Err>>>>,
+}
+
+struct DynamicResource<Err: ErrorRenderer> {
+    rdef: Rc<ResourceDef>,
+    guards: Guards,
+    factory: HttpNewService<Err>,
+    service: RefCell<Option<Rc<HttpService<Err>>>>,
+}
+
+impl<Err: ErrorRenderer> DynamicRouting<Err> {
+    pub(super) fn new(router: DynamicRouter<Err>) -> Self {
+        DynamicRouting {
+            router,
+            version: Cell::new(0),
+            resources: RefCell::new(HashMap::new()),
+        }
+    }
+
+    fn resource(
+        &self,
+        id: DynamicRouteId,
+        table: &Table<Err>,
+    ) -> Rc<DynamicResource<Err>> {
+        self.resources
+            .borrow_mut()
+            .entry(id)
+            .or_insert_with(|| {
+                let factory =
+                    &table.resources.iter().find(|item| item.0 == id).unwrap().2;
+                let (rdef, guards, factory) = factory();
+                Rc::new(DynamicResource {
+                    guards,
+                    factory,
+                    rdef: Rc::new(rdef),
+                    service: RefCell::new(None),
+                })
+            })
+            .clone()
+    }
+
+    /// Route request, request is returned back if no resource matches
+    pub(super) fn call(
+        &self,
+        mut req: WebRequest<Err>,
+    ) -> Result<
+        LocalBoxFuture<'static, Result<WebResponse, Err::Container>>,
+        WebRequest<Err>,
+    > {
+        let resource = {
+            let table = self.router.table.read().unwrap();
+
+            // drop services of unregistered resources
+            if table.version != self.version.get() {
+                self.version.set(table.version);
+                self.resources
+                    .borrow_mut()
+                    .retain(|id, _| table.resources.iter().any(|item| item.0 == *id));
+            }
+
+            let res = table.router.recognize_checked(&mut req, |req, id| {
+                let resource = self.resource(*id.unwrap(), &table);
+                resource.guards.iter().all(|f| f.check(req.head()))
+            });
+            match res {
+                Some((id, _)) => self.resource(*id, &table),
+                None => return Err(req),
+            }
+        };
+        req.add_resource(resource.rdef.clone());
+
+        Ok(async move {
+            let srv = resource.service.borrow().clone();
+            let srv = if let Some(srv) = srv {
+                srv
+            } else if let Ok(srv) = resource.factory.new_service(()).await {
+                let srv = Rc::new(srv);
+                *resource.service.borrow_mut() = Some(srv.clone());
+                srv
+            } else {
+                log::error!("Can not construct dynamic resource service");
+                let req = req.into_parts().0;
+                let res =
+                    default_response::<Err>(&req, StatusCode::INTERNAL_SERVER_ERROR);
+                return Ok(WebResponse::new(req, res));
+            };
+            srv.call(req).await
+        }
+        .boxed_local())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::web::test::{call_service, init_service, read_body, TestRequest};
+    use crate::web::{self, guard, App, HttpResponse};
+
+    #[ntex_rt::test]
+    async fn test_dynamic_router() {
+        fn is_send<T: Send + Sync>(_: &T) {}
+
+        let router = DynamicRouter::new();
+        is_send(&router);
+        let srv = init_service(
+            App::new()
+                .dynamic_routes(router.clone())
+                .service(web::resource("/static").to(|| async { HttpResponse::Ok() })),
+        )
+        .await;
+
+        let req = TestRequest::with_uri("/hooks/1").to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+
+        let id = router.add(|| {
+            web::resource("/hooks/{id}")
+                .guard(guard::Post())
+                .to(|p: web::types::Path<String>| async move { format!("hook {}", p) })
+        });
+        assert_eq!(router.len(), 1);
+
+        let req = TestRequest::with_uri("/hooks/1")
+            .method(crate::http::Method::POST)
+            .to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(read_body(resp).await, "hook 1");
+
+        let req = TestRequest::with_uri("/hooks/1").to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+
+        let req = TestRequest::with_uri("/static").to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        assert!(router.remove(id));
+        assert!(!router.remove(id));
+        assert!(router.is_empty());
+
+        let req = TestRequest::with_uri("/hooks/1")
+            .method(crate::http::Method::POST)
+            .to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    }
+}
@@ -6,6 +6,7 @@ use std::marker::PhantomData;
 
 use bytes::BytesMut;
 use derive_more::{Display, From};
+use serde::Serialize;
 
 pub use actix_threadpool::BlockingError;
 pub use futures::channel::oneshot::Canceled;
@@ -13,6 +14,9 @@ pub use http::Error as HttpError;
 pub use serde_json::error::Error as JsonError;
 pub use url::ParseError as UrlParseError;
 
+use super::httprequest::HttpRequest;
+use super::report::RenderedError;
+use super::types::Data;
 use super::HttpResponse;
 use crate::http::body::Body;
 use crate::http::helpers::Writer;
@@ -22,6 +26,96 @@ pub use super::error_default::{DefaultError, Error};
 
 pub trait ErrorRenderer: Sized + 'static {
     type Container: error::ResponseError + Sized;
+
+    /// Create response for request that does not match any resource or route.
+    ///
+    /// It is used for framework generated *404 Not Found* and
+    /// *405 Method Not Allowed* responses. Default implementation renders
+    /// json or html body depending on request's `Accept` header, for other
+    /// requests body is empty.
+    fn default_response(req: &HttpRequest, status: StatusCode) -> HttpResponse {
+        let reason = status.canonical_reason().unwrap_or("");
+
+        match preferred_format(req) {
+            Some(Format::Json) => HttpResponse::build(status)
+                .content_type("application/json")
+                .body(format!(
+                    "{{\"status\":{},\"error\":\"{}\"}}",
+                    status.as_u16(),
+                    reason
+                )),
+            Some(Format::Html) => HttpResponse::build(status)
+                .content_type("text/html; charset=utf-8")
+                .body(format!(
+                    "<!DOCTYPE html><html><head><title>{0} {1}</title></head>\
+                     <body><h1>{0} {1}</h1></body></html>",
+                    status.as_u16(),
+                    reason
+                )),
+            None => HttpResponse::new(status),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum Format {
+    Json,
+    Html,
+}
+
+/// Select supported media type with highest quality from `Accept` header.
+///
+/// `*/*` selects default response without body.
+fn preferred_format(req: &HttpRequest) -> Option<Format> {
+    let mut items: Vec<(Option<Format>, f32)> = req
+        .headers()
+        .get_all(header::ACCEPT)
+        .filter_map(|val| val.to_str().ok())
+        .flat_map(|val| val.split(','))
+        .filter_map(|item| {
+            let mut parts = item.split(';');
+            let mime = parts.next()?.trim().to_ascii_lowercase();
+            let format = match mime.as_str() {
+                "application/json" | "application/*" => Some(Format::Json),
+                "text/html" | "text/*" => Some(Format::Html),
+                "*/*" => None,
+                _ => return None,
+            };
+            let mut q = 1.0;
+            for param in parts {
+                let mut kv = param.trim().splitn(2, '=');
+                if kv.next() == Some("q") {
+                    q = kv.next().and_then(|v| v.parse().ok()).unwrap_or(0.0);
+                }
+            }
+            if q <= 0.0 {
+                None
+            } else {
+                Some((format, q))
+            }
+        })
+        .collect();
+    // stable sort, header order is preserved for equal quality
+    items.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+    items.into_iter().next().and_then(|(format, _)| format)
+}
+
+/// Custom response for unmatched requests, see `App::default_error_body()`
+pub(super) struct DefaultErrorBody(
+    pub(super) Box<dyn Fn(&HttpRequest, StatusCode) -> HttpResponse>,
+);
+
+/// Create framework generated response for unmatched request
+pub(super) fn default_response<Err: ErrorRenderer>(
+    req: &HttpRequest,
+    status: StatusCode,
+) -> HttpResponse {
+    req.extensions_mut().insert(RenderedError);
+    if let Some(f) = req.app_data::<Data<DefaultErrorBody>>() {
+        (f.get_ref().0)(req, status)
+    } else {
+        Err::default_response(req, status)
+    }
 }
 
 pub trait WebResponseError<Err = DefaultError>:
@@ -94,11 +188,24 @@ pub enum UrlencodedError {
     /// Parse error
     #[display(fmt = "Parse error")]
     Parse,
+    /// Deserialize error
+    #[display(fmt = "Urlencoded deserialize error: {}", _0)]
+    Deserialize(FieldError),
     /// Payload error
     #[display(fmt = "Error that occur during reading payload: {}", _0)]
     Payload(error::PayloadError),
 }
 
+impl UrlencodedError {
+    /// Deserialize error of invalid field
+    pub fn field_error(&self) -> Option<&FieldError> {
+        match self {
+            UrlencodedError::Deserialize(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
 /// A set of errors that can occur during parsing json payloads
 #[derive(Debug, Display, From)]
 pub enum JsonPayloadError {
@@ -110,18 +217,119 @@ pub enum JsonPayloadError {
     ContentType,
     /// Deserialize error
     #[display(fmt = "Json deserialize error: {}", _0)]
-    Deserialize(serde_json::error::Error),
+    Deserialize(FieldError),
     /// Payload error
     #[display(fmt = "Error that occur during reading payload: {}", _0)]
     Payload(error::PayloadError),
 }
 
+impl JsonPayloadError {
+    /// Deserialize error of invalid field
+    pub fn field_error(&self) -> Option<&FieldError> {
+        match self {
+            JsonPayloadError::Deserialize(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+/// A set of errors that can occur during parsing multipart payloads
+#[derive(Debug, Display, From)]
+pub enum MultipartError {
+    /// Content type error
+    #[display(fmt = "Content type error")]
+    ContentType,
+    /// Multipart boundary is not found
+    #[display(fmt = "Multipart boundary is not found")]
+    Boundary,
+    /// Multipart stream is incomplete
+    #[display(fmt = "Multipart stream is incomplete")]
+    Incomplete,
+    /// Part headers parse error
+    #[display(fmt = "Part headers parse error")]
+    Parse,
+    /// Field size is bigger than allowed
+    #[display(fmt = "Field size is bigger than allowed ({} bytes)", _0)]
+    #[from(ignore)]
+    Overflow(u64),
+    /// Number of fields is bigger than allowed
+    #[display(fmt = "Number of fields is bigger than allowed ({})", _0)]
+    #[from(ignore)]
+    TooManyFields(usize),
+    /// Error that occur during reading payload
+    #[display(fmt = "Error that occur during reading payload: {}", _0)]
+    Payload(error::PayloadError),
+}
+
 /// A set of errors that can occur during parsing request paths
 #[derive(Debug, Display, From)]
 pub enum PathError {
-    /// Deserialize error
+    /// Deserialize error, field path is a name of failed path segment
     #[display(fmt = "Path deserialize error: {}", _0)]
-    Deserialize(serde::de::value::Error),
+    Deserialize(FieldError),
+    /// Path parameter parse error
+    #[display(fmt = "{}", _0)]
+    Param(ParamError),
+    /// Error with response created by `PathConfig` error handler
+    #[display(fmt = "{}", error)]
+    #[from(ignore)]
+    Custom {
+        error: Box<PathError>,
+        response: RefCell<Option<HttpResponse>>,
+    },
+}
+
+impl PathError {
+    /// Deserialize error of invalid path segment
+    pub fn field_error(&self) -> Option<&FieldError> {
+        match self {
+            PathError::Deserialize(e) => Some(e),
+            PathError::Custom { error, .. } => error.field_error(),
+            PathError::Param(_) => None,
+        }
+    }
+
+    /// Take response created by `PathConfig` error handler
+    pub(crate) fn take_response(&self) -> Option<HttpResponse> {
+        match self {
+            PathError::Custom { response, .. } => response.borrow_mut().take(),
+            _ => None,
+        }
+    }
+}
+
+/// Error that occur during parsing path parameter with `FromParam` trait
+#[derive(Debug, Display)]
+#[display(fmt = "Can not parse path parameter `{}`: {}", name, error)]
+pub struct ParamError {
+    name: String,
+    error: String,
+    not_found: bool,
+}
+
+impl ParamError {
+    pub(crate) fn new(name: String, error: String, not_found: bool) -> Self {
+        ParamError {
+            name,
+            error,
+            not_found,
+        }
+    }
+
+    /// Name of path parameter
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Parse error message
+    pub fn error(&self) -> &str {
+        &self.error
+    }
+
+    /// Check if error should be rendered as *404 Not Found* response
+    pub fn is_not_found(&self) -> bool {
+        self.not_found
+    }
 }
 
 /// A set of errors that can occur during parsing query strings
@@ -129,9 +337,67 @@ pub enum PathError {
 pub enum QueryPayloadError {
     /// Deserialize error
     #[display(fmt = "Query deserialize error: {}", _0)]
-    Deserialize(serde::de::value::Error),
+    Deserialize(FieldError),
+}
+
+impl QueryPayloadError {
+    /// Deserialize error of invalid field
+    pub fn field_error(&self) -> Option<&FieldError> {
+        match self {
+            QueryPayloadError::Deserialize(e) => Some(e),
+        }
+    }
+}
+
+/// Error that occur during deserialization of query, form or json data.
+///
+/// Error contains path of the invalid field, like `items[0].name`, and
+/// expected type if it is known. Error could be serialized for
+/// structured error responses.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct FieldError {
+    path: String,
+    expected: Option<String>,
+    message: String,
+}
+
+impl FieldError {
+    pub(crate) fn new(path: String, expected: Option<String>, message: String) -> Self {
+        FieldError {
+            path,
+            expected,
+            message,
+        }
+    }
+
+    /// Path of invalid field, empty if error is not related to a field
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// Expected type or value of the field
+    pub fn expected(&self) -> Option<&str> {
+        self.expected.as_deref()
+    }
+
+    /// Error message
+    pub fn message(&self) -> &str {
+        &self.message
+    }
 }
 
+impl fmt::Display for FieldError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.path.is_empty() {
+            f.write_str(&self.message)
+        } else {
+            write!(f, "{}: {}", self.path, self.message)
+        }
+    }
+}
+
+impl std::error::Error for FieldError {}
+
 #[derive(Debug, Display, From)]
 pub enum PayloadError {
     /// Http error.
@@ -145,6 +411,151 @@ pub enum PayloadError {
     Decoding,
 }
 
+/// Errors which can occur when loading configuration from environment
+#[derive(Debug, Display)]
+#[display(fmt = "Environment configuration error ({}*): {}", prefix, error)]
+pub struct EnvError {
+    prefix: String,
+    error: serde::de::value::Error,
+}
+
+impl EnvError {
+    pub(crate) fn new(prefix: &str, error: serde::de::value::Error) -> Self {
+        EnvError {
+            prefix: prefix.to_owned(),
+            error,
+        }
+    }
+
+    /// Environment variables prefix
+    pub fn prefix(&self) -> &str {
+        &self.prefix
+    }
+}
+
+impl std::error::Error for EnvError {}
+
+/// Request processing timeout error
+#[derive(Debug, Display)]
+#[display(fmt = "Request processing timeout")]
+pub struct RequestTimeoutError {
+    status: StatusCode,
+}
+
+impl RequestTimeoutError {
+    pub(crate) fn new(status: StatusCode) -> Self {
+        RequestTimeoutError { status }
+    }
+
+    /// Response status of timed out request
+    pub fn status(&self) -> StatusCode {
+        self.status
+    }
+}
+
+/// Errors which can occur during CSRF token validation
+#[derive(Debug, PartialEq, Display)]
+pub enum CsrfError {
+    /// Token is not issued or is not submitted with request
+    #[display(fmt = "CSRF token is missing")]
+    Missing,
+    /// Submitted token does not match issued token
+    #[display(fmt = "CSRF token mismatch")]
+    Mismatch,
+}
+
+/// Errors which can occur during request authentication
+#[derive(Debug, PartialEq, Display)]
+pub enum AuthError {
+    /// Authorization header is missing or uses different scheme
+    #[display(fmt = "Authorization is required")]
+    Missing(String),
+    /// Authorization header is malformed
+    #[display(fmt = "Malformed authorization header")]
+    Invalid(String),
+    /// Credentials are rejected
+    #[display(fmt = "Invalid credentials")]
+    Rejected(String),
+}
+
+impl AuthError {
+    /// Authentication challenge, value of `WWW-Authenticate` header
+    pub fn challenge(&self) -> &str {
+        match self {
+            AuthError::Missing(ch)
+            | AuthError::Invalid(ch)
+            | AuthError::Rejected(ch) => ch,
+        }
+    }
+}
+
+/// Errors which can occur during GraphQL request extraction
+#[cfg(feature = "graphql")]
+#[derive(Debug, Display)]
+pub enum GraphQLError {
+    /// Content type is not supported
+    #[display(fmt = "Content type is not supported")]
+    ContentType,
+    /// Payload size is bigger than allowed
+    #[display(fmt = "Payload size is bigger than allowed")]
+    Overflow,
+    /// Payload error
+    #[display(fmt = "Error that occur during reading payload: {}", _0)]
+    Payload(error::PayloadError),
+    /// Multipart payload error
+    #[display(fmt = "Error that occur during reading multipart payload: {}", _0)]
+    Form(MultipartError),
+    /// Request can not be parsed
+    #[display(fmt = "Can not parse GraphQL request: {}", _0)]
+    Parse(String),
+    /// Invalid multipart request
+    #[display(fmt = "Invalid multipart request: {}", _0)]
+    Multipart(&'static str),
+}
+
+#[cfg(feature = "graphql")]
+impl From<MultipartError> for GraphQLError {
+    fn from(err: MultipartError) -> Self {
+        GraphQLError::Form(err)
+    }
+}
+
+/// Errors which can occur during JWT validation
+#[cfg(feature = "jwt")]
+#[derive(Debug, Display)]
+pub enum JwtError {
+    /// Token is malformed
+    #[display(fmt = "Malformed token")]
+    Malformed,
+    /// Token signing algorithm is not supported
+    #[display(fmt = "Unsupported token algorithm")]
+    Algorithm,
+    /// Token signing key is not known
+    #[display(fmt = "Unknown token signing key")]
+    UnknownKey,
+    /// Token signature is invalid
+    #[display(fmt = "Invalid token signature")]
+    Signature,
+    /// Token is expired
+    #[display(fmt = "Token is expired")]
+    Expired,
+    /// Token is not valid yet
+    #[display(fmt = "Token is not valid yet")]
+    NotYetValid,
+    /// Token issuer is not allowed
+    #[display(fmt = "Invalid token issuer")]
+    Issuer,
+    /// Token audience is not allowed
+    #[display(fmt = "Invalid token audience")]
+    Audience,
+    /// Claims deserialize error
+    #[display(fmt = "Claims deserialize error: {}", _0)]
+    Claims(JsonError),
+    /// Claims are not available, request is not processed by `Jwt` middleware
+    #[display(fmt = "Token claims are not available")]
+    NoClaims,
+}
+
 /// Helper type that can wrap any error and generate custom response.
 ///
 /// In following example any `io::Error` will be converted into "BAD REQUEST"
@@ -651,8 +1062,34 @@ mod tests {
     use std::io;
 
     use super::*;
+    use crate::web::test::TestRequest;
     use crate::web::DefaultError;
 
+    #[test]
+    fn test_preferred_format() {
+        let format = |accept: &str| {
+            let req = TestRequest::with_header(header::ACCEPT, accept).to_http_request();
+            preferred_format(&req)
+        };
+        assert_eq!(format("application/json"), Some(Format::Json));
+        assert_eq!(format("TEXT/HTML; charset=utf-8"), Some(Format::Html));
+        assert_eq!(
+            format("text/html;q=0.1, application/json"),
+            Some(Format::Json)
+        );
+        assert_eq!(format("application/json;q=0.5, text/*"), Some(Format::Html));
+        assert_eq!(format("text/html, application/json"), Some(Format::Html));
+        assert_eq!(format("text/html;q=0, image/png"), None);
+        assert_eq!(format("*/*"), None);
+        assert_eq!(format("*/*;q=0.8, text/html"), Some(Format::Html));
+        assert_eq!(format("application/json;q=0.5, */*"), None);
+        assert_eq!(format("image/png"), None);
+        assert_eq!(
+            preferred_format(&TestRequest::default().to_http_request()),
+            None
+        );
+    }
+
     #[test]
     fn test_into_error() {
         let err = UrlencodedError::UnknownLength;
@@ -720,8 +1157,15 @@ mod tests {
 
     #[test]
     fn test_query_payload_error() {
-        let err = QueryPayloadError::Deserialize(
-            serde_urlencoded::from_str::<i32>("bad query").unwrap_err(),
+        let err = QueryPayloadError::Deserialize(FieldError::new(
+            "id".to_string(),
+            Some("u32".to_string()),
+            "invalid digit found in string".to_string(),
+        ));
+        assert_eq!(err.field_error().unwrap().path(), "id");
+        assert_eq!(
+            err.to_string(),
+            "Query deserialize error: id: invalid digit found in string"
         );
 
         let resp: HttpResponse = WebResponseError::<DefaultError>::error_response(&err);
@@ -734,15 +1178,57 @@ mod tests {
 
     #[test]
     fn test_path_error() {
-        let err = PathError::Deserialize(
-            serde_urlencoded::from_str::<i32>("bad path").unwrap_err(),
-        );
+        let err = PathError::Deserialize(FieldError::new(
+            "id".to_string(),
+            Some("u32".to_string()),
+            "invalid digit found in string".to_string(),
+        ));
+        assert_eq!(err.field_error().unwrap().path(), "id");
         let resp: HttpResponse = WebResponseError::<DefaultError>::error_response(&err);
         assert_eq!(resp.status(), StatusCode::NOT_FOUND);
         assert_eq!(
             WebResponseError::<DefaultError>::status_code(&err),
             StatusCode::NOT_FOUND
         );
+
+        let err = PathError::Custom {
+            error: Box::new(err),
+            response: RefCell::new(Some(HttpResponse::BadRequest().finish())),
+        };
+        assert_eq!(err.field_error().unwrap().path(), "id");
+        assert_eq!(
+            WebResponseError::<DefaultError>::status_code(&err),
+            StatusCode::BAD_REQUEST
+        );
+        let resp: HttpResponse = WebResponseError::<DefaultError>::error_response(&err);
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[test]
+    fn test_path_param_error() {
+        let err = PathError::from(ParamError::new(
+            "id".to_string(),
+            "invalid digit".to_string(),
+            false,
+        ));
+        assert_eq!(
+            err.to_string(),
+            "Can not parse path parameter `id`: invalid digit"
+        );
+        assert_eq!(
+            WebResponseError::<DefaultError>::status_code(&err),
+            StatusCode::BAD_REQUEST
+        );
+
+        let err = PathError::from(ParamError::new(
+            "id".to_string(),
+            "invalid digit".to_string(),
+            true,
+        ));
+        assert_eq!(
+            WebResponseError::<DefaultError>::status_code(&err),
+            StatusCode::NOT_FOUND
+        );
     }
 
     #[test]
@@ -7,7 +7,7 @@ use serde_json::error::Error as JsonError;
 use serde_urlencoded::ser::Error as FormError;
 
 use crate::http;
-use crate::http::StatusCode;
+use crate::http::{header, StatusCode};
 use crate::util::timeout::TimeoutError;
 
 use super::error::{self, ErrorRenderer, WebResponseError};
@@ -163,10 +163,47 @@ impl WebResponseError<DefaultError> for error::JsonPayloadError {
     }
 }
 
-/// Error renderer for `PathError`
+/// Response renderer for `MultipartError`
+impl WebResponseError<DefaultError> for error::MultipartError {
+    fn status_code(&self) -> StatusCode {
+        match *self {
+            error::MultipartError::Overflow(_)
+            | error::MultipartError::TooManyFields(_) => StatusCode::PAYLOAD_TOO_LARGE,
+            _ => StatusCode::BAD_REQUEST,
+        }
+    }
+}
+
+/// Return `PayloadTooLarge` for `MemoryLimitError`
+impl WebResponseError<DefaultError> for crate::web::middleware::MemoryLimitError {
+    fn status_code(&self) -> StatusCode {
+        StatusCode::PAYLOAD_TOO_LARGE
+    }
+}
+
+/// Error renderer for `PathError`, response of `PathConfig` error handler
+/// is returned as is
 impl WebResponseError<DefaultError> for error::PathError {
     fn status_code(&self) -> StatusCode {
-        StatusCode::NOT_FOUND
+        match self {
+            error::PathError::Param(e) if !e.is_not_found() => StatusCode::BAD_REQUEST,
+            error::PathError::Custom { response, .. } => response
+                .borrow()
+                .as_ref()
+                .map(|res| res.status())
+                .unwrap_or(StatusCode::INTERNAL_SERVER_ERROR),
+            _ => StatusCode::NOT_FOUND,
+        }
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        if let Some(res) = self.take_response() {
+            res
+        } else {
+            HttpResponse::build(self.status_code())
+                .content_type("text/plain; charset=utf-8")
+                .body(self.to_string())
+        }
     }
 }
 
@@ -177,20 +214,105 @@ impl WebResponseError<DefaultError> for error::QueryPayloadError {
     }
 }
 
+/// Return `PayloadTooLarge` for `Overflow` and `BadRequest` for other errors
 impl WebResponseError<DefaultError> for error::PayloadError {
     fn status_code(&self) -> StatusCode {
-        StatusCode::BAD_REQUEST
+        match *self {
+            error::PayloadError::Payload(http::error::PayloadError::Overflow) => {
+                StatusCode::PAYLOAD_TOO_LARGE
+            }
+            _ => StatusCode::BAD_REQUEST,
+        }
+    }
+}
+
+/// Return configured status for `RequestTimeoutError`
+impl WebResponseError<DefaultError> for error::RequestTimeoutError {
+    fn status_code(&self) -> StatusCode {
+        self.status()
+    }
+}
+
+/// Return `FORBIDDEN` for `CsrfError`
+impl WebResponseError<DefaultError> for error::CsrfError {
+    fn status_code(&self) -> StatusCode {
+        StatusCode::FORBIDDEN
+    }
+}
+
+/// Return `UNAUTHORIZED` with authentication challenge for `AuthError`
+impl WebResponseError<DefaultError> for error::AuthError {
+    fn status_code(&self) -> StatusCode {
+        StatusCode::UNAUTHORIZED
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        HttpResponse::build(self.status_code())
+            .header(header::WWW_AUTHENTICATE, self.challenge())
+            .content_type("text/plain; charset=utf-8")
+            .body(self.to_string())
+    }
+}
+
+/// Return `UNAUTHORIZED` with `invalid_token` challenge for `JwtError`
+#[cfg(feature = "jwt")]
+impl WebResponseError<DefaultError> for error::JwtError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            error::JwtError::NoClaims => StatusCode::INTERNAL_SERVER_ERROR,
+            _ => StatusCode::UNAUTHORIZED,
+        }
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        let mut resp = HttpResponse::build(self.status_code());
+        if self.status_code() == StatusCode::UNAUTHORIZED {
+            resp.header(
+                header::WWW_AUTHENTICATE,
+                format!(
+                    "Bearer error=\"invalid_token\", error_description=\"{}\"",
+                    self.to_string().replace('"', "'")
+                ),
+            );
+        }
+        resp.content_type("text/plain; charset=utf-8")
+            .body(self.to_string())
+    }
+}
+
+/// Return `BadRequest` for `GraphQLError`, unsupported content type
+/// and payload size errors have own status codes
+#[cfg(feature = "graphql")]
+impl WebResponseError<DefaultError> for error::GraphQLError {
+    fn status_code(&self) -> StatusCode {
+        match *self {
+            error::GraphQLError::ContentType => StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            error::GraphQLError::Overflow => StatusCode::PAYLOAD_TOO_LARGE,
+            error::GraphQLError::Form(ref e) => {
+                WebResponseError::<DefaultError>::status_code(e)
+            }
+            _ => StatusCode::BAD_REQUEST,
+        }
+    }
+}
+
+/// Return `SERVICE_UNAVAILABLE` for `CircuitOpen`
+impl WebResponseError<DefaultError> for crate::service::CircuitOpen {
+    fn status_code(&self) -> StatusCode {
+        StatusCode::SERVICE_UNAVAILABLE
     }
 }
 
-/// `PayloadError` returns two possible results:
+/// `PayloadError` returns three possible results:
 ///
 /// - `Overflow` returns `PayloadTooLarge`
+/// - `Timeout` returns `RequestTimeout`
 /// - Other errors returns `BadRequest`
 impl WebResponseError<DefaultError> for http::error::PayloadError {
     fn status_code(&self) -> StatusCode {
         match *self {
             http::error::PayloadError::Overflow => StatusCode::PAYLOAD_TOO_LARGE,
+            http::error::PayloadError::Timeout => StatusCode::REQUEST_TIMEOUT,
             _ => StatusCode::BAD_REQUEST,
         }
     }
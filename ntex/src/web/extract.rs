@@ -1,4 +1,5 @@
 //! Request extractors
+use std::any::type_name;
 use std::future::Future;
 use std::pin::Pin;
 use std::task::{Context, Poll};
@@ -9,6 +10,8 @@ use crate::http::Payload;
 
 use super::error::ErrorRenderer;
 use super::httprequest::HttpRequest;
+use super::report::ExtractorFailure;
+use super::types::DataRequirement;
 
 /// Trait implemented by types that can be extracted from request.
 ///
@@ -29,6 +32,22 @@ pub trait FromRequest<Err>: Sized {
     fn extract(req: &HttpRequest) -> Self::Future {
         Self::from_request(req, &mut Payload::None)
     }
+
+    /// Check if request's content type is acceptable for extractor.
+    ///
+    /// Used for strict content-type enforcement, check
+    /// [`App::strict_content_type()`](struct.App.html#method.strict_content_type).
+    /// `None` means extractor does not read request payload or
+    /// accepts any content type.
+    fn accepts_content_type(_: &HttpRequest) -> Option<bool> {
+        None
+    }
+
+    /// Collect application data objects required by extractor.
+    ///
+    /// Used for startup validation, check
+    /// [`App::validate()`](struct.App.html#method.validate).
+    fn required_data(_: &mut Vec<DataRequirement>) {}
 }
 
 /// Optionally extract a field from the request
@@ -192,10 +211,25 @@ macro_rules! tuple_from_req ({$fut_type:ident, $(($n:tt, $T:ident)),+} => {
 
         fn from_request(req: &HttpRequest, payload: &mut Payload) -> Self::Future {
             $fut_type {
+                req: req.clone(),
                 items: <($(Option<$T>,)+)>::default(),
                 $($T: $T::from_request(req, payload),)+
             }
         }
+
+        fn accepts_content_type(req: &HttpRequest) -> Option<bool> {
+            let mut result = None;
+            $(match <$T as FromRequest<Err>>::accepts_content_type(req) {
+                Some(false) => return Some(false),
+                Some(true) => result = Some(true),
+                None => (),
+            })+
+            result
+        }
+
+        fn required_data(data: &mut Vec<DataRequirement>) {
+            $(<$T as FromRequest<Err>>::required_data(data);)+
+        }
     }
 
     #[doc(hidden)]
@@ -204,6 +238,7 @@ macro_rules! tuple_from_req ({$fut_type:ident, $(($n:tt, $T:ident)),+} => {
     where
         $(<$T as $crate::web::FromRequest<Err>>::Error: Into<Err::Container>),+
     {
+        req: HttpRequest,
         items: ($(Option<$T>,)+),
         $(#[pin] $T: $T::Future),+
     }
@@ -225,7 +260,13 @@ macro_rules! tuple_from_req ({$fut_type:ident, $(($n:tt, $T:ident)),+} => {
                             this.items.$n = Some(item);
                         }
                         Poll::Pending => ready = false,
-                        Poll::Ready(Err(e)) => return Poll::Ready(Err(e.into())),
+                        Poll::Ready(Err(e)) => {
+                            let mut ext = this.req.extensions_mut();
+                            if !ext.contains::<ExtractorFailure>() {
+                                ext.insert(ExtractorFailure(type_name::<$T>()));
+                            }
+                            return Poll::Ready(Err(e.into()))
+                        }
                     }
                 }
             )+
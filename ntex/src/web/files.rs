@@ -0,0 +1,807 @@
+//! Static files support
+//!
+//! ```rust
+//! use ntex::web::{self, App, Files, HttpRequest, NamedFile};
+//!
+//! async fn favicon(_: HttpRequest) -> std::io::Result<NamedFile> {
+//!     NamedFile::open("./static/favicon.ico")
+//! }
+//!
+//! fn main() {
+//!     let app = App::new()
+//!         .service(web::resource("/favicon.ico").to(favicon))
+//!         .service(
+//!             Files::new("/static", "./static")
+//!                 .index_file("index.html")
+//!                 .show_listing(),
+//!         );
+//! }
+//! ```
+use std::fmt::{self, Write};
+use std::fs::{self, File, Metadata};
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::{Component, Path, PathBuf};
+use std::pin::Pin;
+use std::rc::Rc;
+use std::task::{Context, Poll};
+use std::time::UNIX_EPOCH;
+
+use futures::future::{ok, FutureExt, LocalBoxFuture, Ready};
+use percent_encoding::{percent_decode_str, utf8_percent_encode, AsciiSet, CONTROLS};
+use tokio::io::AsyncSeek;
+
+use crate::codec::AsyncRead;
+use crate::http::header::{HeaderMap, HeaderValue, ETAG, LAST_MODIFIED};
+use crate::http::{Method, Response, StatusCode};
+
+use super::dev::{WebServiceConfig, WebServiceFactory};
+use super::error::{default_response, BlockingError, ErrorRenderer};
+use super::httprequest::HttpRequest;
+use super::middleware::etag::is_modified;
+use super::responder::Responder;
+use super::types::ranged::http_date;
+use super::types::RangedStream;
+use super::util::{block, get, head, resource, scope};
+
+/// Characters that are escaped in directory listing links
+const HREF: &AsciiSet = &CONTROLS
+    .add(b' ')
+    .add(b'"')
+    .add(b'#')
+    .add(b'%')
+    .add(b'<')
+    .add(b'>')
+    .add(b'?')
+    .add(b'`');
+
+/// Content types by file extension
+const CONTENT_TYPES: &[(&str, &str)] = &[
+    ("html", "text/html; charset=utf-8"),
+    ("htm", "text/html; charset=utf-8"),
+    ("css", "text/css; charset=utf-8"),
+    ("js", "application/javascript; charset=utf-8"),
+    ("mjs", "application/javascript; charset=utf-8"),
+    ("json", "application/json"),
+    ("map", "application/json"),
+    ("txt", "text/plain; charset=utf-8"),
+    ("md", "text/markdown; charset=utf-8"),
+    ("csv", "text/csv; charset=utf-8"),
+    ("xml", "application/xml"),
+    ("svg", "image/svg+xml"),
+    ("png", "image/png"),
+    ("jpg", "image/jpeg"),
+    ("jpeg", "image/jpeg"),
+    ("gif", "image/gif"),
+    ("webp", "image/webp"),
+    ("avif", "image/avif"),
+    ("ico", "image/x-icon"),
+    ("bmp", "image/bmp"),
+    ("woff", "font/woff"),
+    ("woff2", "font/woff2"),
+    ("ttf", "font/ttf"),
+    ("otf", "font/otf"),
+    ("wasm", "application/wasm"),
+    ("pdf", "application/pdf"),
+    ("zip", "application/zip"),
+    ("gz", "application/gzip"),
+    ("tar", "application/x-tar"),
+    ("mp3", "audio/mpeg"),
+    ("ogg", "audio/ogg"),
+    ("wav", "audio/wav"),
+    ("mp4", "video/mp4"),
+    ("webm", "video/webm"),
+];
+
+/// Guess content type of the file by its extension.
+///
+/// Unknown extensions map to `application/octet-stream`.
+pub fn content_type(path: &Path) -> &'static str {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .and_then(|ext| {
+            CONTENT_TYPES
+                .iter()
+                .find(|(e, _)| e.eq_ignore_ascii_case(ext))
+                .map(|(_, ct)| *ct)
+        })
+        .unwrap_or("application/octet-stream")
+}
+
+/// Responder for file from file system.
+///
+/// Content type is detected from file extension. Response contains
+/// *ETag* and *Last-Modified* headers, conditional requests are answered
+/// with *304 Not Modified* response. Byte range requests are supported,
+/// see [`RangedStream`](../types/struct.RangedStream.html).
+///
+/// File content is read on a thread pool.
+pub struct NamedFile {
+    path: PathBuf,
+    file: File,
+    md: Metadata,
+    content_type: &'static str,
+    use_etag: bool,
+    use_last_modified: bool,
+}
+
+impl NamedFile {
+    /// Open file in read-only mode.
+    ///
+    /// This method performs blocking file system calls.
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<NamedFile> {
+        let path = path.as_ref().to_path_buf();
+        let file = File::open(&path)?;
+        let md = file.metadata()?;
+        if md.is_dir() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Path is a directory",
+            ));
+        }
+
+        Ok(NamedFile {
+            content_type: content_type(&path),
+            path,
+            file,
+            md,
+            use_etag: true,
+            use_last_modified: true,
+        })
+    }
+
+    /// Path of the file
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Metadata of the file
+    pub fn metadata(&self) -> &Metadata {
+        &self.md
+    }
+
+    /// Override detected content type
+    pub fn set_content_type(mut self, value: &'static str) -> Self {
+        self.content_type = value;
+        self
+    }
+
+    /// Enable or disable *ETag* header, enabled by default
+    pub fn use_etag(mut self, value: bool) -> Self {
+        self.use_etag = value;
+        self
+    }
+
+    /// Enable or disable *Last-Modified* header, enabled by default
+    pub fn use_last_modified(mut self, value: bool) -> Self {
+        self.use_last_modified = value;
+        self
+    }
+
+    /// Entity tag of the file, based on file size and modification time
+    fn etag(&self) -> Option<String> {
+        let modified = self.md.modified().ok()?;
+        let dur = modified.duration_since(UNIX_EPOCH).ok()?;
+        Some(format!(
+            "{:x}-{:x}-{:x}",
+            self.md.len(),
+            dur.as_secs(),
+            dur.subsec_nanos()
+        ))
+    }
+
+    fn into_response(self, req: &HttpRequest) -> Response {
+        let etag = if self.use_etag { self.etag() } else { None };
+        let modified = if self.use_last_modified {
+            self.md.modified().ok()
+        } else {
+            None
+        };
+
+        if req.method() == Method::GET || req.method() == Method::HEAD {
+            let mut headers = HeaderMap::new();
+            if let Some(ref tag) = etag {
+                if let Ok(val) = HeaderValue::from_str(&format!("\"{}\"", tag)) {
+                    headers.insert(ETAG, val);
+                }
+            }
+            if let Some(modified) = modified {
+                if let Ok(val) = HeaderValue::from_str(&http_date(modified)) {
+                    headers.insert(LAST_MODIFIED, val);
+                }
+            }
+            if !is_modified(req.headers(), &headers) {
+                let mut res = Response::new(StatusCode::NOT_MODIFIED);
+                *res.headers_mut() = headers;
+                return res;
+            }
+        }
+
+        let len = self.md.len();
+        let mut stream = RangedStream::new(BlockingFile::new(self.file), len)
+            .content_type(self.content_type);
+        if let Some(ref tag) = etag {
+            stream = stream.etag(tag);
+        }
+        if let Some(modified) = modified {
+            stream = stream.last_modified(modified);
+        }
+        stream.into_response(req)
+    }
+}
+
+impl fmt::Debug for NamedFile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("NamedFile")
+            .field("path", &self.path)
+            .field("content_type", &self.content_type)
+            .finish()
+    }
+}
+
+impl<Err: ErrorRenderer> Responder<Err> for NamedFile {
+    type Error = Err::Container;
+    type Future = Ready<Result<Response, Self::Error>>;
+
+    fn respond_to(self, req: &HttpRequest) -> Self::Future {
+        ok(self.into_response(req))
+    }
+}
+
+enum Op {
+    Read(io::Result<Vec<u8>>),
+    Seek(io::Result<u64>),
+}
+
+/// File handle that executes blocking operations on a thread pool
+struct BlockingFile {
+    file: Option<File>,
+    pos: u64,
+    fut: Option<LocalBoxFuture<'static, Result<(File, Op), BlockingError<()>>>>,
+}
+
+impl BlockingFile {
+    fn new(file: File) -> Self {
+        BlockingFile {
+            file: Some(file),
+            pos: 0,
+            fut: None,
+        }
+    }
+
+    fn start<F>(&mut self, f: F) -> io::Result<()>
+    where
+        F: FnOnce(&mut File) -> Op + Send + 'static,
+    {
+        let mut file = self
+            .file
+            .take()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::Other, "File is closed"))?;
+        self.fut = Some(
+            block(move || {
+                let op = f(&mut file);
+                Ok((file, op))
+            })
+            .boxed_local(),
+        );
+        Ok(())
+    }
+
+    fn poll_op(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<Op>> {
+        let res = match self.fut {
+            Some(ref mut fut) => futures::ready!(fut.as_mut().poll(cx)),
+            None => {
+                return Poll::Ready(Err(io::Error::new(
+                    io::ErrorKind::Other,
+                    "No pending operation",
+                )))
+            }
+        };
+        self.fut = None;
+        match res {
+            Ok((file, op)) => {
+                self.file = Some(file);
+                Poll::Ready(Ok(op))
+            }
+            Err(_) => Poll::Ready(Err(io::Error::new(
+                io::ErrorKind::Other,
+                "Thread pool is gone",
+            ))),
+        }
+    }
+}
+
+impl AsyncRead for BlockingFile {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        loop {
+            if this.fut.is_none() {
+                let size = buf.len();
+                this.start(move |file| {
+                    let mut data = vec![0; size];
+                    Op::Read(file.read(&mut data).map(|n| {
+                        data.truncate(n);
+                        data
+                    }))
+                })?;
+            }
+            match futures::ready!(this.poll_op(cx))? {
+                Op::Read(res) => {
+                    let data = res?;
+                    buf[..data.len()].copy_from_slice(&data);
+                    this.pos += data.len() as u64;
+                    return Poll::Ready(Ok(data.len()));
+                }
+                // finish pending seek, then start read
+                Op::Seek(res) => this.pos = res?,
+            }
+        }
+    }
+}
+
+impl AsyncSeek for BlockingFile {
+    fn start_seek(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        pos: SeekFrom,
+    ) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        if this.fut.is_some() {
+            if let Op::Seek(res) = futures::ready!(this.poll_op(cx))? {
+                this.pos = res?;
+            }
+        }
+        this.start(move |file| Op::Seek(file.seek(pos)))?;
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_complete(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<io::Result<u64>> {
+        let this = self.get_mut();
+        if this.fut.is_none() {
+            return Poll::Ready(Ok(this.pos));
+        }
+        if let Op::Seek(res) = futures::ready!(this.poll_op(cx))? {
+            this.pos = res?;
+        }
+        Poll::Ready(Ok(this.pos))
+    }
+}
+
+/// Static files service.
+///
+/// Service serves files from the directory under mount path. Request
+/// paths that contain `..` segments are rejected, hidden files are not
+/// served unless `use_hidden_files()` is called. Directory requests are
+/// served with index file or with directory listing, if configured.
+/// Only `GET` and `HEAD` requests are handled.
+///
+/// Service handles all paths under mount path, other services should
+/// not be registered under the same prefix.
+#[derive(Clone)]
+pub struct Files {
+    path: String,
+    inner: Rc<FilesInner>,
+}
+
+struct FilesInner {
+    directory: PathBuf,
+    index: Option<String>,
+    listing: bool,
+    hidden: bool,
+}
+
+impl Files {
+    /// Create files service for directory, mounted at specified path
+    pub fn new<T: Into<PathBuf>>(path: &str, directory: T) -> Self {
+        Files {
+            path: path.trim_end_matches('/').to_string(),
+            inner: Rc::new(FilesInner {
+                directory: directory.into(),
+                index: None,
+                listing: false,
+                hidden: false,
+            }),
+        }
+    }
+
+    /// Serve file with specified name for directory requests
+    pub fn index_file(mut self, name: &str) -> Self {
+        Rc::get_mut(&mut self.inner)
+            .expect("Multiple copies exist")
+            .index = Some(name.to_string());
+        self
+    }
+
+    /// Render html listing for directories without index file
+    pub fn show_listing(mut self) -> Self {
+        Rc::get_mut(&mut self.inner)
+            .expect("Multiple copies exist")
+            .listing = true;
+        self
+    }
+
+    /// Serve files and directories which names start with `.`
+    pub fn use_hidden_files(mut self) -> Self {
+        Rc::get_mut(&mut self.inner)
+            .expect("Multiple copies exist")
+            .hidden = true;
+        self
+    }
+}
+
+impl fmt::Debug for Files {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Files")
+            .field("path", &self.path)
+            .field("directory", &self.inner.directory)
+            .finish()
+    }
+}
+
+enum Entry {
+    File(NamedFile),
+    Listing(String),
+}
+
+impl FilesInner {
+    /// Map request path to file system path
+    fn resolve(&self, tail: &str) -> Option<PathBuf> {
+        let mut path = self.directory.clone();
+        for segment in tail.split('/') {
+            let segment = percent_decode_str(segment).decode_utf8().ok()?;
+            if segment.is_empty() || segment == "." {
+                continue;
+            }
+            if segment == ".."
+                || segment.contains('/')
+                || segment.contains('\\')
+                || segment.contains('\0')
+                || segment.contains(':')
+                || (segment.starts_with('.') && !self.hidden)
+            {
+                return None;
+            }
+            // segment must not replace base directory, i.e. windows prefix
+            let mut components = Path::new(&*segment).components();
+            match (components.next(), components.next()) {
+                (Some(Component::Normal(_)), None) => (),
+                _ => return None,
+            }
+            path.push(&*segment);
+        }
+        Some(path)
+    }
+
+    async fn serve<Err: ErrorRenderer>(self: Rc<Self>, req: HttpRequest) -> Response {
+        let path = match self.resolve(req.match_info().get("path").unwrap_or("")) {
+            Some(path) => path,
+            None => return default_response::<Err>(&req, StatusCode::NOT_FOUND),
+        };
+        let index = self.index.clone();
+        let listing = self.listing;
+        let hidden = self.hidden;
+        let base = if req.path().ends_with('/') {
+            req.path().to_string()
+        } else {
+            format!("{}/", req.path())
+        };
+
+        let entry = block(move || {
+            if !fs::metadata(&path)?.is_dir() {
+                return NamedFile::open(path).map(Entry::File);
+            }
+            if let Some(index) = index {
+                let index = path.join(index);
+                if index.is_file() {
+                    return NamedFile::open(index).map(Entry::File);
+                }
+            }
+            if listing {
+                render_listing(&path, &base, hidden).map(Entry::Listing)
+            } else {
+                Err(io::Error::new(io::ErrorKind::NotFound, "Directory"))
+            }
+        })
+        .await;
+
+        match entry {
+            Ok(Entry::File(file)) => file.into_response(&req),
+            Ok(Entry::Listing(body)) => Response::Ok()
+                .content_type("text/html; charset=utf-8")
+                .body(body),
+            Err(BlockingError::Error(ref e)) if e.kind() == io::ErrorKind::NotFound => {
+                default_response::<Err>(&req, StatusCode::NOT_FOUND)
+            }
+            Err(BlockingError::Error(ref e))
+                if e.kind() == io::ErrorKind::PermissionDenied =>
+            {
+                default_response::<Err>(&req, StatusCode::FORBIDDEN)
+            }
+            Err(e) => {
+                log::error!("Cannot open file {:?}: {}", req.path(), e);
+                default_response::<Err>(&req, StatusCode::INTERNAL_SERVER_ERROR)
+            }
+        }
+    }
+}
+
+/// Render html listing of the directory
+fn render_listing(dir: &Path, base: &str, hidden: bool) -> io::Result<String> {
+    let mut entries = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().into_owned();
+        if name.starts_with('.') && !hidden {
+            continue;
+        }
+        entries.push((name, entry.file_type()?.is_dir()));
+    }
+    entries.sort();
+
+    let title = escape_html(base);
+    let mut body = format!(
+        "<!DOCTYPE html><html><head><meta charset=\"utf-8\">\
+         <title>Index of {0}</title></head><body><h1>Index of {0}</h1><ul>",
+        title
+    );
+    for (name, is_dir) in entries {
+        let slash = if is_dir { "/" } else { "" };
+        let _ = write!(
+            &mut body,
+            "<li><a href=\"{}{}{}\">{}{}</a></li>",
+            utf8_percent_encode(base, HREF),
+            utf8_percent_encode(&name, HREF),
+            slash,
+            escape_html(&name),
+            slash,
+        );
+    }
+    body.push_str("</ul></body></html>");
+    Ok(body)
+}
+
+fn escape_html(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for ch in s.chars() {
+        match ch {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(ch),
+        }
+    }
+    escaped
+}
+
+impl<Err: ErrorRenderer> WebServiceFactory<Err> for Files {
+    fn register(self, config: &mut WebServiceConfig<Err>) {
+        let (i1, i2) = (self.inner.clone(), self.inner);
+
+        scope(&self.path)
+            .service(
+                resource(["", "/{path}*"])
+                    .route(
+                        get().to(move |req: HttpRequest| i1.clone().serve::<Err>(req)),
+                    )
+                    .route(
+                        head().to(move |req: HttpRequest| i2.clone().serve::<Err>(req)),
+                    ),
+            )
+            .register(config)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::{Bytes, BytesMut};
+    use futures::StreamExt;
+
+    use super::*;
+    use crate::http::header::{
+        CONTENT_RANGE, CONTENT_TYPE, IF_MODIFIED_SINCE, IF_NONE_MATCH, RANGE,
+    };
+    use crate::web::test::{call_service, init_service, read_body, TestRequest};
+    use crate::web::App;
+
+    fn test_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "ntex-files-{}-{}",
+            name,
+            std::process::id()
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("sub")).unwrap();
+        fs::create_dir_all(dir.join("docs")).unwrap();
+        fs::write(dir.join("hello.txt"), "hello world").unwrap();
+        fs::write(dir.join(".secret"), "secret").unwrap();
+        fs::write(dir.join("sub").join("index.html"), "<p>index</p>").unwrap();
+        fs::write(dir.join("docs").join("a <b>.css"), "a {}").unwrap();
+        dir
+    }
+
+    async fn read(mut res: Response) -> Bytes {
+        let mut body = res.take_body();
+        let mut bytes = BytesMut::new();
+        while let Some(item) = body.next().await {
+            bytes.extend_from_slice(&item.unwrap());
+        }
+        bytes.freeze()
+    }
+
+    #[ntex_rt::test]
+    async fn test_blocking_file_seek() {
+        let dir = test_dir("seek");
+        let mut file = BlockingFile::new(File::open(dir.join("hello.txt")).unwrap());
+
+        // read right after seek, without polling seek completion
+        futures::future::poll_fn(|cx| {
+            Pin::new(&mut file).start_seek(cx, SeekFrom::Start(6))
+        })
+        .await
+        .unwrap();
+        let mut buf = [0; 16];
+        let n =
+            futures::future::poll_fn(|cx| Pin::new(&mut file).poll_read(cx, &mut buf))
+                .await
+                .unwrap();
+        assert_eq!(&buf[..n], b"world");
+        assert_eq!(file.pos, 11);
+
+        let pos = futures::future::poll_fn(|cx| Pin::new(&mut file).poll_complete(cx))
+            .await
+            .unwrap();
+        assert_eq!(pos, 11);
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_content_type() {
+        assert_eq!(
+            content_type(Path::new("index.HTML")),
+            "text/html; charset=utf-8"
+        );
+        assert_eq!(content_type(Path::new("a/b.png")), "image/png");
+        assert_eq!(content_type(Path::new("a.tar.gz")), "application/gzip");
+        assert_eq!(
+            content_type(Path::new("Makefile")),
+            "application/octet-stream"
+        );
+    }
+
+    #[ntex_rt::test]
+    async fn test_named_file() {
+        let dir = test_dir("named");
+        let path = dir.join("hello.txt");
+
+        let req = TestRequest::default().to_http_request();
+        let res = NamedFile::open(&path).unwrap().into_response(&req);
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(
+            res.headers().get(CONTENT_TYPE).unwrap(),
+            "text/plain; charset=utf-8"
+        );
+        let etag = res.headers().get(ETAG).unwrap().clone();
+        let modified = res.headers().get(LAST_MODIFIED).unwrap().clone();
+        assert_eq!(read(res).await, "hello world");
+
+        let req = TestRequest::default()
+            .header(RANGE, "bytes=6-")
+            .to_http_request();
+        let res = NamedFile::open(&path).unwrap().into_response(&req);
+        assert_eq!(res.status(), StatusCode::PARTIAL_CONTENT);
+        assert_eq!(res.headers().get(CONTENT_RANGE).unwrap(), "bytes 6-10/11");
+        assert_eq!(read(res).await, "world");
+
+        let req = TestRequest::default()
+            .header(IF_NONE_MATCH, etag.clone())
+            .to_http_request();
+        let res = NamedFile::open(&path).unwrap().into_response(&req);
+        assert_eq!(res.status(), StatusCode::NOT_MODIFIED);
+        assert_eq!(res.headers().get(ETAG).unwrap(), &etag);
+
+        let req = TestRequest::default()
+            .header(IF_MODIFIED_SINCE, modified)
+            .to_http_request();
+        let res = NamedFile::open(&path).unwrap().into_response(&req);
+        assert_eq!(res.status(), StatusCode::NOT_MODIFIED);
+
+        let res = NamedFile::open(&path)
+            .unwrap()
+            .use_etag(false)
+            .set_content_type("text/x-custom")
+            .into_response(&TestRequest::default().to_http_request());
+        assert!(!res.headers().contains_key(ETAG));
+        assert_eq!(res.headers().get(CONTENT_TYPE).unwrap(), "text/x-custom");
+
+        assert!(NamedFile::open(&dir).is_err());
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[ntex_rt::test]
+    async fn test_files() {
+        let dir = test_dir("service");
+        let srv = init_service(
+            App::new()
+                .service(Files::new("/static", &dir).index_file("index.html"))
+                .service(Files::new("/listing", &dir).show_listing()),
+        )
+        .await;
+
+        let req = TestRequest::with_uri("/static/hello.txt").to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(read_body(resp).await, "hello world");
+
+        let req = TestRequest::with_uri("/static/hello.txt")
+            .method(Method::HEAD)
+            .to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let req = TestRequest::with_uri("/static/hello.txt")
+            .method(Method::POST)
+            .to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::METHOD_NOT_ALLOWED);
+
+        let req = TestRequest::with_uri("/static/sub/").to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(
+            resp.headers().get(CONTENT_TYPE).unwrap(),
+            "text/html; charset=utf-8"
+        );
+        assert_eq!(read_body(resp).await, "<p>index</p>");
+
+        let req = TestRequest::with_uri("/static/docs/a%20%3Cb%3E.css").to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(read_body(resp).await, "a {}");
+
+        // drive prefix on windows, regular file name on unix
+        #[cfg(unix)]
+        fs::write(dir.join("hello:txt"), "hello").unwrap();
+
+        for path in &[
+            "/static/missing.txt",
+            "/static/.secret",
+            "/static/../Cargo.toml",
+            "/static/sub/%2E%2E/hello.txt",
+            "/static/sub%2F..%2Fhello.txt",
+            "/static/C:%2FWindows%2Fwin.ini",
+            "/static/C:%5CWindows%5Cwin.ini",
+            "/static/C:",
+            "/static/hello:txt",
+            "/static",
+            "/static/docs",
+        ] {
+            let req = TestRequest::with_uri(path).to_request();
+            let resp = call_service(&srv, req).await;
+            assert_eq!(resp.status(), StatusCode::NOT_FOUND, "{}", path);
+        }
+
+        let req = TestRequest::with_uri("/listing/docs").to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let body = read_body(resp).await;
+        let body = std::str::from_utf8(&body).unwrap();
+        assert!(body
+            .contains("<a href=\"/listing/docs/a%20%3Cb%3E.css\">a &lt;b&gt;.css</a>"));
+
+        let req = TestRequest::with_uri("/listing").to_request();
+        let resp = call_service(&srv, req).await;
+        let body = read_body(resp).await;
+        let body = std::str::from_utf8(&body).unwrap();
+        assert!(body.contains("<a href=\"/listing/sub/\">sub/</a>"));
+        assert!(body.contains("<a href=\"/listing/hello.txt\">hello.txt</a>"));
+        assert!(!body.contains(".secret"));
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,807 @@
+//! GraphQL integration.
+//!
+//! Module provides `GraphQLRequest` extractor, `GraphQLResponse` responder
+//! and `GraphQL` service that executes requests with any GraphQL executor.
+//! Requests are accepted as `GET` query parameters and as `POST` payloads
+//! with `application/json`, `application/graphql` and `multipart/form-data`
+//! ([GraphQL multipart request](https://github.com/jaydenseric/graphql-multipart-request-spec))
+//! content types.
+//!
+//! ```rust
+//! use ntex::web::{self, App};
+//! use ntex::web::graphql::{GraphQL, GraphQLRequest, GraphQLResponse};
+//!
+//! async fn execute(req: GraphQLRequest) -> GraphQLResponse {
+//!     // execute request with the schema
+//!     GraphQLResponse::from(serde_json::json!({"data": {"query": req.query}}))
+//! }
+//!
+//! fn main() {
+//!     let app = App::new().service(GraphQL::new("/graphql", execute).graphiql("/graphiql"));
+//! }
+//! ```
+//!
+//! ## GraphQL libraries
+//!
+//! Module does not depend on any GraphQL library and does not provide
+//! adapters for *async-graphql* or *juniper* executors. Both libraries
+//! publish breaking releases often, built-in adapters would tie ntex
+//! releases to specific versions of them. Executor of any library could be
+//! adapted by implementing `GraphQLExecutor` trait for its schema type:
+//!
+//! ```rust,ignore
+//! struct Executor(Schema<Query, EmptyMutation, EmptySubscription>);
+//!
+//! impl GraphQLExecutor for Executor {
+//!     fn execute(&self, req: GraphQLRequest) -> LocalBoxFuture<'static, GraphQLResponse> {
+//!         let schema = self.0.clone();
+//!         async move {
+//!             let res = schema.execute(&req.query).await;
+//!             GraphQLResponse::from(serde_json::to_value(res).unwrap())
+//!         }
+//!         .boxed_local()
+//!     }
+//! }
+//! ```
+use std::convert::Infallible;
+use std::future::Future;
+use std::rc::Rc;
+use std::{collections::HashMap, str};
+
+use bytes::{Bytes, BytesMut};
+use derive_more::Display;
+use futures::future::{ok, ready, FutureExt, LocalBoxFuture, Ready};
+use futures::{Stream, StreamExt};
+use mime::Mime;
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::http::error::{PayloadError, ResponseError};
+use crate::http::{HttpMessage, Method, Payload, Response, StatusCode};
+
+use super::dev::{WebServiceConfig, WebServiceFactory};
+use super::error::ErrorRenderer;
+use super::httprequest::HttpRequest;
+use super::multipart::{upload_stream, UploadError, UploadMeta, UploadSink};
+use super::util::{get, post, resource};
+use super::{types, FromRequest, Responder};
+
+pub use super::error::GraphQLError;
+
+/// GraphQL request
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct GraphQLRequest {
+    /// Query document
+    pub query: String,
+    /// Name of the operation to execute
+    #[serde(rename = "operationName")]
+    pub operation_name: Option<String>,
+    /// Operation variables
+    pub variables: Option<Value>,
+    /// Request extensions
+    pub extensions: Option<Value>,
+    /// Uploaded files of multipart request
+    #[serde(skip)]
+    pub uploads: Vec<Upload>,
+}
+
+/// File uploaded with multipart request.
+///
+/// Variable referenced by upload's path is set to `null` in request
+/// variables.
+#[derive(Debug, Clone)]
+pub struct Upload {
+    path: String,
+    filename: Option<String>,
+    content_type: Option<Mime>,
+    data: Bytes,
+}
+
+impl Upload {
+    /// Path of the variable, for example `variables.file`
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// File name provided by the client
+    pub fn filename(&self) -> Option<&str> {
+        self.filename.as_deref()
+    }
+
+    /// Content type of the uploaded data
+    pub fn content_type(&self) -> Option<&Mime> {
+        self.content_type.as_ref()
+    }
+
+    /// Uploaded data
+    pub fn data(&self) -> &Bytes {
+        &self.data
+    }
+
+    /// Consume upload and return uploaded data
+    pub fn into_data(self) -> Bytes {
+        self.data
+    }
+}
+
+/// GraphQL request extractor configuration
+#[derive(Debug, Clone)]
+pub struct GraphQLConfig {
+    limit: usize,
+    upload_limit: usize,
+}
+
+impl GraphQLConfig {
+    /// Change max size of json and graphql payloads. By default max size is 256Kb
+    pub fn limit(mut self, limit: usize) -> Self {
+        self.limit = limit;
+        self
+    }
+
+    /// Change max size of multipart payloads. By default max size is 16Mb
+    pub fn upload_limit(mut self, limit: usize) -> Self {
+        self.upload_limit = limit;
+        self
+    }
+}
+
+impl Default for GraphQLConfig {
+    fn default() -> Self {
+        GraphQLConfig {
+            limit: 262_144,
+            upload_limit: 16_777_216,
+        }
+    }
+}
+
+impl<Err: ErrorRenderer> FromRequest<Err> for GraphQLRequest {
+    type Error = GraphQLError;
+    type Future = LocalBoxFuture<'static, Result<Self, Self::Error>>;
+
+    #[inline]
+    fn from_request(req: &HttpRequest, payload: &mut Payload) -> Self::Future {
+        extract(req, payload).boxed_local()
+    }
+
+    fn accepts_content_type(req: &HttpRequest) -> Option<bool> {
+        if req.method() == Method::GET || req.method() == Method::HEAD {
+            return None;
+        }
+        Some(match req.mime_type() {
+            Ok(Some(mime)) => {
+                (mime.type_() == mime::MULTIPART && mime.subtype() == mime::FORM_DATA)
+                    || mime.subtype() == mime::JSON
+                    || mime.suffix() == Some(mime::JSON)
+                    || (mime.type_() == mime::APPLICATION && mime.subtype() == "graphql")
+            }
+            _ => false,
+        })
+    }
+}
+
+#[derive(Deserialize)]
+struct QueryParams {
+    query: String,
+    #[serde(rename = "operationName")]
+    operation_name: Option<String>,
+    variables: Option<String>,
+    extensions: Option<String>,
+}
+
+fn parse_json<T: serde::de::DeserializeOwned>(data: &[u8]) -> Result<T, GraphQLError> {
+    serde_json::from_slice(data).map_err(|e| GraphQLError::Parse(e.to_string()))
+}
+
+fn extract(
+    req: &HttpRequest,
+    payload: &mut Payload,
+) -> impl Future<Output = Result<GraphQLRequest, GraphQLError>> {
+    let cfg = req.app_data::<GraphQLConfig>().cloned().unwrap_or_default();
+    let is_get = req.method() == Method::GET || req.method() == Method::HEAD;
+    let query = req.query_string().to_string();
+    let mime = req.mime_type();
+
+    let form = match mime {
+        Ok(Some(ref mime))
+            if mime.type_() == mime::MULTIPART && mime.subtype() == mime::FORM_DATA =>
+        {
+            Some(types::Multipart::new(req, payload))
+        }
+        _ => None,
+    };
+    let payload = payload.take();
+
+    async move {
+        if is_get {
+            let params: QueryParams = serde_urlencoded::from_str(&query)
+                .map_err(|e| GraphQLError::Parse(e.to_string()))?;
+            return Ok(GraphQLRequest {
+                query: params.query,
+                operation_name: params.operation_name,
+                variables: params
+                    .variables
+                    .map(|v| parse_json(v.as_bytes()))
+                    .transpose()?,
+                extensions: params
+                    .extensions
+                    .map(|v| parse_json(v.as_bytes()))
+                    .transpose()?,
+                uploads: Vec::new(),
+            });
+        }
+
+        if let Some(form) = form {
+            return from_multipart(form?, cfg.upload_limit).await;
+        }
+        let mime = match mime {
+            Ok(Some(mime)) => mime,
+            _ => return Err(GraphQLError::ContentType),
+        };
+        if mime.subtype() == mime::JSON || mime.suffix() == Some(mime::JSON) {
+            let body = read_body(payload, cfg.limit).await?;
+            parse_json(&body)
+        } else if mime.type_() == mime::APPLICATION && mime.subtype() == "graphql" {
+            let body = read_body(payload, cfg.limit).await?;
+            let query = str::from_utf8(&body)
+                .map_err(|e| GraphQLError::Parse(e.to_string()))?
+                .to_string();
+            Ok(GraphQLRequest {
+                query,
+                ..Default::default()
+            })
+        } else {
+            Err(GraphQLError::ContentType)
+        }
+    }
+}
+
+async fn read_body<S>(mut payload: S, limit: usize) -> Result<Bytes, GraphQLError>
+where
+    S: Stream<Item = Result<Bytes, PayloadError>> + Unpin,
+{
+    let mut body = BytesMut::new();
+    while let Some(item) = payload.next().await {
+        let chunk = item.map_err(GraphQLError::Payload)?;
+        if body.len() + chunk.len() > limit {
+            return Err(GraphQLError::Overflow);
+        }
+        body.extend_from_slice(&chunk);
+    }
+    Ok(body.freeze())
+}
+
+/// Upload sink that buffers multipart field in memory
+struct FieldBuffer(BytesMut);
+
+impl UploadSink for FieldBuffer {
+    type Output = (Bytes, UploadMeta);
+    type Error = Infallible;
+
+    fn write(&mut self, chunk: Bytes) -> LocalBoxFuture<'_, Result<(), Infallible>> {
+        self.0.extend_from_slice(&chunk);
+        ok(()).boxed_local()
+    }
+
+    fn finalize(
+        self,
+        meta: UploadMeta,
+    ) -> LocalBoxFuture<'static, Result<Self::Output, Infallible>> {
+        ok((self.0.freeze(), meta)).boxed_local()
+    }
+}
+
+/// Build request from GraphQL multipart request, `limit` is a max
+/// size of all fields
+async fn from_multipart(
+    mut form: types::Multipart,
+    limit: usize,
+) -> Result<GraphQLRequest, GraphQLError> {
+    let mut operations = None;
+    let mut map = None;
+    let mut files = HashMap::new();
+    let mut size = 0;
+
+    while let Some(field) = form.next().await {
+        let field = field?;
+        let name = field.name().to_string();
+        let meta = field.meta();
+        let remaining = (limit as u64).saturating_sub(size);
+        let (data, meta) =
+            upload_stream(field, meta, FieldBuffer(BytesMut::new()), Some(remaining))
+                .await
+                .map_err(|e| match e {
+                    UploadError::Overflow => GraphQLError::Overflow,
+                    UploadError::Payload(e) => GraphQLError::Payload(e),
+                    UploadError::Multipart(e) => GraphQLError::Form(e),
+                    UploadError::Sink(e) => match e {},
+                })?;
+        size += meta.size();
+
+        match name.as_str() {
+            "operations" => operations = Some(parse_json::<Value>(&data)?),
+            "map" => map = Some(parse_json::<HashMap<String, Vec<String>>>(&data)?),
+            _ => {
+                files.insert(name, (data, meta));
+            }
+        }
+    }
+
+    let mut operations =
+        operations.ok_or(GraphQLError::Multipart("operations field is missing"))?;
+    if operations.is_array() {
+        return Err(GraphQLError::Multipart(
+            "batched operations are not supported",
+        ));
+    }
+
+    let mut uploads = Vec::new();
+    for (key, paths) in map.ok_or(GraphQLError::Multipart("map field is missing"))? {
+        let (data, meta) = files
+            .remove(&key)
+            .ok_or(GraphQLError::Multipart("file is missing"))?;
+        for path in paths {
+            let value = path
+                .split('.')
+                .try_fold(&mut operations, |value, seg| match value {
+                    Value::Object(ref mut map) => map.get_mut(seg),
+                    Value::Array(ref mut items) => seg
+                        .parse::<usize>()
+                        .ok()
+                        .and_then(move |idx| items.get_mut(idx)),
+                    _ => None,
+                })
+                .ok_or(GraphQLError::Multipart("invalid file path"))?;
+            *value = Value::Null;
+
+            uploads.push(Upload {
+                path,
+                filename: meta.filename().map(|s| s.to_string()),
+                content_type: meta.content_type().cloned(),
+                data: data.clone(),
+            });
+        }
+    }
+
+    let mut req: GraphQLRequest = serde_json::from_value(operations)
+        .map_err(|e| GraphQLError::Parse(e.to_string()))?;
+    req.uploads = uploads;
+    Ok(req)
+}
+
+/// GraphQL response
+#[derive(Debug, Clone)]
+pub struct GraphQLResponse(pub Value);
+
+impl From<Value> for GraphQLResponse {
+    fn from(value: Value) -> Self {
+        GraphQLResponse(value)
+    }
+}
+
+impl<Err: ErrorRenderer> Responder<Err> for GraphQLResponse {
+    type Error = Err::Container;
+    type Future = Ready<Result<Response, Self::Error>>;
+
+    fn respond_to(self, _: &HttpRequest) -> Self::Future {
+        ok(Response::Ok()
+            .content_type("application/json")
+            .body(self.0.to_string()))
+    }
+}
+
+/// GraphQL request executor
+pub trait GraphQLExecutor: 'static {
+    /// Execute GraphQL request
+    fn execute(&self, req: GraphQLRequest) -> LocalBoxFuture<'static, GraphQLResponse>;
+}
+
+impl<F, R> GraphQLExecutor for F
+where
+    F: Fn(GraphQLRequest) -> R + 'static,
+    R: Future<Output = GraphQLResponse> + 'static,
+{
+    fn execute(&self, req: GraphQLRequest) -> LocalBoxFuture<'static, GraphQLResponse> {
+        (self)(req).boxed_local()
+    }
+}
+
+/// GraphiQL page assets.
+///
+/// By default assets are loaded from unpkg with pinned versions. Assets
+/// could be served by the application or loaded from other location,
+/// integrity hash is rendered as subresource integrity attribute.
+///
+/// ```rust
+/// use ntex::web::graphql::GraphiQLAssets;
+///
+/// let assets = GraphiQLAssets::new()
+///     .stylesheet("/static/graphiql.min.css", None)
+///     .script("/static/react.production.min.js", None)
+///     .script("/static/react-dom.production.min.js", None)
+///     .script("/static/graphiql.min.js", Some("sha384-..."));
+/// ```
+#[derive(Debug, Clone)]
+pub struct GraphiQLAssets {
+    stylesheets: Vec<(String, Option<String>)>,
+    scripts: Vec<(String, Option<String>)>,
+}
+
+impl GraphiQLAssets {
+    /// Create empty set of assets
+    pub fn new() -> Self {
+        GraphiQLAssets {
+            stylesheets: Vec::new(),
+            scripts: Vec::new(),
+        }
+    }
+
+    /// Add stylesheet with optional integrity hash
+    pub fn stylesheet(mut self, url: &str, integrity: Option<&str>) -> Self {
+        self.stylesheets
+            .push((url.to_string(), integrity.map(|s| s.to_string())));
+        self
+    }
+
+    /// Add script with optional integrity hash, scripts are loaded in order
+    pub fn script(mut self, url: &str, integrity: Option<&str>) -> Self {
+        self.scripts
+            .push((url.to_string(), integrity.map(|s| s.to_string())));
+        self
+    }
+}
+
+impl Default for GraphiQLAssets {
+    fn default() -> Self {
+        GraphiQLAssets::new()
+            .stylesheet("https://unpkg.com/graphiql@1.4.7/graphiql.min.css", None)
+            .script(
+                "https://unpkg.com/react@17.0.2/umd/react.production.min.js",
+                None,
+            )
+            .script(
+                "https://unpkg.com/react-dom@17.0.2/umd/react-dom.production.min.js",
+                None,
+            )
+            .script("https://unpkg.com/graphiql@1.4.7/graphiql.min.js", None)
+    }
+}
+
+/// GraphQL service.
+///
+/// Service accepts `GET` and `POST` requests at specified path and
+/// executes them with the executor. Extraction errors are rendered with
+/// application's error renderer.
+pub struct GraphQL<E> {
+    path: String,
+    graphiql: Option<(String, GraphiQLAssets)>,
+    executor: Rc<E>,
+}
+
+impl<E: GraphQLExecutor> GraphQL<E> {
+    /// Create GraphQL service for specified path and executor
+    pub fn new(path: &str, executor: E) -> Self {
+        GraphQL {
+            path: path.to_string(),
+            graphiql: None,
+            executor: Rc::new(executor),
+        }
+    }
+
+    /// Serve GraphiQL IDE at specified path, with default assets
+    pub fn graphiql(self, path: &str) -> Self {
+        self.graphiql_with_assets(path, GraphiQLAssets::default())
+    }
+
+    /// Serve GraphiQL IDE at specified path, with specified assets
+    pub fn graphiql_with_assets(mut self, path: &str, assets: GraphiQLAssets) -> Self {
+        self.graphiql = Some((path.to_string(), assets));
+        self
+    }
+}
+
+impl<E, Err> WebServiceFactory<Err> for GraphQL<E>
+where
+    E: GraphQLExecutor,
+    Err: ErrorRenderer,
+    GraphQLError: Into<Err::Container>,
+{
+    fn register(self, config: &mut WebServiceConfig<Err>) {
+        let (exec1, exec2) = (self.executor.clone(), self.executor);
+
+        resource(&self.path)
+            .route(get().to(move |req: HttpRequest| {
+                execute::<_, Err>(exec1.clone(), req, Payload::None)
+            }))
+            .route(post().to(move |req: HttpRequest, pl: types::Payload| {
+                execute::<_, Err>(exec2.clone(), req, pl.0)
+            }))
+            .register(config);
+
+        if let Some((path, assets)) = self.graphiql {
+            let page = graphiql_page(&self.path, &assets);
+            resource(&path)
+                .route(get().to(move || {
+                    ready(
+                        Response::Ok()
+                            .content_type("text/html; charset=utf-8")
+                            .body(page.clone()),
+                    )
+                }))
+                .register(config);
+        }
+    }
+}
+
+async fn execute<E, Err>(
+    executor: Rc<E>,
+    req: HttpRequest,
+    mut payload: Payload,
+) -> Response
+where
+    E: GraphQLExecutor,
+    Err: ErrorRenderer,
+    GraphQLError: Into<Err::Container>,
+{
+    match extract(&req, &mut payload).await {
+        Ok(gql) => {
+            let res = executor.execute(gql).await;
+            Response::Ok()
+                .content_type("application/json")
+                .body(res.0.to_string())
+        }
+        Err(e) => {
+            let err: Err::Container = e.into();
+            err.error_response()
+        }
+    }
+}
+
+fn escape_attr(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('"', "&quot;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+fn asset_attrs(url: &str, integrity: &Option<String>) -> String {
+    match integrity {
+        Some(hash) => format!(
+            "\"{}\" integrity=\"{}\" crossorigin=\"anonymous\"",
+            escape_attr(url),
+            escape_attr(hash)
+        ),
+        None => format!("\"{}\"", escape_attr(url)),
+    }
+}
+
+fn graphiql_page(endpoint: &str, assets: &GraphiQLAssets) -> String {
+    let mut head = String::new();
+    for (url, integrity) in &assets.stylesheets {
+        head.push_str(&format!(
+            "  <link rel=\"stylesheet\" href={} />\n",
+            asset_attrs(url, integrity)
+        ));
+    }
+    let mut scripts = String::new();
+    for (url, integrity) in &assets.scripts {
+        scripts.push_str(&format!(
+            "  <script src={}></script>\n",
+            asset_attrs(url, integrity)
+        ));
+    }
+
+    format!(
+        r#"<!DOCTYPE html>
+<html>
+<head>
+  <title>GraphiQL</title>
+{}</head>
+<body style="margin: 0;">
+  <div id="graphiql" style="height: 100vh;"></div>
+{}  <script>
+    const fetcher = GraphiQL.createFetcher({{ url: {:?} }});
+    ReactDOM.render(React.createElement(GraphiQL, {{ fetcher }}), document.getElementById("graphiql"));
+  </script>
+</body>
+</html>"#,
+        head, scripts, endpoint
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::header::CONTENT_TYPE;
+    use crate::web::error::{DefaultError, WebResponseError};
+    use crate::web::test::{self, from_request, TestRequest};
+    use crate::web::App;
+
+    async fn echo(req: GraphQLRequest) -> GraphQLResponse {
+        GraphQLResponse(serde_json::json!({
+            "query": req.query,
+            "operationName": req.operation_name,
+            "variables": req.variables,
+            "uploads": req.uploads.iter().map(|u| u.path()).collect::<Vec<_>>(),
+        }))
+    }
+
+    #[ntex_rt::test]
+    async fn test_get() {
+        let req = TestRequest::with_uri(
+            "/?query=%7Bhello%7D&operationName=op&variables=%7B%22a%22%3A1%7D",
+        )
+        .to_http_request();
+        let gql = from_request::<GraphQLRequest>(&req, &mut Payload::None)
+            .await
+            .unwrap();
+        assert_eq!(gql.query, "{hello}");
+        assert_eq!(gql.operation_name.as_deref(), Some("op"));
+        assert_eq!(gql.variables, Some(serde_json::json!({"a": 1})));
+
+        let req = TestRequest::with_uri("/?variables=1").to_http_request();
+        assert!(from_request::<GraphQLRequest>(&req, &mut Payload::None)
+            .await
+            .is_err());
+    }
+
+    #[ntex_rt::test]
+    async fn test_post() {
+        let (req, mut pl) = TestRequest::post()
+            .header(CONTENT_TYPE, "application/json")
+            .set_payload(r#"{"query":"{hello}","variables":{"a":1}}"#)
+            .to_http_parts();
+        let gql = from_request::<GraphQLRequest>(&req, &mut pl).await.unwrap();
+        assert_eq!(gql.query, "{hello}");
+        assert_eq!(gql.variables, Some(serde_json::json!({"a": 1})));
+
+        let (req, mut pl) = TestRequest::post()
+            .header(CONTENT_TYPE, "application/graphql")
+            .set_payload("{hello}")
+            .to_http_parts();
+        let gql = from_request::<GraphQLRequest>(&req, &mut pl).await.unwrap();
+        assert_eq!(gql.query, "{hello}");
+
+        let (req, mut pl) = TestRequest::post()
+            .header(CONTENT_TYPE, "text/plain")
+            .set_payload("{hello}")
+            .to_http_parts();
+        let err = from_request::<GraphQLRequest>(&req, &mut pl)
+            .await
+            .unwrap_err();
+        assert_eq!(
+            WebResponseError::<DefaultError>::status_code(&err),
+            StatusCode::UNSUPPORTED_MEDIA_TYPE
+        );
+
+        let (req, mut pl) = TestRequest::post()
+            .header(CONTENT_TYPE, "application/json")
+            .set_payload(r#"{"query":"{hello}"}"#)
+            .data(GraphQLConfig::default().limit(5))
+            .to_http_parts();
+        let err = from_request::<GraphQLRequest>(&req, &mut pl)
+            .await
+            .unwrap_err();
+        assert_eq!(
+            WebResponseError::<DefaultError>::status_code(&err),
+            StatusCode::PAYLOAD_TOO_LARGE
+        );
+    }
+
+    #[ntex_rt::test]
+    async fn test_multipart() {
+        let body = "--xyz\r\n\
+             Content-Disposition: form-data; name=\"operations\"\r\n\r\n\
+             {\"query\":\"mutation($files: [Upload!]!) { upload(files: $files) }\",\
+             \"variables\":{\"files\":[null,null]}}\r\n\
+             --xyz\r\n\
+             Content-Disposition: form-data; name=\"map\"\r\n\r\n\
+             {\"0\":[\"variables.files.0\"],\"1\":[\"variables.files.1\"]}\r\n\
+             --xyz\r\n\
+             Content-Disposition: form-data; name=\"0\"; filename=\"a.txt\"\r\n\
+             Content-Type: text/plain\r\n\r\n\
+             Alpha\r\n\
+             --xyz\r\n\
+             Content-Disposition: form-data; name=\"1\"; filename=\"b.txt\"\r\n\r\n\
+             Bravo\r\nfile\r\n\
+             --xyz--\r\n";
+        let (req, mut pl) = TestRequest::post()
+            .header(CONTENT_TYPE, "multipart/form-data; boundary=xyz")
+            .set_payload(body)
+            .to_http_parts();
+        let mut gql = from_request::<GraphQLRequest>(&req, &mut pl).await.unwrap();
+        assert_eq!(
+            gql.variables,
+            Some(serde_json::json!({"files": [null, null]}))
+        );
+        gql.uploads.sort_by(|a, b| a.path().cmp(b.path()));
+        assert_eq!(gql.uploads.len(), 2);
+        assert_eq!(gql.uploads[0].path(), "variables.files.0");
+        assert_eq!(gql.uploads[0].filename(), Some("a.txt"));
+        assert_eq!(gql.uploads[0].content_type(), Some(&mime::TEXT_PLAIN));
+        assert_eq!(gql.uploads[0].data(), &Bytes::from_static(b"Alpha"));
+        assert_eq!(gql.uploads[1].path(), "variables.files.1");
+        assert_eq!(gql.uploads[1].content_type(), None);
+        assert_eq!(gql.uploads[1].data(), &Bytes::from_static(b"Bravo\r\nfile"));
+
+        let (req, mut pl) = TestRequest::post()
+            .header(CONTENT_TYPE, "multipart/form-data; boundary=xyz")
+            .set_payload(body)
+            .data(GraphQLConfig::default().upload_limit(64))
+            .to_http_parts();
+        let err = from_request::<GraphQLRequest>(&req, &mut pl)
+            .await
+            .unwrap_err();
+        assert_eq!(
+            WebResponseError::<DefaultError>::status_code(&err),
+            StatusCode::PAYLOAD_TOO_LARGE
+        );
+
+        let body = "--xyz\r\n\
+             Content-Disposition: form-data; name=\"operations\"\r\n\r\n\
+             {\"query\":\"{hello}\"}\r\n\
+             --xyz\r\n\
+             Content-Disposition: form-data; name=\"map\"\r\n\r\n\
+             {\"0\":[\"variables.file\"]}\r\n\
+             --xyz--\r\n";
+        let (req, mut pl) = TestRequest::post()
+            .header(CONTENT_TYPE, "multipart/form-data; boundary=xyz")
+            .set_payload(body)
+            .to_http_parts();
+        assert!(from_request::<GraphQLRequest>(&req, &mut pl).await.is_err());
+    }
+
+    #[ntex_rt::test]
+    async fn test_service() {
+        let srv = test::init_service(
+            App::new().service(GraphQL::new("/graphql", echo).graphiql("/graphiql")),
+        )
+        .await;
+
+        let req = TestRequest::with_uri("/graphql?query=%7Bhello%7D").to_request();
+        let res: Value = test::read_response_json(&srv, req).await;
+        assert_eq!(res["query"], "{hello}");
+
+        let req = TestRequest::post()
+            .uri("/graphql")
+            .header(CONTENT_TYPE, "application/json")
+            .set_payload(r#"{"query":"{world}","operationName":"op"}"#)
+            .to_request();
+        let res: Value = test::read_response_json(&srv, req).await;
+        assert_eq!(res["query"], "{world}");
+        assert_eq!(res["operationName"], "op");
+
+        let req = TestRequest::post()
+            .uri("/graphql")
+            .set_payload("{world}")
+            .to_request();
+        let res = test::call_service(&srv, req).await;
+        assert_eq!(res.status(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+
+        let req = TestRequest::with_uri("/graphiql").to_request();
+        let res = test::read_response(&srv, req).await;
+        let page = str::from_utf8(&res).unwrap();
+        assert!(page.contains("\"/graphql\""));
+        assert!(page.contains("https://unpkg.com/graphiql@1.4.7/graphiql.min.js"));
+
+        let srv = test::init_service(
+            App::new().service(
+                GraphQL::new("/graphql", echo).graphiql_with_assets(
+                    "/graphiql",
+                    GraphiQLAssets::new()
+                        .stylesheet("/static/graphiql.css", None)
+                        .script("/static/graphiql.js", Some("sha384-abc")),
+                ),
+            ),
+        )
+        .await;
+        let req = TestRequest::with_uri("/graphiql").to_request();
+        let res = test::read_response(&srv, req).await;
+        let page = str::from_utf8(&res).unwrap();
+        assert!(page.contains("href=\"/static/graphiql.css\" />"));
+        assert!(page.contains(
+            "<script src=\"/static/graphiql.js\" integrity=\"sha384-abc\" \
+             crossorigin=\"anonymous\"></script>"
+        ));
+        assert!(!page.contains("unpkg"));
+    }
+}
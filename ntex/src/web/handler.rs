@@ -1,17 +1,22 @@
+use std::any::type_name;
 use std::future::Future;
 use std::marker::PhantomData;
 use std::pin::Pin;
 use std::task::{Context, Poll};
 
-use futures::future::{FutureExt, LocalBoxFuture};
+use futures::future::{ok, FutureExt, LocalBoxFuture};
 use pin_project::pin_project;
 
+use crate::http::{header, Payload, StatusCode};
+
 use super::error::ErrorRenderer;
 use super::extract::FromRequest;
 use super::httprequest::HttpRequest;
+use super::report::{ExtractorFailure, RenderedError};
 use super::request::WebRequest;
 use super::responder::Responder;
 use super::response::WebResponse;
+use super::types::DataRequirement;
 
 /// Async fn handler
 pub trait Handler<T, Err>: Clone + 'static
@@ -39,6 +44,25 @@ where
     }
 }
 
+/// Strict content-type enforcement switch, stored in app data
+pub(super) struct StrictContentType(pub(super) bool);
+
+/// Check if strict content-type enforcement applies to request
+fn is_strict(req: &HttpRequest, payload: &Payload) -> bool {
+    if let Payload::None = payload {
+        return false;
+    }
+    let empty = req
+        .headers()
+        .get(header::CONTENT_LENGTH)
+        .map_or(false, |len| len == "0");
+
+    !empty
+        && req
+            .app_data::<StrictContentType>()
+            .map_or(false, |strict| strict.0)
+}
+
 pub(super) trait HandlerFn<Err: ErrorRenderer> {
     fn call(
         &self,
@@ -46,6 +70,8 @@ pub(super) trait HandlerFn<Err: ErrorRenderer> {
     ) -> LocalBoxFuture<'static, Result<WebResponse, Err::Container>>;
 
     fn clone_handler(&self) -> Box<dyn HandlerFn<Err>>;
+
+    fn required_data(&self, data: &mut Vec<DataRequirement>);
 }
 
 pub(super) struct HandlerWrapper<F, T, Err>
@@ -90,6 +116,13 @@ where
     ) -> LocalBoxFuture<'static, Result<WebResponse, Err::Container>> {
         let (req, mut payload) = req.into_parts();
 
+        // reject unsupported payload before extractors start reading it
+        if is_strict(&req, &payload) && T::accepts_content_type(&req) == Some(false) {
+            let res = Err::default_response(&req, StatusCode::UNSUPPORTED_MEDIA_TYPE);
+            req.extensions_mut().insert(RenderedError);
+            return ok(WebResponse::new(req, res)).boxed_local();
+        }
+
         HandlerWrapperResponse {
             hnd: self.hnd.clone(),
             fut1: Some(T::from_request(&req, &mut payload)),
@@ -106,6 +139,10 @@ where
             _t: PhantomData,
         })
     }
+
+    fn required_data(&self, data: &mut Vec<DataRequirement>) {
+        T::required_data(data)
+    }
 }
 
 impl<F, T, Err> Clone for HandlerWrapper<F, T, Err>
@@ -166,10 +203,15 @@ where
                     self.poll(cx)
                 }
                 Poll::Pending => Poll::Pending,
-                Poll::Ready(Err(e)) => Poll::Ready(Ok(WebResponse::from_err::<Err, _>(
-                    e,
-                    this.req.take().unwrap(),
-                ))),
+                Poll::Ready(Err(e)) => {
+                    let req = this.req.take().unwrap();
+                    // tuple extractors record failed element
+                    if !req.extensions().contains::<ExtractorFailure>() {
+                        req.extensions_mut()
+                            .insert(ExtractorFailure(type_name::<T>()));
+                    }
+                    Poll::Ready(Ok(WebResponse::from_err::<Err, _>(e, req)))
+                }
             };
         }
 
@@ -8,12 +8,13 @@ use crate::http::{
     Extensions, HeaderMap, HttpMessage, Message, Method, Payload, RequestHead, Uri,
     Version,
 };
-use crate::router::Path;
+use crate::router::{Path, ResourceDef};
 
 use super::config::AppConfig;
 use super::error::{ErrorRenderer, UrlGenerationError};
 use super::extract::FromRequest;
 use super::info::ConnectionInfo;
+use super::response::WebResponse;
 use super::rmap::ResourceMap;
 
 #[derive(Clone)]
@@ -23,6 +24,9 @@ pub struct HttpRequest(pub(crate) Rc<HttpRequestInner>);
 pub(crate) struct HttpRequestInner {
     pub(crate) head: Message<RequestHead>,
     pub(crate) path: Path<Uri>,
+    pub(crate) resources: Vec<Rc<ResourceDef>>,
+    pub(crate) matched: bool,
+    pub(crate) prefix: usize,
     pub(crate) payload: Payload,
     pub(crate) app_data: Rc<Extensions>,
     rmap: Rc<ResourceMap>,
@@ -44,6 +48,9 @@ impl HttpRequest {
         HttpRequest(Rc::new(HttpRequestInner {
             head,
             path,
+            resources: Vec::new(),
+            matched: false,
+            prefix: 0,
             payload,
             rmap,
             config,
@@ -125,6 +132,76 @@ impl HttpRequest {
         &mut Rc::get_mut(&mut self.0).unwrap().path
     }
 
+    /// The resource definition pattern that matched the path.
+    ///
+    /// Patterns of all matched scopes are joined, so for a resource
+    /// `/{id}` registered in scope `/users` pattern is `/users/{id}`.
+    /// Returns `None` if request has not been routed to any resource,
+    /// including requests handled by scope's default service.
+    pub fn match_pattern(&self) -> Option<String> {
+        if !self.0.matched || self.0.resources.is_empty() {
+            return None;
+        }
+
+        let mut pattern = String::new();
+        for rdef in &self.0.resources {
+            let p = rdef.pattern();
+            if pattern.ends_with('/') && p.starts_with('/') {
+                pattern.push_str(&p[1..]);
+            } else {
+                pattern.push_str(p);
+            }
+        }
+        Some(pattern)
+    }
+
+    /// The name of the matched resource.
+    ///
+    /// Returns `None` if request has not been routed to a resource
+    /// or matched resource has no name.
+    pub fn match_name(&self) -> Option<&str> {
+        if !self.0.matched {
+            return None;
+        }
+        self.0
+            .resources
+            .last()
+            .map(|rdef| rdef.name())
+            .filter(|name| !name.is_empty())
+    }
+
+    /// Part of the request path matched by enclosing scopes.
+    ///
+    /// For a resource registered in scope `/static`, and request path
+    /// `/static/css/main.css` prefix is `/static`.
+    pub fn match_prefix(&self) -> &str {
+        &self.0.path.get_ref().path()[..self.0.prefix]
+    }
+
+    /// Request path with enclosing scopes prefix stripped.
+    ///
+    /// For a resource registered in scope `/static`, and request path
+    /// `/static/css/main.css` path is `/css/main.css`.
+    pub fn match_unprefixed(&self) -> &str {
+        &self.0.path.get_ref().path()[self.0.prefix..]
+    }
+
+    #[inline]
+    pub(crate) fn add_resource(&mut self, rdef: Rc<ResourceDef>) {
+        let inner = Rc::get_mut(&mut self.0).unwrap();
+        if rdef.is_prefix() {
+            inner.prefix = inner.path.get_ref().path().len() - inner.path.path().len();
+        }
+        inner.matched = true;
+        inner.resources.push(rdef)
+    }
+
+    /// Mark request as not matched by any resource of enclosing scope
+    #[inline]
+    pub(crate) fn set_unmatched(&mut self) {
+        Rc::get_mut(&mut self.0).unwrap().matched = false;
+    }
+
     /// Request extensions
     #[inline]
     pub fn extensions(&self) -> Ref<'_, Extensions> {
@@ -245,11 +322,67 @@ impl HttpMessage for HttpRequest {
     }
 }
 
+/// Request parts retained by middlewares for rendering errors of inner
+/// services.
+///
+/// Middlewares could not retain request itself, router requires exclusive
+/// access to it. Detached request does not contain headers and payload.
+pub(crate) struct DetachedRequest {
+    method: Method,
+    uri: Uri,
+    version: Version,
+    rmap: Rc<ResourceMap>,
+    config: AppConfig,
+    app_data: Rc<Extensions>,
+    pool: &'static HttpRequestPool,
+}
+
+impl HttpRequest {
+    pub(crate) fn detach(&self) -> DetachedRequest {
+        DetachedRequest {
+            method: self.0.head.method.clone(),
+            uri: self.0.head.uri.clone(),
+            version: self.0.head.version,
+            rmap: self.0.rmap.clone(),
+            config: self.0.config.clone(),
+            app_data: self.0.app_data.clone(),
+            pool: self.0.pool,
+        }
+    }
+}
+
+impl DetachedRequest {
+    pub(crate) fn into_request(self) -> HttpRequest {
+        let mut head = Message::<RequestHead>::new();
+        head.method = self.method;
+        head.uri = self.uri.clone();
+        head.version = self.version;
+
+        HttpRequest::new(
+            Path::new(self.uri),
+            head,
+            Payload::None,
+            self.rmap,
+            self.config,
+            self.app_data,
+            self.pool,
+        )
+    }
+
+    /// Create web response for error
+    pub(crate) fn error_response<Err: ErrorRenderer, B>(
+        self,
+        err: Err::Container,
+    ) -> WebResponse<B> {
+        WebResponse::from_err::<Err, _>(err, self.into_request())
+    }
+}
+
 impl Drop for HttpRequest {
     fn drop(&mut self) {
         if Rc::strong_count(&self.0) == 1 {
             let v = &mut self.0.pool.0.borrow_mut();
-            if v.len() < 128 {
+            if v.len() < self.0.pool.1 {
                 self.extensions_mut().clear();
                 v.push(self.0.clone());
             }
@@ -311,11 +444,12 @@ impl fmt::Debug for HttpRequest {
 }
 
 /// Request's objects pool
-pub(crate) struct HttpRequestPool(RefCell<Vec<Rc<HttpRequestInner>>>);
+pub(crate) struct HttpRequestPool(RefCell<Vec<Rc<HttpRequestInner>>>, usize);
 
 impl HttpRequestPool {
     pub(crate) fn create() -> &'static HttpRequestPool {
-        let pool = HttpRequestPool(RefCell::new(Vec::with_capacity(128)));
+        let size = crate::server::pool_size();
+        let pool = HttpRequestPool(RefCell::new(Vec::with_capacity(size)), size);
         Box::leak(Box::new(pool))
     }
 
@@ -342,7 +476,7 @@ mod tests {
     use crate::http::{header, StatusCode};
     use crate::router::ResourceDef;
     use crate::web::dev::ResourceMap;
-    use crate::web::test::{call_service, init_service, TestRequest};
+    use crate::web::test::{call_service, init_service, read_body, TestRequest};
     use crate::web::{self, App, HttpResponse};
 
     #[test]
@@ -493,6 +627,45 @@ mod tests {
         assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
     }
 
+    #[ntex_rt::test]
+    async fn test_match_prefix() {
+        let srv = init_service(
+            App::new()
+                .service(web::scope("/static/{version}").service(
+                    web::resource("/{*tail}").to(
+                        |req: HttpRequest, path: web::types::Path<(String, String)>| {
+                            ready(HttpResponse::Ok().body(format!(
+                                "{} {} {} {}",
+                                req.match_prefix(),
+                                req.match_unprefixed(),
+                                path.0,
+                                path.1
+                            )))
+                        },
+                    ),
+                ))
+                .service(web::resource("/index.html").to(|req: HttpRequest| {
+                    ready(HttpResponse::Ok().body(format!(
+                        "'{}' {}",
+                        req.match_prefix(),
+                        req.match_unprefixed()
+                    )))
+                })),
+        )
+        .await;
+
+        let req = TestRequest::with_uri("/static/v1/css/a%20b.css").to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(
+            read_body(resp).await,
+            "/static/v1 /css/a%20b.css v1 css/a%20b.css"
+        );
+
+        let req = TestRequest::with_uri("/index.html").to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(read_body(resp).await, "'' /index.html");
+    }
+
     #[ntex_rt::test]
     async fn test_extensions_dropped() {
         struct Tracker {
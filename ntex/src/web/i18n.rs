@@ -0,0 +1,671 @@
+//! Internationalization helpers
+//!
+//! `Locale` extractor negotiates request's locale, `I18n` extractor
+//! provides locale, time zone and message lookup in translation catalog.
+//!
+//! ```rust
+//! use ntex::web::{self, App};
+//! use ntex::web::i18n::{Catalog, I18n, LocaleConfig};
+//!
+//! async fn index(i18n: I18n) -> String {
+//!     i18n.format("greeting", &[("name", &"Bob")])
+//! }
+//!
+//! fn main() {
+//!     let app = App::new()
+//!         .app_data(LocaleConfig::default().supported(&["en", "de"]))
+//!         .app_data(
+//!             Catalog::new()
+//!                 .fallback("en")
+//!                 .message("en", "greeting", "Hello, {name}!")
+//!                 .message("de", "greeting", "Hallo, {name}!"),
+//!         )
+//!         .service(web::resource("/").to(index));
+//! }
+//! ```
+use std::collections::HashMap;
+use std::fmt;
+use std::rc::Rc;
+
+use futures::future::{err, ok, Ready};
+use time::{OffsetDateTime, UtcOffset};
+
+use crate::http::header::{ACCEPT_LANGUAGE, COOKIE};
+use crate::http::Payload;
+
+use super::error::{DataExtractorError, ErrorRenderer};
+use super::extract::FromRequest;
+use super::httprequest::HttpRequest;
+
+/// Source of request's locale
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LocaleSource {
+    /// Query parameter
+    Query,
+    /// Cookie
+    Cookie,
+    /// `Accept-Language` header
+    Header,
+}
+
+/// Locale negotiation configuration.
+///
+/// By default locale is taken from `lang` query parameter, then from
+/// `lang` cookie and then from `Accept-Language` header. Time zone is
+/// taken from `tz` query parameter or cookie. Any locale is accepted and
+/// default locale is `en`.
+#[derive(Clone, Debug)]
+pub struct LocaleConfig {
+    supported: Vec<Locale>,
+    default: Locale,
+    query: String,
+    cookie: String,
+    tz: String,
+    precedence: Vec<LocaleSource>,
+}
+
+impl Default for LocaleConfig {
+    fn default() -> Self {
+        LocaleConfig {
+            supported: Vec::new(),
+            default: Locale("en".to_string()),
+            query: "lang".to_string(),
+            cookie: "lang".to_string(),
+            tz: "tz".to_string(),
+            precedence: vec![
+                LocaleSource::Query,
+                LocaleSource::Cookie,
+                LocaleSource::Header,
+            ],
+        }
+    }
+}
+
+impl LocaleConfig {
+    /// Set supported locales.
+    ///
+    /// Requested locale is matched exactly or by language, for example
+    /// `de-AT` matches `de`. By default any locale is accepted.
+    ///
+    /// Panics if locale is not valid language tag.
+    pub fn supported(mut self, locales: &[&str]) -> Self {
+        self.supported = locales.iter().map(|tag| locale(tag)).collect();
+        self
+    }
+
+    /// Set default locale, it is used if negotiation fails.
+    ///
+    /// Panics if locale is not valid language tag.
+    pub fn default_locale(mut self, tag: &str) -> Self {
+        self.default = locale(tag);
+        self
+    }
+
+    /// Set query parameter name for locale override
+    pub fn query(mut self, name: &str) -> Self {
+        self.query = name.to_string();
+        self
+    }
+
+    /// Set cookie name for locale override
+    pub fn cookie(mut self, name: &str) -> Self {
+        self.cookie = name.to_string();
+        self
+    }
+
+    /// Set query parameter and cookie name for time zone.
+    ///
+    /// Time zone is utc offset, `+02:00`, `-0530` or `Z`.
+    pub fn time_zone(mut self, name: &str) -> Self {
+        self.tz = name.to_string();
+        self
+    }
+
+    /// Set locale sources in order of precedence.
+    ///
+    /// Sources that are not listed are not used.
+    pub fn precedence(mut self, sources: &[LocaleSource]) -> Self {
+        self.precedence = sources.to_vec();
+        self
+    }
+
+    fn negotiate(&self, req: &HttpRequest) -> Locale {
+        for source in &self.precedence {
+            let found = match source {
+                LocaleSource::Query => {
+                    query(req, &self.query).and_then(|value| self.matches(&value))
+                }
+                LocaleSource::Cookie => {
+                    cookie(req, &self.cookie).and_then(|value| self.matches(&value))
+                }
+                LocaleSource::Header => accept_language(req)
+                    .iter()
+                    .find_map(|tag| self.matches(tag)),
+            };
+            if let Some(locale) = found {
+                return locale;
+            }
+        }
+        self.default.clone()
+    }
+
+    fn matches(&self, tag: &str) -> Option<Locale> {
+        let loc = Locale::new(tag)?;
+        if self.supported.is_empty() {
+            return Some(loc);
+        }
+
+        self.supported
+            .iter()
+            .find(|l| **l == loc)
+            .or_else(|| self.supported.iter().find(|l| l.0 == loc.language()))
+            .or_else(|| {
+                self.supported
+                    .iter()
+                    .find(|l| l.language() == loc.language())
+            })
+            .cloned()
+    }
+
+    fn time_zone_of(&self, req: &HttpRequest) -> UtcOffset {
+        query(req, &self.tz)
+            .or_else(|| cookie(req, &self.tz))
+            .and_then(|value| parse_offset(&value))
+            .unwrap_or(UtcOffset::UTC)
+    }
+}
+
+fn locale(tag: &str) -> Locale {
+    match Locale::new(tag) {
+        Some(loc) => loc,
+        None => panic!("Invalid locale: {:?}", tag),
+    }
+}
+
+fn query(req: &HttpRequest, name: &str) -> Option<String> {
+    url::form_urlencoded::parse(req.query_string().as_bytes())
+        .find(|(key, _)| key == name)
+        .map(|(_, value)| value.into_owned())
+}
+
+fn cookie(req: &HttpRequest, name: &str) -> Option<String> {
+    req.headers()
+        .get_all(COOKIE)
+        .filter_map(|val| val.to_str().ok())
+        .flat_map(|val| val.split(';'))
+        .find_map(|pair| {
+            let mut parts = pair.trim().splitn(2, '=');
+            if parts.next()? == name {
+                parts
+                    .next()
+                    .map(|value| value.trim_matches('"').to_string())
+            } else {
+                None
+            }
+        })
+}
+
+/// Parse `Accept-Language` header, tags are sorted by quality
+fn accept_language(req: &HttpRequest) -> Vec<String> {
+    let mut tags: Vec<(String, f32)> = req
+        .headers()
+        .get_all(ACCEPT_LANGUAGE)
+        .filter_map(|val| val.to_str().ok())
+        .flat_map(|val| val.split(','))
+        .filter_map(|item| {
+            let mut parts = item.split(';');
+            let tag = parts.next()?.trim();
+            let mut q = 1.0;
+            for param in parts {
+                let mut kv = param.trim().splitn(2, '=');
+                if kv.next() == Some("q") {
+                    q = kv.next().and_then(|v| v.parse().ok()).unwrap_or(0.0);
+                }
+            }
+            if tag.is_empty() || tag == "*" || q <= 0.0 {
+                None
+            } else {
+                Some((tag.to_string(), q))
+            }
+        })
+        .collect();
+    tags.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+    tags.into_iter().map(|(tag, _)| tag).collect()
+}
+
+/// Parse utc offset, `+02:00`, `-0530`, `+02` or `Z`
+fn parse_offset(s: &str) -> Option<UtcOffset> {
+    let s = s.trim();
+    if s == "Z" || s.eq_ignore_ascii_case("utc") {
+        return Some(UtcOffset::UTC);
+    }
+
+    let sign = match s.as_bytes().first()? {
+        b'+' => 1,
+        b'-' => -1,
+        _ => return None,
+    };
+    let digits: String = s[1..].chars().filter(|c| *c != ':').collect();
+    if !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let (hours, minutes) = match digits.len() {
+        2 => (digits.parse::<i16>().ok()?, 0),
+        4 => (
+            digits[..2].parse::<i16>().ok()?,
+            digits[2..].parse::<i16>().ok()?,
+        ),
+        _ => return None,
+    };
+    if hours > 14 || minutes >= 60 {
+        return None;
+    }
+    Some(UtcOffset::minutes(sign * (hours * 60 + minutes)))
+}
+
+/// Request's locale, language tag such as `en` or `de-AT`.
+///
+/// Locale is negotiated according to `LocaleConfig`, if config is not
+/// registered with `App::app_data()` default config is used.
+///
+/// ```rust
+/// use ntex::web::{self, i18n::Locale, App};
+///
+/// async fn index(locale: Locale) -> String {
+///     format!("Language: {}", locale.language())
+/// }
+///
+/// fn main() {
+///     let app = App::new().service(web::resource("/").to(index));
+/// }
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Locale(String);
+
+impl Locale {
+    /// Parse language tag.
+    ///
+    /// Language is lower cased and region is upper cased, `en-us` is
+    /// normalized to `en-US`. Returns `None` if tag is not valid.
+    pub fn new(tag: &str) -> Option<Self> {
+        let mut result = String::with_capacity(tag.len());
+        for (idx, part) in tag.trim().split(&['-', '_'][..]).enumerate() {
+            let valid = part.len() <= 8
+                && if idx == 0 {
+                    part.len() >= 2 && part.bytes().all(|b| b.is_ascii_alphabetic())
+                } else {
+                    !part.is_empty() && part.bytes().all(|b| b.is_ascii_alphanumeric())
+                };
+            if !valid {
+                return None;
+            }
+
+            if idx == 0 {
+                result.push_str(&part.to_ascii_lowercase());
+            } else {
+                result.push('-');
+                if part.len() == 2 {
+                    result.push_str(&part.to_ascii_uppercase());
+                } else {
+                    result.push_str(part);
+                }
+            }
+        }
+        Some(Locale(result))
+    }
+
+    /// Language tag
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// Primary language subtag, `en` for `en-US`
+    pub fn language(&self) -> &str {
+        self.0.split('-').next().unwrap_or("")
+    }
+
+    /// Region subtag, `US` for `en-US`
+    pub fn region(&self) -> Option<&str> {
+        self.0
+            .split('-')
+            .skip(1)
+            .find(|part| part.len() == 2 || part.bytes().all(|b| b.is_ascii_digit()))
+    }
+}
+
+impl fmt::Display for Locale {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl<Err: ErrorRenderer> FromRequest<Err> for Locale {
+    type Error = Err::Container;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    #[inline]
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        ok(match req.app_data::<LocaleConfig>() {
+            Some(cfg) => cfg.negotiate(req),
+            None => LocaleConfig::default().negotiate(req),
+        })
+    }
+}
+
+/// Translation catalog.
+///
+/// Catalog stores messages per locale. Message lookup falls back from
+/// requested locale to its language and then to fallback locale. Catalog
+/// is registered with `App::app_data()` and used by `I18n` extractor.
+#[derive(Clone, Default)]
+pub struct Catalog(Rc<CatalogInner>);
+
+#[derive(Default)]
+struct CatalogInner {
+    messages: HashMap<String, HashMap<String, String>>,
+    fallback: Option<Locale>,
+}
+
+impl Catalog {
+    /// Construct empty catalog
+    pub fn new() -> Self {
+        Catalog::default()
+    }
+
+    /// Add message.
+    ///
+    /// Panics if locale is not valid language tag.
+    pub fn message(self, locale: &str, key: &str, message: &str) -> Self {
+        self.messages(locale, Some((key, message)))
+    }
+
+    /// Add messages.
+    ///
+    /// Panics if locale is not valid language tag.
+    pub fn messages<I, K, V>(mut self, locale: &str, messages: I) -> Self
+    where
+        I: IntoIterator<Item = (K, V)>,
+        K: Into<String>,
+        V: Into<String>,
+    {
+        let loc = self::locale(locale);
+        Rc::get_mut(&mut self.0)
+            .expect("Multiple copies exist")
+            .messages
+            .entry(loc.0)
+            .or_default()
+            .extend(messages.into_iter().map(|(k, v)| (k.into(), v.into())));
+        self
+    }
+
+    /// Set fallback locale.
+    ///
+    /// Panics if locale is not valid language tag.
+    pub fn fallback(mut self, locale: &str) -> Self {
+        Rc::get_mut(&mut self.0)
+            .expect("Multiple copies exist")
+            .fallback = Some(self::locale(locale));
+        self
+    }
+
+    /// Lookup message
+    pub fn get(&self, locale: &Locale, key: &str) -> Option<&str> {
+        let lookup = |tag: &str| {
+            self.0
+                .messages
+                .get(tag)
+                .and_then(|messages| messages.get(key))
+                .map(|s| s.as_str())
+        };
+
+        lookup(locale.as_str())
+            .or_else(|| lookup(locale.language()))
+            .or_else(|| self.0.fallback.as_ref().and_then(|l| lookup(l.as_str())))
+    }
+
+    /// Lookup message and substitute `{name}` placeholders.
+    ///
+    /// If message is not found, key is used as message.
+    pub fn format(
+        &self,
+        locale: &Locale,
+        key: &str,
+        args: &[(&str, &dyn fmt::Display)],
+    ) -> String {
+        let mut result = self.get(locale, key).unwrap_or(key).to_string();
+        for (name, value) in args {
+            let placeholder = format!("{{{}}}", name);
+            if result.contains(&placeholder) {
+                result = result.replace(&placeholder, &value.to_string());
+            }
+        }
+        result
+    }
+}
+
+impl fmt::Debug for Catalog {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Catalog")
+            .field("locales", &self.0.messages.keys().collect::<Vec<_>>())
+            .field("fallback", &self.0.fallback)
+            .finish()
+    }
+}
+
+/// Per-request internationalization context.
+///
+/// Context contains request's locale and time zone and provides message
+/// lookup in `Catalog`. Extractor fails with `DataExtractorError` if
+/// catalog is not registered with `App::app_data()`.
+#[derive(Clone, Debug)]
+pub struct I18n {
+    locale: Locale,
+    tz: UtcOffset,
+    catalog: Catalog,
+}
+
+impl I18n {
+    /// Request's locale
+    pub fn locale(&self) -> &Locale {
+        &self.locale
+    }
+
+    /// Request's time zone, utc by default
+    pub fn time_zone(&self) -> UtcOffset {
+        self.tz
+    }
+
+    /// Lookup message, key is returned if message is not found
+    pub fn t<'a>(&'a self, key: &'a str) -> &'a str {
+        self.catalog.get(&self.locale, key).unwrap_or(key)
+    }
+
+    /// Lookup message and substitute `{name}` placeholders
+    pub fn format(&self, key: &str, args: &[(&str, &dyn fmt::Display)]) -> String {
+        self.catalog.format(&self.locale, key, args)
+    }
+
+    /// Convert time to request's time zone
+    pub fn local_time(&self, time: OffsetDateTime) -> OffsetDateTime {
+        time.to_offset(self.tz)
+    }
+
+    /// Format time in request's time zone, format uses `time` crate syntax
+    pub fn format_time(&self, time: OffsetDateTime, format: &str) -> String {
+        self.local_time(time).format(format)
+    }
+}
+
+impl<Err: ErrorRenderer> FromRequest<Err> for I18n {
+    type Error = DataExtractorError;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    #[inline]
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        let catalog = if let Some(catalog) = req.app_data::<Catalog>() {
+            catalog.clone()
+        } else {
+            log::debug!(
+                "Failed to construct I18n extractor, catalog is not configured. \
+                 Request path: {:?}",
+                req.path()
+            );
+            return err(DataExtractorError::NotConfigured);
+        };
+
+        let tmp;
+        let cfg = if let Some(cfg) = req.app_data::<LocaleConfig>() {
+            cfg
+        } else {
+            tmp = LocaleConfig::default();
+            &tmp
+        };
+
+        ok(I18n {
+            locale: cfg.negotiate(req),
+            tz: cfg.time_zone_of(req),
+            catalog,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::header;
+    use crate::http::StatusCode;
+    use crate::web::test::{call_service, init_service, read_body, TestRequest};
+    use crate::web::{self, App, DefaultError};
+
+    async fn extract(req: TestRequest) -> Locale {
+        let req = req.to_http_request();
+        <Locale as FromRequest<DefaultError>>::extract(&req)
+            .await
+            .unwrap()
+    }
+
+    #[test]
+    fn test_locale() {
+        let loc = Locale::new("en-us").unwrap();
+        assert_eq!(loc.as_str(), "en-US");
+        assert_eq!(loc.language(), "en");
+        assert_eq!(loc.region(), Some("US"));
+        assert_eq!(Locale::new("zh_Hant_tw").unwrap().as_str(), "zh-Hant-TW");
+        assert_eq!(Locale::new("zh-Hant-TW").unwrap().region(), Some("TW"));
+        assert_eq!(Locale::new("es-419").unwrap().region(), Some("419"));
+        assert_eq!(Locale::new("DE").unwrap().region(), None);
+        assert!(Locale::new("").is_none());
+        assert!(Locale::new("e").is_none());
+        assert!(Locale::new("en-").is_none());
+        assert!(Locale::new("en/us").is_none());
+    }
+
+    #[test]
+    fn test_offset() {
+        assert_eq!(parse_offset("Z"), Some(UtcOffset::UTC));
+        assert_eq!(parse_offset("+02:00"), Some(UtcOffset::hours(2)));
+        assert_eq!(parse_offset("-0530"), Some(UtcOffset::minutes(-330)));
+        assert_eq!(parse_offset("+09"), Some(UtcOffset::hours(9)));
+        assert_eq!(parse_offset("+15:00"), None);
+        assert_eq!(parse_offset("02:00"), None);
+        assert_eq!(parse_offset("+2:00"), None);
+    }
+
+    #[ntex_rt::test]
+    async fn test_negotiate() {
+        let cfg = LocaleConfig::default()
+            .supported(&["en", "de", "pt-BR"])
+            .default_locale("en");
+
+        let req = TestRequest::with_header(
+            header::ACCEPT_LANGUAGE,
+            "fr;q=0.9, de-AT;q=0.8, *;q=0.5",
+        )
+        .data(cfg.clone());
+        assert_eq!(extract(req).await.as_str(), "de");
+
+        let req = TestRequest::with_header(header::ACCEPT_LANGUAGE, "pt, en;q=0.1")
+            .data(cfg.clone());
+        assert_eq!(extract(req).await.as_str(), "pt-BR");
+
+        let req =
+            TestRequest::with_header(header::ACCEPT_LANGUAGE, "fr").data(cfg.clone());
+        assert_eq!(extract(req).await.as_str(), "en");
+
+        let req = TestRequest::with_uri("/?lang=de")
+            .header(header::COOKIE, "a=b; lang=pt-br")
+            .header(header::ACCEPT_LANGUAGE, "en")
+            .data(cfg.clone());
+        assert_eq!(extract(req).await.as_str(), "de");
+
+        let req = TestRequest::with_uri("/?lang=xx")
+            .header(header::COOKIE, "a=b; lang=pt-br")
+            .header(header::ACCEPT_LANGUAGE, "en")
+            .data(cfg.clone());
+        assert_eq!(extract(req).await.as_str(), "pt-BR");
+
+        let req = TestRequest::with_uri("/?lang=de")
+            .header(header::COOKIE, "lang=pt-br")
+            .header(header::ACCEPT_LANGUAGE, "en")
+            .data(
+                cfg.clone()
+                    .precedence(&[LocaleSource::Header, LocaleSource::Query]),
+            );
+        assert_eq!(extract(req).await.as_str(), "en");
+
+        let req = TestRequest::with_header(header::ACCEPT_LANGUAGE, "uk-UA, en");
+        assert_eq!(extract(req).await.as_str(), "uk-UA");
+    }
+
+    #[ntex_rt::test]
+    async fn test_catalog() {
+        let catalog = Catalog::new()
+            .fallback("en")
+            .message("en", "hello", "Hello, {name}!")
+            .message("en", "bye", "Bye")
+            .messages("de", vec![("hello", "Hallo, {name}!")])
+            .message("de-AT", "hello", "Servus, {name}!");
+
+        let de = Locale::new("de-DE").unwrap();
+        assert_eq!(catalog.get(&de, "hello"), Some("Hallo, {name}!"));
+        assert_eq!(catalog.get(&de, "bye"), Some("Bye"));
+        assert_eq!(catalog.get(&de, "missing"), None);
+        assert_eq!(
+            catalog.format(&Locale::new("de-at").unwrap(), "hello", &[("name", &1)]),
+            "Servus, 1!"
+        );
+        assert_eq!(catalog.format(&de, "missing", &[]), "missing");
+
+        let srv = init_service(
+            App::new()
+                .app_data(catalog)
+                .app_data(LocaleConfig::default().supported(&["en", "de"]))
+                .service(web::resource("/").to(|i18n: I18n| async move {
+                    format!(
+                        "{} {} {}",
+                        i18n.format("hello", &[("name", &"Bob")]),
+                        i18n.t("bye"),
+                        i18n.format_time(
+                            OffsetDateTime::from_unix_timestamp(0),
+                            "%H:%M"
+                        )
+                    )
+                })),
+        )
+        .await;
+
+        let req = TestRequest::with_uri("/?tz=%2B02:30")
+            .header(header::ACCEPT_LANGUAGE, "de-CH")
+            .to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(read_body(resp).await, "Hallo, Bob! Bye 02:30");
+
+        let srv = init_service(
+            App::new().service(web::resource("/").to(|_: I18n| async { "" })),
+        )
+        .await;
+        let req = TestRequest::default().to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::INTERNAL_SERVER_ERROR);
+    }
+}
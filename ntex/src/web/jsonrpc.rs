@@ -0,0 +1,450 @@
+//! [JSON-RPC 2.0](https://www.jsonrpc.org/specification) service.
+//!
+//! Requests (single and batch) are dispatched to registered async methods,
+//! method params are deserialized with serde. Service accepts `POST`
+//! requests at specified path.
+//!
+//! ```rust
+//! use ntex::web::{self, App};
+//! use ntex::web::jsonrpc::{JsonRpc, RpcError};
+//!
+//! async fn add(params: (i64, i64)) -> Result<i64, RpcError> {
+//!     Ok(params.0 + params.1)
+//! }
+//!
+//! fn main() {
+//!     let app = App::new().service(JsonRpc::new("/rpc").method("add", add));
+//! }
+//! ```
+use std::collections::HashMap;
+use std::future::Future;
+use std::rc::Rc;
+
+use bytes::BytesMut;
+use futures::future::{join_all, FutureExt, LocalBoxFuture};
+use futures::StreamExt;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::{json, Map, Value};
+
+use crate::http::header::CONTENT_TYPE;
+use crate::http::Response;
+
+use super::dev::{WebServiceConfig, WebServiceFactory};
+use super::error::ErrorRenderer;
+use super::types::Payload;
+use super::util::{post, resource};
+
+/// JSON-RPC error object
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RpcError {
+    code: i64,
+    message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    data: Option<Value>,
+}
+
+impl RpcError {
+    /// Create error object with specified code and message
+    pub fn new<T: Into<String>>(code: i64, message: T) -> Self {
+        RpcError {
+            code,
+            message: message.into(),
+            data: None,
+        }
+    }
+
+    /// Invalid JSON was received
+    pub fn parse_error() -> Self {
+        RpcError::new(-32700, "Parse error")
+    }
+
+    /// Request is not a valid request object
+    pub fn invalid_request() -> Self {
+        RpcError::new(-32600, "Invalid Request")
+    }
+
+    /// Method does not exist
+    pub fn method_not_found() -> Self {
+        RpcError::new(-32601, "Method not found")
+    }
+
+    /// Invalid method parameters
+    pub fn invalid_params() -> Self {
+        RpcError::new(-32602, "Invalid params")
+    }
+
+    /// Internal error
+    pub fn internal_error() -> Self {
+        RpcError::new(-32603, "Internal error")
+    }
+
+    /// Set additional information about the error
+    pub fn data<T: Serialize>(mut self, data: T) -> Self {
+        self.data = serde_json::to_value(data).ok();
+        self
+    }
+
+    /// Error code
+    pub fn code(&self) -> i64 {
+        self.code
+    }
+
+    /// Error message
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+
+type Method = Box<dyn Fn(Value) -> LocalBoxFuture<'static, Result<Value, RpcError>>>;
+
+/// JSON-RPC 2.0 service.
+///
+/// Notifications are executed but produce no response. If request
+/// contains only notifications, service responds with `204 No Content`.
+pub struct JsonRpc {
+    path: String,
+    limit: usize,
+    methods: HashMap<String, Method>,
+}
+
+impl JsonRpc {
+    /// Create JSON-RPC service for specified path
+    pub fn new(path: &str) -> Self {
+        JsonRpc {
+            path: path.to_string(),
+            limit: 262_144,
+            methods: HashMap::new(),
+        }
+    }
+
+    /// Change max size of payload. By default max size is 256Kb
+    pub fn limit(mut self, limit: usize) -> Self {
+        self.limit = limit;
+        self
+    }
+
+    /// Register method.
+    ///
+    /// Method params are deserialized from request's `params` member,
+    /// positional params could be deserialized into tuples or sequences,
+    /// named params into structs or maps. If params are omitted, they are
+    /// deserialized from `null`. Params that can not be deserialized are
+    /// reported with "Invalid params" error.
+    pub fn method<F, P, R, Fut>(mut self, name: &str, f: F) -> Self
+    where
+        F: Fn(P) -> Fut + 'static,
+        P: DeserializeOwned + 'static,
+        R: Serialize + 'static,
+        Fut: Future<Output = Result<R, RpcError>> + 'static,
+    {
+        self.methods.insert(
+            name.to_string(),
+            Box::new(move |params| match serde_json::from_value::<P>(params) {
+                Ok(params) => f(params)
+                    .map(|res| {
+                        res.and_then(|res| {
+                            serde_json::to_value(res).map_err(|e| {
+                                RpcError::internal_error().data(e.to_string())
+                            })
+                        })
+                    })
+                    .boxed_local(),
+                Err(e) => {
+                    let err = RpcError::invalid_params().data(e.to_string());
+                    async move { Err(err) }.boxed_local()
+                }
+            }),
+        );
+        self
+    }
+}
+
+struct Inner {
+    limit: usize,
+    methods: HashMap<String, Method>,
+}
+
+impl<Err: ErrorRenderer> WebServiceFactory<Err> for JsonRpc {
+    fn register(self, config: &mut WebServiceConfig<Err>) {
+        let inner = Rc::new(Inner {
+            limit: self.limit,
+            methods: self.methods,
+        });
+
+        resource(&self.path)
+            .route(post().to(move |pl: Payload| handle(inner.clone(), pl)))
+            .register(config);
+    }
+}
+
+async fn handle(inner: Rc<Inner>, mut pl: Payload) -> Response {
+    let mut body = BytesMut::new();
+    while let Some(item) = pl.next().await {
+        match item {
+            Ok(chunk) => {
+                if body.len() + chunk.len() > inner.limit {
+                    return Response::PayloadTooLarge().finish();
+                }
+                body.extend_from_slice(&chunk);
+            }
+            Err(_) => return Response::BadRequest().finish(),
+        }
+    }
+
+    let res = match serde_json::from_slice::<Value>(&body) {
+        Ok(Value::Array(items)) => {
+            if items.is_empty() {
+                error_object(Value::Null, RpcError::invalid_request())
+            } else {
+                let items: Vec<_> =
+                    join_all(items.into_iter().map(|item| call(&inner, item)))
+                        .await
+                        .into_iter()
+                        .flatten()
+                        .collect();
+                if items.is_empty() {
+                    return Response::NoContent().finish();
+                }
+                Value::Array(items)
+            }
+        }
+        Ok(item) => match call(&inner, item).await {
+            Some(res) => res,
+            None => return Response::NoContent().finish(),
+        },
+        Err(_) => error_object(Value::Null, RpcError::parse_error()),
+    };
+
+    Response::Ok()
+        .header(CONTENT_TYPE, "application/json")
+        .body(res.to_string())
+}
+
+/// Execute single request, returns `None` for notifications
+fn call(inner: &Inner, item: Value) -> LocalBoxFuture<'static, Option<Value>> {
+    let (id, method, params) = match parse_request(item) {
+        Ok(req) => req,
+        Err(id) => {
+            let res = error_object(id, RpcError::invalid_request());
+            return async move { Some(res) }.boxed_local();
+        }
+    };
+
+    let fut = match inner.methods.get(&method) {
+        Some(f) => f(params),
+        None => async { Err(RpcError::method_not_found()) }.boxed_local(),
+    };
+    async move {
+        let res = fut.await;
+        id.map(|id| match res {
+            Ok(result) => json!({"jsonrpc": "2.0", "result": result, "id": id}),
+            Err(err) => error_object(id, err),
+        })
+    }
+    .boxed_local()
+}
+
+/// Validate request object, returns `None` id for notifications.
+///
+/// On error returns id to use in error response.
+fn parse_request(item: Value) -> Result<(Option<Value>, String, Value), Value> {
+    let mut obj = match item {
+        Value::Object(obj) => obj,
+        _ => return Err(Value::Null),
+    };
+
+    let id = match obj.remove("id") {
+        None => None,
+        Some(id @ Value::Null)
+        | Some(id @ Value::String(_))
+        | Some(id @ Value::Number(_)) => Some(id),
+        Some(_) => return Err(Value::Null),
+    };
+    let err_id = || id.clone().unwrap_or(Value::Null);
+
+    if obj.get("jsonrpc").and_then(Value::as_str) != Some("2.0") {
+        return Err(err_id());
+    }
+    let method = match obj.remove("method") {
+        Some(Value::String(method)) => method,
+        _ => return Err(err_id()),
+    };
+    let params = match obj.remove("params") {
+        None => Value::Null,
+        Some(params @ Value::Array(_)) | Some(params @ Value::Object(_)) => params,
+        Some(_) => return Err(err_id()),
+    };
+    Ok((id, method, params))
+}
+
+fn error_object(id: Value, err: RpcError) -> Value {
+    let mut obj = Map::new();
+    obj.insert("jsonrpc".to_string(), Value::from("2.0"));
+    obj.insert("error".to_string(), serde_json::to_value(err).unwrap());
+    obj.insert("id".to_string(), id);
+    Value::Object(obj)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::Deserialize;
+
+    use super::*;
+    use crate::http::StatusCode;
+    use crate::web::test::{self, TestRequest};
+    use crate::web::App;
+
+    #[derive(Deserialize)]
+    struct Greet {
+        name: String,
+    }
+
+    async fn subtract(params: (i64, i64)) -> Result<i64, RpcError> {
+        Ok(params.0 - params.1)
+    }
+
+    async fn greet(params: Greet) -> Result<String, RpcError> {
+        Ok(format!("Hello, {}", params.name))
+    }
+
+    async fn fail(_: ()) -> Result<(), RpcError> {
+        Err(RpcError::new(-32000, "Server error").data("details"))
+    }
+
+    #[ntex_rt::test]
+    async fn test_jsonrpc() {
+        let srv = test::init_service(
+            App::new().service(
+                JsonRpc::new("/rpc")
+                    .method("subtract", subtract)
+                    .method("greet", greet)
+                    .method("fail", fail),
+            ),
+        )
+        .await;
+
+        let call = |body: &'static str| {
+            TestRequest::post()
+                .uri("/rpc")
+                .header(CONTENT_TYPE, "application/json")
+                .set_payload(body)
+                .to_request()
+        };
+
+        let res: Value = test::read_response_json(
+            &srv,
+            call(r#"{"jsonrpc": "2.0", "method": "subtract", "params": [42, 23], "id": 1}"#),
+        )
+        .await;
+        assert_eq!(res, json!({"jsonrpc": "2.0", "result": 19, "id": 1}));
+
+        let res: Value = test::read_response_json(
+            &srv,
+            call(r#"{"jsonrpc": "2.0", "method": "greet", "params": {"name": "ntex"}, "id": "a"}"#),
+        )
+        .await;
+        assert_eq!(
+            res,
+            json!({"jsonrpc": "2.0", "result": "Hello, ntex", "id": "a"})
+        );
+
+        let res: Value = test::read_response_json(
+            &srv,
+            call(r#"{"jsonrpc": "2.0", "method": "fail", "id": 2}"#),
+        )
+        .await;
+        assert_eq!(
+            res,
+            json!({"jsonrpc": "2.0", "error": {"code": -32000, "message": "Server error", "data": "details"}, "id": 2})
+        );
+
+        let res: Value = test::read_response_json(
+            &srv,
+            call(r#"{"jsonrpc": "2.0", "method": "subtract", "params": {"a": 1}, "id": 3}"#),
+        )
+        .await;
+        assert_eq!(res["error"]["code"], -32602);
+        assert_eq!(res["id"], 3);
+
+        let res: Value = test::read_response_json(
+            &srv,
+            call(r#"{"jsonrpc": "2.0", "method": "foobar", "id": "1"}"#),
+        )
+        .await;
+        assert_eq!(
+            res,
+            json!({"jsonrpc": "2.0", "error": {"code": -32601, "message": "Method not found"}, "id": "1"})
+        );
+
+        let res: Value = test::read_response_json(
+            &srv,
+            call(r#"{"jsonrpc": "2.0", "method": "foobar, "params": "bar", "baz]"#),
+        )
+        .await;
+        assert_eq!(
+            res,
+            json!({"jsonrpc": "2.0", "error": {"code": -32700, "message": "Parse error"}, "id": null})
+        );
+
+        let res: Value = test::read_response_json(
+            &srv,
+            call(r#"{"jsonrpc": "2.0", "method": 1, "params": "bar"}"#),
+        )
+        .await;
+        assert_eq!(
+            res,
+            json!({"jsonrpc": "2.0", "error": {"code": -32600, "message": "Invalid Request"}, "id": null})
+        );
+
+        let res: Value = test::read_response_json(&srv, call("[]")).await;
+        assert_eq!(res["error"]["code"], -32600);
+
+        let res = test::call_service(
+            &srv,
+            call(r#"{"jsonrpc": "2.0", "method": "subtract", "params": [1, 2]}"#),
+        )
+        .await;
+        assert_eq!(res.status(), StatusCode::NO_CONTENT);
+    }
+
+    #[ntex_rt::test]
+    async fn test_jsonrpc_batch() {
+        let srv = test::init_service(
+            App::new().service(JsonRpc::new("/rpc").method("subtract", subtract)),
+        )
+        .await;
+
+        let req = TestRequest::post()
+            .uri("/rpc")
+            .set_payload(
+                r#"[
+                {"jsonrpc": "2.0", "method": "subtract", "params": [42, 23], "id": "1"},
+                {"jsonrpc": "2.0", "method": "subtract", "params": [1, 2]},
+                {"foo": "boo"},
+                {"jsonrpc": "2.0", "method": "get_data", "id": "9"},
+                1
+            ]"#,
+            )
+            .to_request();
+        let res: Value = test::read_response_json(&srv, req).await;
+        assert_eq!(
+            res,
+            json!([
+                {"jsonrpc": "2.0", "result": 19, "id": "1"},
+                {"jsonrpc": "2.0", "error": {"code": -32600, "message": "Invalid Request"}, "id": null},
+                {"jsonrpc": "2.0", "error": {"code": -32601, "message": "Method not found"}, "id": "9"},
+                {"jsonrpc": "2.0", "error": {"code": -32600, "message": "Invalid Request"}, "id": null},
+            ])
+        );
+
+        let req = TestRequest::post()
+            .uri("/rpc")
+            .set_payload(
+                r#"[{"jsonrpc": "2.0", "method": "subtract", "params": [1, 2]}]"#,
+            )
+            .to_request();
+        let res = test::call_service(&srv, req).await;
+        assert_eq!(res.status(), StatusCode::NO_CONTENT);
+    }
+}
@@ -0,0 +1,263 @@
+//! Middleware for http authentication
+use std::future::Future;
+use std::marker::PhantomData;
+use std::rc::Rc;
+use std::task::{Context, Poll};
+
+use futures::future::{ok, Either, FutureExt, LocalBoxFuture, Ready};
+
+use crate::service::{Service, Transform};
+use crate::web::dev::{WebRequest, WebResponse};
+use crate::web::error::{AuthError, ErrorRenderer};
+use crate::web::types::auth::{challenge, credentials};
+use crate::web::types::{AuthCredentials, BasicAuth, BearerAuth};
+
+/// `Middleware` for http authentication.
+///
+/// Middleware extracts credentials from `Authorization` header and passes
+/// them to async validator. Request is passed to inner service if
+/// validator returns `true`, otherwise `AuthError` is rendered with
+/// authentication challenge. Validator error is rendered as is. Handlers
+/// could use `BasicAuth` or `BearerAuth` extractors to access credentials.
+///
+/// ```rust
+/// use ntex::web::{self, middleware, types::BasicAuth, App, HttpResponse};
+///
+/// fn main() {
+///     let app = App::new()
+///         .wrap(
+///             middleware::HttpAuthentication::basic(|auth: BasicAuth| async move {
+///                 Ok::<_, web::Error>(auth.password() == Some("secret"))
+///             })
+///             .realm("admin"),
+///         )
+///         .service(web::resource("/").to(|| async { HttpResponse::Ok() }));
+/// }
+/// ```
+pub struct HttpAuthentication<T, F, Err> {
+    inner: Rc<Inner<F>>,
+    _t: PhantomData<(T, Err)>,
+}
+
+struct Inner<F> {
+    validator: F,
+    realm: String,
+}
+
+impl<F, Err> HttpAuthentication<BasicAuth, F, Err> {
+    /// Construct middleware for *Basic* authentication scheme
+    pub fn basic(validator: F) -> Self {
+        Self::new(validator)
+    }
+}
+
+impl<F, Err> HttpAuthentication<BearerAuth, F, Err> {
+    /// Construct middleware for *Bearer* authentication scheme
+    pub fn bearer(validator: F) -> Self {
+        Self::new(validator)
+    }
+}
+
+impl<T, F, Err> HttpAuthentication<T, F, Err> {
+    /// Construct middleware for authentication scheme `T`
+    pub fn new(validator: F) -> Self {
+        HttpAuthentication {
+            inner: Rc::new(Inner {
+                validator,
+                realm: "Restricted".to_string(),
+            }),
+            _t: PhantomData,
+        }
+    }
+
+    /// Set authentication realm.
+    ///
+    /// By default `Restricted` realm is used.
+    pub fn realm<R: Into<String>>(mut self, realm: R) -> Self {
+        Rc::get_mut(&mut self.inner)
+            .expect("Multiple copies exist")
+            .realm = realm.into();
+        self
+    }
+}
+
+impl<S, B, T, F, Fut, E, Err> Transform<S> for HttpAuthentication<T, F, Err>
+where
+    S: Service<Request = WebRequest<Err>, Response = WebResponse<B>> + 'static,
+    T: AuthCredentials + 'static,
+    F: Fn(T) -> Fut + 'static,
+    Fut: Future<Output = Result<bool, E>> + 'static,
+    E: Into<Err::Container>,
+    Err: ErrorRenderer,
+    AuthError: Into<Err::Container>,
+{
+    type Request = WebRequest<Err>;
+    type Response = WebResponse<B>;
+    type Error = S::Error;
+    type InitError = ();
+    type Transform = HttpAuthenticationMiddleware<S, T, F, Err>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ok(HttpAuthenticationMiddleware {
+            service: Rc::new(service),
+            inner: self.inner.clone(),
+            _t: PhantomData,
+        })
+    }
+}
+
+pub struct HttpAuthenticationMiddleware<S, T, F, Err> {
+    service: Rc<S>,
+    inner: Rc<Inner<F>>,
+    _t: PhantomData<(T, Err)>,
+}
+
+impl<S, B, T, F, Fut, E, Err> Service for HttpAuthenticationMiddleware<S, T, F, Err>
+where
+    S: Service<Request = WebRequest<Err>, Response = WebResponse<B>> + 'static,
+    T: AuthCredentials + 'static,
+    F: Fn(T) -> Fut + 'static,
+    Fut: Future<Output = Result<bool, E>> + 'static,
+    E: Into<Err::Container>,
+    Err: ErrorRenderer,
+    AuthError: Into<Err::Container>,
+{
+    type Request = WebRequest<Err>;
+    type Response = WebResponse<B>;
+    type Error = S::Error;
+    type Future = Either<
+        Ready<Result<Self::Response, Self::Error>>,
+        LocalBoxFuture<'static, Result<Self::Response, Self::Error>>,
+    >;
+
+    #[inline]
+    fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(cx)
+    }
+
+    #[inline]
+    fn poll_shutdown(&self, cx: &mut Context<'_>, is_error: bool) -> Poll<()> {
+        self.service.poll_shutdown(cx, is_error)
+    }
+
+    fn call(&self, req: WebRequest<Err>) -> Self::Future {
+        let creds = match credentials::<T>(req.headers(), &self.inner.realm) {
+            Ok(creds) => creds,
+            Err(e) => return Either::Left(ok(req.error_response(e))),
+        };
+
+        let srv = self.service.clone();
+        let inner = self.inner.clone();
+
+        async move {
+            match (inner.validator)(creds).await {
+                Ok(true) => srv.call(req).await,
+                Ok(false) => {
+                    let e = AuthError::Rejected(challenge::<T>(&inner.realm));
+                    Ok(req.error_response(e))
+                }
+                Err(e) => Ok(req.error_response(e)),
+            }
+        }
+        .boxed_local()
+        .right_future()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::header::{self, HeaderValue};
+    use crate::http::StatusCode;
+    use crate::web::test::{call_service, init_service, read_body, TestRequest};
+    use crate::web::{self, App, DefaultError, HttpResponse};
+
+    #[ntex_rt::test]
+    async fn test_basic() {
+        let srv = init_service(
+            App::new()
+                .wrap(
+                    HttpAuthentication::<_, _, DefaultError>::basic(
+                        |auth: BasicAuth| async move {
+                            if auth.user_id() == "error" {
+                                Err(web::error::ErrorBadRequest("error"))
+                            } else {
+                                Ok(auth.password() == Some("secret"))
+                            }
+                        },
+                    )
+                    .realm("admin"),
+                )
+                .service(web::resource("/").to(|auth: BasicAuth| async move {
+                    HttpResponse::Ok().body(auth.user_id().to_string())
+                })),
+        )
+        .await;
+
+        let req = TestRequest::with_header(
+            header::AUTHORIZATION,
+            format!("Basic {}", base64::encode("user:secret")),
+        )
+        .to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(read_body(resp).await, "user");
+
+        let req = TestRequest::with_header(
+            header::AUTHORIZATION,
+            format!("Basic {}", base64::encode("user:wrong")),
+        )
+        .to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(
+            resp.headers().get(header::WWW_AUTHENTICATE).unwrap(),
+            HeaderValue::from_static("Basic realm=\"admin\"")
+        );
+
+        let req = TestRequest::with_header(
+            header::AUTHORIZATION,
+            format!("Basic {}", base64::encode("error:")),
+        )
+        .to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+
+        let req = TestRequest::default().to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(
+            resp.headers().get(header::WWW_AUTHENTICATE).unwrap(),
+            HeaderValue::from_static("Basic realm=\"admin\"")
+        );
+    }
+
+    #[ntex_rt::test]
+    async fn test_bearer() {
+        let srv = init_service(
+            App::new()
+                .wrap(HttpAuthentication::<_, _, DefaultError>::bearer(
+                    |auth: BearerAuth| async move {
+                        Ok::<_, web::Error>(auth.token() == "token")
+                    },
+                ))
+                .service(web::resource("/").to(|| async { HttpResponse::Ok() })),
+        )
+        .await;
+
+        let req =
+            TestRequest::with_header(header::AUTHORIZATION, "Bearer token").to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let req =
+            TestRequest::with_header(header::AUTHORIZATION, "Bearer other").to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+
+        let req = TestRequest::with_header(header::AUTHORIZATION, "Bearer").to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+    }
+}
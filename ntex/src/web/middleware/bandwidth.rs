@@ -0,0 +1,276 @@
+//! Middleware for response bandwidth shaping
+use std::cell::RefCell;
+use std::error::Error;
+use std::future::Future;
+use std::marker::PhantomData;
+use std::pin::Pin;
+use std::rc::Rc;
+use std::task::{Context, Poll};
+
+use bytes::Bytes;
+use futures::future::{ok, Ready};
+use futures::ready;
+use pin_project::pin_project;
+
+use crate::http::body::{Body, BodySize, MessageBody, ResponseBody};
+use crate::http::throttle::TokenBucket;
+use crate::rt::time::{Delay, Instant};
+use crate::service::{Service, Transform};
+use crate::web::dev::{WebRequest, WebResponse};
+
+/// `Middleware` for response bandwidth shaping.
+///
+/// Middleware limits rate of response body transfer, in bytes per second.
+/// By default rate is applied to each response separately, with `shared()`
+/// all responses of wrapped service share the same rate. Rate is enforced
+/// per worker. Short bursts up to one second worth of data are allowed.
+///
+/// Middleware is useful for bulk download resources and scopes.
+///
+/// ```rust
+/// use ntex::web::{self, middleware, App, HttpResponse};
+///
+/// fn main() {
+///     let app = App::new().service(
+///         web::scope("/downloads")
+///             .wrap(middleware::Bandwidth::new(1_048_576).shared(true))
+///             .service(web::resource("/file").to(|| async { HttpResponse::Ok() })),
+///     );
+/// }
+/// ```
+pub struct Bandwidth<E> {
+    rate: usize,
+    shared: bool,
+    _t: PhantomData<E>,
+}
+
+impl<E> Bandwidth<E> {
+    /// Construct `Bandwidth` middleware, `rate` is number of bytes per second.
+    ///
+    /// Panics if rate is zero.
+    pub fn new(rate: usize) -> Self {
+        assert!(rate > 0, "Rate must be greater than zero");
+        Bandwidth {
+            rate,
+            shared: false,
+            _t: PhantomData,
+        }
+    }
+
+    /// Share rate between all responses, by default disabled.
+    pub fn shared(mut self, enabled: bool) -> Self {
+        self.shared = enabled;
+        self
+    }
+}
+
+impl<S, B, E> Transform<S> for Bandwidth<E>
+where
+    B: MessageBody + 'static,
+    S: Service<Request = WebRequest<E>, Response = WebResponse<B>>,
+{
+    type Request = WebRequest<E>;
+    type Response = WebResponse;
+    type Error = S::Error;
+    type InitError = ();
+    type Transform = BandwidthMiddleware<S, E>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        let shared = if self.shared {
+            Some(Rc::new(RefCell::new(TokenBucket::new(
+                self.rate,
+                Instant::now(),
+            ))))
+        } else {
+            None
+        };
+
+        ok(BandwidthMiddleware {
+            service,
+            shared,
+            rate: self.rate,
+            _t: PhantomData,
+        })
+    }
+}
+
+pub struct BandwidthMiddleware<S, E> {
+    service: S,
+    rate: usize,
+    shared: Option<Rc<RefCell<TokenBucket>>>,
+    _t: PhantomData<E>,
+}
+
+impl<S, B, E> Service for BandwidthMiddleware<S, E>
+where
+    B: MessageBody + 'static,
+    S: Service<Request = WebRequest<E>, Response = WebResponse<B>>,
+{
+    type Request = WebRequest<E>;
+    type Response = WebResponse;
+    type Error = S::Error;
+    type Future = BandwidthResponse<S, B, E>;
+
+    #[inline]
+    fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(cx)
+    }
+
+    #[inline]
+    fn poll_shutdown(&self, cx: &mut Context<'_>, is_error: bool) -> Poll<()> {
+        self.service.poll_shutdown(cx, is_error)
+    }
+
+    fn call(&self, req: WebRequest<E>) -> Self::Future {
+        BandwidthResponse {
+            fut: self.service.call(req),
+            rate: self.rate,
+            shared: self.shared.clone(),
+            _t: PhantomData,
+        }
+    }
+}
+
+#[doc(hidden)]
+#[pin_project]
+pub struct BandwidthResponse<S: Service, B, E> {
+    #[pin]
+    fut: S::Future,
+    rate: usize,
+    shared: Option<Rc<RefCell<TokenBucket>>>,
+    _t: PhantomData<(B, E)>,
+}
+
+impl<S, B, E> Future for BandwidthResponse<S, B, E>
+where
+    B: MessageBody + 'static,
+    S: Service<Request = WebRequest<E>, Response = WebResponse<B>>,
+{
+    type Output = Result<WebResponse, S::Error>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        let res = ready!(this.fut.poll(cx))?;
+
+        let bucket = match this.shared.take() {
+            Some(bucket) => Bucket::Shared(bucket),
+            None => Bucket::Own(TokenBucket::new(*this.rate, Instant::now())),
+        };
+        Poll::Ready(Ok(res.map_body(move |_, body| {
+            ResponseBody::Other(Body::from_message(ShapedBody {
+                body,
+                bucket,
+                delay: None,
+                chunk: None,
+            }))
+        })))
+    }
+}
+
+enum Bucket {
+    Own(TokenBucket),
+    Shared(Rc<RefCell<TokenBucket>>),
+}
+
+/// Response body with limited transfer rate
+struct ShapedBody<B> {
+    body: ResponseBody<B>,
+    bucket: Bucket,
+    delay: Option<Delay>,
+    chunk: Option<Bytes>,
+}
+
+impl<B: MessageBody> MessageBody for ShapedBody<B> {
+    fn size(&self) -> BodySize {
+        self.body.size()
+    }
+
+    fn poll_next_chunk(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Bytes, Box<dyn Error>>>> {
+        if self.chunk.is_none() {
+            match ready!(self.body.poll_next_chunk(cx)) {
+                Some(Ok(chunk)) if !chunk.is_empty() => self.chunk = Some(chunk),
+                item => return Poll::Ready(item),
+            }
+        }
+
+        let allowed = match self.bucket {
+            Bucket::Own(ref mut bucket) => {
+                ready!(bucket.poll_ready(&mut self.delay, cx))
+            }
+            Bucket::Shared(ref bucket) => {
+                ready!(bucket.borrow_mut().poll_ready(&mut self.delay, cx))
+            }
+        };
+
+        let mut chunk = self.chunk.take().unwrap();
+        if chunk.len() > allowed {
+            let rest = chunk.split_off(allowed);
+            self.chunk = Some(rest);
+        }
+        match self.bucket {
+            Bucket::Own(ref mut bucket) => bucket.consume(chunk.len()),
+            Bucket::Shared(ref bucket) => bucket.borrow_mut().consume(chunk.len()),
+        }
+        Poll::Ready(Some(Ok(chunk)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::http::StatusCode;
+    use crate::web::test::{call_service, init_service, read_body, TestRequest};
+    use crate::web::{self, App, HttpResponse};
+
+    #[ntex_rt::test]
+    async fn test_bandwidth() {
+        let srv = init_service(
+            App::new()
+                .service(
+                    web::resource("/limited")
+                        .wrap(Bandwidth::new(2000))
+                        .to(|| async { HttpResponse::Ok().body(vec![b'x'; 2500]) }),
+                )
+                .service(
+                    web::resource("/shared")
+                        .wrap(Bandwidth::new(2000).shared(true))
+                        .to(|| async { HttpResponse::Ok().body(vec![b'x'; 1500]) }),
+                ),
+        )
+        .await;
+
+        // burst and 500 bytes at 2000 bytes per second
+        let start = Instant::now();
+        let req = TestRequest::with_uri("/limited").to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(read_body(resp).await.len(), 2500);
+        assert!(start.elapsed() >= Duration::from_millis(200));
+
+        // second response for the same resource gets its own burst
+        let start = Instant::now();
+        let req = TestRequest::with_uri("/limited").to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(read_body(resp).await.len(), 2500);
+        assert!(start.elapsed() >= Duration::from_millis(200));
+        assert!(start.elapsed() < Duration::from_millis(600));
+
+        // shared rate, second response pays for first one
+        let start = Instant::now();
+        let req = TestRequest::with_uri("/shared").to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(read_body(resp).await.len(), 1500);
+        assert!(start.elapsed() < Duration::from_millis(100));
+
+        let req = TestRequest::with_uri("/shared").to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(read_body(resp).await.len(), 1500);
+        assert!(start.elapsed() >= Duration::from_millis(450));
+    }
+}
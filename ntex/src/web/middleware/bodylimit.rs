@@ -0,0 +1,302 @@
+//! Middleware for request body size limit
+use std::cell::Cell;
+use std::marker::PhantomData;
+use std::pin::Pin;
+use std::rc::Rc;
+use std::task::{Context, Poll};
+
+use bytes::Bytes;
+use futures::future::{ok, Either, FutureExt, LocalBoxFuture, Ready};
+use futures::Stream;
+
+use crate::http::error::PayloadError;
+use crate::http::header::CONTENT_LENGTH;
+use crate::http::{Payload, PayloadStream, StatusCode};
+use crate::service::{Service, Transform};
+use crate::web::dev::{WebRequest, WebResponse};
+use crate::web::error::default_response;
+use crate::web::ErrorRenderer;
+
+/// `Middleware` for request body size limit.
+///
+/// Middleware limits size of request payload, payload stream fails with
+/// `PayloadError::Overflow` error if request's *Content-Length* or size
+/// of received payload is larger than the limit. Payload is checked when
+/// it gets read, *Content-Length* is checked before any data is received.
+/// If limit is exceeded, response is replaced with *413 Payload Too Large*
+/// response rendered by error renderer.
+///
+/// Limit could be overridden for `Scope` or `Resource` by registering
+/// another `BodyLimit` middleware, the innermost middleware sets the limit.
+/// Extractor's own limits still apply.
+///
+/// ```rust
+/// use ntex::web::{self, middleware, App, HttpResponse};
+///
+/// fn main() {
+///     let app = App::new()
+///         .wrap(middleware::BodyLimit::new(65_536))
+///         .service(
+///             web::scope("/upload")
+///                 .wrap(middleware::BodyLimit::new(16 * 1_048_576))
+///                 .service(web::resource("/file").to(|| async { HttpResponse::Ok() })),
+///         )
+///         .service(web::resource("/test").to(|| async { HttpResponse::Ok() }));
+/// }
+/// ```
+pub struct BodyLimit<E> {
+    limit: usize,
+    _t: PhantomData<E>,
+}
+
+impl<E> BodyLimit<E> {
+    /// Construct `BodyLimit` middleware, `limit` is max payload size
+    /// in bytes.
+    pub fn new(limit: usize) -> Self {
+        BodyLimit {
+            limit,
+            _t: PhantomData,
+        }
+    }
+}
+
+/// Limit shared between payload stream and middlewares, stored in
+/// request extensions
+#[derive(Clone)]
+struct LimitState(Rc<Inner>);
+
+struct Inner {
+    limit: Cell<usize>,
+    exceeded: Cell<bool>,
+}
+
+impl<S, B, E> Transform<S> for BodyLimit<E>
+where
+    S: Service<Request = WebRequest<E>, Response = WebResponse<B>>,
+    S::Future: 'static,
+    B: 'static,
+    E: ErrorRenderer,
+{
+    type Request = WebRequest<E>;
+    type Response = WebResponse<B>;
+    type Error = S::Error;
+    type InitError = ();
+    type Transform = BodyLimitMiddleware<S, E>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ok(BodyLimitMiddleware {
+            service,
+            limit: self.limit,
+            _t: PhantomData,
+        })
+    }
+}
+
+pub struct BodyLimitMiddleware<S, E> {
+    service: S,
+    limit: usize,
+    _t: PhantomData<E>,
+}
+
+impl<S, B, E> Service for BodyLimitMiddleware<S, E>
+where
+    S: Service<Request = WebRequest<E>, Response = WebResponse<B>>,
+    S::Future: 'static,
+    B: 'static,
+    E: ErrorRenderer,
+{
+    type Request = WebRequest<E>;
+    type Response = WebResponse<B>;
+    type Error = S::Error;
+    type Future =
+        Either<S::Future, LocalBoxFuture<'static, Result<WebResponse<B>, S::Error>>>;
+
+    #[inline]
+    fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(cx)
+    }
+
+    #[inline]
+    fn poll_shutdown(&self, cx: &mut Context<'_>, is_error: bool) -> Poll<()> {
+        self.service.poll_shutdown(cx, is_error)
+    }
+
+    fn call(&self, mut req: WebRequest<E>) -> Self::Future {
+        // inner middleware overrides limit
+        let state = req.extensions().get::<LimitState>().cloned();
+        if let Some(state) = state {
+            state.0.limit.set(self.limit);
+            return Either::Left(self.service.call(req));
+        }
+
+        let length = req
+            .headers()
+            .get(CONTENT_LENGTH)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.parse::<u64>().ok());
+        let state = LimitState(Rc::new(Inner {
+            limit: Cell::new(self.limit),
+            exceeded: Cell::new(false),
+        }));
+        req.extensions_mut().insert(state.clone());
+
+        let payload = LimitedPayload {
+            length,
+            size: 0,
+            state: state.clone(),
+            stream: req.take_payload(),
+        };
+        req.set_payload(Payload::Stream(Box::pin(payload)));
+
+        let fut = self.service.call(req);
+        Either::Right(
+            async move {
+                let res = fut.await?;
+                if state.0.exceeded.get()
+                    && res.status() != StatusCode::PAYLOAD_TOO_LARGE
+                {
+                    let r = default_response::<E>(
+                        res.request(),
+                        StatusCode::PAYLOAD_TOO_LARGE,
+                    );
+                    Ok(res.into_response(r.into_body()))
+                } else {
+                    Ok(res)
+                }
+            }
+            .boxed_local(),
+        )
+    }
+}
+
+/// Payload stream with size limit
+struct LimitedPayload {
+    stream: Payload<PayloadStream>,
+    state: LimitState,
+    length: Option<u64>,
+    size: usize,
+}
+
+impl LimitedPayload {
+    fn overflow(&mut self) -> Poll<Option<Result<Bytes, PayloadError>>> {
+        self.state.0.exceeded.set(true);
+        self.stream = Payload::None;
+        Poll::Ready(Some(Err(PayloadError::Overflow)))
+    }
+}
+
+impl Stream for LimitedPayload {
+    type Item = Result<Bytes, PayloadError>;
+
+    fn poll_next(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        if this.state.0.exceeded.get() {
+            return Poll::Ready(None);
+        }
+
+        let limit = this.state.0.limit.get();
+        if let Some(length) = this.length.take() {
+            if length > limit as u64 {
+                log::trace!("Content-Length {} is larger than limit {}", length, limit);
+                return this.overflow();
+            }
+        }
+
+        match Pin::new(&mut this.stream).poll_next(cx) {
+            Poll::Ready(Some(Ok(chunk))) => {
+                this.size += chunk.len();
+                if this.size > limit {
+                    this.overflow()
+                } else {
+                    Poll::Ready(Some(Ok(chunk)))
+                }
+            }
+            item => item,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::header::CONTENT_TYPE;
+    use crate::web::test::{call_service, init_service, read_body, TestRequest};
+    use crate::web::{self, types, App, DefaultError};
+
+    #[ntex_rt::test]
+    async fn test_body_limit() {
+        let srv = init_service(
+            App::new()
+                .wrap(BodyLimit::<DefaultError>::new(10))
+                .service(web::resource("/bytes").to(|body: Bytes| async move { body }))
+                .service(
+                    web::resource("/json").to(
+                        |body: types::Json<Vec<u32>>| async move {
+                            format!("{}", body.len())
+                        },
+                    ),
+                )
+                .service(web::resource("/ignore").to(|| async { "ok" }))
+                .service(web::scope("/large").wrap(BodyLimit::new(100)).service(
+                    web::resource("/bytes").to(|body: Bytes| async move { body }),
+                )),
+        )
+        .await;
+
+        let req = TestRequest::with_uri("/bytes")
+            .set_payload("0123456789")
+            .to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(read_body(resp).await, "0123456789");
+
+        // payload without content-length
+        let req = TestRequest::with_uri("/bytes")
+            .set_payload("0123456789abc")
+            .to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::PAYLOAD_TOO_LARGE);
+
+        // content-length is checked before payload is read
+        let req = TestRequest::with_uri("/bytes")
+            .header(CONTENT_LENGTH, "1000")
+            .set_payload("01")
+            .to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::PAYLOAD_TOO_LARGE);
+
+        // extractor error is replaced with 413 response
+        let req = TestRequest::with_uri("/json")
+            .header(CONTENT_TYPE, "application/json")
+            .set_payload("[1,2,3,4,5,6]")
+            .to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::PAYLOAD_TOO_LARGE);
+
+        // payload is not read
+        let req = TestRequest::with_uri("/ignore")
+            .header(CONTENT_LENGTH, "1000")
+            .set_payload("01")
+            .to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        // scope overrides limit
+        let req = TestRequest::with_uri("/large/bytes")
+            .set_payload("0123456789abc")
+            .to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(read_body(resp).await, "0123456789abc");
+
+        let req = TestRequest::with_uri("/large/bytes")
+            .set_payload(vec![b'x'; 101])
+            .to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::PAYLOAD_TOO_LARGE);
+    }
+}
@@ -0,0 +1,610 @@
+//! Response caching middleware with pluggable storage backends
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap};
+use std::error::Error;
+use std::future::Future;
+use std::marker::PhantomData;
+use std::pin::Pin;
+use std::rc::Rc;
+use std::str::FromStr;
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant, SystemTime};
+
+use bytes::{Bytes, BytesMut};
+use futures::future::{ok, ready, FutureExt, LocalBoxFuture, Ready};
+use futures::Sink;
+
+use crate::http::body::{Body, BodySize, MessageBody, ResponseBody, TeeBody, TeeError};
+use crate::http::header::{self, CacheControl, HeaderMap, HeaderName, HeaderValue};
+use crate::http::{Method, Response, StatusCode};
+use crate::service::{Service, Transform};
+use crate::web::dev::{WebRequest, WebResponse};
+use crate::web::HttpRequest;
+
+/// Cached response
+#[derive(Clone, Debug)]
+pub struct CachedResponse {
+    /// Response status
+    pub status: StatusCode,
+    /// Response headers
+    pub headers: HeaderMap,
+    /// Response body
+    pub body: Bytes,
+    /// Time when response got stored
+    pub created: SystemTime,
+}
+
+/// Response cache storage backend.
+///
+/// Store must not return entries that are older than ttl passed
+/// to `set()` method. Store errors are logged and treated as cache miss.
+pub trait CacheStore: 'static {
+    /// Load cached response for key
+    fn get(
+        &self,
+        key: &str,
+    ) -> LocalBoxFuture<'static, Result<Option<CachedResponse>, Box<dyn Error>>>;
+
+    /// Store response for key
+    fn set(
+        &self,
+        key: String,
+        res: CachedResponse,
+        ttl: Duration,
+    ) -> LocalBoxFuture<'static, Result<(), Box<dyn Error>>>;
+}
+
+/// In-memory cache store with least recently used eviction.
+///
+/// Store keeps up to `capacity` responses. Store is not shared between
+/// workers if it is constructed in application factory.
+pub struct MemoryCacheStore {
+    capacity: usize,
+    inner: RefCell<Lru>,
+}
+
+#[derive(Default)]
+struct Lru {
+    entries: HashMap<String, (CachedResponse, Instant, u64)>,
+    order: BTreeMap<u64, String>,
+    tick: u64,
+}
+
+impl MemoryCacheStore {
+    /// Construct in-memory store with max number of entries
+    pub fn new(capacity: usize) -> Self {
+        MemoryCacheStore {
+            capacity,
+            inner: RefCell::new(Lru::default()),
+        }
+    }
+
+    /// Number of stored entries, including expired entries
+    pub fn len(&self) -> usize {
+        self.inner.borrow().entries.len()
+    }
+
+    /// Check if store is empty
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl CacheStore for MemoryCacheStore {
+    fn get(
+        &self,
+        key: &str,
+    ) -> LocalBoxFuture<'static, Result<Option<CachedResponse>, Box<dyn Error>>> {
+        let mut inner = self.inner.borrow_mut();
+        let inner = &mut *inner;
+        inner.tick += 1;
+
+        let res = match inner.entries.get_mut(key) {
+            Some(entry) if entry.1 > Instant::now() => {
+                inner.order.remove(&entry.2);
+                inner.order.insert(inner.tick, key.to_string());
+                entry.2 = inner.tick;
+                Some(entry.0.clone())
+            }
+            Some(_) => {
+                if let Some((_, _, tick)) = inner.entries.remove(key) {
+                    inner.order.remove(&tick);
+                }
+                None
+            }
+            None => None,
+        };
+        ready(Ok(res)).boxed_local()
+    }
+
+    fn set(
+        &self,
+        key: String,
+        res: CachedResponse,
+        ttl: Duration,
+    ) -> LocalBoxFuture<'static, Result<(), Box<dyn Error>>> {
+        let mut inner = self.inner.borrow_mut();
+        inner.tick += 1;
+
+        let tick = inner.tick;
+        inner.order.insert(tick, key.clone());
+        if let Some((_, _, tick)) =
+            inner.entries.insert(key, (res, Instant::now() + ttl, tick))
+        {
+            inner.order.remove(&tick);
+        }
+        while inner.entries.len() > self.capacity {
+            let oldest = inner.order.keys().next().cloned();
+            if let Some(key) = oldest.and_then(|tick| inner.order.remove(&tick)) {
+                inner.entries.remove(&key);
+            } else {
+                break;
+            }
+        }
+        ready(Ok(())).boxed_local()
+    }
+}
+
+/// `Middleware` for response caching.
+///
+/// Middleware caches responses for `GET` requests, responses are keyed by
+/// request path, query and values of configured vary headers. Only
+/// *200 OK* responses are stored. Time to live is taken from `s-maxage`
+/// or `max-age` directives of response's *Cache-Control* header, default
+/// ttl is used otherwise. Responses with `no-store`, `no-cache` or
+/// `private` directives, with *Set-Cookie* header or with *Vary* header
+/// that lists headers which are not configured are not stored.
+/// Request's `no-cache`, `no-store` and `max-age` directives are honored.
+///
+/// Cached responses contain *Age* header.
+///
+/// ```rust
+/// use std::time::Duration;
+/// use ntex::http::header;
+/// use ntex::web::{self, middleware, App, HttpResponse};
+///
+/// fn main() {
+///     let app = App::new().service(
+///         web::resource("/index.html")
+///             .wrap(
+///                 middleware::Cache::new(middleware::MemoryCacheStore::new(1024))
+///                     .ttl(Duration::from_secs(5))
+///                     .vary(header::ACCEPT_LANGUAGE),
+///             )
+///             .to(|| async { HttpResponse::Ok().body("index") }),
+///     );
+/// }
+/// ```
+pub struct Cache<St, Err> {
+    store: Rc<St>,
+    inner: Rc<Inner>,
+    _t: PhantomData<Err>,
+}
+
+struct Inner {
+    ttl: Duration,
+    max_size: usize,
+    vary: Vec<HeaderName>,
+}
+
+impl<St: CacheStore, Err> Cache<St, Err> {
+    /// Construct `Cache` middleware with storage backend
+    pub fn new(store: St) -> Self {
+        Cache {
+            store: Rc::new(store),
+            inner: Rc::new(Inner {
+                ttl: Duration::from_secs(60),
+                max_size: 1_048_576,
+                vary: Vec::new(),
+            }),
+            _t: PhantomData,
+        }
+    }
+
+    /// Set default time to live of cached response.
+    ///
+    /// By default ttl is 60 seconds.
+    pub fn ttl(mut self, ttl: Duration) -> Self {
+        Rc::get_mut(&mut self.inner)
+            .expect("Multiple copies exist")
+            .ttl = ttl;
+        self
+    }
+
+    /// Set max size of cached response body.
+    ///
+    /// Larger responses are not stored. By default max size is 1Mb.
+    pub fn max_size(mut self, size: usize) -> Self {
+        Rc::get_mut(&mut self.inner)
+            .expect("Multiple copies exist")
+            .max_size = size;
+        self
+    }
+
+    /// Add request header to cache key.
+    pub fn vary(mut self, name: HeaderName) -> Self {
+        Rc::get_mut(&mut self.inner)
+            .expect("Multiple copies exist")
+            .vary
+            .push(name);
+        self
+    }
+}
+
+impl Inner {
+    fn key(&self, req: &HttpRequest) -> String {
+        let mut key = req.path().to_string();
+        if !req.query_string().is_empty() {
+            key.push('?');
+            key.push_str(req.query_string());
+        }
+        for name in &self.vary {
+            key.push('\n');
+            key.push_str(name.as_str());
+            key.push(':');
+            for (idx, val) in req.headers().get_all(name).enumerate() {
+                if idx > 0 {
+                    key.push(',');
+                }
+                key.push_str(&String::from_utf8_lossy(val.as_bytes()));
+            }
+        }
+        key
+    }
+
+    /// Time to live of response, `None` if response could not be stored
+    fn ttl<B: MessageBody>(&self, res: &WebResponse<B>) -> Option<Duration> {
+        if res.status() != StatusCode::OK
+            || res.headers().contains_key(header::SET_COOKIE)
+        {
+            return None;
+        }
+        match res.response().body().size() {
+            BodySize::Sized(size) if size > self.max_size => return None,
+            BodySize::Sized64(size) if size > self.max_size as u64 => return None,
+            _ => (),
+        }
+        for val in res.headers().get_all(header::VARY) {
+            let val = val.to_str().ok()?;
+            for name in val.split(',').map(|s| s.trim()) {
+                if !self
+                    .vary
+                    .iter()
+                    .any(|v| v.as_str().eq_ignore_ascii_case(name))
+                {
+                    return None;
+                }
+            }
+        }
+
+        let ttl = if let Some(cc) = cache_control(res.headers()) {
+            if cc.is_no_store() || cc.is_no_cache() || cc.is_private() {
+                return None;
+            }
+            cc.get_s_maxage()
+                .or_else(|| cc.get_max_age())
+                .map(Duration::from_secs)
+                .unwrap_or(self.ttl)
+        } else {
+            self.ttl
+        };
+        if ttl == Duration::from_secs(0) {
+            None
+        } else {
+            Some(ttl)
+        }
+    }
+}
+
+fn cache_control(headers: &HeaderMap) -> Option<CacheControl> {
+    headers
+        .get(header::CACHE_CONTROL)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| CacheControl::from_str(v).ok())
+}
+
+impl<S, B, St, E> Transform<S> for Cache<St, E>
+where
+    S: Service<Request = WebRequest<E>, Response = WebResponse<B>> + 'static,
+    B: MessageBody + 'static,
+    St: CacheStore,
+    E: 'static,
+{
+    type Request = WebRequest<E>;
+    type Response = WebResponse;
+    type Error = S::Error;
+    type InitError = ();
+    type Transform = CacheMiddleware<S, St, E>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ok(CacheMiddleware {
+            service: Rc::new(service),
+            store: self.store.clone(),
+            inner: self.inner.clone(),
+            _t: PhantomData,
+        })
+    }
+}
+
+pub struct CacheMiddleware<S, St, E> {
+    service: Rc<S>,
+    store: Rc<St>,
+    inner: Rc<Inner>,
+    _t: PhantomData<E>,
+}
+
+impl<S, B, St, E> Service for CacheMiddleware<S, St, E>
+where
+    S: Service<Request = WebRequest<E>, Response = WebResponse<B>> + 'static,
+    B: MessageBody + 'static,
+    St: CacheStore,
+    E: 'static,
+{
+    type Request = WebRequest<E>;
+    type Response = WebResponse;
+    type Error = S::Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    #[inline]
+    fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(cx)
+    }
+
+    #[inline]
+    fn poll_shutdown(&self, cx: &mut Context<'_>, is_error: bool) -> Poll<()> {
+        self.service.poll_shutdown(cx, is_error)
+    }
+
+    fn call(&self, req: WebRequest<E>) -> Self::Future {
+        let cc = cache_control(req.headers()).unwrap_or_default();
+        if req.method() != Method::GET || cc.is_no_store() {
+            let fut = self.service.call(req);
+            return async move {
+                Ok(fut
+                    .await?
+                    .map_body(|_, body| ResponseBody::Other(Body::from_message(body))))
+            }
+            .boxed_local();
+        }
+
+        let srv = self.service.clone();
+        let store = self.store.clone();
+        let inner = self.inner.clone();
+        let key = inner.key(req.request());
+        let load = if cc.is_no_cache() {
+            None
+        } else {
+            Some(store.get(&key))
+        };
+
+        async move {
+            let cached = match load {
+                Some(fut) => fut.await.unwrap_or_else(|e| {
+                    log::debug!("Cannot load cached response: {}", e);
+                    None
+                }),
+                None => None,
+            };
+            if let Some(cached) = cached {
+                let age = cached.created.elapsed().unwrap_or_default().as_secs();
+                if cc.get_max_age().map(|max| age <= max).unwrap_or(true) {
+                    let mut res = Response::with_body(cached.status, Body::Empty);
+                    *res.headers_mut() = cached.headers;
+                    res.headers_mut()
+                        .insert(header::AGE, HeaderValue::from(age));
+                    return Ok(req.into_response(res.set_body(Body::Bytes(cached.body))));
+                }
+            }
+
+            let res = srv.call(req).await?;
+            if let Some(ttl) = inner.ttl(&res) {
+                let sink = CacheSink {
+                    key: Some(key),
+                    ttl,
+                    store,
+                    status: res.status(),
+                    headers: res.headers().clone(),
+                    body: BytesMut::new(),
+                    fut: None,
+                };
+                let max_size = inner.max_size;
+                Ok(res.map_body(move |_, body| {
+                    ResponseBody::Other(Body::from_message(TeeBody::new(
+                        body, sink, max_size,
+                    )))
+                }))
+            } else {
+                Ok(
+                    res.map_body(|_, body| {
+                        ResponseBody::Other(Body::from_message(body))
+                    }),
+                )
+            }
+        }
+        .boxed_local()
+    }
+}
+
+/// Collects response body and stores response on close
+struct CacheSink<St> {
+    key: Option<String>,
+    ttl: Duration,
+    store: Rc<St>,
+    status: StatusCode,
+    headers: HeaderMap,
+    body: BytesMut,
+    fut: Option<LocalBoxFuture<'static, Result<(), Box<dyn Error>>>>,
+}
+
+impl<St> Unpin for CacheSink<St> {}
+
+impl<St: CacheStore> Sink<Result<Bytes, TeeError>> for CacheSink<St> {
+    type Error = TeeError;
+
+    fn poll_ready(
+        self: Pin<&mut Self>,
+        _: &mut Context<'_>,
+    ) -> Poll<Result<(), TeeError>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn start_send(
+        mut self: Pin<&mut Self>,
+        item: Result<Bytes, TeeError>,
+    ) -> Result<(), TeeError> {
+        let chunk = item?;
+        self.body.extend_from_slice(&chunk);
+        Ok(())
+    }
+
+    fn poll_flush(
+        self: Pin<&mut Self>,
+        _: &mut Context<'_>,
+    ) -> Poll<Result<(), TeeError>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_close(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<(), TeeError>> {
+        let this = &mut *self;
+        if let Some(key) = this.key.take() {
+            let res = CachedResponse {
+                status: this.status,
+                headers: std::mem::replace(&mut this.headers, HeaderMap::new()),
+                body: std::mem::replace(&mut this.body, BytesMut::new()).freeze(),
+                created: SystemTime::now(),
+            };
+            this.fut = Some(this.store.set(key, res, this.ttl));
+        }
+        if let Some(ref mut fut) = this.fut {
+            if let Err(e) = futures::ready!(Pin::new(fut).poll(cx)) {
+                log::debug!("Cannot store response: {}", e);
+            }
+            this.fut = None;
+        }
+        Poll::Ready(Ok(()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+
+    use super::*;
+    use crate::http::header::{ACCEPT_LANGUAGE, AGE, CACHE_CONTROL, VARY};
+    use crate::web::test::{call_service, init_service, read_body, TestRequest};
+    use crate::web::{self, App, DefaultError, HttpResponse};
+
+    fn cached(body: &'static str) -> CachedResponse {
+        CachedResponse {
+            status: StatusCode::OK,
+            headers: HeaderMap::new(),
+            body: Bytes::from_static(body.as_bytes()),
+            created: SystemTime::now(),
+        }
+    }
+
+    #[ntex_rt::test]
+    async fn test_memory_store() {
+        let store = MemoryCacheStore::new(2);
+        let ttl = Duration::from_secs(60);
+        store.set("a".to_string(), cached("a"), ttl).await.unwrap();
+        store.set("b".to_string(), cached("b"), ttl).await.unwrap();
+        assert!(store.get("a").await.unwrap().is_some());
+
+        // "b" is least recently used
+        store.set("c".to_string(), cached("c"), ttl).await.unwrap();
+        assert_eq!(store.len(), 2);
+        assert!(store.get("b").await.unwrap().is_none());
+        assert_eq!(store.get("a").await.unwrap().unwrap().body, "a");
+        assert_eq!(store.get("c").await.unwrap().unwrap().body, "c");
+
+        store
+            .set("a".to_string(), cached("a"), Duration::from_millis(10))
+            .await
+            .unwrap();
+        crate::rt::time::delay_for(Duration::from_millis(20)).await;
+        assert!(store.get("a").await.unwrap().is_none());
+        assert_eq!(store.len(), 1);
+    }
+
+    #[ntex_rt::test]
+    async fn test_cache() {
+        let counter = Rc::new(Cell::new(0));
+        let c = counter.clone();
+        let srv = init_service(
+            App::new()
+                .wrap(
+                    Cache::<_, DefaultError>::new(MemoryCacheStore::new(16))
+                        .vary(ACCEPT_LANGUAGE),
+                )
+                .service(web::resource("/").to(move || {
+                    c.set(c.get() + 1);
+                    let count = c.get();
+                    async move {
+                        HttpResponse::Ok()
+                            .header(VARY, "accept-language")
+                            .body(format!("{}", count))
+                    }
+                }))
+                .service(web::resource("/no-store").to(|| async {
+                    HttpResponse::Ok()
+                        .header(CACHE_CONTROL, "no-store")
+                        .body("no-store")
+                }))
+                .service(web::resource("/vary").to(|| async {
+                    HttpResponse::Ok().header(VARY, "accept").body("vary")
+                })),
+        )
+        .await;
+
+        let resp = call_service(&srv, TestRequest::default().to_request()).await;
+        assert!(!resp.headers().contains_key(AGE));
+        assert_eq!(read_body(resp).await, "1");
+
+        let resp = call_service(&srv, TestRequest::default().to_request()).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(resp.headers().get(AGE).unwrap(), "0");
+        assert_eq!(resp.headers().get(VARY).unwrap(), "accept-language");
+        assert_eq!(read_body(resp).await, "1");
+        assert_eq!(counter.get(), 1);
+
+        // vary header is part of the key
+        let req = TestRequest::default()
+            .header(ACCEPT_LANGUAGE, "en")
+            .to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(read_body(resp).await, "2");
+
+        // query is part of the key
+        let req = TestRequest::with_uri("/?q=1").to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(read_body(resp).await, "3");
+
+        // request no-cache directive
+        let req = TestRequest::default()
+            .header(CACHE_CONTROL, "no-cache")
+            .to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(read_body(resp).await, "4");
+
+        // only GET responses are cached
+        let req = TestRequest::default().method(Method::POST).to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(read_body(resp).await, "5");
+        let resp = call_service(&srv, TestRequest::default().to_request()).await;
+        assert_eq!(read_body(resp).await, "4");
+
+        for path in &["/no-store", "/vary"] {
+            let req = TestRequest::with_uri(path).to_request();
+            let resp = call_service(&srv, req).await;
+            read_body(resp).await;
+            let req = TestRequest::with_uri(path).to_request();
+            let resp = call_service(&srv, req).await;
+            assert!(!resp.headers().contains_key(AGE));
+        }
+    }
+}
@@ -0,0 +1,312 @@
+//! Middleware for canary routing
+use std::marker::PhantomData;
+use std::rc::Rc;
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+use futures::future::{Either, FutureExt, LocalBoxFuture};
+
+use crate::http::header::HeaderName;
+#[cfg(feature = "cookie")]
+use crate::http::HttpMessage;
+use crate::service::{IntoServiceFactory, Service, ServiceFactory, Transform};
+use crate::web::dev::{WebRequest, WebResponse};
+
+/// Canary traffic weight.
+///
+/// Weight is a percentage of requests routed to canary service, from `0`
+/// to `100`. Weight could be cloned and shared between workers, for example
+/// it could be registered as application data and adjusted from a handler.
+#[derive(Clone, Debug, Default)]
+pub struct CanaryWeight(Arc<AtomicU8>);
+
+impl CanaryWeight {
+    /// Create new weight, values above `100` are treated as `100`.
+    pub fn new(percent: u8) -> Self {
+        CanaryWeight(Arc::new(AtomicU8::new(std::cmp::min(percent, 100))))
+    }
+
+    /// Change weight, values above `100` are treated as `100`.
+    pub fn set(&self, percent: u8) {
+        self.0.store(std::cmp::min(percent, 100), Ordering::Release);
+    }
+
+    /// Current weight.
+    pub fn get(&self) -> u8 {
+        self.0.load(Ordering::Acquire)
+    }
+}
+
+/// `Middleware` for canary routing.
+///
+/// Middleware routes weighted percentage of requests to alternate (canary)
+/// service, all other requests are handled by inner service. It could be
+/// used for progressive rollouts of new version of a service within
+/// single process.
+///
+/// By default every request is routed randomly. With sticky key, requests
+/// with the same header or cookie value are always routed to the same
+/// service. Sticky routing is stable while weight grows, clients routed to
+/// canary service stay there. Requests without sticky key are routed
+/// randomly.
+///
+/// ```rust
+/// use ntex::fn_service;
+/// use ntex::web::{self, dev::WebRequest, middleware, App, HttpResponse};
+///
+/// fn main() {
+///     let weight = middleware::CanaryWeight::new(10);
+///
+///     let app = App::new()
+///         .data(weight.clone())
+///         .wrap(
+///             middleware::Canary::new(
+///                 weight,
+///                 fn_service(|req: WebRequest<web::DefaultError>| async move {
+///                     Ok(req.into_response(HttpResponse::Ok().body("canary")))
+///                 }),
+///             )
+///             .sticky_header("x-user-id"),
+///         )
+///         .service(web::resource("/").to(|| async { HttpResponse::Ok() }));
+/// }
+/// ```
+pub struct Canary<T, E> {
+    factory: Rc<T>,
+    inner: Rc<Inner>,
+    _t: PhantomData<E>,
+}
+
+struct Inner {
+    weight: CanaryWeight,
+    sticky: Option<Sticky>,
+}
+
+enum Sticky {
+    Header(HeaderName),
+    #[cfg(feature = "cookie")]
+    Cookie(String),
+}
+
+impl<T, E> Canary<T, E>
+where
+    T: ServiceFactory<Config = (), Request = WebRequest<E>>,
+{
+    /// Construct `Canary` middleware with canary service factory.
+    pub fn new<F>(weight: CanaryWeight, service: F) -> Self
+    where
+        F: IntoServiceFactory<T>,
+    {
+        Canary {
+            factory: Rc::new(service.into_factory()),
+            inner: Rc::new(Inner {
+                weight,
+                sticky: None,
+            }),
+            _t: PhantomData,
+        }
+    }
+
+    /// Route requests by value of specified header.
+    pub fn sticky_header(mut self, name: &str) -> Self {
+        match HeaderName::from_bytes(name.as_bytes()) {
+            Ok(name) => {
+                Rc::get_mut(&mut self.inner)
+                    .expect("Multiple copies exist")
+                    .sticky = Some(Sticky::Header(name))
+            }
+            Err(_) => panic!("Can not create header name"),
+        }
+        self
+    }
+
+    #[cfg(feature = "cookie")]
+    /// Route requests by value of specified cookie.
+    pub fn sticky_cookie(mut self, name: &str) -> Self {
+        Rc::get_mut(&mut self.inner)
+            .expect("Multiple copies exist")
+            .sticky = Some(Sticky::Cookie(name.to_string()));
+        self
+    }
+}
+
+impl Inner {
+    /// Check if request must be routed to canary service
+    fn is_canary<E>(&self, req: &WebRequest<E>) -> bool {
+        let weight = u64::from(self.weight.get());
+        if weight == 0 {
+            return false;
+        } else if weight >= 100 {
+            return true;
+        }
+
+        let bucket = match self.sticky {
+            Some(Sticky::Header(ref name)) => req
+                .headers()
+                .get(name)
+                .map(|val| fxhash::hash64(val.as_bytes())),
+            #[cfg(feature = "cookie")]
+            Some(Sticky::Cookie(ref name)) => {
+                req.cookie(name).map(|c| fxhash::hash64(c.value()))
+            }
+            None => None,
+        }
+        .unwrap_or_else(rand::random::<u64>);
+        bucket % 100 < weight
+    }
+}
+
+impl<S, T, B, E> Transform<S> for Canary<T, E>
+where
+    S: Service<Request = WebRequest<E>, Response = WebResponse<B>> + 'static,
+    T: ServiceFactory<
+            Config = (),
+            Request = WebRequest<E>,
+            Response = WebResponse<B>,
+            Error = S::Error,
+        > + 'static,
+    T::Service: 'static,
+    E: 'static,
+{
+    type Request = WebRequest<E>;
+    type Response = WebResponse<B>;
+    type Error = S::Error;
+    type InitError = ();
+    type Transform = CanaryMiddleware<S, T::Service, E>;
+    type Future = LocalBoxFuture<'static, Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        let fut = self.factory.new_service(());
+        let inner = self.inner.clone();
+
+        async move {
+            match fut.await {
+                Ok(canary) => Ok(CanaryMiddleware {
+                    service,
+                    canary,
+                    inner,
+                    _t: PhantomData,
+                }),
+                Err(_) => {
+                    log::error!("Can not construct canary service");
+                    Err(())
+                }
+            }
+        }
+        .boxed_local()
+    }
+}
+
+pub struct CanaryMiddleware<S, C, E> {
+    service: S,
+    canary: C,
+    inner: Rc<Inner>,
+    _t: PhantomData<E>,
+}
+
+impl<S, C, B, E> Service for CanaryMiddleware<S, C, E>
+where
+    S: Service<Request = WebRequest<E>, Response = WebResponse<B>>,
+    C: Service<Request = WebRequest<E>, Response = WebResponse<B>, Error = S::Error>,
+{
+    type Request = WebRequest<E>;
+    type Response = WebResponse<B>;
+    type Error = S::Error;
+    type Future = Either<S::Future, C::Future>;
+
+    #[inline]
+    fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        let ready1 = self.service.poll_ready(cx)?.is_ready();
+        let ready2 = self.canary.poll_ready(cx)?.is_ready();
+        if ready1 && ready2 {
+            Poll::Ready(Ok(()))
+        } else {
+            Poll::Pending
+        }
+    }
+
+    #[inline]
+    fn poll_shutdown(&self, cx: &mut Context<'_>, is_error: bool) -> Poll<()> {
+        let ready1 = self.service.poll_shutdown(cx, is_error).is_ready();
+        let ready2 = self.canary.poll_shutdown(cx, is_error).is_ready();
+        if ready1 && ready2 {
+            Poll::Ready(())
+        } else {
+            Poll::Pending
+        }
+    }
+
+    fn call(&self, req: WebRequest<E>) -> Self::Future {
+        if self.inner.is_canary(&req) {
+            Either::Right(self.canary.call(req))
+        } else {
+            Either::Left(self.service.call(req))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+
+    use super::*;
+    use crate::fn_service;
+    use crate::web::test::{call_service, init_service, read_body, TestRequest};
+    use crate::web::{self, App, DefaultError, HttpResponse};
+
+    #[ntex_rt::test]
+    async fn test_canary() {
+        let weight = CanaryWeight::new(0);
+        let srv = init_service(
+            App::new()
+                .wrap(
+                    Canary::new(
+                        weight.clone(),
+                        fn_service(|req: WebRequest<DefaultError>| async move {
+                            Ok(req.into_response(HttpResponse::Ok().body("canary")))
+                        }),
+                    )
+                    .sticky_header("x-user"),
+                )
+                .service(
+                    web::resource("/").to(|| async { HttpResponse::Ok().body("main") }),
+                ),
+        )
+        .await;
+
+        let body = |user: &'static str| {
+            let req = TestRequest::default().header("x-user", user).to_request();
+            let srv = &srv;
+            async move { read_body(call_service(srv, req).await).await }
+        };
+
+        assert_eq!(body("1").await, Bytes::from_static(b"main"));
+
+        weight.set(200);
+        assert_eq!(weight.get(), 100);
+        assert_eq!(body("1").await, Bytes::from_static(b"canary"));
+
+        // sticky routing
+        weight.set(50);
+        let users = [
+            "1", "2", "3", "4", "5", "6", "7", "8", "9", "10", "11", "12",
+        ];
+        let mut canary = Vec::new();
+        for user in users.iter() {
+            let res = body(user).await;
+            assert_eq!(res, body(user).await);
+            canary.push(res == Bytes::from_static(b"canary"));
+        }
+        assert!(canary.iter().any(|c| *c));
+        assert!(canary.iter().any(|c| !*c));
+
+        // canary clients stay on canary while weight grows
+        weight.set(80);
+        for (user, c) in users.iter().zip(canary.iter()) {
+            if *c {
+                assert_eq!(body(user).await, Bytes::from_static(b"canary"));
+            }
+        }
+    }
+}
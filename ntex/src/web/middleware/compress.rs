@@ -9,7 +9,9 @@ use std::task::{Context, Poll};
 use futures::future::{ok, Ready};
 use pin_project::pin_project;
 
-use crate::http::body::MessageBody;
+use crate::http::body::{BodySize, MessageBody};
+#[cfg(feature = "dictionary")]
+use crate::http::encoding::dictionary::Dictionary;
 use crate::http::encoding::Encoder;
 use crate::http::header::{ContentEncoding, ACCEPT_ENCODING};
 use crate::service::{Service, Transform};
@@ -17,6 +19,8 @@ use crate::service::{Service, Transform};
 use crate::web::dev::{WebRequest, WebResponse};
 use crate::web::{BodyEncoding, ErrorRenderer};
 
+use super::MediaTypePredicate;
+
 #[derive(Debug, Clone)]
 /// `Middleware` for compressing response body.
 ///
@@ -38,6 +42,10 @@ use crate::web::{BodyEncoding, ErrorRenderer};
 /// ```
 pub struct Compress<Err> {
     enc: ContentEncoding,
+    types: MediaTypePredicate,
+    min_size: usize,
+    #[cfg(feature = "dictionary")]
+    dictionaries: Vec<Dictionary>,
     _t: PhantomData<Err>,
 }
 
@@ -46,9 +54,42 @@ impl<Err> Compress<Err> {
     pub fn new(encoding: ContentEncoding) -> Self {
         Compress {
             enc: encoding,
+            types: MediaTypePredicate::any(),
+            min_size: 0,
+            #[cfg(feature = "dictionary")]
+            dictionaries: Vec::new(),
             _t: PhantomData,
         }
     }
+
+    /// Set media types of responses that get compressed.
+    ///
+    /// By default all responses are compressed.
+    pub fn media_types(mut self, types: MediaTypePredicate) -> Self {
+        self.types = types;
+        self
+    }
+
+    /// Set minimal size of response body that gets compressed.
+    ///
+    /// Responses with known body size below this limit are sent
+    /// uncompressed, streaming responses are always compressed.
+    /// By default limit is 0.
+    pub fn min_size(mut self, size: usize) -> Self {
+        self.min_size = size;
+        self
+    }
+
+    #[cfg(feature = "dictionary")]
+    /// Register shared compression dictionary.
+    ///
+    /// If request advertises registered dictionary with `Available-Dictionary`
+    /// header and accepts `dcb` encoding, response is compressed with this
+    /// dictionary.
+    pub fn dictionary(mut self, dict: Dictionary) -> Self {
+        self.dictionaries.push(dict);
+        self
+    }
 }
 
 impl<Err> Default for Compress<Err> {
@@ -74,6 +115,10 @@ where
         ok(CompressMiddleware {
             service,
             encoding: self.enc,
+            types: self.types.clone(),
+            min_size: self.min_size,
+            #[cfg(feature = "dictionary")]
+            dictionaries: self.dictionaries.clone(),
             _t: PhantomData,
         })
     }
@@ -82,6 +127,10 @@ where
 pub struct CompressMiddleware<S, E> {
     service: S,
     encoding: ContentEncoding,
+    types: MediaTypePredicate,
+    min_size: usize,
+    #[cfg(feature = "dictionary")]
+    dictionaries: Vec<Dictionary>,
     _t: PhantomData<E>,
 }
 
@@ -118,8 +167,19 @@ where
             ContentEncoding::Identity
         };
 
+        #[cfg(feature = "dictionary")]
+        let dictionary = self
+            .dictionaries
+            .iter()
+            .find(|dict| dict.is_available(req.headers()))
+            .cloned();
+
         CompressResponse {
             encoding,
+            types: self.types.clone(),
+            min_size: self.min_size,
+            #[cfg(feature = "dictionary")]
+            dictionary,
             fut: self.service.call(req),
             _t: PhantomData,
         }
@@ -136,6 +196,10 @@ where
     #[pin]
     fut: S::Future,
     encoding: ContentEncoding,
+    types: MediaTypePredicate,
+    min_size: usize,
+    #[cfg(feature = "dictionary")]
+    dictionary: Option<Dictionary>,
     _t: PhantomData<(B, E)>,
 }
 
@@ -154,7 +218,19 @@ where
             Ok(resp) => {
                 let enc = if let Some(enc) = resp.response().get_encoding() {
                     enc
+                } else if !this.types.matches_headers(resp.headers())
+                    || is_small(resp.response().body().size(), *this.min_size)
+                {
+                    ContentEncoding::Identity
                 } else {
+                    #[cfg(feature = "dictionary")]
+                    {
+                        if let Some(dict) = this.dictionary.take() {
+                            return Poll::Ready(Ok(resp.map_body(move |head, body| {
+                                Encoder::dictionary_response(&dict, head, body)
+                            })));
+                        }
+                    }
                     *this.encoding
                 };
 
@@ -167,6 +243,14 @@ where
     }
 }
 
+fn is_small(size: BodySize, min_size: usize) -> bool {
+    match size {
+        BodySize::Sized(size) => size < min_size,
+        BodySize::Sized64(size) => size < min_size as u64,
+        _ => false,
+    }
+}
+
 struct AcceptEncoding {
     encoding: ContentEncoding,
     quality: f64,
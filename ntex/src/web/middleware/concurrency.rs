@@ -0,0 +1,116 @@
+//! Middleware for limiting number of in-flight requests
+use crate::service::{Service, Transform};
+use crate::web::dev::{WebRequest, WebResponse};
+
+use super::priority::{Priority, PriorityMiddleware};
+
+/// `Middleware` for limiting number of concurrently processed requests.
+///
+/// Limit is per worker. If limit is reached, request waits in queue, if
+/// queue is full request is rejected with *503 Service Unavailable*
+/// response. By default queue is disabled and requests are rejected as
+/// soon as limit is reached. Slot is released when inner service returns
+/// response, response body streaming is not accounted.
+///
+/// `Concurrency` is a `Priority` middleware with single request class,
+/// use `Priority` if different endpoints require separate budgets.
+///
+/// ```rust
+/// use ntex::web::{self, middleware, App, HttpResponse};
+///
+/// fn main() {
+///     let app = App::new()
+///         .wrap(middleware::Concurrency::new(256).queue(1024))
+///         .service(web::resource("/").to(|| async { HttpResponse::Ok() }));
+/// }
+/// ```
+pub struct Concurrency<E>(Priority<E>);
+
+impl<E> Concurrency<E> {
+    /// Construct `Concurrency` middleware with max number of in-flight
+    /// requests per worker.
+    pub fn new(max: usize) -> Self {
+        Concurrency(Priority::new(max).max_queue(0))
+    }
+
+    /// Set max number of requests waiting for a slot.
+    ///
+    /// By default queue is disabled.
+    pub fn queue(self, depth: usize) -> Self {
+        Concurrency(self.0.max_queue(depth))
+    }
+}
+
+impl<S, B, E> Transform<S> for Concurrency<E>
+where
+    S: Service<Request = WebRequest<E>, Response = WebResponse<B>>,
+{
+    type Request = WebRequest<E>;
+    type Response = WebResponse<B>;
+    type Error = S::Error;
+    type InitError = ();
+    type Transform = PriorityMiddleware<S, E>;
+    type Future = <Priority<E> as Transform<S>>::Future;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        self.0.new_transform(service)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::future::lazy;
+    use std::future::Future;
+
+    use super::*;
+    use crate::http::StatusCode;
+    use crate::web::test::{ok_service, TestRequest};
+    use crate::web::DefaultError;
+
+    #[ntex_rt::test]
+    async fn test_concurrency() {
+        let mw = Concurrency::<DefaultError>::new(1)
+            .new_transform(ok_service())
+            .await
+            .unwrap();
+
+        let fut1 = mw.call(TestRequest::default().to_srv_request());
+        let resp = mw
+            .call(TestRequest::default().to_srv_request())
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::SERVICE_UNAVAILABLE);
+
+        let resp = fut1.await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        let resp = mw
+            .call(TestRequest::default().to_srv_request())
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+    }
+
+    #[ntex_rt::test]
+    async fn test_queue() {
+        let mw = Concurrency::<DefaultError>::new(1)
+            .queue(1)
+            .new_transform(ok_service())
+            .await
+            .unwrap();
+
+        let fut1 = mw.call(TestRequest::default().to_srv_request());
+        let mut fut2 = Box::pin(mw.call(TestRequest::default().to_srv_request()));
+        assert!(lazy(|cx| fut2.as_mut().poll(cx)).await.is_pending());
+
+        let resp = mw
+            .call(TestRequest::default().to_srv_request())
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::SERVICE_UNAVAILABLE);
+
+        let resp = fut1.await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        let resp = fut2.await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+    }
+}
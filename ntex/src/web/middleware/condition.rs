@@ -0,0 +1,143 @@
+//! Middleware for conditionally enabled middleware
+use std::task::{Context, Poll};
+
+use futures::future::{ok, Either, MapOk, Ready, TryFutureExt};
+
+use crate::service::{Service, Transform};
+
+/// `Middleware` for conditionally enabled middleware.
+///
+/// Condition is checked once, when application gets constructed, so
+/// middleware could be toggled by configuration. Disabled middleware is
+/// not constructed and requests are passed directly to inner service.
+/// Middleware must not change response type of inner service.
+///
+/// ```rust
+/// use ntex::web::{self, middleware, App, HttpResponse};
+///
+/// fn main() {
+///     let debug = std::env::var("DEBUG").is_ok();
+///
+///     let app = App::new()
+///         .wrap(middleware::Condition::new(
+///             debug,
+///             middleware::DefaultHeaders::new().header("X-Debug", "1"),
+///         ))
+///         .service(web::resource("/test").to(|| async { HttpResponse::Ok() }));
+/// }
+/// ```
+#[derive(Clone)]
+pub struct Condition<T> {
+    enabled: bool,
+    mw: T,
+}
+
+impl<T> Condition<T> {
+    /// Construct `Condition` middleware, `mw` is used only if `enabled`
+    /// is true.
+    pub fn new(enabled: bool, mw: T) -> Self {
+        Condition { enabled, mw }
+    }
+}
+
+impl<S, T> Transform<S> for Condition<T>
+where
+    S: Service,
+    T: Transform<S, Request = S::Request, Response = S::Response, Error = S::Error>,
+{
+    type Request = S::Request;
+    type Response = S::Response;
+    type Error = S::Error;
+    type InitError = T::InitError;
+    type Transform = ConditionMiddleware<T::Transform, S>;
+    type Future = Either<
+        MapOk<T::Future, fn(T::Transform) -> Self::Transform>,
+        Ready<Result<Self::Transform, Self::InitError>>,
+    >;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        if self.enabled {
+            let f: fn(T::Transform) -> Self::Transform = ConditionMiddleware::Enabled;
+            Either::Left(self.mw.new_transform(service).map_ok(f))
+        } else {
+            Either::Right(ok(ConditionMiddleware::Disabled(service)))
+        }
+    }
+}
+
+pub enum ConditionMiddleware<E, D> {
+    Enabled(E),
+    Disabled(D),
+}
+
+impl<E, D> Service for ConditionMiddleware<E, D>
+where
+    E: Service,
+    D: Service<Request = E::Request, Response = E::Response, Error = E::Error>,
+{
+    type Request = E::Request;
+    type Response = E::Response;
+    type Error = E::Error;
+    type Future = Either<E::Future, D::Future>;
+
+    #[inline]
+    fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        match self {
+            ConditionMiddleware::Enabled(srv) => srv.poll_ready(cx),
+            ConditionMiddleware::Disabled(srv) => srv.poll_ready(cx),
+        }
+    }
+
+    #[inline]
+    fn poll_shutdown(&self, cx: &mut Context<'_>, is_error: bool) -> Poll<()> {
+        match self {
+            ConditionMiddleware::Enabled(srv) => srv.poll_shutdown(cx, is_error),
+            ConditionMiddleware::Disabled(srv) => srv.poll_shutdown(cx, is_error),
+        }
+    }
+
+    fn call(&self, req: E::Request) -> Self::Future {
+        match self {
+            ConditionMiddleware::Enabled(srv) => Either::Left(srv.call(req)),
+            ConditionMiddleware::Disabled(srv) => Either::Right(srv.call(req)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::header::CONTENT_TYPE;
+    use crate::web::middleware::DefaultHeaders;
+    use crate::web::test::{ok_service, TestRequest};
+    use crate::web::DefaultError;
+
+    #[ntex_rt::test]
+    async fn test_condition() {
+        let mw = Condition::new(
+            true,
+            DefaultHeaders::<DefaultError>::new().header(CONTENT_TYPE, "0001"),
+        )
+        .new_transform(ok_service())
+        .await
+        .unwrap();
+        let resp = mw
+            .call(TestRequest::default().to_srv_request())
+            .await
+            .unwrap();
+        assert_eq!(resp.headers().get(CONTENT_TYPE).unwrap(), "0001");
+
+        let mw = Condition::new(
+            false,
+            DefaultHeaders::<DefaultError>::new().header(CONTENT_TYPE, "0001"),
+        )
+        .new_transform(ok_service())
+        .await
+        .unwrap();
+        let resp = mw
+            .call(TestRequest::default().to_srv_request())
+            .await
+            .unwrap();
+        assert!(!resp.headers().contains_key(CONTENT_TYPE));
+    }
+}
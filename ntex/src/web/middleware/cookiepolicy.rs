@@ -0,0 +1,263 @@
+//! Middleware for application cookie policy
+use std::convert::TryFrom;
+use std::marker::PhantomData;
+use std::rc::Rc;
+use std::task::{Context, Poll};
+
+use coo_kie::SameSite;
+use futures::future::{ok, FutureExt, LocalBoxFuture, Ready};
+
+use crate::http::cookie::{validate_prefix, SetCookie};
+use crate::http::header::{self, HeaderValue};
+use crate::service::{Service, Transform};
+use crate::web::dev::{WebRequest, WebResponse};
+
+/// `Middleware` for application cookie policy.
+///
+/// Middleware post-processes *Set-Cookie* headers of responses:
+///
+/// * cookies without `SameSite` attribute get default `SameSite`
+///   attribute, by default `Lax`
+/// * cookies with `SameSite=None` get `Secure` attribute
+/// * configured cookies get `Partitioned` attribute
+/// * cookies with `__Secure-` or `__Host-` name prefix that do not satisfy
+///   prefix requirements are removed from response
+///
+/// ```rust
+/// use coo_kie::SameSite;
+/// use ntex::web::{self, middleware, App, HttpResponse};
+///
+/// fn main() {
+///     let app = App::new()
+///         .wrap(
+///             middleware::CookiePolicy::new()
+///                 .same_site(SameSite::Strict)
+///                 .partitioned("widget"),
+///         )
+///         .service(web::resource("/test").to(|| async { HttpResponse::Ok() }));
+/// }
+/// ```
+pub struct CookiePolicy<E> {
+    inner: Rc<Inner>,
+    _t: PhantomData<E>,
+}
+
+struct Inner {
+    same_site: Option<SameSite>,
+    partitioned: Vec<String>,
+}
+
+impl<E> Default for CookiePolicy<E> {
+    fn default() -> Self {
+        CookiePolicy {
+            inner: Rc::new(Inner {
+                same_site: Some(SameSite::Lax),
+                partitioned: Vec::new(),
+            }),
+            _t: PhantomData,
+        }
+    }
+}
+
+impl<E> CookiePolicy<E> {
+    /// Construct `CookiePolicy` middleware
+    pub fn new() -> Self {
+        CookiePolicy::default()
+    }
+
+    /// Set default `SameSite` attribute, by default `Lax`
+    pub fn same_site(mut self, value: SameSite) -> Self {
+        Rc::get_mut(&mut self.inner)
+            .expect("Multiple copies exist")
+            .same_site = Some(value);
+        self
+    }
+
+    /// Do not set default `SameSite` attribute
+    pub fn no_same_site(mut self) -> Self {
+        Rc::get_mut(&mut self.inner)
+            .expect("Multiple copies exist")
+            .same_site = None;
+        self
+    }
+
+    /// Set `Partitioned` attribute for cookie with specified name
+    pub fn partitioned<S: Into<String>>(mut self, name: S) -> Self {
+        Rc::get_mut(&mut self.inner)
+            .expect("Multiple copies exist")
+            .partitioned
+            .push(name.into());
+        self
+    }
+}
+
+impl Inner {
+    /// Apply policy to `Set-Cookie` header value, `None` if cookie
+    /// must be removed
+    fn apply(&self, value: HeaderValue) -> Option<HeaderValue> {
+        let mut set_cookie = match value.to_str().ok().map(SetCookie::parse) {
+            Some(Ok(c)) => c,
+            _ => return Some(value),
+        };
+        let mut changed = false;
+
+        let cookie = set_cookie.cookie_mut();
+        if cookie.same_site().is_none() {
+            if let Some(same_site) = self.same_site {
+                cookie.set_same_site(same_site);
+                changed = true;
+            }
+        }
+        if cookie.same_site() == Some(SameSite::None) && cookie.secure() != Some(true) {
+            cookie.set_secure(true);
+            changed = true;
+        }
+        if !set_cookie.is_partitioned()
+            && self
+                .partitioned
+                .iter()
+                .any(|n| n == set_cookie.cookie().name())
+        {
+            set_cookie.set_partitioned(true);
+            changed = true;
+        }
+
+        if let Err(e) = validate_prefix(set_cookie.cookie()) {
+            log::warn!("Cookie {:?} is removed: {}", set_cookie.cookie().name(), e);
+            None
+        } else if changed {
+            HeaderValue::try_from(set_cookie.to_string()).ok()
+        } else {
+            Some(value)
+        }
+    }
+}
+
+impl<S, B, E> Transform<S> for CookiePolicy<E>
+where
+    S: Service<Request = WebRequest<E>, Response = WebResponse<B>>,
+    S::Future: 'static,
+{
+    type Request = WebRequest<E>;
+    type Response = WebResponse<B>;
+    type Error = S::Error;
+    type InitError = ();
+    type Transform = CookiePolicyMiddleware<S, E>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ok(CookiePolicyMiddleware {
+            service,
+            inner: self.inner.clone(),
+            _t: PhantomData,
+        })
+    }
+}
+
+pub struct CookiePolicyMiddleware<S, E> {
+    service: S,
+    inner: Rc<Inner>,
+    _t: PhantomData<E>,
+}
+
+impl<S, B, E> Service for CookiePolicyMiddleware<S, E>
+where
+    S: Service<Request = WebRequest<E>, Response = WebResponse<B>>,
+    S::Future: 'static,
+{
+    type Request = WebRequest<E>;
+    type Response = WebResponse<B>;
+    type Error = S::Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    #[inline]
+    fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(cx)
+    }
+
+    #[inline]
+    fn poll_shutdown(&self, cx: &mut Context<'_>, is_error: bool) -> Poll<()> {
+        self.service.poll_shutdown(cx, is_error)
+    }
+
+    fn call(&self, req: WebRequest<E>) -> Self::Future {
+        let inner = self.inner.clone();
+        let fut = self.service.call(req);
+
+        async move {
+            let mut res = fut.await?;
+            if res.headers().contains_key(header::SET_COOKIE) {
+                let headers = res.headers_mut();
+                let values: Vec<_> =
+                    headers.get_all(header::SET_COOKIE).cloned().collect();
+                headers.remove(header::SET_COOKIE);
+                for value in values {
+                    if let Some(value) = inner.apply(value) {
+                        headers.append(header::SET_COOKIE, value);
+                    }
+                }
+            }
+            Ok(res)
+        }
+        .boxed_local()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use coo_kie::Cookie;
+
+    use super::*;
+    use crate::web::test::{call_service, init_service, TestRequest};
+    use crate::web::{self, App, DefaultError, HttpResponse};
+
+    #[ntex_rt::test]
+    async fn test_cookie_policy() {
+        let srv = init_service(
+            App::new()
+                .wrap(CookiePolicy::<DefaultError>::new().partitioned("widget"))
+                .service(web::resource("/").to(|| async {
+                    HttpResponse::Ok()
+                        .cookie(Cookie::new("lax", "1"))
+                        .cookie(
+                            Cookie::build("strict", "1")
+                                .same_site(SameSite::Strict)
+                                .finish(),
+                        )
+                        .cookie(
+                            Cookie::build("none", "1")
+                                .same_site(SameSite::None)
+                                .finish(),
+                        )
+                        .cookie(Cookie::new("widget", "1"))
+                        .cookie(Cookie::new("__Host-invalid", "1"))
+                        .cookie(
+                            Cookie::build("__Host-valid", "1")
+                                .secure(true)
+                                .path("/")
+                                .finish(),
+                        )
+                        .finish()
+                })),
+        )
+        .await;
+
+        let resp = call_service(&srv, TestRequest::default().to_request()).await;
+        let mut cookies: Vec<_> = resp
+            .headers()
+            .get_all(header::SET_COOKIE)
+            .map(|v| v.to_str().unwrap().to_string())
+            .collect();
+        cookies.sort();
+        assert_eq!(
+            cookies,
+            vec![
+                "__Host-valid=1; SameSite=Lax; Secure; Path=/",
+                "lax=1; SameSite=Lax",
+                "none=1; SameSite=None; Secure",
+                "strict=1; SameSite=Strict",
+                "widget=1; SameSite=Lax; Secure; Partitioned",
+            ]
+        );
+    }
+}
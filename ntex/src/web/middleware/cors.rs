@@ -0,0 +1,544 @@
+//! Cross-origin resource sharing (CORS) middleware
+use std::collections::HashSet;
+use std::convert::TryFrom;
+use std::future::Future;
+use std::marker::PhantomData;
+use std::pin::Pin;
+use std::rc::Rc;
+use std::task::{Context, Poll};
+
+use futures::future::{ok, Either, Ready};
+
+use crate::http::error::HttpError;
+use crate::http::header::{self, HeaderName, HeaderValue};
+use crate::http::{Method, Response, StatusCode};
+use crate::service::{Service, Transform};
+use crate::web::dev::{WebRequest, WebResponse};
+
+/// `Middleware` for cross-origin resource sharing support.
+///
+/// By default requests from any origin are allowed, with *GET*, *HEAD*,
+/// *POST*, *PUT*, *PATCH*, *DELETE* and *OPTIONS* methods and any request
+/// headers. Middleware responds to preflight requests, preflight requests
+/// that do not pass checks are rejected with *400 Bad Request* response.
+/// Actual requests from not allowed origins are passed to inner service,
+/// but response does not get CORS headers, so browser blocks it.
+///
+/// ```rust
+/// use ntex::http::{header, Method};
+/// use ntex::web::{self, middleware, App, HttpResponse};
+///
+/// fn main() {
+///     let app = App::new()
+///         .wrap(
+///             middleware::Cors::new()
+///                 .allowed_origin("https://www.rust-lang.org")
+///                 .allowed_methods(vec![Method::GET, Method::POST])
+///                 .allowed_headers(vec![header::AUTHORIZATION, header::ACCEPT])
+///                 .allowed_header(header::CONTENT_TYPE)
+///                 .supports_credentials()
+///                 .max_age(3600),
+///         )
+///         .service(web::resource("/index.html").to(|| async { HttpResponse::Ok() }));
+/// }
+/// ```
+pub struct Cors<E> {
+    inner: Rc<Inner>,
+    _t: PhantomData<E>,
+}
+
+struct Inner {
+    origins: Option<HashSet<String>>,
+    methods: Vec<Method>,
+    headers: Option<HashSet<HeaderName>>,
+    expose: Vec<HeaderName>,
+    credentials: bool,
+    max_age: Option<usize>,
+    preflight: bool,
+}
+
+impl<E> Default for Cors<E> {
+    fn default() -> Self {
+        Cors {
+            inner: Rc::new(Inner {
+                origins: None,
+                methods: vec![
+                    Method::GET,
+                    Method::HEAD,
+                    Method::POST,
+                    Method::PUT,
+                    Method::PATCH,
+                    Method::DELETE,
+                    Method::OPTIONS,
+                ],
+                headers: None,
+                expose: Vec::new(),
+                credentials: false,
+                max_age: None,
+                preflight: true,
+            }),
+            _t: PhantomData,
+        }
+    }
+}
+
+impl<E> Cors<E> {
+    /// Construct `Cors` middleware.
+    pub fn new() -> Self {
+        Cors::default()
+    }
+
+    /// Add allowed origin.
+    ///
+    /// Origin is a scheme, host and port, i.e. `https://example.com:8080`.
+    /// Once origin is added, requests from other origins are not allowed.
+    /// `*` allows any origin.
+    pub fn allowed_origin(mut self, origin: &str) -> Self {
+        let inner = Rc::get_mut(&mut self.inner).expect("Multiple copies exist");
+        if origin == "*" {
+            inner.origins = None;
+        } else {
+            inner
+                .origins
+                .get_or_insert_with(HashSet::new)
+                .insert(origin.to_ascii_lowercase());
+        }
+        self
+    }
+
+    /// Set allowed methods.
+    pub fn allowed_methods<I>(mut self, methods: I) -> Self
+    where
+        I: IntoIterator<Item = Method>,
+    {
+        Rc::get_mut(&mut self.inner)
+            .expect("Multiple copies exist")
+            .methods = methods.into_iter().collect();
+        self
+    }
+
+    /// Add allowed request header.
+    ///
+    /// Once header is added, request headers that are not in the list
+    /// are not allowed.
+    pub fn allowed_header<H>(mut self, header: H) -> Self
+    where
+        HeaderName: TryFrom<H>,
+        <HeaderName as TryFrom<H>>::Error: Into<HttpError>,
+    {
+        let header = HeaderName::try_from(header)
+            .unwrap_or_else(|_| panic!("Can not create header name"));
+        Rc::get_mut(&mut self.inner)
+            .expect("Multiple copies exist")
+            .headers
+            .get_or_insert_with(HashSet::new)
+            .insert(header);
+        self
+    }
+
+    /// Add allowed request headers.
+    pub fn allowed_headers<I, H>(self, headers: I) -> Self
+    where
+        I: IntoIterator<Item = H>,
+        HeaderName: TryFrom<H>,
+        <HeaderName as TryFrom<H>>::Error: Into<HttpError>,
+    {
+        headers
+            .into_iter()
+            .fold(self, |cors, header| cors.allowed_header(header))
+    }
+
+    /// Add response headers that are exposed to browser scripts.
+    pub fn expose_headers<I, H>(mut self, headers: I) -> Self
+    where
+        I: IntoIterator<Item = H>,
+        HeaderName: TryFrom<H>,
+        <HeaderName as TryFrom<H>>::Error: Into<HttpError>,
+    {
+        let inner = Rc::get_mut(&mut self.inner).expect("Multiple copies exist");
+        for header in headers {
+            inner.expose.push(
+                HeaderName::try_from(header)
+                    .unwrap_or_else(|_| panic!("Can not create header name")),
+            );
+        }
+        self
+    }
+
+    /// Allow requests with credentials, like cookies or authorization headers.
+    pub fn supports_credentials(mut self) -> Self {
+        Rc::get_mut(&mut self.inner)
+            .expect("Multiple copies exist")
+            .credentials = true;
+        self
+    }
+
+    /// Set how long, in seconds, preflight results could be cached.
+    pub fn max_age(mut self, max_age: usize) -> Self {
+        Rc::get_mut(&mut self.inner)
+            .expect("Multiple copies exist")
+            .max_age = Some(max_age);
+        self
+    }
+
+    /// Do not handle preflight requests, pass them to inner service.
+    pub fn disable_preflight(mut self) -> Self {
+        Rc::get_mut(&mut self.inner)
+            .expect("Multiple copies exist")
+            .preflight = false;
+        self
+    }
+}
+
+impl Inner {
+    fn is_origin_allowed(&self, origin: &HeaderValue) -> bool {
+        match self.origins {
+            None => true,
+            Some(ref origins) => origin
+                .to_str()
+                .map(|o| origins.contains(&o.to_ascii_lowercase()))
+                .unwrap_or(false),
+        }
+    }
+
+    /// Value of `Access-Control-Allow-Origin` header
+    fn allow_origin(&self, origin: HeaderValue) -> HeaderValue {
+        if self.origins.is_none() && !self.credentials {
+            HeaderValue::from_static("*")
+        } else {
+            origin
+        }
+    }
+
+    fn preflight<E>(&self, req: &WebRequest<E>, origin: HeaderValue) -> Response {
+        let method = req
+            .headers()
+            .get(header::ACCESS_CONTROL_REQUEST_METHOD)
+            .and_then(|m| Method::from_bytes(m.as_bytes()).ok());
+        match method {
+            Some(ref method) if self.methods.contains(method) => (),
+            _ => {
+                log::debug!("CORS preflight request method is not allowed");
+                return Response::new(StatusCode::BAD_REQUEST);
+            }
+        }
+
+        let req_headers = req.headers().get(header::ACCESS_CONTROL_REQUEST_HEADERS);
+        if let (Some(hdrs), Some(ref allowed)) = (req_headers, &self.headers) {
+            let valid = hdrs.to_str().map(|hdrs| {
+                hdrs.split(',').filter(|h| !h.trim().is_empty()).all(|h| {
+                    HeaderName::try_from(h.trim())
+                        .map(|h| allowed.contains(&h))
+                        .unwrap_or(false)
+                })
+            });
+            if !valid.unwrap_or(false) {
+                log::debug!("CORS preflight request headers are not allowed");
+                return Response::new(StatusCode::BAD_REQUEST);
+            }
+        }
+
+        let mut res = Response::Ok();
+        res.header(
+            header::ACCESS_CONTROL_ALLOW_ORIGIN,
+            self.allow_origin(origin),
+        )
+        .header(header::ACCESS_CONTROL_ALLOW_METHODS, join(&self.methods));
+        match (&self.headers, req_headers) {
+            (Some(headers), _) => {
+                let mut headers: Vec<_> = headers.iter().map(|h| h.as_str()).collect();
+                headers.sort();
+                res.header(header::ACCESS_CONTROL_ALLOW_HEADERS, join(&headers));
+            }
+            (None, Some(hdrs)) => {
+                res.header(header::ACCESS_CONTROL_ALLOW_HEADERS, hdrs.clone());
+            }
+            (None, None) => (),
+        }
+        if self.credentials {
+            res.header(header::ACCESS_CONTROL_ALLOW_CREDENTIALS, "true");
+        }
+        if let Some(max_age) = self.max_age {
+            res.header(header::ACCESS_CONTROL_MAX_AGE, max_age);
+        }
+        if self.origins.is_some() || self.credentials {
+            res.header(header::VARY, "Origin");
+        }
+        res.finish()
+    }
+}
+
+fn join<T: ToString>(items: &[T]) -> String {
+    items
+        .iter()
+        .map(|item| item.to_string())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+impl<S, B, E> Transform<S> for Cors<E>
+where
+    S: Service<Request = WebRequest<E>, Response = WebResponse<B>>,
+{
+    type Request = WebRequest<E>;
+    type Response = WebResponse<B>;
+    type Error = S::Error;
+    type InitError = ();
+    type Transform = CorsMiddleware<S, E>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ok(CorsMiddleware {
+            service,
+            inner: self.inner.clone(),
+            _t: PhantomData,
+        })
+    }
+}
+
+pub struct CorsMiddleware<S, E> {
+    service: S,
+    inner: Rc<Inner>,
+    _t: PhantomData<E>,
+}
+
+impl<S, B, E> Service for CorsMiddleware<S, E>
+where
+    S: Service<Request = WebRequest<E>, Response = WebResponse<B>>,
+{
+    type Request = WebRequest<E>;
+    type Response = WebResponse<B>;
+    type Error = S::Error;
+    type Future =
+        Either<CorsResponse<S, B, E>, Ready<Result<Self::Response, Self::Error>>>;
+
+    #[inline]
+    fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(cx)
+    }
+
+    #[inline]
+    fn poll_shutdown(&self, cx: &mut Context<'_>, is_error: bool) -> Poll<()> {
+        self.service.poll_shutdown(cx, is_error)
+    }
+
+    fn call(&self, req: WebRequest<E>) -> Self::Future {
+        let origin = req.headers().get(header::ORIGIN).cloned();
+        let is_preflight = self.inner.preflight
+            && req.method() == Method::OPTIONS
+            && req
+                .headers()
+                .contains_key(header::ACCESS_CONTROL_REQUEST_METHOD);
+
+        let origin = match origin {
+            Some(origin) if self.inner.is_origin_allowed(&origin) => {
+                if is_preflight {
+                    let res = self.inner.preflight(&req, origin);
+                    return Either::Right(ok(req.into_response(res.into_body())));
+                }
+                Some(origin)
+            }
+            Some(_) => {
+                log::debug!("CORS request origin is not allowed");
+                if is_preflight {
+                    let res = Response::new(StatusCode::BAD_REQUEST);
+                    return Either::Right(ok(req.into_response(res.into_body())));
+                }
+                None
+            }
+            None => None,
+        };
+
+        Either::Left(CorsResponse {
+            origin,
+            fut: self.service.call(req),
+            inner: self.inner.clone(),
+            _t: PhantomData,
+        })
+    }
+}
+
+#[doc(hidden)]
+#[pin_project::pin_project]
+pub struct CorsResponse<S: Service, B, E> {
+    #[pin]
+    fut: S::Future,
+    origin: Option<HeaderValue>,
+    inner: Rc<Inner>,
+    _t: PhantomData<(B, E)>,
+}
+
+impl<S, B, E> Future for CorsResponse<S, B, E>
+where
+    S: Service<Request = WebRequest<E>, Response = WebResponse<B>>,
+{
+    type Output = Result<WebResponse<B>, S::Error>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        let mut res = futures::ready!(this.fut.poll(cx))?;
+
+        if let Some(origin) = this.origin.take() {
+            let inner = &this.inner;
+            let headers = res.headers_mut();
+            headers.insert(
+                header::ACCESS_CONTROL_ALLOW_ORIGIN,
+                inner.allow_origin(origin),
+            );
+            if inner.credentials {
+                headers.insert(
+                    header::ACCESS_CONTROL_ALLOW_CREDENTIALS,
+                    HeaderValue::from_static("true"),
+                );
+            }
+            if !inner.expose.is_empty() {
+                if let Ok(val) = HeaderValue::try_from(join(&inner.expose)) {
+                    headers.insert(header::ACCESS_CONTROL_EXPOSE_HEADERS, val);
+                }
+            }
+            if inner.origins.is_some() || inner.credentials {
+                headers.append(header::VARY, HeaderValue::from_static("Origin"));
+            }
+        }
+        Poll::Ready(Ok(res))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::web::test::{call_service, init_service, TestRequest};
+    use crate::web::{self, App, DefaultError, HttpResponse};
+
+    #[ntex_rt::test]
+    async fn test_cors() {
+        let srv = init_service(
+            App::new()
+                .wrap(
+                    Cors::<DefaultError>::new()
+                        .allowed_origin("https://www.example.com")
+                        .allowed_methods(vec![Method::GET, Method::POST])
+                        .allowed_header(header::CONTENT_TYPE)
+                        .expose_headers(vec!["x-version"])
+                        .supports_credentials()
+                        .max_age(3600),
+                )
+                .service(web::resource("/").to(|| async { HttpResponse::Ok() })),
+        )
+        .await;
+
+        // preflight
+        let req = TestRequest::with_header(header::ORIGIN, "https://www.example.com")
+            .method(Method::OPTIONS)
+            .header(header::ACCESS_CONTROL_REQUEST_METHOD, "POST")
+            .header(header::ACCESS_CONTROL_REQUEST_HEADERS, "content-type")
+            .to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let hdrs = resp.headers();
+        assert_eq!(
+            hdrs.get(header::ACCESS_CONTROL_ALLOW_ORIGIN).unwrap(),
+            "https://www.example.com"
+        );
+        assert_eq!(
+            hdrs.get(header::ACCESS_CONTROL_ALLOW_METHODS).unwrap(),
+            "GET, POST"
+        );
+        assert_eq!(
+            hdrs.get(header::ACCESS_CONTROL_ALLOW_HEADERS).unwrap(),
+            "content-type"
+        );
+        assert_eq!(
+            hdrs.get(header::ACCESS_CONTROL_ALLOW_CREDENTIALS).unwrap(),
+            "true"
+        );
+        assert_eq!(hdrs.get(header::ACCESS_CONTROL_MAX_AGE).unwrap(), "3600");
+
+        // method is not allowed
+        let req = TestRequest::with_header(header::ORIGIN, "https://www.example.com")
+            .method(Method::OPTIONS)
+            .header(header::ACCESS_CONTROL_REQUEST_METHOD, "DELETE")
+            .to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+
+        // header is not allowed
+        let req = TestRequest::with_header(header::ORIGIN, "https://www.example.com")
+            .method(Method::OPTIONS)
+            .header(header::ACCESS_CONTROL_REQUEST_METHOD, "GET")
+            .header(header::ACCESS_CONTROL_REQUEST_HEADERS, "x-custom")
+            .to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+
+        // origin is not allowed
+        let req = TestRequest::with_header(header::ORIGIN, "https://other.com")
+            .method(Method::OPTIONS)
+            .header(header::ACCESS_CONTROL_REQUEST_METHOD, "GET")
+            .to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+
+        let req =
+            TestRequest::with_header(header::ORIGIN, "https://other.com").to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert!(resp
+            .headers()
+            .get(header::ACCESS_CONTROL_ALLOW_ORIGIN)
+            .is_none());
+
+        // actual request
+        let req = TestRequest::with_header(header::ORIGIN, "https://WWW.example.com")
+            .to_request();
+        let resp = call_service(&srv, req).await;
+        let hdrs = resp.headers();
+        assert_eq!(
+            hdrs.get(header::ACCESS_CONTROL_ALLOW_ORIGIN).unwrap(),
+            "https://WWW.example.com"
+        );
+        assert_eq!(
+            hdrs.get(header::ACCESS_CONTROL_EXPOSE_HEADERS).unwrap(),
+            "x-version"
+        );
+        assert_eq!(hdrs.get(header::VARY).unwrap(), "Origin");
+
+        // no origin
+        let req = TestRequest::default().to_request();
+        let resp = call_service(&srv, req).await;
+        assert!(resp
+            .headers()
+            .get(header::ACCESS_CONTROL_ALLOW_ORIGIN)
+            .is_none());
+    }
+
+    #[ntex_rt::test]
+    async fn test_cors_any_origin() {
+        let srv = init_service(
+            App::new()
+                .wrap(Cors::<DefaultError>::new())
+                .service(web::resource("/").to(|| async { HttpResponse::Ok() })),
+        )
+        .await;
+
+        let req = TestRequest::with_header(header::ORIGIN, "https://www.example.com")
+            .method(Method::OPTIONS)
+            .header(header::ACCESS_CONTROL_REQUEST_METHOD, "PUT")
+            .header(header::ACCESS_CONTROL_REQUEST_HEADERS, "x-custom")
+            .to_request();
+        let resp = call_service(&srv, req).await;
+        let hdrs = resp.headers();
+        assert_eq!(hdrs.get(header::ACCESS_CONTROL_ALLOW_ORIGIN).unwrap(), "*");
+        assert_eq!(
+            hdrs.get(header::ACCESS_CONTROL_ALLOW_HEADERS).unwrap(),
+            "x-custom"
+        );
+
+        let req = TestRequest::with_header(header::ORIGIN, "https://www.example.com")
+            .to_request();
+        let resp = call_service(&srv, req).await;
+        let hdrs = resp.headers();
+        assert_eq!(hdrs.get(header::ACCESS_CONTROL_ALLOW_ORIGIN).unwrap(), "*");
+        assert!(hdrs.get(header::VARY).is_none());
+    }
+}
@@ -0,0 +1,517 @@
+//! Middleware for CSRF protection
+use std::convert::TryFrom;
+use std::fmt;
+use std::future::Future;
+use std::marker::PhantomData;
+use std::ops::Deref;
+use std::pin::Pin;
+use std::rc::Rc;
+use std::task::{Context, Poll};
+
+use coo_kie::{Cookie, SameSite};
+use futures::future::{err, ok, Either, Ready};
+
+use crate::http::error::HttpError;
+use crate::http::header::HeaderName;
+use crate::http::{HttpMessage, Method, Payload};
+use crate::service::{Service, Transform};
+use crate::web::dev::{WebRequest, WebResponse};
+use crate::web::error::CsrfError;
+use crate::web::{ErrorRenderer, FromRequest, HttpRequest};
+
+#[cfg(feature = "secure-cookies")]
+use super::session::Session;
+
+/// Session key of CSRF token
+#[cfg(feature = "secure-cookies")]
+const SESSION_KEY: &str = "csrf-token";
+
+/// CSRF token of current request.
+///
+/// Token should be rendered into forms or passed to client side code, so
+/// it could be submitted back with `X-Csrf-Token` header. Extractor fails
+/// with `CsrfError::Missing` if `Csrf` middleware is not registered.
+///
+/// ```rust
+/// use ntex::web::{self, middleware::CsrfToken, HttpResponse};
+///
+/// async fn index(token: CsrfToken) -> HttpResponse {
+///     HttpResponse::Ok().body(format!(
+///         "<meta name=\"csrf-token\" content=\"{}\">", token
+///     ))
+/// }
+/// # fn main() {}
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CsrfToken(Rc<str>);
+
+impl CsrfToken {
+    fn generate() -> Self {
+        let bytes: [u8; 32] = rand::random();
+        CsrfToken(base64::encode_config(bytes, base64::URL_SAFE_NO_PAD).into())
+    }
+
+    /// Token as string
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// Compare tokens in constant time
+    fn verify(&self, submitted: &[u8]) -> bool {
+        let token = self.0.as_bytes();
+        token.len() == submitted.len()
+            && token
+                .iter()
+                .zip(submitted)
+                .fold(0, |acc, (a, b)| acc | (a ^ b))
+                == 0
+    }
+}
+
+impl Deref for CsrfToken {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Display for CsrfToken {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl<Err: ErrorRenderer> FromRequest<Err> for CsrfToken {
+    type Error = CsrfError;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    #[inline]
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        if let Some(token) = req.extensions().get::<CsrfToken>() {
+            ok(token.clone())
+        } else {
+            err(CsrfError::Missing)
+        }
+    }
+}
+
+enum Storage {
+    Cookie,
+    #[cfg(feature = "secure-cookies")]
+    Session,
+}
+
+/// `Middleware` for CSRF protection.
+///
+/// Middleware issues random token and validates it for requests with
+/// unsafe methods, every method except *GET*, *HEAD*, *OPTIONS* and
+/// *TRACE*. Token must be submitted with `X-Csrf-Token` header. Requests
+/// without token or with token mismatch are rejected with `CsrfError`
+/// which is rendered by application's error renderer, `DefaultError`
+/// renders *403 Forbidden* response.
+///
+/// Token could be stored in a cookie, double-submit cookie pattern, or in
+/// a session. Double-submit cookie is not http-only, so client side code
+/// could read it.
+///
+/// ```rust
+/// use ntex::web::{self, middleware, App, HttpResponse};
+///
+/// fn main() {
+///     let app = App::new()
+///         .wrap(middleware::Csrf::double_submit())
+///         .service(web::resource("/").to(|| async { HttpResponse::Ok() }));
+/// }
+/// ```
+pub struct Csrf<Err> {
+    inner: Rc<Inner>,
+    _t: PhantomData<Err>,
+}
+
+struct Inner {
+    storage: Storage,
+    header: HeaderName,
+    name: String,
+    path: String,
+    domain: Option<String>,
+    secure: bool,
+    same_site: Option<SameSite>,
+}
+
+impl<Err> Csrf<Err> {
+    /// Construct `Csrf` middleware with double-submit cookie storage
+    pub fn double_submit() -> Self {
+        Csrf::new(Storage::Cookie)
+    }
+
+    #[cfg(feature = "secure-cookies")]
+    /// Construct `Csrf` middleware with session storage.
+    ///
+    /// Token is stored in the request's `Session`, `SessionManager`
+    /// middleware must be registered after `Csrf` middleware.
+    pub fn session() -> Self {
+        Csrf::new(Storage::Session)
+    }
+
+    fn new(storage: Storage) -> Self {
+        Csrf {
+            inner: Rc::new(Inner {
+                storage,
+                header: HeaderName::from_static("x-csrf-token"),
+                name: "csrf-token".to_owned(),
+                path: "/".to_owned(),
+                domain: None,
+                secure: true,
+                same_site: Some(SameSite::Strict),
+            }),
+            _t: PhantomData,
+        }
+    }
+
+    /// Set token header name, by default `X-Csrf-Token`
+    pub fn header<H>(mut self, header: H) -> Self
+    where
+        HeaderName: TryFrom<H>,
+        <HeaderName as TryFrom<H>>::Error: Into<HttpError>,
+    {
+        Rc::get_mut(&mut self.inner)
+            .expect("Multiple copies exist")
+            .header = HeaderName::try_from(header)
+            .unwrap_or_else(|_| panic!("Can not create header name"));
+        self
+    }
+
+    /// Set cookie name, by default `csrf-token`
+    pub fn cookie_name<S: Into<String>>(mut self, value: S) -> Self {
+        Rc::get_mut(&mut self.inner)
+            .expect("Multiple copies exist")
+            .name = value.into();
+        self
+    }
+
+    /// Set cookie path, by default `/`
+    pub fn cookie_path<S: Into<String>>(mut self, value: S) -> Self {
+        Rc::get_mut(&mut self.inner)
+            .expect("Multiple copies exist")
+            .path = value.into();
+        self
+    }
+
+    /// Set cookie domain
+    pub fn cookie_domain<S: Into<String>>(mut self, value: S) -> Self {
+        Rc::get_mut(&mut self.inner)
+            .expect("Multiple copies exist")
+            .domain = Some(value.into());
+        self
+    }
+
+    /// Set cookie `secure` flag, by default enabled
+    pub fn cookie_secure(mut self, value: bool) -> Self {
+        Rc::get_mut(&mut self.inner)
+            .expect("Multiple copies exist")
+            .secure = value;
+        self
+    }
+
+    /// Set cookie `SameSite` attribute, by default `Strict`
+    pub fn cookie_same_site(mut self, value: SameSite) -> Self {
+        Rc::get_mut(&mut self.inner)
+            .expect("Multiple copies exist")
+            .same_site = Some(value);
+        self
+    }
+}
+
+impl Inner {
+    fn cookie(&self, token: &CsrfToken) -> Cookie<'static> {
+        let mut cookie = Cookie::new(self.name.clone(), token.as_str().to_owned());
+        cookie.set_path(self.path.clone());
+        cookie.set_secure(self.secure);
+        if let Some(ref domain) = self.domain {
+            cookie.set_domain(domain.clone());
+        }
+        if let Some(same_site) = self.same_site {
+            cookie.set_same_site(same_site);
+        }
+        cookie
+    }
+
+    /// Load issued token
+    fn load<Err>(&self, req: &WebRequest<Err>) -> Option<CsrfToken> {
+        match self.storage {
+            Storage::Cookie => req
+                .cookie(&self.name)
+                .filter(|c| !c.value().is_empty())
+                .map(|c| CsrfToken(c.value().into())),
+            #[cfg(feature = "secure-cookies")]
+            Storage::Session => req
+                .extensions()
+                .get::<Session>()
+                .and_then(|s| s.get::<String>(SESSION_KEY).ok())
+                .and_then(|t| t)
+                .map(|t| CsrfToken(t.into())),
+        }
+    }
+
+    /// Store new token, returns cookie that must be set on the response
+    #[cfg_attr(not(feature = "secure-cookies"), allow(unused_variables))]
+    fn store<Err>(
+        &self,
+        req: &WebRequest<Err>,
+        token: &CsrfToken,
+    ) -> Option<Cookie<'static>> {
+        match self.storage {
+            Storage::Cookie => Some(self.cookie(token)),
+            #[cfg(feature = "secure-cookies")]
+            Storage::Session => {
+                if let Some(session) = req.extensions().get::<Session>() {
+                    if let Err(e) = session.set(SESSION_KEY, token.as_str()) {
+                        log::error!("Cannot store CSRF token: {}", e);
+                    }
+                } else {
+                    log::error!("Session is not available, register SessionManager");
+                }
+                None
+            }
+        }
+    }
+}
+
+impl<Err> Default for Csrf<Err> {
+    fn default() -> Self {
+        Csrf::double_submit()
+    }
+}
+
+impl<S, B, E> Transform<S> for Csrf<E>
+where
+    S: Service<Request = WebRequest<E>, Response = WebResponse<B>>,
+    E: ErrorRenderer,
+    CsrfError: Into<E::Container>,
+{
+    type Request = WebRequest<E>;
+    type Response = WebResponse<B>;
+    type Error = S::Error;
+    type InitError = ();
+    type Transform = CsrfMiddleware<S, E>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ok(CsrfMiddleware {
+            service,
+            inner: self.inner.clone(),
+            _t: PhantomData,
+        })
+    }
+}
+
+pub struct CsrfMiddleware<S, E> {
+    service: S,
+    inner: Rc<Inner>,
+    _t: PhantomData<E>,
+}
+
+impl<S, B, E> Service for CsrfMiddleware<S, E>
+where
+    S: Service<Request = WebRequest<E>, Response = WebResponse<B>>,
+    E: ErrorRenderer,
+    CsrfError: Into<E::Container>,
+{
+    type Request = WebRequest<E>;
+    type Response = WebResponse<B>;
+    type Error = S::Error;
+    type Future =
+        Either<CsrfResponse<S, B, E>, Ready<Result<Self::Response, Self::Error>>>;
+
+    #[inline]
+    fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(cx)
+    }
+
+    #[inline]
+    fn poll_shutdown(&self, cx: &mut Context<'_>, is_error: bool) -> Poll<()> {
+        self.service.poll_shutdown(cx, is_error)
+    }
+
+    fn call(&self, req: WebRequest<E>) -> Self::Future {
+        let token = self.inner.load(&req);
+
+        match *req.method() {
+            Method::GET | Method::HEAD | Method::OPTIONS | Method::TRACE => (),
+            _ => {
+                let valid = match (&token, req.headers().get(&self.inner.header)) {
+                    (Some(token), Some(val)) => {
+                        if token.verify(val.as_bytes()) {
+                            Ok(())
+                        } else {
+                            Err(CsrfError::Mismatch)
+                        }
+                    }
+                    _ => Err(CsrfError::Missing),
+                };
+                if let Err(e) = valid {
+                    log::debug!("CSRF validation failed: {}", e);
+                    return Either::Right(ok(req.error_response(e)));
+                }
+            }
+        }
+
+        let (token, cookie) = match token {
+            Some(token) => (token, None),
+            None => {
+                let token = CsrfToken::generate();
+                let cookie = self.inner.store(&req, &token);
+                (token, cookie)
+            }
+        };
+        req.extensions_mut().insert(token);
+
+        Either::Left(CsrfResponse {
+            fut: self.service.call(req),
+            cookie,
+            _t: PhantomData,
+        })
+    }
+}
+
+#[doc(hidden)]
+#[pin_project::pin_project]
+pub struct CsrfResponse<S: Service, B, E> {
+    #[pin]
+    fut: S::Future,
+    cookie: Option<Cookie<'static>>,
+    _t: PhantomData<(B, E)>,
+}
+
+impl<S, B, E> Future for CsrfResponse<S, B, E>
+where
+    S: Service<Request = WebRequest<E>, Response = WebResponse<B>>,
+{
+    type Output = Result<WebResponse<B>, S::Error>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        let mut res = futures::ready!(this.fut.poll(cx))?;
+
+        if let Some(cookie) = this.cookie.take() {
+            if let Err(e) = res.response_mut().add_cookie(&cookie) {
+                log::error!("Cannot set CSRF cookie: {}", e);
+            }
+        }
+        Poll::Ready(Ok(res))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::StatusCode;
+    use crate::web::test::{call_service, init_service, read_body, TestRequest};
+    use crate::web::{self, App, DefaultError, HttpResponse};
+
+    #[ntex_rt::test]
+    async fn test_double_submit() {
+        let srv = init_service(
+            App::new()
+                .wrap(Csrf::<DefaultError>::double_submit().cookie_secure(false))
+                .service(web::resource("/").to(|token: CsrfToken| async move {
+                    HttpResponse::Ok().body(token.to_string())
+                })),
+        )
+        .await;
+
+        // token is issued
+        let req = TestRequest::default().to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let cookie = resp.response().cookies().next().unwrap().into_owned();
+        assert_eq!(cookie.name(), "csrf-token");
+        assert_eq!(cookie.http_only(), None);
+        assert_eq!(cookie.same_site(), Some(SameSite::Strict));
+        let token = cookie.value().to_string();
+        assert_eq!(read_body(resp).await, token.as_bytes());
+
+        // existing token is not re-issued
+        let req = TestRequest::default().cookie(cookie.clone()).to_request();
+        let resp = call_service(&srv, req).await;
+        assert!(resp.response().cookies().next().is_none());
+        assert_eq!(read_body(resp).await, token.as_bytes());
+
+        // unsafe methods
+        let req = TestRequest::post().to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+
+        let req = TestRequest::post().cookie(cookie.clone()).to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+        assert_eq!(read_body(resp).await, "CSRF token is missing");
+
+        let req = TestRequest::post()
+            .cookie(cookie.clone())
+            .header("x-csrf-token", "invalid")
+            .to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+        assert_eq!(read_body(resp).await, "CSRF token mismatch");
+
+        let req = TestRequest::with_uri("/")
+            .method(Method::DELETE)
+            .cookie(cookie)
+            .header("x-csrf-token", token.as_str())
+            .to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+    }
+
+    #[ntex_rt::test]
+    async fn test_token_extractor() {
+        let srv = init_service(App::new().service(
+            web::resource("/").to(|_: CsrfToken| async { HttpResponse::Ok() }),
+        ))
+        .await;
+        let req = TestRequest::default().to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+    }
+
+    #[cfg(feature = "secure-cookies")]
+    #[ntex_rt::test]
+    async fn test_session() {
+        use crate::web::middleware::{CookieSessionStore, SessionManager};
+
+        let srv = init_service(
+            App::new()
+                .wrap(Csrf::<DefaultError>::session())
+                .wrap(SessionManager::new(
+                    CookieSessionStore::signed(&[0; 32]).secure(false),
+                ))
+                .service(web::resource("/").to(|token: CsrfToken| async move {
+                    HttpResponse::Ok().body(token.to_string())
+                })),
+        )
+        .await;
+
+        let req = TestRequest::default().to_request();
+        let resp = call_service(&srv, req).await;
+        let cookie = resp.response().cookies().next().unwrap().into_owned();
+        assert_eq!(cookie.name(), "ntex-session");
+        let token = String::from_utf8(read_body(resp).await.to_vec()).unwrap();
+
+        let req = TestRequest::post()
+            .cookie(cookie.clone())
+            .header("x-csrf-token", token.as_str())
+            .to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(read_body(resp).await, token.as_bytes());
+
+        let req = TestRequest::post()
+            .cookie(cookie)
+            .header("x-csrf-token", "invalid")
+            .to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+    }
+}
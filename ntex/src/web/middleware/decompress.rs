@@ -0,0 +1,175 @@
+//! `Middleware` for decompressing request payload.
+use std::marker::PhantomData;
+use std::task::{Context, Poll};
+
+use futures::future::{ok, Either, Ready};
+
+use crate::http::encoding::Decoder;
+use crate::http::header::{ContentEncoding, CONTENT_ENCODING, CONTENT_LENGTH};
+use crate::http::{Payload, Response, StatusCode};
+use crate::service::{Service, Transform};
+use crate::web::dev::{WebRequest, WebResponse};
+
+/// `Middleware` for decompressing request payload.
+///
+/// Payloads with *gzip*, *deflate* or *br* content encoding get decoded
+/// before they reach extractors or handlers, `Content-Encoding` and
+/// `Content-Length` headers are removed from request. Requests with any
+/// other content encoding are rejected with *415 Unsupported Media Type*
+/// response.
+///
+/// ```rust
+/// use ntex::web::{self, middleware, App, HttpResponse};
+///
+/// fn main() {
+///     let app = App::new()
+///         .wrap(middleware::Decompress::default())
+///         .service(web::resource("/upload").to(|body: String| async move {
+///             HttpResponse::Ok().body(body)
+///         }));
+/// }
+/// ```
+pub struct Decompress<E> {
+    _t: PhantomData<E>,
+}
+
+impl<E> Decompress<E> {
+    /// Construct `Decompress` middleware.
+    pub fn new() -> Self {
+        Decompress { _t: PhantomData }
+    }
+}
+
+impl<E> Default for Decompress<E> {
+    fn default() -> Self {
+        Decompress::new()
+    }
+}
+
+impl<S, B, E> Transform<S> for Decompress<E>
+where
+    S: Service<Request = WebRequest<E>, Response = WebResponse<B>>,
+{
+    type Request = WebRequest<E>;
+    type Response = WebResponse<B>;
+    type Error = S::Error;
+    type InitError = ();
+    type Transform = DecompressMiddleware<S, E>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ok(DecompressMiddleware {
+            service,
+            _t: PhantomData,
+        })
+    }
+}
+
+pub struct DecompressMiddleware<S, E> {
+    service: S,
+    _t: PhantomData<E>,
+}
+
+impl<S, B, E> Service for DecompressMiddleware<S, E>
+where
+    S: Service<Request = WebRequest<E>, Response = WebResponse<B>>,
+{
+    type Request = WebRequest<E>;
+    type Response = WebResponse<B>;
+    type Error = S::Error;
+    type Future = Either<S::Future, Ready<Result<Self::Response, Self::Error>>>;
+
+    #[inline]
+    fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(cx)
+    }
+
+    #[inline]
+    fn poll_shutdown(&self, cx: &mut Context<'_>, is_error: bool) -> Poll<()> {
+        self.service.poll_shutdown(cx, is_error)
+    }
+
+    fn call(&self, mut req: WebRequest<E>) -> Self::Future {
+        let encoding = match req.headers().get(&CONTENT_ENCODING) {
+            Some(val) => {
+                let enc = val.to_str().unwrap_or("").trim();
+                match ContentEncoding::from(enc) {
+                    ContentEncoding::Identity
+                        if !enc.eq_ignore_ascii_case("identity") =>
+                    {
+                        log::debug!("Unsupported payload content encoding: {:?}", enc);
+                        let res = Response::new(StatusCode::UNSUPPORTED_MEDIA_TYPE);
+                        return Either::Right(ok(req.into_response(res.into_body())));
+                    }
+                    enc => enc,
+                }
+            }
+            None => return Either::Left(self.service.call(req)),
+        };
+
+        let headers = req.headers_mut();
+        headers.remove(&CONTENT_ENCODING);
+        if encoding != ContentEncoding::Identity {
+            headers.remove(&CONTENT_LENGTH);
+            let payload = Decoder::new(req.take_payload(), encoding);
+            req.set_payload(Payload::Stream(Box::pin(payload)));
+        }
+        Either::Left(self.service.call(req))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use bytes::Bytes;
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+    use futures::StreamExt;
+
+    use super::*;
+    use crate::web::test::{call_service, init_service, read_body, TestRequest};
+    use crate::web::{self, App, DefaultError, HttpRequest, HttpResponse};
+
+    #[ntex_rt::test]
+    async fn test_decompress() {
+        let srv =
+            init_service(App::new().wrap(Decompress::<DefaultError>::new()).service(
+                web::resource("/").to(
+                    |req: HttpRequest, mut payload: web::types::Payload| async move {
+                        assert!(req.headers().get(CONTENT_ENCODING).is_none());
+                        assert!(req.headers().get(CONTENT_LENGTH).is_none());
+                        let mut body = Vec::new();
+                        while let Some(chunk) = payload.next().await {
+                            body.extend_from_slice(&chunk.unwrap());
+                        }
+                        HttpResponse::Ok().body(body)
+                    },
+                ),
+            ))
+            .await;
+
+        let mut enc = GzEncoder::new(Vec::new(), Compression::default());
+        enc.write_all(b"hello world").unwrap();
+        let data = enc.finish().unwrap();
+
+        let req = TestRequest::with_header(CONTENT_ENCODING, "gzip")
+            .header(CONTENT_LENGTH, data.len())
+            .set_payload(data)
+            .to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(read_body(resp).await, Bytes::from_static(b"hello world"));
+
+        let req = TestRequest::with_header(CONTENT_ENCODING, "identity")
+            .set_payload("hello world")
+            .to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(read_body(resp).await, Bytes::from_static(b"hello world"));
+
+        let req = TestRequest::with_header(CONTENT_ENCODING, "compress")
+            .set_payload("hello world")
+            .to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+    }
+}
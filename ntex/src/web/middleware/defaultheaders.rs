@@ -8,12 +8,17 @@ use futures::future::{ok, FutureExt, LocalBoxFuture, Ready};
 
 use crate::http::error::HttpError;
 use crate::http::header::{HeaderMap, HeaderName, HeaderValue, CONTENT_TYPE};
+use crate::http::StatusCode;
 use crate::service::{Service, Transform};
 use crate::web::dev::{WebRequest, WebResponse};
+use crate::web::ErrorRenderer;
 
 /// `Middleware` for setting default response headers.
 ///
-/// This middleware does not set header if response headers already contains it.
+/// By default middleware does not set header if response headers already
+/// contains it, use `add_if_missing(false)` to append headers to every
+/// response. Header value could be computed from response status with
+/// `header_fn()` method.
 ///
 /// ```rust
 /// use ntex::http;
@@ -21,7 +26,17 @@ use crate::web::dev::{WebRequest, WebResponse};
 ///
 /// fn main() {
 ///     let app = App::new()
-///         .wrap(middleware::DefaultHeaders::new().header("X-Version", "0.2"))
+///         .wrap(
+///             middleware::DefaultHeaders::new()
+///                 .header("X-Version", "0.2")
+///                 .header_fn(http::header::CACHE_CONTROL, |status| {
+///                     if status.is_success() {
+///                         Some(http::header::HeaderValue::from_static("max-age=3600"))
+///                     } else {
+///                         None
+///                     }
+///                 }),
+///         )
 ///         .service(
 ///             web::resource("/test")
 ///                 .route(web::get().to(|| async { HttpResponse::Ok() }))
@@ -37,15 +52,23 @@ pub struct DefaultHeaders<E> {
 
 struct Inner {
     ct: bool,
+    missing: bool,
+    errors: bool,
     headers: HeaderMap,
+    computed: Vec<(HeaderName, HeaderFn)>,
 }
 
+type HeaderFn = Box<dyn Fn(StatusCode) -> Option<HeaderValue>>;
+
 impl<E> Default for DefaultHeaders<E> {
     fn default() -> Self {
         DefaultHeaders {
             inner: Rc::new(Inner {
                 ct: false,
+                missing: true,
+                errors: false,
                 headers: HeaderMap::new(),
+                computed: Vec::new(),
             }),
             _t: PhantomData,
         }
@@ -83,6 +106,47 @@ impl<E> DefaultHeaders<E> {
         self
     }
 
+    /// Set a header with value computed from response status.
+    ///
+    /// Header is not set if function returns `None`.
+    pub fn header_fn<K, F>(mut self, key: K, f: F) -> Self
+    where
+        HeaderName: TryFrom<K>,
+        <HeaderName as TryFrom<K>>::Error: Into<HttpError>,
+        F: Fn(StatusCode) -> Option<HeaderValue> + 'static,
+    {
+        match HeaderName::try_from(key) {
+            Ok(key) => Rc::get_mut(&mut self.inner)
+                .expect("Multiple copies exist")
+                .computed
+                .push((key, Box::new(f))),
+            Err(_) => panic!("Can not create header name"),
+        }
+        self
+    }
+
+    /// Set headers only if response does not contain them, by default enabled.
+    ///
+    /// If disabled, headers are appended to response headers.
+    pub fn add_if_missing(mut self, enabled: bool) -> Self {
+        Rc::get_mut(&mut self.inner)
+            .expect("Multiple copies exist")
+            .missing = enabled;
+        self
+    }
+
+    /// Render errors of inner services, by default disabled.
+    ///
+    /// Errors are rendered to responses, so responses for errors raised
+    /// deep in the stack get default headers as well. If disabled, errors
+    /// are passed to outer services as is.
+    pub fn render_errors(mut self, enabled: bool) -> Self {
+        Rc::get_mut(&mut self.inner)
+            .expect("Multiple copies exist")
+            .errors = enabled;
+        self
+    }
+
     /// Set *CONTENT-TYPE* header if response does not contain this header.
     pub fn content_type(mut self) -> Self {
         Rc::get_mut(&mut self.inner)
@@ -92,10 +156,22 @@ impl<E> DefaultHeaders<E> {
     }
 }
 
+impl Inner {
+    fn set(&self, headers: &mut HeaderMap, key: &HeaderName, value: HeaderValue) {
+        if !self.missing {
+            headers.append(key.clone(), value);
+        } else if !headers.contains_key(key) {
+            headers.insert(key.clone(), value);
+        }
+    }
+}
+
 impl<S, B, E> Transform<S> for DefaultHeaders<E>
 where
     S: Service<Request = WebRequest<E>, Response = WebResponse<B>>,
     S::Future: 'static,
+    S::Error: Into<E::Container>,
+    E: ErrorRenderer,
 {
     type Request = WebRequest<E>;
     type Response = WebResponse<B>;
@@ -123,6 +199,8 @@ impl<S, B, E> Service for DefaultHeadersMiddleware<S, E>
 where
     S: Service<Request = WebRequest<E>, Response = WebResponse<B>>,
     S::Future: 'static,
+    S::Error: Into<E::Container>,
+    E: ErrorRenderer,
 {
     type Request = WebRequest<E>;
     type Response = WebResponse<B>;
@@ -141,15 +219,30 @@ where
 
     fn call(&self, req: WebRequest<E>) -> Self::Future {
         let inner = self.inner.clone();
+        let detached = if inner.errors {
+            Some(req.detach())
+        } else {
+            None
+        };
         let fut = self.service.call(req);
 
         async move {
-            let mut res = fut.await?;
+            let mut res = match fut.await {
+                Ok(res) => res,
+                Err(e) => match detached {
+                    Some(req) => req.error_response::<E, B>(e.into()),
+                    None => return Err(e),
+                },
+            };
 
             // set response headers
             for (key, value) in inner.headers.iter() {
-                if !res.headers().contains_key(key) {
-                    res.headers_mut().insert(key.clone(), value.clone());
+                inner.set(res.headers_mut(), key, value.clone());
+            }
+            let status = res.status();
+            for (key, f) in inner.computed.iter() {
+                if let Some(value) = f(status) {
+                    inner.set(res.headers_mut(), key, value);
                 }
             }
             // default content-type
@@ -167,10 +260,10 @@ where
 
 #[cfg(test)]
 mod tests {
-    use futures::future::ok;
+    use futures::future::{err, ok};
 
     use super::*;
-    use crate::http::header::CONTENT_TYPE;
+    use crate::http::header::{CACHE_CONTROL, CONTENT_TYPE};
     use crate::service::IntoService;
     use crate::web::request::WebRequest;
     use crate::web::test::{ok_service, TestRequest};
@@ -222,4 +315,76 @@ mod tests {
             "application/octet-stream"
         );
     }
+
+    #[ntex_rt::test]
+    async fn test_render_errors() {
+        let srv = |_: WebRequest<DefaultError>| {
+            err::<WebResponse, _>(Error::from(
+                crate::web::error::ErrorServiceUnavailable::<_, DefaultError>(
+                    "unavailable",
+                ),
+            ))
+        };
+        let mw = DefaultHeaders::<DefaultError>::new()
+            .header(CACHE_CONTROL, "no-store")
+            .render_errors(true)
+            .new_transform(srv.into_service())
+            .await
+            .unwrap();
+
+        let req = TestRequest::with_uri("/test").to_srv_request();
+        let resp = mw.call(req).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(resp.request().path(), "/test");
+        assert_eq!(resp.headers().get(CACHE_CONTROL).unwrap(), "no-store");
+
+        let mw = DefaultHeaders::<DefaultError>::new()
+            .header(CACHE_CONTROL, "no-store")
+            .new_transform(srv.into_service())
+            .await
+            .unwrap();
+        let req = TestRequest::default().to_srv_request();
+        assert!(mw.call(req).await.is_err());
+    }
+
+    #[ntex_rt::test]
+    async fn test_header_fn() {
+        let srv = |req: WebRequest<DefaultError>| {
+            let status = if req.path() == "/" {
+                StatusCode::OK
+            } else {
+                StatusCode::NOT_FOUND
+            };
+            ok::<_, Error>(
+                req.into_response(
+                    HttpResponse::build(status)
+                        .header(CACHE_CONTROL, "no-transform")
+                        .finish(),
+                ),
+            )
+        };
+        let mw = DefaultHeaders::<DefaultError>::new()
+            .add_if_missing(false)
+            .header_fn(CACHE_CONTROL, |status| {
+                if status.is_success() {
+                    Some(HeaderValue::from_static("max-age=3600"))
+                } else {
+                    None
+                }
+            })
+            .new_transform(srv.into_service())
+            .await
+            .unwrap();
+
+        let req = TestRequest::default().to_srv_request();
+        let resp = mw.call(req).await.unwrap();
+        let mut values: Vec<_> = resp.headers().get_all(CACHE_CONTROL).collect();
+        values.sort();
+        assert_eq!(values, vec!["max-age=3600", "no-transform"]);
+
+        let req = TestRequest::with_uri("/missing").to_srv_request();
+        let resp = mw.call(req).await.unwrap();
+        let values: Vec<_> = resp.headers().get_all(CACHE_CONTROL).collect();
+        assert_eq!(values, vec!["no-transform"]);
+    }
 }
@@ -0,0 +1,263 @@
+//! Middleware for status code specific response rewriting
+use std::collections::HashMap;
+use std::future::Future;
+use std::marker::PhantomData;
+use std::rc::Rc;
+use std::task::{Context, Poll};
+
+use futures::future::{ok, FutureExt, LocalBoxFuture, Ready};
+
+use crate::http::StatusCode;
+use crate::service::{Service, Transform};
+use crate::web::dev::{WebRequest, WebResponse};
+use crate::web::ErrorRenderer;
+
+type Handler<B> = Box<dyn Fn(WebResponse<B>) -> LocalBoxFuture<'static, WebResponse<B>>>;
+
+/// `Middleware` for status code specific response rewriting.
+///
+/// Handler is an async function that receives response with registered
+/// status code and returns new response, for example custom *404 Not Found*
+/// page. Default handler is called for client and server error responses
+/// that do not have specific handler. Handlers see responses produced by
+/// inner services and middlewares, errors of inner services are rendered
+/// to responses before handlers get called.
+///
+/// ```rust
+/// use ntex::http::{header, StatusCode};
+/// use ntex::web::{self, middleware, App, HttpResponse};
+///
+/// fn main() {
+///     let app = App::new()
+///         .wrap(
+///             middleware::ErrorHandlers::new()
+///                 .handler(StatusCode::NOT_FOUND, |res: web::dev::WebResponse| async move {
+///                     res.into_response(
+///                         HttpResponse::NotFound()
+///                             .content_type("text/html")
+///                             .body("<h1>Page not found</h1>"),
+///                     )
+///                 })
+///                 .default_handler(|mut res: web::dev::WebResponse| async move {
+///                     res.headers_mut().insert(
+///                         header::CACHE_CONTROL,
+///                         header::HeaderValue::from_static("no-store"),
+///                     );
+///                     res
+///                 }),
+///         )
+///         .service(web::resource("/test").to(|| async { HttpResponse::Ok() }));
+/// }
+/// ```
+pub struct ErrorHandlers<B, E> {
+    inner: Rc<Inner<B>>,
+    _t: PhantomData<E>,
+}
+
+struct Inner<B> {
+    handlers: HashMap<StatusCode, Handler<B>>,
+    default: Option<Handler<B>>,
+}
+
+impl<B, E> Default for ErrorHandlers<B, E> {
+    fn default() -> Self {
+        ErrorHandlers {
+            inner: Rc::new(Inner {
+                handlers: HashMap::new(),
+                default: None,
+            }),
+            _t: PhantomData,
+        }
+    }
+}
+
+impl<B, E> ErrorHandlers<B, E> {
+    /// Construct new `ErrorHandlers` instance
+    pub fn new() -> Self {
+        ErrorHandlers::default()
+    }
+
+    /// Register handler for specified status code
+    pub fn handler<F, R>(mut self, status: StatusCode, f: F) -> Self
+    where
+        F: Fn(WebResponse<B>) -> R + 'static,
+        R: Future<Output = WebResponse<B>> + 'static,
+    {
+        Rc::get_mut(&mut self.inner)
+            .expect("Multiple copies exist")
+            .handlers
+            .insert(status, Box::new(move |res| f(res).boxed_local()));
+        self
+    }
+
+    /// Register handler for client and server error responses without
+    /// specific handler
+    pub fn default_handler<F, R>(mut self, f: F) -> Self
+    where
+        F: Fn(WebResponse<B>) -> R + 'static,
+        R: Future<Output = WebResponse<B>> + 'static,
+    {
+        Rc::get_mut(&mut self.inner)
+            .expect("Multiple copies exist")
+            .default = Some(Box::new(move |res| f(res).boxed_local()));
+        self
+    }
+}
+
+impl<B> Inner<B> {
+    fn get(&self, status: StatusCode) -> Option<&Handler<B>> {
+        self.handlers.get(&status).or_else(|| {
+            if status.is_client_error() || status.is_server_error() {
+                self.default.as_ref()
+            } else {
+                None
+            }
+        })
+    }
+}
+
+impl<S, B, E> Transform<S> for ErrorHandlers<B, E>
+where
+    S: Service<Request = WebRequest<E>, Response = WebResponse<B>>,
+    S::Future: 'static,
+    S::Error: Into<E::Container>,
+    B: 'static,
+    E: ErrorRenderer,
+{
+    type Request = WebRequest<E>;
+    type Response = WebResponse<B>;
+    type Error = S::Error;
+    type InitError = ();
+    type Transform = ErrorHandlersMiddleware<S, B, E>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ok(ErrorHandlersMiddleware {
+            service,
+            inner: self.inner.clone(),
+            _t: PhantomData,
+        })
+    }
+}
+
+pub struct ErrorHandlersMiddleware<S, B, E> {
+    service: S,
+    inner: Rc<Inner<B>>,
+    _t: PhantomData<E>,
+}
+
+impl<S, B, E> Service for ErrorHandlersMiddleware<S, B, E>
+where
+    S: Service<Request = WebRequest<E>, Response = WebResponse<B>>,
+    S::Future: 'static,
+    S::Error: Into<E::Container>,
+    B: 'static,
+    E: ErrorRenderer,
+{
+    type Request = WebRequest<E>;
+    type Response = WebResponse<B>;
+    type Error = S::Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    #[inline]
+    fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(cx)
+    }
+
+    #[inline]
+    fn poll_shutdown(&self, cx: &mut Context<'_>, is_error: bool) -> Poll<()> {
+        self.service.poll_shutdown(cx, is_error)
+    }
+
+    fn call(&self, req: WebRequest<E>) -> Self::Future {
+        let inner = self.inner.clone();
+        let detached = req.detach();
+        let fut = self.service.call(req);
+
+        async move {
+            let res = match fut.await {
+                Ok(res) => res,
+                Err(e) => detached.error_response::<E, B>(e.into()),
+            };
+            match inner.get(res.status()) {
+                Some(handler) => Ok(handler(res).await),
+                None => Ok(res),
+            }
+        }
+        .boxed_local()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::header::{HeaderValue, CONTENT_TYPE};
+    use crate::web::middleware::AllowedHosts;
+    use crate::web::test::{call_service, init_service, read_body, TestRequest};
+    use crate::web::{self, App, HttpResponse};
+
+    #[ntex_rt::test]
+    async fn test_handlers() {
+        let srv = init_service(
+            App::new()
+                .wrap(AllowedHosts::new().host("example.com"))
+                .wrap(
+                    ErrorHandlers::new()
+                        .handler(StatusCode::NOT_FOUND, |res: WebResponse| async move {
+                            res.into_response(
+                                HttpResponse::NotFound()
+                                    .content_type("text/html")
+                                    .body("<h1>Not found</h1>"),
+                            )
+                        })
+                        .default_handler(|mut res: WebResponse| async move {
+                            res.headers_mut()
+                                .insert(CONTENT_TYPE, HeaderValue::from_static("error"));
+                            res
+                        }),
+                )
+                .service(web::resource("/").to(|| async { HttpResponse::Ok() }))
+                .service(web::resource("/error").to(|| async {
+                    Err::<HttpResponse, _>(web::error::ErrorInternalServerError::<
+                        _,
+                        web::DefaultError,
+                    >("error"))
+                }))
+                .service(
+                    web::resource("/created")
+                        .to(|| async { HttpResponse::Created().finish() }),
+                ),
+        )
+        .await;
+
+        let req = TestRequest::with_uri("/missing")
+            .header("host", "example.com")
+            .to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+        assert_eq!(resp.headers().get(CONTENT_TYPE).unwrap(), "text/html");
+        assert_eq!(read_body(resp).await, "<h1>Not found</h1>");
+
+        let req = TestRequest::with_uri("/error")
+            .header("host", "example.com")
+            .to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(resp.headers().get(CONTENT_TYPE).unwrap(), "error");
+
+        // response produced by other middleware
+        let req = TestRequest::with_uri("/")
+            .header("host", "example.org")
+            .to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::MISDIRECTED_REQUEST);
+        assert_eq!(resp.headers().get(CONTENT_TYPE).unwrap(), "error");
+
+        let req = TestRequest::with_uri("/created")
+            .header("host", "example.com")
+            .to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::CREATED);
+        assert!(!resp.headers().contains_key(CONTENT_TYPE));
+    }
+}
@@ -0,0 +1,410 @@
+//! Middleware for entity tags and conditional requests
+use std::marker::PhantomData;
+use std::rc::Rc;
+use std::task::{Context, Poll};
+
+use bytes::BytesMut;
+use futures::future::{ok, poll_fn, FutureExt, LocalBoxFuture, Ready};
+use sha2::{Digest, Sha256};
+use time::PrimitiveDateTime;
+
+use crate::http::body::{Body, BodySize, MessageBody, ResponseBody};
+use crate::http::header::{self, HeaderMap, HeaderValue};
+use crate::http::{Method, Response, StatusCode};
+use crate::service::{Service, Transform};
+use crate::web::dev::{WebRequest, WebResponse};
+
+/// `Middleware` for entity tags and conditional `GET` requests.
+///
+/// Middleware computes entity tag of buffered *200 OK* responses for `GET`
+/// and `HEAD` requests, entity tag is a hash of response body. Responses
+/// with streaming bodies or bodies larger than max size are not buffered.
+/// Entity tag set by handler is not overridden.
+///
+/// If request's *If-None-Match* header matches entity tag, or if request
+/// does not have *If-None-Match* header and response is not modified since
+/// date in *If-Modified-Since* header, *304 Not Modified* response with
+/// empty body is returned. Modification date is taken from response's
+/// *Last-Modified* header.
+///
+/// ```rust
+/// use ntex::web::{self, middleware, App, HttpResponse};
+///
+/// fn main() {
+///     let app = App::new()
+///         .wrap(middleware::ETag::new().weak(true))
+///         .service(web::resource("/test").to(|| async { HttpResponse::Ok() }));
+/// }
+/// ```
+pub struct ETag<E> {
+    inner: Rc<Inner>,
+    _t: PhantomData<E>,
+}
+
+struct Inner {
+    weak: bool,
+    max_size: usize,
+}
+
+impl<E> Default for ETag<E> {
+    fn default() -> Self {
+        ETag {
+            inner: Rc::new(Inner {
+                weak: false,
+                max_size: 1_048_576,
+            }),
+            _t: PhantomData,
+        }
+    }
+}
+
+impl<E> ETag<E> {
+    /// Construct `ETag` middleware
+    pub fn new() -> Self {
+        ETag::default()
+    }
+
+    /// Generate weak entity tags, by default entity tags are strong.
+    pub fn weak(mut self, weak: bool) -> Self {
+        Rc::get_mut(&mut self.inner)
+            .expect("Multiple copies exist")
+            .weak = weak;
+        self
+    }
+
+    /// Set max size of buffered response body.
+    ///
+    /// By default max size is 1Mb.
+    pub fn max_size(mut self, size: usize) -> Self {
+        Rc::get_mut(&mut self.inner)
+            .expect("Multiple copies exist")
+            .max_size = size;
+        self
+    }
+}
+
+impl Inner {
+    fn buffered(&self, size: BodySize) -> bool {
+        match size {
+            BodySize::Sized(size) => size <= self.max_size,
+            BodySize::Sized64(size) => size <= self.max_size as u64,
+            _ => false,
+        }
+    }
+
+    fn tag(&self, body: &[u8]) -> String {
+        let digest = Sha256::digest(body);
+        let tag = base64::encode_config(&digest[..16], base64::URL_SAFE_NO_PAD);
+        if self.weak {
+            format!("W/\"{}\"", tag)
+        } else {
+            format!("\"{}\"", tag)
+        }
+    }
+}
+
+impl<S, B, E> Transform<S> for ETag<E>
+where
+    S: Service<Request = WebRequest<E>, Response = WebResponse<B>>,
+    S::Future: 'static,
+    B: MessageBody + 'static,
+{
+    type Request = WebRequest<E>;
+    type Response = WebResponse;
+    type Error = S::Error;
+    type InitError = ();
+    type Transform = ETagMiddleware<S, E>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ok(ETagMiddleware {
+            service,
+            inner: self.inner.clone(),
+            _t: PhantomData,
+        })
+    }
+}
+
+pub struct ETagMiddleware<S, E> {
+    service: S,
+    inner: Rc<Inner>,
+    _t: PhantomData<E>,
+}
+
+impl<S, B, E> Service for ETagMiddleware<S, E>
+where
+    S: Service<Request = WebRequest<E>, Response = WebResponse<B>>,
+    S::Future: 'static,
+    B: MessageBody + 'static,
+{
+    type Request = WebRequest<E>;
+    type Response = WebResponse;
+    type Error = S::Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    #[inline]
+    fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(cx)
+    }
+
+    #[inline]
+    fn poll_shutdown(&self, cx: &mut Context<'_>, is_error: bool) -> Poll<()> {
+        self.service.poll_shutdown(cx, is_error)
+    }
+
+    fn call(&self, req: WebRequest<E>) -> Self::Future {
+        let inner = self.inner.clone();
+        let conditional = req.method() == Method::GET || req.method() == Method::HEAD;
+        let fut = self.service.call(req);
+
+        async move {
+            let mut res = fut.await?;
+            if !conditional || res.status() != StatusCode::OK {
+                return Ok(res
+                    .map_body(|_, body| ResponseBody::Other(Body::from_message(body))));
+            }
+
+            let res = if !res.headers().contains_key(header::ETAG)
+                && inner.buffered(res.response().body().size())
+            {
+                let mut body = res.take_body();
+                let mut buf = BytesMut::new();
+                while let Some(chunk) = poll_fn(|cx| body.poll_next_chunk(cx)).await {
+                    match chunk {
+                        Ok(chunk) => buf.extend_from_slice(&chunk),
+                        Err(e) => {
+                            log::error!("Cannot read response body: {}", e);
+                            let res = res
+                                .into_response(Response::InternalServerError().finish());
+                            return Ok(res);
+                        }
+                    }
+                }
+                if let Ok(tag) = HeaderValue::from_str(&inner.tag(&buf)) {
+                    res.headers_mut().insert(header::ETAG, tag);
+                }
+                res.map_body(|_, _| ResponseBody::Other(Body::Bytes(buf.freeze())))
+            } else {
+                res.map_body(|_, body| ResponseBody::Other(Body::from_message(body)))
+            };
+
+            if is_modified(res.request().headers(), res.headers()) {
+                Ok(res)
+            } else {
+                let mut not_modified = Response::new(StatusCode::NOT_MODIFIED);
+                for (name, value) in res.headers() {
+                    if name != header::CONTENT_LENGTH {
+                        not_modified
+                            .headers_mut()
+                            .append(name.clone(), value.clone());
+                    }
+                }
+                Ok(res.into_response(not_modified))
+            }
+        }
+        .boxed_local()
+    }
+}
+
+/// Evaluate *If-None-Match* and *If-Modified-Since* preconditions
+pub(crate) fn is_modified(req: &HeaderMap, res: &HeaderMap) -> bool {
+    if let Some(val) = req.get(header::IF_NONE_MATCH) {
+        let tag = res.get(header::ETAG).and_then(|v| v.to_str().ok());
+        return match (val.to_str(), tag) {
+            (Ok(val), Some(tag)) => !etag_matches(val, tag),
+            (Ok(val), None) => val.trim() != "*",
+            _ => true,
+        };
+    }
+
+    let since = req.get(header::IF_MODIFIED_SINCE).and_then(http_date);
+    let modified = res.get(header::LAST_MODIFIED).and_then(http_date);
+    match (since, modified) {
+        (Some(since), Some(modified)) => modified > since,
+        _ => true,
+    }
+}
+
+/// Weak comparison of entity tag against *If-None-Match* list
+fn etag_matches(list: &str, tag: &str) -> bool {
+    let opaque = |s: &str| {
+        let s = s.trim();
+        let s = if s.starts_with("W/") { &s[2..] } else { s };
+        if s.len() >= 2 && s.starts_with('"') && s.ends_with('"') {
+            Some(s[1..s.len() - 1].to_string())
+        } else {
+            None
+        }
+    };
+    let tag = match opaque(tag) {
+        Some(tag) => tag,
+        None => return false,
+    };
+
+    let mut rest = list.trim();
+    while !rest.is_empty() {
+        if rest.starts_with('*') {
+            return true;
+        }
+        let start = if rest.starts_with("W/") { 2 } else { 0 };
+        if !rest[start..].starts_with('"') {
+            return false;
+        }
+        let end = match rest[start + 1..].find('"') {
+            Some(idx) => start + idx + 2,
+            None => return false,
+        };
+        if opaque(&rest[..end]).as_ref() == Some(&tag) {
+            return true;
+        }
+        rest = rest[end..].trim_start_matches(|c: char| c == ',' || c.is_whitespace());
+    }
+    false
+}
+
+/// Parse IMF-fixdate, returns unix timestamp
+fn http_date(val: &HeaderValue) -> Option<i64> {
+    let val = val.to_str().ok()?.trim();
+    if !val.ends_with(" GMT") {
+        return None;
+    }
+    PrimitiveDateTime::parse(&val[..val.len() - 4], "%a, %d %b %Y %H:%M:%S")
+        .ok()
+        .map(|dt| dt.assume_utc().timestamp())
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::stream;
+
+    use super::*;
+    use crate::http::header::{ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED};
+    use crate::web::test::{call_service, init_service, read_body, TestRequest};
+    use crate::web::{self, App, DefaultError, HttpResponse};
+
+    #[test]
+    fn test_etag_matches() {
+        assert!(etag_matches("\"a\"", "\"a\""));
+        assert!(etag_matches("W/\"a\"", "\"a\""));
+        assert!(etag_matches("\"b\", W/\"a\"", "W/\"a\""));
+        assert!(etag_matches("\"a,b\",\"c\"", "\"c\""));
+        assert!(etag_matches("*", "\"a\""));
+        assert!(!etag_matches("\"b\", \"c\"", "\"a\""));
+        assert!(!etag_matches("a", "\"a\""));
+    }
+
+    #[test]
+    fn test_http_date() {
+        assert_eq!(
+            http_date(&HeaderValue::from_static("Thu, 01 Jan 1970 00:01:00 GMT")),
+            Some(60)
+        );
+        assert_eq!(http_date(&HeaderValue::from_static("Thursday")), None);
+    }
+
+    #[ntex_rt::test]
+    async fn test_etag() {
+        let srv = init_service(
+            App::new()
+                .wrap(ETag::<DefaultError>::new())
+                .service(
+                    web::resource("/").to(|| async { HttpResponse::Ok().body("body") }),
+                )
+                .service(web::resource("/tagged").to(|| async {
+                    HttpResponse::Ok()
+                        .header(ETAG, "\"v1\"")
+                        .header(LAST_MODIFIED, "Thu, 01 Jan 1970 00:01:00 GMT")
+                        .body("tagged")
+                }))
+                .service(web::resource("/stream").to(|| async {
+                    HttpResponse::Ok().streaming(stream::once(ok::<_, std::io::Error>(
+                        bytes::Bytes::from_static(b"stream"),
+                    )))
+                })),
+        )
+        .await;
+
+        let resp = call_service(&srv, TestRequest::default().to_request()).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let tag = resp.headers().get(ETAG).unwrap().clone();
+        assert!(tag.to_str().unwrap().starts_with('"'));
+        assert_eq!(read_body(resp).await, "body");
+
+        let req = TestRequest::default()
+            .header(IF_NONE_MATCH, tag.clone())
+            .to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::NOT_MODIFIED);
+        assert_eq!(resp.headers().get(ETAG).unwrap(), &tag);
+        assert!(read_body(resp).await.is_empty());
+
+        let req = TestRequest::default()
+            .method(Method::POST)
+            .header(IF_NONE_MATCH, tag)
+            .to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert!(!resp.headers().contains_key(ETAG));
+
+        // handler's entity tag
+        let req = TestRequest::with_uri("/tagged")
+            .header(IF_NONE_MATCH, "\"v0\", W/\"v1\"")
+            .to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::NOT_MODIFIED);
+
+        let req = TestRequest::with_uri("/tagged")
+            .header(IF_NONE_MATCH, "\"v0\"")
+            .header(IF_MODIFIED_SINCE, "Thu, 01 Jan 1970 00:01:00 GMT")
+            .to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let req = TestRequest::with_uri("/tagged")
+            .header(IF_MODIFIED_SINCE, "Thu, 01 Jan 1970 00:01:00 GMT")
+            .to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::NOT_MODIFIED);
+
+        let req = TestRequest::with_uri("/tagged")
+            .header(IF_MODIFIED_SINCE, "Thu, 01 Jan 1970 00:00:59 GMT")
+            .to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(read_body(resp).await, "tagged");
+
+        // streaming body is not buffered
+        let req = TestRequest::with_uri("/stream").to_request();
+        let resp = call_service(&srv, req).await;
+        assert!(!resp.headers().contains_key(ETAG));
+        assert_eq!(read_body(resp).await, "stream");
+    }
+
+    #[ntex_rt::test]
+    async fn test_weak_etag() {
+        let srv = init_service(
+            App::new()
+                .wrap(ETag::<DefaultError>::new().weak(true))
+                .service(
+                    web::resource("/").to(|| async { HttpResponse::Ok().body("body") }),
+                ),
+        )
+        .await;
+
+        let resp = call_service(&srv, TestRequest::default().to_request()).await;
+        let tag = resp
+            .headers()
+            .get(ETAG)
+            .unwrap()
+            .to_str()
+            .unwrap()
+            .to_string();
+        assert!(tag.starts_with("W/\""));
+
+        let req = TestRequest::default()
+            .header(IF_NONE_MATCH, &tag[2..])
+            .to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::NOT_MODIFIED);
+    }
+}
@@ -0,0 +1,178 @@
+//! Middleware from async function
+use std::future::Future;
+use std::marker::PhantomData;
+use std::rc::Rc;
+use std::task::{Context, Poll};
+
+use futures::future::{ok, Ready};
+
+use crate::http::body::Body;
+use crate::service::boxed::{self, BoxFuture, BoxService};
+use crate::service::{Service, Transform};
+use crate::web::dev::{WebRequest, WebResponse};
+use crate::web::ErrorRenderer;
+
+/// Create `Middleware` from async function.
+///
+/// Function receives request and handle of the inner service, function
+/// could modify request before calling inner service and response after.
+/// Handle is cheap to clone and could be moved into async block.
+/// Middleware could be used with `App`, `Scope` and `Resource`.
+///
+/// ```rust
+/// use ntex::http::header::{HeaderValue, CONTENT_TYPE};
+/// use ntex::web::{self, middleware, App, HttpResponse};
+///
+/// fn main() {
+///     let app = App::new()
+///         .wrap(middleware::from_fn(|req, next| async move {
+///             let mut res = next.call(req).await?;
+///             res.headers_mut()
+///                 .insert(CONTENT_TYPE, HeaderValue::from_static("text/plain"));
+///             Ok(res)
+///         }))
+///         .service(web::resource("/test").to(|| async { HttpResponse::Ok() }));
+/// }
+/// ```
+pub fn from_fn<F, R, Err, B, B1>(f: F) -> FromFn<F, Err, B>
+where
+    F: Fn(WebRequest<Err>, Next<Err, B>) -> R,
+    R: Future<Output = Result<WebResponse<B1>, Err::Container>>,
+    Err: ErrorRenderer,
+{
+    FromFn {
+        f: Rc::new(f),
+        _t: PhantomData,
+    }
+}
+
+/// Middleware created from async function, check
+/// [`from_fn()`](fn.from_fn.html)
+pub struct FromFn<F, Err, B> {
+    f: Rc<F>,
+    _t: PhantomData<(Err, B)>,
+}
+
+impl<F, Err, B> Clone for FromFn<F, Err, B> {
+    fn clone(&self) -> Self {
+        FromFn {
+            f: self.f.clone(),
+            _t: PhantomData,
+        }
+    }
+}
+
+/// Handle of the inner service
+pub struct Next<Err: ErrorRenderer, B = Body>(
+    Rc<BoxService<WebRequest<Err>, WebResponse<B>, Err::Container>>,
+);
+
+impl<Err: ErrorRenderer, B> Clone for Next<Err, B> {
+    fn clone(&self) -> Self {
+        Next(self.0.clone())
+    }
+}
+
+impl<Err: ErrorRenderer, B> Next<Err, B> {
+    /// Call inner service
+    pub fn call(
+        &self,
+        req: WebRequest<Err>,
+    ) -> BoxFuture<WebResponse<B>, Err::Container> {
+        self.0.call(req)
+    }
+}
+
+impl<S, F, R, Err, B, B1> Transform<S> for FromFn<F, Err, B>
+where
+    S: Service<
+            Request = WebRequest<Err>,
+            Response = WebResponse<B>,
+            Error = Err::Container,
+        > + 'static,
+    S::Future: 'static,
+    F: Fn(WebRequest<Err>, Next<Err, B>) -> R,
+    R: Future<Output = Result<WebResponse<B1>, Err::Container>>,
+    Err: ErrorRenderer,
+{
+    type Request = WebRequest<Err>;
+    type Response = WebResponse<B1>;
+    type Error = Err::Container;
+    type InitError = ();
+    type Transform = FromFnMiddleware<F, Err, B>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ok(FromFnMiddleware {
+            next: Next(Rc::new(boxed::service(service))),
+            f: self.f.clone(),
+        })
+    }
+}
+
+pub struct FromFnMiddleware<F, Err: ErrorRenderer, B> {
+    next: Next<Err, B>,
+    f: Rc<F>,
+}
+
+impl<F, R, Err, B, B1> Service for FromFnMiddleware<F, Err, B>
+where
+    F: Fn(WebRequest<Err>, Next<Err, B>) -> R,
+    R: Future<Output = Result<WebResponse<B1>, Err::Container>>,
+    Err: ErrorRenderer,
+{
+    type Request = WebRequest<Err>;
+    type Response = WebResponse<B1>;
+    type Error = Err::Container;
+    type Future = R;
+
+    #[inline]
+    fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.next.0.poll_ready(cx)
+    }
+
+    #[inline]
+    fn poll_shutdown(&self, cx: &mut Context<'_>, is_error: bool) -> Poll<()> {
+        self.next.0.poll_shutdown(cx, is_error)
+    }
+
+    fn call(&self, req: WebRequest<Err>) -> Self::Future {
+        (self.f)(req, self.next.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::header::{HeaderValue, CONTENT_TYPE};
+    use crate::http::StatusCode;
+    use crate::web::test::{call_service, init_service, TestRequest};
+    use crate::web::{self, App, DefaultError, HttpResponse};
+
+    #[ntex_rt::test]
+    async fn test_from_fn() {
+        let srv = init_service(
+            App::new()
+                .wrap(from_fn(|req: WebRequest<DefaultError>, next| async move {
+                    if req.path() == "/forbidden" {
+                        return Ok(req.into_response(HttpResponse::Forbidden().finish()));
+                    }
+                    let mut res = next.call(req).await?;
+                    res.headers_mut()
+                        .insert(CONTENT_TYPE, HeaderValue::from_static("0001"));
+                    Ok(res)
+                }))
+                .service(web::resource("/").to(|| async { HttpResponse::Ok() })),
+        )
+        .await;
+
+        let resp = call_service(&srv, TestRequest::default().to_request()).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(resp.headers().get(CONTENT_TYPE).unwrap(), "0001");
+
+        let req = TestRequest::with_uri("/forbidden").to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+        assert!(!resp.headers().contains_key(CONTENT_TYPE));
+    }
+}
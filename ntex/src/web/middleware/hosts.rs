@@ -0,0 +1,262 @@
+//! Middleware for host header validation
+use std::marker::PhantomData;
+use std::rc::Rc;
+use std::task::{Context, Poll};
+
+use futures::future::{ok, Either, Ready};
+
+use crate::http::header::HOST;
+use crate::http::{Response, StatusCode};
+use crate::service::{Service, Transform};
+use crate::web::dev::{WebRequest, WebResponse};
+
+/// `Middleware` for host validation.
+///
+/// Middleware checks request's authority (uri authority for http/2 requests
+/// and *HOST* header for http/1 requests) against list of allowed hosts.
+/// Requests without host or with conflicting uri authority and *HOST* header
+/// are rejected with *400 Bad Request* response, requests for unknown hosts
+/// are rejected with *421 Misdirected Request* response. This protects
+/// application from DNS rebinding attacks and from generating links for
+/// attacker controlled hosts, for example in password reset emails.
+///
+/// Host could be exact name, `example.com`, or wildcard for sub-domains,
+/// `*.example.com`. Wildcard does not match parent domain itself. If allowed
+/// host contains port, request's port must match, otherwise port is ignored.
+/// Comparison is case insensitive.
+///
+/// *X-Forwarded-Host* and *Forwarded* headers are not checked.
+///
+/// ```rust
+/// use ntex::web::{self, middleware, App, HttpResponse};
+///
+/// fn main() {
+///     let app = App::new()
+///         .wrap(
+///             middleware::AllowedHosts::new()
+///                 .host("example.com")
+///                 .host("*.example.com")
+///                 .host("localhost:8080"),
+///         )
+///         .service(web::resource("/test").to(|| async { HttpResponse::Ok() }));
+/// }
+/// ```
+pub struct AllowedHosts<E> {
+    inner: Rc<Inner>,
+    _t: PhantomData<E>,
+}
+
+struct Inner {
+    hosts: Vec<String>,
+}
+
+impl<E> Default for AllowedHosts<E> {
+    fn default() -> Self {
+        AllowedHosts {
+            inner: Rc::new(Inner { hosts: Vec::new() }),
+            _t: PhantomData,
+        }
+    }
+}
+
+impl<E> AllowedHosts<E> {
+    /// Construct `AllowedHosts` middleware, all hosts are rejected.
+    pub fn new() -> Self {
+        AllowedHosts::default()
+    }
+
+    /// Add allowed host.
+    pub fn host(mut self, host: &str) -> Self {
+        Rc::get_mut(&mut self.inner)
+            .expect("Multiple copies exist")
+            .hosts
+            .push(host.trim().trim_end_matches('.').to_lowercase());
+        self
+    }
+}
+
+/// Split host into name and optional port
+fn split_port(host: &str) -> (&str, Option<&str>) {
+    if host.starts_with('[') {
+        // ipv6 literal
+        if let Some(pos) = host.find(']') {
+            let port = &host[pos + 1..];
+            if port.starts_with(':') {
+                return (&host[..=pos], Some(&port[1..]));
+            }
+            return (&host[..=pos], None);
+        }
+        return (host, None);
+    }
+    match host.rfind(':') {
+        Some(pos) => (&host[..pos], Some(&host[pos + 1..])),
+        None => (host, None),
+    }
+}
+
+impl Inner {
+    fn is_allowed(&self, host: &str) -> bool {
+        let host = host.to_lowercase();
+        let (name, port) = split_port(&host);
+        let name = name.trim_end_matches('.');
+
+        self.hosts.iter().any(|allowed| {
+            let (pattern, allowed_port) = split_port(allowed);
+            if allowed_port.is_some() && allowed_port != port {
+                return false;
+            }
+            if pattern.starts_with("*.") {
+                let suffix = &pattern[2..];
+                name.len() > suffix.len() + 1
+                    && name.ends_with(suffix)
+                    && name.as_bytes()[name.len() - suffix.len() - 1] == b'.'
+            } else {
+                name == pattern
+            }
+        })
+    }
+
+    fn check<E>(&self, req: &WebRequest<E>) -> Result<(), StatusCode> {
+        let host = req.headers().get(HOST).map(|h| h.to_str());
+        let authority = req.uri().authority().map(|a| a.as_str());
+
+        let host = match (authority, host) {
+            (Some(authority), Some(Ok(host))) => {
+                if !authority.eq_ignore_ascii_case(host) {
+                    return Err(StatusCode::BAD_REQUEST);
+                }
+                authority
+            }
+            (Some(authority), None) => authority,
+            (None, Some(Ok(host))) => host,
+            _ => return Err(StatusCode::BAD_REQUEST),
+        };
+
+        if self.is_allowed(host) {
+            Ok(())
+        } else {
+            Err(StatusCode::MISDIRECTED_REQUEST)
+        }
+    }
+}
+
+impl<S, B, E> Transform<S> for AllowedHosts<E>
+where
+    S: Service<Request = WebRequest<E>, Response = WebResponse<B>>,
+{
+    type Request = WebRequest<E>;
+    type Response = WebResponse<B>;
+    type Error = S::Error;
+    type InitError = ();
+    type Transform = AllowedHostsMiddleware<S, E>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ok(AllowedHostsMiddleware {
+            service,
+            inner: self.inner.clone(),
+            _t: PhantomData,
+        })
+    }
+}
+
+pub struct AllowedHostsMiddleware<S, E> {
+    service: S,
+    inner: Rc<Inner>,
+    _t: PhantomData<E>,
+}
+
+impl<S, B, E> Service for AllowedHostsMiddleware<S, E>
+where
+    S: Service<Request = WebRequest<E>, Response = WebResponse<B>>,
+{
+    type Request = WebRequest<E>;
+    type Response = WebResponse<B>;
+    type Error = S::Error;
+    type Future = Either<S::Future, Ready<Result<Self::Response, Self::Error>>>;
+
+    #[inline]
+    fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(cx)
+    }
+
+    #[inline]
+    fn poll_shutdown(&self, cx: &mut Context<'_>, is_error: bool) -> Poll<()> {
+        self.service.poll_shutdown(cx, is_error)
+    }
+
+    fn call(&self, req: WebRequest<E>) -> Self::Future {
+        match self.inner.check(&req) {
+            Ok(_) => Either::Left(self.service.call(req)),
+            Err(status) => {
+                log::debug!("Rejected request for host {:?}", req.headers().get(HOST));
+                let res = Response::new(status);
+                Either::Right(ok(req.into_response(res.into_body())))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::web::test::{ok_service, TestRequest};
+    use crate::web::DefaultError;
+
+    #[ntex_rt::test]
+    async fn test_allowed_hosts() {
+        let mw = AllowedHosts::<DefaultError>::new()
+            .host("example.com")
+            .host("*.Example.org")
+            .host("localhost:8080")
+            .host("[::1]")
+            .new_transform(ok_service())
+            .await
+            .unwrap();
+
+        for host in &[
+            "example.com",
+            "EXAMPLE.com:443",
+            "example.com.",
+            "a.example.org",
+            "a.b.example.org:8443",
+            "localhost:8080",
+            "[::1]:8080",
+        ] {
+            let req = TestRequest::default().header(HOST, *host).to_srv_request();
+            let resp = mw.call(req).await.unwrap();
+            assert_eq!(resp.status(), StatusCode::OK, "{}", host);
+        }
+
+        for host in &[
+            "evil.com",
+            "example.com.evil.com",
+            "aexample.org",
+            "example.org",
+            "localhost",
+            "localhost:8081",
+            "[::2]",
+        ] {
+            let req = TestRequest::default().header(HOST, *host).to_srv_request();
+            let resp = mw.call(req).await.unwrap();
+            assert_eq!(resp.status(), StatusCode::MISDIRECTED_REQUEST, "{}", host);
+        }
+
+        let req = TestRequest::default().to_srv_request();
+        let resp = mw.call(req).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+
+        let req = TestRequest::default()
+            .uri("https://example.com/test")
+            .to_srv_request();
+        let resp = mw.call(req).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let req = TestRequest::default()
+            .uri("https://example.com/test")
+            .header(HOST, "evil.com")
+            .to_srv_request();
+        let resp = mw.call(req).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    }
+}
@@ -0,0 +1,267 @@
+//! Middleware for redirecting plain http requests to https
+use std::marker::PhantomData;
+use std::rc::Rc;
+use std::task::{Context, Poll};
+
+use futures::future::{ok, Either, Ready};
+
+use crate::http::header::LOCATION;
+use crate::http::{Response, StatusCode};
+use crate::service::{Service, Transform};
+use crate::web::dev::{WebRequest, WebResponse};
+
+/// `Middleware` for redirecting plain http requests to https.
+///
+/// Request's scheme and host are resolved with `ConnectionInfo`, so
+/// *Forwarded* and *X-Forwarded-Proto* headers set by reverse proxy are
+/// respected. Requests with `https` scheme are passed to inner service,
+/// other requests get *308 Permanent Redirect* response to the same host,
+/// path and query over https.
+///
+/// ```rust
+/// use ntex::web::{self, middleware, App, HttpResponse};
+///
+/// fn main() {
+///     let app = App::new()
+///         .wrap(
+///             middleware::HttpsRedirect::new()
+///                 .port(8443)
+///                 .exclude("/.well-known/acme-challenge/"),
+///         )
+///         .service(web::resource("/test").to(|| async { HttpResponse::Ok() }));
+/// }
+/// ```
+pub struct HttpsRedirect<E> {
+    inner: Rc<Inner>,
+    _t: PhantomData<E>,
+}
+
+struct Inner {
+    status: StatusCode,
+    host: Option<String>,
+    port: Option<u16>,
+    exclude: Vec<String>,
+}
+
+impl<E> Default for HttpsRedirect<E> {
+    fn default() -> Self {
+        HttpsRedirect {
+            inner: Rc::new(Inner {
+                status: StatusCode::PERMANENT_REDIRECT,
+                host: None,
+                port: None,
+                exclude: Vec::new(),
+            }),
+            _t: PhantomData,
+        }
+    }
+}
+
+impl<E> HttpsRedirect<E> {
+    /// Construct `HttpsRedirect` middleware.
+    pub fn new() -> Self {
+        HttpsRedirect::default()
+    }
+
+    /// Use *307 Temporary Redirect* response instead of permanent redirect.
+    pub fn temporary(mut self) -> Self {
+        Rc::get_mut(&mut self.inner)
+            .expect("Multiple copies exist")
+            .status = StatusCode::TEMPORARY_REDIRECT;
+        self
+    }
+
+    /// Redirect to fixed host instead of request's host.
+    pub fn host(mut self, host: &str) -> Self {
+        Rc::get_mut(&mut self.inner)
+            .expect("Multiple copies exist")
+            .host = Some(host.to_string());
+        self
+    }
+
+    /// Set https port, by default standard port is used.
+    pub fn port(mut self, port: u16) -> Self {
+        Rc::get_mut(&mut self.inner)
+            .expect("Multiple copies exist")
+            .port = if port == 443 { None } else { Some(port) };
+        self
+    }
+
+    /// Do not redirect requests with path prefix.
+    pub fn exclude(mut self, prefix: &str) -> Self {
+        Rc::get_mut(&mut self.inner)
+            .expect("Multiple copies exist")
+            .exclude
+            .push(prefix.to_string());
+        self
+    }
+}
+
+impl Inner {
+    /// Location of https resource, `None` if request must not be redirected
+    fn location<E>(&self, req: &WebRequest<E>) -> Option<String> {
+        if self
+            .exclude
+            .iter()
+            .any(|prefix| req.path().starts_with(prefix.as_str()))
+        {
+            return None;
+        }
+
+        let info = req.connection_info();
+        if info.scheme().eq_ignore_ascii_case("https") {
+            return None;
+        }
+
+        let host = match self.host {
+            Some(ref host) => host.as_str(),
+            None => strip_port(info.host()),
+        };
+        let mut location = format!("https://{}", host);
+        if let Some(port) = self.port {
+            location.push_str(&format!(":{}", port));
+        }
+        match req.uri().path_and_query() {
+            Some(pq) => location.push_str(pq.as_str()),
+            None => location.push('/'),
+        }
+        Some(location)
+    }
+}
+
+/// Remove port from host, ipv6 addresses are enclosed in brackets
+fn strip_port(host: &str) -> &str {
+    match host.rfind(':') {
+        Some(idx) if !host[idx..].contains(']') => &host[..idx],
+        _ => host,
+    }
+}
+
+impl<S, B, E> Transform<S> for HttpsRedirect<E>
+where
+    S: Service<Request = WebRequest<E>, Response = WebResponse<B>>,
+{
+    type Request = WebRequest<E>;
+    type Response = WebResponse<B>;
+    type Error = S::Error;
+    type InitError = ();
+    type Transform = HttpsRedirectMiddleware<S, E>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ok(HttpsRedirectMiddleware {
+            service,
+            inner: self.inner.clone(),
+            _t: PhantomData,
+        })
+    }
+}
+
+pub struct HttpsRedirectMiddleware<S, E> {
+    service: S,
+    inner: Rc<Inner>,
+    _t: PhantomData<E>,
+}
+
+impl<S, B, E> Service for HttpsRedirectMiddleware<S, E>
+where
+    S: Service<Request = WebRequest<E>, Response = WebResponse<B>>,
+{
+    type Request = WebRequest<E>;
+    type Response = WebResponse<B>;
+    type Error = S::Error;
+    type Future = Either<S::Future, Ready<Result<Self::Response, Self::Error>>>;
+
+    #[inline]
+    fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(cx)
+    }
+
+    #[inline]
+    fn poll_shutdown(&self, cx: &mut Context<'_>, is_error: bool) -> Poll<()> {
+        self.service.poll_shutdown(cx, is_error)
+    }
+
+    fn call(&self, req: WebRequest<E>) -> Self::Future {
+        match self.inner.location(&req) {
+            None => Either::Left(self.service.call(req)),
+            Some(location) => {
+                log::trace!("Redirect request to {:?}", location);
+                let res = Response::build(self.inner.status)
+                    .header(LOCATION, location)
+                    .finish();
+                Either::Right(ok(req.into_response(res.into_body())))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::header;
+    use crate::web::test::{ok_service, TestRequest};
+    use crate::web::DefaultError;
+
+    #[test]
+    fn test_strip_port() {
+        assert_eq!(strip_port("example.com"), "example.com");
+        assert_eq!(strip_port("example.com:8080"), "example.com");
+        assert_eq!(strip_port("[::1]"), "[::1]");
+        assert_eq!(strip_port("[::1]:8080"), "[::1]");
+    }
+
+    #[ntex_rt::test]
+    async fn test_https_redirect() {
+        let mw = HttpsRedirect::<DefaultError>::new()
+            .exclude("/.well-known/")
+            .new_transform(ok_service())
+            .await
+            .unwrap();
+
+        let req = TestRequest::with_uri("/test?a=b")
+            .header(header::HOST, "example.com:8080")
+            .to_srv_request();
+        let resp = mw.call(req).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::PERMANENT_REDIRECT);
+        assert_eq!(
+            resp.headers().get(LOCATION).unwrap(),
+            "https://example.com/test?a=b"
+        );
+
+        let req = TestRequest::with_uri("/test")
+            .header(header::HOST, "example.com")
+            .header(header::FORWARDED, "proto=https")
+            .to_srv_request();
+        let resp = mw.call(req).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let req = TestRequest::with_uri("/test")
+            .header("x-forwarded-proto", "https")
+            .to_srv_request();
+        let resp = mw.call(req).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let req =
+            TestRequest::with_uri("/.well-known/acme-challenge/token").to_srv_request();
+        let resp = mw.call(req).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let mw = HttpsRedirect::<DefaultError>::new()
+            .temporary()
+            .host("secure.example.com")
+            .port(8443)
+            .new_transform(ok_service())
+            .await
+            .unwrap();
+        let req = TestRequest::with_uri("/test")
+            .header(header::HOST, "example.com")
+            .to_srv_request();
+        let resp = mw.call(req).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::TEMPORARY_REDIRECT);
+        assert_eq!(
+            resp.headers().get(LOCATION).unwrap(),
+            "https://secure.example.com:8443/test"
+        );
+    }
+}
@@ -0,0 +1,934 @@
+//! Middleware for JWT validation
+use std::cell::RefCell;
+use std::marker::PhantomData;
+use std::rc::Rc;
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use bytes::Bytes;
+use futures::future::{ok, Either, FutureExt, LocalBoxFuture, Ready, Shared};
+use ring::{hmac, signature};
+use serde::Deserialize;
+
+use crate::http::client::Client;
+use crate::service::{Service, Transform};
+use crate::web::dev::{WebRequest, WebResponse};
+use crate::web::error::{AuthError, ErrorRenderer, JwtError};
+use crate::web::types::auth::credentials;
+use crate::web::types::claims::JwtPayload;
+use crate::web::types::BearerAuth;
+
+const JWKS_TTL: Duration = Duration::from_secs(600);
+const JWKS_REFRESH: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Algorithm {
+    HS256,
+    RS256,
+    ES256,
+}
+
+impl Algorithm {
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "HS256" => Some(Algorithm::HS256),
+            "RS256" => Some(Algorithm::RS256),
+            "ES256" => Some(Algorithm::ES256),
+            _ => None,
+        }
+    }
+}
+
+/// Key for JWT signature verification.
+///
+/// Key is bound to single signing algorithm, tokens signed with other
+/// algorithms are not verified with this key.
+#[derive(Clone)]
+pub struct JwtKey {
+    kid: Option<String>,
+    key: Key,
+}
+
+#[derive(Clone)]
+enum Key {
+    Hmac(hmac::Key),
+    Rsa { n: Vec<u8>, e: Vec<u8> },
+    Ec(Vec<u8>),
+}
+
+impl JwtKey {
+    /// Construct *HS256* key from shared secret.
+    pub fn hs256(secret: &[u8]) -> Self {
+        JwtKey {
+            kid: None,
+            key: Key::Hmac(hmac::Key::new(hmac::HMAC_SHA256, secret)),
+        }
+    }
+
+    /// Construct *RS256* key from public modulus and exponent.
+    ///
+    /// Modulus and exponent are big-endian encoded, key size must be
+    /// at least 2048 bits.
+    pub fn rs256(n: &[u8], e: &[u8]) -> Self {
+        let trim = |v: &[u8]| v.iter().skip_while(|b| **b == 0).cloned().collect();
+        JwtKey {
+            kid: None,
+            key: Key::Rsa {
+                n: trim(n),
+                e: trim(e),
+            },
+        }
+    }
+
+    /// Construct *ES256* key from uncompressed P-256 public point.
+    pub fn es256(point: &[u8]) -> Self {
+        JwtKey {
+            kid: None,
+            key: Key::Ec(point.to_vec()),
+        }
+    }
+
+    /// Set key id.
+    ///
+    /// If token header contains key id, token is verified only with keys
+    /// with the same id or with keys without id.
+    pub fn kid<T: Into<String>>(mut self, kid: T) -> Self {
+        self.kid = Some(kid.into());
+        self
+    }
+
+    fn algorithm(&self) -> Algorithm {
+        match self.key {
+            Key::Hmac(_) => Algorithm::HS256,
+            Key::Rsa { .. } => Algorithm::RS256,
+            Key::Ec(_) => Algorithm::ES256,
+        }
+    }
+
+    fn verify(&self, message: &[u8], sig: &[u8]) -> bool {
+        match self.key {
+            Key::Hmac(ref key) => hmac::verify(key, message, sig).is_ok(),
+            Key::Rsa { ref n, ref e } => signature::RsaPublicKeyComponents {
+                n: &n[..],
+                e: &e[..],
+            }
+            .verify(&signature::RSA_PKCS1_2048_8192_SHA256, message, sig)
+            .is_ok(),
+            Key::Ec(ref point) => signature::UnparsedPublicKey::new(
+                &signature::ECDSA_P256_SHA256_FIXED,
+                point,
+            )
+            .verify(message, sig)
+            .is_ok(),
+        }
+    }
+
+    /// Convert signing key from JWK set, unsupported keys are skipped
+    fn from_jwk(jwk: &Jwk) -> Option<Self> {
+        match jwk.use_.as_deref() {
+            None | Some("sig") => (),
+            Some(_) => return None,
+        }
+
+        let key = match (jwk.kty.as_str(), jwk.alg.as_deref()) {
+            ("RSA", None) | ("RSA", Some("RS256")) => {
+                let n = decode(jwk.n.as_ref()?).ok()?;
+                let e = decode(jwk.e.as_ref()?).ok()?;
+                JwtKey::rs256(&n, &e)
+            }
+            ("EC", None) | ("EC", Some("ES256"))
+                if jwk.crv.as_deref() == Some("P-256") =>
+            {
+                let x = decode(jwk.x.as_ref()?).ok()?;
+                let y = decode(jwk.y.as_ref()?).ok()?;
+                if x.len() != 32 || y.len() != 32 {
+                    return None;
+                }
+                let mut point = Vec::with_capacity(65);
+                point.push(4);
+                point.extend_from_slice(&x);
+                point.extend_from_slice(&y);
+                JwtKey::es256(&point)
+            }
+            _ => return None,
+        };
+
+        Some(match jwk.kid {
+            Some(ref kid) => key.kid(kid.clone()),
+            None => key,
+        })
+    }
+}
+
+#[derive(Deserialize)]
+struct JwkSet {
+    keys: Vec<Jwk>,
+}
+
+#[derive(Deserialize)]
+struct Jwk {
+    kty: String,
+    kid: Option<String>,
+    alg: Option<String>,
+    #[serde(rename = "use")]
+    use_: Option<String>,
+    n: Option<String>,
+    e: Option<String>,
+    crv: Option<String>,
+    x: Option<String>,
+    y: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct Header {
+    alg: String,
+    kid: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct RegisteredClaims {
+    exp: Option<f64>,
+    nbf: Option<f64>,
+    iss: Option<String>,
+    aud: Option<Audience>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Audience {
+    One(String),
+    Many(Vec<String>),
+}
+
+struct Token<'a> {
+    alg: Algorithm,
+    kid: Option<String>,
+    message: &'a str,
+    payload: Bytes,
+    signature: Vec<u8>,
+}
+
+impl<'a> Token<'a> {
+    fn parse(token: &'a str) -> Result<Self, JwtError> {
+        let pos = token.rfind('.').ok_or(JwtError::Malformed)?;
+        let (message, signature) = (&token[..pos], &token[pos + 1..]);
+
+        let mut parts = message.split('.');
+        let (header, payload) = match (parts.next(), parts.next(), parts.next()) {
+            (Some(header), Some(payload), None) => (header, payload),
+            _ => return Err(JwtError::Malformed),
+        };
+        let header: Header =
+            serde_json::from_slice(&decode(header)?).map_err(|_| JwtError::Malformed)?;
+
+        Ok(Token {
+            alg: Algorithm::from_name(&header.alg).ok_or(JwtError::Algorithm)?,
+            kid: header.kid,
+            message,
+            payload: Bytes::from(decode(payload)?),
+            signature: decode(signature)?,
+        })
+    }
+}
+
+fn decode(s: &str) -> Result<Vec<u8>, JwtError> {
+    base64::decode_config(s, base64::URL_SAFE_NO_PAD).map_err(|_| JwtError::Malformed)
+}
+
+/// `Middleware` for JWT validation.
+///
+/// Middleware extracts *Bearer* token from `Authorization` header, verifies
+/// token signature and validates registered claims. Expiration time and
+/// not before time are checked if token contains them, issuer and audience
+/// are checked if allowed values are configured. Claims of valid token are
+/// available to handlers via [`Claims`](../types/struct.Claims.html)
+/// extractor. Missing or malformed `Authorization` header is rendered as
+/// `AuthError`, invalid token is rendered as `JwtError`.
+///
+/// Supported algorithms are *HS256*, *RS256* and *ES256*. Verification keys
+/// could be configured statically or fetched from JWKS endpoint. Fetched
+/// keys are cached for `jwks_ttl()` period, if token is signed with unknown
+/// key id, keys are re-fetched to pick up rotated keys, but not more often
+/// than once per `jwks_refresh()` interval. Keys are fetched independently
+/// by each worker thread.
+///
+/// ```rust
+/// use ntex::web::{self, middleware, App, HttpResponse};
+///
+/// fn main() {
+///     let app = App::new()
+///         .wrap(
+///             middleware::Jwt::new()
+///                 .jwks("https://auth.example.com/.well-known/jwks.json")
+///                 .issuer("https://auth.example.com/")
+///                 .audience("api"),
+///         )
+///         .service(web::resource("/test").to(|| async { HttpResponse::Ok() }));
+/// }
+/// ```
+pub struct Jwt<Err> {
+    inner: Rc<Inner>,
+    _t: PhantomData<Err>,
+}
+
+struct Inner {
+    keys: Vec<JwtKey>,
+    issuers: Vec<String>,
+    audiences: Vec<String>,
+    leeway: Duration,
+    realm: String,
+    jwks: Option<String>,
+    client: Option<Client>,
+    ttl: Duration,
+    refresh: Duration,
+    state: Rc<RefCell<JwksState>>,
+}
+
+#[derive(Default)]
+struct JwksState {
+    keys: Rc<Vec<JwtKey>>,
+    fetched: Option<Instant>,
+    attempted: Option<Instant>,
+    pending: Option<Shared<LocalBoxFuture<'static, ()>>>,
+}
+
+impl<Err> Default for Jwt<Err> {
+    fn default() -> Self {
+        Jwt {
+            inner: Rc::new(Inner {
+                keys: Vec::new(),
+                issuers: Vec::new(),
+                audiences: Vec::new(),
+                leeway: Duration::from_secs(0),
+                realm: "Restricted".to_string(),
+                jwks: None,
+                client: None,
+                ttl: JWKS_TTL,
+                refresh: JWKS_REFRESH,
+                state: Rc::new(RefCell::new(JwksState::default())),
+            }),
+            _t: PhantomData,
+        }
+    }
+}
+
+impl<Err> Jwt<Err> {
+    /// Construct `Jwt` middleware, no keys are configured.
+    pub fn new() -> Self {
+        Jwt::default()
+    }
+
+    /// Add verification key.
+    pub fn key(mut self, key: JwtKey) -> Self {
+        Rc::get_mut(&mut self.inner)
+            .expect("Multiple copies exist")
+            .keys
+            .push(key);
+        self
+    }
+
+    /// Fetch verification keys from JWKS endpoint.
+    ///
+    /// Only *RS256* and *ES256* signing keys are used.
+    pub fn jwks(mut self, url: &str) -> Self {
+        Rc::get_mut(&mut self.inner)
+            .expect("Multiple copies exist")
+            .jwks = Some(url.to_string());
+        self
+    }
+
+    /// Set http client for JWKS fetching.
+    ///
+    /// By default client with default settings is used.
+    pub fn jwks_client(mut self, client: Client) -> Self {
+        Rc::get_mut(&mut self.inner)
+            .expect("Multiple copies exist")
+            .client = Some(client);
+        self
+    }
+
+    /// Set JWKS cache period.
+    ///
+    /// By default keys are re-fetched every 10 minutes.
+    pub fn jwks_ttl(mut self, ttl: Duration) -> Self {
+        Rc::get_mut(&mut self.inner)
+            .expect("Multiple copies exist")
+            .ttl = ttl;
+        self
+    }
+
+    /// Set minimal interval between JWKS fetches.
+    ///
+    /// Interval limits re-fetching for tokens with unknown key id and
+    /// retries of failed fetches. By default interval is 30 seconds.
+    pub fn jwks_refresh(mut self, interval: Duration) -> Self {
+        Rc::get_mut(&mut self.inner)
+            .expect("Multiple copies exist")
+            .refresh = interval;
+        self
+    }
+
+    /// Add allowed issuer.
+    ///
+    /// If issuers are configured, token's `iss` claim must match one of
+    /// them.
+    pub fn issuer(mut self, iss: &str) -> Self {
+        Rc::get_mut(&mut self.inner)
+            .expect("Multiple copies exist")
+            .issuers
+            .push(iss.to_string());
+        self
+    }
+
+    /// Add allowed audience.
+    ///
+    /// If audiences are configured, token's `aud` claim must contain one
+    /// of them.
+    pub fn audience(mut self, aud: &str) -> Self {
+        Rc::get_mut(&mut self.inner)
+            .expect("Multiple copies exist")
+            .audiences
+            .push(aud.to_string());
+        self
+    }
+
+    /// Set allowed clock skew for `exp` and `nbf` claims.
+    ///
+    /// By default leeway is not allowed.
+    pub fn leeway(mut self, leeway: Duration) -> Self {
+        Rc::get_mut(&mut self.inner)
+            .expect("Multiple copies exist")
+            .leeway = leeway;
+        self
+    }
+
+    /// Set authentication realm.
+    ///
+    /// By default `Restricted` realm is used.
+    pub fn realm<R: Into<String>>(mut self, realm: R) -> Self {
+        Rc::get_mut(&mut self.inner)
+            .expect("Multiple copies exist")
+            .realm = realm.into();
+        self
+    }
+}
+
+impl Inner {
+    fn jwks_expired(&self) -> bool {
+        let state = self.state.borrow();
+        self.jwks.is_some()
+            && !state
+                .fetched
+                .map(|t| t.elapsed() < self.ttl)
+                .unwrap_or(false)
+            && self.jwks_refreshable(&state)
+    }
+
+    fn jwks_unknown_key(&self) -> bool {
+        self.jwks.is_some() && self.jwks_refreshable(&self.state.borrow())
+    }
+
+    fn jwks_refreshable(&self, state: &JwksState) -> bool {
+        match state.attempted {
+            Some(t) => state.pending.is_some() || t.elapsed() >= self.refresh,
+            None => true,
+        }
+    }
+
+    /// Fetch keys from JWKS endpoint, concurrent requests share single fetch
+    fn jwks_refresh(&self) -> Shared<LocalBoxFuture<'static, ()>> {
+        let mut state = self.state.borrow_mut();
+        if let Some(ref fut) = state.pending {
+            return fut.clone();
+        }
+        state.attempted = Some(Instant::now());
+
+        let url = self.jwks.clone().unwrap_or_default();
+        let client = self.client.clone();
+        let st = self.state.clone();
+        let fut = async move {
+            match fetch(client.unwrap_or_default(), &url).await {
+                Ok(keys) => {
+                    log::debug!("Fetched {} keys from JWKS {:?}", keys.len(), url);
+                    let mut state = st.borrow_mut();
+                    state.keys = Rc::new(keys);
+                    state.fetched = Some(Instant::now());
+                }
+                Err(e) => log::error!("Cannot fetch JWKS {:?}: {}", url, e),
+            }
+            st.borrow_mut().pending = None;
+        }
+        .boxed_local()
+        .shared();
+
+        state.pending = Some(fut.clone());
+        fut
+    }
+
+    fn verify(&self, token: &Token<'_>) -> Result<(), JwtError> {
+        let fetched = self.state.borrow().keys.clone();
+        let mut known = false;
+
+        for key in self.keys.iter().chain(fetched.iter()) {
+            if key.algorithm() != token.alg {
+                continue;
+            }
+            match (&key.kid, &token.kid) {
+                (Some(kid1), Some(kid2)) if kid1 != kid2 => continue,
+                (None, Some(_)) => (),
+                _ => known = true,
+            }
+            if key.verify(token.message.as_bytes(), &token.signature) {
+                return Ok(());
+            }
+        }
+
+        if known {
+            Err(JwtError::Signature)
+        } else {
+            Err(JwtError::UnknownKey)
+        }
+    }
+
+    fn validate_claims(&self, payload: &[u8]) -> Result<(), JwtError> {
+        let claims: RegisteredClaims =
+            serde_json::from_slice(payload).map_err(|_| JwtError::Malformed)?;
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs_f64())
+            .unwrap_or(0.0);
+        let leeway = self.leeway.as_secs_f64();
+
+        if let Some(exp) = claims.exp {
+            if now >= exp + leeway {
+                return Err(JwtError::Expired);
+            }
+        }
+        if let Some(nbf) = claims.nbf {
+            if now + leeway < nbf {
+                return Err(JwtError::NotYetValid);
+            }
+        }
+        if !self.issuers.is_empty() {
+            match claims.iss {
+                Some(ref iss) if self.issuers.contains(iss) => (),
+                _ => return Err(JwtError::Issuer),
+            }
+        }
+        if !self.audiences.is_empty() {
+            let valid = match claims.aud {
+                Some(Audience::One(ref aud)) => self.audiences.contains(aud),
+                Some(Audience::Many(ref aud)) => {
+                    aud.iter().any(|aud| self.audiences.contains(aud))
+                }
+                None => false,
+            };
+            if !valid {
+                return Err(JwtError::Audience);
+            }
+        }
+        Ok(())
+    }
+}
+
+async fn fetch(client: Client, url: &str) -> Result<Vec<JwtKey>, String> {
+    let mut res = client.get(url).send().await.map_err(|e| e.to_string())?;
+    if !res.status().is_success() {
+        return Err(res.status().to_string());
+    }
+    let body = res.body().await.map_err(|e| e.to_string())?;
+    let set: JwkSet = serde_json::from_slice(&body).map_err(|e| e.to_string())?;
+    Ok(set.keys.iter().filter_map(JwtKey::from_jwk).collect())
+}
+
+/// Validate token, returns token claims
+async fn validate(inner: Rc<Inner>, creds: BearerAuth) -> Result<Bytes, JwtError> {
+    let token = Token::parse(creds.token())?;
+
+    if inner.jwks_expired() {
+        inner.jwks_refresh().await;
+    }
+    match inner.verify(&token) {
+        Err(JwtError::UnknownKey) if inner.jwks_unknown_key() => {
+            inner.jwks_refresh().await;
+            inner.verify(&token)?
+        }
+        res => res?,
+    }
+    inner.validate_claims(&token.payload)?;
+
+    Ok(token.payload)
+}
+
+impl<S, B, Err> Transform<S> for Jwt<Err>
+where
+    S: Service<Request = WebRequest<Err>, Response = WebResponse<B>> + 'static,
+    Err: ErrorRenderer,
+    AuthError: Into<Err::Container>,
+    JwtError: Into<Err::Container>,
+{
+    type Request = WebRequest<Err>;
+    type Response = WebResponse<B>;
+    type Error = S::Error;
+    type InitError = ();
+    type Transform = JwtMiddleware<S, Err>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ok(JwtMiddleware {
+            service: Rc::new(service),
+            inner: self.inner.clone(),
+            _t: PhantomData,
+        })
+    }
+}
+
+pub struct JwtMiddleware<S, Err> {
+    service: Rc<S>,
+    inner: Rc<Inner>,
+    _t: PhantomData<Err>,
+}
+
+impl<S, B, Err> Service for JwtMiddleware<S, Err>
+where
+    S: Service<Request = WebRequest<Err>, Response = WebResponse<B>> + 'static,
+    Err: ErrorRenderer,
+    AuthError: Into<Err::Container>,
+    JwtError: Into<Err::Container>,
+{
+    type Request = WebRequest<Err>;
+    type Response = WebResponse<B>;
+    type Error = S::Error;
+    type Future = Either<
+        Ready<Result<Self::Response, Self::Error>>,
+        LocalBoxFuture<'static, Result<Self::Response, Self::Error>>,
+    >;
+
+    #[inline]
+    fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(cx)
+    }
+
+    #[inline]
+    fn poll_shutdown(&self, cx: &mut Context<'_>, is_error: bool) -> Poll<()> {
+        self.service.poll_shutdown(cx, is_error)
+    }
+
+    fn call(&self, req: WebRequest<Err>) -> Self::Future {
+        let creds = match credentials::<BearerAuth>(req.headers(), &self.inner.realm) {
+            Ok(creds) => creds,
+            Err(e) => return Either::Left(ok(req.error_response(e))),
+        };
+
+        let srv = self.service.clone();
+        let inner = self.inner.clone();
+
+        async move {
+            match validate(inner, creds).await {
+                Ok(payload) => {
+                    req.extensions_mut().insert(JwtPayload(payload));
+                    srv.call(req).await
+                }
+                Err(e) => {
+                    log::debug!("Rejected token for {:?}: {}", req.path(), e);
+                    Ok(req.error_response(e))
+                }
+            }
+        }
+        .boxed_local()
+        .right_future()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use ring::rand::SystemRandom;
+    use ring::signature::{EcdsaKeyPair, KeyPair, RsaKeyPair};
+    use serde_derive::Deserialize;
+
+    use super::*;
+    use crate::http::header::{self, HeaderValue};
+    use crate::http::{Response, StatusCode};
+    use crate::web::test::{self, call_service, init_service, read_body, TestRequest};
+    use crate::web::types::Claims;
+    use crate::web::{self, App, DefaultError, HttpResponse};
+
+    fn encode(data: &[u8]) -> String {
+        base64::encode_config(data, base64::URL_SAFE_NO_PAD)
+    }
+
+    fn now() -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs()
+    }
+
+    fn message(alg: &str, kid: Option<&str>, claims: &str) -> String {
+        let header = match kid {
+            Some(kid) => format!("{{\"alg\":\"{}\",\"kid\":\"{}\"}}", alg, kid),
+            None => format!("{{\"alg\":\"{}\"}}", alg),
+        };
+        format!(
+            "{}.{}",
+            encode(header.as_bytes()),
+            encode(claims.as_bytes())
+        )
+    }
+
+    fn hs256(secret: &[u8], claims: &str) -> String {
+        let msg = message("HS256", None, claims);
+        let key = hmac::Key::new(hmac::HMAC_SHA256, secret);
+        let sig = hmac::sign(&key, msg.as_bytes());
+        format!("{}.{}", msg, encode(sig.as_ref()))
+    }
+
+    fn es256(key: &EcdsaKeyPair, kid: &str, claims: &str) -> String {
+        let msg = message("ES256", Some(kid), claims);
+        let sig = key.sign(&SystemRandom::new(), msg.as_bytes()).unwrap();
+        format!("{}.{}", msg, encode(sig.as_ref()))
+    }
+
+    fn es256_key() -> EcdsaKeyPair {
+        let rng = SystemRandom::new();
+        let pkcs8 = EcdsaKeyPair::generate_pkcs8(
+            &signature::ECDSA_P256_SHA256_FIXED_SIGNING,
+            &rng,
+        )
+        .unwrap();
+        EcdsaKeyPair::from_pkcs8(
+            &signature::ECDSA_P256_SHA256_FIXED_SIGNING,
+            pkcs8.as_ref(),
+        )
+        .unwrap()
+    }
+
+    fn es256_jwk(key: &EcdsaKeyPair, kid: &str) -> String {
+        let point = key.public_key().as_ref();
+        format!(
+            "{{\"kty\":\"EC\",\"crv\":\"P-256\",\"kid\":\"{}\",\"x\":\"{}\",\"y\":\"{}\"}}",
+            kid,
+            encode(&point[1..33]),
+            encode(&point[33..])
+        )
+    }
+
+    fn bearer(token: &str) -> TestRequest {
+        TestRequest::with_header(header::AUTHORIZATION, format!("Bearer {}", token))
+    }
+
+    #[derive(Deserialize)]
+    struct User {
+        sub: String,
+    }
+
+    #[ntex_rt::test]
+    async fn test_hs256() {
+        let srv = init_service(
+            App::new()
+                .wrap(
+                    Jwt::<DefaultError>::new()
+                        .key(JwtKey::hs256(b"secret"))
+                        .issuer("issuer")
+                        .audience("api"),
+                )
+                .service(web::resource("/").to(|claims: Claims<User>| async move {
+                    HttpResponse::Ok().body(claims.sub.clone())
+                })),
+        )
+        .await;
+
+        let exp = now() + 60;
+        let claims = format!(
+            "{{\"sub\":\"user\",\"iss\":\"issuer\",\"aud\":[\"web\",\"api\"],\"exp\":{}}}",
+            exp
+        );
+        let req = bearer(&hs256(b"secret", &claims)).to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(read_body(resp).await, "user");
+
+        let req = bearer(&hs256(b"other", &claims)).to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(
+            resp.headers().get(header::WWW_AUTHENTICATE).unwrap(),
+            HeaderValue::from_static(
+                "Bearer error=\"invalid_token\", error_description=\"Invalid token signature\""
+            )
+        );
+
+        let req = TestRequest::default().to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(
+            resp.headers().get(header::WWW_AUTHENTICATE).unwrap(),
+            HeaderValue::from_static("Bearer realm=\"Restricted\"")
+        );
+
+        let req = bearer("abc.def").to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[ntex_rt::test]
+    async fn test_claims() {
+        let jwt = Jwt::<DefaultError>::new()
+            .key(JwtKey::hs256(b"secret"))
+            .issuer("issuer")
+            .audience("api")
+            .leeway(Duration::from_secs(30));
+        let now = now();
+
+        let check = |claims: String| {
+            let token = hs256(b"secret", &claims);
+            let token = Token::parse(&token).unwrap();
+            jwt.inner.verify(&token)?;
+            jwt.inner.validate_claims(&token.payload)
+        };
+
+        let claims = |exp: u64, nbf: u64, iss: &str, aud: &str| {
+            format!(
+                "{{\"iss\":\"{}\",\"aud\":\"{}\",\"exp\":{},\"nbf\":{}}}",
+                iss, aud, exp, nbf
+            )
+        };
+
+        assert!(check(claims(now + 10, now, "issuer", "api")).is_ok());
+        assert!(check(claims(now - 10, now, "issuer", "api")).is_ok());
+        assert!(check(claims(now, now + 10, "issuer", "api")).is_ok());
+        assert!(matches!(
+            check(claims(now - 60, now - 120, "issuer", "api")),
+            Err(JwtError::Expired)
+        ));
+        assert!(matches!(
+            check(claims(now + 120, now + 60, "issuer", "api")),
+            Err(JwtError::NotYetValid)
+        ));
+        assert!(matches!(
+            check(claims(now + 10, now, "other", "api")),
+            Err(JwtError::Issuer)
+        ));
+        assert!(matches!(
+            check(claims(now + 10, now, "issuer", "web")),
+            Err(JwtError::Audience)
+        ));
+        assert!(matches!(
+            check("{\"iss\":\"issuer\"}".to_string()),
+            Err(JwtError::Audience)
+        ));
+        assert!(matches!(check("[]".to_string()), Err(JwtError::Malformed)));
+
+        let token = format!("{}.sig", message("none", None, "{}"));
+        assert!(matches!(Token::parse(&token), Err(JwtError::Algorithm)));
+        let token = format!("{}.sig", message("RS256", None, "{}"));
+        let token = Token::parse(&token).unwrap();
+        assert!(matches!(
+            jwt.inner.verify(&token),
+            Err(JwtError::UnknownKey)
+        ));
+    }
+
+    #[ntex_rt::test]
+    async fn test_rs256() {
+        let pem = include_str!("../../../tests/key.pem");
+        let der: String = pem.lines().filter(|l| !l.starts_with("-----")).collect();
+        let key = RsaKeyPair::from_pkcs8(&base64::decode(&der).unwrap()).unwrap();
+        let public = key.public_key();
+
+        let jwt = Jwt::<DefaultError>::new().key(
+            JwtKey::rs256(
+                public.modulus().big_endian_without_leading_zero(),
+                public.exponent().big_endian_without_leading_zero(),
+            )
+            .kid("rsa"),
+        );
+
+        let sign = |kid: &str| {
+            let msg = message("RS256", Some(kid), "{\"sub\":\"user\"}");
+            let mut sig = vec![0; key.public_modulus_len()];
+            key.sign(
+                &signature::RSA_PKCS1_SHA256,
+                &SystemRandom::new(),
+                msg.as_bytes(),
+                &mut sig,
+            )
+            .unwrap();
+            format!("{}.{}", msg, encode(&sig))
+        };
+
+        let token = sign("rsa");
+        assert!(jwt.inner.verify(&Token::parse(&token).unwrap()).is_ok());
+        let token = sign("other");
+        assert!(matches!(
+            jwt.inner.verify(&Token::parse(&token).unwrap()),
+            Err(JwtError::UnknownKey)
+        ));
+    }
+
+    #[ntex_rt::test]
+    async fn test_jwks_rotation() {
+        let key1 = es256_key();
+        let key2 = es256_key();
+        let jwks = Arc::new(Mutex::new(format!(
+            "{{\"keys\":[{},{{\"kty\":\"oct\",\"k\":\"c2VjcmV0\"}}]}}",
+            es256_jwk(&key1, "1")
+        )));
+        let hits = Arc::new(Mutex::new(0));
+
+        let jwks2 = jwks.clone();
+        let hits2 = hits.clone();
+        let upstream = test::server(move || {
+            let jwks = jwks2.clone();
+            let hits = hits2.clone();
+            App::new().service(web::resource("/jwks").to(move || {
+                *hits.lock().unwrap() += 1;
+                let body = jwks.lock().unwrap().clone();
+                async move { Response::Ok().content_type("application/json").body(body) }
+            }))
+        });
+
+        let srv = init_service(
+            App::new()
+                .wrap(
+                    Jwt::<DefaultError>::new()
+                        .jwks(&format!("http://{}/jwks", upstream.addr()))
+                        .jwks_refresh(Duration::from_secs(0)),
+                )
+                .service(web::resource("/").to(|| async { HttpResponse::Ok() })),
+        )
+        .await;
+
+        let req = bearer(&es256(&key1, "1", "{}")).to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let req = bearer(&es256(&key1, "1", "{}")).to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(*hits.lock().unwrap(), 1);
+
+        // signature of known key id is not re-fetched
+        let req = bearer(&es256(&key2, "1", "{}")).to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(*hits.lock().unwrap(), 1);
+
+        // rotate keys
+        *jwks.lock().unwrap() = format!(
+            "{{\"keys\":[{},{}]}}",
+            es256_jwk(&key1, "1"),
+            es256_jwk(&key2, "2")
+        );
+        let req = bearer(&es256(&key2, "2", "{}")).to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(*hits.lock().unwrap(), 2);
+
+        let req = bearer(&es256(&key2, "3", "{}")).to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(*hits.lock().unwrap(), 3);
+    }
+}
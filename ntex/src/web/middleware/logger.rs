@@ -12,15 +12,20 @@ use std::task::{Context, Poll};
 
 use bytes::Bytes;
 use futures::future::{ok, Ready};
-use regex::Regex;
+use regex::{Regex, RegexSet};
 use time::OffsetDateTime;
 
 use crate::http::body::{BodySize, MessageBody, ResponseBody};
 use crate::http::header::HeaderName;
+use crate::http::{Arrival, RequestHead, ResponseHead, StatusCode};
 use crate::service::{Service, Transform};
 use crate::web::dev::{WebRequest, WebResponse};
 use crate::web::HttpResponse;
 
+use super::memory::MemoryUsage;
+use super::requestid::RequestIdValue;
+use super::timing::{millis, Timings};
+
 /// `Middleware` for logging request and response info to the terminal.
 ///
 /// `Logger` middleware uses standard log crate to log information. You should
@@ -69,6 +74,12 @@ use crate::web::HttpResponse;
 ///
 /// `%D`  Time taken to serve the request, in milliseconds
 ///
+/// `%M`  Peak memory accounted for the request by `MemoryAccounting`
+/// middleware, in bytes
+///
+/// `%Q`  Time request spent queued in the server after arrival and before
+/// processing started, in milliseconds
+///
 /// `%U`  Request URL
 ///
 /// `%{FOO}i`  request.headers['FOO']
@@ -77,6 +88,17 @@ use crate::web::HttpResponse;
 ///
 /// `%{FOO}e`  os.environ['FOO']
 ///
+/// `%{FOO}m`  Duration of `FOO` span recorded by `ServerTiming` middleware,
+/// in milliseconds
+///
+/// `%{request_id}x`  Request id set by `RequestId` middleware
+///
+/// `%{FOO}xi`  Custom request replacement labelled `FOO`, check
+/// [`custom_request_replace()`](#method.custom_request_replace)
+///
+/// `%{FOO}xo`  Custom response replacement labelled `FOO`, check
+/// [`custom_response_replace()`](#method.custom_response_replace)
+///
 pub struct Logger<Err> {
     inner: Rc<Inner>,
     _t: PhantomData<Err>,
@@ -85,16 +107,74 @@ pub struct Logger<Err> {
 struct Inner {
     format: Format,
     exclude: HashSet<String>,
+    exclude_regex: RegexSet,
+    exclude_status: HashSet<StatusCode>,
+    output: Output,
+}
+
+enum Output {
+    Log {
+        target: Option<String>,
+        level: log::Level,
+    },
+    Sink(Box<dyn AccessLogSink>),
+}
+
+/// Destination for access log records.
+///
+/// Implemented for closures that take formatted record.
+pub trait AccessLogSink {
+    /// Write formatted access log record
+    fn write(&self, record: &str);
+}
+
+impl<F> AccessLogSink for F
+where
+    F: Fn(&str),
+{
+    fn write(&self, record: &str) {
+        (self)(record)
+    }
+}
+
+impl Inner {
+    fn new(format: Format) -> Self {
+        Inner {
+            format,
+            exclude: HashSet::new(),
+            exclude_regex: RegexSet::empty(),
+            exclude_status: HashSet::new(),
+            output: Output::Log {
+                target: None,
+                level: log::Level::Info,
+            },
+        }
+    }
+
+    fn write(&self, record: FormatDisplay<'_>) {
+        match self.output {
+            Output::Log {
+                target: Some(ref target),
+                level,
+            } => log::log!(target: target, level, "{}", record),
+            Output::Log {
+                target: None,
+                level,
+            } => log::log!(level, "{}", record),
+            Output::Sink(ref sink) => sink.write(&record.to_string()),
+        }
+    }
+
+    fn is_excluded(&self, path: &str) -> bool {
+        self.exclude.contains(path) || self.exclude_regex.is_match(path)
+    }
 }
 
 impl<Err> Logger<Err> {
     /// Create `Logger` middleware with the specified `format`.
     pub fn new(format: &str) -> Logger<Err> {
         Logger {
-            inner: Rc::new(Inner {
-                format: Format::new(format),
-                exclude: HashSet::new(),
-            }),
+            inner: Rc::new(Inner::new(Format::new(format))),
             _t: PhantomData,
         }
     }
@@ -107,6 +187,125 @@ impl<Err> Logger<Err> {
             .insert(path.into());
         self
     }
+
+    /// Ignore and do not log access info for paths that match regex.
+    ///
+    /// Panics if `pattern` is not a valid regex.
+    pub fn exclude_regex<T: Into<String>>(mut self, pattern: T) -> Self {
+        let inner = Rc::get_mut(&mut self.inner).unwrap();
+        let mut patterns = inner.exclude_regex.patterns().to_vec();
+        patterns.push(pattern.into());
+        inner.exclude_regex = RegexSet::new(patterns).unwrap();
+        self
+    }
+
+    /// Ignore and do not log access info for responses with specified status.
+    ///
+    /// ```rust
+    /// use ntex::http::StatusCode;
+    /// use ntex::web::{middleware::Logger, App};
+    ///
+    /// fn main() {
+    ///     let app = App::new().wrap(
+    ///         Logger::default()
+    ///             .exclude_regex("^/health")
+    ///             .exclude_status(StatusCode::OK)
+    ///             .exclude_status(StatusCode::NOT_MODIFIED),
+    ///     );
+    /// }
+    /// ```
+    pub fn exclude_status(mut self, status: StatusCode) -> Self {
+        Rc::get_mut(&mut self.inner)
+            .unwrap()
+            .exclude_status
+            .insert(status);
+        self
+    }
+
+    /// Set `log` crate target and level of access log records.
+    ///
+    /// By default records are logged with *info* level and middleware's
+    /// module path as target.
+    pub fn target<T: Into<String>>(mut self, target: T, level: log::Level) -> Self {
+        Rc::get_mut(&mut self.inner).unwrap().output = Output::Log {
+            level,
+            target: Some(target.into()),
+        };
+        self
+    }
+
+    /// Write access log records to custom sink instead of `log` crate.
+    ///
+    /// ```rust
+    /// use std::io::Write;
+    /// use std::sync::Mutex;
+    /// use ntex::web::{middleware::Logger, App};
+    ///
+    /// fn main() {
+    ///     let file = Mutex::new(std::io::sink());
+    ///     let app = App::new().wrap(Logger::default().writer(move |record: &str| {
+    ///         let _ = writeln!(file.lock().unwrap(), "{}", record);
+    ///     }));
+    /// }
+    /// ```
+    pub fn writer<T: AccessLogSink + 'static>(mut self, sink: T) -> Self {
+        Rc::get_mut(&mut self.inner).unwrap().output = Output::Sink(Box::new(sink));
+        self
+    }
+
+    /// Register a function that renders `%{label}xi` token.
+    ///
+    /// Function is called after inner services complete, so it could
+    /// use request extensions populated by inner middlewares and handlers.
+    ///
+    /// ```rust
+    /// use ntex::web::{self, middleware::Logger, App};
+    ///
+    /// struct UserId(u64);
+    ///
+    /// fn main() {
+    ///     let app = App::new().wrap(
+    ///         Logger::new("%a %{user}xi %s").custom_request_replace("user", |req| {
+    ///             req.extensions()
+    ///                 .get::<UserId>()
+    ///                 .map(|id| id.0.to_string())
+    ///                 .unwrap_or_else(|| "-".to_string())
+    ///         }),
+    ///     );
+    /// }
+    /// ```
+    pub fn custom_request_replace<F>(mut self, label: &str, f: F) -> Self
+    where
+        F: Fn(&RequestHead) -> String + 'static,
+    {
+        let f = CustomRequestFn(Rc::new(f));
+        let inner = Rc::get_mut(&mut self.inner).unwrap();
+        for unit in &mut inner.format.0 {
+            if let FormatText::CustomRequest(ref name, ref mut func) = unit {
+                if name == label {
+                    *func = Some(f.clone());
+                }
+            }
+        }
+        self
+    }
+
+    /// Register a function that renders `%{label}xo` token.
+    pub fn custom_response_replace<F>(mut self, label: &str, f: F) -> Self
+    where
+        F: Fn(&ResponseHead) -> String + 'static,
+    {
+        let f = CustomResponseFn(Rc::new(f));
+        let inner = Rc::get_mut(&mut self.inner).unwrap();
+        for unit in &mut inner.format.0 {
+            if let FormatText::CustomResponse(ref name, ref mut func) = unit {
+                if name == label {
+                    *func = Some(f.clone());
+                }
+            }
+        }
+        self
+    }
 }
 
 impl<Err> Default for Logger<Err> {
@@ -117,10 +316,7 @@ impl<Err> Default for Logger<Err> {
     /// ```
     fn default() -> Self {
         Logger {
-            inner: Rc::new(Inner {
-                format: Format::default(),
-                exclude: HashSet::new(),
-            }),
+            inner: Rc::new(Inner::new(Format::default())),
             _t: PhantomData,
         }
     }
@@ -176,10 +372,11 @@ where
 
     #[inline]
     fn call(&self, req: WebRequest<E>) -> Self::Future {
-        if self.inner.exclude.contains(req.path()) {
+        if self.inner.is_excluded(req.path()) {
             LoggerResponse {
                 fut: self.service.call(req),
                 format: None,
+                inner: self.inner.clone(),
                 time: OffsetDateTime::now(),
                 _t: PhantomData,
             }
@@ -193,6 +390,7 @@ where
             LoggerResponse {
                 fut: self.service.call(req),
                 format: Some(format),
+                inner: self.inner.clone(),
                 time: now,
                 _t: PhantomData,
             }
@@ -211,6 +409,7 @@ where
     fut: S::Future,
     time: OffsetDateTime,
     format: Option<Format>,
+    inner: Rc<Inner>,
     _t: PhantomData<(B, E)>,
 }
 
@@ -229,20 +428,45 @@ where
             Err(e) => return Poll::Ready(Err(e)),
         };
 
+        // status is known only after inner service completes
+        if this.inner.exclude_status.contains(&res.status()) {
+            *this.format = None;
+        }
+
         if let Some(ref mut format) = this.format {
             for unit in &mut format.0 {
                 unit.render_response(res.response());
             }
+            if let Some(timings) = res.request().extensions().get::<Timings>() {
+                for unit in &mut format.0 {
+                    unit.render_timings(timings);
+                }
+            }
+            if let Some(usage) = res.request().extensions().get::<MemoryUsage>() {
+                for unit in &mut format.0 {
+                    unit.render_memory(usage);
+                }
+            }
+            if let Some(id) = res.request().extensions().get::<RequestIdValue>() {
+                for unit in &mut format.0 {
+                    unit.render_request_id(id);
+                }
+            }
+            for unit in &mut format.0 {
+                unit.render_custom(res.request().head(), res.response().head());
+            }
         }
 
         let time = *this.time;
         let format = this.format.take();
+        let inner = this.inner.clone();
 
         Poll::Ready(Ok(res.map_body(move |_, body| {
             ResponseBody::Body(StreamLog {
                 body,
                 time,
                 format,
+                inner,
                 size: 0,
             })
         })))
@@ -252,6 +476,7 @@ where
 pub struct StreamLog<B> {
     body: ResponseBody<B>,
     format: Option<Format>,
+    inner: Rc<Inner>,
     size: usize,
     time: OffsetDateTime,
 }
@@ -265,7 +490,7 @@ impl<B> Drop for StreamLog<B> {
                 }
                 Ok(())
             };
-            log::info!("{}", FormatDisplay(&render));
+            self.inner.write(FormatDisplay(&render));
         }
     }
 }
@@ -308,7 +533,8 @@ impl Format {
     /// Returns `None` if the format string syntax is incorrect.
     fn new(s: &str) -> Format {
         log::trace!("Access log format: {}", s);
-        let fmt = Regex::new(r"%(\{([A-Za-z0-9\-_]+)\}([ioe])|[atPrUsbTD]?)").unwrap();
+        let fmt = Regex::new(r"%(\{([A-Za-z0-9\-_]+)\}([ioem]|xi|xo|x)|[atPrUsbTDQM]?)")
+            .unwrap();
 
         let mut idx = 0;
         let mut results = Vec::new();
@@ -329,6 +555,11 @@ impl Format {
                         HeaderName::try_from(key.as_str()).unwrap(),
                     ),
                     "e" => FormatText::EnvironHeader(key.as_str().to_owned()),
+                    "m" => FormatText::Timing(key.as_str().to_owned()),
+                    "xi" => FormatText::CustomRequest(key.as_str().to_owned(), None),
+                    "xo" => FormatText::CustomResponse(key.as_str().to_owned(), None),
+                    "x" if key.as_str() == "request_id" => FormatText::RequestId,
+                    "x" => FormatText::Str(m.as_str().to_owned()),
                     _ => unreachable!(),
                 })
             } else {
@@ -343,6 +574,8 @@ impl Format {
                     "U" => FormatText::UrlPath,
                     "T" => FormatText::Time,
                     "D" => FormatText::TimeMillis,
+                    "Q" => FormatText::QueueMillis,
+                    "M" => FormatText::Memory,
                     _ => FormatText::Str(m.as_str().to_owned()),
                 });
             }
@@ -368,11 +601,35 @@ enum FormatText {
     ResponseSize,
     Time,
     TimeMillis,
+    QueueMillis,
+    Memory,
     RemoteAddr,
     UrlPath,
     RequestHeader(HeaderName),
     ResponseHeader(HeaderName),
     EnvironHeader(String),
+    Timing(String),
+    RequestId,
+    CustomRequest(String, Option<CustomRequestFn>),
+    CustomResponse(String, Option<CustomResponseFn>),
+}
+
+#[derive(Clone)]
+struct CustomRequestFn(Rc<dyn Fn(&RequestHead) -> String>);
+
+impl fmt::Debug for CustomRequestFn {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str("custom_request_fn")
+    }
+}
+
+#[derive(Clone)]
+struct CustomResponseFn(Rc<dyn Fn(&ResponseHead) -> String>);
+
+impl fmt::Debug for CustomResponseFn {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str("custom_response_fn")
+    }
 }
 
 impl FormatText {
@@ -403,6 +660,11 @@ impl FormatText {
                     "-".fmt(fmt)
                 }
             }
+            FormatText::Timing(_)
+            | FormatText::Memory
+            | FormatText::RequestId
+            | FormatText::CustomRequest(..)
+            | FormatText::CustomResponse(..) => "-".fmt(fmt),
             _ => Ok(()),
         }
     }
@@ -428,6 +690,40 @@ impl FormatText {
         }
     }
 
+    fn render_timings(&mut self, timings: &Timings) {
+        if let FormatText::Timing(ref name) = *self {
+            *self = if let Some(dur) = timings.get(name) {
+                FormatText::Str(format!("{:.6}", millis(dur)))
+            } else {
+                FormatText::Str("-".to_string())
+            };
+        }
+    }
+
+    fn render_memory(&mut self, usage: &MemoryUsage) {
+        if let FormatText::Memory = *self {
+            *self = FormatText::Str(usage.peak().to_string());
+        }
+    }
+
+    fn render_request_id(&mut self, id: &RequestIdValue) {
+        if let FormatText::RequestId = *self {
+            *self = FormatText::Str(id.to_string());
+        }
+    }
+
+    fn render_custom(&mut self, req: &RequestHead, res: &ResponseHead) {
+        match *self {
+            FormatText::CustomRequest(_, Some(ref f)) => {
+                *self = FormatText::Str((f.0)(req));
+            }
+            FormatText::CustomResponse(_, Some(ref f)) => {
+                *self = FormatText::Str((f.0)(res));
+            }
+            _ => (),
+        }
+    }
+
     fn render_request<E>(&mut self, now: OffsetDateTime, req: &WebRequest<E>) {
         match *self {
             FormatText::RequestLine => {
@@ -449,6 +745,13 @@ impl FormatText {
                 };
             }
             FormatText::UrlPath => *self = FormatText::Str(req.path().to_string()),
+            FormatText::QueueMillis => {
+                *self = if let Some(arrival) = req.extensions().get::<Arrival>() {
+                    FormatText::Str(format!("{:.6}", millis(arrival.elapsed())))
+                } else {
+                    FormatText::Str("-".to_string())
+                };
+            }
             FormatText::RequestTime => {
                 *self = FormatText::Str(now.format("%Y-%m-%dT%H:%M:%S"))
             }
@@ -609,4 +912,166 @@ mod tests {
         let s = format!("{}", FormatDisplay(&render));
         assert!(s.contains(&format!("{}", now.format("%Y-%m-%dT%H:%M:%S"))));
     }
+
+    #[ntex_rt::test]
+    async fn test_queue_time_format() {
+        let mut format = Format::new("%Q");
+        let req = TestRequest::default().to_srv_request();
+        let now = OffsetDateTime::now();
+        for unit in &mut format.0 {
+            unit.render_request(now, &req);
+        }
+        assert!(matches!(format.0[0], FormatText::Str(ref s) if s == "-"));
+
+        let mut format = Format::new("%Q");
+        let req = TestRequest::default().to_srv_request();
+        req.extensions_mut()
+            .insert(Arrival::new(std::time::Instant::now()));
+        for unit in &mut format.0 {
+            unit.render_request(now, &req);
+        }
+        match format.0[0] {
+            FormatText::Str(ref s) => assert!(s.parse::<f64>().unwrap() >= 0.0),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn test_request_id_format() {
+        let mut format = Format::new("%{request_id}x %{other}x");
+        let id = RequestIdValue::generate();
+        for unit in &mut format.0 {
+            unit.render_request_id(&id);
+        }
+
+        let now = OffsetDateTime::now();
+        let render = |fmt: &mut Formatter<'_>| {
+            for unit in &format.0 {
+                unit.render(fmt, 1024, now)?;
+            }
+            Ok(())
+        };
+        let s = format!("{}", FormatDisplay(&render));
+        assert_eq!(s, format!("{} %{{other}}x", id));
+    }
+
+    #[test]
+    fn test_timing_format() {
+        let mut format = Format::new("%{db}m %{cache}m");
+        let timings = Timings::new();
+        timings.add("db", std::time::Duration::from_micros(1500));
+        for unit in &mut format.0 {
+            unit.render_timings(&timings);
+        }
+
+        let now = OffsetDateTime::now();
+        let render = |fmt: &mut Formatter<'_>| {
+            for unit in &format.0 {
+                unit.render(fmt, 1024, now)?;
+            }
+            Ok(())
+        };
+        let s = format!("{}", FormatDisplay(&render));
+        assert_eq!(s, "1.500000 -");
+    }
+
+    #[test]
+    fn test_memory_format() {
+        let mut format = Format::new("%M");
+        let usage = MemoryUsage::new();
+        usage
+            .allocate(super::super::MemoryKind::Body, 2048)
+            .unwrap();
+        for unit in &mut format.0 {
+            unit.render_memory(&usage);
+        }
+        assert!(matches!(format.0[0], FormatText::Str(ref s) if s == "2048"));
+    }
+
+    #[ntex_rt::test]
+    async fn test_custom_replace() {
+        let mut format = Logger::<DefaultError>::new("%{user}xi %{status}xo %{none}xi")
+            .custom_request_replace("user", |req| {
+                req.extensions().get::<u64>().unwrap().to_string()
+            })
+            .custom_response_replace("status", |res| res.status.to_string())
+            .inner
+            .format
+            .clone();
+
+        let req = TestRequest::default().to_srv_request();
+        req.extensions_mut().insert(10u64);
+        let resp = HttpResponse::build(StatusCode::NOT_FOUND).finish();
+        for unit in &mut format.0 {
+            unit.render_custom(req.head(), resp.head());
+        }
+
+        let now = OffsetDateTime::now();
+        let render = |fmt: &mut Formatter<'_>| {
+            for unit in &format.0 {
+                unit.render(fmt, 1024, now)?;
+            }
+            Ok(())
+        };
+        let s = format!("{}", FormatDisplay(&render));
+        assert_eq!(s, "10 404 Not Found -");
+    }
+
+    #[ntex_rt::test]
+    async fn test_exclude() {
+        let logger = Logger::<DefaultError>::default()
+            .exclude("/index")
+            .exclude_regex("^/health")
+            .exclude_regex("\\.png$")
+            .exclude_status(StatusCode::NOT_FOUND);
+        assert!(logger.inner.is_excluded("/index"));
+        assert!(logger.inner.is_excluded("/health/db"));
+        assert!(logger.inner.is_excluded("/static/logo.png"));
+        assert!(!logger.inner.is_excluded("/api/health"));
+
+        let srv = |req: WebRequest<DefaultError>| {
+            let status = if req.path() == "/missing" {
+                StatusCode::NOT_FOUND
+            } else {
+                StatusCode::OK
+            };
+            ok::<_, Error>(req.into_response(HttpResponse::build(status).finish()))
+        };
+        let srv = Transform::new_transform(&logger, srv.into_service())
+            .await
+            .unwrap();
+
+        let req = TestRequest::with_uri("/missing").to_srv_request();
+        let res = srv.call(req).await.unwrap();
+        assert!(res.response().body().as_ref().unwrap().format.is_none());
+
+        let req = TestRequest::with_uri("/health").to_srv_request();
+        let res = srv.call(req).await.unwrap();
+        assert!(res.response().body().as_ref().unwrap().format.is_none());
+
+        let req = TestRequest::with_uri("/test").to_srv_request();
+        let res = srv.call(req).await.unwrap();
+        assert!(res.response().body().as_ref().unwrap().format.is_some());
+    }
+
+    #[ntex_rt::test]
+    async fn test_writer() {
+        let records = Rc::new(std::cell::RefCell::new(Vec::new()));
+        let records2 = records.clone();
+        let logger = Logger::<DefaultError>::new("%s %U")
+            .writer(move |rec: &str| records2.borrow_mut().push(rec.to_string()));
+
+        let srv = |req: WebRequest<DefaultError>| {
+            ok::<_, Error>(req.into_response(HttpResponse::Ok().finish()))
+        };
+        let srv = Transform::new_transform(&logger, srv.into_service())
+            .await
+            .unwrap();
+
+        let req = TestRequest::with_uri("/test").to_srv_request();
+        let res = srv.call(req).await.unwrap();
+        assert!(records.borrow().is_empty());
+        drop(res);
+        assert_eq!(*records.borrow(), vec!["200 /test".to_string()]);
+    }
 }
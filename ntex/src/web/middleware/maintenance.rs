@@ -0,0 +1,270 @@
+//! Middleware for maintenance mode
+use std::convert::TryFrom;
+use std::marker::PhantomData;
+use std::rc::Rc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+use bytes::Bytes;
+use futures::future::{ok, Either, Ready};
+
+use crate::http::error::HttpError;
+use crate::http::header::{HeaderValue, CONTENT_TYPE, RETRY_AFTER};
+use crate::http::Response;
+use crate::service::{Service, Transform};
+use crate::web::dev::{WebRequest, WebResponse};
+
+/// Maintenance mode switch.
+///
+/// Switch could be cloned and shared between workers, for example it
+/// could be registered as application data and toggled from a handler.
+#[derive(Clone, Debug, Default)]
+pub struct MaintenanceMode(Arc<AtomicBool>);
+
+impl MaintenanceMode {
+    /// Create new switch, maintenance mode is disabled.
+    pub fn new() -> Self {
+        MaintenanceMode::default()
+    }
+
+    /// Enable maintenance mode.
+    pub fn enable(&self) {
+        self.0.store(true, Ordering::Release);
+    }
+
+    /// Disable maintenance mode.
+    pub fn disable(&self) {
+        self.0.store(false, Ordering::Release);
+    }
+
+    /// Check if maintenance mode is enabled.
+    pub fn is_enabled(&self) -> bool {
+        self.0.load(Ordering::Acquire)
+    }
+}
+
+/// `Middleware` for maintenance mode.
+///
+/// If maintenance mode is enabled, middleware responds with
+/// *503 Service Unavailable* response to all requests except requests
+/// for allowed paths (health endpoints, etc).
+///
+/// ```rust
+/// use ntex::web::{self, middleware, App, HttpResponse};
+///
+/// fn main() {
+///     let mode = middleware::MaintenanceMode::new();
+///
+///     let app = App::new()
+///         .data(mode.clone())
+///         .wrap(middleware::Maintenance::new(mode).allow("/health"))
+///         .service(web::resource("/health").to(|| async { HttpResponse::Ok() }))
+///         .service(web::resource("/test").to(|| async { HttpResponse::Ok() }));
+/// }
+/// ```
+pub struct Maintenance<E> {
+    inner: Rc<Inner>,
+    _t: PhantomData<E>,
+}
+
+struct Inner {
+    mode: MaintenanceMode,
+    allowed: Vec<String>,
+    retry_after: Option<HeaderValue>,
+    content_type: HeaderValue,
+    body: Bytes,
+}
+
+impl<E> Maintenance<E> {
+    /// Construct `Maintenance` middleware.
+    pub fn new(mode: MaintenanceMode) -> Self {
+        Maintenance {
+            inner: Rc::new(Inner {
+                mode,
+                allowed: Vec::new(),
+                retry_after: Some(HeaderValue::from_static("60")),
+                content_type: HeaderValue::from_static("text/plain; charset=utf-8"),
+                body: Bytes::from_static(b"Service is under maintenance"),
+            }),
+            _t: PhantomData,
+        }
+    }
+
+    /// Allow requests for specified path and all its sub-paths
+    /// while maintenance mode is enabled.
+    pub fn allow(mut self, path: &str) -> Self {
+        Rc::get_mut(&mut self.inner)
+            .expect("Multiple copies exist")
+            .allowed
+            .push(path.trim_end_matches('/').to_string());
+        self
+    }
+
+    /// Set *RETRY-AFTER* header value in seconds.
+    ///
+    /// To disable header set value to `None`. By default it is set to 60 seconds.
+    pub fn retry_after(mut self, secs: Option<u64>) -> Self {
+        Rc::get_mut(&mut self.inner)
+            .expect("Multiple copies exist")
+            .retry_after = secs.map(HeaderValue::from);
+        self
+    }
+
+    /// Set maintenance response body and its content type.
+    pub fn body<V, B>(mut self, content_type: V, body: B) -> Self
+    where
+        HeaderValue: TryFrom<V>,
+        <HeaderValue as TryFrom<V>>::Error: Into<HttpError>,
+        B: Into<Bytes>,
+    {
+        let inner = Rc::get_mut(&mut self.inner).expect("Multiple copies exist");
+        match HeaderValue::try_from(content_type) {
+            Ok(value) => inner.content_type = value,
+            Err(_) => panic!("Can not create header value"),
+        }
+        inner.body = body.into();
+        self
+    }
+}
+
+impl Inner {
+    fn is_allowed(&self, path: &str) -> bool {
+        self.allowed.iter().any(|prefix| {
+            path.starts_with(prefix.as_str())
+                && (path.len() == prefix.len() || path.as_bytes()[prefix.len()] == b'/')
+        })
+    }
+}
+
+impl<S, B, E> Transform<S> for Maintenance<E>
+where
+    S: Service<Request = WebRequest<E>, Response = WebResponse<B>>,
+{
+    type Request = WebRequest<E>;
+    type Response = WebResponse<B>;
+    type Error = S::Error;
+    type InitError = ();
+    type Transform = MaintenanceMiddleware<S, E>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ok(MaintenanceMiddleware {
+            service,
+            inner: self.inner.clone(),
+            _t: PhantomData,
+        })
+    }
+}
+
+pub struct MaintenanceMiddleware<S, E> {
+    service: S,
+    inner: Rc<Inner>,
+    _t: PhantomData<E>,
+}
+
+impl<S, B, E> Service for MaintenanceMiddleware<S, E>
+where
+    S: Service<Request = WebRequest<E>, Response = WebResponse<B>>,
+{
+    type Request = WebRequest<E>;
+    type Response = WebResponse<B>;
+    type Error = S::Error;
+    type Future = Either<S::Future, Ready<Result<Self::Response, Self::Error>>>;
+
+    #[inline]
+    fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(cx)
+    }
+
+    #[inline]
+    fn poll_shutdown(&self, cx: &mut Context<'_>, is_error: bool) -> Poll<()> {
+        self.service.poll_shutdown(cx, is_error)
+    }
+
+    fn call(&self, req: WebRequest<E>) -> Self::Future {
+        if self.inner.mode.is_enabled() && !self.inner.is_allowed(req.path()) {
+            let mut res = Response::ServiceUnavailable();
+            res.header(CONTENT_TYPE, self.inner.content_type.clone());
+            if let Some(ref val) = self.inner.retry_after {
+                res.header(RETRY_AFTER, val.clone());
+            }
+            let res = res.body(self.inner.body.clone());
+            Either::Right(ok(req.into_response(res.into_body())))
+        } else {
+            Either::Left(self.service.call(req))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::StatusCode;
+    use crate::web::test::{ok_service, read_body, TestRequest};
+    use crate::web::DefaultError;
+
+    #[ntex_rt::test]
+    async fn test_maintenance() {
+        let mode = MaintenanceMode::new();
+        let mw = Maintenance::<DefaultError>::new(mode.clone())
+            .allow("/health/")
+            .new_transform(ok_service())
+            .await
+            .unwrap();
+
+        let req = TestRequest::with_uri("/test").to_srv_request();
+        let resp = mw.call(req).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        mode.enable();
+        let req = TestRequest::with_uri("/test").to_srv_request();
+        let resp = mw.call(req).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(resp.headers().get(RETRY_AFTER).unwrap(), "60");
+        assert_eq!(
+            read_body(resp).await,
+            Bytes::from_static(b"Service is under maintenance")
+        );
+
+        let req = TestRequest::with_uri("/health").to_srv_request();
+        let resp = mw.call(req).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        let req = TestRequest::with_uri("/health/db").to_srv_request();
+        let resp = mw.call(req).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        let req = TestRequest::with_uri("/healthz").to_srv_request();
+        let resp = mw.call(req).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::SERVICE_UNAVAILABLE);
+
+        mode.disable();
+        let req = TestRequest::with_uri("/test").to_srv_request();
+        let resp = mw.call(req).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+    }
+
+    #[ntex_rt::test]
+    async fn test_maintenance_body() {
+        let mode = MaintenanceMode::new();
+        mode.enable();
+        let mw = Maintenance::<DefaultError>::new(mode)
+            .retry_after(None)
+            .body("application/json", "{\"error\":\"maintenance\"}")
+            .new_transform(ok_service())
+            .await
+            .unwrap();
+
+        let req = TestRequest::default().to_srv_request();
+        let resp = mw.call(req).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert!(resp.headers().get(RETRY_AFTER).is_none());
+        assert_eq!(
+            resp.headers().get(CONTENT_TYPE).unwrap(),
+            "application/json"
+        );
+        assert_eq!(
+            read_body(resp).await,
+            Bytes::from_static(b"{\"error\":\"maintenance\"}")
+        );
+    }
+}
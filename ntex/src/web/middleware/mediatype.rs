@@ -0,0 +1,199 @@
+//! Content type predicate for body transforming middlewares
+use std::fmt;
+use std::rc::Rc;
+
+use crate::http::header::{HeaderMap, CONTENT_TYPE};
+
+/// Set of media types that are subject to response body transformation.
+///
+/// Predicate is shared by middlewares that transform or inspect response
+/// body, like `Compress`, so allowlist is configured once. Pattern could be
+/// exact media type `application/json`, any subtype `text/*`, structured
+/// syntax suffix `*/*+json` or any media type `*/*`. Parameters are ignored,
+/// comparison is case insensitive. Denied patterns take precedence over
+/// allowed.
+///
+/// ```rust
+/// use ntex::web::{self, middleware, App, HttpResponse};
+///
+/// fn main() {
+///     let types = middleware::MediaTypePredicate::new()
+///         .allow("text/*")
+///         .allow("application/json")
+///         .allow("*/*+xml")
+///         .deny("text/event-stream");
+///
+///     let app = App::new()
+///         .wrap(middleware::Compress::default().media_types(types))
+///         .service(web::resource("/").to(|| async { HttpResponse::Ok() }));
+/// }
+/// ```
+#[derive(Clone)]
+pub struct MediaTypePredicate(Rc<Inner>);
+
+#[derive(Clone)]
+struct Inner {
+    allow: Vec<Pattern>,
+    deny: Vec<Pattern>,
+    missing: bool,
+}
+
+#[derive(Clone, Debug)]
+enum Pattern {
+    Any,
+    Type(String),
+    Suffix(String),
+    Exact(String, String),
+}
+
+impl Pattern {
+    fn parse(pattern: &str) -> Pattern {
+        let pattern = pattern.trim().to_ascii_lowercase();
+        let mut parts = pattern.splitn(2, '/');
+        let tp = parts.next().unwrap_or("").to_string();
+        let subtype = parts.next().unwrap_or("*").to_string();
+
+        if tp == "*" {
+            if subtype.starts_with("*+") {
+                Pattern::Suffix(subtype[2..].to_string())
+            } else {
+                Pattern::Any
+            }
+        } else if subtype == "*" {
+            Pattern::Type(tp)
+        } else {
+            Pattern::Exact(tp, subtype)
+        }
+    }
+
+    fn matches(&self, tp: &str, subtype: &str) -> bool {
+        match self {
+            Pattern::Any => true,
+            Pattern::Type(t) => t == tp,
+            Pattern::Suffix(s) => subtype
+                .rfind('+')
+                .map_or(false, |pos| &subtype[pos + 1..] == s),
+            Pattern::Exact(t, s) => t == tp && s == subtype,
+        }
+    }
+}
+
+impl MediaTypePredicate {
+    /// Create predicate that does not match any media type.
+    pub fn new() -> Self {
+        MediaTypePredicate(Rc::new(Inner {
+            allow: Vec::new(),
+            deny: Vec::new(),
+            missing: false,
+        }))
+    }
+
+    /// Create predicate that matches all responses.
+    pub fn any() -> Self {
+        MediaTypePredicate::new().allow("*/*").missing(true)
+    }
+
+    /// Create predicate for common compressible text based media types.
+    pub fn compressible() -> Self {
+        MediaTypePredicate::new()
+            .allow("text/*")
+            .allow("application/json")
+            .allow("application/javascript")
+            .allow("application/xml")
+            .allow("application/wasm")
+            .allow("image/svg+xml")
+            .allow("*/*+json")
+            .allow("*/*+xml")
+            .deny("text/event-stream")
+    }
+
+    /// Add allowed media type pattern.
+    pub fn allow(mut self, pattern: &str) -> Self {
+        Rc::make_mut(&mut self.0)
+            .allow
+            .push(Pattern::parse(pattern));
+        self
+    }
+
+    /// Add denied media type pattern.
+    pub fn deny(mut self, pattern: &str) -> Self {
+        Rc::make_mut(&mut self.0).deny.push(Pattern::parse(pattern));
+        self
+    }
+
+    /// Match responses without content type, by default disabled.
+    pub fn missing(mut self, val: bool) -> Self {
+        Rc::make_mut(&mut self.0).missing = val;
+        self
+    }
+
+    /// Check media type, parameters are ignored.
+    pub fn matches(&self, media_type: &str) -> bool {
+        let media_type = media_type.split(';').next().unwrap().trim();
+        let media_type = media_type.to_ascii_lowercase();
+        let mut parts = media_type.splitn(2, '/');
+        let tp = parts.next().unwrap_or("");
+        let subtype = parts.next().unwrap_or("");
+        if tp.is_empty() || subtype.is_empty() {
+            return false;
+        }
+
+        !self.0.deny.iter().any(|p| p.matches(tp, subtype))
+            && self.0.allow.iter().any(|p| p.matches(tp, subtype))
+    }
+
+    /// Check `Content-Type` header of message.
+    pub fn matches_headers(&self, headers: &HeaderMap) -> bool {
+        match headers.get(CONTENT_TYPE) {
+            Some(val) => val.to_str().map(|val| self.matches(val)).unwrap_or(false),
+            None => self.0.missing,
+        }
+    }
+}
+
+impl Default for MediaTypePredicate {
+    /// Same as `MediaTypePredicate::compressible()`
+    fn default() -> Self {
+        MediaTypePredicate::compressible()
+    }
+}
+
+impl fmt::Debug for MediaTypePredicate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MediaTypePredicate")
+            .field("allow", &self.0.allow)
+            .field("deny", &self.0.deny)
+            .field("missing", &self.0.missing)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::header::HeaderValue;
+
+    #[test]
+    fn test_media_type_predicate() {
+        let pred = MediaTypePredicate::compressible();
+        assert!(pred.matches("text/html; charset=utf-8"));
+        assert!(pred.matches("Application/JSON"));
+        assert!(pred.matches("application/problem+json"));
+        assert!(pred.matches("image/svg+xml"));
+        assert!(!pred.matches("text/event-stream"));
+        assert!(!pred.matches("image/png"));
+        assert!(!pred.matches("application/octet-stream"));
+        assert!(!pred.matches("invalid"));
+
+        let mut headers = HeaderMap::new();
+        assert!(!pred.matches_headers(&headers));
+        assert!(MediaTypePredicate::any().matches_headers(&headers));
+        headers.insert(CONTENT_TYPE, HeaderValue::from_static("text/plain"));
+        assert!(pred.matches_headers(&headers));
+
+        let pred = MediaTypePredicate::any().deny("image/*");
+        assert!(pred.matches("video/mp4"));
+        assert!(!pred.matches("image/jpeg"));
+        assert!(!MediaTypePredicate::new().matches("text/plain"));
+    }
+}
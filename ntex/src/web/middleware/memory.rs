@@ -0,0 +1,374 @@
+//! Middleware for per-request memory accounting
+use std::cell::Cell;
+use std::marker::PhantomData;
+use std::rc::Rc;
+use std::task::{Context, Poll};
+use std::{error, fmt, mem};
+
+use derive_more::Display;
+use futures::future::{ok, Ready};
+
+use crate::http::{Extensions, Payload};
+use crate::service::{Service, Transform};
+use crate::web::dev::{WebRequest, WebResponse};
+use crate::web::{ErrorRenderer, FromRequest, HttpRequest};
+
+/// Kind of accounted memory
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum MemoryKind {
+    /// Buffered request body
+    Body,
+    /// Request extension data
+    Extensions,
+}
+
+/// Error returned if per-request memory limit is exceeded
+#[derive(Copy, Clone, Debug, Display, PartialEq, Eq)]
+#[display(
+    fmt = "Request memory limit is exceeded, {} bytes requested, {} bytes allowed",
+    requested,
+    limit
+)]
+pub struct MemoryLimitError {
+    /// Total amount of memory including failed allocation
+    pub requested: usize,
+    /// Per-request memory limit
+    pub limit: usize,
+}
+
+impl error::Error for MemoryLimitError {}
+
+/// Per-request memory usage.
+///
+/// `MemoryAccounting` middleware stores `MemoryUsage` in request extensions.
+/// Extractors that buffer request body (`Bytes`, `String`, `Json`, `Form`)
+/// account buffered data automatically, handlers and middlewares could
+/// account extension data. `MemoryUsage` is a cheap handle, clones refer
+/// to the same counters. If middleware is not registered, extractor returns
+/// detached usage without limit.
+///
+/// ```rust
+/// use ntex::web::{self, middleware::MemoryUsage, HttpResponse};
+///
+/// struct Cache(Vec<u8>);
+///
+/// async fn index(
+///     req: web::HttpRequest,
+///     usage: MemoryUsage,
+/// ) -> Result<HttpResponse, web::Error> {
+///     let data = vec![0; 1024];
+///     usage.allocate(web::middleware::MemoryKind::Extensions, data.len())?;
+///     req.extensions_mut().insert(Cache(data));
+///
+///     Ok(HttpResponse::Ok().body(format!("{} bytes in use", usage.total())))
+/// }
+/// # fn main() {}
+/// ```
+#[derive(Clone, Default)]
+pub struct MemoryUsage(Rc<Usage>);
+
+#[derive(Default)]
+struct Usage {
+    body: Cell<usize>,
+    extensions: Cell<usize>,
+    peak: Cell<usize>,
+    limit: Option<usize>,
+}
+
+impl MemoryUsage {
+    /// Create memory usage counters without limit
+    pub fn new() -> Self {
+        MemoryUsage::default()
+    }
+
+    /// Create memory usage counters with limit in bytes
+    pub fn with_limit(limit: usize) -> Self {
+        MemoryUsage(Rc::new(Usage {
+            limit: Some(limit),
+            ..Default::default()
+        }))
+    }
+
+    /// Per-request memory limit
+    pub fn limit(&self) -> Option<usize> {
+        self.0.limit
+    }
+
+    /// Bytes allocated for buffered request body
+    pub fn body(&self) -> usize {
+        self.0.body.get()
+    }
+
+    /// Bytes allocated for extension data
+    pub fn extensions(&self) -> usize {
+        self.0.extensions.get()
+    }
+
+    /// Total bytes currently accounted
+    pub fn total(&self) -> usize {
+        self.body() + self.extensions()
+    }
+
+    /// Max total bytes accounted during request processing
+    pub fn peak(&self) -> usize {
+        self.0.peak.get()
+    }
+
+    /// Account allocated memory.
+    ///
+    /// Returns error and does not change counters if allocation
+    /// exceeds memory limit.
+    pub fn allocate(
+        &self,
+        kind: MemoryKind,
+        size: usize,
+    ) -> Result<(), MemoryLimitError> {
+        let total = self.total() + size;
+        if let Some(limit) = self.0.limit {
+            if total > limit {
+                return Err(MemoryLimitError {
+                    limit,
+                    requested: total,
+                });
+            }
+        }
+
+        let counter = self.counter(kind);
+        counter.set(counter.get() + size);
+        if total > self.0.peak.get() {
+            self.0.peak.set(total);
+        }
+        Ok(())
+    }
+
+    /// Account released memory
+    pub fn release(&self, kind: MemoryKind, size: usize) {
+        let counter = self.counter(kind);
+        counter.set(counter.get().saturating_sub(size));
+    }
+
+    /// Insert value to extensions and account its size.
+    ///
+    /// Only inline size of the value is accounted, heap data owned
+    /// by the value must be accounted with `allocate()` method.
+    pub fn insert<T: 'static>(
+        &self,
+        extensions: &mut Extensions,
+        value: T,
+    ) -> Result<(), MemoryLimitError> {
+        self.allocate(MemoryKind::Extensions, mem::size_of::<T>())?;
+        if let Some(prev) = extensions.remove::<T>() {
+            self.release(MemoryKind::Extensions, mem::size_of_val(&prev));
+        }
+        extensions.insert(value);
+        Ok(())
+    }
+
+    fn counter(&self, kind: MemoryKind) -> &Cell<usize> {
+        match kind {
+            MemoryKind::Body => &self.0.body,
+            MemoryKind::Extensions => &self.0.extensions,
+        }
+    }
+}
+
+impl fmt::Debug for MemoryUsage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MemoryUsage")
+            .field("body", &self.body())
+            .field("extensions", &self.extensions())
+            .field("peak", &self.peak())
+            .field("limit", &self.limit())
+            .finish()
+    }
+}
+
+impl<Err: ErrorRenderer> FromRequest<Err> for MemoryUsage {
+    type Error = Err::Container;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    #[inline]
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        ok(req
+            .extensions()
+            .get::<MemoryUsage>()
+            .cloned()
+            .unwrap_or_default())
+    }
+}
+
+/// Account buffered body chunk, if memory accounting is enabled
+pub(crate) fn account_body(
+    usage: &Option<MemoryUsage>,
+    size: usize,
+) -> Result<(), MemoryLimitError> {
+    if let Some(ref usage) = usage {
+        usage.allocate(MemoryKind::Body, size)
+    } else {
+        Ok(())
+    }
+}
+
+/// `Middleware` for per-request memory accounting.
+///
+/// Middleware adds `MemoryUsage` to request extensions. Peak usage could be
+/// logged with `%M` token of `Logger` middleware. If limit is set,
+/// body extractors fail with payload overflow error once request
+/// exceeds the limit.
+///
+/// ```rust
+/// use ntex::web::{self, middleware, App, HttpResponse};
+///
+/// fn main() {
+///     let app = App::new()
+///         .wrap(middleware::Logger::new("%r %s %M"))
+///         .wrap(middleware::MemoryAccounting::new().limit(1024 * 1024))
+///         .service(web::resource("/").to(|body: String| async move {
+///             HttpResponse::Ok().body(body)
+///         }));
+/// }
+/// ```
+pub struct MemoryAccounting<Err> {
+    limit: Option<usize>,
+    _t: PhantomData<Err>,
+}
+
+impl<Err> MemoryAccounting<Err> {
+    /// Construct `MemoryAccounting` middleware without limit
+    pub fn new() -> Self {
+        MemoryAccounting {
+            limit: None,
+            _t: PhantomData,
+        }
+    }
+
+    /// Set per-request memory limit in bytes
+    pub fn limit(mut self, limit: usize) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+}
+
+impl<Err> Default for MemoryAccounting<Err> {
+    fn default() -> Self {
+        MemoryAccounting::new()
+    }
+}
+
+impl<S, B, E> Transform<S> for MemoryAccounting<E>
+where
+    S: Service<Request = WebRequest<E>, Response = WebResponse<B>>,
+{
+    type Request = WebRequest<E>;
+    type Response = WebResponse<B>;
+    type Error = S::Error;
+    type InitError = ();
+    type Transform = MemoryAccountingMiddleware<S, E>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ok(MemoryAccountingMiddleware {
+            service,
+            limit: self.limit,
+            _t: PhantomData,
+        })
+    }
+}
+
+pub struct MemoryAccountingMiddleware<S, E> {
+    service: S,
+    limit: Option<usize>,
+    _t: PhantomData<E>,
+}
+
+impl<S, B, E> Service for MemoryAccountingMiddleware<S, E>
+where
+    S: Service<Request = WebRequest<E>, Response = WebResponse<B>>,
+{
+    type Request = WebRequest<E>;
+    type Response = WebResponse<B>;
+    type Error = S::Error;
+    type Future = S::Future;
+
+    #[inline]
+    fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(cx)
+    }
+
+    #[inline]
+    fn poll_shutdown(&self, cx: &mut Context<'_>, is_error: bool) -> Poll<()> {
+        self.service.poll_shutdown(cx, is_error)
+    }
+
+    fn call(&self, req: WebRequest<E>) -> Self::Future {
+        let usage = if let Some(limit) = self.limit {
+            MemoryUsage::with_limit(limit)
+        } else {
+            MemoryUsage::new()
+        };
+        req.extensions_mut().insert(usage);
+        self.service.call(req)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+
+    use super::*;
+    use crate::http::StatusCode;
+    use crate::web::test::{call_service, init_service, read_body, TestRequest};
+    use crate::web::{self, App, DefaultError, HttpResponse};
+
+    #[test]
+    fn test_usage() {
+        let usage = MemoryUsage::with_limit(100);
+        usage.allocate(MemoryKind::Body, 60).unwrap();
+        usage.allocate(MemoryKind::Extensions, 30).unwrap();
+        assert_eq!(
+            usage.allocate(MemoryKind::Body, 20),
+            Err(MemoryLimitError {
+                requested: 110,
+                limit: 100
+            })
+        );
+        assert_eq!(usage.body(), 60);
+        assert_eq!(usage.extensions(), 30);
+        assert_eq!(usage.total(), 90);
+
+        usage.release(MemoryKind::Extensions, 30);
+        assert_eq!(usage.total(), 60);
+        assert_eq!(usage.peak(), 90);
+
+        let mut ext = Extensions::new();
+        usage.insert(&mut ext, 1u64).unwrap();
+        usage.insert(&mut ext, 2u64).unwrap();
+        assert_eq!(ext.get::<u64>(), Some(&2));
+        assert_eq!(usage.extensions(), 8);
+    }
+
+    #[ntex_rt::test]
+    async fn test_memory_accounting() {
+        let srv = init_service(
+            App::new()
+                .wrap(MemoryAccounting::<DefaultError>::new().limit(16))
+                .service(web::resource("/").to(
+                    |_: web::types::Json<String>, usage: MemoryUsage| async move {
+                        HttpResponse::Ok().body(format!("{}", usage.body()))
+                    },
+                )),
+        )
+        .await;
+
+        let req = TestRequest::default().set_json(&"0123456789").to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(read_body(resp).await, Bytes::from_static(b"12"));
+
+        let req = TestRequest::default()
+            .set_json(&"0123456789abcdef")
+            .to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::PAYLOAD_TOO_LARGE);
+    }
+}
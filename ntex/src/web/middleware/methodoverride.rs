@@ -0,0 +1,321 @@
+//! Middleware for http method override
+use std::marker::PhantomData;
+use std::rc::Rc;
+use std::task::{Context, Poll};
+
+use bytes::BytesMut;
+use futures::future::{ok, Either, FutureExt, LocalBoxFuture, Ready};
+use futures::{stream, StreamExt};
+
+use crate::http::header::{HeaderName, CONTENT_TYPE};
+use crate::http::{Method, Payload, Response, StatusCode};
+use crate::service::{Service, Transform};
+use crate::web::dev::{WebRequest, WebResponse};
+
+const X_HTTP_METHOD_OVERRIDE: &str = "x-http-method-override";
+
+/// `Middleware` for http method override.
+///
+/// Clients that can send only *GET* and *POST* requests, like html forms,
+/// could tunnel other methods through *POST* request. Middleware replaces
+/// method of *POST* request with value of *X-HTTP-Method-Override* header,
+/// or with value of `_method` field of urlencoded form if form support is
+/// enabled. Override is applied only if target method is in allowlist, by
+/// default allowlist contains *PUT*, *PATCH* and *DELETE* methods.
+///
+/// Middleware must be registered on application level, so override happens
+/// before routing.
+///
+/// ```rust
+/// use ntex::http::Method;
+/// use ntex::web::{self, middleware, App, HttpResponse};
+///
+/// fn main() {
+///     let app = App::new()
+///         .wrap(
+///             middleware::MethodOverride::new()
+///                 .methods(vec![Method::PUT, Method::DELETE])
+///                 .form_field(true),
+///         )
+///         .service(
+///             web::resource("/item")
+///                 .route(web::put().to(|| async { HttpResponse::Ok() }))
+///                 .route(web::delete().to(|| async { HttpResponse::NoContent() })),
+///         );
+/// }
+/// ```
+pub struct MethodOverride<E> {
+    inner: Rc<Inner>,
+    _t: PhantomData<E>,
+}
+
+struct Inner {
+    methods: Vec<Method>,
+    form: bool,
+    limit: usize,
+}
+
+impl<E> Default for MethodOverride<E> {
+    fn default() -> Self {
+        MethodOverride {
+            inner: Rc::new(Inner {
+                methods: vec![Method::PUT, Method::PATCH, Method::DELETE],
+                form: false,
+                limit: 16_384,
+            }),
+            _t: PhantomData,
+        }
+    }
+}
+
+impl<E> MethodOverride<E> {
+    /// Construct `MethodOverride` middleware with default allowlist.
+    pub fn new() -> Self {
+        MethodOverride::default()
+    }
+
+    /// Set allowlist of override target methods.
+    pub fn methods<I: IntoIterator<Item = Method>>(mut self, methods: I) -> Self {
+        Rc::get_mut(&mut self.inner)
+            .expect("Multiple copies exist")
+            .methods = methods.into_iter().collect();
+        self
+    }
+
+    /// Use `_method` field of urlencoded form body, by default disabled.
+    ///
+    /// Middleware reads whole form body to check the field, body is passed
+    /// to inner service unchanged.
+    pub fn form_field(mut self, enabled: bool) -> Self {
+        Rc::get_mut(&mut self.inner)
+            .expect("Multiple copies exist")
+            .form = enabled;
+        self
+    }
+
+    /// Set max size of form body, by default 16Kb.
+    ///
+    /// Larger forms get rejected with *413 Payload Too Large* response.
+    pub fn limit(mut self, limit: usize) -> Self {
+        Rc::get_mut(&mut self.inner)
+            .expect("Multiple copies exist")
+            .limit = limit;
+        self
+    }
+}
+
+impl Inner {
+    /// Parse method and check allowlist
+    fn method(&self, value: &[u8]) -> Option<Method> {
+        let method = Method::from_bytes(&value.to_ascii_uppercase()).ok()?;
+        if self.methods.contains(&method) {
+            Some(method)
+        } else {
+            log::debug!("Method override to {} is not allowed", method);
+            None
+        }
+    }
+
+    fn is_form<E>(&self, req: &WebRequest<E>) -> bool {
+        self.form
+            && req
+                .headers()
+                .get(CONTENT_TYPE)
+                .and_then(|v| v.to_str().ok())
+                .map_or(false, |v| {
+                    v.split(';')
+                        .next()
+                        .unwrap()
+                        .trim()
+                        .eq_ignore_ascii_case("application/x-www-form-urlencoded")
+                })
+    }
+}
+
+impl<S, B, E> Transform<S> for MethodOverride<E>
+where
+    S: Service<Request = WebRequest<E>, Response = WebResponse<B>> + 'static,
+    E: 'static,
+{
+    type Request = WebRequest<E>;
+    type Response = WebResponse<B>;
+    type Error = S::Error;
+    type InitError = ();
+    type Transform = MethodOverrideMiddleware<S, E>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ok(MethodOverrideMiddleware {
+            service: Rc::new(service),
+            inner: self.inner.clone(),
+            _t: PhantomData,
+        })
+    }
+}
+
+pub struct MethodOverrideMiddleware<S, E> {
+    service: Rc<S>,
+    inner: Rc<Inner>,
+    _t: PhantomData<E>,
+}
+
+impl<S, B, E> Service for MethodOverrideMiddleware<S, E>
+where
+    S: Service<Request = WebRequest<E>, Response = WebResponse<B>> + 'static,
+    E: 'static,
+{
+    type Request = WebRequest<E>;
+    type Response = WebResponse<B>;
+    type Error = S::Error;
+    type Future =
+        Either<S::Future, LocalBoxFuture<'static, Result<Self::Response, Self::Error>>>;
+
+    #[inline]
+    fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(cx)
+    }
+
+    #[inline]
+    fn poll_shutdown(&self, cx: &mut Context<'_>, is_error: bool) -> Poll<()> {
+        self.service.poll_shutdown(cx, is_error)
+    }
+
+    fn call(&self, mut req: WebRequest<E>) -> Self::Future {
+        if req.method() != Method::POST {
+            return Either::Left(self.service.call(req));
+        }
+
+        let header = req
+            .headers()
+            .get(HeaderName::from_static(X_HTTP_METHOD_OVERRIDE))
+            .map(|v| self.inner.method(v.as_bytes()));
+        if let Some(method) = header {
+            if let Some(method) = method {
+                req.head_mut().method = method;
+            }
+            return Either::Left(self.service.call(req));
+        }
+
+        if !self.inner.is_form(&req) {
+            return Either::Left(self.service.call(req));
+        }
+
+        let srv = self.service.clone();
+        let inner = self.inner.clone();
+
+        async move {
+            let mut payload = req.take_payload();
+            let mut body = BytesMut::new();
+            while let Some(chunk) = payload.next().await {
+                match chunk {
+                    Ok(chunk) => {
+                        if body.len() + chunk.len() > inner.limit {
+                            let res = Response::new(StatusCode::PAYLOAD_TOO_LARGE);
+                            return Ok(req.into_response(res.into_body()));
+                        }
+                        body.extend_from_slice(&chunk);
+                    }
+                    Err(e) => {
+                        log::debug!("Cannot read form body: {:?}", e);
+                        let res = Response::new(StatusCode::BAD_REQUEST);
+                        return Ok(req.into_response(res.into_body()));
+                    }
+                }
+            }
+            let body = body.freeze();
+
+            let method = serde_urlencoded::from_bytes::<Vec<(String, String)>>(&body)
+                .unwrap_or_default()
+                .into_iter()
+                .find(|(key, _)| key == "_method")
+                .and_then(|(_, value)| inner.method(value.as_bytes()));
+            if let Some(method) = method {
+                req.head_mut().method = method;
+            }
+
+            // pass body to inner service
+            req.set_payload(Payload::Stream(Box::pin(stream::once(ok(body)))));
+            srv.call(req).await
+        }
+        .boxed_local()
+        .right_future()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::web::test::{call_service, init_service, read_body, TestRequest};
+    use crate::web::{self, App, DefaultError, HttpResponse};
+
+    #[ntex_rt::test]
+    async fn test_method_override() {
+        let srv = init_service(
+            App::new()
+                .wrap(
+                    MethodOverride::<DefaultError>::new()
+                        .methods(vec![Method::PUT, Method::DELETE])
+                        .form_field(true)
+                        .limit(64),
+                )
+                .service(
+                    web::resource("/")
+                        .route(
+                            web::get().to(|| async { HttpResponse::Ok().body("get") }),
+                        )
+                        .route(web::put().to(|body: String| async move {
+                            HttpResponse::Ok().body(format!("put {}", body))
+                        }))
+                        .route(
+                            web::delete()
+                                .to(|| async { HttpResponse::Ok().body("delete") }),
+                        )
+                        .route(
+                            web::post().to(|| async { HttpResponse::Ok().body("post") }),
+                        ),
+                ),
+        )
+        .await;
+
+        let req = TestRequest::post()
+            .header(X_HTTP_METHOD_OVERRIDE, "delete")
+            .to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(read_body(resp).await, "delete");
+
+        // not in allowlist
+        let req = TestRequest::post()
+            .header(X_HTTP_METHOD_OVERRIDE, "PATCH")
+            .to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(read_body(resp).await, "post");
+
+        // only post requests could be overridden
+        let req = TestRequest::get()
+            .header(X_HTTP_METHOD_OVERRIDE, "DELETE")
+            .to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(read_body(resp).await, "get");
+
+        let req = TestRequest::post()
+            .header(CONTENT_TYPE, "application/x-www-form-urlencoded")
+            .set_payload("name=test&_method=PUT")
+            .to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(read_body(resp).await, "put name=test&_method=PUT");
+
+        let req = TestRequest::post()
+            .header(CONTENT_TYPE, "application/x-www-form-urlencoded")
+            .set_payload("name=test")
+            .to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(read_body(resp).await, "post");
+
+        let req = TestRequest::post()
+            .header(CONTENT_TYPE, "application/x-www-form-urlencoded")
+            .set_payload(format!("_method=PUT&name={}", "a".repeat(64)))
+            .to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::PAYLOAD_TOO_LARGE);
+    }
+}
@@ -0,0 +1,499 @@
+//! Middleware for request, error and panic metrics
+use std::collections::BTreeMap;
+use std::fmt;
+use std::marker::PhantomData;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+
+use futures::future::{ok, FutureExt, LocalBoxFuture, Ready};
+
+use crate::service::{Service, Transform};
+use crate::web::dev::{WebRequest, WebResponse};
+use crate::web::report::{ExtractorFailure, RenderedError};
+use crate::web::ErrorRenderer;
+
+/// Default latency histogram buckets, in milliseconds
+const BUCKETS: [u64; 11] = [5, 10, 25, 50, 100, 250, 500, 1000, 2500, 5000, 10000];
+
+/// `Middleware` for request, error and panic metrics.
+///
+/// Middleware counts requests, request latency, responses by status class,
+/// handler panics, extractor failures by extractor type and responses
+/// rendered by error renderer by status class. Latency is measured until
+/// response head is ready, body streaming is not included. Errors of inner
+/// services are rendered to responses, panics are counted and resumed.
+///
+/// Metrics are shared between clones, so single instance could be used
+/// for all workers. `snapshot()` returns current values, snapshot's
+/// `Display` implementation renders prometheus text format.
+///
+/// ```rust
+/// use ntex::web::{self, middleware, App, HttpResponse};
+///
+/// fn main() {
+///     let metrics = middleware::Metrics::new();
+///     let m = metrics.clone();
+///
+///     let app = App::new()
+///         .wrap(metrics)
+///         .service(web::resource("/metrics").to(move || {
+///             let snapshot = m.snapshot();
+///             async move { HttpResponse::Ok().body(snapshot.to_string()) }
+///         }));
+/// }
+/// ```
+pub struct Metrics<E> {
+    inner: Arc<Inner>,
+    _t: PhantomData<E>,
+}
+
+struct Inner {
+    buckets: Vec<(Duration, AtomicU64)>,
+    requests: AtomicU64,
+    latency: AtomicU64,
+    responses: [AtomicU64; 5],
+    rendered: [AtomicU64; 5],
+    panics: AtomicU64,
+    extractors: Mutex<BTreeMap<&'static str, u64>>,
+}
+
+impl<E> Default for Metrics<E> {
+    fn default() -> Self {
+        Metrics {
+            inner: Arc::new(Inner {
+                buckets: BUCKETS
+                    .iter()
+                    .map(|ms| (Duration::from_millis(*ms), AtomicU64::new(0)))
+                    .collect(),
+                requests: AtomicU64::new(0),
+                latency: AtomicU64::new(0),
+                responses: Default::default(),
+                rendered: Default::default(),
+                panics: AtomicU64::new(0),
+                extractors: Mutex::new(BTreeMap::new()),
+            }),
+            _t: PhantomData,
+        }
+    }
+}
+
+impl<E> Clone for Metrics<E> {
+    fn clone(&self) -> Self {
+        Metrics {
+            inner: self.inner.clone(),
+            _t: PhantomData,
+        }
+    }
+}
+
+impl<E> Metrics<E> {
+    /// Construct `Metrics` middleware.
+    pub fn new() -> Self {
+        Metrics::default()
+    }
+
+    /// Set upper bounds of latency histogram buckets.
+    ///
+    /// By default buckets are from 5 milliseconds to 10 seconds.
+    pub fn buckets(mut self, buckets: &[Duration]) -> Self {
+        let mut buckets = buckets.to_vec();
+        buckets.sort();
+        buckets.dedup();
+        Arc::get_mut(&mut self.inner)
+            .expect("Multiple copies exist")
+            .buckets = buckets
+            .into_iter()
+            .map(|bound| (bound, AtomicU64::new(0)))
+            .collect();
+        self
+    }
+
+    /// Current metrics values
+    pub fn snapshot(&self) -> MetricsSnapshot {
+        let inner = &self.inner;
+        let mut count = 0;
+        let buckets = inner
+            .buckets
+            .iter()
+            .map(|(bound, cnt)| {
+                count += cnt.load(Ordering::Relaxed);
+                (*bound, count)
+            })
+            .collect();
+
+        MetricsSnapshot {
+            buckets,
+            requests: inner.requests.load(Ordering::Relaxed),
+            latency: Duration::from_micros(inner.latency.load(Ordering::Relaxed)),
+            responses: load(&inner.responses),
+            rendered: load(&inner.rendered),
+            panics: inner.panics.load(Ordering::Relaxed),
+            extractors: inner.extractors.lock().unwrap().clone(),
+        }
+    }
+}
+
+fn load(counters: &[AtomicU64; 5]) -> [u64; 5] {
+    let mut values = [0; 5];
+    for (val, cnt) in values.iter_mut().zip(counters.iter()) {
+        *val = cnt.load(Ordering::Relaxed);
+    }
+    values
+}
+
+/// Index of status class, `None` for invalid status codes
+fn class_idx(status: u16) -> Option<usize> {
+    match status / 100 {
+        cls @ 1..=5 => Some(cls as usize - 1),
+        _ => None,
+    }
+}
+
+impl Inner {
+    fn record<B>(&self, res: &WebResponse<B>, latency: Duration) {
+        self.latency
+            .fetch_add(latency.as_micros() as u64, Ordering::Relaxed);
+        if let Some((_, cnt)) = self.buckets.iter().find(|(bound, _)| latency <= *bound)
+        {
+            cnt.fetch_add(1, Ordering::Relaxed);
+        }
+
+        let class = class_idx(res.status().as_u16());
+        if let Some(idx) = class {
+            self.responses[idx].fetch_add(1, Ordering::Relaxed);
+        }
+
+        let extensions = res.request().extensions();
+        if extensions.contains::<RenderedError>() {
+            if let Some(idx) = class {
+                self.rendered[idx].fetch_add(1, Ordering::Relaxed);
+            }
+        }
+        if let Some(failure) = extensions.get::<ExtractorFailure>() {
+            *self
+                .extractors
+                .lock()
+                .unwrap()
+                .entry(failure.0)
+                .or_insert(0) += 1;
+        }
+    }
+}
+
+/// Metrics values, check [`Metrics::snapshot()`](struct.Metrics.html#method.snapshot)
+#[derive(Debug, Clone)]
+pub struct MetricsSnapshot {
+    buckets: Vec<(Duration, u64)>,
+    requests: u64,
+    latency: Duration,
+    responses: [u64; 5],
+    rendered: [u64; 5],
+    panics: u64,
+    extractors: BTreeMap<&'static str, u64>,
+}
+
+impl MetricsSnapshot {
+    /// Number of started requests
+    pub fn requests(&self) -> u64 {
+        self.requests
+    }
+
+    /// Total latency of completed requests
+    pub fn latency(&self) -> Duration {
+        self.latency
+    }
+
+    /// Latency histogram, cumulative number of completed requests
+    /// for each bucket's upper bound
+    pub fn latency_buckets(&self) -> &[(Duration, u64)] {
+        &self.buckets
+    }
+
+    /// Number of responses by status class, first element is for *1xx*
+    /// responses, last one is for *5xx* responses
+    pub fn responses(&self) -> &[u64; 5] {
+        &self.responses
+    }
+
+    /// Number of responses rendered by error renderer by status class
+    pub fn rendered_errors(&self) -> &[u64; 5] {
+        &self.rendered
+    }
+
+    /// Number of handler panics
+    pub fn panics(&self) -> u64 {
+        self.panics
+    }
+
+    /// Number of extractor failures by extractor type name
+    pub fn extractor_failures(&self) -> &BTreeMap<&'static str, u64> {
+        &self.extractors
+    }
+}
+
+impl fmt::Display for MetricsSnapshot {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "# TYPE http_requests_total counter")?;
+        writeln!(f, "http_requests_total {}", self.requests)?;
+
+        let completed = self.responses.iter().sum::<u64>();
+        writeln!(f, "# TYPE http_request_duration_seconds histogram")?;
+        for (bound, count) in &self.buckets {
+            writeln!(
+                f,
+                "http_request_duration_seconds_bucket{{le=\"{}\"}} {}",
+                bound.as_secs_f64(),
+                count
+            )?;
+        }
+        writeln!(
+            f,
+            "http_request_duration_seconds_bucket{{le=\"+Inf\"}} {}",
+            completed
+        )?;
+        writeln!(
+            f,
+            "http_request_duration_seconds_sum {}",
+            self.latency.as_secs_f64()
+        )?;
+        writeln!(f, "http_request_duration_seconds_count {}", completed)?;
+
+        writeln!(f, "# TYPE http_responses_total counter")?;
+        for (idx, count) in self.responses.iter().enumerate() {
+            writeln!(
+                f,
+                "http_responses_total{{class=\"{}xx\"}} {}",
+                idx + 1,
+                count
+            )?;
+        }
+
+        writeln!(f, "# TYPE http_rendered_errors_total counter")?;
+        for (idx, count) in self.rendered.iter().enumerate() {
+            writeln!(
+                f,
+                "http_rendered_errors_total{{class=\"{}xx\"}} {}",
+                idx + 1,
+                count
+            )?;
+        }
+
+        writeln!(f, "# TYPE http_handler_panics_total counter")?;
+        writeln!(f, "http_handler_panics_total {}", self.panics)?;
+
+        writeln!(f, "# TYPE http_extractor_failures_total counter")?;
+        for (name, count) in &self.extractors {
+            writeln!(
+                f,
+                "http_extractor_failures_total{{extractor=\"{}\"}} {}",
+                name.replace('\\', "\\\\").replace('"', "\\\""),
+                count
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl<S, B, E> Transform<S> for Metrics<E>
+where
+    S: Service<Request = WebRequest<E>, Response = WebResponse<B>>,
+    S::Future: 'static,
+    S::Error: Into<E::Container>,
+    B: 'static,
+    E: ErrorRenderer,
+{
+    type Request = WebRequest<E>;
+    type Response = WebResponse<B>;
+    type Error = S::Error;
+    type InitError = ();
+    type Transform = MetricsMiddleware<S, E>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ok(MetricsMiddleware {
+            service,
+            inner: self.inner.clone(),
+            _t: PhantomData,
+        })
+    }
+}
+
+pub struct MetricsMiddleware<S, E> {
+    service: S,
+    inner: Arc<Inner>,
+    _t: PhantomData<E>,
+}
+
+impl<S, B, E> Service for MetricsMiddleware<S, E>
+where
+    S: Service<Request = WebRequest<E>, Response = WebResponse<B>>,
+    S::Future: 'static,
+    S::Error: Into<E::Container>,
+    B: 'static,
+    E: ErrorRenderer,
+{
+    type Request = WebRequest<E>;
+    type Response = WebResponse<B>;
+    type Error = S::Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    #[inline]
+    fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(cx)
+    }
+
+    #[inline]
+    fn poll_shutdown(&self, cx: &mut Context<'_>, is_error: bool) -> Poll<()> {
+        self.service.poll_shutdown(cx, is_error)
+    }
+
+    fn call(&self, req: WebRequest<E>) -> Self::Future {
+        let inner = self.inner.clone();
+        inner.requests.fetch_add(1, Ordering::Relaxed);
+
+        let start = Instant::now();
+        let detached = req.detach();
+        // service could panic before future is created
+        let fut = match panic::catch_unwind(AssertUnwindSafe(|| self.service.call(req)))
+        {
+            Ok(fut) => fut,
+            Err(panic) => {
+                inner.panics.fetch_add(1, Ordering::Relaxed);
+                panic::resume_unwind(panic)
+            }
+        };
+
+        async move {
+            let res = match AssertUnwindSafe(fut).catch_unwind().await {
+                Ok(Ok(res)) => res,
+                Ok(Err(e)) => detached.error_response::<E, B>(e.into()),
+                Err(panic) => {
+                    inner.panics.fetch_add(1, Ordering::Relaxed);
+                    panic::resume_unwind(panic)
+                }
+            };
+            inner.record(&res, start.elapsed());
+            Ok(res)
+        }
+        .boxed_local()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::StatusCode;
+    use crate::web::test::{call_service, init_service, TestRequest};
+    use crate::web::{self, types, App, DefaultError, HttpResponse};
+
+    #[test]
+    fn test_class_idx() {
+        assert_eq!(class_idx(100), Some(0));
+        assert_eq!(class_idx(204), Some(1));
+        assert_eq!(class_idx(599), Some(4));
+        assert_eq!(class_idx(600), None);
+    }
+
+    #[ntex_rt::test]
+    async fn test_metrics() {
+        let metrics = Metrics::<DefaultError>::new();
+        let srv = init_service(
+            App::new()
+                .wrap(metrics.clone())
+                .service(web::resource("/").to(|| async { HttpResponse::Ok() }))
+                .service(
+                    web::resource("/num/{num}")
+                        .to(|num: types::Path<u32>| async move { format!("{}", num) }),
+                )
+                .service(web::resource("/error").to(|| async {
+                    Err::<HttpResponse, _>(web::error::ErrorInternalServerError::<
+                        _,
+                        DefaultError,
+                    >("error"))
+                }))
+                .service(web::resource("/panic").to(|| async {
+                    if true {
+                        panic!("handler panic");
+                    }
+                    HttpResponse::Ok()
+                })),
+        )
+        .await;
+
+        let req = TestRequest::with_uri("/").to_request();
+        assert_eq!(call_service(&srv, req).await.status(), StatusCode::OK);
+        let req = TestRequest::with_uri("/num/10").to_request();
+        assert_eq!(call_service(&srv, req).await.status(), StatusCode::OK);
+        let req = TestRequest::with_uri("/num/abc").to_request();
+        assert_eq!(
+            call_service(&srv, req).await.status(),
+            StatusCode::NOT_FOUND
+        );
+        let req = TestRequest::with_uri("/error").to_request();
+        assert_eq!(
+            call_service(&srv, req).await.status(),
+            StatusCode::INTERNAL_SERVER_ERROR
+        );
+        let req = TestRequest::with_uri("/missing").to_request();
+        assert_eq!(
+            call_service(&srv, req).await.status(),
+            StatusCode::NOT_FOUND
+        );
+
+        let req = TestRequest::with_uri("/panic").to_request();
+        let res = AssertUnwindSafe(call_service(&srv, req))
+            .catch_unwind()
+            .await;
+        assert!(res.is_err());
+
+        let snapshot = metrics.snapshot();
+        assert_eq!(snapshot.requests(), 6);
+        assert_eq!(snapshot.responses(), &[0, 2, 0, 2, 1]);
+        assert_eq!(snapshot.rendered_errors(), &[0, 0, 0, 2, 1]);
+        assert_eq!(snapshot.panics(), 1);
+        assert_eq!(snapshot.latency_buckets().last().unwrap().1, 5);
+        let failures = snapshot.extractor_failures();
+        assert_eq!(failures.len(), 1);
+        assert_eq!(
+            failures.get(std::any::type_name::<types::Path<u32>>()),
+            Some(&1)
+        );
+
+        let text = snapshot.to_string();
+        assert!(text.contains("http_requests_total 6\n"));
+        assert!(text.contains("http_responses_total{class=\"4xx\"} 2\n"));
+        assert!(text.contains("http_rendered_errors_total{class=\"5xx\"} 1\n"));
+        assert!(text.contains("http_handler_panics_total 1\n"));
+        assert!(text.contains("http_request_duration_seconds_count 5\n"));
+    }
+
+    #[ntex_rt::test]
+    async fn test_tuple_extractor() {
+        let metrics = Metrics::<DefaultError>::new();
+        let srv = init_service(App::new().wrap(metrics.clone()).service(
+            web::resource("/{num}").to(
+                |_: web::HttpRequest, num: types::Path<u32>| async move {
+                    format!("{}", num)
+                },
+            ),
+        ))
+        .await;
+
+        let req = TestRequest::with_uri("/abc").to_request();
+        assert_eq!(
+            call_service(&srv, req).await.status(),
+            StatusCode::NOT_FOUND
+        );
+
+        let snapshot = metrics.snapshot();
+        assert_eq!(
+            snapshot
+                .extractor_failures()
+                .get(std::any::type_name::<types::Path<u32>>()),
+            Some(&1)
+        );
+    }
+}
@@ -1,12 +1,120 @@
 //! Middlewares
 
+mod mediatype;
+pub use self::mediatype::MediaTypePredicate;
+
+mod condition;
+pub use self::condition::Condition;
+
+mod fnmiddleware;
+pub use self::fnmiddleware::{from_fn, FromFn, Next};
+
 #[cfg(feature = "compress")]
 mod compress;
 #[cfg(feature = "compress")]
 pub use self::compress::Compress;
+#[cfg(feature = "compress")]
+mod decompress;
+#[cfg(feature = "compress")]
+pub use self::decompress::Decompress;
+
+mod auth;
+pub use self::auth::HttpAuthentication;
+
+#[cfg(feature = "jwt")]
+mod jwt;
+#[cfg(feature = "jwt")]
+pub use self::jwt::{Jwt, JwtKey};
 
 mod logger;
-pub use self::logger::Logger;
+pub use self::logger::{AccessLogSink, Logger};
+
+mod metrics;
+pub use self::metrics::{Metrics, MetricsSnapshot};
+
+mod cors;
+pub use self::cors::Cors;
 
 mod defaultheaders;
 pub use self::defaultheaders::DefaultHeaders;
+
+mod secure;
+pub use self::secure::SecureHeaders;
+
+mod errhandlers;
+pub use self::errhandlers::ErrorHandlers;
+
+mod scrub;
+pub use self::scrub::ScrubHeaders;
+
+mod maintenance;
+pub use self::maintenance::{Maintenance, MaintenanceMode};
+
+mod https;
+pub use self::https::HttpsRedirect;
+
+mod hosts;
+pub use self::hosts::AllowedHosts;
+
+mod normalize;
+pub use self::normalize::{DuplicatePolicy, NormalizeHeaders};
+
+mod normalizepath;
+pub use self::normalizepath::{NormalizePath, TrailingSlash};
+
+mod priority;
+pub use self::priority::Priority;
+
+mod bandwidth;
+pub use self::bandwidth::Bandwidth;
+
+mod concurrency;
+pub use self::concurrency::Concurrency;
+
+mod bodylimit;
+pub use self::bodylimit::BodyLimit;
+
+mod cache;
+pub use self::cache::{Cache, CacheStore, CachedResponse, MemoryCacheStore};
+
+pub(in crate::web) mod etag;
+pub use self::etag::ETag;
+
+mod shadow;
+pub use self::shadow::Shadow;
+
+mod canary;
+pub use self::canary::{Canary, CanaryWeight};
+
+mod methodoverride;
+pub use self::methodoverride::MethodOverride;
+
+#[cfg(feature = "cookie")]
+mod cookiepolicy;
+#[cfg(feature = "cookie")]
+pub use self::cookiepolicy::CookiePolicy;
+
+#[cfg(feature = "cookie")]
+mod csrf;
+#[cfg(feature = "cookie")]
+pub use self::csrf::{Csrf, CsrfToken};
+
+#[cfg(feature = "secure-cookies")]
+mod session;
+#[cfg(feature = "secure-cookies")]
+pub use self::session::{
+    CookieSessionStore, Session, SessionError, SessionManager, SessionState,
+    SessionStatus, SessionStore,
+};
+
+mod requestid;
+pub use self::requestid::{RequestId, RequestIdValue};
+
+mod timeout;
+pub use self::timeout::Timeout;
+
+mod timing;
+pub use self::timing::{ServerTiming, TimingGuard, Timings};
+
+pub(in crate::web) mod memory;
+pub use self::memory::{MemoryAccounting, MemoryKind, MemoryLimitError, MemoryUsage};
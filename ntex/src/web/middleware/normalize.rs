@@ -0,0 +1,389 @@
+//! Middleware for request headers normalization
+use std::convert::TryFrom;
+use std::marker::PhantomData;
+use std::rc::Rc;
+use std::task::{Context, Poll};
+
+use futures::future::{ok, Either, Ready};
+
+use crate::http::error::HttpError;
+use crate::http::header::{self, HeaderMap, HeaderName, HeaderValue};
+use crate::http::{Response, StatusCode};
+use crate::service::{Service, Transform};
+use crate::web::dev::{WebRequest, WebResponse};
+
+/// Hop-by-hop headers, these headers are meaningful only for single
+/// transport-level connection and must not be forwarded by proxies.
+const HOP_BY_HOP: &[HeaderName] =
+    &[header::PROXY_AUTHORIZATION, header::TE, header::TRAILER];
+
+/// Non-standard hop-by-hop headers
+const HOP_BY_HOP_EXT: &[&str] = &["keep-alive", "proxy-connection"];
+
+/// Headers that could not be removed via `Connection` header
+const PROTECTED: &[HeaderName] = &[
+    header::CONNECTION,
+    header::CONTENT_LENGTH,
+    header::HOST,
+    header::TRANSFER_ENCODING,
+    header::UPGRADE,
+];
+
+/// Policy for handling of repeated request headers
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DuplicatePolicy {
+    /// Keep single value, the one returned by `HeaderMap::get()`
+    Keep,
+    /// Join values into single comma separated value, values are joined
+    /// in `HeaderMap::get_all()` order
+    Join,
+    /// Reject request with *400 Bad Request* response
+    Reject,
+}
+
+/// `Middleware` for request headers normalization.
+///
+/// Middleware canonicalizes request headers before request gets routed.
+/// By default hop-by-hop headers (*Keep-Alive*, *Proxy-Connection*,
+/// *Proxy-Authorization*, *TE*, *Trailer* and headers listed in
+/// *Connection* header) are removed and requests with multiple *Host*
+/// headers are rejected with *400 Bad Request* response. *Connection* and
+/// *Upgrade* headers are preserved, so websocket handshakes still work.
+///
+/// Configuration depends on deployment topology. If application is exposed
+/// to untrusted clients, headers usually set by trusted reverse proxy
+/// could be removed with `strip()` method. If application runs behind
+/// proxy that already strips hop-by-hop headers, use `hop_by_hop(false)`.
+///
+/// ```rust
+/// use ntex::web::{self, middleware, middleware::DuplicatePolicy, App, HttpResponse};
+///
+/// fn main() {
+///     let app = App::new()
+///         .wrap(
+///             middleware::NormalizeHeaders::new()
+///                 .strip("x-forwarded-for")
+///                 .duplicates("accept", DuplicatePolicy::Join)
+///                 .duplicates("content-type", DuplicatePolicy::Reject),
+///         )
+///         .service(web::resource("/test").to(|| async { HttpResponse::Ok() }));
+/// }
+/// ```
+pub struct NormalizeHeaders<E> {
+    inner: Rc<Inner>,
+    _t: PhantomData<E>,
+}
+
+struct Inner {
+    hop_by_hop: bool,
+    single_host: bool,
+    strip: Vec<HeaderName>,
+    duplicates: Vec<(HeaderName, DuplicatePolicy)>,
+}
+
+impl<E> Default for NormalizeHeaders<E> {
+    fn default() -> Self {
+        NormalizeHeaders {
+            inner: Rc::new(Inner {
+                hop_by_hop: true,
+                single_host: true,
+                strip: Vec::new(),
+                duplicates: Vec::new(),
+            }),
+            _t: PhantomData,
+        }
+    }
+}
+
+impl<E> NormalizeHeaders<E> {
+    /// Construct `NormalizeHeaders` middleware.
+    pub fn new() -> Self {
+        NormalizeHeaders::default()
+    }
+
+    /// Remove hop-by-hop headers.
+    ///
+    /// By default hop-by-hop headers are removed.
+    pub fn hop_by_hop(mut self, enabled: bool) -> Self {
+        Rc::get_mut(&mut self.inner)
+            .expect("Multiple copies exist")
+            .hop_by_hop = enabled;
+        self
+    }
+
+    /// Reject requests with multiple *Host* headers.
+    ///
+    /// By default requests with multiple *Host* headers are rejected.
+    pub fn single_host(mut self, enabled: bool) -> Self {
+        Rc::get_mut(&mut self.inner)
+            .expect("Multiple copies exist")
+            .single_host = enabled;
+        self
+    }
+
+    /// Remove header from every request.
+    pub fn strip<K>(mut self, key: K) -> Self
+    where
+        HeaderName: TryFrom<K>,
+        <HeaderName as TryFrom<K>>::Error: Into<HttpError>,
+    {
+        match HeaderName::try_from(key) {
+            Ok(key) => Rc::get_mut(&mut self.inner)
+                .expect("Multiple copies exist")
+                .strip
+                .push(key),
+            Err(_) => panic!("Can not create header name"),
+        }
+        self
+    }
+
+    /// Set policy for repeated header.
+    ///
+    /// Repeated headers are passed as is, unless policy is set.
+    pub fn duplicates<K>(mut self, key: K, policy: DuplicatePolicy) -> Self
+    where
+        HeaderName: TryFrom<K>,
+        <HeaderName as TryFrom<K>>::Error: Into<HttpError>,
+    {
+        match HeaderName::try_from(key) {
+            Ok(key) => {
+                let inner = Rc::get_mut(&mut self.inner).expect("Multiple copies exist");
+                inner.duplicates.retain(|(k, _)| *k != key);
+                inner.duplicates.push((key, policy));
+            }
+            Err(_) => panic!("Can not create header name"),
+        }
+        self
+    }
+}
+
+impl Inner {
+    fn normalize(&self, headers: &mut HeaderMap) -> Result<(), HeaderName> {
+        if self.single_host && headers.get_all(header::HOST).nth(1).is_some() {
+            return Err(header::HOST);
+        }
+
+        if self.hop_by_hop {
+            let listed: Vec<HeaderName> = headers
+                .get_all(header::CONNECTION)
+                .filter_map(|val| val.to_str().ok())
+                .flat_map(|val| val.split(','))
+                .filter_map(|name| HeaderName::try_from(name.trim()).ok())
+                .filter(|name| !PROTECTED.contains(name))
+                .collect();
+            for name in listed {
+                headers.remove(name);
+            }
+            for name in HOP_BY_HOP {
+                headers.remove(name);
+            }
+            for name in HOP_BY_HOP_EXT {
+                headers.remove(*name);
+            }
+        }
+
+        for name in &self.strip {
+            headers.remove(name);
+        }
+
+        for (name, policy) in &self.duplicates {
+            if headers.get_all(name).nth(1).is_none() {
+                continue;
+            }
+            let value = match policy {
+                DuplicatePolicy::Keep => headers.get(name).cloned(),
+                DuplicatePolicy::Join => {
+                    let mut value = Vec::new();
+                    for val in headers.get_all(name) {
+                        if !value.is_empty() {
+                            value.extend_from_slice(b", ");
+                        }
+                        value.extend_from_slice(val.as_bytes());
+                    }
+                    HeaderValue::from_bytes(&value).ok()
+                }
+                DuplicatePolicy::Reject => return Err(name.clone()),
+            };
+            if let Some(value) = value {
+                headers.insert(name.clone(), value);
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<S, B, E> Transform<S> for NormalizeHeaders<E>
+where
+    S: Service<Request = WebRequest<E>, Response = WebResponse<B>>,
+{
+    type Request = WebRequest<E>;
+    type Response = WebResponse<B>;
+    type Error = S::Error;
+    type InitError = ();
+    type Transform = NormalizeHeadersMiddleware<S, E>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ok(NormalizeHeadersMiddleware {
+            service,
+            inner: self.inner.clone(),
+            _t: PhantomData,
+        })
+    }
+}
+
+pub struct NormalizeHeadersMiddleware<S, E> {
+    service: S,
+    inner: Rc<Inner>,
+    _t: PhantomData<E>,
+}
+
+impl<S, B, E> Service for NormalizeHeadersMiddleware<S, E>
+where
+    S: Service<Request = WebRequest<E>, Response = WebResponse<B>>,
+{
+    type Request = WebRequest<E>;
+    type Response = WebResponse<B>;
+    type Error = S::Error;
+    type Future = Either<S::Future, Ready<Result<Self::Response, Self::Error>>>;
+
+    #[inline]
+    fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(cx)
+    }
+
+    #[inline]
+    fn poll_shutdown(&self, cx: &mut Context<'_>, is_error: bool) -> Poll<()> {
+        self.service.poll_shutdown(cx, is_error)
+    }
+
+    fn call(&self, mut req: WebRequest<E>) -> Self::Future {
+        match self.inner.normalize(req.headers_mut()) {
+            Ok(_) => Either::Left(self.service.call(req)),
+            Err(name) => {
+                log::debug!("Rejected request with repeated {:?} header", name);
+                let res = Response::new(StatusCode::BAD_REQUEST);
+                Either::Right(ok(req.into_response(res.into_body())))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::web::test::{ok_service, TestRequest};
+    use crate::web::DefaultError;
+
+    #[ntex_rt::test]
+    async fn test_hop_by_hop() {
+        let mw = NormalizeHeaders::<DefaultError>::new()
+            .strip("x-forwarded-for")
+            .new_transform(ok_service())
+            .await
+            .unwrap();
+
+        let mut req = TestRequest::default()
+            .header(header::CONNECTION, "Upgrade, X-Secret, Host")
+            .header(header::UPGRADE, "websocket")
+            .header(header::HOST, "example.com")
+            .header("x-secret", "1")
+            .header("keep-alive", "timeout=5")
+            .header(header::TE, "trailers")
+            .header("x-forwarded-for", "127.0.0.1")
+            .header("x-other", "1")
+            .to_srv_request();
+        assert!(mw.inner.normalize(req.headers_mut()).is_ok());
+
+        let headers = req.headers();
+        assert!(headers.contains_key(header::CONNECTION));
+        assert!(headers.contains_key(header::UPGRADE));
+        assert!(headers.contains_key(header::HOST));
+        assert!(headers.contains_key("x-other"));
+        assert!(!headers.contains_key("x-secret"));
+        assert!(!headers.contains_key("keep-alive"));
+        assert!(!headers.contains_key(header::TE));
+        assert!(!headers.contains_key("x-forwarded-for"));
+
+        let mw = NormalizeHeaders::<DefaultError>::new()
+            .hop_by_hop(false)
+            .new_transform(ok_service())
+            .await
+            .unwrap();
+        let mut req = TestRequest::default()
+            .header(header::CONNECTION, "X-Secret")
+            .header("x-secret", "1")
+            .header("keep-alive", "timeout=5")
+            .to_srv_request();
+        assert!(mw.inner.normalize(req.headers_mut()).is_ok());
+        assert!(req.headers().contains_key("x-secret"));
+        assert!(req.headers().contains_key("keep-alive"));
+    }
+
+    #[ntex_rt::test]
+    async fn test_duplicates() {
+        let mw = NormalizeHeaders::<DefaultError>::new()
+            .duplicates("x-keep", DuplicatePolicy::Keep)
+            .duplicates("x-join", DuplicatePolicy::Join)
+            .duplicates("x-reject", DuplicatePolicy::Reject)
+            .new_transform(ok_service())
+            .await
+            .unwrap();
+
+        let mut req = TestRequest::default()
+            .header("x-keep", "1")
+            .header("x-keep", "2")
+            .header("x-join", "1")
+            .header("x-join", "2")
+            .header("x-reject", "1")
+            .to_srv_request();
+        assert!(mw.inner.normalize(req.headers_mut()).is_ok());
+
+        let headers = req.headers();
+        assert_eq!(headers.get_all("x-keep").count(), 1);
+        assert_eq!(headers.get_all("x-join").count(), 1);
+        let joined = headers.get("x-join").unwrap().to_str().unwrap();
+        assert!(joined == "1, 2" || joined == "2, 1");
+        assert_eq!(headers.get_all("x-reject").collect::<Vec<_>>(), vec!["1"]);
+
+        let req = TestRequest::default()
+            .header("x-reject", "1")
+            .header("x-reject", "2")
+            .to_srv_request();
+        let resp = mw.call(req).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[ntex_rt::test]
+    async fn test_single_host() {
+        let mw = NormalizeHeaders::<DefaultError>::new()
+            .new_transform(ok_service())
+            .await
+            .unwrap();
+
+        let req = TestRequest::default()
+            .header(header::HOST, "example.com")
+            .to_srv_request();
+        let resp = mw.call(req).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let req = TestRequest::default()
+            .header(header::HOST, "example.com")
+            .header(header::HOST, "evil.com")
+            .to_srv_request();
+        let resp = mw.call(req).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+
+        let mw = NormalizeHeaders::<DefaultError>::new()
+            .single_host(false)
+            .new_transform(ok_service())
+            .await
+            .unwrap();
+        let req = TestRequest::default()
+            .header(header::HOST, "example.com")
+            .header(header::HOST, "evil.com")
+            .to_srv_request();
+        let resp = mw.call(req).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+    }
+}
@@ -0,0 +1,223 @@
+//! Middleware for path normalization
+use std::marker::PhantomData;
+use std::task::{Context, Poll};
+
+use futures::future::{ok, Ready};
+
+use crate::http::Uri;
+use crate::service::{Service, Transform};
+use crate::web::dev::{WebRequest, WebResponse};
+
+/// Trailing slash policy of `NormalizePath` middleware
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TrailingSlash {
+    /// Remove trailing slash, root path `/` is preserved
+    Trim,
+    /// Append trailing slash if path does not have one
+    Always,
+    /// Only merge duplicate slashes, trailing slash is preserved as is
+    MergeOnly,
+}
+
+/// `Middleware` for path normalization.
+///
+/// Middleware merges duplicate slashes, `//a///b` becomes `/a/b`, and
+/// applies trailing slash policy, by default trailing slash is removed.
+/// Path is rewritten before routing, so middleware must be registered on
+/// application level. Query is preserved.
+///
+/// ```rust
+/// use ntex::web::{self, middleware, App, HttpResponse};
+///
+/// fn main() {
+///     let app = App::new()
+///         .wrap(middleware::NormalizePath::new(middleware::TrailingSlash::Always))
+///         .service(web::resource("/test/").to(|| async { HttpResponse::Ok() }));
+/// }
+/// ```
+pub struct NormalizePath<E> {
+    trailing: TrailingSlash,
+    _t: PhantomData<E>,
+}
+
+impl<E> Default for NormalizePath<E> {
+    fn default() -> Self {
+        NormalizePath::new(TrailingSlash::Trim)
+    }
+}
+
+impl<E> NormalizePath<E> {
+    /// Construct `NormalizePath` middleware with trailing slash policy.
+    pub fn new(trailing: TrailingSlash) -> Self {
+        NormalizePath {
+            trailing,
+            _t: PhantomData,
+        }
+    }
+}
+
+impl<S, B, E> Transform<S> for NormalizePath<E>
+where
+    S: Service<Request = WebRequest<E>, Response = WebResponse<B>>,
+{
+    type Request = WebRequest<E>;
+    type Response = WebResponse<B>;
+    type Error = S::Error;
+    type InitError = ();
+    type Transform = NormalizePathMiddleware<S, E>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ok(NormalizePathMiddleware {
+            service,
+            trailing: self.trailing,
+            _t: PhantomData,
+        })
+    }
+}
+
+pub struct NormalizePathMiddleware<S, E> {
+    service: S,
+    trailing: TrailingSlash,
+    _t: PhantomData<E>,
+}
+
+impl<S, E> NormalizePathMiddleware<S, E> {
+    fn normalize(&self, path: &str) -> Option<String> {
+        let mut result = String::with_capacity(path.len() + 1);
+        for ch in path.chars() {
+            if ch != '/' || !result.ends_with('/') {
+                result.push(ch);
+            }
+        }
+
+        match self.trailing {
+            TrailingSlash::Trim => {
+                if result.len() > 1 && result.ends_with('/') {
+                    result.pop();
+                }
+            }
+            TrailingSlash::Always => {
+                if !result.ends_with('/') {
+                    result.push('/');
+                }
+            }
+            TrailingSlash::MergeOnly => (),
+        }
+
+        if result == path {
+            None
+        } else {
+            Some(result)
+        }
+    }
+}
+
+impl<S, B, E> Service for NormalizePathMiddleware<S, E>
+where
+    S: Service<Request = WebRequest<E>, Response = WebResponse<B>>,
+{
+    type Request = WebRequest<E>;
+    type Response = WebResponse<B>;
+    type Error = S::Error;
+    type Future = S::Future;
+
+    #[inline]
+    fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(cx)
+    }
+
+    #[inline]
+    fn poll_shutdown(&self, cx: &mut Context<'_>, is_error: bool) -> Poll<()> {
+        self.service.poll_shutdown(cx, is_error)
+    }
+
+    fn call(&self, mut req: WebRequest<E>) -> Self::Future {
+        if let Some(path) = self.normalize(req.path()) {
+            let head = req.head_mut();
+            let mut parts = head.uri.clone().into_parts();
+            parts.path_and_query = match head.uri.query() {
+                Some(query) => format!("{}?{}", path, query).parse().ok(),
+                None => path.parse().ok(),
+            };
+
+            match Uri::from_parts(parts) {
+                Ok(uri) => {
+                    head.uri = uri.clone();
+                    req.match_info_mut().set(uri);
+                }
+                Err(e) => log::debug!("Can not normalize path {:?}: {}", path, e),
+            }
+        }
+        self.service.call(req)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::StatusCode;
+    use crate::web::test::{call_service, init_service, read_body, TestRequest};
+    use crate::web::{self, App, HttpRequest};
+
+    #[ntex_rt::test]
+    async fn test_normalize() {
+        let srv = init_service(
+            App::new()
+                .wrap(NormalizePath::default())
+                .service(web::resource("/").to(|| async { "root" }))
+                .service(web::resource("/v1/{name}").to(
+                    |req: HttpRequest| async move {
+                        format!(
+                            "{} {} {}",
+                            req.path(),
+                            req.match_info().get("name").unwrap(),
+                            req.query_string()
+                        )
+                    },
+                )),
+        )
+        .await;
+
+        for (uri, body) in &[
+            ("/v1/something", "/v1/something something "),
+            ("//v1//something/", "/v1/something something "),
+            ("///v1/something//?a=b", "/v1/something something a=b"),
+            ("//", "root"),
+            ("/", "root"),
+        ] {
+            let req = TestRequest::with_uri(uri).to_request();
+            let resp = call_service(&srv, req).await;
+            assert_eq!(resp.status(), StatusCode::OK, "{}", uri);
+            assert_eq!(read_body(resp).await, body.as_bytes());
+        }
+    }
+
+    #[ntex_rt::test]
+    async fn test_trailing_slash() {
+        let srv = init_service(
+            App::new()
+                .wrap(NormalizePath::new(TrailingSlash::Always))
+                .service(web::resource("/v1/test/").to(|| async { "test" })),
+        )
+        .await;
+        for uri in &["/v1/test", "/v1//test//", "/v1/test/?a=b"] {
+            let req = TestRequest::with_uri(uri).to_request();
+            let resp = call_service(&srv, req).await;
+            assert_eq!(resp.status(), StatusCode::OK, "{}", uri);
+        }
+
+        let srv = init_service(
+            App::new()
+                .wrap(NormalizePath::new(TrailingSlash::MergeOnly))
+                .service(web::resource("/v1/test").to(|| async { "test" })),
+        )
+        .await;
+        let req = TestRequest::with_uri("//v1//test").to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let req = TestRequest::with_uri("/v1/test/").to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    }
+}
@@ -0,0 +1,537 @@
+//! Middleware for weighted request scheduling
+use std::cell::{Cell, RefCell};
+use std::collections::VecDeque;
+use std::future::Future;
+use std::pin::Pin;
+use std::rc::Rc;
+use std::task::{Context, Poll};
+
+use futures::future::{ok, Either, Ready};
+
+use crate::http::header::{HeaderName, RETRY_AFTER};
+use crate::http::Response;
+use crate::service::{Service, Transform};
+use crate::task::LocalWaker;
+use crate::web::dev::{WebRequest, WebResponse};
+
+/// `Middleware` for weighted request scheduling.
+///
+/// Middleware classifies requests into priority classes and limits number
+/// of concurrently processed requests for each class. Every class gets
+/// share of total concurrency budget proportional to its weight, but at
+/// least one slot. Requests that exceed class budget wait in class queue,
+/// so saturated bulk endpoints do not starve health checks or admin
+/// traffic. If class queue is full, request is rejected with
+/// *503 Service Unavailable* response.
+///
+/// Request class is determined by classification rules in registration
+/// order, first matching rule wins. Requests that do not match any rule
+/// belong to default class, `"default"` with weight 1 unless configured
+/// otherwise. Rules that point to unknown class are ignored.
+///
+/// Budgets are per worker. Slot is released when inner service returns
+/// response, response body streaming is not accounted.
+///
+/// ```rust
+/// use ntex::web::{self, middleware, App, HttpResponse};
+///
+/// fn main() {
+///     let app = App::new()
+///         .wrap(
+///             middleware::Priority::new(64)
+///                 .class("admin", 1)
+///                 .class("bulk", 4)
+///                 .path("/admin", "admin")
+///                 .path("/health", "admin")
+///                 .path("/export", "bulk")
+///                 .max_queue(256),
+///         )
+///         .service(web::resource("/health").to(|| async { HttpResponse::Ok() }))
+///         .service(web::resource("/export").to(|| async { HttpResponse::Ok() }));
+/// }
+/// ```
+pub struct Priority<E> {
+    inner: Rc<Inner<E>>,
+}
+
+struct Inner<E> {
+    concurrency: usize,
+    classes: Vec<(&'static str, usize)>,
+    default: &'static str,
+    rules: Vec<Rule<E>>,
+    max_queue: Option<usize>,
+}
+
+enum Rule<E> {
+    Path(String, &'static str),
+    Header(HeaderName),
+    Fn(Box<dyn Fn(&WebRequest<E>) -> Option<&'static str>>),
+}
+
+impl<E> Priority<E> {
+    /// Construct `Priority` middleware with total concurrency budget
+    /// per worker.
+    pub fn new(concurrency: usize) -> Self {
+        Priority {
+            inner: Rc::new(Inner {
+                concurrency,
+                classes: vec![("default", 1)],
+                default: "default",
+                rules: Vec::new(),
+                max_queue: None,
+            }),
+        }
+    }
+
+    /// Register priority class with weight.
+    ///
+    /// Registering existing class updates its weight.
+    pub fn class(mut self, name: &'static str, weight: usize) -> Self {
+        let inner = Rc::get_mut(&mut self.inner).expect("Multiple copies exist");
+        if let Some(item) = inner.classes.iter_mut().find(|item| item.0 == name) {
+            item.1 = weight;
+        } else {
+            inner.classes.push((name, weight));
+        }
+        self
+    }
+
+    /// Set class for requests that do not match any rule.
+    ///
+    /// Class is registered with weight 1 if it is not registered yet.
+    pub fn default_class(mut self, name: &'static str) -> Self {
+        if !self.inner.classes.iter().any(|item| item.0 == name) {
+            self = self.class(name, 1);
+        }
+        Rc::get_mut(&mut self.inner)
+            .expect("Multiple copies exist")
+            .default = name;
+        self
+    }
+
+    /// Assign requests for specified path and all its sub-paths to class.
+    pub fn path(mut self, path: &str, class: &'static str) -> Self {
+        Rc::get_mut(&mut self.inner)
+            .expect("Multiple copies exist")
+            .rules
+            .push(Rule::Path(path.trim_end_matches('/').to_string(), class));
+        self
+    }
+
+    /// Use value of specified request header as class name.
+    ///
+    /// Clients could choose any class with this header, so it must be used
+    /// only if header is set by trusted upstream, like load balancer that
+    /// strips client provided value.
+    pub fn header<N>(mut self, name: N) -> Self
+    where
+        HeaderName: std::convert::TryFrom<N>,
+    {
+        let name = match std::convert::TryFrom::try_from(name) {
+            Ok(name) => name,
+            Err(_) => panic!("Can not create header name"),
+        };
+        Rc::get_mut(&mut self.inner)
+            .expect("Multiple copies exist")
+            .rules
+            .push(Rule::Header(name));
+        self
+    }
+
+    /// Classify requests with custom function.
+    ///
+    /// If function returns `None`, next rule is checked.
+    pub fn classify<F>(mut self, f: F) -> Self
+    where
+        F: Fn(&WebRequest<E>) -> Option<&'static str> + 'static,
+    {
+        Rc::get_mut(&mut self.inner)
+            .expect("Multiple copies exist")
+            .rules
+            .push(Rule::Fn(Box::new(f)));
+        self
+    }
+
+    /// Set max number of waiting requests per class.
+    ///
+    /// By default queue is not limited.
+    pub fn max_queue(mut self, max: usize) -> Self {
+        Rc::get_mut(&mut self.inner)
+            .expect("Multiple copies exist")
+            .max_queue = Some(max);
+        self
+    }
+}
+
+impl<E> Inner<E> {
+    fn index(&self, name: &str) -> Option<usize> {
+        self.classes.iter().position(|item| item.0 == name)
+    }
+
+    fn classify(&self, req: &WebRequest<E>) -> usize {
+        for rule in &self.rules {
+            let class = match rule {
+                Rule::Path(ref prefix, class) => {
+                    let path = req.path();
+                    if path.starts_with(prefix.as_str())
+                        && (path.len() == prefix.len()
+                            || path.as_bytes()[prefix.len()] == b'/')
+                    {
+                        Some(*class)
+                    } else {
+                        None
+                    }
+                }
+                Rule::Header(ref name) => {
+                    req.headers().get(name).and_then(|val| val.to_str().ok())
+                }
+                Rule::Fn(ref f) => f(req),
+            };
+            if let Some(idx) = class.and_then(|class| self.index(class)) {
+                return idx;
+            }
+        }
+        self.index(self.default).unwrap_or(0)
+    }
+
+    fn budgets(&self) -> Vec<Class> {
+        let total = self.classes.iter().map(|item| item.1).sum::<usize>().max(1);
+        self.classes
+            .iter()
+            .map(|item| Class {
+                budget: std::cmp::max(1, self.concurrency * item.1 / total),
+                in_flight: Cell::new(0),
+                waiters: RefCell::new(VecDeque::new()),
+            })
+            .collect()
+    }
+}
+
+impl<S, B, E> Transform<S> for Priority<E>
+where
+    S: Service<Request = WebRequest<E>, Response = WebResponse<B>>,
+{
+    type Request = WebRequest<E>;
+    type Response = WebResponse<B>;
+    type Error = S::Error;
+    type InitError = ();
+    type Transform = PriorityMiddleware<S, E>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ok(PriorityMiddleware {
+            service: Rc::new(service),
+            classes: Rc::new(self.inner.budgets()),
+            inner: self.inner.clone(),
+        })
+    }
+}
+
+struct Class {
+    budget: usize,
+    in_flight: Cell<usize>,
+    waiters: RefCell<VecDeque<Rc<Waiter>>>,
+}
+
+#[derive(Default)]
+struct Waiter {
+    waker: LocalWaker,
+    granted: Cell<bool>,
+}
+
+/// Scheduling slot, slot is released on drop
+struct Slot {
+    classes: Rc<Vec<Class>>,
+    idx: usize,
+    waiter: Option<Rc<Waiter>>,
+}
+
+impl Slot {
+    fn poll_acquire(&mut self, cx: &mut Context<'_>) -> Poll<()> {
+        if let Some(ref waiter) = self.waiter {
+            if waiter.granted.get() {
+                self.waiter = None;
+            } else {
+                waiter.waker.register(cx.waker());
+                return Poll::Pending;
+            }
+        }
+        Poll::Ready(())
+    }
+}
+
+impl Drop for Slot {
+    fn drop(&mut self) {
+        let class = &self.classes[self.idx];
+        if let Some(ref waiter) = self.waiter {
+            if !waiter.granted.get() {
+                class
+                    .waiters
+                    .borrow_mut()
+                    .retain(|item| !Rc::ptr_eq(item, waiter));
+                return;
+            }
+        }
+
+        // pass slot to next waiting request
+        if let Some(waiter) = class.waiters.borrow_mut().pop_front() {
+            waiter.granted.set(true);
+            waiter.waker.wake();
+        } else {
+            class.in_flight.set(class.in_flight.get() - 1);
+        }
+    }
+}
+
+pub struct PriorityMiddleware<S, E> {
+    service: Rc<S>,
+    classes: Rc<Vec<Class>>,
+    inner: Rc<Inner<E>>,
+}
+
+impl<S, B, E> Service for PriorityMiddleware<S, E>
+where
+    S: Service<Request = WebRequest<E>, Response = WebResponse<B>>,
+{
+    type Request = WebRequest<E>;
+    type Response = WebResponse<B>;
+    type Error = S::Error;
+    type Future =
+        Either<PriorityResponse<S, E>, Ready<Result<Self::Response, Self::Error>>>;
+
+    #[inline]
+    fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(cx)
+    }
+
+    #[inline]
+    fn poll_shutdown(&self, cx: &mut Context<'_>, is_error: bool) -> Poll<()> {
+        self.service.poll_shutdown(cx, is_error)
+    }
+
+    fn call(&self, req: WebRequest<E>) -> Self::Future {
+        let idx = self.inner.classify(&req);
+        let class = &self.classes[idx];
+
+        let waiter = if class.in_flight.get() < class.budget {
+            class.in_flight.set(class.in_flight.get() + 1);
+            None
+        } else if self
+            .inner
+            .max_queue
+            .map(|max| class.waiters.borrow().len() >= max)
+            .unwrap_or(false)
+        {
+            log::trace!(
+                "Priority class {:?} queue is full, reject request",
+                self.inner.classes[idx].0
+            );
+            let res = Response::ServiceUnavailable()
+                .header(RETRY_AFTER, "1")
+                .finish();
+            return Either::Right(ok(req.into_response(res.into_body())));
+        } else {
+            let waiter = Rc::new(Waiter::default());
+            class.waiters.borrow_mut().push_back(waiter.clone());
+            Some(waiter)
+        };
+
+        let slot = Slot {
+            idx,
+            waiter,
+            classes: self.classes.clone(),
+        };
+        let (fut, req) = if slot.waiter.is_none() {
+            (Some(self.service.call(req)), None)
+        } else {
+            (None, Some(req))
+        };
+
+        Either::Left(PriorityResponse {
+            fut,
+            req,
+            slot: Some(slot),
+            service: self.service.clone(),
+        })
+    }
+}
+
+#[doc(hidden)]
+#[pin_project::pin_project]
+pub struct PriorityResponse<S: Service, E> {
+    #[pin]
+    fut: Option<S::Future>,
+    req: Option<WebRequest<E>>,
+    slot: Option<Slot>,
+    service: Rc<S>,
+}
+
+impl<S, B, E> Future for PriorityResponse<S, E>
+where
+    S: Service<Request = WebRequest<E>, Response = WebResponse<B>>,
+{
+    type Output = Result<WebResponse<B>, S::Error>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut this = self.project();
+
+        if this.fut.is_none() {
+            if let Some(ref mut slot) = this.slot {
+                if slot.poll_acquire(cx).is_pending() {
+                    return Poll::Pending;
+                }
+            }
+            // request waited in queue, check inner service readiness
+            if let Err(e) = futures::ready!(this.service.poll_ready(cx)) {
+                this.slot.take();
+                return Poll::Ready(Err(e));
+            }
+            let req = this.req.take().unwrap();
+            this.fut.set(Some(this.service.call(req)));
+        }
+
+        let res = futures::ready!(this.fut.as_pin_mut().unwrap().poll(cx));
+        this.slot.take();
+        Poll::Ready(res)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::future::lazy;
+
+    use super::*;
+    use crate::http::StatusCode;
+    use crate::web::test::{ok_service, TestRequest};
+    use crate::web::DefaultError;
+
+    #[ntex_rt::test]
+    async fn test_priority() {
+        let mw = Priority::<DefaultError>::new(2)
+            .class("bulk", 1)
+            .path("/bulk", "bulk")
+            .new_transform(ok_service())
+            .await
+            .unwrap();
+
+        let req = TestRequest::with_uri("/bulk/1").to_srv_request();
+        let fut1 = mw.call(req);
+        let req = TestRequest::with_uri("/bulk/2").to_srv_request();
+        let mut fut2 = Box::pin(mw.call(req));
+        assert!(lazy(|cx| fut2.as_mut().poll(cx)).await.is_pending());
+
+        // default class is not affected by saturated bulk class
+        let req = TestRequest::with_uri("/test").to_srv_request();
+        let resp = mw.call(req).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let resp = fut1.await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        let resp = fut2.await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(mw.classes[1].in_flight.get(), 0);
+    }
+
+    #[ntex_rt::test]
+    async fn test_max_queue() {
+        let mw = Priority::<DefaultError>::new(1)
+            .class("admin", 1)
+            .header("x-priority")
+            .max_queue(1)
+            .new_transform(ok_service())
+            .await
+            .unwrap();
+
+        let fut1 = mw.call(TestRequest::default().to_srv_request());
+        let fut2 = mw.call(TestRequest::default().to_srv_request());
+        let resp = mw
+            .call(TestRequest::default().to_srv_request())
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(resp.headers().get(RETRY_AFTER).unwrap(), "1");
+
+        let req = TestRequest::default()
+            .header("x-priority", "admin")
+            .to_srv_request();
+        let resp = mw.call(req).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        // dropped waiting request releases its queue position
+        drop(fut2);
+        assert!(mw.classes[0].waiters.borrow().is_empty());
+        drop(fut1);
+        assert_eq!(mw.classes[0].in_flight.get(), 0);
+    }
+
+    #[ntex_rt::test]
+    async fn test_poll_ready() {
+        struct Srv(Rc<Cell<bool>>);
+
+        impl Service for Srv {
+            type Request = WebRequest<DefaultError>;
+            type Response = WebResponse;
+            type Error = ();
+            type Future = Ready<Result<WebResponse, ()>>;
+
+            fn poll_ready(&self, _: &mut Context<'_>) -> Poll<Result<(), ()>> {
+                if self.0.get() {
+                    Poll::Ready(Ok(()))
+                } else {
+                    Poll::Pending
+                }
+            }
+
+            fn call(&self, req: WebRequest<DefaultError>) -> Self::Future {
+                ok(req.into_response(Response::Ok().finish()))
+            }
+        }
+
+        let ready = Rc::new(Cell::new(true));
+        let mw = Priority::<DefaultError>::new(1)
+            .new_transform(Srv(ready.clone()))
+            .await
+            .unwrap();
+
+        let fut1 = mw.call(TestRequest::default().to_srv_request());
+        let mut fut2 = Box::pin(mw.call(TestRequest::default().to_srv_request()));
+        assert!(lazy(|cx| fut2.as_mut().poll(cx)).await.is_pending());
+
+        // queued request waits for inner service readiness
+        ready.set(false);
+        assert!(fut1.await.is_ok());
+        assert!(lazy(|cx| fut2.as_mut().poll(cx)).await.is_pending());
+
+        ready.set(true);
+        let resp = fut2.await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(mw.classes[0].in_flight.get(), 0);
+    }
+
+    #[ntex_rt::test]
+    async fn test_classify() {
+        let mw = Priority::<DefaultError>::new(4)
+            .class("bulk", 3)
+            .default_class("low")
+            .classify(|req| {
+                if req.method() == crate::http::Method::POST {
+                    Some("bulk")
+                } else {
+                    None
+                }
+            })
+            .new_transform(ok_service())
+            .await
+            .unwrap();
+        assert_eq!(mw.classes.len(), 3);
+        assert_eq!(mw.classes[1].budget, 2);
+        assert_eq!(mw.classes[2].budget, 1);
+
+        let req = TestRequest::default()
+            .method(crate::http::Method::POST)
+            .to_srv_request();
+        assert_eq!(mw.inner.classify(&req), 1);
+        let req = TestRequest::default().to_srv_request();
+        assert_eq!(mw.inner.classify(&req), 2);
+    }
+}
@@ -0,0 +1,320 @@
+//! Middleware for request id generation and propagation
+use std::convert::TryFrom;
+use std::fmt;
+use std::future::Future;
+use std::marker::PhantomData;
+use std::ops::Deref;
+use std::pin::Pin;
+use std::rc::Rc;
+use std::task::{Context, Poll};
+
+use futures::future::{ok, Ready};
+
+use crate::http::error::HttpError;
+use crate::http::header::{HeaderName, HeaderValue};
+use crate::http::Payload;
+use crate::service::{Service, Transform};
+use crate::web::dev::{WebRequest, WebResponse};
+use crate::web::{ErrorRenderer, FromRequest, HttpRequest};
+
+/// Maximum length of accepted incoming request id
+const MAX_LENGTH: usize = 128;
+
+/// Request id.
+///
+/// `RequestId` middleware stores `RequestIdValue` in request extensions.
+/// If middleware is not registered, extractor generates new id and stores
+/// it in request extensions, so all extractors of the request get the
+/// same value.
+///
+/// ```rust
+/// use ntex::web::{self, middleware::RequestIdValue, HttpResponse};
+///
+/// async fn index(id: RequestIdValue) -> HttpResponse {
+///     log::info!("request {}: processing", id);
+///     HttpResponse::Ok().finish()
+/// }
+/// # fn main() {}
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RequestIdValue(Rc<str>);
+
+impl RequestIdValue {
+    /// Generate new random id, in UUID v4 format
+    pub fn generate() -> Self {
+        let mut bytes: [u8; 16] = rand::random();
+        bytes[6] = (bytes[6] & 0x0f) | 0x40;
+        bytes[8] = (bytes[8] & 0x3f) | 0x80;
+
+        let mut id = String::with_capacity(36);
+        for (idx, b) in bytes.iter().enumerate() {
+            if idx == 4 || idx == 6 || idx == 8 || idx == 10 {
+                id.push('-');
+            }
+            id.push_str(&format!("{:02x}", b));
+        }
+        RequestIdValue(id.into())
+    }
+
+    /// Request id as string
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    fn from_header(val: &HeaderValue) -> Option<Self> {
+        match val.to_str() {
+            Ok(s) if !s.is_empty() && s.len() <= MAX_LENGTH => {
+                Some(RequestIdValue(s.into()))
+            }
+            _ => None,
+        }
+    }
+}
+
+impl Deref for RequestIdValue {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Display for RequestIdValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl<Err: ErrorRenderer> FromRequest<Err> for RequestIdValue {
+    type Error = Err::Container;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    #[inline]
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        if let Some(id) = req.extensions().get::<RequestIdValue>() {
+            return ok(id.clone());
+        }
+        let id = RequestIdValue::generate();
+        req.extensions_mut().insert(id.clone());
+        ok(id)
+    }
+}
+
+/// `Middleware` for request id generation and propagation.
+///
+/// Middleware accepts request id from incoming `X-Request-Id` header or
+/// generates new one, stores it in request extensions as `RequestIdValue`
+/// and adds it to response headers. Incoming ids longer than 128 bytes or
+/// with non-visible characters are replaced with generated ids. `Logger`
+/// middleware renders request id with `%{request_id}x` token.
+///
+/// ```rust
+/// use ntex::web::{self, middleware, App, HttpResponse};
+///
+/// fn main() {
+///     let app = App::new()
+///         .wrap(middleware::Logger::new("%{request_id}x %r %s"))
+///         .wrap(middleware::RequestId::default())
+///         .service(web::resource("/").to(|| async { HttpResponse::Ok() }));
+/// }
+/// ```
+pub struct RequestId<Err> {
+    inner: Rc<Inner>,
+    _t: PhantomData<Err>,
+}
+
+struct Inner {
+    header: HeaderName,
+    trust_incoming: bool,
+}
+
+impl<Err> RequestId<Err> {
+    /// Construct `RequestId` middleware
+    pub fn new() -> Self {
+        RequestId {
+            inner: Rc::new(Inner {
+                header: HeaderName::from_static("x-request-id"),
+                trust_incoming: true,
+            }),
+            _t: PhantomData,
+        }
+    }
+
+    /// Set request and response header name, by default `X-Request-Id`
+    pub fn header<H>(mut self, header: H) -> Self
+    where
+        HeaderName: TryFrom<H>,
+        <HeaderName as TryFrom<H>>::Error: Into<HttpError>,
+    {
+        Rc::get_mut(&mut self.inner)
+            .expect("Multiple copies exist")
+            .header = HeaderName::try_from(header)
+            .unwrap_or_else(|_| panic!("Can not create header name"));
+        self
+    }
+
+    /// Accept request id from incoming request, by default enabled
+    ///
+    /// If disabled, middleware always generates new request id.
+    pub fn trust_incoming(mut self, enabled: bool) -> Self {
+        Rc::get_mut(&mut self.inner)
+            .expect("Multiple copies exist")
+            .trust_incoming = enabled;
+        self
+    }
+}
+
+impl<Err> Default for RequestId<Err> {
+    fn default() -> Self {
+        RequestId::new()
+    }
+}
+
+impl<S, B, E> Transform<S> for RequestId<E>
+where
+    S: Service<Request = WebRequest<E>, Response = WebResponse<B>>,
+{
+    type Request = WebRequest<E>;
+    type Response = WebResponse<B>;
+    type Error = S::Error;
+    type InitError = ();
+    type Transform = RequestIdMiddleware<S, E>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ok(RequestIdMiddleware {
+            service,
+            inner: self.inner.clone(),
+            _t: PhantomData,
+        })
+    }
+}
+
+pub struct RequestIdMiddleware<S, E> {
+    service: S,
+    inner: Rc<Inner>,
+    _t: PhantomData<E>,
+}
+
+impl<S, B, E> Service for RequestIdMiddleware<S, E>
+where
+    S: Service<Request = WebRequest<E>, Response = WebResponse<B>>,
+{
+    type Request = WebRequest<E>;
+    type Response = WebResponse<B>;
+    type Error = S::Error;
+    type Future = RequestIdResponse<S, B, E>;
+
+    #[inline]
+    fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(cx)
+    }
+
+    #[inline]
+    fn poll_shutdown(&self, cx: &mut Context<'_>, is_error: bool) -> Poll<()> {
+        self.service.poll_shutdown(cx, is_error)
+    }
+
+    fn call(&self, req: WebRequest<E>) -> Self::Future {
+        let id = if self.inner.trust_incoming {
+            req.headers()
+                .get(&self.inner.header)
+                .and_then(RequestIdValue::from_header)
+        } else {
+            None
+        };
+        let id = id.unwrap_or_else(RequestIdValue::generate);
+        req.extensions_mut().insert(id.clone());
+
+        RequestIdResponse {
+            fut: self.service.call(req),
+            id,
+            inner: self.inner.clone(),
+            _t: PhantomData,
+        }
+    }
+}
+
+#[doc(hidden)]
+#[pin_project::pin_project]
+pub struct RequestIdResponse<S: Service, B, E> {
+    #[pin]
+    fut: S::Future,
+    id: RequestIdValue,
+    inner: Rc<Inner>,
+    _t: PhantomData<(B, E)>,
+}
+
+impl<S, B, E> Future for RequestIdResponse<S, B, E>
+where
+    S: Service<Request = WebRequest<E>, Response = WebResponse<B>>,
+{
+    type Output = Result<WebResponse<B>, S::Error>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        let mut res = futures::ready!(this.fut.poll(cx))?;
+
+        if !res.headers().contains_key(&this.inner.header) {
+            if let Ok(val) = HeaderValue::from_str(this.id.as_str()) {
+                res.headers_mut().insert(this.inner.header.clone(), val);
+            }
+        }
+        Poll::Ready(Ok(res))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::web::test::{call_service, init_service, read_body, TestRequest};
+    use crate::web::{self, App, DefaultError, HttpResponse};
+
+    #[ntex_rt::test]
+    async fn test_request_id() {
+        let srv =
+            init_service(App::new().wrap(RequestId::<DefaultError>::new()).service(
+                web::resource("/").to(|id: RequestIdValue| async move {
+                    HttpResponse::Ok().body(id.to_string())
+                }),
+            ))
+            .await;
+
+        let req = TestRequest::default().to_request();
+        let resp = call_service(&srv, req).await;
+        let id = resp.headers().get("x-request-id").unwrap().clone();
+        assert_eq!(id.len(), 36);
+        assert_eq!(id.as_bytes()[14], b'4');
+        assert_eq!(read_body(resp).await, id.as_bytes());
+
+        let req = TestRequest::with_header("x-request-id", "abc-123").to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.headers().get("x-request-id").unwrap(), "abc-123");
+        assert_eq!(read_body(resp).await, "abc-123");
+
+        let long = "a".repeat(MAX_LENGTH + 1);
+        let req = TestRequest::with_header("x-request-id", long.as_str()).to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.headers().get("x-request-id").unwrap().len(), 36);
+    }
+
+    #[ntex_rt::test]
+    async fn test_request_id_untrusted() {
+        let srv = init_service(
+            App::new()
+                .wrap(
+                    RequestId::<DefaultError>::new()
+                        .header("x-correlation-id")
+                        .trust_incoming(false),
+                )
+                .service(web::resource("/").to(|| async { HttpResponse::Ok() })),
+        )
+        .await;
+
+        let req = TestRequest::with_header("x-correlation-id", "abc-123").to_request();
+        let resp = call_service(&srv, req).await;
+        let id = resp.headers().get("x-correlation-id").unwrap();
+        assert_ne!(id, "abc-123");
+        assert_eq!(id.len(), 36);
+    }
+}
@@ -0,0 +1,345 @@
+//! Middleware for scrubbing internal response headers
+use std::convert::TryFrom;
+use std::marker::PhantomData;
+use std::rc::Rc;
+use std::task::{Context, Poll};
+
+use futures::future::{ok, FutureExt, LocalBoxFuture, Ready};
+
+use crate::http::error::HttpError;
+use crate::http::header::{self, HeaderMap, HeaderName};
+use crate::http::StatusCode;
+use crate::service::{Service, Transform};
+use crate::web::dev::{WebRequest, WebResponse};
+use crate::web::ErrorRenderer;
+
+/// Headers required for message framing, these headers are never removed
+const FRAMING: &[HeaderName] = &[
+    header::CONNECTION,
+    header::CONTENT_ENCODING,
+    header::CONTENT_LENGTH,
+    header::CONTENT_TYPE,
+    header::TRANSFER_ENCODING,
+    header::UPGRADE,
+];
+
+/// `Middleware` for scrubbing internal response headers.
+///
+/// Middleware is a final stage of response processing and should be
+/// registered last, so it wraps all other middlewares. It removes internal
+/// headers from every response leaving the server. By default headers
+/// with `X-Internal-` prefix and `X-Stack-Trace` header are removed, more
+/// headers could be added with `header()` and `prefix()` methods. If
+/// allowed headers are configured with `allow()` method, all other headers
+/// are removed as well. Headers required for message framing are always
+/// preserved, *101 Switching Protocols* responses are not scrubbed.
+///
+/// Errors of inner services are rendered to responses, so error responses
+/// get scrubbed too. Use `DefaultHeaders::render_errors()` to ensure error
+/// responses still get default headers.
+///
+/// ```rust
+/// use ntex::web::{self, middleware, App, HttpResponse};
+///
+/// fn main() {
+///     let app = App::new()
+///         .wrap(middleware::DefaultHeaders::new().render_errors(true))
+///         .wrap(
+///             middleware::ScrubHeaders::new()
+///                 .header("x-backend-server")
+///                 .prefix("x-debug-"),
+///         )
+///         .service(web::resource("/test").to(|| async { HttpResponse::Ok() }));
+/// }
+/// ```
+pub struct ScrubHeaders<E> {
+    inner: Rc<Inner>,
+    _t: PhantomData<E>,
+}
+
+struct Inner {
+    headers: Vec<HeaderName>,
+    prefixes: Vec<String>,
+    allow: Vec<HeaderName>,
+}
+
+impl<E> Default for ScrubHeaders<E> {
+    fn default() -> Self {
+        ScrubHeaders {
+            inner: Rc::new(Inner {
+                headers: vec![HeaderName::from_static("x-stack-trace")],
+                prefixes: vec!["x-internal-".to_string()],
+                allow: Vec::new(),
+            }),
+            _t: PhantomData,
+        }
+    }
+}
+
+impl<E> ScrubHeaders<E> {
+    /// Construct `ScrubHeaders` middleware.
+    pub fn new() -> Self {
+        ScrubHeaders::default()
+    }
+
+    /// Remove header from every response.
+    pub fn header<K>(mut self, key: K) -> Self
+    where
+        HeaderName: TryFrom<K>,
+        <HeaderName as TryFrom<K>>::Error: Into<HttpError>,
+    {
+        match HeaderName::try_from(key) {
+            Ok(key) => Rc::get_mut(&mut self.inner)
+                .expect("Multiple copies exist")
+                .headers
+                .push(key),
+            Err(_) => panic!("Can not create header name"),
+        }
+        self
+    }
+
+    /// Remove headers with name prefix from every response.
+    ///
+    /// Prefix is case insensitive.
+    pub fn prefix(mut self, prefix: &str) -> Self {
+        Rc::get_mut(&mut self.inner)
+            .expect("Multiple copies exist")
+            .prefixes
+            .push(prefix.to_lowercase());
+        self
+    }
+
+    /// Allow header.
+    ///
+    /// If allowed headers are configured, all other headers are removed.
+    /// Headers removed with `header()` and `prefix()` are removed even if
+    /// allowed.
+    pub fn allow<K>(mut self, key: K) -> Self
+    where
+        HeaderName: TryFrom<K>,
+        <HeaderName as TryFrom<K>>::Error: Into<HttpError>,
+    {
+        match HeaderName::try_from(key) {
+            Ok(key) => Rc::get_mut(&mut self.inner)
+                .expect("Multiple copies exist")
+                .allow
+                .push(key),
+            Err(_) => panic!("Can not create header name"),
+        }
+        self
+    }
+}
+
+impl Inner {
+    fn is_scrubbed(&self, name: &HeaderName) -> bool {
+        if FRAMING.contains(name) {
+            return false;
+        }
+        (!self.allow.is_empty() && !self.allow.contains(name))
+            || self.headers.contains(name)
+            || self
+                .prefixes
+                .iter()
+                .any(|prefix| name.as_str().starts_with(prefix.as_str()))
+    }
+
+    fn scrub(&self, headers: &mut HeaderMap) {
+        let names: Vec<HeaderName> = headers
+            .keys()
+            .filter(|name| self.is_scrubbed(name))
+            .cloned()
+            .collect();
+        for name in names {
+            headers.remove(name);
+        }
+    }
+}
+
+impl<S, B, E> Transform<S> for ScrubHeaders<E>
+where
+    S: Service<Request = WebRequest<E>, Response = WebResponse<B>>,
+    S::Future: 'static,
+    S::Error: Into<E::Container>,
+    E: ErrorRenderer,
+{
+    type Request = WebRequest<E>;
+    type Response = WebResponse<B>;
+    type Error = S::Error;
+    type InitError = ();
+    type Transform = ScrubHeadersMiddleware<S, E>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ok(ScrubHeadersMiddleware {
+            service,
+            inner: self.inner.clone(),
+            _t: PhantomData,
+        })
+    }
+}
+
+pub struct ScrubHeadersMiddleware<S, E> {
+    service: S,
+    inner: Rc<Inner>,
+    _t: PhantomData<E>,
+}
+
+impl<S, B, E> Service for ScrubHeadersMiddleware<S, E>
+where
+    S: Service<Request = WebRequest<E>, Response = WebResponse<B>>,
+    S::Future: 'static,
+    S::Error: Into<E::Container>,
+    E: ErrorRenderer,
+{
+    type Request = WebRequest<E>;
+    type Response = WebResponse<B>;
+    type Error = S::Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    #[inline]
+    fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(cx)
+    }
+
+    #[inline]
+    fn poll_shutdown(&self, cx: &mut Context<'_>, is_error: bool) -> Poll<()> {
+        self.service.poll_shutdown(cx, is_error)
+    }
+
+    fn call(&self, req: WebRequest<E>) -> Self::Future {
+        let inner = self.inner.clone();
+        let detached = req.detach();
+        let fut = self.service.call(req);
+
+        async move {
+            let mut res = match fut.await {
+                Ok(res) => res,
+                Err(e) => detached.error_response::<E, B>(e.into()),
+            };
+            if res.status() != StatusCode::SWITCHING_PROTOCOLS {
+                inner.scrub(res.headers_mut());
+            }
+            Ok(res)
+        }
+        .boxed_local()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::future::{err, ok};
+
+    use super::*;
+    use crate::service::IntoService;
+    use crate::web::middleware::DefaultHeaders;
+    use crate::web::test::{call_service, init_service, TestRequest};
+    use crate::web::{self, App, DefaultError, Error, HttpResponse};
+
+    #[ntex_rt::test]
+    async fn test_scrub() {
+        let srv = |req: WebRequest<DefaultError>| {
+            ok::<_, Error>(
+                req.into_response(
+                    HttpResponse::Ok()
+                        .header("X-Internal-Host", "db-1")
+                        .header("x-stack-trace", "main.rs:1")
+                        .header("x-backend", "1")
+                        .header("x-debug-id", "1")
+                        .header("x-version", "1")
+                        .content_type("text/plain")
+                        .finish(),
+                ),
+            )
+        };
+
+        let mw = ScrubHeaders::<DefaultError>::new()
+            .header("x-backend")
+            .prefix("X-Debug-")
+            .new_transform(srv.into_service())
+            .await
+            .unwrap();
+        let req = TestRequest::default().to_srv_request();
+        let resp = mw.call(req).await.unwrap();
+        let mut names: Vec<_> = resp.headers().keys().map(|k| k.as_str()).collect();
+        names.sort();
+        assert_eq!(names, vec!["content-type", "x-version"]);
+
+        let mw = ScrubHeaders::<DefaultError>::new()
+            .allow("x-backend")
+            .allow("x-internal-host")
+            .new_transform(srv.into_service())
+            .await
+            .unwrap();
+        let req = TestRequest::default().to_srv_request();
+        let resp = mw.call(req).await.unwrap();
+        let mut names: Vec<_> = resp.headers().keys().map(|k| k.as_str()).collect();
+        names.sort();
+        assert_eq!(names, vec!["content-type", "x-backend"]);
+    }
+
+    #[ntex_rt::test]
+    async fn test_errors() {
+        let srv = |_: WebRequest<DefaultError>| {
+            err::<WebResponse, _>(Error::from(crate::web::error::ErrorBadGateway::<
+                _,
+                DefaultError,
+            >("upstream")))
+        };
+        let mw = ScrubHeaders::<DefaultError>::new()
+            .allow(header::CACHE_CONTROL)
+            .new_transform(srv.into_service())
+            .await
+            .unwrap();
+        let req = TestRequest::default().to_srv_request();
+        let resp = mw.call(req).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::BAD_GATEWAY);
+        assert!(resp.headers().contains_key(header::CONTENT_TYPE));
+
+        let srv = init_service(
+            App::new()
+                .wrap_fn(|req, srv| {
+                    let fut = srv.call(req);
+                    async move {
+                        let mut res = fut.await?;
+                        res.headers_mut().insert(
+                            HeaderName::from_static("x-internal-id"),
+                            header::HeaderValue::from_static("1"),
+                        );
+                        if res.status() == StatusCode::OK {
+                            Err(crate::web::error::ErrorBadGateway::<_, DefaultError>(
+                                "upstream",
+                            )
+                            .into())
+                        } else {
+                            Ok(res)
+                        }
+                    }
+                })
+                .wrap(
+                    DefaultHeaders::new()
+                        .header(header::CACHE_CONTROL, "no-store")
+                        .render_errors(true),
+                )
+                .wrap(ScrubHeaders::new())
+                .service(web::resource("/").to(|| async { HttpResponse::Ok() })),
+        )
+        .await;
+
+        let req = TestRequest::default().to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::BAD_GATEWAY);
+        assert_eq!(
+            resp.headers().get(header::CACHE_CONTROL).unwrap(),
+            "no-store"
+        );
+
+        let req = TestRequest::with_uri("/missing").to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+        assert!(!resp.headers().contains_key("x-internal-id"));
+        assert_eq!(
+            resp.headers().get(header::CACHE_CONTROL).unwrap(),
+            "no-store"
+        );
+    }
+}
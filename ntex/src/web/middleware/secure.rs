@@ -0,0 +1,359 @@
+//! Middleware for setting security response headers
+use std::convert::TryFrom;
+use std::marker::PhantomData;
+use std::rc::Rc;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use futures::future::{ok, FutureExt, LocalBoxFuture, Ready};
+
+use crate::http::error::HttpError;
+use crate::http::header::{self, HeaderMap, HeaderName, HeaderValue};
+use crate::service::{Service, Transform};
+use crate::web::dev::{WebRequest, WebResponse};
+use crate::web::ErrorRenderer;
+
+/// `Middleware` for setting security response headers.
+///
+/// By default middleware sets following headers:
+///
+/// * `Strict-Transport-Security: max-age=31536000; includeSubDomains`
+/// * `Content-Security-Policy: default-src 'self'`
+/// * `X-Content-Type-Options: nosniff`
+/// * `X-Frame-Options: DENY`
+/// * `Referrer-Policy: strict-origin-when-cross-origin`
+///
+/// Each header could be overridden with corresponding method or with
+/// `header()` method, and disabled with `disable()` method. Headers set
+/// by inner services are not overridden, so particular handler could use
+/// its own policy.
+///
+/// ```rust
+/// use std::time::Duration;
+/// use ntex::web::{self, middleware, App, HttpResponse};
+///
+/// fn main() {
+///     let app = App::new()
+///         .wrap(
+///             middleware::SecureHeaders::new()
+///                 .hsts(Duration::from_secs(86400), false, false)
+///                 .content_security_policy("default-src 'self'; img-src *")
+///                 .disable("x-frame-options"),
+///         )
+///         .service(web::resource("/test").to(|| async { HttpResponse::Ok() }));
+/// }
+/// ```
+pub struct SecureHeaders<E> {
+    inner: Rc<Inner>,
+    _t: PhantomData<E>,
+}
+
+struct Inner {
+    errors: bool,
+    headers: HeaderMap,
+}
+
+impl<E> Default for SecureHeaders<E> {
+    fn default() -> Self {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            header::STRICT_TRANSPORT_SECURITY,
+            HeaderValue::from_static("max-age=31536000; includeSubDomains"),
+        );
+        headers.insert(
+            header::CONTENT_SECURITY_POLICY,
+            HeaderValue::from_static("default-src 'self'"),
+        );
+        headers.insert(
+            header::X_CONTENT_TYPE_OPTIONS,
+            HeaderValue::from_static("nosniff"),
+        );
+        headers.insert(header::X_FRAME_OPTIONS, HeaderValue::from_static("DENY"));
+        headers.insert(
+            header::REFERRER_POLICY,
+            HeaderValue::from_static("strict-origin-when-cross-origin"),
+        );
+
+        SecureHeaders {
+            inner: Rc::new(Inner {
+                headers,
+                errors: false,
+            }),
+            _t: PhantomData,
+        }
+    }
+}
+
+impl<E> SecureHeaders<E> {
+    /// Construct `SecureHeaders` middleware with default headers.
+    pub fn new() -> Self {
+        SecureHeaders::default()
+    }
+
+    /// Set a header, overrides default value.
+    pub fn header<K, V>(mut self, key: K, value: V) -> Self
+    where
+        HeaderName: TryFrom<K>,
+        <HeaderName as TryFrom<K>>::Error: Into<HttpError>,
+        HeaderValue: TryFrom<V>,
+        <HeaderValue as TryFrom<V>>::Error: Into<HttpError>,
+    {
+        #[allow(clippy::match_wild_err_arm)]
+        match HeaderName::try_from(key) {
+            Ok(key) => match HeaderValue::try_from(value) {
+                Ok(value) => {
+                    Rc::get_mut(&mut self.inner)
+                        .expect("Multiple copies exist")
+                        .headers
+                        .insert(key, value);
+                }
+                Err(_) => panic!("Can not create header value"),
+            },
+            Err(_) => panic!("Can not create header name"),
+        }
+        self
+    }
+
+    /// Do not set header.
+    pub fn disable<K>(mut self, key: K) -> Self
+    where
+        HeaderName: TryFrom<K>,
+        <HeaderName as TryFrom<K>>::Error: Into<HttpError>,
+    {
+        match HeaderName::try_from(key) {
+            Ok(key) => {
+                Rc::get_mut(&mut self.inner)
+                    .expect("Multiple copies exist")
+                    .headers
+                    .remove(key);
+            }
+            Err(_) => panic!("Can not create header name"),
+        }
+        self
+    }
+
+    /// Set *STRICT-TRANSPORT-SECURITY* policy.
+    pub fn hsts(self, max_age: Duration, subdomains: bool, preload: bool) -> Self {
+        let mut value = format!("max-age={}", max_age.as_secs());
+        if subdomains {
+            value.push_str("; includeSubDomains");
+        }
+        if preload {
+            value.push_str("; preload");
+        }
+        self.header(header::STRICT_TRANSPORT_SECURITY, value)
+    }
+
+    /// Set *CONTENT-SECURITY-POLICY* header.
+    pub fn content_security_policy(self, policy: &str) -> Self {
+        self.header(header::CONTENT_SECURITY_POLICY, policy)
+    }
+
+    /// Set *X-FRAME-OPTIONS* header, `DENY` or `SAMEORIGIN`.
+    pub fn frame_options(self, value: &str) -> Self {
+        self.header(header::X_FRAME_OPTIONS, value)
+    }
+
+    /// Set *REFERRER-POLICY* header.
+    pub fn referrer_policy(self, policy: &str) -> Self {
+        self.header(header::REFERRER_POLICY, policy)
+    }
+
+    /// Render errors of inner services, by default disabled.
+    ///
+    /// Errors are rendered to responses, so error responses get security
+    /// headers as well. If disabled, errors are passed to outer services
+    /// as is.
+    pub fn render_errors(mut self, enabled: bool) -> Self {
+        Rc::get_mut(&mut self.inner)
+            .expect("Multiple copies exist")
+            .errors = enabled;
+        self
+    }
+}
+
+impl<S, B, E> Transform<S> for SecureHeaders<E>
+where
+    S: Service<Request = WebRequest<E>, Response = WebResponse<B>>,
+    S::Future: 'static,
+    S::Error: Into<E::Container>,
+    E: ErrorRenderer,
+{
+    type Request = WebRequest<E>;
+    type Response = WebResponse<B>;
+    type Error = S::Error;
+    type InitError = ();
+    type Transform = SecureHeadersMiddleware<S, E>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ok(SecureHeadersMiddleware {
+            service,
+            inner: self.inner.clone(),
+            _t: PhantomData,
+        })
+    }
+}
+
+pub struct SecureHeadersMiddleware<S, E> {
+    service: S,
+    inner: Rc<Inner>,
+    _t: PhantomData<E>,
+}
+
+impl<S, B, E> Service for SecureHeadersMiddleware<S, E>
+where
+    S: Service<Request = WebRequest<E>, Response = WebResponse<B>>,
+    S::Future: 'static,
+    S::Error: Into<E::Container>,
+    E: ErrorRenderer,
+{
+    type Request = WebRequest<E>;
+    type Response = WebResponse<B>;
+    type Error = S::Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    #[inline]
+    fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(cx)
+    }
+
+    #[inline]
+    fn poll_shutdown(&self, cx: &mut Context<'_>, is_error: bool) -> Poll<()> {
+        self.service.poll_shutdown(cx, is_error)
+    }
+
+    fn call(&self, req: WebRequest<E>) -> Self::Future {
+        let inner = self.inner.clone();
+        let detached = if inner.errors {
+            Some(req.detach())
+        } else {
+            None
+        };
+        let fut = self.service.call(req);
+
+        async move {
+            let mut res = match fut.await {
+                Ok(res) => res,
+                Err(e) => match detached {
+                    Some(req) => req.error_response::<E, B>(e.into()),
+                    None => return Err(e),
+                },
+            };
+
+            for (key, value) in inner.headers.iter() {
+                if !res.headers().contains_key(key) {
+                    res.headers_mut().insert(key.clone(), value.clone());
+                }
+            }
+            Ok(res)
+        }
+        .boxed_local()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::future::{err, ok};
+
+    use super::*;
+    use crate::http::StatusCode;
+    use crate::service::IntoService;
+    use crate::web::test::{ok_service, TestRequest};
+    use crate::web::{DefaultError, Error, HttpResponse};
+
+    #[ntex_rt::test]
+    async fn test_secure_headers() {
+        let mw = SecureHeaders::<DefaultError>::new()
+            .new_transform(ok_service())
+            .await
+            .unwrap();
+        let req = TestRequest::default().to_srv_request();
+        let resp = mw.call(req).await.unwrap();
+        assert_eq!(
+            resp.headers()
+                .get(header::STRICT_TRANSPORT_SECURITY)
+                .unwrap(),
+            "max-age=31536000; includeSubDomains"
+        );
+        assert_eq!(
+            resp.headers().get(header::CONTENT_SECURITY_POLICY).unwrap(),
+            "default-src 'self'"
+        );
+        assert_eq!(
+            resp.headers().get(header::X_CONTENT_TYPE_OPTIONS).unwrap(),
+            "nosniff"
+        );
+        assert_eq!(resp.headers().get(header::X_FRAME_OPTIONS).unwrap(), "DENY");
+        assert_eq!(
+            resp.headers().get(header::REFERRER_POLICY).unwrap(),
+            "strict-origin-when-cross-origin"
+        );
+
+        let srv = |req: WebRequest<DefaultError>| {
+            ok::<_, Error>(
+                req.into_response(
+                    HttpResponse::Ok()
+                        .header(header::CONTENT_SECURITY_POLICY, "default-src *")
+                        .finish(),
+                ),
+            )
+        };
+        let mw = SecureHeaders::<DefaultError>::new()
+            .hsts(Duration::from_secs(60), false, true)
+            .content_security_policy("default-src 'none'")
+            .frame_options("SAMEORIGIN")
+            .referrer_policy("no-referrer")
+            .disable(header::X_CONTENT_TYPE_OPTIONS)
+            .new_transform(srv.into_service())
+            .await
+            .unwrap();
+        let req = TestRequest::default().to_srv_request();
+        let resp = mw.call(req).await.unwrap();
+        assert_eq!(
+            resp.headers()
+                .get(header::STRICT_TRANSPORT_SECURITY)
+                .unwrap(),
+            "max-age=60; preload"
+        );
+        assert_eq!(
+            resp.headers().get(header::CONTENT_SECURITY_POLICY).unwrap(),
+            "default-src *"
+        );
+        assert_eq!(
+            resp.headers().get(header::X_FRAME_OPTIONS).unwrap(),
+            "SAMEORIGIN"
+        );
+        assert_eq!(
+            resp.headers().get(header::REFERRER_POLICY).unwrap(),
+            "no-referrer"
+        );
+        assert!(!resp.headers().contains_key(header::X_CONTENT_TYPE_OPTIONS));
+    }
+
+    #[ntex_rt::test]
+    async fn test_render_errors() {
+        let srv = |_: WebRequest<DefaultError>| {
+            err::<WebResponse, _>(Error::from(crate::web::error::ErrorForbidden::<
+                _,
+                DefaultError,
+            >("forbidden")))
+        };
+        let mw = SecureHeaders::<DefaultError>::new()
+            .render_errors(true)
+            .new_transform(srv.into_service())
+            .await
+            .unwrap();
+        let req = TestRequest::default().to_srv_request();
+        let resp = mw.call(req).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+        assert_eq!(resp.headers().get(header::X_FRAME_OPTIONS).unwrap(), "DENY");
+
+        let mw = SecureHeaders::<DefaultError>::new()
+            .new_transform(srv.into_service())
+            .await
+            .unwrap();
+        let req = TestRequest::default().to_srv_request();
+        assert!(mw.call(req).await.is_err());
+    }
+}
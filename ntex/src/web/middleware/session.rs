@@ -0,0 +1,727 @@
+//! Session middleware with pluggable storage backends
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::marker::PhantomData;
+use std::rc::Rc;
+use std::task::{Context, Poll};
+use std::time::Duration;
+use std::{error, fmt, mem};
+
+use coo_kie::{Cookie, CookieJar, SameSite};
+use derive_more::Display;
+use futures::future::{ok, ready, FutureExt, LocalBoxFuture, Ready};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::http::cookie::SecureJar;
+use crate::http::{HttpMessage, Payload, Response, StatusCode};
+use crate::service::{Service, Transform};
+use crate::util::shutdown::{Shutdown, ShutdownOrder};
+use crate::web::dev::{WebRequest, WebResponse};
+use crate::web::{ErrorRenderer, FromRequest, HttpRequest};
+
+/// Maximum size of session cookie value
+const MAX_COOKIE_SIZE: usize = 4064;
+
+/// Session state, serialized values by key
+pub type SessionState = HashMap<String, String>;
+
+/// Status of session after request processing
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum SessionStatus {
+    /// Session is not modified
+    Unchanged,
+    /// Session state is modified
+    Changed,
+    /// Session is removed
+    Purged,
+    /// Session key must be renewed, i.e. after login
+    Renewed,
+}
+
+/// Errors which can occur during session loading or saving
+#[derive(Debug, Display)]
+pub enum SessionError {
+    /// Session state serialization error
+    #[display(fmt = "Session serialization error: {}", _0)]
+    Serialize(serde_json::Error),
+    /// Serialized session state is too large
+    #[display(fmt = "Session state is too large")]
+    Overflow,
+    /// Storage backend error
+    #[display(fmt = "Session store error: {}", _0)]
+    Store(Box<dyn error::Error>),
+}
+
+impl error::Error for SessionError {}
+
+/// Session storage backend.
+///
+/// Store loads session state before request get passed to inner
+/// service and persists modified state after inner service completes.
+pub trait SessionStore: 'static {
+    /// Load session state for request.
+    ///
+    /// Returns `None` if request does not have a session.
+    fn load(
+        &self,
+        req: &HttpRequest,
+    ) -> LocalBoxFuture<'static, Result<Option<SessionState>, SessionError>>;
+
+    /// Persist session state.
+    ///
+    /// Returns cookie that must be set on the response. Store is called
+    /// only if session status is not `SessionStatus::Unchanged`.
+    fn save(
+        &self,
+        req: &HttpRequest,
+        state: SessionState,
+        status: SessionStatus,
+    ) -> LocalBoxFuture<'static, Result<Option<Cookie<'static>>, SessionError>>;
+
+    /// Flush pending state to the storage on shutdown.
+    ///
+    /// Called after inner service completes shutdown, so all in-flight
+    /// requests already saved their sessions.
+    fn poll_shutdown(&self, _: &mut Context<'_>, _: bool) -> Poll<()> {
+        Poll::Ready(())
+    }
+}
+
+/// Request session.
+///
+/// `SessionManager` middleware stores `Session` in request extensions.
+/// Values are serialized with `serde_json`. If middleware is not
+/// registered, extractor returns detached empty session.
+///
+/// ```rust
+/// use ntex::web::{self, middleware::Session, HttpResponse};
+///
+/// async fn index(session: Session) -> Result<HttpResponse, serde_json::Error> {
+///     let counter = session.get::<u32>("counter")?.unwrap_or(0) + 1;
+///     session.set("counter", counter)?;
+///     Ok(HttpResponse::Ok().body(format!("Counter: {}", counter)))
+/// }
+/// # fn main() {}
+/// ```
+#[derive(Clone)]
+pub struct Session(Rc<RefCell<SessionInner>>);
+
+struct SessionInner {
+    state: SessionState,
+    status: SessionStatus,
+}
+
+impl Session {
+    fn new(state: SessionState) -> Self {
+        Session(Rc::new(RefCell::new(SessionInner {
+            state,
+            status: SessionStatus::Unchanged,
+        })))
+    }
+
+    /// Get a value from the session.
+    pub fn get<T: DeserializeOwned>(
+        &self,
+        key: &str,
+    ) -> Result<Option<T>, serde_json::Error> {
+        if let Some(s) = self.0.borrow().state.get(key) {
+            Ok(Some(serde_json::from_str(s)?))
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Set a value in the session.
+    pub fn set<T: Serialize>(
+        &self,
+        key: &str,
+        value: T,
+    ) -> Result<(), serde_json::Error> {
+        let value = serde_json::to_string(&value)?;
+        let mut inner = self.0.borrow_mut();
+        inner.changed();
+        inner.state.insert(key.to_owned(), value);
+        Ok(())
+    }
+
+    /// Remove value from the session.
+    pub fn remove(&self, key: &str) {
+        let mut inner = self.0.borrow_mut();
+        if inner.state.remove(key).is_some() {
+            inner.changed();
+        }
+    }
+
+    /// Clear the session.
+    pub fn clear(&self) {
+        let mut inner = self.0.borrow_mut();
+        inner.changed();
+        inner.state.clear()
+    }
+
+    /// Remove session, state is cleared and session cookie is removed.
+    pub fn purge(&self) {
+        let mut inner = self.0.borrow_mut();
+        inner.status = SessionStatus::Purged;
+        inner.state.clear();
+    }
+
+    /// Renew session key, state is preserved.
+    pub fn renew(&self) {
+        let mut inner = self.0.borrow_mut();
+        if inner.status != SessionStatus::Purged {
+            inner.status = SessionStatus::Renewed;
+        }
+    }
+
+    /// Current session status
+    pub fn status(&self) -> SessionStatus {
+        self.0.borrow().status
+    }
+
+    fn take(&self) -> (SessionState, SessionStatus) {
+        let mut inner = self.0.borrow_mut();
+        (mem::take(&mut inner.state), inner.status)
+    }
+}
+
+impl SessionInner {
+    fn changed(&mut self) {
+        if self.status == SessionStatus::Unchanged {
+            self.status = SessionStatus::Changed;
+        }
+    }
+}
+
+impl fmt::Debug for Session {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let inner = self.0.borrow();
+        f.debug_struct("Session")
+            .field("state", &inner.state)
+            .field("status", &inner.status)
+            .finish()
+    }
+}
+
+impl<Err: ErrorRenderer> FromRequest<Err> for Session {
+    type Error = Err::Container;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    #[inline]
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        ok(req
+            .extensions()
+            .get::<Session>()
+            .cloned()
+            .unwrap_or_else(|| Session::new(SessionState::new())))
+    }
+}
+
+/// Cookie session storage backend.
+///
+/// Session state is stored on the client side, in a signed or
+/// private (encrypted) cookie. Cookie size is limited to 4064 bytes,
+/// bigger state can not be saved. Cookies with invalid signature are
+/// ignored and new session is started.
+pub struct CookieSessionStore {
+    jar: SecureJar,
+    name: String,
+    path: String,
+    domain: Option<String>,
+    secure: bool,
+    http_only: bool,
+    same_site: Option<SameSite>,
+    max_age: Option<time::Duration>,
+}
+
+impl CookieSessionStore {
+    /// Construct store with signed cookies.
+    ///
+    /// Key must be at least 32 bytes long.
+    pub fn signed(key: &[u8]) -> Self {
+        CookieSessionStore::new(SecureJar::signed(key))
+    }
+
+    /// Construct store with private cookies, cookie value is encrypted.
+    ///
+    /// Key must be at least 32 bytes long.
+    pub fn private(key: &[u8]) -> Self {
+        CookieSessionStore::new(SecureJar::private(key))
+    }
+
+    fn new(jar: SecureJar) -> Self {
+        CookieSessionStore {
+            jar,
+            name: "ntex-session".to_owned(),
+            path: "/".to_owned(),
+            domain: None,
+            secure: true,
+            http_only: true,
+            same_site: Some(SameSite::Lax),
+            max_age: None,
+        }
+    }
+
+    /// Set cookie name, by default `ntex-session`
+    pub fn name<S: Into<String>>(mut self, value: S) -> Self {
+        self.name = value.into();
+        self
+    }
+
+    /// Set cookie path, by default `/`
+    pub fn path<S: Into<String>>(mut self, value: S) -> Self {
+        self.path = value.into();
+        self
+    }
+
+    /// Set cookie domain
+    pub fn domain<S: Into<String>>(mut self, value: S) -> Self {
+        self.domain = Some(value.into());
+        self
+    }
+
+    /// Set `secure` flag, by default enabled
+    pub fn secure(mut self, value: bool) -> Self {
+        self.secure = value;
+        self
+    }
+
+    /// Set `http_only` flag, by default enabled
+    pub fn http_only(mut self, value: bool) -> Self {
+        self.http_only = value;
+        self
+    }
+
+    /// Set `same_site` attribute, by default `Lax`
+    pub fn same_site(mut self, value: SameSite) -> Self {
+        self.same_site = Some(value);
+        self
+    }
+
+    /// Set cookie max age in seconds, by default session cookie is used
+    pub fn max_age(mut self, seconds: i64) -> Self {
+        self.max_age = Some(time::Duration::seconds(seconds));
+        self
+    }
+
+    fn cookie(&self, value: String) -> Cookie<'static> {
+        let mut cookie = Cookie::new(self.name.clone(), value);
+        cookie.set_path(self.path.clone());
+        cookie.set_secure(self.secure);
+        cookie.set_http_only(self.http_only);
+        if let Some(ref domain) = self.domain {
+            cookie.set_domain(domain.clone());
+        }
+        if let Some(same_site) = self.same_site {
+            cookie.set_same_site(same_site);
+        }
+        if let Some(max_age) = self.max_age {
+            cookie.set_max_age(max_age);
+        }
+        cookie
+    }
+
+    fn load_state(
+        &self,
+        req: &HttpRequest,
+    ) -> Result<Option<SessionState>, SessionError> {
+        let cookie = if let Some(cookie) = req.cookie(&self.name) {
+            cookie
+        } else {
+            return Ok(None);
+        };
+
+        match self.jar.open(cookie) {
+            Some(cookie) => serde_json::from_str(cookie.value())
+                .map(Some)
+                .map_err(SessionError::Serialize),
+            None => {
+                log::debug!("Session cookie verification failed");
+                Ok(None)
+            }
+        }
+    }
+
+    fn save_state(
+        &self,
+        state: SessionState,
+        status: SessionStatus,
+    ) -> Result<Option<Cookie<'static>>, SessionError> {
+        if status == SessionStatus::Purged {
+            let mut jar = CookieJar::new();
+            jar.add_original(self.cookie(String::new()));
+            jar.remove(self.cookie(String::new()));
+            return Ok(jar.delta().next().cloned());
+        }
+
+        let value = serde_json::to_string(&state).map_err(SessionError::Serialize)?;
+        let cookie = self.jar.seal(self.cookie(value));
+        if cookie.value().len() > MAX_COOKIE_SIZE {
+            return Err(SessionError::Overflow);
+        }
+        Ok(Some(cookie))
+    }
+}
+
+impl SessionStore for CookieSessionStore {
+    fn load(
+        &self,
+        req: &HttpRequest,
+    ) -> LocalBoxFuture<'static, Result<Option<SessionState>, SessionError>> {
+        ready(self.load_state(req)).boxed_local()
+    }
+
+    fn save(
+        &self,
+        _: &HttpRequest,
+        state: SessionState,
+        status: SessionStatus,
+    ) -> LocalBoxFuture<'static, Result<Option<Cookie<'static>>, SessionError>> {
+        ready(self.save_state(state, status)).boxed_local()
+    }
+}
+
+/// `Middleware` for session management.
+///
+/// Middleware loads session state from the store, adds `Session` to
+/// request extensions and saves modified state after inner service
+/// completes. Sessions that fail to load are replaced with new empty
+/// sessions, if state can not be saved *500 Internal Server Error*
+/// response is returned.
+///
+/// ```rust
+/// use ntex::web::{self, middleware, App, HttpResponse};
+///
+/// fn main() {
+///     let app = App::new()
+///         .wrap(middleware::SessionManager::new(
+///             middleware::CookieSessionStore::private(&[0; 32]).secure(false),
+///         ))
+///         .service(web::resource("/").to(|| async { HttpResponse::Ok() }));
+/// }
+/// ```
+pub struct SessionManager<St, Err> {
+    store: Rc<St>,
+    timeout: Option<Duration>,
+    _t: PhantomData<Err>,
+}
+
+impl<St: SessionStore, Err> SessionManager<St, Err> {
+    /// Construct `SessionManager` middleware with storage backend
+    pub fn new(store: St) -> Self {
+        SessionManager {
+            store: Rc::new(store),
+            timeout: None,
+            _t: PhantomData,
+        }
+    }
+
+    /// Set timeout for session store shutdown.
+    ///
+    /// On shutdown middleware waits for inner service and then for session
+    /// store to complete shutdown. By default store shutdown is not limited.
+    pub fn shutdown_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+}
+
+impl<S, B, St, E> Transform<S> for SessionManager<St, E>
+where
+    S: Service<Request = WebRequest<E>, Response = WebResponse<B>> + 'static,
+    St: SessionStore,
+    B: 'static,
+    E: 'static,
+{
+    type Request = WebRequest<E>;
+    type Response = WebResponse<B>;
+    type Error = S::Error;
+    type InitError = ();
+    type Transform = SessionManagerMiddleware<S, St, E>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        let mut shutdown = Shutdown::new(ShutdownOrder::InnerFirst);
+        if let Some(timeout) = self.timeout {
+            shutdown = shutdown.outer_timeout(timeout);
+        }
+
+        ok(SessionManagerMiddleware {
+            service: Rc::new(service),
+            store: self.store.clone(),
+            shutdown,
+            _t: PhantomData,
+        })
+    }
+}
+
+pub struct SessionManagerMiddleware<S, St, E> {
+    service: Rc<S>,
+    store: Rc<St>,
+    shutdown: Shutdown,
+    _t: PhantomData<E>,
+}
+
+impl<S, B, St, E> Service for SessionManagerMiddleware<S, St, E>
+where
+    S: Service<Request = WebRequest<E>, Response = WebResponse<B>> + 'static,
+    St: SessionStore,
+    B: 'static,
+    E: 'static,
+{
+    type Request = WebRequest<E>;
+    type Response = WebResponse<B>;
+    type Error = S::Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    #[inline]
+    fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(cx)
+    }
+
+    #[inline]
+    fn poll_shutdown(&self, cx: &mut Context<'_>, is_error: bool) -> Poll<()> {
+        let store = &self.store;
+        self.shutdown
+            .poll_shutdown(cx, is_error, &*self.service, |cx, is_error| {
+                store.poll_shutdown(cx, is_error)
+            })
+    }
+
+    fn call(&self, req: WebRequest<E>) -> Self::Future {
+        let srv = self.service.clone();
+        let store = self.store.clone();
+        let load = store.load(req.request());
+
+        async move {
+            let state = match load.await {
+                Ok(state) => state.unwrap_or_default(),
+                Err(e) => {
+                    log::debug!("Cannot load session: {}", e);
+                    SessionState::new()
+                }
+            };
+            let session = Session::new(state);
+            req.extensions_mut().insert(session.clone());
+
+            let mut res = srv.call(req).await?;
+
+            let (state, status) = session.take();
+            if status == SessionStatus::Unchanged {
+                return Ok(res);
+            }
+            match store.save(res.request(), state, status).await {
+                Ok(Some(cookie)) => {
+                    if let Err(e) = res.response_mut().add_cookie(&cookie) {
+                        log::error!("Cannot set session cookie: {}", e);
+                    }
+                    Ok(res)
+                }
+                Ok(None) => Ok(res),
+                Err(e) => {
+                    log::error!("Cannot save session: {}", e);
+                    let err = Response::new(StatusCode::INTERNAL_SERVER_ERROR);
+                    Ok(res.into_response(err.into_body()))
+                }
+            }
+        }
+        .boxed_local()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::header;
+    use crate::web::test::{call_service, init_service, read_body, TestRequest};
+    use crate::web::{self, App, DefaultError, HttpResponse};
+
+    fn app_cookie(res: &WebResponse) -> Cookie<'static> {
+        res.response().cookies().next().unwrap().into_owned()
+    }
+
+    #[ntex_rt::test]
+    async fn test_cookie_session() {
+        check_store(CookieSessionStore::signed(&[0; 32])).await;
+        check_store(CookieSessionStore::private(&[0; 32])).await;
+    }
+
+    async fn check_store(store: CookieSessionStore) {
+        let srv = init_service(
+            App::new()
+                .wrap(SessionManager::<_, DefaultError>::new(store))
+                .route(
+                    "/",
+                    web::get().to(|session: Session| async move {
+                        let counter = session.get::<u32>("counter")?.unwrap_or(0);
+                        session.set("counter", counter + 1)?;
+                        Ok::<_, serde_json::Error>(format!("{}", counter + 1))
+                    }),
+                )
+                .route(
+                    "/read",
+                    web::get().to(|session: Session| async move {
+                        format!("{:?}", session.get::<u32>("counter").unwrap())
+                    }),
+                )
+                .route(
+                    "/logout",
+                    web::get().to(|session: Session| async move {
+                        session.purge();
+                        HttpResponse::Ok()
+                    }),
+                ),
+        )
+        .await;
+
+        let req = TestRequest::with_uri("/").to_request();
+        let resp = call_service(&srv, req).await;
+        let cookie = app_cookie(&resp);
+        assert_eq!(cookie.name(), "ntex-session");
+        assert_eq!(cookie.http_only(), Some(true));
+        assert_eq!(read_body(resp).await, "1");
+
+        let req = TestRequest::with_uri("/").cookie(cookie).to_request();
+        let resp = call_service(&srv, req).await;
+        let cookie = app_cookie(&resp);
+        assert_eq!(read_body(resp).await, "2");
+
+        // unchanged session
+        let req = TestRequest::with_uri("/read")
+            .cookie(cookie.clone())
+            .to_request();
+        let resp = call_service(&srv, req).await;
+        assert!(resp.headers().get(header::SET_COOKIE).is_none());
+        assert_eq!(read_body(resp).await, "Some(2)");
+
+        // tampered cookie
+        let mut tampered = cookie.clone();
+        tampered.set_value(format!("{}0", cookie.value()));
+        let req = TestRequest::with_uri("/read").cookie(tampered).to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(read_body(resp).await, "None");
+
+        let req = TestRequest::with_uri("/logout").cookie(cookie).to_request();
+        let resp = call_service(&srv, req).await;
+        let cookie = app_cookie(&resp);
+        assert_eq!(cookie.value(), "");
+        assert_eq!(cookie.max_age(), Some(time::Duration::seconds(0)));
+    }
+
+    #[ntex_rt::test]
+    async fn test_cookie_session_overflow() {
+        let srv = init_service(
+            App::new()
+                .wrap(SessionManager::<_, DefaultError>::new(
+                    CookieSessionStore::signed(&[0; 32]),
+                ))
+                .route(
+                    "/",
+                    web::get().to(|session: Session| async move {
+                        session.set("data", "x".repeat(MAX_COOKIE_SIZE)).unwrap();
+                        HttpResponse::Ok()
+                    }),
+                ),
+        )
+        .await;
+
+        let req = TestRequest::with_uri("/").to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::INTERNAL_SERVER_ERROR);
+    }
+
+    #[ntex_rt::test]
+    async fn test_shutdown() {
+        use std::cell::Cell;
+
+        use futures::future::lazy;
+
+        type Log = Rc<RefCell<Vec<&'static str>>>;
+
+        struct Store(Log);
+
+        impl SessionStore for Store {
+            fn load(
+                &self,
+                _: &HttpRequest,
+            ) -> LocalBoxFuture<'static, Result<Option<SessionState>, SessionError>>
+            {
+                ok(None).boxed_local()
+            }
+
+            fn save(
+                &self,
+                _: &HttpRequest,
+                _: SessionState,
+                _: SessionStatus,
+            ) -> LocalBoxFuture<'static, Result<Option<Cookie<'static>>, SessionError>>
+            {
+                ok(None).boxed_local()
+            }
+
+            fn poll_shutdown(&self, _: &mut Context<'_>, _: bool) -> Poll<()> {
+                self.0.borrow_mut().push("store");
+                Poll::Ready(())
+            }
+        }
+
+        struct Srv(Log, Rc<Cell<bool>>);
+
+        impl Service for Srv {
+            type Request = WebRequest<DefaultError>;
+            type Response = WebResponse;
+            type Error = ();
+            type Future = Ready<Result<WebResponse, ()>>;
+
+            fn poll_ready(&self, _: &mut Context<'_>) -> Poll<Result<(), ()>> {
+                Poll::Ready(Ok(()))
+            }
+
+            fn poll_shutdown(&self, _: &mut Context<'_>, _: bool) -> Poll<()> {
+                if self.1.get() {
+                    self.0.borrow_mut().push("inner");
+                    Poll::Ready(())
+                } else {
+                    Poll::Pending
+                }
+            }
+
+            fn call(&self, req: WebRequest<DefaultError>) -> Self::Future {
+                ok(req.into_response(HttpResponse::Ok().finish()))
+            }
+        }
+
+        let log: Log = Rc::new(RefCell::new(Vec::new()));
+        let ready = Rc::new(Cell::new(false));
+        let srv = SessionManager::new(Store(log.clone()))
+            .new_transform(Srv(log.clone(), ready.clone()))
+            .await
+            .unwrap();
+
+        // store is flushed after inner service
+        assert!(lazy(|cx| srv.poll_shutdown(cx, false)).await.is_pending());
+        assert!(log.borrow().is_empty());
+        ready.set(true);
+        assert!(lazy(|cx| srv.poll_shutdown(cx, false)).await.is_ready());
+        assert_eq!(&*log.borrow(), &["inner", "store"]);
+    }
+
+    #[test]
+    fn test_session_status() {
+        let session = Session::new(SessionState::new());
+        assert_eq!(session.status(), SessionStatus::Unchanged);
+        session.remove("key");
+        assert_eq!(session.status(), SessionStatus::Unchanged);
+        session.set("key", 1).unwrap();
+        assert_eq!(session.status(), SessionStatus::Changed);
+        session.renew();
+        assert_eq!(session.status(), SessionStatus::Renewed);
+        session.set("key", 2).unwrap();
+        assert_eq!(session.status(), SessionStatus::Renewed);
+        assert_eq!(session.get::<u32>("key").unwrap(), Some(2));
+        session.purge();
+        assert_eq!(session.status(), SessionStatus::Purged);
+        assert_eq!(session.get::<u32>("key").unwrap(), None);
+    }
+}
@@ -0,0 +1,340 @@
+//! Middleware for request shadowing
+use std::marker::PhantomData;
+use std::rc::Rc;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use bytes::BytesMut;
+use futures::future::{ok, Either, FutureExt, LocalBoxFuture, Ready};
+use futures::{stream, StreamExt};
+
+use crate::http::client::{Client, ClientRequest};
+use crate::http::header::{self, HeaderName};
+use crate::http::{Payload, RequestHead};
+use crate::service::{Service, Transform};
+use crate::web::dev::{WebRequest, WebResponse};
+
+/// Headers that are not copied to shadow request
+const SKIP: &[HeaderName] = &[
+    header::CONNECTION,
+    header::CONTENT_LENGTH,
+    header::HOST,
+    header::PROXY_AUTHORIZATION,
+    header::TE,
+    header::TRAILER,
+    header::TRANSFER_ENCODING,
+    header::UPGRADE,
+];
+
+/// `Middleware` for request shadowing.
+///
+/// Middleware mirrors sample of requests to secondary upstream, for example
+/// to test new version of a service with production traffic. Shadow request
+/// contains method, path, headers and body of original request, it is sent
+/// in background after response for original request is ready. Responses
+/// and errors of shadow requests are ignored.
+///
+/// Request body get buffered up to a limit, requests with larger bodies
+/// are not mirrored. By default limit is 64Kb.
+///
+/// ```rust
+/// use ntex::web::{self, middleware, App, HttpResponse};
+///
+/// fn main() {
+///     let app = App::new()
+///         .wrap(middleware::Shadow::new("http://staging.local:8080").sample(0.1))
+///         .service(web::resource("/").to(|| async { HttpResponse::Ok() }));
+/// }
+/// ```
+pub struct Shadow<E> {
+    inner: Rc<Inner>,
+    _t: PhantomData<E>,
+}
+
+struct Inner {
+    upstream: String,
+    sample: f64,
+    limit: usize,
+    timeout: Duration,
+    client: Option<Client>,
+}
+
+impl<E> Shadow<E> {
+    /// Construct `Shadow` middleware for upstream url.
+    ///
+    /// Path and query of original request are appended to the url.
+    pub fn new(upstream: &str) -> Self {
+        Shadow {
+            inner: Rc::new(Inner {
+                upstream: upstream.trim_end_matches('/').to_string(),
+                sample: 1.0,
+                limit: 65_536,
+                timeout: Duration::from_secs(5),
+                client: None,
+            }),
+            _t: PhantomData,
+        }
+    }
+
+    /// Set fraction of requests to mirror, from `0.0` to `1.0`.
+    ///
+    /// By default all requests are mirrored.
+    pub fn sample(mut self, sample: f64) -> Self {
+        Rc::get_mut(&mut self.inner)
+            .expect("Multiple copies exist")
+            .sample = sample;
+        self
+    }
+
+    /// Set max size of mirrored request body. By default max size is 64Kb
+    pub fn limit(mut self, limit: usize) -> Self {
+        Rc::get_mut(&mut self.inner)
+            .expect("Multiple copies exist")
+            .limit = limit;
+        self
+    }
+
+    /// Set shadow request timeout. By default timeout is 5 seconds
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        Rc::get_mut(&mut self.inner)
+            .expect("Multiple copies exist")
+            .timeout = timeout;
+        self
+    }
+
+    /// Set http client for shadow requests.
+    ///
+    /// By default client with default settings is used.
+    pub fn client(mut self, client: Client) -> Self {
+        Rc::get_mut(&mut self.inner)
+            .expect("Multiple copies exist")
+            .client = Some(client);
+        self
+    }
+}
+
+impl Inner {
+    /// Build shadow request, path and query of original request
+    /// are appended to upstream url
+    fn request(&self, client: &Client, head: &RequestHead) -> ClientRequest {
+        let url = format!(
+            "{}{}",
+            self.upstream,
+            head.uri.path_and_query().map(|p| p.as_str()).unwrap_or("/")
+        );
+
+        let mut req = client
+            .request_from(url.as_str(), head)
+            .timeout(self.timeout);
+        for name in SKIP {
+            req.headers_mut().remove(name);
+        }
+        req
+    }
+}
+
+impl<S, B, E> Transform<S> for Shadow<E>
+where
+    S: Service<Request = WebRequest<E>, Response = WebResponse<B>> + 'static,
+    E: 'static,
+{
+    type Request = WebRequest<E>;
+    type Response = WebResponse<B>;
+    type Error = S::Error;
+    type InitError = ();
+    type Transform = ShadowMiddleware<S, E>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ok(ShadowMiddleware {
+            service: Rc::new(service),
+            client: self.inner.client.clone().unwrap_or_default(),
+            inner: self.inner.clone(),
+            _t: PhantomData,
+        })
+    }
+}
+
+pub struct ShadowMiddleware<S, E> {
+    service: Rc<S>,
+    client: Client,
+    inner: Rc<Inner>,
+    _t: PhantomData<E>,
+}
+
+impl<S, B, E> Service for ShadowMiddleware<S, E>
+where
+    S: Service<Request = WebRequest<E>, Response = WebResponse<B>> + 'static,
+    E: 'static,
+{
+    type Request = WebRequest<E>;
+    type Response = WebResponse<B>;
+    type Error = S::Error;
+    type Future =
+        Either<S::Future, LocalBoxFuture<'static, Result<Self::Response, Self::Error>>>;
+
+    #[inline]
+    fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(cx)
+    }
+
+    #[inline]
+    fn poll_shutdown(&self, cx: &mut Context<'_>, is_error: bool) -> Poll<()> {
+        self.service.poll_shutdown(cx, is_error)
+    }
+
+    fn call(&self, mut req: WebRequest<E>) -> Self::Future {
+        if self.inner.sample < 1.0 && rand::random::<f64>() >= self.inner.sample {
+            return Either::Left(self.service.call(req));
+        }
+
+        let srv = self.service.clone();
+        let client = self.client.clone();
+        let inner = self.inner.clone();
+
+        async move {
+            let mut payload = req.take_payload();
+            let mut body = BytesMut::new();
+            let mut complete = true;
+            while let Some(chunk) = payload.next().await {
+                match chunk {
+                    Ok(chunk) => {
+                        body.extend_from_slice(&chunk);
+                        if body.len() > inner.limit {
+                            complete = false;
+                            break;
+                        }
+                    }
+                    Err(e) => {
+                        // pass error to inner service
+                        let err = stream::once(async move { Err(e) });
+                        req.set_payload(Payload::Stream(Box::pin(err)));
+                        return srv.call(req).await;
+                    }
+                }
+            }
+            let body = body.freeze();
+
+            let shadow = if complete {
+                Some(inner.request(&client, req.head()))
+            } else {
+                log::trace!("Request body is too large, request is not mirrored");
+                None
+            };
+
+            // pass buffered body and rest of the payload to inner service
+            let buffered = stream::once(ok(body.clone()));
+            req.set_payload(Payload::Stream(Box::pin(buffered.chain(payload))));
+            let res = srv.call(req).await;
+
+            if let Some(shadow) = shadow {
+                crate::rt::spawn(async move {
+                    let uri = shadow.get_uri().clone();
+                    match shadow.send_body(body).await {
+                        Ok(res) => log::trace!(
+                            "Shadow request {} completed: {}",
+                            uri,
+                            res.status()
+                        ),
+                        Err(e) => log::debug!("Shadow request {} failed: {}", uri, e),
+                    }
+                });
+            }
+            res
+        }
+        .boxed_local()
+        .right_future()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use bytes::Bytes;
+
+    use super::*;
+    use crate::http::{Method, Response, StatusCode};
+    use crate::web::test::{self, call_service, init_service, read_body, TestRequest};
+    use crate::web::{self, App, DefaultError, HttpRequest, HttpResponse};
+
+    #[ntex_rt::test]
+    async fn test_shadow() {
+        let seen = Arc::new(Mutex::new(Vec::new()));
+
+        let seen2 = seen.clone();
+        let upstream = test::server(move || {
+            let seen = seen2.clone();
+            App::new().service(web::resource("/{tail}*").to(
+                move |req: HttpRequest, body: Bytes| {
+                    seen.lock().unwrap().push((
+                        req.method().clone(),
+                        req.uri().to_string(),
+                        req.headers().get("x-test").cloned(),
+                        body,
+                    ));
+                    async { Response::InternalServerError() }
+                },
+            ))
+        });
+
+        let srv = init_service(
+            App::new()
+                .wrap(
+                    Shadow::<DefaultError>::new(&format!("http://{}/", upstream.addr()))
+                        .limit(8),
+                )
+                .service(
+                    web::resource("/test")
+                        .to(|body: Bytes| async move { HttpResponse::Ok().body(body) }),
+                ),
+        )
+        .await;
+
+        let req = TestRequest::with_uri("/test?q=1")
+            .method(Method::POST)
+            .header("x-test", "1")
+            .set_payload("data")
+            .to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(read_body(resp).await, Bytes::from_static(b"data"));
+
+        // body is too large
+        let req = TestRequest::with_uri("/test")
+            .method(Method::POST)
+            .set_payload("0123456789")
+            .to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(read_body(resp).await, Bytes::from_static(b"0123456789"));
+
+        for _ in 0..100 {
+            if !seen.lock().unwrap().is_empty() {
+                break;
+            }
+            crate::rt::time::delay_for(Duration::from_millis(20)).await;
+        }
+        crate::rt::time::delay_for(Duration::from_millis(50)).await;
+
+        let seen = seen.lock().unwrap();
+        assert_eq!(seen.len(), 1);
+        assert_eq!(seen[0].0, Method::POST);
+        assert_eq!(seen[0].1, "/test?q=1");
+        assert_eq!(seen[0].2.as_ref().unwrap(), "1");
+        assert_eq!(seen[0].3, Bytes::from_static(b"data"));
+    }
+
+    #[ntex_rt::test]
+    async fn test_sample() {
+        let srv = init_service(
+            App::new()
+                .wrap(Shadow::<DefaultError>::new("http://127.0.0.1:1").sample(0.0))
+                .service(web::resource("/").to(|| async { HttpResponse::Ok() })),
+        )
+        .await;
+
+        let resp = call_service(&srv, TestRequest::default().to_request()).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+    }
+}
@@ -0,0 +1,235 @@
+//! Middleware for request processing timeout
+use std::future::Future;
+use std::marker::PhantomData;
+use std::pin::Pin;
+use std::rc::Rc;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use futures::future::{ok, Either, Ready};
+
+use crate::http::StatusCode;
+use crate::rt::time::{delay_for, Delay};
+use crate::service::{Service, Transform};
+use crate::web::dev::{WebRequest, WebResponse};
+use crate::web::error::{ErrorRenderer, RequestTimeoutError};
+
+/// `Middleware` for request processing timeout.
+///
+/// If handler does not complete within specified time, handler future is
+/// dropped and `RequestTimeoutError` is returned, `DefaultError` renders
+/// it as *504 Gateway Timeout* response. Middleware could be registered
+/// for whole application or for particular resource. Timeout is disabled
+/// if it is set to 0.
+///
+/// ```rust
+/// use std::time::Duration;
+/// use ntex::web::{self, middleware, App, HttpResponse};
+///
+/// fn main() {
+///     let app = App::new()
+///         .wrap(middleware::Timeout::new(Duration::from_secs(30)))
+///         .service(
+///             web::resource("/report")
+///                 .wrap(middleware::Timeout::new(Duration::from_secs(120)))
+///                 .to(|| async { HttpResponse::Ok() }),
+///         );
+/// }
+/// ```
+pub struct Timeout<Err> {
+    inner: Rc<Inner>,
+    _t: PhantomData<Err>,
+}
+
+struct Inner {
+    timeout: Duration,
+    status: StatusCode,
+}
+
+impl<Err> Timeout<Err> {
+    /// Construct `Timeout` middleware
+    pub fn new(timeout: Duration) -> Self {
+        Timeout {
+            inner: Rc::new(Inner {
+                timeout,
+                status: StatusCode::GATEWAY_TIMEOUT,
+            }),
+            _t: PhantomData,
+        }
+    }
+
+    /// Set response status of `RequestTimeoutError`.
+    ///
+    /// By default *504 Gateway Timeout* is used.
+    pub fn status(mut self, status: StatusCode) -> Self {
+        Rc::get_mut(&mut self.inner)
+            .expect("Multiple copies exist")
+            .status = status;
+        self
+    }
+}
+
+impl<S, B, E> Transform<S> for Timeout<E>
+where
+    S: Service<Request = WebRequest<E>, Response = WebResponse<B>, Error = E::Container>,
+    E: ErrorRenderer,
+    RequestTimeoutError: Into<E::Container>,
+{
+    type Request = WebRequest<E>;
+    type Response = WebResponse<B>;
+    type Error = S::Error;
+    type InitError = ();
+    type Transform = TimeoutMiddleware<S, E>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ok(TimeoutMiddleware {
+            service,
+            inner: self.inner.clone(),
+            _t: PhantomData,
+        })
+    }
+}
+
+pub struct TimeoutMiddleware<S, E> {
+    service: S,
+    inner: Rc<Inner>,
+    _t: PhantomData<E>,
+}
+
+impl<S, B, E> Service for TimeoutMiddleware<S, E>
+where
+    S: Service<Request = WebRequest<E>, Response = WebResponse<B>, Error = E::Container>,
+    E: ErrorRenderer,
+    RequestTimeoutError: Into<E::Container>,
+{
+    type Request = WebRequest<E>;
+    type Response = WebResponse<B>;
+    type Error = S::Error;
+    type Future = Either<TimeoutResponse<S, B, E>, S::Future>;
+
+    #[inline]
+    fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(cx)
+    }
+
+    #[inline]
+    fn poll_shutdown(&self, cx: &mut Context<'_>, is_error: bool) -> Poll<()> {
+        self.service.poll_shutdown(cx, is_error)
+    }
+
+    fn call(&self, req: WebRequest<E>) -> Self::Future {
+        if self.inner.timeout == Duration::from_millis(0) {
+            return Either::Right(self.service.call(req));
+        }
+
+        Either::Left(TimeoutResponse {
+            fut: self.service.call(req),
+            delay: delay_for(self.inner.timeout),
+            inner: self.inner.clone(),
+            _t: PhantomData,
+        })
+    }
+}
+
+#[doc(hidden)]
+#[pin_project::pin_project]
+pub struct TimeoutResponse<S: Service, B, E> {
+    #[pin]
+    fut: S::Future,
+    #[pin]
+    delay: Delay,
+    inner: Rc<Inner>,
+    _t: PhantomData<(B, E)>,
+}
+
+impl<S, B, E> Future for TimeoutResponse<S, B, E>
+where
+    S: Service<Request = WebRequest<E>, Response = WebResponse<B>, Error = E::Container>,
+    E: ErrorRenderer,
+    RequestTimeoutError: Into<E::Container>,
+{
+    type Output = Result<WebResponse<B>, S::Error>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+
+        if let Poll::Ready(res) = this.fut.poll(cx) {
+            return Poll::Ready(res);
+        }
+
+        match this.delay.poll(cx) {
+            Poll::Ready(_) => {
+                log::debug!("Request processing timeout");
+                Poll::Ready(Err(RequestTimeoutError::new(this.inner.status).into()))
+            }
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::error::ResponseError;
+    use crate::rt::time::delay_for;
+    use crate::web::test::{call_service, init_service, TestRequest};
+    use crate::web::{self, App, DefaultError, HttpResponse};
+
+    #[ntex_rt::test]
+    async fn test_timeout() {
+        let srv = init_service(
+            App::new()
+                .wrap(Timeout::<DefaultError>::new(Duration::from_millis(50)))
+                .service(web::resource("/fast").to(|| async { HttpResponse::Ok() }))
+                .service(web::resource("/slow").to(|| async {
+                    delay_for(Duration::from_millis(200)).await;
+                    HttpResponse::Ok()
+                }))
+                .service(
+                    web::resource("/resource")
+                        .wrap(
+                            Timeout::new(Duration::from_millis(10))
+                                .status(StatusCode::SERVICE_UNAVAILABLE),
+                        )
+                        .to(|| async {
+                            delay_for(Duration::from_millis(30)).await;
+                            HttpResponse::Ok()
+                        }),
+                ),
+        )
+        .await;
+
+        let req = TestRequest::with_uri("/fast").to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let req = TestRequest::with_uri("/slow").to_request();
+        let err = srv.call(req).await.err().unwrap();
+        assert_eq!(err.error_response().status(), StatusCode::GATEWAY_TIMEOUT);
+
+        let req = TestRequest::with_uri("/resource").to_request();
+        let err = srv.call(req).await.err().unwrap();
+        assert_eq!(
+            err.error_response().status(),
+            StatusCode::SERVICE_UNAVAILABLE
+        );
+    }
+
+    #[ntex_rt::test]
+    async fn test_timeout_disabled() {
+        let srv = init_service(
+            App::new()
+                .wrap(Timeout::<DefaultError>::new(Duration::from_millis(0)))
+                .service(web::resource("/").to(|| async {
+                    delay_for(Duration::from_millis(20)).await;
+                    HttpResponse::Ok()
+                })),
+        )
+        .await;
+
+        let req = TestRequest::default().to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+    }
+}
@@ -0,0 +1,379 @@
+//! Middleware for `Server-Timing` response header
+use std::cell::RefCell;
+use std::fmt::{self, Write};
+use std::future::Future;
+use std::marker::PhantomData;
+use std::pin::Pin;
+use std::rc::Rc;
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+
+use futures::future::{ok, Ready};
+
+use crate::http::header::{HeaderName, HeaderValue};
+use crate::http::Payload;
+use crate::service::{Service, Transform};
+use crate::web::dev::{WebRequest, WebResponse};
+use crate::web::{ErrorRenderer, FromRequest, HttpRequest};
+
+/// Per-request timing breakdown.
+///
+/// `ServerTiming` middleware stores `Timings` in request extensions,
+/// middlewares and handlers append named spans to it. `Timings` is a cheap
+/// handle, clones refer to the same list of spans. If middleware is not
+/// registered, extractor returns detached empty list.
+///
+/// ```rust
+/// use ntex::web::{self, middleware::Timings, HttpResponse};
+///
+/// async fn index(timings: Timings) -> HttpResponse {
+///     {
+///         let _span = timings.start("db");
+///         // query database
+///     }
+///     timings.add_desc("cache", "miss", std::time::Duration::from_millis(2));
+///     HttpResponse::Ok().finish()
+/// }
+/// # fn main() {}
+/// ```
+#[derive(Clone, Default)]
+pub struct Timings(Rc<RefCell<Vec<Span>>>);
+
+struct Span {
+    name: String,
+    desc: Option<String>,
+    dur: Duration,
+}
+
+impl Timings {
+    /// Create empty timings list
+    pub fn new() -> Self {
+        Timings::default()
+    }
+
+    /// Add span with specified duration.
+    ///
+    /// Span name must be valid http token.
+    pub fn add<N: Into<String>>(&self, name: N, dur: Duration) {
+        self.0.borrow_mut().push(Span {
+            dur,
+            name: name.into(),
+            desc: None,
+        });
+    }
+
+    /// Add span with description
+    pub fn add_desc<N, D>(&self, name: N, desc: D, dur: Duration)
+    where
+        N: Into<String>,
+        D: Into<String>,
+    {
+        self.0.borrow_mut().push(Span {
+            dur,
+            name: name.into(),
+            desc: Some(desc.into()),
+        });
+    }
+
+    /// Start span, span is added when returned guard is dropped
+    pub fn start<N: Into<String>>(&self, name: N) -> TimingGuard {
+        TimingGuard {
+            timings: self.clone(),
+            name: Some(name.into()),
+            start: Instant::now(),
+        }
+    }
+
+    /// Total duration of spans with specified name
+    pub fn get(&self, name: &str) -> Option<Duration> {
+        self.0
+            .borrow()
+            .iter()
+            .filter(|s| s.name == name)
+            .fold(None, |total, s| {
+                Some(total.unwrap_or_else(Duration::default) + s.dur)
+            })
+    }
+
+    /// Number of recorded spans
+    pub fn len(&self) -> usize {
+        self.0.borrow().len()
+    }
+
+    /// Check if no spans are recorded
+    pub fn is_empty(&self) -> bool {
+        self.0.borrow().is_empty()
+    }
+}
+
+/// Formats spans as `Server-Timing` header value
+impl fmt::Display for Timings {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (idx, span) in self.0.borrow().iter().enumerate() {
+            if idx > 0 {
+                f.write_str(", ")?;
+            }
+            f.write_str(&span.name)?;
+            if let Some(ref desc) = span.desc {
+                f.write_str(";desc=\"")?;
+                for ch in desc.chars() {
+                    if ch == '"' || ch == '\\' {
+                        f.write_char('\\')?;
+                    }
+                    f.write_char(ch)?;
+                }
+                f.write_char('"')?;
+            }
+            write!(f, ";dur={:.3}", millis(span.dur))?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Debug for Timings {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Timings({})", self)
+    }
+}
+
+impl<Err: ErrorRenderer> FromRequest<Err> for Timings {
+    type Error = Err::Container;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    #[inline]
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        ok(req
+            .extensions()
+            .get::<Timings>()
+            .cloned()
+            .unwrap_or_default())
+    }
+}
+
+/// Span guard, adds span to timings on drop
+pub struct TimingGuard {
+    timings: Timings,
+    name: Option<String>,
+    start: Instant,
+}
+
+impl TimingGuard {
+    /// Finish span with description
+    pub fn finish_desc<D: Into<String>>(mut self, desc: D) {
+        if let Some(name) = self.name.take() {
+            self.timings.add_desc(name, desc, self.start.elapsed());
+        }
+    }
+}
+
+impl Drop for TimingGuard {
+    fn drop(&mut self) {
+        if let Some(name) = self.name.take() {
+            self.timings.add(name, self.start.elapsed());
+        }
+    }
+}
+
+pub(super) fn millis(dur: Duration) -> f64 {
+    dur.as_nanos() as f64 / 1_000_000.0
+}
+
+/// `Middleware` for `Server-Timing` response header.
+///
+/// Middleware adds `Timings` to request extensions and serializes recorded
+/// spans to `Server-Timing` response header. By default, middleware also
+/// records `total` span, time spent in inner services.
+///
+/// ```rust
+/// use ntex::web::{self, middleware, App, HttpResponse};
+///
+/// fn main() {
+///     let app = App::new()
+///         .wrap(middleware::ServerTiming::default())
+///         .service(web::resource("/").to(|| async { HttpResponse::Ok() }));
+/// }
+/// ```
+pub struct ServerTiming<Err> {
+    total: bool,
+    _t: PhantomData<Err>,
+}
+
+impl<Err> ServerTiming<Err> {
+    /// Construct `ServerTiming` middleware
+    pub fn new() -> Self {
+        ServerTiming {
+            total: true,
+            _t: PhantomData,
+        }
+    }
+
+    /// Record `total` span, by default enabled
+    pub fn total(mut self, enabled: bool) -> Self {
+        self.total = enabled;
+        self
+    }
+}
+
+impl<Err> Default for ServerTiming<Err> {
+    fn default() -> Self {
+        ServerTiming::new()
+    }
+}
+
+impl<S, B, E> Transform<S> for ServerTiming<E>
+where
+    S: Service<Request = WebRequest<E>, Response = WebResponse<B>>,
+{
+    type Request = WebRequest<E>;
+    type Response = WebResponse<B>;
+    type Error = S::Error;
+    type InitError = ();
+    type Transform = ServerTimingMiddleware<S, E>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ok(ServerTimingMiddleware {
+            service,
+            total: self.total,
+            _t: PhantomData,
+        })
+    }
+}
+
+pub struct ServerTimingMiddleware<S, E> {
+    service: S,
+    total: bool,
+    _t: PhantomData<E>,
+}
+
+impl<S, B, E> Service for ServerTimingMiddleware<S, E>
+where
+    S: Service<Request = WebRequest<E>, Response = WebResponse<B>>,
+{
+    type Request = WebRequest<E>;
+    type Response = WebResponse<B>;
+    type Error = S::Error;
+    type Future = ServerTimingResponse<S, B, E>;
+
+    #[inline]
+    fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(cx)
+    }
+
+    #[inline]
+    fn poll_shutdown(&self, cx: &mut Context<'_>, is_error: bool) -> Poll<()> {
+        self.service.poll_shutdown(cx, is_error)
+    }
+
+    fn call(&self, req: WebRequest<E>) -> Self::Future {
+        let timings = Timings::new();
+        req.extensions_mut().insert(timings.clone());
+
+        ServerTimingResponse {
+            fut: self.service.call(req),
+            start: if self.total {
+                Some(Instant::now())
+            } else {
+                None
+            },
+            timings,
+            _t: PhantomData,
+        }
+    }
+}
+
+#[doc(hidden)]
+#[pin_project::pin_project]
+pub struct ServerTimingResponse<S: Service, B, E> {
+    #[pin]
+    fut: S::Future,
+    timings: Timings,
+    start: Option<Instant>,
+    _t: PhantomData<(B, E)>,
+}
+
+impl<S, B, E> Future for ServerTimingResponse<S, B, E>
+where
+    S: Service<Request = WebRequest<E>, Response = WebResponse<B>>,
+{
+    type Output = Result<WebResponse<B>, S::Error>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        let mut res = futures::ready!(this.fut.poll(cx))?;
+
+        if let Some(start) = this.start.take() {
+            this.timings.add("total", start.elapsed());
+        }
+        if !this.timings.is_empty() {
+            if let Ok(value) = HeaderValue::from_str(&this.timings.to_string()) {
+                res.headers_mut()
+                    .append(HeaderName::from_static("server-timing"), value);
+            }
+        }
+        Poll::Ready(Ok(res))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::StatusCode;
+    use crate::web::test::{init_service, TestRequest};
+    use crate::web::{self, App, DefaultError, HttpResponse};
+
+    #[test]
+    fn test_timings() {
+        let timings = Timings::new();
+        assert!(timings.is_empty());
+        assert_eq!(timings.get("db"), None);
+
+        timings.add("db", Duration::from_micros(1500));
+        timings.add_desc("cache", "hit \"fast\"", Duration::from_millis(1));
+        timings.add("db", Duration::from_micros(500));
+        drop(timings.start("app"));
+        timings.start("render").finish_desc("html");
+
+        assert_eq!(timings.len(), 5);
+        assert_eq!(timings.get("db"), Some(Duration::from_millis(2)));
+        let value = timings.to_string();
+        assert!(value.starts_with(
+            "db;dur=1.500, cache;desc=\"hit \\\"fast\\\"\";dur=1.000, db;dur=0.500, app;dur="
+        ));
+        assert!(value.contains(", render;desc=\"html\";dur="));
+    }
+
+    #[ntex_rt::test]
+    async fn test_server_timing() {
+        let mut srv = init_service(
+            App::new()
+                .wrap(ServerTiming::<DefaultError>::new())
+                .service(web::resource("/").to(|t: Timings| async move {
+                    t.add("db", Duration::from_millis(5));
+                    HttpResponse::Ok().finish()
+                })),
+        )
+        .await;
+
+        let req = TestRequest::with_uri("/").to_request();
+        let res = srv.call(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+        let value = res
+            .headers()
+            .get("server-timing")
+            .unwrap()
+            .to_str()
+            .unwrap();
+        assert!(value.starts_with("db;dur=5.000, total;dur="));
+
+        let mut srv = init_service(
+            App::new()
+                .wrap(ServerTiming::<DefaultError>::new().total(false))
+                .service(web::resource("/").to(|| async { HttpResponse::Ok() })),
+        )
+        .await;
+        let req = TestRequest::with_uri("/").to_request();
+        let res = srv.call(req).await.unwrap();
+        assert!(!res.headers().contains_key("server-timing"));
+    }
+}
@@ -58,21 +58,33 @@
 //! ## Package feature
 //!
 //! * `cookie` - enables http cookie support
+//! * `tus` - enables resumable uploads support
+//! * `graphql` - enables GraphQL integration helpers
 //! * `compress` - enables content encoding compression support
+//! * `jwt` - enables jwt validation middleware and `Claims` extractor
 //! * `openssl` - enables ssl support via `openssl` crate
 //! * `rustls` - enables ssl support via `rustls` crate
 
 mod app;
 mod app_service;
 mod config;
+mod dynamic;
 pub mod error;
 mod error_default;
 mod extract;
+pub mod files;
+#[cfg(feature = "graphql")]
+pub mod graphql;
 pub mod guard;
 mod handler;
 mod httprequest;
+pub mod i18n;
 mod info;
+pub mod jsonrpc;
 pub mod middleware;
+pub mod multipart;
+mod redirect;
+pub mod report;
 mod request;
 mod resource;
 mod responder;
@@ -82,9 +94,14 @@ mod route;
 mod scope;
 mod server;
 mod service;
+pub mod sitemap;
 pub mod test;
+#[cfg(feature = "tus")]
+pub mod tus;
 pub mod types;
 mod util;
+pub mod wellknown;
+pub mod xmlrpc;
 
 // re-export proc macro
 pub use ntex_macros::web_connect as connect;
@@ -102,15 +119,20 @@ pub use crate::http::ResponseBuilder as HttpResponseBuilder;
 
 pub use self::app::App;
 pub use self::config::ServiceConfig;
+pub use self::dynamic::{DynamicRouteId, DynamicRouter};
 pub use self::error::{DefaultError, Error, ErrorRenderer, WebResponseError};
 pub use self::extract::FromRequest;
+pub use self::files::{Files, NamedFile};
 pub use self::handler::Handler;
 pub use self::httprequest::HttpRequest;
+pub use self::redirect::Redirect;
 pub use self::resource::Resource;
 pub use self::responder::{Either, Responder};
 pub use self::route::Route;
 pub use self::scope::Scope;
-pub use self::server::HttpServer;
+pub use self::server::{
+    HttpServer, HttpServerConfig, HttpServerTlsConfig, ListenerInfo, StartupReport,
+};
 pub use self::util::*;
 
 pub mod dev {
@@ -124,7 +146,7 @@ pub mod dev {
     pub use crate::web::info::ConnectionInfo;
     pub use crate::web::request::WebRequest;
     pub use crate::web::response::WebResponse;
-    pub use crate::web::rmap::ResourceMap;
+    pub use crate::web::rmap::{ResourceMap, RouteInfo};
     pub use crate::web::service::{
         WebServiceAdapter, WebServiceConfig, WebServiceFactory,
     };
@@ -0,0 +1,403 @@
+//! Multipart uploads support
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use bytes::Bytes;
+use derive_more::{Display, From};
+use futures::future::{ready, FutureExt, LocalBoxFuture};
+use futures::{Stream, StreamExt};
+use mime::Mime;
+
+use crate::http::error::PayloadError;
+
+use super::error::{BlockingError, MultipartError};
+use super::util::block;
+
+/// Upload metadata
+#[derive(Debug, Clone, Default)]
+pub struct UploadMeta {
+    name: Option<String>,
+    filename: Option<String>,
+    content_type: Option<Mime>,
+    size: u64,
+}
+
+impl UploadMeta {
+    /// Create new upload metadata
+    pub fn new(
+        name: Option<String>,
+        filename: Option<String>,
+        content_type: Option<Mime>,
+    ) -> Self {
+        UploadMeta {
+            name,
+            filename,
+            content_type,
+            size: 0,
+        }
+    }
+
+    /// Name of the form field
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    /// File name provided by the client
+    pub fn filename(&self) -> Option<&str> {
+        self.filename.as_deref()
+    }
+
+    /// Content type of the uploaded data
+    pub fn content_type(&self) -> Option<&Mime> {
+        self.content_type.as_ref()
+    }
+
+    /// Size of the uploaded data in bytes
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+}
+
+/// Destination for streamed uploads.
+///
+/// Sink receives upload data chunk by chunk as it arrives from the client,
+/// so uploaded data does not have to be buffered in memory or in local files.
+/// Sink could be implemented for any storage, for example for s3 compatible
+/// object store with multipart upload api.
+pub trait UploadSink {
+    /// The type of value produced by the sink on successful upload
+    type Output;
+
+    /// The type of error produced by the sink
+    type Error;
+
+    /// Write chunk of upload data
+    fn write(&mut self, chunk: Bytes) -> LocalBoxFuture<'_, Result<(), Self::Error>>;
+
+    /// Complete upload, all data has been written to the sink.
+    fn finalize(
+        self,
+        meta: UploadMeta,
+    ) -> LocalBoxFuture<'static, Result<Self::Output, Self::Error>>;
+
+    /// Abort upload, sink should release all resources allocated for the upload.
+    ///
+    /// By default does nothing.
+    fn abort(self) -> LocalBoxFuture<'static, ()>
+    where
+        Self: Sized,
+    {
+        ready(()).boxed_local()
+    }
+}
+
+/// Errors which can occur during streaming upload to a sink
+#[derive(Debug, Display, From)]
+pub enum UploadError<E> {
+    /// Upload size is bigger than allowed
+    #[display(fmt = "Upload size is bigger than allowed")]
+    Overflow,
+    /// Error that occur during reading payload
+    #[display(fmt = "Error that occur during reading payload: {}", _0)]
+    Payload(PayloadError),
+    /// Error that occur during reading multipart field
+    #[display(fmt = "Error that occur during reading multipart field: {}", _0)]
+    Multipart(MultipartError),
+    /// Sink error
+    #[display(fmt = "Upload sink error: {}", _0)]
+    #[from(ignore)]
+    Sink(E),
+}
+
+/// Stream upload data to the sink.
+///
+/// If `limit` is set and stream produces more data than allowed upload
+/// get aborted with `UploadError::Overflow` error. Sink get aborted
+/// on any error.
+///
+/// ```rust
+/// use ntex::web::{self, error, types, HttpResponse};
+/// use ntex::web::multipart::{upload, FsSink, UploadMeta};
+///
+/// async fn index(body: types::Payload) -> Result<HttpResponse, error::Error> {
+///     let meta = UploadMeta::new(None, Some("data.bin".to_string()), None);
+///     let res = upload(body, meta, FsSink::new("/tmp/data.bin"), Some(1024 * 1024))
+///         .await
+///         .map_err(|e| error::ErrorBadRequest(e.to_string()))?;
+///     Ok(HttpResponse::Ok().body(format!("uploaded {} bytes", res.meta().size())))
+/// }
+/// ```
+pub async fn upload<S, T>(
+    stream: S,
+    meta: UploadMeta,
+    sink: T,
+    limit: Option<u64>,
+) -> Result<T::Output, UploadError<T::Error>>
+where
+    S: Stream<Item = Result<Bytes, PayloadError>> + Unpin,
+    T: UploadSink,
+{
+    upload_stream(stream, meta, sink, limit).await
+}
+
+/// Stream upload data to the sink, stream error get converted
+/// to `UploadError`
+pub(in crate::web) async fn upload_stream<S, T, E>(
+    mut stream: S,
+    mut meta: UploadMeta,
+    mut sink: T,
+    limit: Option<u64>,
+) -> Result<T::Output, UploadError<T::Error>>
+where
+    S: Stream<Item = Result<Bytes, E>> + Unpin,
+    T: UploadSink,
+    UploadError<T::Error>: From<E>,
+{
+    while let Some(item) = stream.next().await {
+        let chunk = match item {
+            Ok(chunk) => chunk,
+            Err(e) => {
+                sink.abort().await;
+                return Err(e.into());
+            }
+        };
+
+        meta.size += chunk.len() as u64;
+        if let Some(limit) = limit {
+            if meta.size > limit {
+                sink.abort().await;
+                return Err(UploadError::Overflow);
+            }
+        }
+
+        if let Err(e) = sink.write(chunk).await {
+            sink.abort().await;
+            return Err(UploadError::Sink(e));
+        }
+    }
+
+    sink.finalize(meta).await.map_err(UploadError::Sink)
+}
+
+/// File uploaded by `FsSink`
+#[derive(Debug)]
+pub struct UploadedFile {
+    path: PathBuf,
+    meta: UploadMeta,
+}
+
+impl UploadedFile {
+    /// Path of the stored file
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Upload metadata
+    pub fn meta(&self) -> &UploadMeta {
+        &self.meta
+    }
+}
+
+/// Upload sink that stores upload data to a file.
+///
+/// File operations are executed on a thread pool.
+pub struct FsSink {
+    path: PathBuf,
+    file: Option<File>,
+    created: bool,
+}
+
+impl FsSink {
+    /// Create sink for specified path, file get created on first write.
+    ///
+    /// Existing file is not overwritten, upload fails if file exists.
+    pub fn new<P: Into<PathBuf>>(path: P) -> Self {
+        FsSink {
+            path: path.into(),
+            file: None,
+            created: false,
+        }
+    }
+}
+
+/// Create new file, fails if file already exists
+fn create(path: &Path) -> io::Result<File> {
+    OpenOptions::new().write(true).create_new(true).open(path)
+}
+
+fn blocking_err(err: BlockingError<io::Error>) -> io::Error {
+    match err {
+        BlockingError::Error(e) => e,
+        BlockingError::Canceled => {
+            io::Error::new(io::ErrorKind::Other, "Thread pool is gone")
+        }
+    }
+}
+
+impl UploadSink for FsSink {
+    type Output = UploadedFile;
+    type Error = io::Error;
+
+    fn write(&mut self, chunk: Bytes) -> LocalBoxFuture<'_, Result<(), io::Error>> {
+        async move {
+            let mut file = match self.file.take() {
+                Some(file) => file,
+                None => {
+                    let path = self.path.clone();
+                    let file =
+                        block(move || create(&path)).await.map_err(blocking_err)?;
+                    // only file created by the sink is removed on abort
+                    self.created = true;
+                    file
+                }
+            };
+            let file = block(move || {
+                file.write_all(&chunk)?;
+                Ok(file)
+            })
+            .await
+            .map_err(blocking_err)?;
+
+            self.file = Some(file);
+            Ok(())
+        }
+        .boxed_local()
+    }
+
+    fn finalize(
+        self,
+        meta: UploadMeta,
+    ) -> LocalBoxFuture<'static, Result<UploadedFile, io::Error>> {
+        let FsSink { path, file, .. } = self;
+
+        async move {
+            let p = path.clone();
+            block(move || {
+                let file = match file {
+                    Some(file) => file,
+                    None => create(&p)?,
+                };
+                file.sync_all()
+            })
+            .await
+            .map_err(blocking_err)?;
+
+            Ok(UploadedFile { path, meta })
+        }
+        .boxed_local()
+    }
+
+    fn abort(self) -> LocalBoxFuture<'static, ()> {
+        let FsSink {
+            path,
+            file,
+            created,
+        } = self;
+
+        async move {
+            drop(file);
+            if created {
+                let _ = block(move || fs::remove_file(path)).await;
+            }
+        }
+        .boxed_local()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::stream;
+
+    use super::*;
+
+    #[derive(Default)]
+    struct MemSink(Vec<u8>);
+
+    impl UploadSink for MemSink {
+        type Output = (Vec<u8>, UploadMeta);
+        type Error = ();
+
+        fn write(&mut self, chunk: Bytes) -> LocalBoxFuture<'_, Result<(), ()>> {
+            self.0.extend_from_slice(&chunk);
+            ready(Ok(())).boxed_local()
+        }
+
+        fn finalize(
+            self,
+            meta: UploadMeta,
+        ) -> LocalBoxFuture<'static, Result<Self::Output, ()>> {
+            ready(Ok((self.0, meta))).boxed_local()
+        }
+    }
+
+    #[ntex_rt::test]
+    async fn test_upload() {
+        let meta = UploadMeta::new(
+            Some("file".to_string()),
+            Some("test.txt".to_string()),
+            Some(mime::TEXT_PLAIN),
+        );
+        let body = stream::iter(vec![
+            Ok(Bytes::from_static(b"hello ")),
+            Ok(Bytes::from_static(b"world")),
+        ]);
+        let (data, meta) = upload(body, meta, MemSink::default(), None).await.unwrap();
+        assert_eq!(data, b"hello world");
+        assert_eq!(meta.name(), Some("file"));
+        assert_eq!(meta.filename(), Some("test.txt"));
+        assert_eq!(meta.content_type(), Some(&mime::TEXT_PLAIN));
+        assert_eq!(meta.size(), 11);
+
+        let body = stream::iter(vec![
+            Ok(Bytes::from_static(b"hello ")),
+            Ok(Bytes::from_static(b"world")),
+        ]);
+        let res = upload(body, UploadMeta::default(), MemSink::default(), Some(8)).await;
+        assert!(matches!(res, Err(UploadError::Overflow)));
+
+        let body = stream::iter(vec![
+            Ok(Bytes::from_static(b"hello ")),
+            Err(PayloadError::Incomplete(None)),
+        ]);
+        let res = upload(body, UploadMeta::default(), MemSink::default(), None).await;
+        assert!(matches!(res, Err(UploadError::Payload(_))));
+    }
+
+    #[ntex_rt::test]
+    async fn test_fs_sink() {
+        let path = std::env::temp_dir()
+            .join(format!("ntex-upload-test-{}", std::process::id()));
+
+        let body = stream::iter(vec![
+            Ok(Bytes::from_static(b"hello ")),
+            Ok(Bytes::from_static(b"world")),
+        ]);
+        let res = upload(body, UploadMeta::default(), FsSink::new(&path), None)
+            .await
+            .unwrap();
+        assert_eq!(res.path(), path.as_path());
+        assert_eq!(res.meta().size(), 11);
+        assert_eq!(fs::read(&path).unwrap(), b"hello world");
+
+        // existing file is not overwritten or removed
+        let body = stream::iter(vec![Ok(Bytes::from_static(b"data"))]);
+        let res = upload(body, UploadMeta::default(), FsSink::new(&path), None).await;
+        match res {
+            Err(UploadError::Sink(e)) => {
+                assert_eq!(e.kind(), io::ErrorKind::AlreadyExists)
+            }
+            _ => panic!(),
+        }
+        assert_eq!(fs::read(&path).unwrap(), b"hello world");
+        fs::remove_file(&path).unwrap();
+
+        let body = stream::iter(vec![
+            Ok(Bytes::from_static(b"hello ")),
+            Ok(Bytes::from_static(b"world")),
+        ]);
+        let res = upload(body, UploadMeta::default(), FsSink::new(&path), Some(8)).await;
+        assert!(matches!(res, Err(UploadError::Overflow)));
+        assert!(!path.exists());
+    }
+}
@@ -0,0 +1,151 @@
+use std::borrow::Cow;
+
+use futures::future::{ok, Ready};
+
+use crate::http::header::LOCATION;
+use crate::http::{Response, StatusCode};
+
+use super::error::ErrorRenderer;
+use super::httprequest::HttpRequest;
+use super::responder::Responder;
+
+/// Redirect responder.
+///
+/// By default responder returns *307 Temporary Redirect* response, request
+/// method and body are preserved by client. Location could be absolute url
+/// or path relative to request's url.
+///
+/// ```rust
+/// use ntex::web::{self, App, Redirect};
+///
+/// async fn old_index() -> Redirect {
+///     Redirect::to("/new/index.html").permanent()
+/// }
+///
+/// fn main() {
+///     let app = App::new()
+///         .service(web::resource("/index.html").to(old_index));
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct Redirect {
+    location: Cow<'static, str>,
+    status: StatusCode,
+}
+
+impl Redirect {
+    /// Create *307 Temporary Redirect* responder
+    pub fn to<T: Into<Cow<'static, str>>>(location: T) -> Self {
+        Redirect {
+            location: location.into(),
+            status: StatusCode::TEMPORARY_REDIRECT,
+        }
+    }
+
+    /// Use *308 Permanent Redirect* status
+    pub fn permanent(mut self) -> Self {
+        self.status = StatusCode::PERMANENT_REDIRECT;
+        self
+    }
+
+    /// Use *307 Temporary Redirect* status
+    pub fn temporary(mut self) -> Self {
+        self.status = StatusCode::TEMPORARY_REDIRECT;
+        self
+    }
+
+    /// Use *303 See Other* status.
+    ///
+    /// Client follows redirect with *GET* request, useful for redirecting
+    /// after form submission.
+    pub fn see_other(mut self) -> Self {
+        self.status = StatusCode::SEE_OTHER;
+        self
+    }
+
+    /// Use *301 Moved Permanently* status.
+    ///
+    /// Clients could change request method to *GET*, use `permanent()`
+    /// to preserve request method.
+    pub fn moved_permanently(mut self) -> Self {
+        self.status = StatusCode::MOVED_PERMANENTLY;
+        self
+    }
+
+    /// Location of redirect
+    pub fn location(&self) -> &str {
+        &self.location
+    }
+
+    /// Status code of redirect response
+    pub fn status(&self) -> StatusCode {
+        self.status
+    }
+}
+
+impl<Err: ErrorRenderer> Responder<Err> for Redirect {
+    type Error = Err::Container;
+    type Future = Ready<Result<Response, Self::Error>>;
+
+    fn respond_to(self, _: &HttpRequest) -> Self::Future {
+        ok(Response::build(self.status)
+            .header(LOCATION, self.location.as_ref())
+            .finish())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::web::test::{call_service, init_service, TestRequest};
+    use crate::web::{self, App, DefaultError};
+
+    #[ntex_rt::test]
+    async fn test_redirect() {
+        let req = TestRequest::default().to_http_request();
+        let resp = <Redirect as Responder<DefaultError>>::respond_to(
+            Redirect::to("/new"),
+            &req,
+        )
+        .await
+        .unwrap();
+        assert_eq!(resp.status(), StatusCode::TEMPORARY_REDIRECT);
+        assert_eq!(resp.headers().get(LOCATION).unwrap(), "/new");
+
+        for (redirect, status) in [
+            (
+                Redirect::to("/new").permanent(),
+                StatusCode::PERMANENT_REDIRECT,
+            ),
+            (Redirect::to("/new").see_other(), StatusCode::SEE_OTHER),
+            (
+                Redirect::to("/new").moved_permanently(),
+                StatusCode::MOVED_PERMANENTLY,
+            ),
+            (
+                Redirect::to("/new").see_other().temporary(),
+                StatusCode::TEMPORARY_REDIRECT,
+            ),
+        ]
+        .iter()
+        .cloned()
+        {
+            assert_eq!(redirect.status(), status);
+            let resp = <Redirect as Responder<DefaultError>>::respond_to(redirect, &req)
+                .await
+                .unwrap();
+            assert_eq!(resp.status(), status);
+        }
+
+        let srv = init_service(App::new().service(web::resource("/").to(|| async {
+            Redirect::to(format!("https://{}/", "example.com")).permanent()
+        })))
+        .await;
+        let resp = call_service(&srv, TestRequest::default().to_request()).await;
+        assert_eq!(resp.status(), StatusCode::PERMANENT_REDIRECT);
+        assert_eq!(
+            resp.headers().get(LOCATION).unwrap(),
+            "https://example.com/"
+        );
+    }
+}
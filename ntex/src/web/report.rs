@@ -0,0 +1,214 @@
+//! Error reporting for server errors and panics
+use std::any::Any;
+use std::fmt;
+
+use crate::http::header::{HeaderMap, HeaderName};
+use crate::http::{Method, StatusCode, Uri};
+
+use super::request::WebRequest;
+
+/// Error reporter.
+///
+/// Reporter get called for every *5xx* response and for every panic in
+/// application's services. Reporter could forward reports to an error
+/// tracking service, for example Sentry adapter converts `ErrorReport` to
+/// a Sentry event. Reporter is registered with `App::error_reporter()`.
+///
+/// Reporter is implemented for `Fn(&ErrorReport)` closures.
+///
+/// ```rust
+/// use ntex::web::{self, App, HttpResponse};
+/// use ntex::web::report::ErrorReport;
+///
+/// fn main() {
+///     let app = App::new()
+///         .error_reporter(|report: &ErrorReport| {
+///             log::error!("{}", report);
+///         })
+///         .service(web::resource("/").to(|| async { HttpResponse::Ok() }));
+/// }
+/// ```
+pub trait ErrorReporter {
+    /// Report error
+    fn report(&self, report: &ErrorReport);
+
+    /// Name of the header that contains request id.
+    ///
+    /// By default it is `x-request-id`.
+    fn request_id_header(&self) -> HeaderName {
+        HeaderName::from_static("x-request-id")
+    }
+
+    /// Request headers that should be captured in reports.
+    ///
+    /// Only these headers are captured, so credentials and cookies are not
+    /// leaked to reports. By default `user-agent`, `referer`, `content-type`
+    /// and `content-length` headers are captured.
+    fn headers(&self) -> Vec<HeaderName> {
+        vec![
+            HeaderName::from_static("user-agent"),
+            HeaderName::from_static("referer"),
+            HeaderName::from_static("content-type"),
+            HeaderName::from_static("content-length"),
+        ]
+    }
+}
+
+impl<F> ErrorReporter for F
+where
+    F: Fn(&ErrorReport),
+{
+    fn report(&self, report: &ErrorReport) {
+        (*self)(report)
+    }
+}
+
+/// Kind of reported error
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ReportKind {
+    /// Service responded with *5xx* response
+    Response,
+    /// Service returned error
+    Error,
+    /// Service panicked
+    Panic,
+}
+
+/// Error report with request metadata
+#[derive(Debug)]
+pub struct ErrorReport {
+    kind: ReportKind,
+    method: Method,
+    uri: Uri,
+    route: Option<String>,
+    request_id: Option<String>,
+    headers: HeaderMap,
+    status: StatusCode,
+    message: Option<String>,
+}
+
+impl ErrorReport {
+    /// Kind of error
+    pub fn kind(&self) -> ReportKind {
+        self.kind
+    }
+
+    /// Request method
+    pub fn method(&self) -> &Method {
+        &self.method
+    }
+
+    /// Request uri
+    pub fn uri(&self) -> &Uri {
+        &self.uri
+    }
+
+    /// Pattern of the matched resource.
+    ///
+    /// Pattern is not available for panics.
+    pub fn route(&self) -> Option<&str> {
+        self.route.as_deref()
+    }
+
+    /// Request id
+    pub fn request_id(&self) -> Option<&str> {
+        self.request_id.as_deref()
+    }
+
+    /// Captured request headers
+    pub fn headers(&self) -> &HeaderMap {
+        &self.headers
+    }
+
+    /// Response status, *500 Internal Server Error* for panics
+    pub fn status(&self) -> StatusCode {
+        self.status
+    }
+
+    /// Error or panic message
+    pub fn message(&self) -> Option<&str> {
+        self.message.as_deref()
+    }
+}
+
+impl fmt::Display for ErrorReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {} {}", self.status, self.method, self.uri)?;
+        if let Some(ref route) = self.route {
+            write!(f, " route: {}", route)?;
+        }
+        if let Some(ref id) = self.request_id {
+            write!(f, " request-id: {}", id)?;
+        }
+        if let Some(ref msg) = self.message {
+            write!(f, " error: {}", msg)?;
+        }
+        Ok(())
+    }
+}
+
+/// Request metadata captured before request get processed
+pub(super) struct RequestMeta {
+    method: Method,
+    uri: Uri,
+    request_id: Option<String>,
+    headers: HeaderMap,
+}
+
+impl RequestMeta {
+    pub(super) fn new<Err>(req: &WebRequest<Err>, reporter: &dyn ErrorReporter) -> Self {
+        let request_id = req
+            .headers()
+            .get(reporter.request_id_header())
+            .and_then(|v| v.to_str().ok())
+            .map(|s| s.to_string());
+
+        let mut headers = HeaderMap::new();
+        for name in reporter.headers() {
+            for value in req.headers().get_all(&name) {
+                headers.append(name.clone(), value.clone());
+            }
+        }
+
+        RequestMeta {
+            request_id,
+            headers,
+            method: req.method().clone(),
+            uri: req.uri().clone(),
+        }
+    }
+
+    pub(super) fn report(
+        self,
+        kind: ReportKind,
+        status: StatusCode,
+        route: Option<String>,
+        message: Option<String>,
+    ) -> ErrorReport {
+        ErrorReport {
+            kind,
+            status,
+            route,
+            message,
+            method: self.method,
+            uri: self.uri,
+            request_id: self.request_id,
+            headers: self.headers,
+        }
+    }
+}
+
+/// Extract message from panic payload
+pub(super) fn panic_message(panic: &(dyn Any + Send)) -> Option<String> {
+    if let Some(s) = panic.downcast_ref::<&str>() {
+        Some((*s).to_string())
+    } else {
+        panic.downcast_ref::<String>().cloned()
+    }
+}
+
+/// Request extension, marks response rendered by error renderer
+pub(crate) struct RenderedError;
+
+/// Request extension, type name of extractor that failed
+pub(crate) struct ExtractorFailure(pub(crate) &'static str);
@@ -7,11 +7,11 @@ use crate::http::{
     Extensions, HeaderMap, HttpMessage, Method, Payload, PayloadStream, RequestHead,
     Response, Uri, Version,
 };
-use crate::router::{Path, Resource};
+use crate::router::{Path, Resource, ResourceDef};
 
 use super::config::AppConfig;
 use super::error::ErrorRenderer;
-use super::httprequest::HttpRequest;
+use super::httprequest::{DetachedRequest, HttpRequest};
 use super::info::ConnectionInfo;
 use super::response::WebResponse;
 use super::rmap::ResourceMap;
@@ -42,6 +42,11 @@ impl<Err> WebRequest<Err> {
         }
     }
 
+    /// Retain request parts for rendering errors of inner services
+    pub(crate) fn detach(&self) -> DetachedRequest {
+        self.req.detach()
+    }
+
     /// Deconstruct request into parts
     pub fn into_parts(mut self) -> (HttpRequest, Payload) {
         let pl = Rc::get_mut(&mut (self.req).0).unwrap().payload.take();
@@ -82,6 +87,12 @@ impl<Err> WebRequest<Err> {
         WebResponse::new(self.req, res.into())
     }
 
+    /// Get reference to inner request
+    #[inline]
+    pub fn request(&self) -> &HttpRequest {
+        &self.req
+    }
+
     /// This method returns reference to the request head
     #[inline]
     pub fn head(&self) -> &RequestHead {
@@ -176,6 +187,16 @@ impl<Err> WebRequest<Err> {
         self.req.match_info_mut()
     }
 
+    #[inline]
+    pub(crate) fn add_resource(&mut self, rdef: Rc<ResourceDef>) {
+        self.req.add_resource(rdef)
+    }
+
+    #[inline]
+    pub(crate) fn set_unmatched(&mut self) {
+        self.req.set_unmatched()
+    }
+
     #[inline]
     /// Get a reference to a `ResourceMap` of current application.
     pub fn resource_map(&self) -> &ResourceMap {
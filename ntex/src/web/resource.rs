@@ -7,18 +7,17 @@ use std::{fmt, mem};
 
 use futures::future::{ok, Either, LocalBoxFuture, Ready};
 
-use crate::http::{Extensions, Response};
+use crate::http::{Extensions, StatusCode};
 use crate::router::{IntoPattern, ResourceDef};
 use crate::service::boxed::{self, BoxService, BoxServiceFactory};
-use crate::service::{
-    apply, apply_fn_factory, IntoServiceFactory, Service, ServiceFactory, Transform,
-};
+use crate::service::{apply, IntoServiceFactory, Service, ServiceFactory, Transform};
 
 use super::dev::{insert_slesh, WebServiceConfig, WebServiceFactory};
-use super::error::ErrorRenderer;
+use super::error::{default_response, ErrorRenderer};
 use super::extract::FromRequest;
 use super::guard::Guard;
 use super::handler::Handler;
+use super::middleware::{from_fn, Next};
 use super::request::WebRequest;
 use super::responder::Responder;
 use super::response::WebResponse;
@@ -285,14 +284,14 @@ where
     /// Register a resource middleware function.
     ///
     /// This function accepts instance of `WebRequest` type and
-    /// mutable reference to the next middleware in chain.
+    /// `next` handle of the next middleware in chain, handle could be
+    /// moved into async block.
     ///
     /// This is similar to `App's` middlewares, but middleware get invoked on resource level.
     /// Resource level middlewares are not allowed to change response
     /// type (i.e modify response's body).
     ///
     /// ```rust
-    /// use ntex::service::Service;
     /// use ntex::web::{self, App};
     /// use ntex::http::header::{CONTENT_TYPE, HeaderValue};
     ///
@@ -303,15 +302,12 @@ where
     /// fn main() {
     ///     let app = App::new().service(
     ///         web::resource("/index.html")
-    ///             .wrap_fn(|req, srv| {
-    ///                 let fut = srv.call(req);
-    ///                 async {
-    ///                     let mut res = fut.await?;
-    ///                     res.headers_mut().insert(
-    ///                        CONTENT_TYPE, HeaderValue::from_static("text/plain"),
-    ///                     );
-    ///                     Ok(res)
-    ///                 }
+    ///             .wrap_fn(|req, next| async move {
+    ///                 let mut res = next.call(req).await?;
+    ///                 res.headers_mut().insert(
+    ///                    CONTENT_TYPE, HeaderValue::from_static("text/plain"),
+    ///                 );
+    ///                 Ok(res)
     ///             })
     ///             .route(web::get().to(index)));
     /// }
@@ -330,11 +326,13 @@ where
         >,
     >
     where
-        F: Fn(WebRequest<Err>, &T::Service) -> R + Clone,
+        F: Fn(WebRequest<Err>, Next<Err>) -> R,
         R: Future<Output = Result<WebResponse, Err::Container>>,
+        T::Service: 'static,
+        <T::Service as Service>::Future: 'static,
     {
         Resource {
-            endpoint: apply_fn_factory(self.endpoint, mw),
+            endpoint: apply(from_fn(mw), self.endpoint),
             rdef: self.rdef,
             name: self.name,
             guards: self.guards,
@@ -387,10 +385,12 @@ where
         } else {
             Some(std::mem::replace(&mut self.guards, Vec::new()))
         };
+        let patterns: Vec<_> =
+            self.rdef.iter().map(|p| config.expand_pattern(p)).collect();
         let mut rdef = if config.is_root() || !self.rdef.is_empty() {
-            ResourceDef::new(insert_slesh(self.rdef.clone()))
+            ResourceDef::new(insert_slesh(patterns))
         } else {
-            ResourceDef::new(self.rdef.clone())
+            ResourceDef::new(patterns)
         };
         if let Some(ref name) = self.name {
             *rdef.name_mut() = name.clone();
@@ -399,10 +399,41 @@ where
         if let Some(ref mut ext) = self.data {
             config.set_service_data(ext);
         }
+        // check data required by handlers
+        let mut required = Vec::new();
+        for route in &self.routes {
+            route.required_data(&mut required);
+        }
+        config.validate_data(&rdef, self.data.as_ref(), &required);
+
         config.register_service(rdef, guards, self, None)
     }
 }
 
+impl<Err, T> Resource<Err, T>
+where
+    T: ServiceFactory<
+            Config = (),
+            Request = WebRequest<Err>,
+            Response = WebResponse,
+            Error = Err::Container,
+            InitError = (),
+        > + 'static,
+    Err: ErrorRenderer,
+{
+    /// Split root level resource into definition, guards and service factory
+    pub(super) fn into_parts(
+        mut self,
+    ) -> (ResourceDef, Vec<Box<dyn Guard>>, HttpNewService<Err>) {
+        let guards = mem::take(&mut self.guards);
+        let mut rdef = ResourceDef::new(insert_slesh(self.rdef.clone()));
+        if let Some(ref name) = self.name {
+            *rdef.name_mut() = name.clone();
+        }
+        (rdef, guards, boxed::factory(self.into_factory()))
+    }
+}
+
 impl<Err, T> IntoServiceFactory<T> for Resource<Err, T>
 where
     T: ServiceFactory<
@@ -521,10 +552,8 @@ impl<Err: ErrorRenderer> Service for ResourceService<Err> {
             Either::Right(default.call(req))
         } else {
             let req = req.into_parts().0;
-            Either::Left(ok(WebResponse::new(
-                req,
-                Response::MethodNotAllowed().finish(),
-            )))
+            let res = default_response::<Err>(&req, StatusCode::METHOD_NOT_ALLOWED);
+            Either::Left(ok(WebResponse::new(req, res)))
         }
     }
 }
@@ -97,6 +97,17 @@ impl<Err: ErrorRenderer> Responder<Err> for ResponseBuilder {
     }
 }
 
+#[cfg(feature = "send")]
+impl<Err: ErrorRenderer> Responder<Err> for crate::http::SendResponse {
+    type Error = Err::Container;
+    type Future = Ready<Result<Response, Self::Error>>;
+
+    #[inline]
+    fn respond_to(self, _: &HttpRequest) -> Self::Future {
+        ok(self.into())
+    }
+}
+
 impl<T, Err> Responder<Err> for Option<T>
 where
     T: Responder<Err>,
@@ -1,10 +1,17 @@
 use std::fmt;
 
+use futures::future::{ok, Ready};
+
 use crate::http::body::{Body, MessageBody, ResponseBody};
-use crate::http::{HeaderMap, Response, ResponseHead, StatusCode};
+use crate::http::{
+    HeaderMap, Payload, Response, ResponseCommitted, ResponseHead, StatusCode, Uri,
+};
+use crate::router::Path;
 
 use super::error::ErrorRenderer;
+use super::extract::FromRequest;
 use super::httprequest::HttpRequest;
+use super::report::RenderedError;
 
 /// An service http response
 pub struct WebResponse<B = Body> {
@@ -27,6 +34,7 @@ impl<B> WebResponse<B> {
 
         let err = err.into();
         let res: Response = err.error_response();
+        request.extensions_mut().insert(RenderedError);
 
         if res.head().status == StatusCode::INTERNAL_SERVER_ERROR {
             log::error!("Internal Server Error: {:?}", err);
@@ -61,6 +69,27 @@ impl<B> WebResponse<B> {
         &self.request
     }
 
+    /// Get a reference to the Path parameters of matched resource.
+    #[inline]
+    pub fn match_info(&self) -> &Path<Uri> {
+        self.request.match_info()
+    }
+
+    /// The resource definition pattern that matched the request path.
+    ///
+    /// Check [`HttpRequest::match_pattern()`](../struct.HttpRequest.html#method.match_pattern)
+    /// for detailed information.
+    #[inline]
+    pub fn match_pattern(&self) -> Option<String> {
+        self.request.match_pattern()
+    }
+
+    /// The name of the resource that matched the request path.
+    #[inline]
+    pub fn match_name(&self) -> Option<&str> {
+        self.request.match_name()
+    }
+
     /// Get reference to response
     #[inline]
     pub fn response(&self) -> &Response<B> {
@@ -129,11 +158,37 @@ impl<B> WebResponse<B> {
 }
 
 impl<B> Into<Response<B>> for WebResponse<B> {
-    fn into(self) -> Response<B> {
+    fn into(mut self) -> Response<B> {
+        // pass response hooks to dispatcher
+        let commit = self.request.extensions_mut().remove::<ResponseCommitted>();
+        if let Some(commit) = commit {
+            self.response.extensions_mut().insert(commit);
+        }
         self.response
     }
 }
 
+/// Extract response delivery hooks of the request.
+///
+/// Hooks are stored in request extensions and passed to dispatcher
+/// with the response.
+impl<Err: ErrorRenderer> FromRequest<Err> for ResponseCommitted {
+    type Error = Err::Container;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    #[inline]
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        let mut extensions = req.extensions_mut();
+        if let Some(commit) = extensions.get::<ResponseCommitted>() {
+            ok(commit.clone())
+        } else {
+            let commit = ResponseCommitted::new();
+            extensions.insert(commit.clone());
+            ok(commit)
+        }
+    }
+}
+
 impl<B: MessageBody> fmt::Debug for WebResponse<B> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         let res = writeln!(
@@ -8,6 +8,25 @@ use crate::router::ResourceDef;
 use crate::web::error::UrlGenerationError;
 use crate::web::httprequest::HttpRequest;
 
+/// Registered route, part of application route table
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RouteInfo {
+    pattern: String,
+    name: String,
+}
+
+impl RouteInfo {
+    /// Full path pattern of the route, including scope prefixes
+    pub fn pattern(&self) -> &str {
+        &self.pattern
+    }
+
+    /// Route name, empty string for unnamed routes
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct ResourceMap {
     root: ResourceDef,
@@ -35,6 +54,47 @@ impl ResourceMap {
         }
     }
 
+    /// Names of all named resources, in registration order
+    pub(crate) fn names(&self, names: &mut Vec<String>) {
+        for (pattern, nested) in &self.patterns {
+            if !pattern.name().is_empty() {
+                names.push(pattern.name().to_string());
+            }
+            if let Some(ref nested) = nested {
+                nested.names(names);
+            }
+        }
+    }
+
+    /// Application route table.
+    ///
+    /// Returns full patterns of all registered resources, in registration
+    /// order. External resources are not included.
+    pub fn routes(&self) -> Vec<RouteInfo> {
+        let mut routes = Vec::new();
+        self.collect_routes("", &mut routes);
+        routes
+    }
+
+    fn collect_routes(&self, prefix: &str, routes: &mut Vec<RouteInfo>) {
+        for (pattern, nested) in &self.patterns {
+            let path = if prefix.ends_with('/') && pattern.pattern().starts_with('/') {
+                format!("{}{}", prefix, &pattern.pattern()[1..])
+            } else {
+                format!("{}{}", prefix, pattern.pattern())
+            };
+
+            if let Some(ref nested) = nested {
+                nested.collect_routes(&path, routes);
+            } else if !pattern.pattern().contains("://") {
+                routes.push(RouteInfo {
+                    pattern: path,
+                    name: pattern.name().to_string(),
+                });
+            }
+        }
+    }
+
     pub(crate) fn finish(&self, current: Rc<ResourceMap>) {
         for (_, nested) in &self.patterns {
             if let Some(ref nested) = nested {
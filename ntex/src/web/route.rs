@@ -1,8 +1,9 @@
+use std::future::Future;
 use std::mem;
 use std::rc::Rc;
 use std::task::{Context, Poll};
 
-use futures::future::{ok, ready, LocalBoxFuture, Ready};
+use futures::future::{ok, ready, FutureExt, LocalBoxFuture, Ready};
 
 use crate::http::Method;
 use crate::{Service, ServiceFactory};
@@ -15,8 +16,16 @@ use super::handler::{Handler, HandlerFn, HandlerWrapper};
 use super::request::WebRequest;
 use super::responder::Responder;
 use super::response::WebResponse;
+use super::types::DataRequirement;
 use super::HttpResponse;
 
+type RequestHook<Err> = Rc<
+    dyn Fn(
+        WebRequest<Err>,
+    ) -> LocalBoxFuture<'static, Result<WebRequest<Err>, WebResponse>>,
+>;
+type ResponseHook = Rc<dyn Fn(WebResponse) -> LocalBoxFuture<'static, WebResponse>>;
+
 /// Resource route definition
 ///
 /// Route uses builder-like pattern for configuration.
@@ -25,6 +34,8 @@ pub struct Route<Err: ErrorRenderer = DefaultError> {
     handler: Box<dyn HandlerFn<Err>>,
     methods: Vec<Method>,
     guards: Rc<Vec<Box<dyn Guard>>>,
+    map_request: Vec<RequestHook<Err>>,
+    map_response: Vec<ResponseHook>,
 }
 
 impl<Err: ErrorRenderer> Route<Err> {
@@ -34,6 +45,8 @@ impl<Err: ErrorRenderer> Route<Err> {
             handler: Box::new(HandlerWrapper::new(|| ready(HttpResponse::NotFound()))),
             methods: Vec::new(),
             guards: Rc::new(Vec::new()),
+            map_request: Vec::new(),
+            map_response: Vec::new(),
         }
     }
 
@@ -47,11 +60,23 @@ impl<Err: ErrorRenderer> Route<Err> {
         mem::replace(Rc::get_mut(&mut self.guards).unwrap(), Vec::new())
     }
 
+    pub(super) fn required_data(&self, data: &mut Vec<DataRequirement>) {
+        self.handler.required_data(data)
+    }
+
     pub(super) fn service(&self) -> RouteService<Err> {
         RouteService {
-            handler: self.handler.clone_handler(),
+            handler: Rc::from(self.handler.clone_handler()),
             guards: self.guards.clone(),
             methods: self.methods.clone(),
+            hooks: if self.map_request.is_empty() && self.map_response.is_empty() {
+                None
+            } else {
+                Some(Rc::new((
+                    self.map_request.clone(),
+                    self.map_response.clone(),
+                )))
+            },
         }
     }
 }
@@ -71,9 +96,10 @@ impl<Err: ErrorRenderer> ServiceFactory for Route<Err> {
 }
 
 pub struct RouteService<Err: ErrorRenderer> {
-    handler: Box<dyn HandlerFn<Err>>,
+    handler: Rc<dyn HandlerFn<Err>>,
     methods: Vec<Method>,
     guards: Rc<Vec<Box<dyn Guard>>>,
+    hooks: Option<Rc<(Vec<RequestHook<Err>>, Vec<ResponseHook>)>>,
 }
 
 impl<Err: ErrorRenderer> RouteService<Err> {
@@ -104,7 +130,28 @@ impl<Err: ErrorRenderer> Service for RouteService<Err> {
 
     #[inline]
     fn call(&self, req: WebRequest<Err>) -> Self::Future {
-        self.handler.call(req)
+        if let Some(ref hooks) = self.hooks {
+            let hooks = hooks.clone();
+            let handler = self.handler.clone();
+
+            async move {
+                let mut req = req;
+                for f in hooks.0.iter() {
+                    req = match f(req).await {
+                        Ok(req) => req,
+                        Err(res) => return Ok(res),
+                    };
+                }
+                let mut res = handler.call(req).await?;
+                for f in hooks.1.iter() {
+                    res = f(res).await;
+                }
+                Ok(res)
+            }
+            .boxed_local()
+        } else {
+            self.handler.call(req)
+        }
     }
 }
 
@@ -203,6 +250,70 @@ impl<Err: ErrorRenderer> Route<Err> {
         self.handler = Box::new(HandlerWrapper::new(handler));
         self
     }
+
+    /// Transform request before it get passed to the route handler.
+    ///
+    /// This is lightweight alternative to a middleware for a single route.
+    /// Function could stop request processing by returning response
+    /// as an error, in that case handler does not get called.
+    /// Multiple functions get called in order of registration.
+    ///
+    /// ```rust
+    /// use ntex::http::header::CONTENT_TYPE;
+    /// use ntex::web::{self, App, HttpResponse};
+    ///
+    /// fn main() {
+    ///     let app = App::new().service(web::resource("/index.html").route(
+    ///         web::post()
+    ///             .map_request(|mut req| async move {
+    ///                 if !req.headers().contains_key(CONTENT_TYPE) {
+    ///                     return Err(req.into_response(HttpResponse::BadRequest()));
+    ///                 }
+    ///                 Ok(req)
+    ///             })
+    ///             .to(|| async { HttpResponse::Ok() })),
+    ///     );
+    /// }
+    /// ```
+    pub fn map_request<F, R>(mut self, f: F) -> Self
+    where
+        F: Fn(WebRequest<Err>) -> R + 'static,
+        R: Future<Output = Result<WebRequest<Err>, WebResponse>> + 'static,
+    {
+        self.map_request
+            .push(Rc::new(move |req| f(req).boxed_local()));
+        self
+    }
+
+    /// Transform response generated by the route handler.
+    ///
+    /// Multiple functions get called in order of registration.
+    ///
+    /// ```rust
+    /// use ntex::http::header::{HeaderValue, CACHE_CONTROL};
+    /// use ntex::web::{self, App, HttpResponse};
+    ///
+    /// fn main() {
+    ///     let app = App::new().service(web::resource("/index.html").route(
+    ///         web::get()
+    ///             .map_response(|mut res| async move {
+    ///                 res.headers_mut()
+    ///                     .insert(CACHE_CONTROL, HeaderValue::from_static("no-cache"));
+    ///                 res
+    ///             })
+    ///             .to(|| async { HttpResponse::Ok() })),
+    ///     );
+    /// }
+    /// ```
+    pub fn map_response<F, R>(mut self, f: F) -> Self
+    where
+        F: Fn(WebResponse) -> R + 'static,
+        R: Future<Output = WebResponse> + 'static,
+    {
+        self.map_response
+            .push(Rc::new(move |res| f(res).boxed_local()));
+        self
+    }
 }
 
 #[cfg(test)]
@@ -212,10 +323,11 @@ mod tests {
     use bytes::Bytes;
     use serde_derive::Serialize;
 
-    use crate::http::{Method, StatusCode};
+    use crate::http::header::{self, HeaderValue};
+    use crate::http::{HttpMessage, Method, StatusCode};
     use crate::rt::time::delay_for;
     use crate::web::test::{call_service, init_service, read_body, TestRequest};
-    use crate::web::{self, error, App, DefaultError, HttpResponse};
+    use crate::web::{self, error, App, DefaultError, HttpRequest, HttpResponse};
 
     #[derive(Serialize, PartialEq, Debug)]
     struct MyObject {
@@ -290,4 +402,55 @@ mod tests {
         let body = read_body(resp).await;
         assert_eq!(body, Bytes::from_static(b"{\"name\":\"test\"}"));
     }
+
+    #[ntex_rt::test]
+    async fn test_route_map() {
+        let srv = init_service(
+            App::new().service(
+                web::resource("/test")
+                    .route(
+                        web::get()
+                            .map_request(|mut req| async move {
+                                req.headers_mut().insert(
+                                    header::CONTENT_TYPE,
+                                    HeaderValue::from_static("text/plain"),
+                                );
+                                Ok(req)
+                            })
+                            .map_response(|mut res| async move {
+                                res.headers_mut().insert(
+                                    header::CACHE_CONTROL,
+                                    HeaderValue::from_static("no-cache"),
+                                );
+                                res
+                            })
+                            .to(|req: HttpRequest| async move {
+                                HttpResponse::Ok().body(req.content_type().to_string())
+                            }),
+                    )
+                    .route(web::post().map_request(|req| async move {
+                        Err(req.error_response(
+                            error::ErrorBadRequest::<_, DefaultError>("err"),
+                        ))
+                    })),
+            ),
+        )
+        .await;
+
+        let req = TestRequest::with_uri("/test").to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(
+            resp.headers().get(header::CACHE_CONTROL).unwrap(),
+            "no-cache"
+        );
+        let body = read_body(resp).await;
+        assert_eq!(body, Bytes::from_static(b"text/plain"));
+
+        let req = TestRequest::with_uri("/test")
+            .method(Method::POST)
+            .to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    }
 }
@@ -6,17 +6,17 @@ use std::task::{Context, Poll};
 
 use futures::future::{ok, Either, Future, LocalBoxFuture, Ready};
 
-use crate::http::{Extensions, Response};
+use crate::http::{Extensions, StatusCode};
 use crate::router::{ResourceDef, ResourceInfo, Router};
 use crate::service::boxed::{self, BoxService, BoxServiceFactory};
-use crate::service::{
-    apply, apply_fn_factory, IntoServiceFactory, Service, ServiceFactory, Transform,
-};
+use crate::service::{apply, IntoServiceFactory, Service, ServiceFactory, Transform};
 
 use super::config::ServiceConfig;
 use super::dev::{WebServiceConfig, WebServiceFactory};
-use super::error::ErrorRenderer;
+use super::error::{default_response, ErrorRenderer};
 use super::guard::Guard;
+use super::handler::StrictContentType;
+use super::middleware::{from_fn, Next};
 use super::request::WebRequest;
 use super::resource::Resource;
 use super::response::WebResponse;
@@ -167,6 +167,14 @@ where
         self
     }
 
+    /// Enable or disable strict content-type enforcement for scope.
+    ///
+    /// Overrides application setting, check
+    /// [`App::strict_content_type()`](struct.App.html#method.strict_content_type).
+    pub fn strict_content_type(self, enabled: bool) -> Self {
+        self.app_data(StrictContentType(enabled))
+    }
+
     /// Run external configuration as part of the scope building
     /// process
     ///
@@ -355,8 +363,10 @@ where
     /// to Route or Application level middleware, in that Scope-level middleware
     /// can not modify WebResponse.
     ///
+    /// Closure receives request and `next` handle of the inner service,
+    /// handle could be moved into async block.
+    ///
     /// ```rust
-    /// use ntex::service::Service;
     /// use ntex::web;
     /// use ntex::http::header::{CONTENT_TYPE, HeaderValue};
     ///
@@ -367,15 +377,12 @@ where
     /// fn main() {
     ///     let app = web::App::new().service(
     ///         web::scope("/app")
-    ///             .wrap_fn(|req, srv| {
-    ///                 let fut = srv.call(req);
-    ///                 async {
-    ///                     let mut res = fut.await?;
-    ///                     res.headers_mut().insert(
-    ///                        CONTENT_TYPE, HeaderValue::from_static("text/plain"),
-    ///                     );
-    ///                     Ok(res)
-    ///                 }
+    ///             .wrap_fn(|req, next| async move {
+    ///                 let mut res = next.call(req).await?;
+    ///                 res.headers_mut().insert(
+    ///                    CONTENT_TYPE, HeaderValue::from_static("text/plain"),
+    ///                 );
+    ///                 Ok(res)
     ///             })
     ///             .route("/index.html", web::get().to(index)));
     /// }
@@ -394,11 +401,13 @@ where
         >,
     >
     where
-        F: Fn(WebRequest<Err>, &T::Service) -> R + Clone,
+        F: Fn(WebRequest<Err>, Next<Err>) -> R,
         R: Future<Output = Result<WebResponse, Err::Container>>,
+        T::Service: 'static,
+        <T::Service as Service>::Future: 'static,
     {
         Scope {
-            endpoint: apply_fn_factory(self.endpoint, mw),
+            endpoint: apply(from_fn(mw), self.endpoint),
             rdef: self.rdef,
             data: self.data,
             guards: self.guards,
@@ -428,7 +437,11 @@ where
         }
 
         // register nested services
+        self.rdef = config.expand_pattern(&self.rdef);
         let mut cfg = config.clone_config();
+        if let Some(ref ext) = self.data {
+            cfg.set_data_container(ext);
+        }
         self.services
             .into_iter()
             .for_each(|mut srv| srv.register(&mut cfg));
@@ -588,7 +601,8 @@ impl<Err: ErrorRenderer> Future for ScopeFactoryResponse<Err> {
                 .fold(Router::build(), |mut router, item| {
                     match item {
                         CreateScopeServiceItem::Service(path, guards, service) => {
-                            router.rdef(path, service).2 = guards;
+                            router.rdef(path.clone(), (service, Rc::new(path))).2 =
+                                guards;
                         }
                         CreateScopeServiceItem::Future(_, _, _) => unreachable!(),
                     }
@@ -608,7 +622,7 @@ impl<Err: ErrorRenderer> Future for ScopeFactoryResponse<Err> {
 
 pub struct ScopeService<Err: ErrorRenderer> {
     data: Option<Rc<Extensions>>,
-    router: Router<HttpService<Err>, Vec<Box<dyn Guard>>>,
+    router: Router<(HttpService<Err>, Rc<ResourceDef>), Vec<Box<dyn Guard>>>,
     default: Option<HttpService<Err>>,
     _ready: Option<(WebRequest<Err>, ResourceInfo)>,
 }
@@ -636,16 +650,20 @@ impl<Err: ErrorRenderer> Service for ScopeService<Err> {
             true
         });
 
-        if let Some((srv, _info)) = res {
+        if let Some(((srv, rdef), _info)) = res {
+            req.add_resource(rdef.clone());
             if let Some(ref data) = self.data {
                 req.set_data_container(data.clone());
             }
             Either::Left(srv.call(req))
         } else if let Some(ref default) = self.default {
+            req.set_unmatched();
             Either::Left(default.call(req))
         } else {
+            req.set_unmatched();
             let req = req.into_parts().0;
-            Either::Right(ok(WebResponse::new(req, Response::NotFound().finish())))
+            let res = default_response::<Err>(&req, StatusCode::NOT_FOUND);
+            Either::Right(ok(WebResponse::new(req, res)))
         }
     }
 }
@@ -1108,6 +1126,56 @@ mod tests {
         );
     }
 
+    #[ntex_rt::test]
+    async fn test_match_info() {
+        let srv = init_service(
+            App::new().service(
+                web::scope("/app").service(
+                    web::scope("/{project}/").service(
+                        web::resource("/users/{id}")
+                            .name("user")
+                            .to(|| async { HttpResponse::Ok() }),
+                    ),
+                ),
+            ),
+        )
+        .await;
+
+        let req = TestRequest::with_uri("/app/ntex/users/10").to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(resp.match_pattern().unwrap(), "/app/{project}/users/{id}");
+        assert_eq!(resp.match_name(), Some("user"));
+        assert_eq!(resp.match_info().get("project"), Some("ntex"));
+        assert_eq!(resp.match_info().get("id"), Some("10"));
+
+        let req = TestRequest::with_uri("/app/ntex/unknown").to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+        assert_eq!(resp.match_pattern(), None);
+        assert_eq!(resp.match_name(), None);
+
+        // scope default service
+        let srv = init_service(
+            App::new().service(
+                web::scope("/app")
+                    .service(web::resource("/index").to(|| async { HttpResponse::Ok() }))
+                    .default_service(|r: WebRequest<DefaultError>| {
+                        ok(r.into_response(HttpResponse::NotFound()))
+                    }),
+            ),
+        )
+        .await;
+        let req = TestRequest::with_uri("/app/unknown").to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+        assert_eq!(resp.match_pattern(), None);
+
+        let req = TestRequest::with_uri("/app/index").to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.match_pattern().unwrap(), "/app/index");
+    }
+
     #[ntex_rt::test]
     async fn test_override_data() {
         let srv = init_service(App::new().data(1usize).service(
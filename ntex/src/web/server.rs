@@ -1,7 +1,11 @@
+use std::future::Future;
 use std::marker::PhantomData;
+use std::path::PathBuf;
 use std::sync::{Arc, Mutex};
 use std::{fmt, io, net};
 
+use serde::Deserialize;
+
 #[cfg(feature = "openssl")]
 use crate::server::openssl::{AlpnError, SslAcceptor, SslAcceptorBuilder};
 #[cfg(feature = "rustls")]
@@ -16,10 +20,13 @@ use crate::http::{
 };
 #[cfg(unix)]
 use crate::pipeline_factory;
-use crate::server::{Server, ServerBuilder};
+use crate::server::{
+    balance::Balance, RestartPolicy, Server, ServerBuilder, SocketOpts, WarmupPolicy,
+};
 use crate::{map_config, IntoServiceFactory, Service, ServiceFactory};
 
 use super::config::AppConfig;
+use super::rmap::{ResourceMap, RouteInfo};
 
 struct Config {
     host: Option<String>,
@@ -29,6 +36,178 @@ struct Config {
     handshake_timeout: u64,
 }
 
+/// Http server configuration.
+///
+/// Configuration could be deserialized from deployment configuration file
+/// and applied with `HttpServer::from_config()` method. Timeouts are in
+/// milliseconds, unset values keep `HttpServer` defaults.
+///
+/// ```rust
+/// use ntex::web::HttpServerConfig;
+///
+/// let config: HttpServerConfig = serde_json::from_str(r#"{
+///     "bind": ["127.0.0.1:8080"],
+///     "workers": 4,
+///     "keep_alive": 30,
+///     "client_timeout": 3000
+/// }"#).unwrap();
+/// assert_eq!(config.workers, Some(4));
+/// ```
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct HttpServerConfig {
+    /// Addresses to bind
+    pub bind: Vec<String>,
+    /// Tls listeners
+    pub tls: Option<HttpServerTlsConfig>,
+    /// Number of workers
+    pub workers: Option<usize>,
+    /// Maximum number of pending connections
+    pub backlog: Option<i32>,
+    /// Maximum per-worker number of concurrent connections
+    pub maxconn: Option<usize>,
+    /// Maximum per-worker number of concurrent connection establish processes
+    pub maxconnrate: Option<usize>,
+    /// Keep-alive in seconds, `0` disables keep-alive
+    pub keep_alive: Option<usize>,
+    /// Client request timeout
+    pub client_timeout: Option<u64>,
+    /// Connection disconnect timeout
+    pub disconnect_timeout: Option<u64>,
+    /// Ssl handshake timeout
+    pub ssl_handshake_timeout: Option<u64>,
+    /// Graceful shutdown timeout in seconds
+    pub shutdown_timeout: Option<u64>,
+    /// Server host name
+    pub hostname: Option<String>,
+}
+
+/// Tls listeners configuration.
+///
+/// Certificate chain and private key are loaded from PEM files. Requires
+/// `openssl` or `rustls` feature, if both features are enabled openssl
+/// is used.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct HttpServerTlsConfig {
+    /// Addresses to bind
+    pub bind: Vec<String>,
+    /// Path to certificate chain file
+    pub cert: PathBuf,
+    /// Path to private key file
+    pub key: PathBuf,
+}
+
+/// Listener information
+#[derive(Clone, Debug)]
+pub struct ListenerInfo {
+    addr: String,
+    secure: bool,
+    protocols: Vec<&'static str>,
+}
+
+impl ListenerInfo {
+    fn new(addr: String, secure: bool, protocols: &[&'static str]) -> Self {
+        ListenerInfo {
+            addr,
+            secure,
+            protocols: protocols.to_vec(),
+        }
+    }
+
+    /// Bound address, socket address or unix socket path
+    pub fn addr(&self) -> &str {
+        &self.addr
+    }
+
+    /// Returns true if listener accepts tls connections
+    pub fn secure(&self) -> bool {
+        self.secure
+    }
+
+    /// Http protocols supported by listener
+    pub fn protocols(&self) -> &[&'static str] {
+        &self.protocols
+    }
+}
+
+/// Server startup report.
+///
+/// Report is passed to a callback registered with
+/// `HttpServer::startup_report()` method.
+#[derive(Clone, Debug)]
+pub struct StartupReport {
+    listeners: Vec<ListenerInfo>,
+    workers: usize,
+    routes: Vec<RouteInfo>,
+}
+
+impl StartupReport {
+    /// Bound listeners
+    pub fn listeners(&self) -> &[ListenerInfo] {
+        &self.listeners
+    }
+
+    /// Number of workers
+    pub fn workers(&self) -> usize {
+        self.workers
+    }
+
+    /// Application route table.
+    ///
+    /// Route table is available only for `App` based services.
+    pub fn routes(&self) -> &[RouteInfo] {
+        &self.routes
+    }
+}
+
+impl fmt::Display for StartupReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Starting {} workers", self.workers)?;
+        for lst in &self.listeners {
+            writeln!(
+                f,
+                "Listening on {}://{} ({})",
+                if lst.secure { "https" } else { "http" },
+                lst.addr,
+                lst.protocols.join(", ")
+            )?;
+        }
+        for route in &self.routes {
+            if route.name().is_empty() {
+                writeln!(f, "Route {}", route.pattern())?;
+            } else {
+                writeln!(f, "Route {} ({})", route.pattern(), route.name())?;
+            }
+        }
+        Ok(())
+    }
+}
+
+type ReportCallback = Box<dyn FnOnce(&StartupReport) + Send>;
+
+/// Startup report shared between workers, first constructed application
+/// completes report with its route table and calls report callback.
+pub(crate) struct StartupReporter(Mutex<Option<(StartupReport, ReportCallback)>>);
+
+impl StartupReporter {
+    fn new() -> Self {
+        StartupReporter(Mutex::new(None))
+    }
+
+    fn set(&self, report: StartupReport, f: ReportCallback) {
+        *self.0.lock().unwrap() = Some((report, f));
+    }
+
+    pub(crate) fn report(&self, rmap: &ResourceMap) {
+        let item = self.0.lock().unwrap().take();
+        if let Some((mut report, f)) = item {
+            report.routes = rmap.routes();
+            f(&report);
+        }
+    }
+}
+
 /// An HTTP Server.
 ///
 /// Create new http server with application factory.
@@ -60,7 +239,11 @@ where
     pub(super) factory: F,
     config: Arc<Mutex<Config>>,
     backlog: i32,
+    opts: SocketOpts,
     builder: ServerBuilder,
+    listeners: Vec<ListenerInfo>,
+    reporter: Arc<StartupReporter>,
+    report: Option<ReportCallback>,
     _t: PhantomData<(S, B)>,
 }
 
@@ -87,15 +270,143 @@ where
                 handshake_timeout: 5000,
             })),
             backlog: 1024,
+            opts: SocketOpts::default(),
             builder: ServerBuilder::default(),
+            listeners: Vec::new(),
+            reporter: Arc::new(StartupReporter::new()),
+            report: None,
             _t: PhantomData,
         }
     }
 
+    /// Create new http server with application factory and configuration.
+    ///
+    /// Applies configuration and binds all configured addresses.
+    ///
+    /// ```rust,no_run
+    /// use ntex::web::{self, App, HttpResponse, HttpServer, HttpServerConfig};
+    ///
+    /// #[ntex::main]
+    /// async fn main() -> std::io::Result<()> {
+    ///     let config: HttpServerConfig = serde_json::from_str(
+    ///         &std::fs::read_to_string("server.json")?,
+    ///     )?;
+    ///
+    ///     HttpServer::from_config(
+    ///         || App::new().service(web::resource("/").to(|| async { HttpResponse::Ok() })),
+    ///         &config,
+    ///     )?
+    ///     .run()
+    ///     .await
+    /// }
+    /// ```
+    pub fn from_config(factory: F, config: &HttpServerConfig) -> io::Result<Self> {
+        let mut srv = HttpServer::new(factory);
+
+        if let Some(num) = config.workers {
+            srv = srv.workers(num);
+        }
+        if let Some(backlog) = config.backlog {
+            srv = srv.backlog(backlog);
+        }
+        if let Some(num) = config.maxconn {
+            srv = srv.maxconn(num);
+        }
+        if let Some(num) = config.maxconnrate {
+            srv = srv.maxconnrate(num);
+        }
+        if let Some(val) = config.keep_alive {
+            srv = if val == 0 {
+                srv.keep_alive(KeepAlive::Disabled)
+            } else {
+                srv.keep_alive(val)
+            };
+        }
+        if let Some(val) = config.client_timeout {
+            srv = srv.client_timeout(val);
+        }
+        if let Some(val) = config.disconnect_timeout {
+            srv = srv.disconnect_timeout(val);
+        }
+        if let Some(val) = config.ssl_handshake_timeout {
+            srv = srv.ssl_handshake_timeout(val);
+        }
+        if let Some(sec) = config.shutdown_timeout {
+            srv = srv.shutdown_timeout(sec);
+        }
+        if let Some(ref host) = config.hostname {
+            srv = srv.server_hostname(host);
+        }
+
+        for addr in &config.bind {
+            srv = srv.bind(addr.as_str())?;
+        }
+        if let Some(ref tls) = config.tls {
+            srv = srv.bind_tls_config(tls)?;
+        }
+        Ok(srv)
+    }
+
+    #[cfg(feature = "openssl")]
+    fn bind_tls_config(mut self, tls: &HttpServerTlsConfig) -> io::Result<Self> {
+        use open_ssl::ssl::{SslFiletype, SslMethod};
+
+        for addr in &tls.bind {
+            let mut builder = SslAcceptor::mozilla_intermediate(SslMethod::tls())?;
+            builder.set_private_key_file(&tls.key, SslFiletype::PEM)?;
+            builder.set_certificate_chain_file(&tls.cert)?;
+            self = self.bind_openssl(addr.as_str(), builder)?;
+        }
+        Ok(self)
+    }
+
+    #[cfg(all(feature = "rustls", not(feature = "openssl")))]
+    fn bind_tls_config(mut self, tls: &HttpServerTlsConfig) -> io::Result<Self> {
+        use rust_tls::internal::pemfile::{certs, pkcs8_private_keys, rsa_private_keys};
+        use rust_tls::NoClientAuth;
+        use std::{fs::File, io::BufReader};
+
+        let invalid = |msg: &str| io::Error::new(io::ErrorKind::InvalidData, msg);
+
+        let certs = certs(&mut BufReader::new(File::open(&tls.cert)?))
+            .map_err(|_| invalid("Can not parse certificate chain"))?;
+        let mut keys = pkcs8_private_keys(&mut BufReader::new(File::open(&tls.key)?))
+            .map_err(|_| invalid("Can not parse private key"))?;
+        if keys.is_empty() {
+            keys = rsa_private_keys(&mut BufReader::new(File::open(&tls.key)?))
+                .map_err(|_| invalid("Can not parse private key"))?;
+        }
+        if keys.is_empty() {
+            return Err(invalid("Private key is not found"));
+        }
+
+        let mut config = RustlsServerConfig::new(NoClientAuth::new());
+        config
+            .set_single_cert(certs, keys.remove(0))
+            .map_err(|e| invalid(&e.to_string()))?;
+
+        for addr in &tls.bind {
+            self = self.bind_rustls(addr.as_str(), config.clone())?;
+        }
+        Ok(self)
+    }
+
+    #[cfg(not(any(feature = "openssl", feature = "rustls")))]
+    fn bind_tls_config(self, tls: &HttpServerTlsConfig) -> io::Result<Self> {
+        if tls.bind.is_empty() {
+            Ok(self)
+        } else {
+            Err(io::Error::new(
+                io::ErrorKind::Other,
+                "Tls support requires openssl or rustls feature",
+            ))
+        }
+    }
+
     /// Set number of workers to start.
     ///
     /// By default http server uses number of available logical cpu as threads
-    /// count.
+    /// count, cpu quota of linux cgroup is taken into account.
     pub fn workers(mut self, num: usize) -> Self {
         self.builder = self.builder.workers(num);
         self
@@ -117,6 +428,57 @@ where
         self
     }
 
+    /// Accept connections in workers with per-worker `SO_REUSEPORT`
+    /// listeners instead of accept loop.
+    ///
+    /// Listeners passed to `listen()` method must have `SO_REUSEPORT` option
+    /// set. Only supported on unix platforms.
+    ///
+    /// This method should be called before `bind()` method call.
+    pub fn reuse_port(mut self, enabled: bool) -> Self {
+        self.opts.reuse_port = enabled;
+        self.builder = self.builder.reuse_port(enabled);
+        self
+    }
+
+    #[cfg(feature = "tcp-fastopen")]
+    /// Enable TCP fast open for listeners.
+    ///
+    /// Only supported on linux. This method should be called before
+    /// `bind()` method call.
+    pub fn tcp_fastopen(mut self, qlen: u32) -> Self {
+        self.opts.fastopen = Some(qlen);
+        self.builder = self.builder.tcp_fastopen(qlen);
+        self
+    }
+
+    #[cfg(feature = "mptcp")]
+    /// Use multipath TCP for listeners.
+    ///
+    /// Only supported on linux. This method should be called before
+    /// `bind()` method call.
+    pub fn mptcp(mut self, enabled: bool) -> Self {
+        self.opts.mptcp = enabled;
+        self.builder = self.builder.mptcp(enabled);
+        self
+    }
+
+    /// Pin worker threads to cpu cores.
+    ///
+    /// Only supported on linux, on other platforms this option is ignored.
+    pub fn cpu_affinity(mut self, enabled: bool) -> Self {
+        self.builder = self.builder.cpu_affinity(enabled);
+        self
+    }
+
+    /// Set strategy for distributing accepted connections between workers.
+    ///
+    /// By default workers are selected in round-robin order.
+    pub fn balance<T: Balance + 'static>(mut self, balance: T) -> Self {
+        self.builder = self.builder.balance(balance);
+        self
+    }
+
     /// Sets the maximum per-worker number of concurrent connections.
     ///
     /// All socket listeners will stop accepting connections when this limit is reached
@@ -221,6 +583,87 @@ where
         self
     }
 
+    /// Set worker warm-up function.
+    ///
+    /// Function get called once in each worker thread after application
+    /// get constructed. Server starts accepting connections only after
+    /// all workers complete warm-up. Restarted workers run warm-up as well.
+    pub fn warmup<W, R, E>(mut self, f: W) -> Self
+    where
+        W: Fn() -> R + Send + Clone + 'static,
+        R: Future<Output = Result<(), E>> + 'static,
+        E: fmt::Debug + 'static,
+    {
+        self.builder = self.builder.warmup(f);
+        self
+    }
+
+    /// Timeout for workers warm-up in seconds.
+    ///
+    /// By default warm-up timeout sets to 30 seconds.
+    pub fn warmup_timeout(mut self, sec: u64) -> Self {
+        self.builder = self.builder.warmup_timeout(sec);
+        self
+    }
+
+    /// Set server behavior in case of worker warm-up failure.
+    ///
+    /// By default server stops.
+    pub fn warmup_policy(mut self, policy: WarmupPolicy) -> Self {
+        self.builder = self.builder.warmup_policy(policy);
+        self
+    }
+
+    /// Set worker restart policy.
+    ///
+    /// By default workers are restarted immediately without limit.
+    pub fn restart_policy(mut self, policy: RestartPolicy) -> Self {
+        self.builder = self.builder.restart_policy(policy);
+        self
+    }
+
+    /// Set number of worker processes.
+    ///
+    /// Enables pre-fork mode, each worker process starts `workers` worker
+    /// threads. Worker process re-executes current executable, check
+    /// `ServerBuilder::processes()` for details. By default pre-fork mode
+    /// is disabled.
+    #[cfg(unix)]
+    pub fn processes(mut self, num: usize) -> Self {
+        self.builder = self.builder.processes(num);
+        self
+    }
+
+    /// Set startup report callback.
+    ///
+    /// Callback get called once, after first worker constructs application.
+    /// Report contains bound addresses, supported protocols, number of
+    /// workers and application route table. Route table is available only
+    /// for `App` based services, for other services callback does not
+    /// get called.
+    ///
+    /// ```rust,no_run
+    /// use ntex::web::{self, App, HttpResponse, HttpServer};
+    ///
+    /// #[ntex::main]
+    /// async fn main() -> std::io::Result<()> {
+    ///     HttpServer::new(
+    ///         || App::new().service(web::resource("/").to(|| async { HttpResponse::Ok() }))
+    ///     )
+    ///         .startup_report(|report| log::info!("{}", report))
+    ///         .bind("127.0.0.1:0")?
+    ///         .run()
+    ///         .await
+    /// }
+    /// ```
+    pub fn startup_report<R>(mut self, f: R) -> Self
+    where
+        R: FnOnce(&StartupReport) + Send + 'static,
+    {
+        self.report = Some(Box::new(f));
+        self
+    }
+
     /// Use listener for accepting incoming connection requests
     ///
     /// HttpServer does not change any configuration for TcpListener,
@@ -228,7 +671,10 @@ where
     pub fn listen(mut self, lst: net::TcpListener) -> io::Result<Self> {
         let cfg = self.config.clone();
         let factory = self.factory.clone();
+        let reporter = self.reporter.clone();
         let addr = lst.local_addr().unwrap();
+        self.listeners
+            .push(ListenerInfo::new(addr.to_string(), false, &["http/1.1"]));
 
         self.builder = self.builder.listen(
             format!("ntex-web-service-{}", addr),
@@ -239,7 +685,8 @@ where
                     false,
                     addr,
                     c.host.clone().unwrap_or_else(|| format!("{}", addr)),
-                );
+                )
+                .with_reporter(reporter.clone());
 
                 HttpService::build()
                     .keep_alive(c.keep_alive)
@@ -272,7 +719,13 @@ where
     ) -> io::Result<Self> {
         let factory = self.factory.clone();
         let cfg = self.config.clone();
+        let reporter = self.reporter.clone();
         let addr = lst.local_addr().unwrap();
+        self.listeners.push(ListenerInfo::new(
+            addr.to_string(),
+            true,
+            &["h2", "http/1.1"],
+        ));
 
         self.builder = self.builder.listen(
             format!("ntex-web-service-{}", addr),
@@ -283,7 +736,8 @@ where
                     true,
                     addr,
                     c.host.clone().unwrap_or_else(|| format!("{}", addr)),
-                );
+                )
+                .with_reporter(reporter.clone());
                 HttpService::build()
                     .keep_alive(c.keep_alive)
                     .client_timeout(c.client_timeout)
@@ -316,7 +770,13 @@ where
     ) -> io::Result<Self> {
         let factory = self.factory.clone();
         let cfg = self.config.clone();
+        let reporter = self.reporter.clone();
         let addr = lst.local_addr().unwrap();
+        self.listeners.push(ListenerInfo::new(
+            addr.to_string(),
+            true,
+            &["h2", "http/1.1"],
+        ));
 
         self.builder = self.builder.listen(
             format!("ntex-web-rustls-service-{}", addr),
@@ -327,7 +787,8 @@ where
                     true,
                     addr,
                     c.host.clone().unwrap_or_else(|| format!("{}", addr)),
-                );
+                )
+                .with_reporter(reporter.clone());
                 HttpService::build()
                     .keep_alive(c.keep_alive)
                     .client_timeout(c.client_timeout)
@@ -344,7 +805,7 @@ where
     ///
     /// To bind multiple addresses this method can be called multiple times.
     pub fn bind<A: net::ToSocketAddrs>(mut self, addr: A) -> io::Result<Self> {
-        let sockets = self.bind2(addr)?;
+        let sockets = crate::server::bind_addr(addr, self.backlog, self.opts)?;
 
         for lst in sockets {
             self = self.listen(lst)?;
@@ -353,37 +814,6 @@ where
         Ok(self)
     }
 
-    fn bind2<A: net::ToSocketAddrs>(
-        &self,
-        addr: A,
-    ) -> io::Result<Vec<net::TcpListener>> {
-        let mut err = None;
-        let mut succ = false;
-        let mut sockets = Vec::new();
-        for addr in addr.to_socket_addrs()? {
-            match crate::server::create_tcp_listener(addr, self.backlog) {
-                Ok(lst) => {
-                    succ = true;
-                    sockets.push(lst);
-                }
-                Err(e) => err = Some(e),
-            }
-        }
-
-        if !succ {
-            if let Some(e) = err.take() {
-                Err(e)
-            } else {
-                Err(io::Error::new(
-                    io::ErrorKind::Other,
-                    "Can not bind to address.",
-                ))
-            }
-        } else {
-            Ok(sockets)
-        }
-    }
-
     #[cfg(feature = "openssl")]
     /// Start listening for incoming tls connections.
     ///
@@ -396,7 +826,7 @@ where
     where
         A: net::ToSocketAddrs,
     {
-        let sockets = self.bind2(addr)?;
+        let sockets = crate::server::bind_addr(addr, self.backlog, self.opts)?;
         let acceptor = openssl_acceptor(builder)?;
 
         for lst in sockets {
@@ -415,7 +845,7 @@ where
         addr: A,
         config: RustlsServerConfig,
     ) -> io::Result<Self> {
-        let sockets = self.bind2(addr)?;
+        let sockets = crate::server::bind_addr(addr, self.backlog, self.opts)?;
         for lst in sockets {
             self = self.listen_rustls_inner(lst, config.clone())?;
         }
@@ -439,7 +869,16 @@ where
             8080,
         );
 
-        let addr = format!("ntex-web-service-{:?}", lst.local_addr()?);
+        let reporter = self.reporter.clone();
+        let local_addr = lst.local_addr()?;
+        if let Some(path) = local_addr.as_pathname() {
+            self.listeners.push(ListenerInfo::new(
+                path.display().to_string(),
+                false,
+                &["http/1.1"],
+            ));
+        }
+        let addr = format!("ntex-web-service-{:?}", local_addr);
 
         self.builder = self.builder.listen_uds(addr, lst, move || {
             let c = cfg.lock().unwrap();
@@ -447,7 +886,8 @@ where
                 false,
                 socket_addr,
                 c.host.clone().unwrap_or_else(|| format!("{}", socket_addr)),
-            );
+            )
+            .with_reporter(reporter.clone());
             pipeline_factory(|io: UnixStream| ok((io, Protocol::Http1, None))).and_then(
                 HttpService::build()
                     .keep_alive(c.keep_alive)
@@ -475,6 +915,13 @@ where
             8080,
         );
 
+        let reporter = self.reporter.clone();
+        self.listeners.push(ListenerInfo::new(
+            addr.as_ref().display().to_string(),
+            false,
+            &["http/1.1"],
+        ));
+
         self.builder = self.builder.bind_uds(
             format!("ntex-web-service-{:?}", addr.as_ref()),
             addr,
@@ -484,7 +931,8 @@ where
                     false,
                     socket_addr,
                     c.host.clone().unwrap_or_else(|| format!("{}", socket_addr)),
-                );
+                )
+                .with_reporter(reporter.clone());
                 pipeline_factory(|io: UnixStream| ok((io, Protocol::Http1, None)))
                     .and_then(
                         HttpService::build()
@@ -532,6 +980,14 @@ where
     /// }
     /// ```
     pub fn run(self) -> Server {
+        if let Some(f) = self.report {
+            let report = StartupReport {
+                listeners: self.listeners,
+                workers: self.builder.workers_count(),
+                routes: Vec::new(),
+            };
+            self.reporter.set(report, f);
+        }
         self.builder.start()
     }
 }
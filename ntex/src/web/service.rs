@@ -1,3 +1,6 @@
+use std::any::TypeId;
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
 use std::rc::Rc;
 
 use crate::http::Extensions;
@@ -11,7 +14,7 @@ use super::guard::Guard;
 use super::request::WebRequest;
 use super::response::WebResponse;
 use super::rmap::ResourceMap;
-use super::types::data::DataFactory;
+use super::types::data::{DataFactory, DataRequirement};
 
 pub trait WebServiceFactory<Err: ErrorRenderer> {
     fn register(self, config: &mut WebServiceConfig<Err>);
@@ -49,6 +52,30 @@ type Guards = Vec<Box<dyn Guard>>;
 type HttpServiceFactory<Err: ErrorRenderer> =
     boxed::BoxServiceFactory<(), WebRequest<Err>, WebResponse, Err::Container, ()>;
 
+/// Startup validation state of application data
+pub(crate) struct DataValidation {
+    /// types available in application level data container
+    app: HashSet<TypeId>,
+    /// types that get copied to resource and scope level containers
+    data: HashSet<TypeId>,
+    errors: RefCell<Vec<String>>,
+}
+
+impl DataValidation {
+    pub(crate) fn new(app: HashSet<TypeId>, data: HashSet<TypeId>) -> Self {
+        DataValidation {
+            app,
+            data,
+            errors: RefCell::new(Vec::new()),
+        }
+    }
+
+    /// Validation errors
+    pub(crate) fn errors(&self) -> Vec<String> {
+        self.errors.borrow().clone()
+    }
+}
+
 /// Application service configuration
 pub struct WebServiceConfig<Err: ErrorRenderer> {
     config: AppConfig,
@@ -61,6 +88,9 @@ pub struct WebServiceConfig<Err: ErrorRenderer> {
         Option<Rc<ResourceMap>>,
     )>,
     service_data: Rc<Vec<Box<dyn DataFactory>>>,
+    patterns: Rc<HashMap<String, String>>,
+    validation: Option<Rc<DataValidation>>,
+    container: Option<Rc<HashSet<TypeId>>>,
 }
 
 impl<Err: ErrorRenderer> WebServiceConfig<Err> {
@@ -69,11 +99,16 @@ impl<Err: ErrorRenderer> WebServiceConfig<Err> {
         config: AppConfig,
         default: Rc<HttpServiceFactory<Err>>,
         service_data: Rc<Vec<Box<dyn DataFactory>>>,
+        patterns: Rc<HashMap<String, String>>,
+        validation: Option<Rc<DataValidation>>,
     ) -> Self {
         WebServiceConfig {
             config,
             default,
             service_data,
+            patterns,
+            validation,
+            container: None,
             root: true,
             services: Vec::new(),
         }
@@ -105,7 +140,102 @@ impl<Err: ErrorRenderer> WebServiceConfig<Err> {
             services: Vec::new(),
             root: false,
             service_data: self.service_data.clone(),
+            patterns: self.patterns.clone(),
+            validation: self.validation.clone(),
+            container: self.container.clone(),
+        }
+    }
+
+    /// Set data container for nested services
+    pub(crate) fn set_data_container(&mut self, ext: &Extensions) {
+        if let Some(ref validation) = self.validation {
+            let mut types = validation.data.clone();
+            types.extend(ext.type_ids());
+            self.container = Some(Rc::new(types));
+        }
+    }
+
+    /// Check if data required by resource handlers is available
+    pub(crate) fn validate_data(
+        &self,
+        rdef: &ResourceDef,
+        ext: Option<&Extensions>,
+        required: &[DataRequirement],
+    ) {
+        let validation = if let Some(ref validation) = self.validation {
+            validation
+        } else {
+            return;
+        };
+
+        for item in required {
+            let id = item.type_id();
+            let found = match (ext, &self.container) {
+                (Some(ext), _) => ext.contains_type(id) || validation.data.contains(&id),
+                (None, Some(container)) => container.contains(&id),
+                (None, None) => validation.app.contains(&id),
+            };
+            if !found {
+                let msg = format!(
+                    "{} is not configured for resource {:?}",
+                    item.type_name(),
+                    rdef.pattern()
+                );
+                let mut errors = validation.errors.borrow_mut();
+                if !errors.contains(&msg) {
+                    errors.push(msg);
+                }
+            }
+        }
+    }
+
+    /// Replace references to named segment patterns with regular expressions
+    ///
+    /// Dynamic segment `{name:pattern}` gets expanded if `pattern` is
+    /// registered with `App::segment_pattern()`.
+    pub(crate) fn expand_pattern(&self, path: &str) -> String {
+        if self.patterns.is_empty() {
+            return path.to_string();
+        }
+
+        let mut result = String::with_capacity(path.len());
+        let mut rest = path;
+        while let Some(start) = rest.find('{') {
+            result.push_str(&rest[..start]);
+            rest = &rest[start..];
+
+            // find closing brace, regex could contain braces as well
+            let mut depth = 0;
+            let mut end = rest.len();
+            for (idx, ch) in rest.char_indices() {
+                match ch {
+                    '{' => depth += 1,
+                    '}' => {
+                        depth -= 1;
+                        if depth == 0 {
+                            end = idx + 1;
+                            break;
+                        }
+                    }
+                    _ => (),
+                }
+            }
+
+            let segment = &rest[..end];
+            let expanded = segment[1..segment.len() - 1].find(':').and_then(|idx| {
+                let name = &segment[1..idx + 1];
+                self.patterns
+                    .get(&segment[idx + 2..segment.len() - 1])
+                    .map(|re| format!("{{{}:{}}}", name, re))
+            });
+            match expanded {
+                Some(expanded) => result.push_str(&expanded),
+                None => result.push_str(segment),
+            }
+            rest = &rest[end..];
         }
+        result.push_str(rest);
+        result
     }
 
     /// Service configuration
@@ -261,10 +391,11 @@ where
             Some(std::mem::replace(&mut self.guards, Vec::new()))
         };
 
+        let patterns = self.rdef.iter().map(|p| config.expand_pattern(p)).collect();
         let mut rdef = if config.is_root() || !self.rdef.is_empty() {
-            ResourceDef::new(insert_slesh(self.rdef))
+            ResourceDef::new(insert_slesh(patterns))
         } else {
-            ResourceDef::new(self.rdef)
+            ResourceDef::new(patterns)
         };
         if let Some(ref name) = self.name {
             *rdef.name_mut() = name.clone();
@@ -0,0 +1,438 @@
+//! Sitemap and robots.txt services
+//!
+//! ```rust
+//! use ntex::web::{self, App};
+//! use ntex::web::sitemap::{ChangeFreq, Robots, Sitemap, SitemapEntry};
+//!
+//! fn main() {
+//!     let app = App::new()
+//!         .service(web::resource("/").name("index").to(|| async { "index" }))
+//!         .service(web::resource("/about").name("about").to(|| async { "about" }))
+//!         .service(
+//!             Sitemap::new("/sitemap.xml")
+//!                 .all_routes()
+//!                 .entries(|_| {
+//!                     futures::stream::iter((1..3).map(|id| {
+//!                         SitemapEntry::new(format!("/posts/{}", id))
+//!                             .changefreq(ChangeFreq::Weekly)
+//!                     }))
+//!                 }),
+//!         )
+//!         .service(
+//!             Robots::new()
+//!                 .user_agent("*")
+//!                 .disallow("/admin")
+//!                 .sitemap("/sitemap.xml"),
+//!         );
+//! }
+//! ```
+use std::convert::Infallible;
+use std::fmt::Write;
+use std::rc::Rc;
+
+use bytes::Bytes;
+use futures::future::ready;
+use futures::stream::{self, LocalBoxStream, Stream, StreamExt};
+
+use crate::http::{Response, StatusCode};
+
+use super::dev::{WebServiceConfig, WebServiceFactory};
+use super::error::ErrorRenderer;
+use super::httprequest::HttpRequest;
+use super::util::{get, resource};
+
+/// How frequently the page is likely to change
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ChangeFreq {
+    Always,
+    Hourly,
+    Daily,
+    Weekly,
+    Monthly,
+    Yearly,
+    Never,
+}
+
+impl ChangeFreq {
+    fn as_str(self) -> &'static str {
+        match self {
+            ChangeFreq::Always => "always",
+            ChangeFreq::Hourly => "hourly",
+            ChangeFreq::Daily => "daily",
+            ChangeFreq::Weekly => "weekly",
+            ChangeFreq::Monthly => "monthly",
+            ChangeFreq::Yearly => "yearly",
+            ChangeFreq::Never => "never",
+        }
+    }
+}
+
+/// Sitemap url entry
+#[derive(Debug, Clone)]
+pub struct SitemapEntry {
+    loc: String,
+    lastmod: Option<String>,
+    changefreq: Option<ChangeFreq>,
+    priority: Option<f32>,
+}
+
+impl SitemapEntry {
+    /// Create entry for location.
+    ///
+    /// Location that starts with `/` is resolved against request's
+    /// scheme and host.
+    pub fn new<T: Into<String>>(loc: T) -> Self {
+        SitemapEntry {
+            loc: loc.into(),
+            lastmod: None,
+            changefreq: None,
+            priority: None,
+        }
+    }
+
+    /// Set last modification date, in W3C Datetime format
+    pub fn lastmod<T: Into<String>>(mut self, lastmod: T) -> Self {
+        self.lastmod = Some(lastmod.into());
+        self
+    }
+
+    /// Set change frequency
+    pub fn changefreq(mut self, changefreq: ChangeFreq) -> Self {
+        self.changefreq = Some(changefreq);
+        self
+    }
+
+    /// Set priority of the url, valid values range from 0.0 to 1.0
+    pub fn priority(mut self, priority: f32) -> Self {
+        self.priority = Some(priority.max(0.0).min(1.0));
+        self
+    }
+
+    fn to_xml(&self, base: &str) -> Bytes {
+        let mut buf = String::with_capacity(128);
+        buf.push_str("<url><loc>");
+        if self.loc.starts_with('/') {
+            escape(base, &mut buf);
+        }
+        escape(&self.loc, &mut buf);
+        buf.push_str("</loc>");
+        if let Some(ref lastmod) = self.lastmod {
+            buf.push_str("<lastmod>");
+            escape(lastmod, &mut buf);
+            buf.push_str("</lastmod>");
+        }
+        if let Some(changefreq) = self.changefreq {
+            let _ = write!(buf, "<changefreq>{}</changefreq>", changefreq.as_str());
+        }
+        if let Some(priority) = self.priority {
+            let _ = write!(buf, "<priority>{:.1}</priority>", priority);
+        }
+        buf.push_str("</url>\n");
+        Bytes::from(buf)
+    }
+}
+
+fn escape(s: &str, buf: &mut String) {
+    for ch in s.chars() {
+        match ch {
+            '&' => buf.push_str("&amp;"),
+            '<' => buf.push_str("&lt;"),
+            '>' => buf.push_str("&gt;"),
+            '"' => buf.push_str("&quot;"),
+            '\'' => buf.push_str("&apos;"),
+            _ => buf.push(ch),
+        }
+    }
+}
+
+type EntriesFn = Box<dyn Fn(&HttpRequest) -> LocalBoxStream<'static, SitemapEntry>>;
+
+/// Sitemap service.
+///
+/// Sitemap is generated from named routes and from dynamic entries. Body is
+/// streamed, so dynamic entries do not have to be loaded into memory at once.
+/// Named routes that contain dynamic segments are skipped.
+pub struct Sitemap {
+    path: String,
+    all_routes: bool,
+    routes: Vec<String>,
+    entries: Vec<EntriesFn>,
+}
+
+impl Sitemap {
+    /// Create sitemap service for specified path
+    pub fn new(path: &str) -> Self {
+        Sitemap {
+            path: path.to_string(),
+            all_routes: false,
+            routes: Vec::new(),
+            entries: Vec::new(),
+        }
+    }
+
+    /// Include all named routes
+    pub fn all_routes(mut self) -> Self {
+        self.all_routes = true;
+        self
+    }
+
+    /// Include named route
+    pub fn route(mut self, name: &str) -> Self {
+        self.routes.push(name.to_string());
+        self
+    }
+
+    /// Add dynamic entries.
+    ///
+    /// Function get called for each sitemap request.
+    pub fn entries<F, S>(mut self, f: F) -> Self
+    where
+        F: Fn(&HttpRequest) -> S + 'static,
+        S: Stream<Item = SitemapEntry> + 'static,
+    {
+        self.entries.push(Box::new(move |req| f(req).boxed_local()));
+        self
+    }
+
+    fn render(&self, req: &HttpRequest) -> Response {
+        let base = {
+            let info = req.connection_info();
+            format!("{}://{}", info.scheme(), info.host())
+        };
+
+        let names = if self.all_routes {
+            let mut names = Vec::new();
+            req.resource_map().names(&mut names);
+            names
+        } else {
+            self.routes.clone()
+        };
+        let mut routes = Vec::new();
+        for name in names {
+            match req.url_for_static(&name) {
+                Ok(url) => routes.push(SitemapEntry::new(url.to_string()).to_xml("")),
+                Err(e) => log::trace!("Skip {:?} route for sitemap: {}", name, e),
+            }
+        }
+
+        let dynamic =
+            stream::iter(self.entries.iter().map(|f| f(req)).collect::<Vec<_>>())
+                .flatten()
+                .map(move |entry| entry.to_xml(&base));
+
+        let body = stream::once(ready(Bytes::from_static(
+            b"<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+              <urlset xmlns=\"http://www.sitemaps.org/schemas/sitemap/0.9\">\n",
+        )))
+        .chain(stream::iter(routes))
+        .chain(dynamic)
+        .chain(stream::once(ready(Bytes::from_static(b"</urlset>\n"))))
+        .map(Ok::<_, Infallible>);
+
+        Response::build(StatusCode::OK)
+            .content_type("application/xml")
+            .streaming(body)
+    }
+}
+
+impl<Err: ErrorRenderer> WebServiceFactory<Err> for Sitemap {
+    fn register(self, config: &mut WebServiceConfig<Err>) {
+        let path = self.path.clone();
+        let sitemap = Rc::new(self);
+
+        resource(&path)
+            .route(get().to(move |req: HttpRequest| ready(sitemap.render(&req))))
+            .register(config)
+    }
+}
+
+/// robots.txt service.
+///
+/// Service is mounted at `/robots.txt`. Rules are rendered in the order
+/// they were added, `user_agent()` starts a new group of rules.
+pub struct Robots {
+    lines: Vec<(&'static str, String)>,
+    sitemaps: Vec<String>,
+}
+
+impl Default for Robots {
+    fn default() -> Self {
+        Robots::new()
+    }
+}
+
+impl Robots {
+    /// Create empty robots.txt service
+    pub fn new() -> Self {
+        Robots {
+            lines: Vec::new(),
+            sitemaps: Vec::new(),
+        }
+    }
+
+    /// Start rules group for user agent
+    pub fn user_agent(mut self, agent: &str) -> Self {
+        self.lines.push(("User-agent", agent.to_string()));
+        self
+    }
+
+    /// Allow path
+    pub fn allow(mut self, path: &str) -> Self {
+        self.lines.push(("Allow", path.to_string()));
+        self
+    }
+
+    /// Disallow path
+    pub fn disallow(mut self, path: &str) -> Self {
+        self.lines.push(("Disallow", path.to_string()));
+        self
+    }
+
+    /// Set crawl delay in seconds
+    pub fn crawl_delay(mut self, secs: u32) -> Self {
+        self.lines.push(("Crawl-delay", secs.to_string()));
+        self
+    }
+
+    /// Add sitemap location.
+    ///
+    /// Location that starts with `/` is resolved against request's
+    /// scheme and host.
+    pub fn sitemap(mut self, loc: &str) -> Self {
+        self.sitemaps.push(loc.to_string());
+        self
+    }
+
+    fn render(&self, req: &HttpRequest) -> Response {
+        let mut body = String::new();
+        for (idx, (name, value)) in self.lines.iter().enumerate() {
+            if idx != 0 && *name == "User-agent" && self.lines[idx - 1].0 != "User-agent"
+            {
+                body.push('\n');
+            }
+            let _ = writeln!(body, "{}: {}", name, value);
+        }
+        if !self.sitemaps.is_empty() {
+            if !body.is_empty() {
+                body.push('\n');
+            }
+            let info = req.connection_info();
+            for loc in &self.sitemaps {
+                if loc.starts_with('/') {
+                    let _ = writeln!(
+                        body,
+                        "Sitemap: {}://{}{}",
+                        info.scheme(),
+                        info.host(),
+                        loc
+                    );
+                } else {
+                    let _ = writeln!(body, "Sitemap: {}", loc);
+                }
+            }
+        }
+
+        Response::build(StatusCode::OK)
+            .content_type("text/plain; charset=utf-8")
+            .body(body)
+    }
+}
+
+impl<Err: ErrorRenderer> WebServiceFactory<Err> for Robots {
+    fn register(self, config: &mut WebServiceConfig<Err>) {
+        let robots = Rc::new(self);
+
+        resource("/robots.txt")
+            .route(get().to(move |req: HttpRequest| ready(robots.render(&req))))
+            .register(config)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::web::test::{call_service, init_service, read_body, TestRequest};
+    use crate::web::{self, App};
+
+    #[ntex_rt::test]
+    async fn test_sitemap() {
+        let srv = init_service(
+            App::new()
+                .service(web::resource("/").name("index").to(|| async { "" }))
+                .service(
+                    web::scope("/blog")
+                        .service(web::resource("/").name("blog").to(|| async { "" }))
+                        .service(
+                            web::resource("/{id}").name("post").to(|| async { "" }),
+                        ),
+                )
+                .service(Sitemap::new("/sitemap.xml").all_routes().entries(|_| {
+                    stream::iter(vec![
+                        SitemapEntry::new("/blog/1?a=1&b=2")
+                            .lastmod("2020-04-01")
+                            .changefreq(ChangeFreq::Daily)
+                            .priority(0.8),
+                        SitemapEntry::new("https://example.com/"),
+                    ])
+                }))
+                .service(Sitemap::new("/index.xml").route("index")),
+        )
+        .await;
+
+        let req = TestRequest::with_uri("/sitemap.xml").to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(
+            read_body(resp).await,
+            Bytes::from_static(
+                b"<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+                  <urlset xmlns=\"http://www.sitemaps.org/schemas/sitemap/0.9\">\n\
+                  <url><loc>http://localhost:8080/</loc></url>\n\
+                  <url><loc>http://localhost:8080/blog/</loc></url>\n\
+                  <url><loc>http://localhost:8080/blog/1?a=1&amp;b=2</loc>\
+                  <lastmod>2020-04-01</lastmod><changefreq>daily</changefreq>\
+                  <priority>0.8</priority></url>\n\
+                  <url><loc>https://example.com/</loc></url>\n\
+                  </urlset>\n"
+            )
+        );
+
+        let req = TestRequest::with_uri("/index.xml").to_request();
+        let resp = call_service(&srv, req).await;
+        let body = read_body(resp).await;
+        assert!(std::str::from_utf8(&body)
+            .unwrap()
+            .contains("<url><loc>http://localhost:8080/</loc></url>\n</urlset>"));
+    }
+
+    #[ntex_rt::test]
+    async fn test_robots() {
+        let srv = init_service(
+            App::new().service(
+                Robots::new()
+                    .user_agent("*")
+                    .disallow("/admin")
+                    .user_agent("bot1")
+                    .user_agent("bot2")
+                    .allow("/")
+                    .crawl_delay(10)
+                    .sitemap("/sitemap.xml")
+                    .sitemap("https://example.com/sitemap.xml"),
+            ),
+        )
+        .await;
+
+        let req = TestRequest::with_uri("/robots.txt").to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(
+            read_body(resp).await,
+            Bytes::from_static(
+                b"User-agent: *\nDisallow: /admin\n\n\
+                  User-agent: bot1\nUser-agent: bot2\nAllow: /\nCrawl-delay: 10\n\n\
+                  Sitemap: http://localhost:8080/sitemap.xml\n\
+                  Sitemap: https://example.com/sitemap.xml\n"
+            )
+        );
+    }
+}
@@ -0,0 +1,683 @@
+//! Resumable uploads, implementation of [tus](https://tus.io) protocol.
+//!
+//! Supported protocol extensions are `creation`, `creation-defer-length`,
+//! `expiration` and `termination`.
+//!
+//! ```rust
+//! use ntex::web::{self, App};
+//! use ntex::web::tus::{MemoryStorage, Tus};
+//!
+//! fn main() {
+//!     let storage = MemoryStorage::new();
+//!
+//!     let app = App::new().service(
+//!         Tus::new("/files", storage)
+//!             .max_size(1024 * 1024 * 1024)
+//!             .expiration(std::time::Duration::from_secs(24 * 3600)),
+//!     );
+//! }
+//! ```
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::io;
+use std::rc::Rc;
+use std::time::{Duration, SystemTime};
+
+use bytes::{Bytes, BytesMut};
+use futures::future::{ready, FutureExt, LocalBoxFuture};
+use futures::StreamExt;
+use time::OffsetDateTime;
+
+use crate::http::header::{self, HeaderMap};
+use crate::http::{Method, Response, ResponseBuilder, StatusCode};
+
+use super::dev::{WebServiceConfig, WebServiceFactory};
+use super::error::ErrorRenderer;
+use super::httprequest::HttpRequest;
+use super::types::Payload;
+use super::util::{delete, head, method, patch, post, resource, scope};
+
+const TUS_VERSION: &str = "1.0.0";
+const TUS_EXTENSIONS: &str = "creation,creation-defer-length,expiration,termination";
+const OFFSET_CONTENT_TYPE: &str = "application/offset+octet-stream";
+
+const TUS_RESUMABLE: &str = "tus-resumable";
+const TUS_VERSION_HDR: &str = "tus-version";
+const TUS_EXTENSION: &str = "tus-extension";
+const TUS_MAX_SIZE: &str = "tus-max-size";
+const UPLOAD_OFFSET: &str = "upload-offset";
+const UPLOAD_LENGTH: &str = "upload-length";
+const UPLOAD_DEFER_LENGTH: &str = "upload-defer-length";
+const UPLOAD_METADATA: &str = "upload-metadata";
+const UPLOAD_EXPIRES: &str = "upload-expires";
+
+/// Upload state
+#[derive(Debug, Clone)]
+pub struct UploadInfo {
+    /// Number of bytes received
+    pub offset: u64,
+    /// Total size of the upload, `None` if size is deferred
+    pub length: Option<u64>,
+    /// Raw value of `Upload-Metadata` header
+    pub metadata: Option<String>,
+    /// Upload creation time
+    pub created: SystemTime,
+}
+
+/// Storage for resumable uploads
+pub trait TusStorage: 'static {
+    /// Create new upload, returns upload id
+    fn create(
+        &self,
+        length: Option<u64>,
+        metadata: Option<String>,
+    ) -> LocalBoxFuture<'static, io::Result<String>>;
+
+    /// Get upload state, returns `None` if upload does not exist
+    fn info(&self, id: &str) -> LocalBoxFuture<'static, io::Result<Option<UploadInfo>>>;
+
+    /// Set total size of the upload with deferred length
+    fn set_length(
+        &self,
+        id: &str,
+        length: u64,
+    ) -> LocalBoxFuture<'static, io::Result<()>>;
+
+    /// Append chunk of data at specified offset
+    fn append(
+        &self,
+        id: &str,
+        offset: u64,
+        data: Bytes,
+    ) -> LocalBoxFuture<'static, io::Result<()>>;
+
+    /// Remove upload and all its data
+    fn remove(&self, id: &str) -> LocalBoxFuture<'static, io::Result<()>>;
+}
+
+/// In-memory upload storage
+#[derive(Clone, Default)]
+pub struct MemoryStorage(Rc<RefCell<HashMap<String, (UploadInfo, BytesMut)>>>);
+
+impl MemoryStorage {
+    /// Create new storage
+    pub fn new() -> Self {
+        MemoryStorage::default()
+    }
+
+    /// Get uploaded data
+    pub fn data(&self, id: &str) -> Option<Bytes> {
+        self.0
+            .borrow()
+            .get(id)
+            .map(|(_, data)| Bytes::copy_from_slice(data))
+    }
+}
+
+fn not_found() -> io::Error {
+    io::Error::new(io::ErrorKind::NotFound, "Upload not found")
+}
+
+impl TusStorage for MemoryStorage {
+    fn create(
+        &self,
+        length: Option<u64>,
+        metadata: Option<String>,
+    ) -> LocalBoxFuture<'static, io::Result<String>> {
+        let id = format!("{:032x}", rand::random::<u128>());
+        let info = UploadInfo {
+            length,
+            metadata,
+            offset: 0,
+            created: SystemTime::now(),
+        };
+        self.0
+            .borrow_mut()
+            .insert(id.clone(), (info, BytesMut::new()));
+        ready(Ok(id)).boxed_local()
+    }
+
+    fn info(&self, id: &str) -> LocalBoxFuture<'static, io::Result<Option<UploadInfo>>> {
+        let info = self.0.borrow().get(id).map(|(info, _)| info.clone());
+        ready(Ok(info)).boxed_local()
+    }
+
+    fn set_length(
+        &self,
+        id: &str,
+        length: u64,
+    ) -> LocalBoxFuture<'static, io::Result<()>> {
+        let res = match self.0.borrow_mut().get_mut(id) {
+            Some((info, _)) => {
+                info.length = Some(length);
+                Ok(())
+            }
+            None => Err(not_found()),
+        };
+        ready(res).boxed_local()
+    }
+
+    fn append(
+        &self,
+        id: &str,
+        offset: u64,
+        data: Bytes,
+    ) -> LocalBoxFuture<'static, io::Result<()>> {
+        let res = match self.0.borrow_mut().get_mut(id) {
+            Some((info, buf)) if info.offset == offset => {
+                buf.extend_from_slice(&data);
+                info.offset += data.len() as u64;
+                Ok(())
+            }
+            Some(_) => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Upload offset does not match",
+            )),
+            None => Err(not_found()),
+        };
+        ready(res).boxed_local()
+    }
+
+    fn remove(&self, id: &str) -> LocalBoxFuture<'static, io::Result<()>> {
+        self.0.borrow_mut().remove(id);
+        ready(Ok(())).boxed_local()
+    }
+}
+
+/// Tus protocol service.
+///
+/// Service registers scope for specified path, uploads are created
+/// with `POST` request to the scope path and are available
+/// at `{path}/{upload-id}`.
+pub struct Tus<S> {
+    path: String,
+    inner: Rc<Inner<S>>,
+}
+
+struct Inner<S> {
+    storage: S,
+    max_size: Option<u64>,
+    expiration: Option<Duration>,
+}
+
+impl<S: TusStorage> Tus<S> {
+    /// Create tus service for specified path and storage
+    pub fn new(path: &str, storage: S) -> Self {
+        Tus {
+            path: path.to_string(),
+            inner: Rc::new(Inner {
+                storage,
+                max_size: None,
+                expiration: None,
+            }),
+        }
+    }
+
+    /// Set max size of an upload in bytes.
+    ///
+    /// By default size is not limited.
+    pub fn max_size(mut self, size: u64) -> Self {
+        Rc::get_mut(&mut self.inner)
+            .expect("Multiple copies exist")
+            .max_size = Some(size);
+        self
+    }
+
+    /// Set upload expiration time.
+    ///
+    /// Unfinished uploads get removed after expiration time since
+    /// upload creation. By default uploads do not expire.
+    pub fn expiration(mut self, timeout: Duration) -> Self {
+        Rc::get_mut(&mut self.inner)
+            .expect("Multiple copies exist")
+            .expiration = Some(timeout);
+        self
+    }
+}
+
+impl<S: TusStorage, Err: ErrorRenderer> WebServiceFactory<Err> for Tus<S> {
+    fn register(self, config: &mut WebServiceConfig<Err>) {
+        let (i1, i2, i3, i4, i5) = (
+            self.inner.clone(),
+            self.inner.clone(),
+            self.inner.clone(),
+            self.inner.clone(),
+            self.inner,
+        );
+
+        scope(&self.path)
+            .service(
+                resource("")
+                    .route(method(Method::OPTIONS).to(move || ready(i1.options())))
+                    .route(post().to(move |req: HttpRequest| i2.clone().create(req))),
+            )
+            .service(
+                resource("/{id}")
+                    .route(head().to(move |req: HttpRequest| i3.clone().head(req)))
+                    .route(patch().to(move |req: HttpRequest, pl: Payload| {
+                        i4.clone().patch(req, pl)
+                    }))
+                    .route(delete().to(move |req: HttpRequest| i5.clone().delete(req))),
+            )
+            .register(config)
+    }
+}
+
+fn response(status: StatusCode) -> ResponseBuilder {
+    let mut res = Response::build(status);
+    res.header(TUS_RESUMABLE, TUS_VERSION);
+    res
+}
+
+fn header_u64(headers: &HeaderMap, name: &str) -> Option<Option<u64>> {
+    headers
+        .get(name)
+        .map(|val| val.to_str().ok().and_then(|s| s.trim().parse().ok()))
+}
+
+fn check_version(req: &HttpRequest) -> Result<(), Response> {
+    match req.headers().get(TUS_RESUMABLE) {
+        Some(val) if val == TUS_VERSION => Ok(()),
+        _ => Err(response(StatusCode::PRECONDITION_FAILED)
+            .header(TUS_VERSION_HDR, TUS_VERSION)
+            .finish()),
+    }
+}
+
+fn server_error(err: io::Error) -> Response {
+    log::error!("Tus storage error: {}", err);
+    response(StatusCode::INTERNAL_SERVER_ERROR).finish()
+}
+
+impl<S: TusStorage> Inner<S> {
+    fn options(&self) -> Response {
+        let mut res = response(StatusCode::NO_CONTENT);
+        res.header(TUS_VERSION_HDR, TUS_VERSION)
+            .header(TUS_EXTENSION, TUS_EXTENSIONS);
+        if let Some(size) = self.max_size {
+            res.header(TUS_MAX_SIZE, size);
+        }
+        res.finish()
+    }
+
+    fn expires(&self, info: &UploadInfo) -> Option<SystemTime> {
+        self.expiration.map(|exp| info.created + exp)
+    }
+
+    fn set_expires(&self, res: &mut ResponseBuilder, info: &UploadInfo) {
+        if let Some(expires) = self.expires(info) {
+            res.header(
+                UPLOAD_EXPIRES,
+                OffsetDateTime::from(expires).format("%a, %d %b %Y %H:%M:%S GMT"),
+            );
+        }
+    }
+
+    /// Get upload state, expired unfinished uploads get removed
+    async fn info(&self, id: &str) -> io::Result<Option<UploadInfo>> {
+        if let Some(info) = self.storage.info(id).await? {
+            let finished = info.length.map(|len| info.offset >= len).unwrap_or(false);
+
+            match self.expires(&info) {
+                Some(expires) if !finished && expires <= SystemTime::now() => {
+                    self.storage.remove(id).await?;
+                    Ok(None)
+                }
+                _ => Ok(Some(info)),
+            }
+        } else {
+            Ok(None)
+        }
+    }
+
+    async fn create(self: Rc<Self>, req: HttpRequest) -> Response {
+        if let Err(res) = check_version(&req) {
+            return res;
+        }
+
+        let length = match header_u64(req.headers(), UPLOAD_LENGTH) {
+            Some(Some(length)) => Some(length),
+            Some(None) => return response(StatusCode::BAD_REQUEST).finish(),
+            None => match req.headers().get(UPLOAD_DEFER_LENGTH) {
+                Some(val) if val == "1" => None,
+                _ => return response(StatusCode::BAD_REQUEST).finish(),
+            },
+        };
+        if let (Some(length), Some(max)) = (length, self.max_size) {
+            if length > max {
+                return response(StatusCode::PAYLOAD_TOO_LARGE).finish();
+            }
+        }
+        let metadata = req
+            .headers()
+            .get(UPLOAD_METADATA)
+            .and_then(|val| val.to_str().ok())
+            .map(|val| val.to_string());
+
+        let id = match self.storage.create(length, metadata).await {
+            Ok(id) => id,
+            Err(e) => return server_error(e),
+        };
+        let info = match self.storage.info(&id).await {
+            Ok(Some(info)) => info,
+            Ok(None) => return server_error(not_found()),
+            Err(e) => return server_error(e),
+        };
+
+        let path = req.path();
+        let location = if path.ends_with('/') {
+            format!("{}{}", path, id)
+        } else {
+            format!("{}/{}", path, id)
+        };
+
+        let mut res = response(StatusCode::CREATED);
+        res.header(header::LOCATION, location);
+        self.set_expires(&mut res, &info);
+        res.finish()
+    }
+
+    async fn head(self: Rc<Self>, req: HttpRequest) -> Response {
+        if let Err(res) = check_version(&req) {
+            return res;
+        }
+
+        let info = match self.info(&req.match_info()["id"]).await {
+            Ok(Some(info)) => info,
+            Ok(None) => return response(StatusCode::NOT_FOUND).finish(),
+            Err(e) => return server_error(e),
+        };
+
+        let mut res = response(StatusCode::OK);
+        res.header(header::CACHE_CONTROL, "no-store")
+            .header(UPLOAD_OFFSET, info.offset);
+        if let Some(length) = info.length {
+            res.header(UPLOAD_LENGTH, length);
+        } else {
+            res.header(UPLOAD_DEFER_LENGTH, "1");
+        }
+        if let Some(ref metadata) = info.metadata {
+            res.header(UPLOAD_METADATA, metadata.as_str());
+        }
+        self.set_expires(&mut res, &info);
+        res.finish()
+    }
+
+    async fn patch(self: Rc<Self>, req: HttpRequest, mut payload: Payload) -> Response {
+        if let Err(res) = check_version(&req) {
+            return res;
+        }
+
+        match req.headers().get(header::CONTENT_TYPE) {
+            Some(val) if val == OFFSET_CONTENT_TYPE => (),
+            _ => return response(StatusCode::UNSUPPORTED_MEDIA_TYPE).finish(),
+        }
+        let mut offset = match header_u64(req.headers(), UPLOAD_OFFSET) {
+            Some(Some(offset)) => offset,
+            _ => return response(StatusCode::BAD_REQUEST).finish(),
+        };
+
+        let id = &req.match_info()["id"];
+        let mut info = match self.info(id).await {
+            Ok(Some(info)) => info,
+            Ok(None) => return response(StatusCode::NOT_FOUND).finish(),
+            Err(e) => return server_error(e),
+        };
+        if info.offset != offset {
+            return response(StatusCode::CONFLICT).finish();
+        }
+
+        // deferred upload length
+        if info.length.is_none() {
+            match header_u64(req.headers(), UPLOAD_LENGTH) {
+                Some(Some(length)) => {
+                    if self.max_size.map(|max| length > max).unwrap_or(false)
+                        || length < offset
+                    {
+                        return response(StatusCode::PAYLOAD_TOO_LARGE).finish();
+                    }
+                    if let Err(e) = self.storage.set_length(id, length).await {
+                        return server_error(e);
+                    }
+                    info.length = Some(length);
+                }
+                Some(None) => return response(StatusCode::BAD_REQUEST).finish(),
+                None => (),
+            }
+        }
+        let limit = info.length.or(self.max_size);
+
+        while let Some(item) = payload.next().await {
+            let chunk = match item {
+                Ok(chunk) => chunk,
+                Err(e) => {
+                    log::trace!("Tus upload payload error: {}", e);
+                    break;
+                }
+            };
+            let size = chunk.len() as u64;
+            if limit.map(|limit| offset + size > limit).unwrap_or(false) {
+                return response(StatusCode::PAYLOAD_TOO_LARGE).finish();
+            }
+            if let Err(e) = self.storage.append(id, offset, chunk).await {
+                return server_error(e);
+            }
+            offset += size;
+        }
+
+        let mut res = response(StatusCode::NO_CONTENT);
+        res.header(UPLOAD_OFFSET, offset);
+        self.set_expires(&mut res, &info);
+        res.finish()
+    }
+
+    async fn delete(self: Rc<Self>, req: HttpRequest) -> Response {
+        if let Err(res) = check_version(&req) {
+            return res;
+        }
+
+        let id = &req.match_info()["id"];
+        match self.info(id).await {
+            Ok(Some(_)) => (),
+            Ok(None) => return response(StatusCode::NOT_FOUND).finish(),
+            Err(e) => return server_error(e),
+        }
+        match self.storage.remove(id).await {
+            Ok(_) => response(StatusCode::NO_CONTENT).finish(),
+            Err(e) => server_error(e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::web::test::{call_service, init_service, TestRequest};
+    use crate::web::App;
+
+    #[ntex_rt::test]
+    async fn test_tus() {
+        let storage = MemoryStorage::new();
+        let srv = init_service(
+            App::new().service(Tus::new("/files", storage.clone()).max_size(10)),
+        )
+        .await;
+
+        let req = TestRequest::with_uri("/files")
+            .method(Method::OPTIONS)
+            .to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+        assert_eq!(resp.headers().get(TUS_MAX_SIZE).unwrap(), "10");
+        assert_eq!(resp.headers().get(TUS_VERSION_HDR).unwrap(), TUS_VERSION);
+
+        // missing version
+        let req = TestRequest::with_uri("/files")
+            .method(Method::POST)
+            .header(UPLOAD_LENGTH, "5")
+            .to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::PRECONDITION_FAILED);
+
+        // too large
+        let req = TestRequest::with_uri("/files")
+            .method(Method::POST)
+            .header(TUS_RESUMABLE, TUS_VERSION)
+            .header(UPLOAD_LENGTH, "11")
+            .to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::PAYLOAD_TOO_LARGE);
+
+        let req = TestRequest::with_uri("/files")
+            .method(Method::POST)
+            .header(TUS_RESUMABLE, TUS_VERSION)
+            .header(UPLOAD_LENGTH, "10")
+            .header(
+                UPLOAD_METADATA,
+                "filename d29ybGRfZG9taW5hdGlvbl9wbGFuLnBkZg==",
+            )
+            .to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::CREATED);
+        let location = resp
+            .headers()
+            .get(header::LOCATION)
+            .unwrap()
+            .to_str()
+            .unwrap()
+            .to_string();
+        assert!(location.starts_with("/files/"));
+        let id = location.trim_start_matches("/files/").to_string();
+
+        let req = TestRequest::with_uri(&location)
+            .method(Method::PATCH)
+            .header(TUS_RESUMABLE, TUS_VERSION)
+            .header(header::CONTENT_TYPE, OFFSET_CONTENT_TYPE)
+            .header(UPLOAD_OFFSET, "0")
+            .set_payload(Bytes::from_static(b"hello"))
+            .to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+        assert_eq!(resp.headers().get(UPLOAD_OFFSET).unwrap(), "5");
+
+        // wrong offset
+        let req = TestRequest::with_uri(&location)
+            .method(Method::PATCH)
+            .header(TUS_RESUMABLE, TUS_VERSION)
+            .header(header::CONTENT_TYPE, OFFSET_CONTENT_TYPE)
+            .header(UPLOAD_OFFSET, "0")
+            .set_payload(Bytes::from_static(b"world"))
+            .to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::CONFLICT);
+
+        let req = TestRequest::with_uri(&location)
+            .method(Method::HEAD)
+            .header(TUS_RESUMABLE, TUS_VERSION)
+            .to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(resp.headers().get(UPLOAD_OFFSET).unwrap(), "5");
+        assert_eq!(resp.headers().get(UPLOAD_LENGTH).unwrap(), "10");
+        assert_eq!(
+            resp.headers().get(UPLOAD_METADATA).unwrap(),
+            "filename d29ybGRfZG9taW5hdGlvbl9wbGFuLnBkZg=="
+        );
+
+        let req = TestRequest::with_uri(&location)
+            .method(Method::PATCH)
+            .header(TUS_RESUMABLE, TUS_VERSION)
+            .header(header::CONTENT_TYPE, OFFSET_CONTENT_TYPE)
+            .header(UPLOAD_OFFSET, "5")
+            .set_payload(Bytes::from_static(b"world"))
+            .to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+        assert_eq!(resp.headers().get(UPLOAD_OFFSET).unwrap(), "10");
+        assert_eq!(
+            storage.data(&id).unwrap(),
+            Bytes::from_static(b"helloworld")
+        );
+
+        let req = TestRequest::with_uri(&location)
+            .method(Method::DELETE)
+            .header(TUS_RESUMABLE, TUS_VERSION)
+            .to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+        assert!(storage.data(&id).is_none());
+    }
+
+    #[ntex_rt::test]
+    async fn test_tus_defer_length_and_expiration() {
+        let storage = MemoryStorage::new();
+        let srv = init_service(App::new().service(
+            Tus::new("/files", storage.clone()).expiration(Duration::from_secs(0)),
+        ))
+        .await;
+
+        let req = TestRequest::with_uri("/files")
+            .method(Method::POST)
+            .header(TUS_RESUMABLE, TUS_VERSION)
+            .header(UPLOAD_DEFER_LENGTH, "1")
+            .to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::CREATED);
+        assert!(resp.headers().contains_key(UPLOAD_EXPIRES));
+        let location = resp
+            .headers()
+            .get(header::LOCATION)
+            .unwrap()
+            .to_str()
+            .unwrap()
+            .to_string();
+
+        // upload is expired
+        let req = TestRequest::with_uri(&location)
+            .method(Method::HEAD)
+            .header(TUS_RESUMABLE, TUS_VERSION)
+            .to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+
+        let srv = init_service(App::new().service(Tus::new("/files", storage))).await;
+        let req = TestRequest::with_uri("/files")
+            .method(Method::POST)
+            .header(TUS_RESUMABLE, TUS_VERSION)
+            .header(UPLOAD_DEFER_LENGTH, "1")
+            .to_request();
+        let resp = call_service(&srv, req).await;
+        let location = resp
+            .headers()
+            .get(header::LOCATION)
+            .unwrap()
+            .to_str()
+            .unwrap()
+            .to_string();
+
+        let req = TestRequest::with_uri(&location)
+            .method(Method::HEAD)
+            .header(TUS_RESUMABLE, TUS_VERSION)
+            .to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.headers().get(UPLOAD_DEFER_LENGTH).unwrap(), "1");
+
+        let req = TestRequest::with_uri(&location)
+            .method(Method::PATCH)
+            .header(TUS_RESUMABLE, TUS_VERSION)
+            .header(header::CONTENT_TYPE, OFFSET_CONTENT_TYPE)
+            .header(UPLOAD_OFFSET, "0")
+            .header(UPLOAD_LENGTH, "3")
+            .set_payload(Bytes::from_static(b"hello"))
+            .to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::PAYLOAD_TOO_LARGE);
+
+        let req = TestRequest::with_uri(&location)
+            .method(Method::HEAD)
+            .header(TUS_RESUMABLE, TUS_VERSION)
+            .to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.headers().get(UPLOAD_LENGTH).unwrap(), "3");
+    }
+}
@@ -0,0 +1,324 @@
+//! Basic and Bearer authentication extractors
+use futures::future::{err, ok, Ready};
+
+use crate::http::header::{HeaderMap, AUTHORIZATION};
+use crate::http::Payload;
+use crate::web::error::{AuthError, ErrorRenderer};
+use crate::web::{FromRequest, HttpRequest};
+
+/// Credentials of http authentication scheme.
+///
+/// Credentials are parsed from `Authorization` header.
+pub trait AuthCredentials: Sized {
+    /// Authentication scheme name
+    const SCHEME: &'static str;
+
+    /// Parse credentials, value is `Authorization` header without
+    /// scheme name.
+    fn parse(value: &str) -> Option<Self>;
+}
+
+/// Authentication extractors configuration.
+///
+/// Realm is used for authentication challenge, default realm is
+/// `Restricted`.
+///
+/// ```rust
+/// use ntex::web::{self, types::{AuthConfig, BasicAuth}, App};
+///
+/// async fn index(auth: BasicAuth) -> String {
+///     format!("Hello, {}!", auth.user_id())
+/// }
+///
+/// fn main() {
+///     let app = App::new()
+///         .app_data(AuthConfig::default().realm("admin"))
+///         .service(web::resource("/index.html").to(index));
+/// }
+/// ```
+#[derive(Clone, Debug)]
+pub struct AuthConfig {
+    realm: String,
+}
+
+impl AuthConfig {
+    /// Set authentication realm
+    pub fn realm<T: Into<String>>(mut self, realm: T) -> Self {
+        self.realm = realm.into();
+        self
+    }
+}
+
+impl Default for AuthConfig {
+    fn default() -> Self {
+        AuthConfig {
+            realm: "Restricted".to_string(),
+        }
+    }
+}
+
+/// Extractor for http *Basic* authentication credentials.
+///
+/// Extractor fails with `AuthError` if credentials are missing or
+/// malformed, error contains authentication challenge.
+///
+/// ```rust
+/// use ntex::web::{self, types::BasicAuth, App};
+///
+/// async fn index(auth: BasicAuth) -> String {
+///     format!("Hello, {}!", auth.user_id())
+/// }
+///
+/// fn main() {
+///     let app = App::new().service(web::resource("/index.html").to(index));
+/// }
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BasicAuth {
+    user_id: String,
+    password: Option<String>,
+}
+
+impl BasicAuth {
+    /// User id
+    pub fn user_id(&self) -> &str {
+        &self.user_id
+    }
+
+    /// Password, `None` if password is empty
+    pub fn password(&self) -> Option<&str> {
+        self.password.as_deref()
+    }
+}
+
+impl AuthCredentials for BasicAuth {
+    const SCHEME: &'static str = "Basic";
+
+    fn parse(value: &str) -> Option<Self> {
+        let decoded = base64::decode(value).ok()?;
+        let decoded = String::from_utf8(decoded).ok()?;
+        let mut parts = decoded.splitn(2, ':');
+        let user_id = parts.next()?.to_string();
+        let password = parts.next()?;
+
+        Some(BasicAuth {
+            user_id,
+            password: if password.is_empty() {
+                None
+            } else {
+                Some(password.to_string())
+            },
+        })
+    }
+}
+
+impl<Err: ErrorRenderer> FromRequest<Err> for BasicAuth {
+    type Error = AuthError;
+    type Future = Ready<Result<Self, AuthError>>;
+
+    #[inline]
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        extract(req)
+    }
+}
+
+/// Extractor for http *Bearer* authentication token.
+///
+/// Extractor fails with `AuthError` if token is missing or malformed,
+/// error contains authentication challenge.
+///
+/// ```rust
+/// use ntex::web::{self, types::BearerAuth, App};
+///
+/// async fn index(auth: BearerAuth) -> String {
+///     format!("Token: {}", auth.token())
+/// }
+///
+/// fn main() {
+///     let app = App::new().service(web::resource("/index.html").to(index));
+/// }
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BearerAuth {
+    token: String,
+}
+
+impl BearerAuth {
+    /// Bearer token
+    pub fn token(&self) -> &str {
+        &self.token
+    }
+}
+
+impl AuthCredentials for BearerAuth {
+    const SCHEME: &'static str = "Bearer";
+
+    fn parse(value: &str) -> Option<Self> {
+        if value.is_empty() || value.contains(char::is_whitespace) {
+            None
+        } else {
+            Some(BearerAuth {
+                token: value.to_string(),
+            })
+        }
+    }
+}
+
+impl<Err: ErrorRenderer> FromRequest<Err> for BearerAuth {
+    type Error = AuthError;
+    type Future = Ready<Result<Self, AuthError>>;
+
+    #[inline]
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        extract(req)
+    }
+}
+
+fn extract<T: AuthCredentials>(req: &HttpRequest) -> Ready<Result<T, AuthError>> {
+    let tmp;
+    let cfg = if let Some(cfg) = req.app_data::<AuthConfig>() {
+        cfg
+    } else {
+        tmp = AuthConfig::default();
+        &tmp
+    };
+
+    match credentials(req.headers(), &cfg.realm) {
+        Ok(creds) => ok(creds),
+        Err(e) => {
+            log::debug!(
+                "Failed during {} authentication. Request path: {:?}",
+                T::SCHEME,
+                req.path()
+            );
+            err(e)
+        }
+    }
+}
+
+/// Parse credentials from `Authorization` header
+pub(crate) fn credentials<T: AuthCredentials>(
+    headers: &HeaderMap,
+    realm: &str,
+) -> Result<T, AuthError> {
+    let value = headers
+        .get(AUTHORIZATION)
+        .ok_or_else(|| AuthError::Missing(challenge::<T>(realm)))?
+        .to_str()
+        .map_err(|_| AuthError::Invalid(challenge::<T>(realm)))?
+        .trim();
+
+    let mut parts = value.splitn(2, ' ');
+    let scheme = parts.next().unwrap_or("");
+    if !scheme.eq_ignore_ascii_case(T::SCHEME) {
+        return Err(AuthError::Missing(challenge::<T>(realm)));
+    }
+
+    parts
+        .next()
+        .and_then(|value| T::parse(value.trim()))
+        .ok_or_else(|| AuthError::Invalid(challenge::<T>(realm)))
+}
+
+/// Authentication challenge for `WWW-Authenticate` header
+pub(crate) fn challenge<T: AuthCredentials>(realm: &str) -> String {
+    format!(
+        "{} realm=\"{}\"",
+        T::SCHEME,
+        realm.replace('\\', "\\\\").replace('"', "\\\"")
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::header::{self, HeaderValue};
+    use crate::http::StatusCode;
+    use crate::web::test::{call_service, init_service, read_body, TestRequest};
+    use crate::web::{self, App, DefaultError};
+
+    #[test]
+    fn test_basic_parse() {
+        let auth = BasicAuth::parse(&base64::encode("user:pass")).unwrap();
+        assert_eq!(auth.user_id(), "user");
+        assert_eq!(auth.password(), Some("pass"));
+
+        let auth = BasicAuth::parse(&base64::encode("user:")).unwrap();
+        assert_eq!(auth.password(), None);
+        let auth = BasicAuth::parse(&base64::encode("user:p:a")).unwrap();
+        assert_eq!(auth.password(), Some("p:a"));
+
+        assert!(BasicAuth::parse(&base64::encode("user")).is_none());
+        assert!(BasicAuth::parse("!!!").is_none());
+    }
+
+    #[test]
+    fn test_bearer_parse() {
+        assert_eq!(BearerAuth::parse("abc.def").unwrap().token(), "abc.def");
+        assert!(BearerAuth::parse("").is_none());
+        assert!(BearerAuth::parse("a b").is_none());
+    }
+
+    #[ntex_rt::test]
+    async fn test_extract() {
+        let req = TestRequest::with_header(
+            header::AUTHORIZATION,
+            format!("basic {}", base64::encode("user:pass")),
+        )
+        .to_http_request();
+        let auth = <BasicAuth as FromRequest<DefaultError>>::extract(&req)
+            .await
+            .unwrap();
+        assert_eq!(auth.user_id(), "user");
+
+        let e = <BearerAuth as FromRequest<DefaultError>>::extract(&req)
+            .await
+            .err()
+            .unwrap();
+        assert_eq!(
+            e,
+            AuthError::Missing("Bearer realm=\"Restricted\"".to_string())
+        );
+
+        let req = TestRequest::with_header(header::AUTHORIZATION, "Bearer a b")
+            .data(AuthConfig::default().realm("a\"b"))
+            .to_http_request();
+        let e = <BearerAuth as FromRequest<DefaultError>>::extract(&req)
+            .await
+            .err()
+            .unwrap();
+        assert_eq!(e, AuthError::Invalid("Bearer realm=\"a\\\"b\"".to_string()));
+
+        let req = TestRequest::default().to_http_request();
+        let e = <BasicAuth as FromRequest<DefaultError>>::extract(&req)
+            .await
+            .err()
+            .unwrap();
+        assert_eq!(e.challenge(), "Basic realm=\"Restricted\"");
+    }
+
+    #[ntex_rt::test]
+    async fn test_response() {
+        let srv = init_service(
+            App::new().service(
+                web::resource("/")
+                    .to(|auth: BearerAuth| async move { auth.token().to_string() }),
+            ),
+        )
+        .await;
+
+        let req =
+            TestRequest::with_header(header::AUTHORIZATION, "Bearer token").to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(read_body(resp).await, "token");
+
+        let req = TestRequest::default().to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(
+            resp.headers().get(header::WWW_AUTHENTICATE).unwrap(),
+            HeaderValue::from_static("Bearer realm=\"Restricted\"")
+        );
+    }
+}
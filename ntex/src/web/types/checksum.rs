@@ -0,0 +1,304 @@
+//! Request body checksum extractor
+use std::cell::RefCell;
+use std::fmt;
+use std::pin::Pin;
+use std::rc::Rc;
+use std::task::{Context, Poll};
+
+use bytes::Bytes;
+use futures::future::{ok, Ready};
+use futures::Stream;
+use sha2::{Digest, Sha256};
+
+use crate::http::error::PayloadError;
+use crate::http::Payload;
+use crate::web::error::ErrorRenderer;
+use crate::web::{FromRequest, HttpRequest};
+
+/// Checksum algorithm
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ChecksumAlgorithm {
+    /// SHA-256 digest
+    Sha256,
+    /// CRC-32C (Castagnoli) checksum
+    Crc32c,
+}
+
+/// Request body checksum extractor.
+///
+/// Extractor computes checksum of the request's body as it streams
+/// through. Body is not buffered, extractor wraps request's payload and
+/// hashes every chunk that is read by the next extractors or by the handler.
+/// Checksum is available once the body is completely read, so checksum
+/// extractor must be placed before body extractors.
+///
+/// [**ChecksumConfig**](struct.ChecksumConfig.html) allows to configure
+/// checksum algorithm and max size of the body. Size limit is checked for
+/// each chunk, payload stream fails with `PayloadError::Overflow` error as
+/// soon as limit is reached.
+///
+/// ## Example
+///
+/// ```rust
+/// use bytes::Bytes;
+/// use ntex::web::{self, types::Checksum};
+///
+/// /// body checksum and body itself
+/// async fn index(sum: Checksum, body: Bytes) -> String {
+///     format!("Body {:?}, sha256: {}", body, sum.to_hex().unwrap())
+/// }
+///
+/// fn main() {
+///     let app = web::App::new().service(
+///         web::resource("/index.html").route(
+///             web::post().to(index))
+///     );
+/// }
+/// ```
+#[derive(Clone)]
+pub struct Checksum(Rc<RefCell<Inner>>);
+
+impl Checksum {
+    /// Checksum algorithm
+    pub fn algorithm(&self) -> ChecksumAlgorithm {
+        self.0.borrow().algorithm
+    }
+
+    /// Raw checksum value.
+    ///
+    /// Returns `None` if request's body is not completely read.
+    pub fn digest(&self) -> Option<Bytes> {
+        self.0.borrow().digest.clone()
+    }
+
+    /// Checksum value as lower case hex string.
+    ///
+    /// Returns `None` if request's body is not completely read.
+    pub fn to_hex(&self) -> Option<String> {
+        self.0
+            .borrow()
+            .digest
+            .as_ref()
+            .map(|digest| digest.iter().map(|b| format!("{:02x}", b)).collect())
+    }
+}
+
+impl fmt::Debug for Checksum {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Checksum({:?}, {:?})", self.algorithm(), self.to_hex())
+    }
+}
+
+impl<Err: ErrorRenderer> FromRequest<Err> for Checksum {
+    type Error = Err::Container;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    #[inline]
+    fn from_request(req: &HttpRequest, payload: &mut Payload) -> Self::Future {
+        let tmp;
+        let cfg = if let Some(cfg) = req.app_data::<ChecksumConfig>() {
+            cfg
+        } else {
+            tmp = ChecksumConfig::default();
+            &tmp
+        };
+
+        let inner = Rc::new(RefCell::new(Inner {
+            hasher: Some(match cfg.algorithm {
+                ChecksumAlgorithm::Sha256 => Hasher::Sha256(Sha256::new()),
+                ChecksumAlgorithm::Crc32c => Hasher::Crc32c(0),
+            }),
+            algorithm: cfg.algorithm,
+            limit: cfg.limit,
+            size: 0,
+            digest: None,
+        }));
+        *payload = Payload::Stream(Box::pin(ChecksumStream {
+            payload: payload.take(),
+            inner: inner.clone(),
+        }));
+
+        ok(Checksum(inner))
+    }
+}
+
+/// Checksum extractor configuration
+#[derive(Clone)]
+pub struct ChecksumConfig {
+    algorithm: ChecksumAlgorithm,
+    limit: usize,
+}
+
+impl ChecksumConfig {
+    /// Create `ChecksumConfig` instance for specified algorithm.
+    pub fn new(algorithm: ChecksumAlgorithm) -> Self {
+        ChecksumConfig {
+            algorithm,
+            limit: 262_144,
+        }
+    }
+
+    /// Change max size of payload. By default max size is 256Kb
+    pub fn limit(mut self, limit: usize) -> Self {
+        self.limit = limit;
+        self
+    }
+}
+
+impl Default for ChecksumConfig {
+    fn default() -> Self {
+        ChecksumConfig::new(ChecksumAlgorithm::Sha256)
+    }
+}
+
+enum Hasher {
+    Sha256(Sha256),
+    Crc32c(u32),
+}
+
+struct Inner {
+    hasher: Option<Hasher>,
+    algorithm: ChecksumAlgorithm,
+    limit: usize,
+    size: usize,
+    digest: Option<Bytes>,
+}
+
+/// Payload stream that hashes data as it is read
+struct ChecksumStream {
+    payload: Payload,
+    inner: Rc<RefCell<Inner>>,
+}
+
+impl Stream for ChecksumStream {
+    type Item = Result<Bytes, PayloadError>;
+
+    fn poll_next(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        let mut inner = this.inner.borrow_mut();
+
+        match Pin::new(&mut this.payload).poll_next(cx) {
+            Poll::Ready(Some(Ok(chunk))) => {
+                inner.size += chunk.len();
+                if inner.size > inner.limit {
+                    inner.hasher = None;
+                    return Poll::Ready(Some(Err(PayloadError::Overflow)));
+                }
+                match inner.hasher {
+                    Some(Hasher::Sha256(ref mut h)) => h.input(&chunk),
+                    Some(Hasher::Crc32c(ref mut crc)) => {
+                        *crc = crc32c::crc32c_append(*crc, &chunk)
+                    }
+                    None => return Poll::Ready(Some(Err(PayloadError::Overflow))),
+                }
+                Poll::Ready(Some(Ok(chunk)))
+            }
+            Poll::Ready(None) => {
+                inner.digest = match inner.hasher.take() {
+                    Some(Hasher::Sha256(h)) => Some(Bytes::copy_from_slice(&h.result())),
+                    Some(Hasher::Crc32c(crc)) => {
+                        Some(Bytes::copy_from_slice(&crc.to_be_bytes()))
+                    }
+                    None => inner.digest.take(),
+                };
+                Poll::Ready(None)
+            }
+            res => res,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+    use futures::StreamExt;
+
+    use super::*;
+    use crate::http::StatusCode;
+    use crate::web::test::{call_service, init_service, read_body, TestRequest};
+    use crate::web::{self, types, App, HttpResponse};
+
+    #[ntex_rt::test]
+    async fn test_checksum() {
+        let (req, mut pl) = TestRequest::default()
+            .set_payload(Bytes::from_static(b"hello world"))
+            .to_http_parts();
+
+        let (sum, body) =
+            <(Checksum, Bytes) as FromRequest<crate::web::DefaultError>>::from_request(
+                &req, &mut pl,
+            )
+            .await
+            .unwrap();
+        assert_eq!(sum.algorithm(), ChecksumAlgorithm::Sha256);
+        assert_eq!(
+            sum.to_hex().unwrap(),
+            "b94d27b9934d3e08a52e52d7da7dabfac484efe37a5380ee9088f7ace2efcde9"
+        );
+        assert_eq!(body, Bytes::from_static(b"hello world"));
+
+        let (req, mut pl) = TestRequest::default()
+            .data(ChecksumConfig::new(ChecksumAlgorithm::Crc32c))
+            .set_payload(Bytes::from_static(b"123456789"))
+            .to_http_parts();
+        let sum = <Checksum as FromRequest<crate::web::DefaultError>>::from_request(
+            &req, &mut pl,
+        )
+        .await
+        .unwrap();
+        // body is not read yet
+        assert!(sum.digest().is_none());
+
+        let _ = <Bytes as FromRequest<crate::web::DefaultError>>::from_request(
+            &req, &mut pl,
+        )
+        .await
+        .unwrap();
+        assert_eq!(sum.digest().unwrap(), &[0xe3, 0x06, 0x92, 0x83][..]);
+    }
+
+    #[ntex_rt::test]
+    async fn test_checksum_limit() {
+        let srv = init_service(
+            App::new().service(
+                web::resource("/")
+                    .app_data(ChecksumConfig::default().limit(4))
+                    .route(web::post().to(|sum: Checksum, body: Bytes| async move {
+                        HttpResponse::Ok().body(format!("{:?} {:?}", sum.to_hex(), body))
+                    })),
+            ),
+        )
+        .await;
+
+        let req = TestRequest::post()
+            .set_payload(Bytes::from_static(b"hello world"))
+            .to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::PAYLOAD_TOO_LARGE);
+
+        let srv = init_service(App::new().service(web::resource("/").route(
+            web::post().to(|sum: Checksum, mut body: types::Payload| async move {
+                while let Some(chunk) = body.next().await {
+                    chunk.unwrap();
+                }
+                HttpResponse::Ok().body(sum.to_hex().unwrap())
+            }),
+        )))
+        .await;
+
+        let req = TestRequest::post()
+            .set_payload(Bytes::from_static(b"hello world"))
+            .to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(
+            read_body(resp).await,
+            Bytes::from_static(
+                b"b94d27b9934d3e08a52e52d7da7dabfac484efe37a5380ee9088f7ace2efcde9"
+            )
+        );
+    }
+}
@@ -0,0 +1,129 @@
+//! JWT claims extractor
+use std::{fmt, ops};
+
+use bytes::Bytes;
+use futures::future::{err, ok, Ready};
+use serde::de::DeserializeOwned;
+
+use crate::http::Payload;
+use crate::web::error::{ErrorRenderer, JwtError};
+use crate::web::{FromRequest, HttpRequest};
+
+/// Validated token payload, stored in request extensions by `Jwt` middleware
+pub(in crate::web) struct JwtPayload(pub(in crate::web) Bytes);
+
+/// Extractor for claims of validated JWT.
+///
+/// Claims are available only for requests processed by
+/// [`Jwt`](../middleware/struct.Jwt.html) middleware, otherwise extractor
+/// fails with `JwtError::NoClaims` error.
+///
+/// ```rust
+/// use ntex::web::{self, middleware, types::Claims, App};
+///
+/// #[derive(serde::Deserialize)]
+/// struct User {
+///     sub: String,
+/// }
+///
+/// async fn index(claims: Claims<User>) -> String {
+///     format!("Welcome {}!", claims.sub)
+/// }
+///
+/// fn main() {
+///     let app = App::new()
+///         .wrap(middleware::Jwt::new().key(middleware::JwtKey::hs256(b"secret")))
+///         .service(web::resource("/index.html").to(index));
+/// }
+/// ```
+pub struct Claims<T>(pub T);
+
+impl<T> Claims<T> {
+    /// Deconstruct to an inner value
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T> ops::Deref for Claims<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T> ops::DerefMut for Claims<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.0
+    }
+}
+
+impl<T> fmt::Debug for Claims<T>
+where
+    T: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Claims: {:?}", self.0)
+    }
+}
+
+impl<T, Err> FromRequest<Err> for Claims<T>
+where
+    T: DeserializeOwned,
+    Err: ErrorRenderer,
+{
+    type Error = JwtError;
+    type Future = Ready<Result<Self, JwtError>>;
+
+    #[inline]
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        match req.extensions().get::<JwtPayload>() {
+            Some(payload) => match serde_json::from_slice(&payload.0) {
+                Ok(claims) => ok(Claims(claims)),
+                Err(e) => {
+                    log::debug!(
+                        "Failed to deserialize token claims. Request path: {:?}",
+                        req.path()
+                    );
+                    err(JwtError::Claims(e))
+                }
+            },
+            None => err(JwtError::NoClaims),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_derive::Deserialize;
+
+    use super::*;
+    use crate::web::test::TestRequest;
+    use crate::web::DefaultError;
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct User {
+        sub: String,
+    }
+
+    #[ntex_rt::test]
+    async fn test_claims() {
+        let req = TestRequest::default().to_http_request();
+        let res = <Claims<User> as FromRequest<DefaultError>>::extract(&req).await;
+        assert!(matches!(res, Err(JwtError::NoClaims)));
+
+        req.extensions_mut()
+            .insert(JwtPayload(Bytes::from_static(b"{\"sub\":\"user\"}")));
+        let claims = <Claims<User> as FromRequest<DefaultError>>::extract(&req)
+            .await
+            .unwrap();
+        assert_eq!(claims.sub, "user");
+        assert_eq!(claims.into_inner(), User { sub: "user".into() });
+
+        req.extensions_mut()
+            .insert(JwtPayload(Bytes::from_static(b"{\"id\":1}")));
+        let res = <Claims<User> as FromRequest<DefaultError>>::extract(&req).await;
+        assert!(matches!(res, Err(JwtError::Claims(_))));
+    }
+}
@@ -1,3 +1,4 @@
+use std::any::TypeId;
 use std::ops::Deref;
 use std::sync::Arc;
 
@@ -11,6 +12,39 @@ use crate::web::httprequest::HttpRequest;
 /// Application data factory
 pub(crate) trait DataFactory {
     fn create(&self, extensions: &mut Extensions) -> bool;
+
+    /// Type id of created data object
+    fn data_type(&self) -> TypeId;
+}
+
+/// Application data object required by request extractor.
+///
+/// Used for startup validation, check
+/// [`App::validate()`](../struct.App.html#method.validate).
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct DataRequirement {
+    type_id: TypeId,
+    type_name: &'static str,
+}
+
+impl DataRequirement {
+    /// Create requirement for data object of type `T`.
+    pub fn of<T: 'static>() -> Self {
+        DataRequirement {
+            type_id: TypeId::of::<T>(),
+            type_name: std::any::type_name::<T>(),
+        }
+    }
+
+    /// Type id of required data object
+    pub fn type_id(&self) -> TypeId {
+        self.type_id
+    }
+
+    /// Type name of required data object
+    pub fn type_name(&self) -> &'static str {
+        self.type_name
+    }
 }
 
 /// Application data.
@@ -116,6 +150,10 @@ impl<T: 'static, E: ErrorRenderer> FromRequest<E> for Data<T> {
             err(DataExtractorError::NotConfigured)
         }
     }
+
+    fn required_data(data: &mut Vec<DataRequirement>) {
+        data.push(DataRequirement::of::<Data<T>>());
+    }
 }
 
 impl<T: 'static> DataFactory for Data<T> {
@@ -127,6 +165,10 @@ impl<T: 'static> DataFactory for Data<T> {
             false
         }
     }
+
+    fn data_type(&self) -> TypeId {
+        TypeId::of::<Data<T>>()
+    }
 }
 
 #[cfg(test)]
@@ -0,0 +1,483 @@
+//! Deserialization with location of invalid field
+use std::cell::RefCell;
+use std::fmt;
+
+use serde::de::{
+    Deserialize, DeserializeSeed, Deserializer, EnumAccess, MapAccess, SeqAccess,
+    Visitor,
+};
+
+use crate::web::error::FieldError;
+
+/// Deserialize urlencoded data
+pub(crate) fn from_urlencoded<'de, T>(input: &'de [u8]) -> Result<T, FieldError>
+where
+    T: Deserialize<'de>,
+{
+    deserialize(serde_urlencoded::Deserializer::new(
+        url::form_urlencoded::parse(input),
+    ))
+}
+
+/// Deserialize json data
+pub(crate) fn from_json<'de, T>(input: &'de [u8]) -> Result<T, FieldError>
+where
+    T: Deserialize<'de>,
+{
+    let mut de = serde_json::Deserializer::from_slice(input);
+    let value = deserialize(&mut de)?;
+    de.end()
+        .map_err(|e| FieldError::new(String::new(), None, e.to_string()))?;
+    Ok(value)
+}
+
+pub(super) fn deserialize<'de, D, T>(de: D) -> Result<T, FieldError>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    let track = Track::default();
+    T::deserialize(Wrap::new(de, &Chain::Root, &track))
+        .map_err(|e| track.into_error(e.to_string()))
+}
+
+/// Location of the value being deserialized
+enum Chain<'a> {
+    Root,
+    Seq { parent: &'a Chain<'a>, index: usize },
+    Map { parent: &'a Chain<'a>, key: String },
+}
+
+impl<'a> fmt::Display for Chain<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Chain::Root => Ok(()),
+            Chain::Seq { parent, index } => write!(f, "{}[{}]", parent, index),
+            Chain::Map { parent, key } => {
+                if let Chain::Root = parent {
+                    f.write_str(key)
+                } else {
+                    write!(f, "{}.{}", parent, key)
+                }
+            }
+        }
+    }
+}
+
+/// Location and expected type of the first failed value
+#[derive(Default)]
+struct Track {
+    path: RefCell<Option<String>>,
+    expected: RefCell<Option<String>>,
+    key: RefCell<Option<String>>,
+}
+
+impl Track {
+    fn expecting(&self, expected: String) {
+        if self.path.borrow().is_none() {
+            *self.expected.borrow_mut() = Some(expected);
+        }
+    }
+
+    fn fail<E>(&self, chain: &Chain<'_>, hint: Option<&'static str>, err: E) -> E {
+        let mut path = self.path.borrow_mut();
+        if path.is_none() {
+            *path = Some(chain.to_string());
+            let mut expected = self.expected.borrow_mut();
+            if expected.is_none() {
+                *expected = hint.map(|s| s.to_string());
+            }
+        }
+        err
+    }
+
+    fn into_error(self, message: String) -> FieldError {
+        FieldError::new(
+            self.path.into_inner().unwrap_or_default(),
+            self.expected.into_inner(),
+            message,
+        )
+    }
+}
+
+/// Wrapper for deserializer, visitor or seed that tracks location
+struct Wrap<'a, X> {
+    inner: X,
+    chain: &'a Chain<'a>,
+    track: &'a Track,
+    key: bool,
+}
+
+impl<'a, X> Wrap<'a, X> {
+    fn new(inner: X, chain: &'a Chain<'a>, track: &'a Track) -> Self {
+        Wrap {
+            inner,
+            chain,
+            track,
+            key: false,
+        }
+    }
+
+    fn wrap<Y>(&self, inner: Y) -> Wrap<'a, Y> {
+        Wrap {
+            inner,
+            chain: self.chain,
+            track: self.track,
+            key: self.key,
+        }
+    }
+
+    /// Remember map key
+    fn capture<F: FnOnce() -> String>(&self, f: F) {
+        if self.key {
+            *self.track.key.borrow_mut() = Some(f());
+        }
+    }
+}
+
+macro_rules! forward_deserialize {
+    ($($method:ident($($arg:ident: $ty:ty),*) => $hint:expr,)*) => {$(
+        fn $method<V>(self, $($arg: $ty,)* visitor: V) -> Result<V::Value, D::Error>
+        where
+            V: Visitor<'de>,
+        {
+            let visitor = self.wrap(visitor);
+            let (chain, track) = (self.chain, self.track);
+            self.inner
+                .$method($($arg,)* visitor)
+                .map_err(|e| track.fail(chain, $hint, e))
+        }
+    )*};
+}
+
+impl<'a, 'de, D: Deserializer<'de>> Deserializer<'de> for Wrap<'a, D> {
+    type Error = D::Error;
+
+    forward_deserialize! {
+        deserialize_any() => None,
+        deserialize_bool() => Some("bool"),
+        deserialize_i8() => Some("i8"),
+        deserialize_i16() => Some("i16"),
+        deserialize_i32() => Some("i32"),
+        deserialize_i64() => Some("i64"),
+        deserialize_i128() => Some("i128"),
+        deserialize_u8() => Some("u8"),
+        deserialize_u16() => Some("u16"),
+        deserialize_u32() => Some("u32"),
+        deserialize_u64() => Some("u64"),
+        deserialize_u128() => Some("u128"),
+        deserialize_f32() => Some("f32"),
+        deserialize_f64() => Some("f64"),
+        deserialize_char() => Some("char"),
+        deserialize_str() => Some("string"),
+        deserialize_string() => Some("string"),
+        deserialize_bytes() => Some("bytes"),
+        deserialize_byte_buf() => Some("bytes"),
+        deserialize_option() => None,
+        deserialize_unit() => Some("unit"),
+        deserialize_unit_struct(name: &'static str) => None,
+        deserialize_newtype_struct(name: &'static str) => None,
+        deserialize_seq() => Some("sequence"),
+        deserialize_tuple(len: usize) => Some("tuple"),
+        deserialize_tuple_struct(name: &'static str, len: usize) => Some("tuple"),
+        deserialize_map() => Some("map"),
+        deserialize_struct(
+            name: &'static str,
+            fields: &'static [&'static str]
+        ) => None,
+        deserialize_enum(
+            name: &'static str,
+            variants: &'static [&'static str]
+        ) => None,
+        deserialize_identifier() => None,
+        deserialize_ignored_any() => None,
+    }
+
+    fn is_human_readable(&self) -> bool {
+        self.inner.is_human_readable()
+    }
+}
+
+impl<'a, 'de, T: DeserializeSeed<'de>> DeserializeSeed<'de> for Wrap<'a, T> {
+    type Value = T::Value;
+
+    fn deserialize<D>(self, deserializer: D) -> Result<T::Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let de = self.wrap(deserializer);
+        self.inner.deserialize(de)
+    }
+}
+
+/// Display adapter for visitor's expectation
+struct Expecting<'b, V>(&'b V);
+
+impl<'b, 'de, V: Visitor<'de>> fmt::Display for Expecting<'b, V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.expecting(f)
+    }
+}
+
+macro_rules! forward_visit {
+    ($($method:ident($v:ident: $ty:ty) => $key:expr,)*) => {$(
+        fn $method<E>(self, $v: $ty) -> Result<V::Value, E>
+        where
+            E: serde::de::Error,
+        {
+            self.capture(|| $key);
+            self.inner.$method($v)
+        }
+    )*};
+}
+
+impl<'a, 'de, V: Visitor<'de>> Visitor<'de> for Wrap<'a, V> {
+    type Value = V::Value;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let expected = Expecting(&self.inner).to_string();
+        f.write_str(&expected)?;
+        self.track.expecting(expected);
+        Ok(())
+    }
+
+    forward_visit! {
+        visit_bool(v: bool) => v.to_string(),
+        visit_i8(v: i8) => v.to_string(),
+        visit_i16(v: i16) => v.to_string(),
+        visit_i32(v: i32) => v.to_string(),
+        visit_i64(v: i64) => v.to_string(),
+        visit_i128(v: i128) => v.to_string(),
+        visit_u8(v: u8) => v.to_string(),
+        visit_u16(v: u16) => v.to_string(),
+        visit_u32(v: u32) => v.to_string(),
+        visit_u64(v: u64) => v.to_string(),
+        visit_u128(v: u128) => v.to_string(),
+        visit_f32(v: f32) => v.to_string(),
+        visit_f64(v: f64) => v.to_string(),
+        visit_char(v: char) => v.to_string(),
+        visit_str(v: &str) => v.to_string(),
+        visit_borrowed_str(v: &'de str) => v.to_string(),
+        visit_string(v: String) => v.clone(),
+        visit_bytes(v: &[u8]) => String::from_utf8_lossy(v).into_owned(),
+        visit_borrowed_bytes(v: &'de [u8]) => String::from_utf8_lossy(v).into_owned(),
+        visit_byte_buf(v: Vec<u8>) => String::from_utf8_lossy(&v).into_owned(),
+    }
+
+    fn visit_none<E>(self) -> Result<V::Value, E>
+    where
+        E: serde::de::Error,
+    {
+        self.inner.visit_none()
+    }
+
+    fn visit_unit<E>(self) -> Result<V::Value, E>
+    where
+        E: serde::de::Error,
+    {
+        self.inner.visit_unit()
+    }
+
+    fn visit_some<D>(self, deserializer: D) -> Result<V::Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let de = self.wrap(deserializer);
+        self.inner.visit_some(de)
+    }
+
+    fn visit_newtype_struct<D>(self, deserializer: D) -> Result<V::Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let de = self.wrap(deserializer);
+        self.inner.visit_newtype_struct(de)
+    }
+
+    fn visit_seq<A>(self, seq: A) -> Result<V::Value, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        self.inner.visit_seq(WrapSeq {
+            seq,
+            chain: self.chain,
+            track: self.track,
+            index: 0,
+        })
+    }
+
+    fn visit_map<A>(self, map: A) -> Result<V::Value, A::Error>
+    where
+        A: MapAccess<'de>,
+    {
+        self.inner.visit_map(WrapMap {
+            map,
+            chain: self.chain,
+            track: self.track,
+        })
+    }
+
+    fn visit_enum<A>(self, data: A) -> Result<V::Value, A::Error>
+    where
+        A: EnumAccess<'de>,
+    {
+        self.inner.visit_enum(data)
+    }
+}
+
+struct WrapSeq<'a, S> {
+    seq: S,
+    chain: &'a Chain<'a>,
+    track: &'a Track,
+    index: usize,
+}
+
+impl<'a, 'de, S: SeqAccess<'de>> SeqAccess<'de> for WrapSeq<'a, S> {
+    type Error = S::Error;
+
+    fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>, S::Error>
+    where
+        T: DeserializeSeed<'de>,
+    {
+        let chain = Chain::Seq {
+            parent: self.chain,
+            index: self.index,
+        };
+        self.index += 1;
+
+        let track = self.track;
+        self.seq
+            .next_element_seed(Wrap::new(seed, &chain, track))
+            .map_err(|e| track.fail(&chain, None, e))
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        self.seq.size_hint()
+    }
+}
+
+struct WrapMap<'a, M> {
+    map: M,
+    chain: &'a Chain<'a>,
+    track: &'a Track,
+}
+
+impl<'a, 'de, M: MapAccess<'de>> MapAccess<'de> for WrapMap<'a, M> {
+    type Error = M::Error;
+
+    fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>, M::Error>
+    where
+        K: DeserializeSeed<'de>,
+    {
+        let mut seed = Wrap::new(seed, self.chain, self.track);
+        seed.key = true;
+        self.map.next_key_seed(seed)
+    }
+
+    fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value, M::Error>
+    where
+        V: DeserializeSeed<'de>,
+    {
+        let key = self
+            .track
+            .key
+            .borrow_mut()
+            .take()
+            .unwrap_or_else(|| "?".to_string());
+        let chain = Chain::Map {
+            parent: self.chain,
+            key,
+        };
+
+        let track = self.track;
+        self.map
+            .next_value_seed(Wrap::new(seed, &chain, track))
+            .map_err(|e| track.fail(&chain, None, e))
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        self.map.size_hint()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use serde::Deserialize;
+
+    use super::*;
+
+    #[derive(Deserialize, Debug)]
+    struct Item {
+        id: u32,
+        #[allow(dead_code)]
+        tags: Vec<String>,
+    }
+
+    #[derive(Deserialize, Debug)]
+    struct Order {
+        #[allow(dead_code)]
+        name: String,
+        items: Vec<Item>,
+        #[allow(dead_code)]
+        meta: Option<HashMap<String, bool>>,
+    }
+
+    #[derive(Deserialize, Debug)]
+    struct Params {
+        #[allow(dead_code)]
+        name: String,
+        #[allow(dead_code)]
+        age: u8,
+    }
+
+    #[test]
+    fn test_json() {
+        let order: Order =
+            from_json(br#"{"name":"a","items":[{"id":1,"tags":[]}]}"#).unwrap();
+        assert_eq!(order.items[0].id, 1);
+
+        let err = from_json::<Order>(
+            br#"{"name":"a","items":[{"id":1,"tags":[]},{"id":"2","tags":[]}]}"#,
+        )
+        .unwrap_err();
+        assert_eq!(err.path(), "items[1].id");
+        assert_eq!(err.expected(), Some("u32"));
+        assert!(err.message().starts_with("invalid type: string \"2\""));
+
+        let err = from_json::<Order>(br#"{"name":"a","items":[{"id":1,"tags":[1]}]}"#)
+            .unwrap_err();
+        assert_eq!(err.path(), "items[0].tags[0]");
+        assert_eq!(err.expected(), Some("a string"));
+
+        let err =
+            from_json::<Order>(br#"{"name":"a","items":[],"meta":{"x":true,"y":"1"}}"#)
+                .unwrap_err();
+        assert_eq!(err.path(), "meta.y");
+        assert_eq!(err.expected(), Some("a boolean"));
+
+        let err = from_json::<Order>(br#"{"name":"a","items":[{"id":1}]}"#).unwrap_err();
+        assert_eq!(err.path(), "items[0]");
+        assert!(err.message().starts_with("missing field `tags`"));
+
+        let err = from_json::<Order>(br#"{"name":"a","items":[]} x"#).unwrap_err();
+        assert_eq!(err.path(), "");
+        assert_eq!(err.expected(), None);
+    }
+
+    #[test]
+    fn test_urlencoded() {
+        let p: Params = from_urlencoded(b"name=john&age=32").unwrap();
+        assert_eq!(p.age, 32);
+
+        let err = from_urlencoded::<Params>(b"name=john&age=old").unwrap_err();
+        assert_eq!(err.path(), "age");
+        assert_eq!(err.expected(), Some("u8"));
+        assert_eq!(err.to_string(), "age: invalid digit found in string");
+
+        let err = from_urlencoded::<Params>(b"age=32").unwrap_err();
+        assert_eq!(err.path(), "");
+        assert_eq!(err.message(), "missing field `name`");
+    }
+}
@@ -0,0 +1,231 @@
+//! Environment configuration extractor
+use std::ops::Deref;
+use std::sync::Arc;
+use std::{env, fmt};
+
+use futures::future::{err, ok, Ready};
+use serde::de::DeserializeOwned;
+
+use crate::http::Payload;
+use crate::web::error::{DataExtractorError, EnvError, ErrorRenderer};
+use crate::web::extract::FromRequest;
+use crate::web::httprequest::HttpRequest;
+
+use super::data::DataRequirement;
+
+/// Configuration loaded from environment variables.
+///
+/// Variables with specified prefix are collected, prefix is stripped and
+/// the rest of the name is lower-cased, so `APP_DATABASE_URL` variable
+/// becomes `database_url` field. Values are parsed the same way as query
+/// parameters. Configuration should be loaded once at startup and stored
+/// as application data with `App::app_data()`, loading errors are
+/// reported before server starts. Nested structures are not supported.
+///
+/// If configuration is not stored in application data, using `Env<T>`
+/// extractor would cause *Internal Server Error* response.
+///
+/// ```rust
+/// use ntex::web::{self, types::Env, App, HttpResponse};
+///
+/// #[derive(serde::Deserialize)]
+/// struct Settings {
+///     database_url: String,
+///     #[serde(default)]
+///     pool_size: usize,
+/// }
+///
+/// async fn index(settings: Env<Settings>) -> HttpResponse {
+///     HttpResponse::Ok().body(format!("pool size: {}", settings.pool_size))
+/// }
+///
+/// fn main() {
+///     std::env::set_var("APP_DATABASE_URL", "postgres://localhost/db");
+///     let settings = Env::<Settings>::from_env("APP_").unwrap();
+///
+///     let app = App::new()
+///         .app_data(settings.clone())
+///         .service(web::resource("/").to(index));
+/// }
+/// ```
+pub struct Env<T>(Arc<T>);
+
+impl<T: DeserializeOwned> Env<T> {
+    /// Load configuration from environment variables with prefix.
+    pub fn from_env(prefix: &str) -> Result<Env<T>, EnvError> {
+        Env::from_vars(
+            prefix,
+            env::vars_os().filter_map(|(key, val)| {
+                Some((key.into_string().ok()?, val.into_string().ok()?))
+            }),
+        )
+    }
+
+    /// Load configuration from provided variables with prefix.
+    pub fn from_vars<I>(prefix: &str, vars: I) -> Result<Env<T>, EnvError>
+    where
+        I: IntoIterator<Item = (String, String)>,
+    {
+        let vars: Vec<_> = vars
+            .into_iter()
+            .filter(|(key, _)| key.len() > prefix.len() && key.starts_with(prefix))
+            .map(|(key, val)| (key[prefix.len()..].to_lowercase(), val))
+            .collect();
+
+        let encoded = serde_urlencoded::to_string(&vars)
+            .map_err(|e| EnvError::new(prefix, serde::de::Error::custom(e)))?;
+        serde_urlencoded::from_str(&encoded)
+            .map(|cfg| Env(Arc::new(cfg)))
+            .map_err(|e| EnvError::new(prefix, e))
+    }
+}
+
+impl<T> Env<T> {
+    /// Get reference to inner configuration.
+    pub fn get_ref(&self) -> &T {
+        self.0.as_ref()
+    }
+
+    /// Convert to the internal Arc<T>
+    pub fn into_inner(self) -> Arc<T> {
+        self.0
+    }
+}
+
+impl<T> Deref for Env<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        self.0.as_ref()
+    }
+}
+
+impl<T> Clone for Env<T> {
+    fn clone(&self) -> Env<T> {
+        Env(self.0.clone())
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for Env<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Env").field(&self.0).finish()
+    }
+}
+
+impl<T: 'static, E: ErrorRenderer> FromRequest<E> for Env<T> {
+    type Error = DataExtractorError;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    #[inline]
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        if let Some(cfg) = req.app_data::<Env<T>>() {
+            ok(cfg.clone())
+        } else {
+            log::debug!(
+                "Failed to construct Env extractor. Request path: {:?}",
+                req.path()
+            );
+            err(DataExtractorError::NotConfigured)
+        }
+    }
+
+    fn required_data(data: &mut Vec<DataRequirement>) {
+        data.push(DataRequirement::of::<Env<T>>());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::Deserialize;
+
+    use super::*;
+    use crate::http::StatusCode;
+    use crate::web::test::{call_service, init_service, TestRequest};
+    use crate::web::{self, App, HttpResponse};
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Settings {
+        database_url: String,
+        pool_size: usize,
+        #[serde(default)]
+        debug: bool,
+    }
+
+    fn vars(vars: &[(&str, &str)]) -> Vec<(String, String)> {
+        vars.iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_from_vars() {
+        let cfg = Env::<Settings>::from_vars(
+            "APP_",
+            vars(&[
+                ("APP_DATABASE_URL", "postgres://localhost/db?a=b&c"),
+                ("APP_POOL_SIZE", "10"),
+                ("APP_", "empty"),
+                ("OTHER_DEBUG", "true"),
+            ]),
+        )
+        .unwrap();
+        assert_eq!(
+            *cfg,
+            Settings {
+                database_url: "postgres://localhost/db?a=b&c".to_string(),
+                pool_size: 10,
+                debug: false,
+            }
+        );
+
+        let e = Env::<Settings>::from_vars("APP_", vars(&[("APP_POOL_SIZE", "10")]))
+            .unwrap_err();
+        assert_eq!(e.prefix(), "APP_");
+        assert!(e.to_string().contains("database_url"));
+
+        let e = Env::<Settings>::from_vars(
+            "APP_",
+            vars(&[("APP_DATABASE_URL", "url"), ("APP_POOL_SIZE", "many")]),
+        );
+        assert!(e.is_err());
+    }
+
+    #[test]
+    fn test_from_env() {
+        env::set_var("NTEX_ENV_TEST_DATABASE_URL", "url");
+        env::set_var("NTEX_ENV_TEST_POOL_SIZE", "5");
+        env::set_var("NTEX_ENV_TEST_DEBUG", "true");
+        let cfg = Env::<Settings>::from_env("NTEX_ENV_TEST_").unwrap();
+        assert_eq!(cfg.database_url, "url");
+        assert_eq!(cfg.pool_size, 5);
+        assert!(cfg.debug);
+    }
+
+    #[ntex_rt::test]
+    async fn test_extractor() {
+        let cfg = Env::<Settings>::from_vars(
+            "",
+            vars(&[("database_url", "url"), ("pool_size", "1")]),
+        )
+        .unwrap();
+
+        let srv = init_service(App::new().app_data(cfg).service(web::resource("/").to(
+            |cfg: Env<Settings>| async move {
+                assert_eq!(cfg.pool_size, 1);
+                HttpResponse::Ok()
+            },
+        )))
+        .await;
+        let req = TestRequest::default().to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let srv = init_service(App::new().service(
+            web::resource("/").to(|_: Env<Settings>| async { HttpResponse::Ok() }),
+        ))
+        .await;
+        let req = TestRequest::default().to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::INTERNAL_SERVER_ERROR);
+    }
+}
@@ -1,5 +1,6 @@
 //! Form extractor
 
+use std::borrow::Cow;
 use std::future::Future;
 use std::pin::Pin;
 use std::task::{Context, Poll};
@@ -9,16 +10,22 @@ use bytes::BytesMut;
 use encoding_rs::{Encoding, UTF_8};
 use futures::future::{err, ok, FutureExt, LocalBoxFuture, Ready};
 use futures::StreamExt;
+use percent_encoding::percent_decode;
 use serde::de::DeserializeOwned;
 use serde::Serialize;
+use url::form_urlencoded;
 
 #[cfg(feature = "compress")]
 use crate::http::encoding::Decoder;
 use crate::http::header::{CONTENT_LENGTH, CONTENT_TYPE};
 use crate::http::{HttpMessage, Payload, Response, StatusCode};
 use crate::web::error::{ErrorRenderer, UrlencodedError};
+use crate::web::middleware::memory::{account_body, MemoryUsage};
 use crate::web::{FromRequest, HttpRequest, Responder};
 
+use super::de::from_urlencoded;
+use super::nested::from_pairs;
+
 /// Form data helper (`application/x-www-form-urlencoded`)
 ///
 /// Can be use to extract url-encoded data from the request body,
@@ -113,19 +120,74 @@ where
 
     #[inline]
     fn from_request(req: &HttpRequest, payload: &mut Payload) -> Self::Future {
-        let limit = req
-            .app_data::<FormConfig>()
-            .map(|c| c.limit)
-            .unwrap_or(16384);
+        let cfg = req.app_data::<FormConfig>().cloned().unwrap_or_default();
 
         UrlEncoded::new(req, payload)
-            .limit(limit)
+            .limit(cfg.limit)
+            .nested(cfg.nested)
+            .encoding(cfg.encoding)
             .map(move |res| match res {
                 Err(e) => Err(e),
                 Ok(item) => Ok(Form(item)),
             })
             .boxed_local()
     }
+
+    fn accepts_content_type(req: &HttpRequest) -> Option<bool> {
+        Some(is_urlencoded(req))
+    }
+}
+
+fn is_urlencoded(req: &HttpRequest) -> bool {
+    req.content_type()
+        .eq_ignore_ascii_case("application/x-www-form-urlencoded")
+}
+
+/// Charset of the content type
+fn charset(req: &HttpRequest) -> Result<Option<&'static Encoding>, UrlencodedError> {
+    let mt = req.mime_type().map_err(|_| UrlencodedError::ContentType)?;
+    match mt.as_ref().and_then(|mt| mt.get_param(mime::CHARSET)) {
+        Some(charset) => Encoding::for_label_no_replacement(charset.as_str().as_bytes())
+            .map(Some)
+            .ok_or(UrlencodedError::ContentType),
+        None => Ok(None),
+    }
+}
+
+/// Charset from `_charset_` form field
+fn charset_field(body: &[u8]) -> Option<&'static Encoding> {
+    body.split(|b| *b == b'&')
+        .find(|pair| pair.starts_with(b"_charset_="))
+        .and_then(|pair| Encoding::for_label_no_replacement(&pair[10..]))
+}
+
+/// Split urlencoded data to decoded key-value pairs
+fn parse_pairs(
+    body: &[u8],
+    encoding: &'static Encoding,
+) -> Result<Vec<(String, String)>, UrlencodedError> {
+    body.split(|b| *b == b'&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let (key, value) = match pair.iter().position(|b| *b == b'=') {
+                Some(idx) => (&pair[..idx], &pair[idx + 1..]),
+                None => (pair, &b""[..]),
+            };
+            Ok((decode(key, encoding)?, decode(value, encoding)?))
+        })
+        .collect()
+}
+
+fn decode(s: &[u8], encoding: &'static Encoding) -> Result<String, UrlencodedError> {
+    let s: Vec<u8> = s
+        .iter()
+        .map(|b| if *b == b'+' { b' ' } else { *b })
+        .collect();
+    let bytes: Cow<'_, [u8]> = percent_decode(&s).into();
+    encoding
+        .decode_without_bom_handling_and_without_replacement(&bytes)
+        .map(|s| s.into_owned())
+        .ok_or(UrlencodedError::Parse)
 }
 
 impl<T: fmt::Debug> fmt::Debug for Form<T> {
@@ -184,9 +246,44 @@ impl<T: Serialize, Err: ErrorRenderer> Responder<Err> for Form<T> {
 ///     );
 /// }
 /// ```
+///
+/// ## Nested keys
+///
+/// With nested keys enabled bracketed keys are decoded to nested
+/// structures, for example `items[0][name]=x&tags[]=a&tags[]=b`.
+///
+/// ```rust
+/// use ntex::web::{self, App};
+/// use serde_derive::Deserialize;
+///
+/// #[derive(Deserialize)]
+/// struct Item {
+///     name: String,
+/// }
+///
+/// #[derive(Deserialize)]
+/// struct Order {
+///     items: Vec<Item>,
+///     tags: Vec<String>,
+/// }
+///
+/// async fn index(form: web::types::Form<Order>) -> String {
+///     format!("{} items", form.items.len())
+/// }
+///
+/// fn main() {
+///     let app = App::new().service(
+///         web::resource("/order")
+///             .app_data(web::types::FormConfig::default().nested(true))
+///             .route(web::post().to(index))
+///     );
+/// }
+/// ```
 #[derive(Clone)]
 pub struct FormConfig {
     limit: usize,
+    nested: bool,
+    encoding: &'static Encoding,
 }
 
 impl FormConfig {
@@ -195,11 +292,30 @@ impl FormConfig {
         self.limit = limit;
         self
     }
+
+    /// Decode bracketed keys to nested structures. By default disabled
+    pub fn nested(mut self, nested: bool) -> Self {
+        self.nested = nested;
+        self
+    }
+
+    /// Set default charset of form data.
+    ///
+    /// Default charset is used if content type does not specify charset
+    /// and form does not contain `_charset_` field. By default utf-8.
+    pub fn encoding(mut self, encoding: &'static Encoding) -> Self {
+        self.encoding = encoding;
+        self
+    }
 }
 
 impl Default for FormConfig {
     fn default() -> Self {
-        FormConfig { limit: 16384 }
+        FormConfig {
+            limit: 16384,
+            nested: false,
+            encoding: UTF_8,
+        }
     }
 }
 
@@ -221,8 +337,11 @@ struct UrlEncoded<U> {
     stream: Option<Payload>,
     limit: usize,
     length: Option<usize>,
+    charset: Option<&'static Encoding>,
     encoding: &'static Encoding,
+    nested: bool,
     err: Option<UrlencodedError>,
+    usage: Option<MemoryUsage>,
     fut: Option<LocalBoxFuture<'static, Result<U, UrlencodedError>>>,
 }
 
@@ -230,12 +349,12 @@ impl<U> UrlEncoded<U> {
     /// Create a new future to URL encode a request
     fn new(req: &HttpRequest, payload: &mut Payload) -> UrlEncoded<U> {
         // check content type
-        if req.content_type().to_lowercase() != "application/x-www-form-urlencoded" {
+        if !is_urlencoded(req) {
             return Self::err(UrlencodedError::ContentType);
         }
-        let encoding = match req.encoding() {
-            Ok(enc) => enc,
-            Err(_) => return Self::err(UrlencodedError::ContentType),
+        let charset = match charset(req) {
+            Ok(charset) => charset,
+            Err(e) => return Self::err(e),
         };
 
         let mut len = None;
@@ -257,10 +376,13 @@ impl<U> UrlEncoded<U> {
         let payload = payload.take();
 
         UrlEncoded {
-            encoding,
+            charset,
+            encoding: UTF_8,
+            nested: false,
             stream: Some(payload),
             limit: 32_768,
             length: len,
+            usage: req.extensions().get::<MemoryUsage>().cloned(),
             fut: None,
             err: None,
         }
@@ -273,7 +395,10 @@ impl<U> UrlEncoded<U> {
             fut: None,
             err: Some(e),
             length: None,
+            usage: None,
+            charset: None,
             encoding: UTF_8,
+            nested: false,
         }
     }
 
@@ -282,6 +407,18 @@ impl<U> UrlEncoded<U> {
         self.limit = limit;
         self
     }
+
+    /// Decode bracketed keys to nested structures
+    fn nested(mut self, nested: bool) -> Self {
+        self.nested = nested;
+        self
+    }
+
+    /// Set default charset
+    fn encoding(mut self, encoding: &'static Encoding) -> Self {
+        self.encoding = encoding;
+        self
+    }
 }
 
 impl<U> Future for UrlEncoded<U>
@@ -308,7 +445,8 @@ where
         }
 
         // future
-        let encoding = self.encoding;
+        let (charset, encoding, nested) = (self.charset, self.encoding, self.nested);
+        let usage = self.usage.take();
         let mut stream = self.stream.take().unwrap();
 
         self.fut = Some(
@@ -322,21 +460,30 @@ where
                             size: body.len() + chunk.len(),
                             limit,
                         });
+                    } else if let Err(e) = account_body(&usage, chunk.len()) {
+                        return Err(UrlencodedError::Overflow {
+                            size: e.requested,
+                            limit: e.limit,
+                        });
                     } else {
                         body.extend_from_slice(&chunk);
                     }
                 }
 
-                if encoding == UTF_8 {
-                    serde_urlencoded::from_bytes::<U>(&body)
-                        .map_err(|_| UrlencodedError::Parse)
+                let encoding =
+                    charset.or_else(|| charset_field(&body)).unwrap_or(encoding);
+                if encoding == UTF_8 && !nested {
+                    return Ok(from_urlencoded::<U>(&body)?);
+                }
+
+                let pairs = parse_pairs(&body, encoding)?;
+                if nested {
+                    Ok(from_pairs::<U>(pairs)?)
                 } else {
-                    let body = encoding
-                        .decode_without_bom_handling_and_without_replacement(&body)
-                        .map(|s| s.into_owned())
-                        .ok_or(UrlencodedError::Parse)?;
-                    serde_urlencoded::from_str::<U>(&body)
-                        .map_err(|_| UrlencodedError::Parse)
+                    let body = form_urlencoded::Serializer::new(String::new())
+                        .extend_pairs(pairs)
+                        .finish();
+                    Ok(from_urlencoded::<U>(body.as_bytes())?)
                 }
             }
             .boxed_local(),
@@ -457,6 +604,95 @@ mod tests {
         );
     }
 
+    #[ntex_rt::test]
+    async fn test_form_nested() {
+        #[derive(Deserialize, Debug, PartialEq)]
+        struct Order {
+            items: Vec<Info>,
+            tags: Vec<String>,
+        }
+
+        let (req, mut pl) =
+            TestRequest::with_header(CONTENT_TYPE, "application/x-www-form-urlencoded")
+                .data(FormConfig::default().nested(true))
+                .set_payload(Bytes::from_static(
+                    b"items[0][hello]=a+b&items[0][counter]=1&tags[]=x&tags[]=y%20z",
+                ))
+                .to_http_parts();
+
+        let Form(s) = from_request::<Form<Order>>(&req, &mut pl).await.unwrap();
+        assert_eq!(
+            s,
+            Order {
+                items: vec![Info {
+                    hello: "a b".into(),
+                    counter: 1
+                }],
+                tags: vec!["x".into(), "y z".into()],
+            }
+        );
+
+        let (req, mut pl) =
+            TestRequest::with_header(CONTENT_TYPE, "application/x-www-form-urlencoded")
+                .data(FormConfig::default().nested(true))
+                .set_payload(Bytes::from_static(
+                    b"items[0][hello]=a&items[0][counter]=x&tags[]=x",
+                ))
+                .to_http_parts();
+
+        let err = from_request::<Form<Order>>(&req, &mut pl)
+            .await
+            .unwrap_err();
+        assert!(format!("{}", err).contains("items[0].counter"));
+    }
+
+    #[ntex_rt::test]
+    async fn test_urlencoded_charset() {
+        // windows-1251 encoded "привет"
+        let (req, mut pl) = TestRequest::with_header(
+            CONTENT_TYPE,
+            "application/x-www-form-urlencoded; charset=windows-1251",
+        )
+        .set_payload(Bytes::from_static(b"hello=%EF%F0%E8%E2%E5%F2&counter=1"))
+        .to_http_parts();
+
+        let info = UrlEncoded::<Info>::new(&req, &mut pl).await.unwrap();
+        assert_eq!(info.hello, "привет");
+
+        // charset from `_charset_` field
+        let (req, mut pl) =
+            TestRequest::with_header(CONTENT_TYPE, "application/x-www-form-urlencoded")
+                .set_payload(Bytes::from_static(
+                    b"_charset_=windows-1251&hello=%EF%F0%E8%E2%E5%F2&counter=1",
+                ))
+                .to_http_parts();
+
+        let info = UrlEncoded::<Info>::new(&req, &mut pl).await.unwrap();
+        assert_eq!(info.hello, "привет");
+
+        // default charset
+        let (req, mut pl) =
+            TestRequest::with_header(CONTENT_TYPE, "application/x-www-form-urlencoded")
+                .set_payload(Bytes::from_static(b"hello=%EF%F0%E8%E2%E5%F2&counter=1"))
+                .to_http_parts();
+
+        let info = UrlEncoded::<Info>::new(&req, &mut pl)
+            .encoding(encoding_rs::WINDOWS_1251)
+            .await
+            .unwrap();
+        assert_eq!(info.hello, "привет");
+
+        let (req, mut pl) = TestRequest::with_header(
+            CONTENT_TYPE,
+            "application/x-www-form-urlencoded; charset=unknown",
+        )
+        .set_payload(Bytes::from_static(b"hello=world&counter=1"))
+        .to_http_parts();
+
+        let info = UrlEncoded::<Info>::new(&req, &mut pl).await;
+        assert!(matches!(info.err().unwrap(), UrlencodedError::ContentType));
+    }
+
     #[ntex_rt::test]
     async fn test_responder() {
         let req = TestRequest::default().to_http_request();
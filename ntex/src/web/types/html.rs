@@ -0,0 +1,260 @@
+use std::error::Error;
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use bytes::{Bytes, BytesMut};
+use futures::future::{ok, ready, FutureExt, LocalBoxFuture, Ready};
+use futures::stream::{FuturesOrdered, Stream};
+
+use crate::http::body::{Body, BodySize, MessageBody};
+use crate::http::header::CONTENT_TYPE;
+use crate::http::{Response, StatusCode};
+use crate::web::error::ErrorRenderer;
+use crate::web::httprequest::HttpRequest;
+use crate::web::responder::Responder;
+
+/// Streaming html responder.
+///
+/// Document is built from static parts and async parts. Response is
+/// sent immediately, parts are streamed to the peer in document order as
+/// soon as they resolve, so head of the document could be rendered by
+/// the browser while the rest of the page is loading. Async parts are
+/// resolved concurrently.
+///
+/// Consecutive ready parts are coalesced into single chunk, use `flush()`
+/// to force chunk boundary. If async part fails, response stream is
+/// terminated and connection is closed.
+///
+/// ```rust
+/// use ntex::web::types::HtmlStream;
+///
+/// async fn load_items() -> Result<String, std::io::Error> {
+///     Ok("<li>item</li>".to_string())
+/// }
+///
+/// async fn index() -> HtmlStream {
+///     HtmlStream::new()
+///         .push("<html><head><title>Items</title></head><body>")
+///         .flush()
+///         .push_async(async {
+///             let items = load_items().await?;
+///             Ok::<_, std::io::Error>(format!("<ul>{}</ul>", items))
+///         })
+///         .push("</body></html>")
+/// }
+/// # fn main() {}
+/// ```
+pub struct HtmlStream {
+    status: StatusCode,
+    parts: Vec<LocalBoxFuture<'static, Result<Part, Box<dyn Error>>>>,
+}
+
+enum Part {
+    Data(Bytes),
+    Flush,
+}
+
+impl Default for HtmlStream {
+    fn default() -> Self {
+        HtmlStream::new()
+    }
+}
+
+impl HtmlStream {
+    /// Create empty html stream
+    pub fn new() -> Self {
+        HtmlStream {
+            status: StatusCode::OK,
+            parts: Vec::new(),
+        }
+    }
+
+    /// Set response status code
+    pub fn status(mut self, status: StatusCode) -> Self {
+        self.status = status;
+        self
+    }
+
+    /// Append static part of the document
+    pub fn push<T: Into<Bytes>>(mut self, data: T) -> Self {
+        self.parts
+            .push(ready(Ok(Part::Data(data.into()))).boxed_local());
+        self
+    }
+
+    /// Append part of the document that resolves asynchronously
+    pub fn push_async<F, T, E>(mut self, fut: F) -> Self
+    where
+        F: Future<Output = Result<T, E>> + 'static,
+        T: Into<Bytes>,
+        E: Into<Box<dyn Error>>,
+    {
+        self.parts.push(
+            fut.map(|res| res.map(|data| Part::Data(data.into())).map_err(Into::into))
+                .boxed_local(),
+        );
+        self
+    }
+
+    /// Send all preceding parts to the peer as separate chunk
+    pub fn flush(mut self) -> Self {
+        self.parts.push(ready(Ok(Part::Flush)).boxed_local());
+        self
+    }
+}
+
+impl fmt::Debug for HtmlStream {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("HtmlStream")
+            .field("status", &self.status)
+            .field("parts", &self.parts.len())
+            .finish()
+    }
+}
+
+impl<Err: ErrorRenderer> Responder<Err> for HtmlStream {
+    type Error = Err::Container;
+    type Future = Ready<Result<Response, Self::Error>>;
+
+    fn respond_to(self, _: &HttpRequest) -> Self::Future {
+        ok(Response::build(self.status)
+            .header(CONTENT_TYPE, "text/html; charset=utf-8")
+            .body(Body::from_message(HtmlBody {
+                parts: self.parts.into_iter().collect(),
+                error: None,
+            })))
+    }
+}
+
+struct HtmlBody {
+    parts: FuturesOrdered<LocalBoxFuture<'static, Result<Part, Box<dyn Error>>>>,
+    error: Option<Box<dyn Error>>,
+}
+
+impl MessageBody for HtmlBody {
+    fn size(&self) -> BodySize {
+        BodySize::Stream
+    }
+
+    fn poll_next_chunk(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Bytes, Box<dyn Error>>>> {
+        if let Some(err) = self.error.take() {
+            return Poll::Ready(Some(Err(err)));
+        }
+
+        let mut buf = BytesMut::new();
+        loop {
+            match Pin::new(&mut self.parts).poll_next(cx) {
+                Poll::Ready(Some(Ok(Part::Data(data)))) => buf.extend_from_slice(&data),
+                Poll::Ready(Some(Ok(Part::Flush))) => {
+                    if !buf.is_empty() {
+                        break;
+                    }
+                }
+                Poll::Ready(Some(Err(err))) => {
+                    if buf.is_empty() {
+                        return Poll::Ready(Some(Err(err)));
+                    }
+                    self.error = Some(err);
+                    break;
+                }
+                Poll::Ready(None) | Poll::Pending => {
+                    if buf.is_empty() {
+                        return if self.parts.is_empty() {
+                            Poll::Ready(None)
+                        } else {
+                            Poll::Pending
+                        };
+                    }
+                    break;
+                }
+            }
+        }
+        Poll::Ready(Some(Ok(buf.freeze())))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+
+    use futures::future::{lazy, poll_fn};
+
+    use super::*;
+    use crate::channel::oneshot;
+    use crate::web::test::{respond_to, TestRequest};
+
+    #[ntex_rt::test]
+    async fn test_html_stream() {
+        let (tx1, rx1) = oneshot::channel::<&'static str>();
+        let (tx2, rx2) = oneshot::channel::<&'static str>();
+
+        let html = HtmlStream::new()
+            .push("<html>")
+            .push("<body>")
+            .flush()
+            .push("<p>")
+            .push_async(rx1)
+            .push_async(rx2)
+            .push("</body></html>");
+
+        let req = TestRequest::default().to_http_request();
+        let mut resp = respond_to(html, &req).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(
+            resp.headers().get(CONTENT_TYPE).unwrap(),
+            "text/html; charset=utf-8"
+        );
+        let mut body = resp.take_body();
+        assert_eq!(body.size(), BodySize::Stream);
+
+        match lazy(|cx| body.poll_next_chunk(cx)).await {
+            Poll::Ready(Some(Ok(chunk))) => assert_eq!(chunk, "<html><body>"),
+            _ => panic!(),
+        }
+        match lazy(|cx| body.poll_next_chunk(cx)).await {
+            Poll::Ready(Some(Ok(chunk))) => assert_eq!(chunk, "<p>"),
+            _ => panic!(),
+        }
+        assert!(lazy(|cx| body.poll_next_chunk(cx)).await.is_pending());
+
+        // parts are streamed in document order
+        let _ = tx2.send("2");
+        assert!(lazy(|cx| body.poll_next_chunk(cx)).await.is_pending());
+        let _ = tx1.send("1");
+        match lazy(|cx| body.poll_next_chunk(cx)).await {
+            Poll::Ready(Some(Ok(chunk))) => assert_eq!(chunk, "12</body></html>"),
+            _ => panic!(),
+        }
+        assert!(matches!(
+            lazy(|cx| body.poll_next_chunk(cx)).await,
+            Poll::Ready(None)
+        ));
+    }
+
+    #[ntex_rt::test]
+    async fn test_html_stream_error() {
+        let html = HtmlStream::new().push("<html>").push_async(async {
+            Err::<&'static str, _>(io::Error::from(io::ErrorKind::Other))
+        });
+
+        let req = TestRequest::default().to_http_request();
+        let mut resp = respond_to(html, &req).await.unwrap();
+        let mut body = resp.take_body();
+        assert_eq!(
+            poll_fn(|cx| body.poll_next_chunk(cx))
+                .await
+                .unwrap()
+                .unwrap(),
+            "<html>"
+        );
+        assert!(poll_fn(|cx| body.poll_next_chunk(cx))
+            .await
+            .unwrap()
+            .is_err());
+    }
+}
@@ -1,24 +1,29 @@
 //! Json extractor/responder
 
+use std::fmt::{self, Write};
 use std::future::Future;
+use std::ops;
 use std::pin::Pin;
 use std::sync::Arc;
 use std::task::{Context, Poll};
-use std::{fmt, ops};
 
 use bytes::BytesMut;
 use futures::future::{err, ok, FutureExt, LocalBoxFuture, Ready};
 use futures::StreamExt;
 use serde::de::DeserializeOwned;
 use serde::Serialize;
+use sha2::{Digest, Sha256};
 
 #[cfg(feature = "compress")]
 use crate::http::encoding::Decoder;
-use crate::http::header::CONTENT_LENGTH;
-use crate::http::{HttpMessage, Payload, Response, StatusCode};
+use crate::http::header::{CONTENT_LENGTH, ETAG, IF_NONE_MATCH};
+use crate::http::{HttpMessage, Method, Payload, Response, StatusCode};
 use crate::web::error::{ErrorRenderer, JsonError, JsonPayloadError};
+use crate::web::middleware::memory::{account_body, MemoryUsage};
 use crate::web::{FromRequest, HttpRequest, Responder};
 
+use super::de::from_json;
+
 /// Json helper
 ///
 /// Json can be used for two different purpose. First is for json response
@@ -133,6 +138,105 @@ impl<T: Serialize, Err: ErrorRenderer> Responder<Err> for Json<T> {
     }
 }
 
+/// Json responder with `ETag` support.
+///
+/// `CachedJson` serializes the value, computes a strong `ETag` from
+/// the serialized body and responds with `304 Not Modified` and empty body
+/// if the request's `If-None-Match` header matches the tag. Useful for
+/// endpoints that get polled frequently and rarely change.
+///
+/// ```rust
+/// use ntex::web;
+/// use serde_derive::Serialize;
+///
+/// #[derive(Serialize)]
+/// struct Stats {
+///     requests: u64,
+/// }
+///
+/// async fn index() -> web::types::CachedJson<Stats> {
+///     web::types::CachedJson(Stats { requests: 100 })
+/// }
+/// # fn main() {}
+/// ```
+pub struct CachedJson<T>(pub T);
+
+impl<T> CachedJson<T> {
+    /// Deconstruct to an inner value
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T> ops::Deref for CachedJson<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T> ops::DerefMut for CachedJson<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.0
+    }
+}
+
+impl<T> fmt::Debug for CachedJson<T>
+where
+    T: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "CachedJson: {:?}", self.0)
+    }
+}
+
+impl<T> From<Json<T>> for CachedJson<T> {
+    fn from(json: Json<T>) -> Self {
+        CachedJson(json.0)
+    }
+}
+
+impl<T: Serialize, Err: ErrorRenderer> Responder<Err> for CachedJson<T> {
+    type Error = JsonError;
+    type Future = Ready<Result<Response, Self::Error>>;
+
+    fn respond_to(self, req: &HttpRequest) -> Self::Future {
+        let body = match serde_json::to_string(&self.0) {
+            Ok(body) => body,
+            Err(e) => return err(e),
+        };
+
+        let digest = Sha256::digest(body.as_bytes());
+        let mut etag = String::with_capacity(34);
+        etag.push('"');
+        for b in &digest[..16] {
+            let _ = write!(etag, "{:02x}", b);
+        }
+        etag.push('"');
+
+        let not_modified = (req.method() == Method::GET || req.method() == Method::HEAD)
+            && req
+                .headers()
+                .get_all(IF_NONE_MATCH)
+                .filter_map(|hdr| hdr.to_str().ok())
+                .flat_map(|hdr| hdr.split(','))
+                .map(|tag| tag.trim())
+                .any(|tag| tag == "*" || tag.trim_start_matches("W/") == etag.as_str());
+
+        if not_modified {
+            ok(Response::build(StatusCode::NOT_MODIFIED)
+                .header(ETAG, etag)
+                .finish())
+        } else {
+            ok(Response::build(StatusCode::OK)
+                .header(ETAG, etag)
+                .content_type("application/json")
+                .body(body))
+        }
+    }
+}
+
 /// Json extractor. Allow to extract typed information from request's
 /// payload.
 ///
@@ -195,6 +299,27 @@ where
             })
             .boxed_local()
     }
+
+    fn accepts_content_type(req: &HttpRequest) -> Option<bool> {
+        let ctype = req
+            .app_data::<JsonConfig>()
+            .and_then(|c| c.content_type.clone());
+        Some(is_json(req, &ctype))
+    }
+}
+
+/// Check request content type, custom predicate is used for non-json types
+fn is_json(
+    req: &HttpRequest,
+    ctype: &Option<Arc<dyn Fn(mime::Mime) -> bool + Send + Sync>>,
+) -> bool {
+    if let Ok(Some(mime)) = req.mime_type() {
+        mime.subtype() == mime::JSON
+            || mime.suffix() == Some(mime::JSON)
+            || ctype.as_ref().map_or(false, |predicate| predicate(mime))
+    } else {
+        false
+    }
 }
 
 /// Json extractor configuration
@@ -276,6 +401,7 @@ struct JsonBody<U> {
     #[cfg(not(feature = "compress"))]
     stream: Option<Payload>,
     err: Option<JsonPayloadError>,
+    usage: Option<MemoryUsage>,
     fut: Option<LocalBoxFuture<'static, Result<U, JsonPayloadError>>>,
 }
 
@@ -290,19 +416,12 @@ where
         ctype: Option<Arc<dyn Fn(mime::Mime) -> bool + Send + Sync>>,
     ) -> Self {
         // check content-type
-        let json = if let Ok(Some(mime)) = req.mime_type() {
-            mime.subtype() == mime::JSON
-                || mime.suffix() == Some(mime::JSON)
-                || ctype.as_ref().map_or(false, |predicate| predicate(mime))
-        } else {
-            false
-        };
-
-        if !json {
+        if !is_json(req, &ctype) {
             return JsonBody {
                 limit: 262_144,
                 length: None,
                 stream: None,
+                usage: None,
                 fut: None,
                 err: Some(JsonPayloadError::ContentType),
             };
@@ -323,6 +442,7 @@ where
             limit: 262_144,
             length: len,
             stream: Some(payload),
+            usage: req.extensions().get::<MemoryUsage>().cloned(),
             fut: None,
             err: None,
         }
@@ -356,6 +476,7 @@ where
                 return Poll::Ready(Err(JsonPayloadError::Overflow));
             }
         }
+        let usage = self.usage.take();
         let mut stream = self.stream.take().unwrap();
 
         self.fut = Some(
@@ -364,13 +485,15 @@ where
 
                 while let Some(item) = stream.next().await {
                     let chunk = item?;
-                    if (body.len() + chunk.len()) > limit {
+                    if (body.len() + chunk.len()) > limit
+                        || account_body(&usage, chunk.len()).is_err()
+                    {
                         return Err(JsonPayloadError::Overflow);
                     } else {
                         body.extend_from_slice(&chunk);
                     }
                 }
-                Ok(serde_json::from_slice::<U>(&body)?)
+                Ok(from_json::<U>(&body)?)
             }
             .boxed_local(),
         );
@@ -385,6 +508,7 @@ mod tests {
     use serde_derive::{Deserialize, Serialize};
 
     use super::*;
+    use crate::http::body::{BodySize, MessageBody};
     use crate::http::header;
     use crate::web::test::{from_request, respond_to, TestRequest};
 
@@ -424,6 +548,53 @@ mod tests {
         assert_eq!(resp.body().bin_ref(), b"{\"name\":\"test\"}");
     }
 
+    #[ntex_rt::test]
+    async fn test_cached_responder() {
+        let req = TestRequest::default().to_http_request();
+        let j = CachedJson(MyObject {
+            name: "test".to_string(),
+        });
+        let resp = respond_to(j, &req).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(
+            resp.headers().get(header::CONTENT_TYPE).unwrap(),
+            header::HeaderValue::from_static("application/json")
+        );
+        let etag = resp.headers().get(header::ETAG).unwrap().clone();
+        assert!(etag.to_str().unwrap().starts_with('"'));
+        assert!(!etag.to_str().unwrap().starts_with("W/"));
+        assert_eq!(resp.body().bin_ref(), b"{\"name\":\"test\"}");
+
+        let req = TestRequest::default()
+            .header(header::IF_NONE_MATCH, etag.clone())
+            .to_http_request();
+        let j = CachedJson(MyObject {
+            name: "test".to_string(),
+        });
+        let resp = respond_to(j, &req).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::NOT_MODIFIED);
+        assert_eq!(resp.headers().get(header::ETAG).unwrap(), &etag);
+        assert_eq!(resp.body().size(), BodySize::Empty);
+
+        let req = TestRequest::default()
+            .header(header::IF_NONE_MATCH, "\"other\"")
+            .to_http_request();
+        let j = CachedJson(MyObject {
+            name: "test".to_string(),
+        });
+        let resp = respond_to(j, &req).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let req = TestRequest::post()
+            .header(header::IF_NONE_MATCH, etag)
+            .to_http_request();
+        let j = CachedJson(MyObject {
+            name: "test".to_string(),
+        });
+        let resp = respond_to(j, &req).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+    }
+
     #[ntex_rt::test]
     async fn test_extract() {
         let (req, mut pl) = TestRequest::default()
@@ -1,15 +1,37 @@
 //! Extractor types
 
+pub(in crate::web) mod auth;
+#[cfg(feature = "checksum")]
+mod checksum;
+#[cfg(feature = "jwt")]
+pub(in crate::web) mod claims;
 pub(in crate::web) mod data;
+mod de;
+mod env;
 pub(in crate::web) mod form;
+mod html;
 pub(in crate::web) mod json;
+mod multipart;
+mod nested;
+mod pagination;
 mod path;
 pub(in crate::web) mod payload;
 mod query;
+pub(in crate::web) mod ranged;
 
-pub use self::data::Data;
+pub use self::auth::{AuthConfig, AuthCredentials, BasicAuth, BearerAuth};
+#[cfg(feature = "checksum")]
+pub use self::checksum::{Checksum, ChecksumAlgorithm, ChecksumConfig};
+#[cfg(feature = "jwt")]
+pub use self::claims::Claims;
+pub use self::data::{Data, DataRequirement};
+pub use self::env::Env;
 pub use self::form::{Form, FormConfig};
-pub use self::json::{Json, JsonConfig};
-pub use self::path::Path;
+pub use self::html::HtmlStream;
+pub use self::json::{CachedJson, Json, JsonConfig};
+pub use self::multipart::{Field, Multipart, MultipartConfig};
+pub use self::pagination::{Paginated, Pagination, PaginationConfig};
+pub use self::path::{FromParam, FromParams, Params, Path, PathConfig};
 pub use self::payload::{Payload, PayloadConfig};
 pub use self::query::Query;
+pub use self::ranged::RangedStream;
@@ -0,0 +1,769 @@
+//! Multipart payload extractor
+use std::cell::RefCell;
+use std::convert::TryFrom;
+use std::pin::Pin;
+use std::rc::Rc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::task::{Context, Poll};
+use std::{fmt, io};
+
+use bytes::{Buf, Bytes, BytesMut};
+use futures::future::{err, ok, Ready};
+use futures::{ready, Stream};
+use mime::Mime;
+use percent_encoding::percent_decode_str;
+
+#[cfg(feature = "compress")]
+use crate::http::encoding::Decoder;
+use crate::http::header::{self, HeaderMap, HeaderName, HeaderValue};
+use crate::http::{HttpMessage, Payload};
+use crate::web::error::{ErrorRenderer, MultipartError};
+use crate::web::multipart::{
+    upload_stream, FsSink, UploadError, UploadMeta, UploadSink, UploadedFile,
+};
+use crate::web::{FromRequest, HttpRequest};
+
+const MAX_HEADERS: usize = 32;
+const MAX_HEADERS_SIZE: usize = 8192;
+
+/// Multipart payload extractor (`multipart/form-data`)
+///
+/// Multipart is a stream of form fields. Field data is not buffered,
+/// field is a stream of data chunks as they arrive from the client.
+/// Fields must be read in order, unread data of the current field
+/// is skipped when next field is requested.
+///
+/// [**MultipartConfig**](struct.MultipartConfig.html) allows to configure
+/// size limits.
+///
+/// ```rust
+/// use futures::StreamExt;
+/// use ntex::web::{self, error, types, HttpResponse};
+///
+/// async fn index(mut form: types::Multipart) -> Result<HttpResponse, error::Error> {
+///     let mut files = Vec::new();
+///     while let Some(field) = form.next().await {
+///         let field = field?;
+///         if field.filename().is_some() {
+///             let file = field
+///                 .save_temp()
+///                 .await
+///                 .map_err(|e| error::ErrorBadRequest(e.to_string()))?;
+///             files.push(file);
+///         }
+///     }
+///     Ok(HttpResponse::Ok().body(format!("uploaded {} files", files.len())))
+/// }
+/// # fn main() {}
+/// ```
+pub struct Multipart {
+    inner: Rc<RefCell<Inner>>,
+}
+
+impl Multipart {
+    /// Create multipart stream for request's payload
+    pub fn new(
+        req: &HttpRequest,
+        payload: &mut Payload,
+    ) -> Result<Self, MultipartError> {
+        let boundary = boundary(req)?;
+        let config = req
+            .app_data::<MultipartConfig>()
+            .cloned()
+            .unwrap_or_default();
+
+        #[cfg(feature = "compress")]
+        let payload = Decoder::from_headers(payload.take(), req.headers());
+        #[cfg(not(feature = "compress"))]
+        let payload = payload.take();
+
+        Ok(Multipart {
+            inner: Rc::new(RefCell::new(Inner {
+                config,
+                stream: payload,
+                buf: BytesMut::new(),
+                boundary: Bytes::from(format!("--{}", boundary)),
+                delimiter: Bytes::from(format!("\r\n--{}", boundary)),
+                state: State::Preamble,
+                fields: 0,
+            })),
+        })
+    }
+}
+
+impl<Err: ErrorRenderer> FromRequest<Err> for Multipart {
+    type Error = MultipartError;
+    type Future = Ready<Result<Multipart, MultipartError>>;
+
+    #[inline]
+    fn from_request(req: &HttpRequest, payload: &mut Payload) -> Self::Future {
+        match Multipart::new(req, payload) {
+            Ok(mp) => ok(mp),
+            Err(e) => err(e),
+        }
+    }
+
+    fn accepts_content_type(req: &HttpRequest) -> Option<bool> {
+        Some(is_multipart(req))
+    }
+}
+
+impl Stream for Multipart {
+    type Item = Result<Field, MultipartError>;
+
+    fn poll_next(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        let mut inner = this.inner.borrow_mut();
+
+        match ready!(inner.poll_field(cx)) {
+            Ok(Some(headers)) => {
+                let index = inner.fields;
+                drop(inner);
+
+                let res = Field::new(this.inner.clone(), index, headers);
+                if res.is_err() {
+                    this.inner.borrow_mut().state = State::Failed;
+                }
+                Poll::Ready(Some(res))
+            }
+            Ok(None) => Poll::Ready(None),
+            Err(e) => {
+                inner.state = State::Failed;
+                Poll::Ready(Some(Err(e)))
+            }
+        }
+    }
+}
+
+impl fmt::Debug for Multipart {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Multipart")
+            .field("fields", &self.inner.borrow().fields)
+            .finish()
+    }
+}
+
+/// Multipart form field
+///
+/// Field is a stream of data chunks. Field size is limited by
+/// [**MultipartConfig**](struct.MultipartConfig.html), file parts
+/// and other fields have separate limits.
+pub struct Field {
+    inner: Rc<RefCell<Inner>>,
+    index: usize,
+    headers: HeaderMap,
+    name: String,
+    filename: Option<String>,
+    content_type: Option<Mime>,
+    size: u64,
+    limit: u64,
+}
+
+impl Field {
+    fn new(
+        inner: Rc<RefCell<Inner>>,
+        index: usize,
+        headers: HeaderMap,
+    ) -> Result<Self, MultipartError> {
+        let (name, filename) = headers
+            .get(header::CONTENT_DISPOSITION)
+            .and_then(|v| v.to_str().ok())
+            .and_then(content_disposition)
+            .ok_or(MultipartError::Parse)?;
+        let content_type = match headers.get(header::CONTENT_TYPE) {
+            Some(v) => Some(
+                v.to_str()
+                    .ok()
+                    .and_then(|v| v.parse().ok())
+                    .ok_or(MultipartError::Parse)?,
+            ),
+            None => None,
+        };
+        let limit = {
+            let config = &inner.borrow().config;
+            if filename.is_some() {
+                config.file_limit
+            } else {
+                config.field_limit
+            }
+        };
+
+        Ok(Field {
+            inner,
+            index,
+            headers,
+            name,
+            filename,
+            content_type,
+            limit,
+            size: 0,
+        })
+    }
+
+    /// Part headers
+    pub fn headers(&self) -> &HeaderMap {
+        &self.headers
+    }
+
+    /// Name of the form field
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// File name provided by the client, only file parts have file name
+    pub fn filename(&self) -> Option<&str> {
+        self.filename.as_deref()
+    }
+
+    /// Content type of the part, if provided by the client
+    pub fn content_type(&self) -> Option<&Mime> {
+        self.content_type.as_ref()
+    }
+
+    /// Upload metadata of the field
+    pub fn meta(&self) -> UploadMeta {
+        UploadMeta::new(
+            Some(self.name.clone()),
+            self.filename.clone(),
+            self.content_type.clone(),
+        )
+    }
+
+    /// Stream field data to the sink
+    pub async fn upload<T>(self, sink: T) -> Result<T::Output, UploadError<T::Error>>
+    where
+        T: UploadSink,
+    {
+        let meta = self.meta();
+        upload_stream(self, meta, sink, None).await
+    }
+
+    /// Store field data to a file in temporary directory.
+    ///
+    /// File is not removed automatically, it is up to caller
+    /// to move or remove the file.
+    pub async fn save_temp(self) -> Result<UploadedFile, UploadError<io::Error>> {
+        static COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+        let path = std::env::temp_dir().join(format!(
+            "ntex-multipart-{}-{}",
+            std::process::id(),
+            COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+        self.upload(FsSink::new(path)).await
+    }
+}
+
+impl Stream for Field {
+    type Item = Result<Bytes, MultipartError>;
+
+    fn poll_next(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        let mut inner = this.inner.borrow_mut();
+
+        // field data is already consumed
+        if inner.state != State::Data(this.index) {
+            return Poll::Ready(None);
+        }
+
+        match ready!(inner.poll_data(cx)) {
+            Ok(Some(chunk)) => {
+                this.size += chunk.len() as u64;
+                if this.size > this.limit {
+                    inner.state = State::Failed;
+                    Poll::Ready(Some(Err(MultipartError::Overflow(this.limit))))
+                } else {
+                    Poll::Ready(Some(Ok(chunk)))
+                }
+            }
+            Ok(None) => Poll::Ready(None),
+            Err(e) => {
+                inner.state = State::Failed;
+                Poll::Ready(Some(Err(e)))
+            }
+        }
+    }
+}
+
+impl fmt::Debug for Field {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Field")
+            .field("name", &self.name)
+            .field("filename", &self.filename)
+            .field("content_type", &self.content_type)
+            .field("headers", &self.headers)
+            .finish()
+    }
+}
+
+/// Multipart extractor configuration
+///
+/// ```rust
+/// use ntex::web::{self, types, App, HttpResponse};
+///
+/// async fn index(form: types::Multipart) -> HttpResponse {
+///     HttpResponse::Ok().finish()
+/// }
+///
+/// fn main() {
+///     let app = App::new().service(
+///         web::resource("/upload")
+///             .app_data(
+///                 types::MultipartConfig::default()
+///                     .file_limit(100 * 1024 * 1024)
+///                     .max_fields(16),
+///             )
+///             .route(web::post().to(index)),
+///     );
+/// }
+/// ```
+#[derive(Clone)]
+pub struct MultipartConfig {
+    field_limit: u64,
+    file_limit: u64,
+    max_fields: usize,
+}
+
+impl MultipartConfig {
+    /// Change max size of non-file field. By default max size is 64Kb
+    pub fn field_limit(mut self, limit: u64) -> Self {
+        self.field_limit = limit;
+        self
+    }
+
+    /// Change max size of file part. By default max size is 10Mb
+    pub fn file_limit(mut self, limit: u64) -> Self {
+        self.file_limit = limit;
+        self
+    }
+
+    /// Change max number of fields. By default 128 fields are allowed
+    pub fn max_fields(mut self, num: usize) -> Self {
+        self.max_fields = num;
+        self
+    }
+}
+
+impl Default for MultipartConfig {
+    fn default() -> Self {
+        MultipartConfig {
+            field_limit: 65_536,
+            file_limit: 10 * 1024 * 1024,
+            max_fields: 128,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum State {
+    /// Data before first boundary
+    Preamble,
+    /// Boundary is consumed, next is either part headers or end of stream
+    Boundary,
+    Headers,
+    /// Data of the field with specified index
+    Data(usize),
+    Eof,
+    Failed,
+}
+
+struct Inner {
+    #[cfg(feature = "compress")]
+    stream: Decoder<Payload>,
+    #[cfg(not(feature = "compress"))]
+    stream: Payload,
+    buf: BytesMut,
+    boundary: Bytes,
+    delimiter: Bytes,
+    state: State,
+    fields: usize,
+    config: MultipartConfig,
+}
+
+impl Inner {
+    /// Read next chunk of payload into buffer
+    fn poll_fill(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), MultipartError>> {
+        match ready!(Pin::new(&mut self.stream).poll_next(cx)) {
+            Some(Ok(chunk)) => {
+                self.buf.extend_from_slice(&chunk);
+                Poll::Ready(Ok(()))
+            }
+            Some(Err(e)) => Poll::Ready(Err(e.into())),
+            None => Poll::Ready(Err(MultipartError::Incomplete)),
+        }
+    }
+
+    /// Read next chunk of current field's data
+    fn poll_data(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<Option<Bytes>, MultipartError>> {
+        loop {
+            if let Some(idx) = find(&self.buf, &self.delimiter) {
+                return if idx > 0 {
+                    Poll::Ready(Ok(Some(self.buf.split_to(idx).freeze())))
+                } else {
+                    self.buf.advance(self.delimiter.len());
+                    self.state = State::Boundary;
+                    Poll::Ready(Ok(None))
+                };
+            }
+
+            // tail of the buffer could be a part of delimiter
+            let len = self.buf.len();
+            if len > self.delimiter.len() {
+                let chunk = self.buf.split_to(len - self.delimiter.len());
+                return Poll::Ready(Ok(Some(chunk.freeze())));
+            }
+            ready!(self.poll_fill(cx))?;
+        }
+    }
+
+    /// Read headers of next field
+    fn poll_field(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<Option<HeaderMap>, MultipartError>> {
+        loop {
+            match self.state {
+                State::Eof | State::Failed => return Poll::Ready(Ok(None)),
+                State::Data(_) => {
+                    // skip unread data of current field
+                    ready!(self.poll_data(cx))?;
+                }
+                State::Preamble => {
+                    if let Some(idx) = find(&self.buf, &self.boundary) {
+                        self.buf.advance(idx + self.boundary.len());
+                        self.state = State::Boundary;
+                    } else {
+                        let len = self.buf.len();
+                        if len > self.boundary.len() {
+                            self.buf.advance(len - self.boundary.len());
+                        }
+                        ready!(self.poll_fill(cx))?;
+                    }
+                }
+                State::Boundary => {
+                    if self.buf.starts_with(b" ") || self.buf.starts_with(b"\t") {
+                        // transport padding
+                        self.buf.advance(1);
+                    } else if self.buf.starts_with(b"--") {
+                        self.state = State::Eof;
+                        return Poll::Ready(Ok(None));
+                    } else if self.buf.starts_with(b"\r\n") {
+                        self.buf.advance(2);
+                        self.state = State::Headers;
+                    } else if self.buf.len() >= 2 {
+                        return Poll::Ready(Err(MultipartError::Parse));
+                    } else {
+                        ready!(self.poll_fill(cx))?;
+                    }
+                }
+                State::Headers => {
+                    if let Some(headers) = self.parse_headers()? {
+                        self.fields += 1;
+                        if self.fields > self.config.max_fields {
+                            return Poll::Ready(Err(MultipartError::TooManyFields(
+                                self.config.max_fields,
+                            )));
+                        }
+                        self.state = State::Data(self.fields);
+                        return Poll::Ready(Ok(Some(headers)));
+                    }
+                    ready!(self.poll_fill(cx))?;
+                }
+            }
+        }
+    }
+
+    fn parse_headers(&mut self) -> Result<Option<HeaderMap>, MultipartError> {
+        let mut parsed = [httparse::EMPTY_HEADER; MAX_HEADERS];
+
+        match httparse::parse_headers(&self.buf, &mut parsed) {
+            Ok(httparse::Status::Complete((len, parsed))) => {
+                let mut headers = HeaderMap::new();
+                for h in parsed {
+                    let name = HeaderName::try_from(h.name)
+                        .map_err(|_| MultipartError::Parse)?;
+                    let value = HeaderValue::try_from(h.value)
+                        .map_err(|_| MultipartError::Parse)?;
+                    headers.append(name, value);
+                }
+                self.buf.advance(len);
+                Ok(Some(headers))
+            }
+            Ok(httparse::Status::Partial) => {
+                if self.buf.len() > MAX_HEADERS_SIZE {
+                    Err(MultipartError::Parse)
+                } else {
+                    Ok(None)
+                }
+            }
+            Err(_) => Err(MultipartError::Parse),
+        }
+    }
+}
+
+fn find(buf: &[u8], pattern: &[u8]) -> Option<usize> {
+    buf.windows(pattern.len()).position(|w| w == pattern)
+}
+
+fn is_multipart(req: &HttpRequest) -> bool {
+    req.content_type()
+        .eq_ignore_ascii_case("multipart/form-data")
+}
+
+fn boundary(req: &HttpRequest) -> Result<String, MultipartError> {
+    let mt = match req.mime_type() {
+        Ok(Some(mt)) => mt,
+        _ => return Err(MultipartError::ContentType),
+    };
+    if mt.type_() != mime::MULTIPART || mt.subtype() != mime::FORM_DATA {
+        return Err(MultipartError::ContentType);
+    }
+    match mt.get_param(mime::BOUNDARY) {
+        Some(b) if !b.as_str().is_empty() && b.as_str().len() <= 70 => {
+            Ok(b.as_str().to_string())
+        }
+        _ => Err(MultipartError::Boundary),
+    }
+}
+
+/// Parse `Content-Disposition` header of the part, returns
+/// field name and file name
+fn content_disposition(value: &str) -> Option<(String, Option<String>)> {
+    let end = value.find(';').unwrap_or(value.len());
+    if !value[..end].trim().eq_ignore_ascii_case("form-data") {
+        return None;
+    }
+
+    let mut rest = &value[end..];
+    let (mut name, mut filename, mut filename_ext) = (None, None, None);
+    while let Some((key, val, tail)) = next_param(rest) {
+        match key.as_str() {
+            "name" => name = Some(val),
+            "filename" => filename = Some(val),
+            "filename*" => filename_ext = ext_value(&val),
+            _ => (),
+        }
+        rest = tail;
+    }
+    name.map(|name| (name, filename_ext.or(filename)))
+}
+
+/// Parse next `key=value` parameter, value could be a quoted string
+fn next_param(s: &str) -> Option<(String, String, &str)> {
+    let s = s.trim_start_matches(|c: char| c == ';' || c.is_whitespace());
+    let eq = s.find('=')?;
+    let key = s[..eq].trim().to_ascii_lowercase();
+    let s = s[eq + 1..].trim_start();
+
+    if s.starts_with('"') {
+        let mut val = String::new();
+        let mut chars = s.char_indices().skip(1);
+        while let Some((idx, c)) = chars.next() {
+            match c {
+                '\\' => {
+                    if let Some((_, c)) = chars.next() {
+                        val.push(c);
+                    }
+                }
+                '"' => return Some((key, val, &s[idx + 1..])),
+                c => val.push(c),
+            }
+        }
+        None
+    } else {
+        let end = s.find(';').unwrap_or(s.len());
+        Some((key, s[..end].trim().to_string(), &s[end..]))
+    }
+}
+
+/// Decode RFC 5987 extended value, only utf-8 charset is supported
+fn ext_value(val: &str) -> Option<String> {
+    let mut parts = val.splitn(3, '\'');
+    let charset = parts.next()?;
+    let _lang = parts.next()?;
+    let value = parts.next()?;
+
+    if charset.eq_ignore_ascii_case("utf-8") {
+        percent_decode_str(value)
+            .decode_utf8()
+            .ok()
+            .map(|s| s.into_owned())
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::{stream, StreamExt};
+
+    use super::*;
+    use crate::http::error::PayloadError;
+    use crate::http::PayloadExt;
+    use crate::web::test::{from_request, TestRequest};
+
+    const BODY: &[u8] = b"preamble\r\n\
+        --abbc761f78ff4d7cb7573b5a23f96ef0\r\n\
+        Content-Disposition: form-data; name=\"text\"\r\n\
+        \r\n\
+        test value\r\n\
+        --abbc761f78ff4d7cb7573b5a23f96ef0\r\n\
+        Content-Disposition: form-data; name=\"file\"; filename=\"fn.txt\"\r\n\
+        Content-Type: text/plain; charset=utf-8\r\n\
+        \r\n\
+        data\r\n--abbc\r\n\
+        --abbc761f78ff4d7cb7573b5a23f96ef0--\r\n\
+        epilogue";
+
+    fn request(config: MultipartConfig) -> TestRequest {
+        TestRequest::default()
+            .header(
+                header::CONTENT_TYPE,
+                "multipart/form-data; boundary=\"abbc761f78ff4d7cb7573b5a23f96ef0\"",
+            )
+            .data(config)
+    }
+
+    async fn read(field: &mut Field) -> Result<Vec<u8>, MultipartError> {
+        let mut data = Vec::new();
+        while let Some(chunk) = field.next().await {
+            data.extend_from_slice(&chunk?);
+        }
+        Ok(data)
+    }
+
+    #[test]
+    fn test_content_disposition() {
+        assert_eq!(
+            content_disposition("form-data; name=\"a\\\"b\"; filename=c.txt"),
+            Some(("a\"b".to_string(), Some("c.txt".to_string())))
+        );
+        assert_eq!(
+            content_disposition(
+                "Form-Data; name=file; filename=\"a.txt\"; filename*=UTF-8''%E2%82%AC.txt"
+            ),
+            Some(("file".to_string(), Some("\u{20ac}.txt".to_string())))
+        );
+        assert_eq!(content_disposition("form-data; filename=a.txt"), None);
+        assert_eq!(content_disposition("attachment; name=a"), None);
+    }
+
+    #[ntex_rt::test]
+    async fn test_multipart() {
+        // payload split into small chunks
+        let chunks: Vec<Result<_, PayloadError>> = BODY
+            .chunks(7)
+            .map(|c| Ok(Bytes::copy_from_slice(c)))
+            .collect();
+        let (req, _) = request(MultipartConfig::default()).to_http_parts();
+        let mut pl = stream::iter(chunks).into_payload();
+        let mut form = from_request::<Multipart>(&req, &mut pl).await.unwrap();
+
+        let mut field = form.next().await.unwrap().unwrap();
+        assert_eq!(field.name(), "text");
+        assert_eq!(field.filename(), None);
+        assert_eq!(field.content_type(), None);
+        assert_eq!(read(&mut field).await.unwrap(), b"test value");
+
+        let mut field = form.next().await.unwrap().unwrap();
+        assert_eq!(field.name(), "file");
+        assert_eq!(field.filename(), Some("fn.txt"));
+        assert_eq!(
+            field.content_type().unwrap().as_ref(),
+            "text/plain; charset=utf-8"
+        );
+        assert_eq!(read(&mut field).await.unwrap(), b"data\r\n--abbc");
+
+        assert!(form.next().await.is_none());
+    }
+
+    #[ntex_rt::test]
+    async fn test_skip_field() {
+        let (req, mut pl) = request(MultipartConfig::default())
+            .set_payload(BODY)
+            .to_http_parts();
+        let mut form = from_request::<Multipart>(&req, &mut pl).await.unwrap();
+
+        let mut first = form.next().await.unwrap().unwrap();
+        let mut second = form.next().await.unwrap().unwrap();
+        assert!(first.next().await.is_none());
+        assert_eq!(second.name(), "file");
+        assert_eq!(read(&mut second).await.unwrap(), b"data\r\n--abbc");
+        assert!(form.next().await.is_none());
+    }
+
+    #[ntex_rt::test]
+    async fn test_errors() {
+        let (req, mut pl) = TestRequest::default()
+            .header(header::CONTENT_TYPE, "text/plain")
+            .to_http_parts();
+        let res = from_request::<Multipart>(&req, &mut pl).await;
+        assert!(matches!(res, Err(MultipartError::ContentType)));
+
+        let (req, mut pl) = TestRequest::default()
+            .header(header::CONTENT_TYPE, "multipart/form-data")
+            .to_http_parts();
+        let res = from_request::<Multipart>(&req, &mut pl).await;
+        assert!(matches!(res, Err(MultipartError::Boundary)));
+
+        // field overflow
+        let (req, mut pl) = request(MultipartConfig::default().field_limit(4))
+            .set_payload(BODY)
+            .to_http_parts();
+        let mut form = from_request::<Multipart>(&req, &mut pl).await.unwrap();
+        let mut field = form.next().await.unwrap().unwrap();
+        assert!(matches!(
+            read(&mut field).await,
+            Err(MultipartError::Overflow(4))
+        ));
+        assert!(form.next().await.is_none());
+
+        // too many fields
+        let (req, mut pl) = request(MultipartConfig::default().max_fields(1))
+            .set_payload(BODY)
+            .to_http_parts();
+        let mut form = from_request::<Multipart>(&req, &mut pl).await.unwrap();
+        assert!(form.next().await.unwrap().is_ok());
+        assert!(matches!(
+            form.next().await,
+            Some(Err(MultipartError::TooManyFields(1)))
+        ));
+
+        // incomplete payload
+        let (req, mut pl) = request(MultipartConfig::default())
+            .set_payload(&BODY[..100])
+            .to_http_parts();
+        let mut form = from_request::<Multipart>(&req, &mut pl).await.unwrap();
+        let mut field = form.next().await.unwrap().unwrap();
+        assert!(matches!(
+            read(&mut field).await,
+            Err(MultipartError::Incomplete)
+        ));
+    }
+
+    #[ntex_rt::test]
+    async fn test_save_temp() {
+        let (req, mut pl) = request(MultipartConfig::default())
+            .set_payload(BODY)
+            .to_http_parts();
+        let mut form = from_request::<Multipart>(&req, &mut pl).await.unwrap();
+
+        let _ = form.next().await.unwrap().unwrap();
+        let field = form.next().await.unwrap().unwrap();
+        let file = field.save_temp().await.unwrap();
+        assert_eq!(file.meta().name(), Some("file"));
+        assert_eq!(file.meta().filename(), Some("fn.txt"));
+        assert_eq!(file.meta().size(), 12);
+        assert_eq!(std::fs::read(file.path()).unwrap(), b"data\r\n--abbc");
+        std::fs::remove_file(file.path()).unwrap();
+    }
+}
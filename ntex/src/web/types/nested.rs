@@ -0,0 +1,388 @@
+//! Urlencoded data with nested keys, `items[0][name]=value`
+use std::fmt;
+
+use serde::de::value::{Error, MapDeserializer, SeqDeserializer};
+use serde::de::{
+    self, Deserialize, Deserializer, IntoDeserializer, Unexpected, Visitor,
+};
+
+use crate::web::error::FieldError;
+
+/// Max nesting level of keys
+const MAX_DEPTH: usize = 16;
+
+/// Deserialize decoded urlencoded pairs with nested keys.
+///
+/// Bracketed key segments build nested maps, empty segment appends value
+/// to a sequence, maps with numeric keys could be deserialized as
+/// sequences. Repeated plain keys build sequences as well.
+pub(super) fn from_pairs<T>(pairs: Vec<(String, String)>) -> Result<T, FieldError>
+where
+    T: for<'de> Deserialize<'de>,
+{
+    let mut root = Vec::new();
+    for (key, value) in pairs {
+        let segments = parse_key(&key);
+        if segments.len() > MAX_DEPTH {
+            return Err(FieldError::new(
+                key,
+                None,
+                "Key nesting is too deep".to_string(),
+            ));
+        }
+        if !insert(&mut root, &segments, value) {
+            return Err(FieldError::new(
+                key,
+                None,
+                "Conflicting values for key".to_string(),
+            ));
+        }
+    }
+    super::de::deserialize(Value::Map(root))
+}
+
+/// Split key to segments, `a[b][]` is split to `a`, `b` and empty segment.
+///
+/// Malformed keys are not split.
+fn parse_key(key: &str) -> Vec<&str> {
+    let start = match key.find('[') {
+        Some(idx) if idx > 0 && key.ends_with(']') => idx,
+        _ => return vec![key],
+    };
+
+    let mut segments = vec![&key[..start]];
+    let mut rest = &key[start..];
+    while !rest.is_empty() {
+        let end = match rest.find(']') {
+            Some(end) if rest.starts_with('[') => end,
+            _ => return vec![key],
+        };
+        segments.push(&rest[1..end]);
+        rest = &rest[end + 1..];
+    }
+    segments
+}
+
+/// Insert value to the tree, returns `false` if value conflicts
+/// with existing value
+fn insert(map: &mut Vec<(String, Value)>, segments: &[&str], value: String) -> bool {
+    let key = if segments[0].is_empty() {
+        map.len().to_string()
+    } else {
+        segments[0].to_string()
+    };
+    let pos = map.iter().position(|(k, _)| *k == key);
+
+    if segments.len() == 1 {
+        match pos.map(|idx| &mut map[idx].1) {
+            None => map.push((key, Value::Str(value))),
+            Some(Value::Seq(items)) => items.push(Value::Str(value)),
+            Some(item @ Value::Str(_)) => {
+                let prev = std::mem::replace(item, Value::Seq(Vec::new()));
+                *item = Value::Seq(vec![prev, Value::Str(value)]);
+            }
+            Some(Value::Map(_)) => return false,
+        }
+        true
+    } else {
+        let idx = match pos {
+            Some(idx) => idx,
+            None => {
+                map.push((key, Value::Map(Vec::new())));
+                map.len() - 1
+            }
+        };
+        match map[idx].1 {
+            Value::Map(ref mut map) => insert(map, &segments[1..], value),
+            _ => false,
+        }
+    }
+}
+
+/// Decoded value tree
+enum Value {
+    Str(String),
+    Seq(Vec<Value>),
+    Map(Vec<(String, Value)>),
+}
+
+impl Value {
+    fn unexpected(&self) -> Unexpected<'_> {
+        match self {
+            Value::Str(s) => Unexpected::Str(s),
+            Value::Seq(_) => Unexpected::Seq,
+            Value::Map(_) => Unexpected::Map,
+        }
+    }
+
+    /// Deserialize sequence, map keys must be indexes
+    fn visit_seq<'de, V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self {
+            Value::Seq(items) => {
+                visitor.visit_seq(SeqDeserializer::new(items.into_iter()))
+            }
+            Value::Map(items) => {
+                let mut indexed = Vec::with_capacity(items.len());
+                for (key, value) in items {
+                    match key.parse::<usize>() {
+                        Ok(idx) => indexed.push((idx, value)),
+                        Err(_) => {
+                            return Err(de::Error::invalid_value(
+                                Unexpected::Str(&key),
+                                &"sequence index",
+                            ))
+                        }
+                    }
+                }
+                indexed.sort_by_key(|(idx, _)| *idx);
+                visitor.visit_seq(SeqDeserializer::new(
+                    indexed.into_iter().map(|(_, value)| value),
+                ))
+            }
+            Value::Str(_) => {
+                visitor.visit_seq(SeqDeserializer::new(Some(self).into_iter()))
+            }
+        }
+    }
+}
+
+impl<'de> IntoDeserializer<'de, Error> for Value {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self {
+        self
+    }
+}
+
+macro_rules! deserialize_parse {
+    ($($method:ident => $visit:ident,)*) => {$(
+        fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+            match self {
+                Value::Str(s) => match s.parse() {
+                    Ok(val) => visitor.$visit(val),
+                    Err(_) => Err(de::Error::invalid_value(Unexpected::Str(&s), &visitor)),
+                },
+                _ => Err(de::Error::invalid_type(self.unexpected(), &visitor)),
+            }
+        }
+    )*};
+}
+
+impl<'de> Deserializer<'de> for Value {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self {
+            Value::Str(s) => visitor.visit_string(s),
+            Value::Seq(_) => self.visit_seq(visitor),
+            Value::Map(items) => {
+                visitor.visit_map(MapDeserializer::new(items.into_iter()))
+            }
+        }
+    }
+
+    fn deserialize_bool<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self {
+            Value::Str(ref s) if s == "true" || s == "on" => visitor.visit_bool(true),
+            Value::Str(ref s) if s == "false" || s == "off" => visitor.visit_bool(false),
+            Value::Str(ref s) => {
+                Err(de::Error::invalid_value(Unexpected::Str(s), &visitor))
+            }
+            _ => Err(de::Error::invalid_type(self.unexpected(), &visitor)),
+        }
+    }
+
+    deserialize_parse! {
+        deserialize_i8 => visit_i8,
+        deserialize_i16 => visit_i16,
+        deserialize_i32 => visit_i32,
+        deserialize_i64 => visit_i64,
+        deserialize_i128 => visit_i128,
+        deserialize_u8 => visit_u8,
+        deserialize_u16 => visit_u16,
+        deserialize_u32 => visit_u32,
+        deserialize_u64 => visit_u64,
+        deserialize_u128 => visit_u128,
+        deserialize_f32 => visit_f32,
+        deserialize_f64 => visit_f64,
+        deserialize_char => visit_char,
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_unit<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(
+        self,
+        _: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        self.visit_seq(visitor)
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(
+        self,
+        _: usize,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        self.visit_seq(visitor)
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(
+        self,
+        _: &'static str,
+        _: usize,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        self.visit_seq(visitor)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _: &'static str,
+        _: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        match self {
+            Value::Str(s) => visitor.visit_enum(s.into_deserializer()),
+            _ => Err(de::Error::invalid_type(self.unexpected(), &visitor)),
+        }
+    }
+
+    serde::forward_to_deserialize_any! {
+        str string bytes byte_buf map struct identifier ignored_any
+    }
+}
+
+impl fmt::Debug for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Str(s) => s.fmt(f),
+            Value::Seq(items) => f.debug_list().entries(items).finish(),
+            Value::Map(items) => f
+                .debug_map()
+                .entries(items.iter().map(|(k, v)| (k, v)))
+                .finish(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use serde::Deserialize;
+
+    use super::*;
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct Item {
+        name: String,
+        qty: u32,
+    }
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct Order {
+        id: u64,
+        items: Vec<Item>,
+        tags: Vec<String>,
+        flags: HashMap<String, bool>,
+        note: Option<String>,
+    }
+
+    fn pairs(s: &[(&str, &str)]) -> Vec<(String, String)> {
+        s.iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_parse_key() {
+        assert_eq!(parse_key("a"), vec!["a"]);
+        assert_eq!(parse_key("a[b][]"), vec!["a", "b", ""]);
+        assert_eq!(parse_key("a[b]c]"), vec!["a[b]c]"]);
+        assert_eq!(parse_key("[a]"), vec!["[a]"]);
+        assert_eq!(parse_key("a[b"), vec!["a[b"]);
+    }
+
+    #[test]
+    fn test_nested() {
+        let order: Order = from_pairs(pairs(&[
+            ("id", "1"),
+            ("items[1][name]", "b"),
+            ("items[1][qty]", "2"),
+            ("items[0][name]", "a"),
+            ("items[0][qty]", "1"),
+            ("tags[]", "x"),
+            ("tags[]", "y"),
+            ("flags[gift]", "on"),
+        ]))
+        .unwrap();
+        assert_eq!(
+            order,
+            Order {
+                id: 1,
+                items: vec![
+                    Item {
+                        name: "a".to_string(),
+                        qty: 1
+                    },
+                    Item {
+                        name: "b".to_string(),
+                        qty: 2
+                    }
+                ],
+                tags: vec!["x".to_string(), "y".to_string()],
+                flags: vec![("gift".to_string(), true)].into_iter().collect(),
+                note: None,
+            }
+        );
+
+        // repeated and single plain keys
+        #[derive(Deserialize, Debug, PartialEq)]
+        struct Tags {
+            tags: Vec<String>,
+        }
+        let tags: Tags = from_pairs(pairs(&[("tags", "x"), ("tags", "y")])).unwrap();
+        assert_eq!(tags.tags, vec!["x", "y"]);
+        let tags: Tags = from_pairs(pairs(&[("tags", "x")])).unwrap();
+        assert_eq!(tags.tags, vec!["x"]);
+    }
+
+    #[test]
+    fn test_errors() {
+        let err = from_pairs::<Order>(pairs(&[
+            ("id", "1"),
+            ("items[0][name]", "a"),
+            ("items[0][qty]", "x"),
+        ]))
+        .unwrap_err();
+        assert_eq!(err.path(), "items[0].qty");
+        assert_eq!(err.expected(), Some("u32"));
+
+        let err =
+            from_pairs::<Order>(pairs(&[("id", "1"), ("id[a]", "2")])).unwrap_err();
+        assert_eq!(err.path(), "id[a]");
+        assert_eq!(err.message(), "Conflicting values for key");
+
+        let key = "a".to_string() + &"[a]".repeat(MAX_DEPTH);
+        let err = from_pairs::<Order>(pairs(&[(&key, "1")])).unwrap_err();
+        assert_eq!(err.message(), "Key nesting is too deep");
+    }
+}
@@ -0,0 +1,359 @@
+//! Cursor pagination helpers
+use std::future::Future;
+use std::marker::PhantomData;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use futures::future::{err, ok, Ready};
+use futures::ready;
+use pin_project::pin_project;
+use serde::Deserialize;
+
+use crate::http::header::{HeaderValue, LINK};
+use crate::http::{Payload, Response};
+use crate::web::error::{ErrorRenderer, QueryPayloadError};
+use crate::web::{FromRequest, HttpRequest, Responder};
+
+use super::de::from_urlencoded;
+
+/// Cursor pagination parameters extractor.
+///
+/// Extracts `limit` and `cursor` parameters from the request's query.
+/// Limit is bounded by [**PaginationConfig**](struct.PaginationConfig.html),
+/// if `limit` parameter is missing default limit is used, limit that exceeds
+/// max limit is reduced to max limit.
+///
+/// ## Example
+///
+/// ```rust
+/// use ntex::web::{self, types::{Pagination, Paginated}};
+///
+/// async fn index(page: Pagination) -> Paginated<String> {
+///     let start: usize = page.cursor().and_then(|c| c.parse().ok()).unwrap_or(0);
+///     let items: Vec<_> = (start..start + page.limit()).map(|i| i.to_string()).collect();
+///
+///     Paginated::new(items.join(","), &page)
+///         .next((start + page.limit()).to_string())
+/// }
+///
+/// fn main() {
+///     let app = web::App::new().service(
+///         web::resource("/items").name("items").route(web::get().to(index))
+///     );
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Pagination {
+    limit: usize,
+    cursor: Option<String>,
+}
+
+impl Pagination {
+    /// Max number of items to return
+    pub fn limit(&self) -> usize {
+        self.limit
+    }
+
+    /// Opaque position of the page, `None` for the first page
+    pub fn cursor(&self) -> Option<&str> {
+        self.cursor.as_deref()
+    }
+}
+
+#[derive(Deserialize)]
+struct PaginationQuery {
+    limit: Option<usize>,
+    cursor: Option<String>,
+}
+
+impl<Err: ErrorRenderer> FromRequest<Err> for Pagination {
+    type Error = QueryPayloadError;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    #[inline]
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        let tmp;
+        let cfg = if let Some(cfg) = req.app_data::<PaginationConfig>() {
+            cfg
+        } else {
+            tmp = PaginationConfig::default();
+            &tmp
+        };
+
+        match from_urlencoded::<PaginationQuery>(req.query_string().as_bytes()) {
+            Ok(q) => ok(Pagination {
+                limit: q
+                    .limit
+                    .unwrap_or(cfg.default_limit)
+                    .max(1)
+                    .min(cfg.max_limit),
+                cursor: q.cursor.filter(|c| !c.is_empty()),
+            }),
+            Err(e) => {
+                log::debug!(
+                    "Failed during Pagination extractor deserialization. \
+                     Request path: {:?}",
+                    req.path()
+                );
+                err(QueryPayloadError::Deserialize(e))
+            }
+        }
+    }
+}
+
+/// Pagination extractor configuration
+#[derive(Clone)]
+pub struct PaginationConfig {
+    default_limit: usize,
+    max_limit: usize,
+}
+
+impl PaginationConfig {
+    /// Set default number of items per page. By default it is 20
+    pub fn default_limit(mut self, limit: usize) -> Self {
+        self.default_limit = limit.max(1);
+        self
+    }
+
+    /// Set max number of items per page. By default it is 100
+    pub fn max_limit(mut self, limit: usize) -> Self {
+        self.max_limit = limit.max(1);
+        self
+    }
+}
+
+impl Default for PaginationConfig {
+    fn default() -> Self {
+        PaginationConfig {
+            default_limit: 20,
+            max_limit: 100,
+        }
+    }
+}
+
+/// Paginated responder.
+///
+/// Wraps responder and adds RFC 8288 `Link` header with `next` and `prev`
+/// links. Links are generated with `HttpRequest::url_for()` for the named
+/// resource that matched the request, or for the resource set with
+/// `Paginated::resource()`. Query parameters of the request are preserved,
+/// `cursor` and `limit` parameters are replaced.
+pub struct Paginated<T, Err = crate::web::DefaultError> {
+    responder: T,
+    limit: usize,
+    next: Option<String>,
+    prev: Option<String>,
+    resource: Option<(String, Vec<String>)>,
+    _t: PhantomData<Err>,
+}
+
+impl<T, Err> Paginated<T, Err> {
+    /// Create paginated responder for current page
+    pub fn new(responder: T, page: &Pagination) -> Self {
+        Paginated {
+            responder,
+            limit: page.limit,
+            next: None,
+            prev: None,
+            resource: None,
+            _t: PhantomData,
+        }
+    }
+
+    /// Set cursor of the next page
+    pub fn next<S: Into<String>>(mut self, cursor: S) -> Self {
+        self.next = Some(cursor.into());
+        self
+    }
+
+    /// Set cursor of the previous page
+    pub fn prev<S: Into<String>>(mut self, cursor: S) -> Self {
+        self.prev = Some(cursor.into());
+        self
+    }
+
+    /// Use named resource for links generation
+    pub fn resource<U, I>(mut self, name: &str, elements: U) -> Self
+    where
+        U: IntoIterator<Item = I>,
+        I: AsRef<str>,
+    {
+        self.resource = Some((
+            name.to_string(),
+            elements
+                .into_iter()
+                .map(|s| s.as_ref().to_string())
+                .collect(),
+        ));
+        self
+    }
+
+    fn link(&self, req: &HttpRequest) -> Option<HeaderValue> {
+        if self.next.is_none() && self.prev.is_none() {
+            return None;
+        }
+
+        let url = if let Some((ref name, ref elements)) = self.resource {
+            req.url_for(name, elements)
+        } else if let Some(name) = req.match_name() {
+            req.url_for(name, req.match_info().iter().map(|(_, v)| v))
+        } else {
+            let info = req.connection_info();
+            url::Url::parse(&format!(
+                "{}://{}{}",
+                info.scheme(),
+                info.host(),
+                req.path()
+            ))
+            .map_err(|e| e.into())
+        };
+        let url = match url {
+            Ok(url) => url,
+            Err(e) => {
+                log::debug!("Cannot generate pagination link: {}", e);
+                return None;
+            }
+        };
+
+        let query: Vec<_> = url::form_urlencoded::parse(req.query_string().as_bytes())
+            .filter(|(k, _)| k != "cursor" && k != "limit")
+            .collect();
+        let limit = self.limit.to_string();
+        let link = |cursor: &str, rel: &str| {
+            let mut url = url.clone();
+            url.query_pairs_mut()
+                .clear()
+                .extend_pairs(query.iter())
+                .append_pair("cursor", cursor)
+                .append_pair("limit", &limit);
+            format!("<{}>; rel=\"{}\"", url, rel)
+        };
+
+        let mut links = Vec::new();
+        if let Some(ref cursor) = self.next {
+            links.push(link(cursor, "next"));
+        }
+        if let Some(ref cursor) = self.prev {
+            links.push(link(cursor, "prev"));
+        }
+        HeaderValue::from_str(&links.join(", ")).ok()
+    }
+}
+
+impl<T: Responder<Err>, Err: ErrorRenderer> Responder<Err> for Paginated<T, Err> {
+    type Error = T::Error;
+    type Future = PaginatedFut<T, Err>;
+
+    fn respond_to(self, req: &HttpRequest) -> Self::Future {
+        let link = self.link(req);
+        PaginatedFut {
+            fut: self.responder.respond_to(req),
+            link,
+        }
+    }
+}
+
+#[doc(hidden)]
+#[pin_project]
+pub struct PaginatedFut<T: Responder<Err>, Err> {
+    #[pin]
+    fut: T::Future,
+    link: Option<HeaderValue>,
+}
+
+impl<T: Responder<Err>, Err> Future for PaginatedFut<T, Err> {
+    type Output = Result<Response, T::Error>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+
+        let mut res = ready!(this.fut.poll(cx))?;
+        if let Some(link) = this.link.take() {
+            res.headers_mut().append(LINK, link);
+        }
+        Poll::Ready(Ok(res))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::StatusCode;
+    use crate::web::test::{
+        call_service, from_request, init_service, read_body, TestRequest,
+    };
+    use crate::web::{self, App};
+
+    #[ntex_rt::test]
+    async fn test_pagination_extract() {
+        let req = TestRequest::with_uri("/items").to_http_request();
+        let page = from_request::<Pagination>(&req, &mut Payload::None)
+            .await
+            .unwrap();
+        assert_eq!(page.limit(), 20);
+        assert_eq!(page.cursor(), None);
+
+        let req = TestRequest::with_uri("/items?limit=1000&cursor=abc")
+            .data(PaginationConfig::default().max_limit(50))
+            .to_http_request();
+        let page = from_request::<Pagination>(&req, &mut Payload::None)
+            .await
+            .unwrap();
+        assert_eq!(page.limit(), 50);
+        assert_eq!(page.cursor(), Some("abc"));
+
+        let req = TestRequest::with_uri("/items?limit=0").to_http_request();
+        let page = from_request::<Pagination>(&req, &mut Payload::None)
+            .await
+            .unwrap();
+        assert_eq!(page.limit(), 1);
+
+        let req = TestRequest::with_uri("/items?limit=abc").to_http_request();
+        assert!(from_request::<Pagination>(&req, &mut Payload::None)
+            .await
+            .is_err());
+    }
+
+    #[ntex_rt::test]
+    async fn test_paginated() {
+        let srv = init_service(
+            App::new()
+                .service(web::resource("/{user}/items").name("items").to(
+                    |page: Pagination| async move {
+                        Paginated::new("items", &page).next("b").prev("a")
+                    },
+                ))
+                .service(web::resource("/first").to(|page: Pagination| async move {
+                    Paginated::new("first", &page)
+                        .next("b")
+                        .resource("items", &["user1"])
+                }))
+                .service(web::resource("/last").to(|page: Pagination| async move {
+                    Paginated::new("last", &page)
+                })),
+        )
+        .await;
+
+        let req = TestRequest::with_uri("/user1/items?limit=5&cursor=x&sort=name")
+            .to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(
+            resp.headers().get(LINK).unwrap(),
+            "<http://localhost:8080/user1/items?sort=name&cursor=b&limit=5>; rel=\"next\", \
+             <http://localhost:8080/user1/items?sort=name&cursor=a&limit=5>; rel=\"prev\""
+        );
+        assert_eq!(read_body(resp).await, "items");
+
+        let req = TestRequest::with_uri("/first").to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(
+            resp.headers().get(LINK).unwrap(),
+            "<http://localhost:8080/user1/items?cursor=b&limit=20>; rel=\"next\""
+        );
+
+        let req = TestRequest::with_uri("/last").to_request();
+        let resp = call_service(&srv, req).await;
+        assert!(resp.headers().get(LINK).is_none());
+    }
+}
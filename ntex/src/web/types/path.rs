@@ -1,13 +1,19 @@
 //! Path extractor
+use std::cell::RefCell;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::str::FromStr;
+use std::sync::Arc;
 use std::{fmt, ops};
 
 use futures::future::{ready, Ready};
 use serde::de;
 
-use crate::http::Payload;
-use crate::router::PathDeserializer;
-use crate::web::error::{ErrorRenderer, PathError};
-use crate::web::{FromRequest, HttpRequest};
+use crate::http::{Payload, Uri};
+use crate::router::{self, PathDeserializer};
+use crate::web::error::{ErrorRenderer, FieldError, ParamError, PathError};
+use crate::web::{FromRequest, HttpRequest, HttpResponse};
+
+use super::de::deserialize;
 
 #[derive(PartialEq, Eq, PartialOrd, Ord)]
 /// Extract typed information from the request's path.
@@ -163,15 +169,290 @@ where
     #[inline]
     fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
         ready(
-            de::Deserialize::deserialize(PathDeserializer::new(req.match_info()))
+            deserialize(PathDeserializer::new(req.match_info()))
                 .map(|inner| Path { inner })
                 .map_err(move |e| {
+                    let e = segment_error(e, req.match_info());
                     log::debug!(
-                        "Failed during Path extractor deserialization. \
+                        "Failed during Path extractor deserialization: {}. \
                          Request path: {:?}",
+                        e,
                         req.path()
                     );
-                    PathError::from(e)
+                    PathConfig::handle(PathError::from(e), req)
+                }),
+        )
+    }
+}
+
+/// Replace sequence index in error path with name of path segment
+fn segment_error(err: FieldError, path: &router::Path<Uri>) -> FieldError {
+    let field = err.path();
+    let (idx, rest) = if field.is_empty() && path.len() == 1 {
+        (0, "")
+    } else if field.starts_with('[') {
+        let end = match field.find(']') {
+            Some(end) => end,
+            None => return err,
+        };
+        match field[1..end].parse::<usize>() {
+            Ok(idx) => (idx, &field[end + 1..]),
+            Err(_) => return err,
+        }
+    } else {
+        return err;
+    };
+
+    match path.iter().nth(idx) {
+        Some((name, _)) => {
+            let rest = rest.trim_start_matches('.');
+            let field = if rest.is_empty() {
+                name.to_string()
+            } else {
+                format!("{}.{}", name, rest)
+            };
+            FieldError::new(
+                field,
+                err.expected().map(|s| s.to_string()),
+                err.message().to_string(),
+            )
+        }
+        None => err,
+    }
+}
+
+/// Path extractor configuration.
+///
+/// Configuration is used by [`Path`](struct.Path.html) and
+/// [`Params`](struct.Params.html) extractors.
+///
+/// ```rust
+/// use ntex::web::{self, App, HttpResponse};
+///
+/// async fn index(id: web::types::Path<u32>) -> String {
+///     format!("Item {}", id)
+/// }
+///
+/// fn main() {
+///     let app = App::new().service(
+///         web::resource("/items/{id}")
+///             .app_data(web::types::PathConfig::default().error_handler(
+///                 |err, _| {
+///                     let field = err.field_error().map(|e| e.path()).unwrap_or("");
+///                     HttpResponse::BadRequest().body(format!("Invalid `{}`", field))
+///                 },
+///             ))
+///             .route(web::get().to(index)),
+///     );
+/// }
+/// ```
+#[derive(Clone, Default)]
+pub struct PathConfig {
+    ehandler:
+        Option<Arc<dyn Fn(&PathError, &HttpRequest) -> HttpResponse + Send + Sync>>,
+}
+
+impl PathConfig {
+    /// Set custom error handler.
+    ///
+    /// Handler creates response for path extraction error.
+    pub fn error_handler<F>(mut self, f: F) -> Self
+    where
+        F: Fn(&PathError, &HttpRequest) -> HttpResponse + Send + Sync + 'static,
+    {
+        self.ehandler = Some(Arc::new(f));
+        self
+    }
+
+    /// Apply error handler of request's configuration
+    fn handle(err: PathError, req: &HttpRequest) -> PathError {
+        let cfg = req.app_data::<PathConfig>();
+        if let Some(handler) = cfg.and_then(|c| c.ehandler.as_ref()) {
+            let response = handler(&err, req);
+            PathError::Custom {
+                error: Box::new(err),
+                response: RefCell::new(Some(response)),
+            }
+        } else {
+            err
+        }
+    }
+}
+
+/// Parse path parameter value.
+///
+/// Trait is used by [`Params`](struct.Params.html) extractor, each path
+/// segment is parsed separately. Parse error message includes parameter
+/// name, failed request gets *404 Not Found* response, or
+/// *400 Bad Request* response if `NOT_FOUND` is `false`.
+///
+/// ```rust
+/// use ntex::web::{self, types::FromParam};
+///
+/// enum Kind {
+///     Book,
+///     Movie,
+/// }
+///
+/// impl FromParam for Kind {
+///     type Error = &'static str;
+///     const NOT_FOUND: bool = false;
+///
+///     fn from_param(param: &str) -> Result<Self, Self::Error> {
+///         match param {
+///             "book" => Ok(Kind::Book),
+///             "movie" => Ok(Kind::Movie),
+///             _ => Err("unknown kind"),
+///         }
+///     }
+/// }
+///
+/// async fn index(params: web::types::Params<(Kind, u32)>) -> String {
+///     match params.0 {
+///         Kind::Book => format!("Book {}", params.1),
+///         Kind::Movie => format!("Movie {}", params.1),
+///     }
+/// }
+///
+/// fn main() {
+///     let app = web::App::new().service(
+///         web::resource("/{kind}/{id}").route(web::get().to(index))
+///     );
+/// }
+/// ```
+pub trait FromParam: Sized {
+    /// The type of error returned on parse failure
+    type Error: fmt::Display;
+
+    /// Respond with *404 Not Found* if parameter could not be parsed,
+    /// otherwise with *400 Bad Request*.
+    const NOT_FOUND: bool = true;
+
+    /// Parse path parameter value
+    fn from_param(param: &str) -> Result<Self, Self::Error>;
+}
+
+macro_rules! from_param_str {
+    ($($t:ty),*) => {$(
+        impl FromParam for $t {
+            type Error = <$t as FromStr>::Err;
+
+            fn from_param(param: &str) -> Result<Self, Self::Error> {
+                param.parse()
+            }
+        }
+    )*}
+}
+
+from_param_str!(
+    String, bool, char, u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128, isize,
+    f32, f64, IpAddr, Ipv4Addr, Ipv6Addr
+);
+
+/// Tuple of path parameters, implemented for tuples of `FromParam` types
+pub trait FromParams: Sized {
+    /// Parse path parameters in order of path segments
+    fn from_params(path: &router::Path<Uri>) -> Result<Self, ParamError>;
+}
+
+macro_rules! tuple_from_params ({$(($n:tt, $T:ident)),+} => {
+    impl<$($T: FromParam),+> FromParams for ($($T,)+) {
+        fn from_params(path: &router::Path<Uri>) -> Result<Self, ParamError> {
+            let mut iter = path.iter();
+            Ok(($({
+                let (name, value) = iter.next().ok_or_else(|| {
+                    ParamError::new(
+                        $n.to_string(),
+                        "parameter is missing".to_string(),
+                        true,
+                    )
+                })?;
+                $T::from_param(value).map_err(|e| {
+                    ParamError::new(name.to_string(), e.to_string(), $T::NOT_FOUND)
+                })?
+            },)+))
+        }
+    }
+});
+
+#[rustfmt::skip]
+mod m {
+    use super::*;
+
+    tuple_from_params!((0, A));
+    tuple_from_params!((0, A), (1, B));
+    tuple_from_params!((0, A), (1, B), (2, C));
+    tuple_from_params!((0, A), (1, B), (2, C), (3, D));
+    tuple_from_params!((0, A), (1, B), (2, C), (3, D), (4, E));
+    tuple_from_params!((0, A), (1, B), (2, C), (3, D), (4, E), (5, F));
+}
+
+/// Extract typed path parameters with `FromParam` trait.
+///
+/// Unlike [`Path`](struct.Path.html), each path segment is parsed
+/// separately, in order of segments. Check [`FromParam`](trait.FromParam.html)
+/// for details.
+///
+/// ```rust
+/// use std::net::IpAddr;
+/// use ntex::web;
+///
+/// async fn index(params: web::types::Params<(IpAddr, u16)>) -> String {
+///     format!("Address {}:{}", params.0, params.1)
+/// }
+///
+/// fn main() {
+///     let app = web::App::new().service(
+///         web::resource("/{addr}/{port}").route(web::get().to(index))
+///     );
+/// }
+/// ```
+pub struct Params<T> {
+    inner: T,
+}
+
+impl<T> Params<T> {
+    /// Deconstruct to an inner value
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+}
+
+impl<T> ops::Deref for Params<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.inner
+    }
+}
+
+impl<T> ops::DerefMut for Params<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.inner
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for Params<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.inner.fmt(f)
+    }
+}
+
+impl<T, Err: ErrorRenderer> FromRequest<Err> for Params<T>
+where
+    T: FromParams,
+{
+    type Error = PathError;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    #[inline]
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        ready(
+            T::from_params(req.match_info())
+                .map(|inner| Params { inner })
+                .map_err(|e| {
+                    log::debug!("{}. Request path: {:?}", e, req.path());
+                    PathConfig::handle(PathError::from(e), req)
                 }),
         )
     }
@@ -183,8 +464,10 @@ mod tests {
     use serde_derive::Deserialize;
 
     use super::*;
+    use crate::http::StatusCode;
     use crate::router::Router;
     use crate::web::test::{from_request, TestRequest};
+    use crate::web::{DefaultError, WebResponseError};
 
     #[derive(Deserialize, Debug, Display)]
     #[display(fmt = "MyStruct({}, {})", key, value)]
@@ -193,7 +476,7 @@ mod tests {
         value: String,
     }
 
-    #[derive(Deserialize)]
+    #[derive(Deserialize, Debug)]
     struct Test2 {
         key: String,
         value: u32,
@@ -242,6 +525,96 @@ mod tests {
         let () = from_request::<()>(&req, &mut pl).await.unwrap();
     }
 
+    #[ntex_rt::test]
+    async fn test_params_extract() {
+        let mut router = Router::<usize>::build();
+        router.path("/{name}/{id}/{addr}", 10).0.set_id(0);
+        let router = router.finish();
+
+        let mut req = TestRequest::with_uri("/user/32/127.0.0.1").to_srv_request();
+        router.recognize(req.match_info_mut());
+
+        let (req, mut pl) = req.into_parts();
+        let res = from_request::<Params<(String, u8, IpAddr)>>(&req, &mut pl)
+            .await
+            .unwrap();
+        assert_eq!(res.0, "user");
+        assert_eq!(res.1, 32);
+        assert_eq!(res.2, IpAddr::from([127, 0, 0, 1]));
+
+        let res = from_request::<Params<(String,)>>(&req, &mut pl)
+            .await
+            .unwrap();
+        assert_eq!(res.into_inner().0, "user");
+
+        let err = from_request::<Params<(String, bool)>>(&req, &mut pl)
+            .await
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Can not parse path parameter `id`: provided string was not `true` or `false`"
+        );
+
+        let err = from_request::<Params<(String, u8, IpAddr, u8)>>(&req, &mut pl)
+            .await
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Can not parse path parameter `3`: parameter is missing"
+        );
+    }
+
+    #[ntex_rt::test]
+    async fn test_path_error() {
+        let mut router = Router::<usize>::build();
+        router.path("/{key}/{value}/", 10).0.set_id(0);
+        let router = router.finish();
+
+        let mut req = TestRequest::with_uri("/name/user1/").to_srv_request();
+        router.recognize(req.match_info_mut());
+
+        let (req, mut pl) = req.into_parts();
+        let err = from_request::<Path<(String, u32)>>(&req, &mut pl)
+            .await
+            .unwrap_err();
+        let field = err.field_error().unwrap();
+        assert_eq!(field.path(), "value");
+        assert_eq!(field.expected(), Some("u32"));
+
+        let err = from_request::<Path<Test2>>(&req, &mut pl)
+            .await
+            .unwrap_err();
+        assert_eq!(err.field_error().unwrap().path(), "value");
+
+        let mut router = Router::<usize>::build();
+        router.path("/{id}/", 10).0.set_id(0);
+        let router = router.finish();
+
+        let mut req = TestRequest::with_uri("/abc/")
+            .data(PathConfig::default().error_handler(|err, _| {
+                let field = err.field_error().map(|e| e.path()).unwrap_or("param");
+                HttpResponse::BadRequest().body(format!("Invalid {}", field))
+            }))
+            .to_srv_request();
+        router.recognize(req.match_info_mut());
+
+        let (req, mut pl) = req.into_parts();
+        let err = from_request::<Path<u32>>(&req, &mut pl).await.unwrap_err();
+        assert_eq!(err.field_error().unwrap().path(), "id");
+        let resp: HttpResponse = WebResponseError::<DefaultError>::error_response(&err);
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(
+            resp.body().as_ref().unwrap(),
+            &crate::http::body::Body::from("Invalid id")
+        );
+
+        let err = from_request::<Params<(bool,)>>(&req, &mut pl)
+            .await
+            .unwrap_err();
+        let resp: HttpResponse = WebResponseError::<DefaultError>::error_response(&err);
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    }
+
     #[ntex_rt::test]
     async fn test_request_extract() {
         let mut router = Router::<usize>::build();
@@ -12,6 +12,7 @@ use mime::Mime;
 
 use crate::http::{error, header, HttpMessage};
 use crate::web::error::{ErrorRenderer, PayloadError};
+use crate::web::middleware::memory::{account_body, MemoryUsage};
 use crate::web::{FromRequest, HttpRequest};
 
 /// Payload extractor returns request 's payload stream.
@@ -313,6 +314,7 @@ struct HttpMessageBody {
     #[cfg(not(feature = "compress"))]
     stream: Option<crate::http::Payload>,
     err: Option<PayloadError>,
+    usage: Option<MemoryUsage>,
     fut: Option<LocalBoxFuture<'static, Result<Bytes, PayloadError>>>,
 }
 
@@ -348,6 +350,7 @@ impl HttpMessageBody {
             stream,
             limit: 262_144,
             length: len,
+            usage: req.extensions().get::<MemoryUsage>().cloned(),
             fut: None,
             err: None,
         }
@@ -366,6 +369,7 @@ impl HttpMessageBody {
             fut: None,
             err: Some(e),
             length: None,
+            usage: None,
         }
     }
 }
@@ -392,6 +396,7 @@ impl Future for HttpMessageBody {
 
         // future
         let limit = self.limit;
+        let usage = self.usage.take();
         let mut stream = self.stream.take().unwrap();
         self.fut = Some(
             async move {
@@ -399,7 +404,9 @@ impl Future for HttpMessageBody {
 
                 while let Some(item) = stream.next().await {
                     let chunk = item?;
-                    if body.len() + chunk.len() > limit {
+                    if body.len() + chunk.len() > limit
+                        || account_body(&usage, chunk.len()).is_err()
+                    {
                         return Err(PayloadError::from(error::PayloadError::Overflow));
                     } else {
                         body.extend_from_slice(&chunk);
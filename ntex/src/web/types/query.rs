@@ -9,6 +9,8 @@ use crate::http::Payload;
 use crate::web::error::{ErrorRenderer, QueryPayloadError};
 use crate::web::{FromRequest, HttpRequest};
 
+use super::de::from_urlencoded;
+
 /// Extract typed information from the request's query.
 ///
 /// **Note**: A query string consists of unordered `key=value` pairs, therefore it cannot
@@ -61,7 +63,7 @@ impl<T> Query<T> {
     where
         T: de::DeserializeOwned,
     {
-        serde_urlencoded::from_str::<T>(query_str)
+        from_urlencoded::<T>(query_str.as_bytes())
             .map(|val| Ok(Query(val)))
             .unwrap_or_else(move |e| Err(QueryPayloadError::Deserialize(e)))
     }
@@ -136,7 +138,7 @@ where
 
     #[inline]
     fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
-        serde_urlencoded::from_str::<T>(req.query_string())
+        from_urlencoded::<T>(req.query_string().as_bytes())
             .map(|val| ok(Query(val)))
             .unwrap_or_else(move |e| {
                 let e = QueryPayloadError::Deserialize(e);
@@ -185,7 +187,8 @@ mod tests {
         let req = TestRequest::with_uri("/name/user1/").to_srv_request();
         let (req, mut pl) = req.into_parts();
         let res = from_request::<Query<Id>>(&req, &mut pl).await;
-        assert!(res.is_err());
+        let err = res.err().unwrap();
+        assert_eq!(err.field_error().unwrap().message(), "missing field `id`");
 
         let req = TestRequest::with_uri("/name/user1/?id=test").to_srv_request();
         let (req, mut pl) = req.into_parts();
@@ -0,0 +1,420 @@
+use std::error::Error;
+use std::io::{self, SeekFrom};
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::SystemTime;
+use std::{cmp, fmt};
+
+use bytes::{Bytes, BytesMut};
+use futures::future::{ok, Ready};
+use futures::Stream;
+use time::OffsetDateTime;
+use tokio::io::AsyncSeek;
+
+use crate::codec::AsyncRead;
+use crate::http::body::{Body, SizedStream};
+use crate::http::header::{
+    HeaderValue, ACCEPT_RANGES, CONTENT_RANGE, CONTENT_TYPE, ETAG, IF_RANGE,
+    LAST_MODIFIED, RANGE,
+};
+use crate::http::{Method, Response, StatusCode};
+use crate::web::error::ErrorRenderer;
+use crate::web::httprequest::HttpRequest;
+use crate::web::responder::Responder;
+
+const CHUNK_SIZE: u64 = 65_536;
+
+/// Responder for seekable streams with byte-range support.
+///
+/// `RangedStream` serves any `AsyncRead + AsyncSeek` source with known
+/// length, like blobs from object stores or databases. Single byte range
+/// requests are served with *206 Partial Content* response, unsatisfiable
+/// ranges are rejected with *416 Range Not Satisfiable* response. Multiple
+/// ranges and malformed *RANGE* headers are ignored and full content is
+/// served.
+///
+/// If request contains *IF-RANGE* header, range is served only if header
+/// matches strong entity tag or last modification date of the content.
+///
+/// ```rust
+/// use std::io::Cursor;
+/// use ntex::web::{self, types::RangedStream};
+///
+/// async fn index() -> RangedStream<Cursor<Vec<u8>>> {
+///     let data = b"0123456789".to_vec();
+///     let len = data.len() as u64;
+///     RangedStream::new(Cursor::new(data), len)
+///         .content_type("application/octet-stream")
+///         .etag("v1")
+/// }
+/// # fn main() {}
+/// ```
+pub struct RangedStream<T> {
+    io: T,
+    len: u64,
+    content_type: Option<HeaderValue>,
+    etag: Option<String>,
+    last_modified: Option<String>,
+}
+
+impl<T> RangedStream<T>
+where
+    T: AsyncRead + AsyncSeek + Unpin + 'static,
+{
+    /// Create responder for stream with specified length
+    pub fn new(io: T, len: u64) -> Self {
+        RangedStream {
+            io,
+            len,
+            content_type: None,
+            etag: None,
+            last_modified: None,
+        }
+    }
+
+    /// Set response content type
+    pub fn content_type(mut self, value: &'static str) -> Self {
+        self.content_type = Some(HeaderValue::from_static(value));
+        self
+    }
+
+    /// Set strong entity tag of the content, tag must not be quoted
+    pub fn etag(mut self, tag: &str) -> Self {
+        self.etag = Some(format!("\"{}\"", tag));
+        self
+    }
+
+    /// Set last modification date of the content
+    pub fn last_modified(mut self, modified: SystemTime) -> Self {
+        self.last_modified = Some(http_date(modified));
+        self
+    }
+
+    fn if_range(&self, req: &HttpRequest) -> bool {
+        match req
+            .headers()
+            .get(IF_RANGE)
+            .and_then(|hdr| hdr.to_str().ok())
+        {
+            Some(val) => {
+                let val = val.trim();
+                if val.starts_with('"') {
+                    self.etag.as_ref().map(|tag| tag == val).unwrap_or(false)
+                } else if val.starts_with("W/") {
+                    false
+                } else {
+                    self.last_modified
+                        .as_ref()
+                        .map(|date| date == val)
+                        .unwrap_or(false)
+                }
+            }
+            None => true,
+        }
+    }
+}
+
+impl<T> fmt::Debug for RangedStream<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RangedStream")
+            .field("len", &self.len)
+            .field("etag", &self.etag)
+            .finish()
+    }
+}
+
+impl<T, Err> Responder<Err> for RangedStream<T>
+where
+    T: AsyncRead + AsyncSeek + Unpin + 'static,
+    Err: ErrorRenderer,
+{
+    type Error = Err::Container;
+    type Future = Ready<Result<Response, Self::Error>>;
+
+    fn respond_to(self, req: &HttpRequest) -> Self::Future {
+        ok(self.into_response(req))
+    }
+}
+
+impl<T> RangedStream<T>
+where
+    T: AsyncRead + AsyncSeek + Unpin + 'static,
+{
+    pub(crate) fn into_response(self, req: &HttpRequest) -> Response {
+        let mut res = Response::build(StatusCode::OK);
+        res.header(ACCEPT_RANGES, "bytes");
+        if let Some(ref val) = self.content_type {
+            res.header(CONTENT_TYPE, val.clone());
+        }
+        if let Some(ref tag) = self.etag {
+            res.header(ETAG, tag.as_str());
+        }
+        if let Some(ref date) = self.last_modified {
+            res.header(LAST_MODIFIED, date.as_str());
+        }
+
+        let range = if req.method() == Method::GET || req.method() == Method::HEAD {
+            req.headers()
+                .get(RANGE)
+                .and_then(|hdr| hdr.to_str().ok())
+                .filter(|_| self.if_range(req))
+                .map(|hdr| parse_range(hdr, self.len))
+        } else {
+            None
+        };
+
+        let (start, length) = match range {
+            Some(Ok(Some((start, end)))) => {
+                res.status(StatusCode::PARTIAL_CONTENT).header(
+                    CONTENT_RANGE,
+                    format!("bytes {}-{}/{}", start, end, self.len),
+                );
+                (start, end - start + 1)
+            }
+            Some(Err(_)) => {
+                return res
+                    .status(StatusCode::RANGE_NOT_SATISFIABLE)
+                    .header(CONTENT_RANGE, format!("bytes */{}", self.len))
+                    .finish();
+            }
+            _ => (0, self.len),
+        };
+
+        let reader = ChunkedReader {
+            io: self.io,
+            state: ReaderState::Seek(start),
+            remaining: length,
+        };
+        res.body(Body::from_message(SizedStream::new(length, reader)))
+    }
+}
+
+/// Format time as IMF-fixdate
+pub(crate) fn http_date(t: SystemTime) -> String {
+    OffsetDateTime::from(t)
+        .format("%a, %d %b %Y %H:%M:%S GMT")
+        .to_string()
+}
+
+/// Parse single byte range.
+///
+/// Returns `Ok(None)` if range should be ignored.
+fn parse_range(hdr: &str, len: u64) -> Result<Option<(u64, u64)>, ()> {
+    let hdr = hdr.trim();
+    if !hdr.starts_with("bytes=") || hdr.contains(',') {
+        return Ok(None);
+    }
+    let mut parts = hdr[6..].trim().splitn(2, '-');
+    let start = parts.next().unwrap().trim();
+    let end = match parts.next() {
+        Some(end) => end.trim(),
+        None => return Ok(None),
+    };
+
+    if start.is_empty() {
+        // suffix range
+        let suffix = match end.parse::<u64>() {
+            Ok(suffix) => suffix,
+            Err(_) => return Ok(None),
+        };
+        if suffix == 0 || len == 0 {
+            return Err(());
+        }
+        Ok(Some((len.saturating_sub(suffix), len - 1)))
+    } else {
+        let start = match start.parse::<u64>() {
+            Ok(start) => start,
+            Err(_) => return Ok(None),
+        };
+        let end = if end.is_empty() {
+            u64::max_value()
+        } else {
+            match end.parse::<u64>() {
+                Ok(end) if end >= start => end,
+                _ => return Ok(None),
+            }
+        };
+        if start >= len {
+            return Err(());
+        }
+        Ok(Some((start, cmp::min(end, len - 1))))
+    }
+}
+
+enum ReaderState {
+    Seek(u64),
+    Seeking,
+    Read,
+}
+
+struct ChunkedReader<T> {
+    io: T,
+    state: ReaderState,
+    remaining: u64,
+}
+
+impl<T> Stream for ChunkedReader<T>
+where
+    T: AsyncRead + AsyncSeek + Unpin,
+{
+    type Item = Result<Bytes, Box<dyn Error>>;
+
+    fn poll_next(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Self::Item>> {
+        let this = &mut *self;
+        loop {
+            match this.state {
+                ReaderState::Seek(pos) => {
+                    if let Err(e) = futures::ready!(
+                        Pin::new(&mut this.io).start_seek(cx, SeekFrom::Start(pos))
+                    ) {
+                        return Poll::Ready(Some(Err(e.into())));
+                    }
+                    this.state = ReaderState::Seeking;
+                }
+                ReaderState::Seeking => {
+                    if let Err(e) =
+                        futures::ready!(Pin::new(&mut this.io).poll_complete(cx))
+                    {
+                        return Poll::Ready(Some(Err(e.into())));
+                    }
+                    this.state = ReaderState::Read;
+                }
+                ReaderState::Read => {
+                    if this.remaining == 0 {
+                        return Poll::Ready(None);
+                    }
+                    let size = cmp::min(this.remaining, CHUNK_SIZE) as usize;
+                    let mut buf = BytesMut::with_capacity(size);
+                    buf.resize(size, 0);
+
+                    let n = match futures::ready!(
+                        Pin::new(&mut this.io).poll_read(cx, &mut buf)
+                    ) {
+                        Ok(0) => {
+                            let e = io::Error::new(
+                                io::ErrorKind::UnexpectedEof,
+                                "Stream is shorter than its length",
+                            );
+                            return Poll::Ready(Some(Err(e.into())));
+                        }
+                        Ok(n) => n,
+                        Err(e) => return Poll::Ready(Some(Err(e.into()))),
+                    };
+                    buf.truncate(n);
+                    this.remaining -= n as u64;
+                    return Poll::Ready(Some(Ok(buf.freeze())));
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use futures::StreamExt;
+
+    use super::*;
+    use crate::web::test::{respond_to, TestRequest};
+
+    async fn read(mut res: Response) -> Bytes {
+        let mut body = res.take_body();
+        let mut bytes = BytesMut::new();
+        while let Some(item) = body.next().await {
+            bytes.extend_from_slice(&item.unwrap());
+        }
+        bytes.freeze()
+    }
+
+    fn stream() -> RangedStream<Cursor<&'static [u8]>> {
+        RangedStream::new(Cursor::new(&b"0123456789"[..]), 10).etag("v1")
+    }
+
+    #[test]
+    fn test_parse_range() {
+        assert_eq!(parse_range("bytes=0-4", 10), Ok(Some((0, 4))));
+        assert_eq!(parse_range("bytes=5-", 10), Ok(Some((5, 9))));
+        assert_eq!(parse_range("bytes=8-100", 10), Ok(Some((8, 9))));
+        assert_eq!(parse_range("bytes=-3", 10), Ok(Some((7, 9))));
+        assert_eq!(parse_range("bytes=-30", 10), Ok(Some((0, 9))));
+        assert_eq!(parse_range("bytes=10-", 10), Err(()));
+        assert_eq!(parse_range("bytes=-0", 10), Err(()));
+        assert_eq!(parse_range("bytes=5-4", 10), Ok(None));
+        assert_eq!(parse_range("bytes=0-1,3-4", 10), Ok(None));
+        assert_eq!(parse_range("items=0-1", 10), Ok(None));
+        assert_eq!(parse_range("bytes=a-b", 10), Ok(None));
+    }
+
+    #[ntex_rt::test]
+    async fn test_ranged_stream() {
+        let req = TestRequest::default().to_http_request();
+        let resp = respond_to(stream(), &req).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(resp.headers().get(ACCEPT_RANGES).unwrap(), "bytes");
+        assert_eq!(resp.headers().get(ETAG).unwrap(), "\"v1\"");
+        assert_eq!(read(resp).await, Bytes::from_static(b"0123456789"));
+
+        let req = TestRequest::default()
+            .header(RANGE, "bytes=2-4")
+            .to_http_request();
+        let resp = respond_to(stream(), &req).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::PARTIAL_CONTENT);
+        assert_eq!(resp.headers().get(CONTENT_RANGE).unwrap(), "bytes 2-4/10");
+        assert_eq!(read(resp).await, Bytes::from_static(b"234"));
+
+        let req = TestRequest::default()
+            .header(RANGE, "bytes=20-")
+            .to_http_request();
+        let resp = respond_to(stream(), &req).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::RANGE_NOT_SATISFIABLE);
+        assert_eq!(resp.headers().get(CONTENT_RANGE).unwrap(), "bytes */10");
+    }
+
+    #[ntex_rt::test]
+    async fn test_if_range() {
+        let req = TestRequest::default()
+            .header(RANGE, "bytes=-2")
+            .header(IF_RANGE, "\"v1\"")
+            .to_http_request();
+        let resp = respond_to(stream(), &req).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::PARTIAL_CONTENT);
+        assert_eq!(read(resp).await, Bytes::from_static(b"89"));
+
+        let req = TestRequest::default()
+            .header(RANGE, "bytes=-2")
+            .header(IF_RANGE, "\"v2\"")
+            .to_http_request();
+        let resp = respond_to(stream(), &req).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(read(resp).await, Bytes::from_static(b"0123456789"));
+
+        let modified = SystemTime::UNIX_EPOCH;
+        let req = TestRequest::default()
+            .header(RANGE, "bytes=0-0")
+            .header(IF_RANGE, "Thu, 01 Jan 1970 00:00:00 GMT")
+            .to_http_request();
+        let resp = respond_to(stream().last_modified(modified), &req)
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::PARTIAL_CONTENT);
+        assert_eq!(read(resp).await, Bytes::from_static(b"0"));
+    }
+
+    #[ntex_rt::test]
+    async fn test_short_stream() {
+        let req = TestRequest::default().to_http_request();
+        let mut resp = respond_to(RangedStream::new(Cursor::new(&b"012"[..]), 10), &req)
+            .await
+            .unwrap();
+        let mut body = resp.take_body();
+        assert_eq!(
+            body.next().await.unwrap().unwrap(),
+            Bytes::from_static(b"012")
+        );
+        assert!(body.next().await.unwrap().is_err());
+    }
+}
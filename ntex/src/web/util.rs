@@ -50,6 +50,10 @@ use super::{HttpResponse, HttpResponseBuilder};
 ///         .route(web::head().to(|| async { web::HttpResponse::MethodNotAllowed() }))
 /// );
 /// ```
+///
+/// Last segment could be a catch-all segment `{*identifier}`, it matches
+/// remainder of the path including slashes, value is not percent-decoded.
+/// `{identifier}*` and `{identifier:.*}` forms are equivalent.
 pub fn resource<T: IntoPattern, Err: ErrorRenderer>(path: T) -> Resource<Err> {
     Resource::new(path)
 }
@@ -0,0 +1,505 @@
+//! Standardized `/.well-known/` endpoints
+//!
+//! ```rust
+//! use std::time::Duration;
+//! use ntex::web::{self, App};
+//! use ntex::web::wellknown::{SecurityTxt, WellKnown};
+//!
+//! fn main() {
+//!     let app = App::new().service(
+//!         WellKnown::new()
+//!             .security_txt(
+//!                 SecurityTxt::new("mailto:security@example.com")
+//!                     .expires_in(Duration::from_secs(365 * 24 * 3600))
+//!                     .policy("https://example.com/security-policy"),
+//!             )
+//!             .change_password("/account/password")
+//!             .openid_configuration("https://auth.example.com"),
+//!     );
+//! }
+//! ```
+use std::cell::RefCell;
+use std::fmt::Write;
+use std::rc::Rc;
+use std::str::FromStr;
+use std::time::{Duration, Instant, SystemTime};
+
+use bytes::Bytes;
+use futures::future::{ready, FutureExt, LocalBoxFuture, Shared};
+use time::OffsetDateTime;
+
+use crate::http::client::Client;
+use crate::http::header::{
+    CacheControl, HeaderValue, CACHE_CONTROL, CONTENT_TYPE, LOCATION,
+};
+use crate::http::Response;
+
+use super::dev::{WebServiceConfig, WebServiceFactory};
+use super::error::ErrorRenderer;
+use super::util::{get, resource, scope};
+
+const OIDC_DISCOVERY: &str = "/.well-known/openid-configuration";
+/// Cache ttl of discovery document without `max-age` directive
+const OIDC_TTL: u64 = 3600;
+
+/// `security.txt` document, RFC 9116
+#[derive(Debug, Clone)]
+pub struct SecurityTxt {
+    fields: Vec<(&'static str, String)>,
+    expires: Expires,
+}
+
+#[derive(Debug, Clone)]
+enum Expires {
+    At(SystemTime),
+    In(Duration),
+}
+
+impl SecurityTxt {
+    /// Create `security.txt` document with contact uri.
+    ///
+    /// By default document expires in one year after it get served.
+    pub fn new(contact: &str) -> Self {
+        SecurityTxt {
+            fields: vec![("Contact", contact.to_string())],
+            expires: Expires::In(Duration::from_secs(365 * 24 * 3600)),
+        }
+    }
+
+    /// Add contact uri
+    pub fn contact(mut self, contact: &str) -> Self {
+        self.fields.push(("Contact", contact.to_string()));
+        self
+    }
+
+    /// Set date after which document should be considered stale
+    pub fn expires(mut self, expires: SystemTime) -> Self {
+        self.expires = Expires::At(expires);
+        self
+    }
+
+    /// Set expiration relative to the time document get served
+    pub fn expires_in(mut self, dur: Duration) -> Self {
+        self.expires = Expires::In(dur);
+        self
+    }
+
+    /// Add link to the key for encrypted communication
+    pub fn encryption(mut self, uri: &str) -> Self {
+        self.fields.push(("Encryption", uri.to_string()));
+        self
+    }
+
+    /// Add link to the acknowledgments page
+    pub fn acknowledgments(mut self, uri: &str) -> Self {
+        self.fields.push(("Acknowledgments", uri.to_string()));
+        self
+    }
+
+    /// Set preferred languages, comma separated list of language tags
+    pub fn preferred_languages(mut self, langs: &str) -> Self {
+        self.fields.push(("Preferred-Languages", langs.to_string()));
+        self
+    }
+
+    /// Add canonical uri of the document
+    pub fn canonical(mut self, uri: &str) -> Self {
+        self.fields.push(("Canonical", uri.to_string()));
+        self
+    }
+
+    /// Add link to the security policy
+    pub fn policy(mut self, uri: &str) -> Self {
+        self.fields.push(("Policy", uri.to_string()));
+        self
+    }
+
+    /// Add link to the security related job positions
+    pub fn hiring(mut self, uri: &str) -> Self {
+        self.fields.push(("Hiring", uri.to_string()));
+        self
+    }
+
+    fn render(&self) -> String {
+        let mut body = String::new();
+        for (name, value) in &self.fields {
+            let _ = writeln!(body, "{}: {}", name, value);
+        }
+        let expires = match self.expires {
+            Expires::At(tm) => tm,
+            Expires::In(dur) => SystemTime::now() + dur,
+        };
+        let _ = writeln!(
+            body,
+            "Expires: {}",
+            OffsetDateTime::from(expires).format("%Y-%m-%dT%H:%M:%SZ")
+        );
+        body
+    }
+}
+
+/// `/.well-known/` endpoints service.
+///
+/// Service is mounted at `/.well-known` path.
+#[derive(Default)]
+pub struct WellKnown {
+    security_txt: Option<SecurityTxt>,
+    change_password: Option<String>,
+    oidc: Option<String>,
+    custom: Vec<(String, String, Bytes)>,
+}
+
+impl WellKnown {
+    /// Create empty `/.well-known/` service
+    pub fn new() -> Self {
+        WellKnown::default()
+    }
+
+    /// Serve `security.txt` document
+    pub fn security_txt(mut self, doc: SecurityTxt) -> Self {
+        self.security_txt = Some(doc);
+        self
+    }
+
+    /// Redirect `change-password` requests to the password change page
+    pub fn change_password(mut self, location: &str) -> Self {
+        self.change_password = Some(location.to_string());
+        self
+    }
+
+    /// Pass `openid-configuration` requests through to the OpenID provider.
+    ///
+    /// Discovery document is requested from the issuer and cached by each
+    /// worker for `max-age` of provider's `Cache-Control` header, or for one
+    /// hour if header is not set. Documents with `no-store` or `no-cache`
+    /// directives are not cached. Concurrent requests share single refresh,
+    /// stale document is served if refresh fails.
+    pub fn openid_configuration(mut self, issuer: &str) -> Self {
+        self.oidc = Some(format!(
+            "{}{}",
+            issuer.trim_end_matches('/'),
+            OIDC_DISCOVERY
+        ));
+        self
+    }
+
+    /// Serve static document
+    pub fn document<B: Into<Bytes>>(
+        mut self,
+        name: &str,
+        content_type: &str,
+        body: B,
+    ) -> Self {
+        self.custom
+            .push((name.to_string(), content_type.to_string(), body.into()));
+        self
+    }
+}
+
+impl<Err: ErrorRenderer> WebServiceFactory<Err> for WellKnown {
+    fn register(self, config: &mut WebServiceConfig<Err>) {
+        let mut scope = scope("/.well-known");
+
+        if let Some(doc) = self.security_txt {
+            scope =
+                scope.service(resource("/security.txt").route(get().to(move || {
+                    ready(
+                        Response::Ok()
+                            .content_type("text/plain; charset=utf-8")
+                            .body(doc.render()),
+                    )
+                })));
+        }
+        if let Some(location) = self.change_password {
+            scope =
+                scope.service(resource("/change-password").route(get().to(move || {
+                    ready(
+                        Response::Found()
+                            .header(LOCATION, location.as_str())
+                            .finish(),
+                    )
+                })));
+        }
+        if let Some(url) = self.oidc {
+            let cache = Rc::new(OidcCache {
+                url,
+                client: Rc::new(Client::new()),
+                doc: RefCell::new(None),
+                refresh: RefCell::new(None),
+            });
+            scope = scope.service(
+                resource("/openid-configuration")
+                    .route(get().to(move || openid_configuration(cache.clone()))),
+            );
+        }
+        for (name, content_type, body) in self.custom {
+            scope = scope.service(resource(format!("/{}", name)).route(get().to(
+                move || {
+                    ready(
+                        Response::Ok()
+                            .content_type(content_type.as_str())
+                            .body(body.clone()),
+                    )
+                },
+            )));
+        }
+
+        scope.register(config)
+    }
+}
+
+/// Discovery document fetched from the OpenID provider
+#[derive(Clone)]
+struct OidcDocument {
+    content_type: Option<HeaderValue>,
+    body: Bytes,
+    expires: Instant,
+}
+
+impl OidcDocument {
+    fn response(&self) -> Response {
+        let mut resp = Response::Ok();
+        if let Some(ref ct) = self.content_type {
+            resp.header(CONTENT_TYPE, ct.clone());
+        }
+        resp.body(self.body.clone())
+    }
+}
+
+/// Discovery document cache, refresh is shared by concurrent requests
+struct OidcCache {
+    url: String,
+    client: Rc<Client>,
+    doc: RefCell<Option<OidcDocument>>,
+    refresh: RefCell<Option<Shared<LocalBoxFuture<'static, Result<OidcDocument, ()>>>>>,
+}
+
+async fn openid_configuration(cache: Rc<OidcCache>) -> Response {
+    if let Some(ref doc) = *cache.doc.borrow() {
+        if doc.expires > Instant::now() {
+            return doc.response();
+        }
+    }
+
+    let refresh = cache
+        .refresh
+        .borrow_mut()
+        .get_or_insert_with(|| {
+            fetch_discovery(cache.client.clone(), cache.url.clone())
+                .boxed_local()
+                .shared()
+        })
+        .clone();
+    let res = refresh.await;
+    cache.refresh.borrow_mut().take();
+
+    match res {
+        Ok(doc) => {
+            let resp = doc.response();
+            *cache.doc.borrow_mut() = Some(doc);
+            resp
+        }
+        Err(_) => match *cache.doc.borrow() {
+            Some(ref doc) => doc.response(),
+            None => Response::BadGateway().finish(),
+        },
+    }
+}
+
+async fn fetch_discovery(client: Rc<Client>, url: String) -> Result<OidcDocument, ()> {
+    let mut res = match client.get(&url).send().await {
+        Ok(res) => res,
+        Err(e) => {
+            log::error!("Cannot fetch discovery document {:?}: {}", url, e);
+            return Err(());
+        }
+    };
+    if !res.status().is_success() {
+        log::error!(
+            "Cannot fetch discovery document {:?}: {}",
+            url,
+            res.status()
+        );
+        return Err(());
+    }
+
+    let ttl = res
+        .headers()
+        .get(CACHE_CONTROL)
+        .and_then(|val| val.to_str().ok())
+        .and_then(|val| CacheControl::from_str(val).ok())
+        .map(|cc| {
+            if cc.is_no_store() || cc.is_no_cache() {
+                0
+            } else {
+                cc.get_max_age().unwrap_or(OIDC_TTL)
+            }
+        })
+        .unwrap_or(OIDC_TTL);
+
+    match res.body().await {
+        Ok(body) => Ok(OidcDocument {
+            content_type: res.headers().get(CONTENT_TYPE).cloned(),
+            body,
+            expires: Instant::now() + Duration::from_secs(ttl),
+        }),
+        Err(e) => {
+            log::error!("Cannot read discovery document {:?}: {}", url, e);
+            Err(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::{header, StatusCode};
+    use crate::web::test::{self, call_service, init_service, read_body, TestRequest};
+    use crate::web::{self, App};
+
+    #[ntex_rt::test]
+    async fn test_well_known() {
+        let srv = init_service(
+            App::new().service(
+                WellKnown::new()
+                    .security_txt(
+                        SecurityTxt::new("mailto:security@example.com")
+                            .contact("https://example.com/contact")
+                            .expires(SystemTime::UNIX_EPOCH + Duration::from_secs(86400))
+                            .preferred_languages("en, de"),
+                    )
+                    .change_password("/account/password")
+                    .document("assetlinks.json", "application/json", "[]"),
+            ),
+        )
+        .await;
+
+        let req = TestRequest::with_uri("/.well-known/security.txt").to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(
+            read_body(resp).await,
+            Bytes::from_static(
+                b"Contact: mailto:security@example.com\n\
+                  Contact: https://example.com/contact\n\
+                  Preferred-Languages: en, de\n\
+                  Expires: 1970-01-02T00:00:00Z\n"
+            )
+        );
+
+        let req = TestRequest::with_uri("/.well-known/change-password").to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::FOUND);
+        assert_eq!(
+            resp.headers().get(header::LOCATION).unwrap(),
+            "/account/password"
+        );
+
+        let req = TestRequest::with_uri("/.well-known/assetlinks.json").to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(
+            resp.headers().get(header::CONTENT_TYPE).unwrap(),
+            "application/json"
+        );
+        assert_eq!(read_body(resp).await, Bytes::from_static(b"[]"));
+
+        let req =
+            TestRequest::with_uri("/.well-known/openid-configuration").to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[ntex_rt::test]
+    async fn test_openid_configuration() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::sync::Arc;
+
+        let hits = Arc::new(AtomicUsize::new(0));
+        let hits2 = hits.clone();
+        let upstream = test::server(move || {
+            let hits = hits2.clone();
+            App::new().service(web::resource(OIDC_DISCOVERY).to(move || {
+                hits.fetch_add(1, Ordering::Relaxed);
+                async {
+                    Response::Ok()
+                        .content_type("application/json")
+                        .header(header::CACHE_CONTROL, "public, max-age=60")
+                        .body("{\"issuer\":\"test\"}")
+                }
+            }))
+        });
+        let issuer = format!("http://{}/", upstream.addr());
+
+        let srv = init_service(
+            App::new().service(WellKnown::new().openid_configuration(&issuer)),
+        )
+        .await;
+
+        let req =
+            TestRequest::with_uri("/.well-known/openid-configuration").to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(
+            resp.headers().get(header::CONTENT_TYPE).unwrap(),
+            "application/json"
+        );
+        assert_eq!(
+            read_body(resp).await,
+            Bytes::from_static(b"{\"issuer\":\"test\"}")
+        );
+        assert_eq!(hits.load(Ordering::Relaxed), 1);
+
+        // cached document
+        let req =
+            TestRequest::with_uri("/.well-known/openid-configuration").to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(
+            read_body(resp).await,
+            Bytes::from_static(b"{\"issuer\":\"test\"}")
+        );
+        assert_eq!(hits.load(Ordering::Relaxed), 1);
+    }
+
+    #[ntex_rt::test]
+    async fn test_openid_configuration_refresh() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::sync::Arc;
+
+        let hits = Arc::new(AtomicUsize::new(0));
+        let hits2 = hits.clone();
+        let upstream = test::server(move || {
+            let hits = hits2.clone();
+            App::new().service(web::resource(OIDC_DISCOVERY).to(move || {
+                hits.fetch_add(1, Ordering::Relaxed);
+                async {
+                    crate::rt::time::delay_for(Duration::from_millis(50)).await;
+                    Response::Ok()
+                        .header(header::CACHE_CONTROL, "no-cache")
+                        .body("{}")
+                }
+            }))
+        });
+        let issuer = format!("http://{}", upstream.addr());
+
+        let srv = init_service(
+            App::new().service(WellKnown::new().openid_configuration(&issuer)),
+        )
+        .await;
+
+        // concurrent requests share single refresh
+        let call = || {
+            let req =
+                TestRequest::with_uri("/.well-known/openid-configuration").to_request();
+            call_service(&srv, req)
+        };
+        let (resp1, resp2) = futures::future::join(call(), call()).await;
+        assert_eq!(resp1.status(), StatusCode::OK);
+        assert_eq!(resp2.status(), StatusCode::OK);
+        assert_eq!(hits.load(Ordering::Relaxed), 1);
+
+        // document is not cached
+        let resp = call().await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(hits.load(Ordering::Relaxed), 2);
+    }
+}
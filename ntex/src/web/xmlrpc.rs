@@ -0,0 +1,702 @@
+//! [XML-RPC](http://xmlrpc.com/spec.md) service.
+//!
+//! Method calls are parsed into `serde_json::Value` tree and method params
+//! are deserialized with serde, results are serialized back into XML-RPC
+//! values. Errors are reported with fault responses, fault codes follow
+//! [interoperability specification](http://xmlrpc-epi.sourceforge.net/specs/rfc.fault_codes.php).
+//!
+//! ```rust
+//! use ntex::web::{self, App};
+//! use ntex::web::xmlrpc::{Fault, XmlRpc};
+//!
+//! async fn add(params: (i64, i64)) -> Result<i64, Fault> {
+//!     Ok(params.0 + params.1)
+//! }
+//!
+//! fn main() {
+//!     let app = App::new().service(XmlRpc::new("/RPC2").method("math.add", add));
+//! }
+//! ```
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::fmt::Write;
+use std::future::Future;
+use std::rc::Rc;
+
+use bytes::BytesMut;
+use futures::future::{FutureExt, LocalBoxFuture};
+use futures::StreamExt;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::{Map, Number, Value};
+
+use crate::http::header::CONTENT_TYPE;
+use crate::http::Response;
+
+use super::dev::{WebServiceConfig, WebServiceFactory};
+use super::error::ErrorRenderer;
+use super::types::Payload;
+use super::util::{post, resource};
+
+/// XML-RPC fault
+#[derive(Debug, Clone, PartialEq)]
+pub struct Fault {
+    code: i32,
+    string: String,
+}
+
+impl Fault {
+    /// Create fault with specified code and description
+    pub fn new<T: Into<String>>(code: i32, string: T) -> Self {
+        Fault {
+            code,
+            string: string.into(),
+        }
+    }
+
+    /// Method call is not well formed
+    pub fn parse_error() -> Self {
+        Fault::new(-32700, "parse error. not well formed")
+    }
+
+    /// Method call is not valid XML-RPC request
+    pub fn invalid_request() -> Self {
+        Fault::new(
+            -32600,
+            "server error. invalid xml-rpc. not conforming to spec",
+        )
+    }
+
+    /// Method does not exist
+    pub fn method_not_found() -> Self {
+        Fault::new(-32601, "server error. requested method not found")
+    }
+
+    /// Invalid method parameters
+    pub fn invalid_params() -> Self {
+        Fault::new(-32602, "server error. invalid method parameters")
+    }
+
+    /// Internal error
+    pub fn internal_error() -> Self {
+        Fault::new(-32603, "server error. internal xml-rpc error")
+    }
+
+    /// Fault code
+    pub fn code(&self) -> i32 {
+        self.code
+    }
+
+    /// Fault description
+    pub fn string(&self) -> &str {
+        &self.string
+    }
+
+    /// Render fault response
+    pub fn to_xml(&self) -> String {
+        let mut fault = Map::new();
+        fault.insert("faultCode".to_string(), Value::from(self.code));
+        fault.insert("faultString".to_string(), Value::from(self.string.as_str()));
+
+        let mut out =
+            String::from("<?xml version=\"1.0\"?>\n<methodResponse><fault><value>");
+        write_value(&mut out, &Value::Object(fault));
+        out.push_str("</value></fault></methodResponse>");
+        out
+    }
+}
+
+/// Parsed XML-RPC method call
+#[derive(Debug, Clone, PartialEq)]
+pub struct MethodCall {
+    /// Method name
+    pub name: String,
+    /// Method params
+    pub params: Vec<Value>,
+}
+
+impl MethodCall {
+    /// Parse `methodCall` document
+    pub fn from_slice(data: &[u8]) -> Result<Self, Fault> {
+        let s = std::str::from_utf8(data).map_err(|_| Fault::parse_error())?;
+        Parser { s, pos: 0 }.method_call()
+    }
+
+    /// Deserialize method params.
+    ///
+    /// Params are deserialized from sequence, so they could be
+    /// deserialized into tuples or vectors.
+    pub fn params<T: DeserializeOwned>(&self) -> Result<T, Fault> {
+        serde_json::from_value(Value::Array(self.params.clone()))
+            .map_err(|e| Fault::new(-32602, e.to_string()))
+    }
+}
+
+/// Render `methodResponse` document with single param
+pub fn method_response<T: Serialize>(value: &T) -> Result<String, Fault> {
+    let value =
+        serde_json::to_value(value).map_err(|e| Fault::new(-32603, e.to_string()))?;
+
+    let mut out =
+        String::from("<?xml version=\"1.0\"?>\n<methodResponse><params><param><value>");
+    write_value(&mut out, &value);
+    out.push_str("</value></param></params></methodResponse>");
+    Ok(out)
+}
+
+type Method = Box<dyn Fn(MethodCall) -> LocalBoxFuture<'static, Result<String, Fault>>>;
+
+/// XML-RPC service.
+///
+/// Service accepts `POST` requests at specified path. Responses are always
+/// sent with `200 OK` status, errors are reported with fault responses.
+pub struct XmlRpc {
+    path: String,
+    limit: usize,
+    methods: HashMap<String, Method>,
+}
+
+impl XmlRpc {
+    /// Create XML-RPC service for specified path
+    pub fn new(path: &str) -> Self {
+        XmlRpc {
+            path: path.to_string(),
+            limit: 262_144,
+            methods: HashMap::new(),
+        }
+    }
+
+    /// Change max size of payload. By default max size is 256Kb
+    pub fn limit(mut self, limit: usize) -> Self {
+        self.limit = limit;
+        self
+    }
+
+    /// Register method.
+    ///
+    /// Method params are deserialized from sequence of call params.
+    pub fn method<F, P, R, Fut>(mut self, name: &str, f: F) -> Self
+    where
+        F: Fn(P) -> Fut + 'static,
+        P: DeserializeOwned + 'static,
+        R: Serialize + 'static,
+        Fut: Future<Output = Result<R, Fault>> + 'static,
+    {
+        self.methods.insert(
+            name.to_string(),
+            Box::new(move |call| match call.params::<P>() {
+                Ok(params) => f(params)
+                    .map(|res| res.and_then(|res| method_response(&res)))
+                    .boxed_local(),
+                Err(err) => async move { Err(err) }.boxed_local(),
+            }),
+        );
+        self
+    }
+}
+
+struct Inner {
+    limit: usize,
+    methods: HashMap<String, Method>,
+}
+
+impl<Err: ErrorRenderer> WebServiceFactory<Err> for XmlRpc {
+    fn register(self, config: &mut WebServiceConfig<Err>) {
+        let inner = Rc::new(Inner {
+            limit: self.limit,
+            methods: self.methods,
+        });
+
+        resource(&self.path)
+            .route(post().to(move |pl: Payload| handle(inner.clone(), pl)))
+            .register(config);
+    }
+}
+
+async fn handle(inner: Rc<Inner>, mut pl: Payload) -> Response {
+    let mut body = BytesMut::new();
+    while let Some(item) = pl.next().await {
+        match item {
+            Ok(chunk) => {
+                if body.len() + chunk.len() > inner.limit {
+                    return Response::PayloadTooLarge().finish();
+                }
+                body.extend_from_slice(&chunk);
+            }
+            Err(_) => return Response::BadRequest().finish(),
+        }
+    }
+
+    let res = match MethodCall::from_slice(&body) {
+        Ok(call) => match inner.methods.get(&call.name) {
+            Some(f) => f(call).await,
+            None => Err(Fault::method_not_found()),
+        },
+        Err(err) => Err(err),
+    };
+
+    Response::Ok()
+        .header(CONTENT_TYPE, "text/xml")
+        .body(res.unwrap_or_else(|fault| fault.to_xml()))
+}
+
+fn escape(out: &mut String, s: &str) {
+    for ch in s.chars() {
+        match ch {
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '&' => out.push_str("&amp;"),
+            _ => out.push(ch),
+        }
+    }
+}
+
+fn write_value(out: &mut String, value: &Value) {
+    match value {
+        Value::Null => out.push_str("<nil/>"),
+        Value::Bool(b) => {
+            let _ = write!(out, "<boolean>{}</boolean>", if *b { 1 } else { 0 });
+        }
+        Value::Number(n) => {
+            if let Some(n) = n.as_i64() {
+                if i32::try_from(n).is_ok() {
+                    let _ = write!(out, "<int>{}</int>", n);
+                } else {
+                    let _ = write!(out, "<i8>{}</i8>", n);
+                }
+            } else {
+                let _ = write!(out, "<double>{}</double>", n.as_f64().unwrap_or(0.0));
+            }
+        }
+        Value::String(s) => {
+            out.push_str("<string>");
+            escape(out, s);
+            out.push_str("</string>");
+        }
+        Value::Array(items) => {
+            out.push_str("<array><data>");
+            for item in items {
+                out.push_str("<value>");
+                write_value(out, item);
+                out.push_str("</value>");
+            }
+            out.push_str("</data></array>");
+        }
+        Value::Object(map) => {
+            out.push_str("<struct>");
+            for (name, item) in map {
+                out.push_str("<member><name>");
+                escape(out, name);
+                out.push_str("</name><value>");
+                write_value(out, item);
+                out.push_str("</value></member>");
+            }
+            out.push_str("</struct>");
+        }
+    }
+}
+
+/// Max nesting level of arrays and structs
+const MAX_DEPTH: usize = 64;
+
+/// Minimal xml parser, sufficient for XML-RPC documents
+struct Parser<'a> {
+    s: &'a str,
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn rest(&self) -> &'a str {
+        &self.s[self.pos..]
+    }
+
+    /// Skip whitespaces, xml declaration and comments
+    fn skip(&mut self) -> Result<(), Fault> {
+        loop {
+            let rest = self.rest();
+            let trimmed = rest.trim_start();
+            self.pos += rest.len() - trimmed.len();
+
+            let end = if trimmed.starts_with("<?") {
+                "?>"
+            } else if trimmed.starts_with("<!--") {
+                "-->"
+            } else {
+                return Ok(());
+            };
+            let idx = trimmed.find(end).ok_or_else(Fault::parse_error)?;
+            self.pos += idx + end.len();
+        }
+    }
+
+    /// Parse open tag, returns tag name and flag if element is empty
+    fn open(&mut self) -> Result<(&'a str, bool), Fault> {
+        self.skip()?;
+        let rest = self.rest();
+        if !rest.starts_with('<') || rest.starts_with("</") {
+            return Err(Fault::invalid_request());
+        }
+        let end = rest.find('>').ok_or_else(Fault::parse_error)?;
+        self.pos += end + 1;
+
+        let tag = &rest[1..end];
+        let empty = tag.ends_with('/');
+        let tag = tag.trim_end_matches('/');
+        let name = tag.split_whitespace().next().unwrap_or("");
+        Ok((name, empty))
+    }
+
+    fn expect_open(&mut self, name: &str) -> Result<bool, Fault> {
+        match self.open()? {
+            (tag, empty) if tag == name => Ok(empty),
+            _ => Err(Fault::invalid_request()),
+        }
+    }
+
+    fn close(&mut self, name: &str) -> Result<(), Fault> {
+        self.skip()?;
+        let rest = self.rest();
+        if !rest.starts_with("</") {
+            return Err(Fault::invalid_request());
+        }
+        let end = rest.find('>').ok_or_else(Fault::parse_error)?;
+        if rest[2..end].trim() != name {
+            return Err(Fault::invalid_request());
+        }
+        self.pos += end + 1;
+        Ok(())
+    }
+
+    fn is_close(&mut self) -> Result<bool, Fault> {
+        self.skip()?;
+        Ok(self.rest().starts_with("</"))
+    }
+
+    /// Read character data until next tag
+    fn text(&mut self) -> Result<String, Fault> {
+        let rest = self.rest();
+        let end = rest.find('<').ok_or_else(Fault::parse_error)?;
+        self.pos += end;
+
+        let mut out = String::with_capacity(end);
+        let mut data = &rest[..end];
+        while let Some(idx) = data.find('&') {
+            out.push_str(&data[..idx]);
+            let semi = data[idx..].find(';').ok_or_else(Fault::parse_error)?;
+            let entity = &data[idx + 1..idx + semi];
+            let ch = match entity {
+                "lt" => '<',
+                "gt" => '>',
+                "amp" => '&',
+                "quot" => '"',
+                "apos" => '\'',
+                _ => {
+                    let code = if entity.starts_with("#x") {
+                        u32::from_str_radix(&entity[2..], 16).ok()
+                    } else if entity.starts_with('#') {
+                        entity[1..].parse().ok()
+                    } else {
+                        None
+                    };
+                    code.and_then(std::char::from_u32)
+                        .ok_or_else(Fault::parse_error)?
+                }
+            };
+            out.push(ch);
+            data = &data[idx + semi + 1..];
+        }
+        out.push_str(data);
+        Ok(out)
+    }
+
+    fn method_call(&mut self) -> Result<MethodCall, Fault> {
+        if self.expect_open("methodCall")? {
+            return Err(Fault::invalid_request());
+        }
+        if self.expect_open("methodName")? {
+            return Err(Fault::invalid_request());
+        }
+        let name = self.text()?.trim().to_string();
+        self.close("methodName")?;
+
+        let mut params = Vec::new();
+        if !self.is_close()? && !self.expect_open("params")? {
+            while !self.is_close()? {
+                if self.expect_open("param")? || self.expect_open("value")? {
+                    return Err(Fault::invalid_request());
+                }
+                params.push(self.value(0)?);
+                self.close("param")?;
+            }
+            self.close("params")?;
+        }
+        self.close("methodCall")?;
+
+        self.skip()?;
+        if !self.rest().is_empty() {
+            return Err(Fault::parse_error());
+        }
+        Ok(MethodCall { name, params })
+    }
+
+    /// Parse content of `value` element including closing tag
+    fn value(&mut self, depth: usize) -> Result<Value, Fault> {
+        if depth > MAX_DEPTH {
+            return Err(Fault::new(
+                -32600,
+                "server error. invalid xml-rpc. nesting is too deep",
+            ));
+        }
+
+        // value without type is a string
+        let text = self.text()?;
+        if self.rest().starts_with("</") {
+            self.close("value")?;
+            return Ok(Value::String(text));
+        }
+
+        let (tag, empty) = self.open()?;
+        let value = match tag {
+            "array" => {
+                let mut items = Vec::new();
+                if !empty {
+                    if !self.expect_open("data")? {
+                        while !self.is_close()? {
+                            if self.expect_open("value")? {
+                                items.push(Value::String(String::new()));
+                            } else {
+                                items.push(self.value(depth + 1)?);
+                            }
+                        }
+                        self.close("data")?;
+                    }
+                    self.close("array")?;
+                }
+                Value::Array(items)
+            }
+            "struct" => {
+                let mut map = Map::new();
+                if !empty {
+                    while !self.is_close()? {
+                        if self.expect_open("member")? || self.expect_open("name")? {
+                            return Err(Fault::invalid_request());
+                        }
+                        let name = self.text()?;
+                        self.close("name")?;
+                        let value = if self.expect_open("value")? {
+                            Value::String(String::new())
+                        } else {
+                            self.value(depth + 1)?
+                        };
+                        self.close("member")?;
+                        map.insert(name, value);
+                    }
+                    self.close("struct")?;
+                }
+                Value::Object(map)
+            }
+            "nil" => {
+                if !empty {
+                    self.close("nil")?;
+                }
+                Value::Null
+            }
+            _ => {
+                let text = if empty {
+                    String::new()
+                } else {
+                    let text = self.text()?;
+                    self.close(tag)?;
+                    text
+                };
+                scalar(tag, text)?
+            }
+        };
+        self.close("value")?;
+        Ok(value)
+    }
+}
+
+fn scalar(tag: &str, text: String) -> Result<Value, Fault> {
+    match tag {
+        "i4" | "int" | "i8" => text
+            .trim()
+            .parse::<i64>()
+            .map(Value::from)
+            .map_err(|_| Fault::invalid_request()),
+        "boolean" => match text.trim() {
+            "1" => Ok(Value::Bool(true)),
+            "0" => Ok(Value::Bool(false)),
+            _ => Err(Fault::invalid_request()),
+        },
+        "double" => text
+            .trim()
+            .parse::<f64>()
+            .ok()
+            .and_then(Number::from_f64)
+            .map(Value::Number)
+            .ok_or_else(Fault::invalid_request),
+        "string" | "dateTime.iso8601" => Ok(Value::String(text)),
+        "base64" => Ok(Value::String(text.split_whitespace().collect())),
+        _ => Err(Fault::invalid_request()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::web::test::{self, TestRequest};
+    use crate::web::App;
+
+    #[test]
+    fn test_parse() {
+        let call = MethodCall::from_slice(
+            br#"<?xml version="1.0"?>
+            <!-- comment -->
+            <methodCall>
+              <methodName>examples.getStateName</methodName>
+              <params>
+                <param><value><i4>41</i4></value></param>
+                <param><value>bare &amp; &#x41;</value></param>
+                <param><value><boolean>1</boolean></value></param>
+                <param><value><double>-1.5</double></value></param>
+                <param><value><array><data>
+                  <value><int>1</int></value>
+                  <value><string>two</string></value>
+                  <value/>
+                </data></array></value></param>
+                <param><value><struct>
+                  <member><name>a</name><value><nil/></value></member>
+                  <member><name>b</name><value><string/></value></member>
+                </struct></value></param>
+              </params>
+            </methodCall>"#,
+        )
+        .unwrap();
+        assert_eq!(call.name, "examples.getStateName");
+        assert_eq!(
+            Value::Array(call.params.clone()),
+            serde_json::json!([41, "bare & A", true, -1.5, [1, "two", ""], {"a": null, "b": ""}])
+        );
+        let (a, b, ..): (u8, String, bool, f64, Value, Value) = call.params().unwrap();
+        assert_eq!(a, 41);
+        assert_eq!(b, "bare & A");
+        assert_eq!(call.params::<(String,)>().unwrap_err().code(), -32602);
+
+        let call = MethodCall::from_slice(
+            b"<methodCall><methodName>ping</methodName></methodCall>",
+        )
+        .unwrap();
+        assert_eq!(call.name, "ping");
+        assert!(call.params.is_empty());
+
+        assert_eq!(
+            MethodCall::from_slice(b"<methodCall><methodName>ping").unwrap_err(),
+            Fault::parse_error()
+        );
+        assert_eq!(
+            MethodCall::from_slice(b"<methodCall><name>ping</name></methodCall>")
+                .unwrap_err(),
+            Fault::invalid_request()
+        );
+    }
+
+    #[test]
+    fn test_response() {
+        assert_eq!(
+            method_response(
+                &serde_json::json!({"a": [1, "<b>"], "c": 5_000_000_000u64})
+            )
+            .unwrap(),
+            "<?xml version=\"1.0\"?>\n<methodResponse><params><param><value><struct>\
+             <member><name>a</name><value><array><data><value><int>1</int></value>\
+             <value><string>&lt;b&gt;</string></value></data></array></value></member>\
+             <member><name>c</name><value><i8>5000000000</i8></value></member>\
+             </struct></value></param></params></methodResponse>"
+        );
+        assert_eq!(
+            Fault::new(4, "Too many parameters.").to_xml(),
+            "<?xml version=\"1.0\"?>\n<methodResponse><fault><value><struct>\
+             <member><name>faultCode</name><value><int>4</int></value></member>\
+             <member><name>faultString</name><value><string>Too many parameters.</string></value></member>\
+             </struct></value></fault></methodResponse>"
+        );
+    }
+
+    #[ntex_rt::test]
+    async fn test_service() {
+        async fn add(params: (i64, i64)) -> Result<i64, Fault> {
+            Ok(params.0 + params.1)
+        }
+
+        let srv = test::init_service(
+            App::new().service(XmlRpc::new("/RPC2").method("math.add", add)),
+        )
+        .await;
+
+        let call = |body: &'static str| {
+            TestRequest::post()
+                .uri("/RPC2")
+                .set_payload(body)
+                .to_request()
+        };
+
+        let res = test::read_response(
+            &srv,
+            call(
+                "<methodCall><methodName>math.add</methodName><params>\
+                  <param><value><int>2</int></value></param>\
+                  <param><value><int>3</int></value></param>\
+                  </params></methodCall>",
+            ),
+        )
+        .await;
+        assert_eq!(
+            res,
+            "<?xml version=\"1.0\"?>\n<methodResponse><params><param><value>\
+             <int>5</int></value></param></params></methodResponse>"
+        );
+
+        let res = test::read_response(
+            &srv,
+            call("<methodCall><methodName>math.sub</methodName></methodCall>"),
+        )
+        .await;
+        assert_eq!(res, Fault::method_not_found().to_xml());
+
+        let res = test::read_response(
+            &srv,
+            call("<methodCall><methodName>math.add</methodName></methodCall>"),
+        )
+        .await;
+        assert!(std::str::from_utf8(&res)
+            .unwrap()
+            .contains("<int>-32602</int>"));
+
+        // deeply nested values
+        let nested = |depth: usize| {
+            let mut body = String::from(
+                "<methodCall><methodName>math.add</methodName><params><param><value>",
+            );
+            for _ in 0..depth {
+                body.push_str("<array><data><value>");
+            }
+            body.push_str("<int>1</int>");
+            for _ in 0..depth {
+                body.push_str("</value></data></array>");
+            }
+            body.push_str("</value></param></params></methodCall>");
+            body
+        };
+        assert!(MethodCall::from_slice(nested(MAX_DEPTH).as_bytes()).is_ok());
+
+        let req = TestRequest::post()
+            .uri("/RPC2")
+            .set_payload(nested(5_000))
+            .to_request();
+        let res = test::read_response(&srv, req).await;
+        assert!(std::str::from_utf8(&res)
+            .unwrap()
+            .contains("<int>-32600</int>"));
+    }
+}
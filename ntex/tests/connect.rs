@@ -65,6 +65,32 @@ async fn test_static_str() {
     assert!(con.is_err());
 }
 
+#[cfg(all(feature = "tcp-fastopen", feature = "mptcp"))]
+#[ntex::test]
+async fn test_socket_opts() {
+    use futures::StreamExt;
+
+    let srv = test_server(|| {
+        fn_service(|io: TcpStream| async {
+            let mut framed = Framed::new(io, BytesCodec);
+            if let Some(Ok(item)) = framed.next().await {
+                framed.send(item.freeze()).await?;
+            }
+            Ok::<_, io::Error>(())
+        })
+    });
+
+    let conn = ntex::connect::Connector::default()
+        .tcp_fastopen(true)
+        .mptcp(true);
+    let con = conn.call(Connect::with("10", srv.addr())).await.unwrap();
+
+    let mut framed = Framed::new(con, BytesCodec);
+    framed.send(Bytes::from_static(b"test")).await.unwrap();
+    let item = framed.next().await.unwrap().unwrap();
+    assert_eq!(&item[..], b"test");
+}
+
 #[ntex::test]
 async fn test_new_service() {
     let srv = test_server(|| {
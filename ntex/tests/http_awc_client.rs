@@ -816,3 +816,137 @@ async fn client_bearer_auth() {
     let response = request.send().await.unwrap();
     assert!(response.status().is_success());
 }
+
+#[cfg(feature = "signing")]
+#[ntex::test]
+async fn test_client_signing() {
+    use ntex::http::client::signing::HttpSignature;
+
+    let srv = test::server(|| {
+        App::new().service(web::resource("/").route(web::to(|req: HttpRequest| {
+            let input = req
+                .headers()
+                .get("signature-input")
+                .map(|v| v.to_str().unwrap().to_string())
+                .unwrap_or_default();
+            let digest = req.headers().contains_key("content-digest");
+            async move {
+                if digest && req.headers().contains_key("signature") {
+                    HttpResponse::Ok().body(input)
+                } else {
+                    HttpResponse::BadRequest().finish()
+                }
+            }
+        })))
+    });
+
+    let client = Client::build()
+        .signer(
+            &format!("http://localhost:{}", srv.addr().port()),
+            HttpSignature::hmac_sha256("key", b"secret"),
+        )
+        .finish();
+
+    let mut response = client.post(srv.url("/")).send_body(STR).await.unwrap();
+    assert!(response.status().is_success());
+    let bytes = response.body().await.unwrap();
+    assert!(bytes.starts_with(
+        b"sig1=(\"@method\" \"@authority\" \"@path\" \"content-digest\");created="
+    ));
+
+    // frozen requests are signed on every send
+    let req = client.post(srv.url("/")).freeze().unwrap();
+    assert!(req.send_body(STR).await.unwrap().status().is_success());
+    assert!(req.send_body(STR).await.unwrap().status().is_success());
+
+    // other origins are not signed
+    let response = Client::new().post(srv.url("/")).send().await.unwrap();
+    assert_eq!(response.status(), ntex::http::StatusCode::BAD_REQUEST);
+}
+
+#[cfg(feature = "har")]
+#[ntex::test]
+async fn test_client_har() {
+    use ntex::http::client::har::HarRecorder;
+
+    let srv = test::server(|| {
+        App::new().service(web::resource("/").route(web::to(|body: Bytes| async move {
+            HttpResponse::Ok().header("x-test", "111").body(body)
+        })))
+    });
+
+    let har = HarRecorder::new();
+    let client = Client::build().har(har.clone()).finish();
+
+    let mut response = client.post(srv.url("/?q=1")).send_body(STR).await.unwrap();
+    assert!(response.status().is_success());
+    let bytes = response.body().await.unwrap();
+    assert_eq!(bytes, Bytes::from_static(STR.as_ref()));
+    assert_eq!(har.len(), 1);
+
+    let log = har.to_json();
+    let entry = &log["log"]["entries"][0];
+    assert_eq!(entry["request"]["method"], "POST");
+    assert_eq!(entry["request"]["queryString"][0]["name"], "q");
+    assert_eq!(entry["request"]["postData"]["text"], STR);
+    assert_eq!(entry["response"]["status"], 200);
+    assert_eq!(entry["response"]["content"]["text"], STR);
+    assert!(entry["response"]["headers"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .any(|h| h["name"] == "x-test" && h["value"] == "111"));
+
+    let mut buf = Vec::new();
+    har.write(&mut buf).unwrap();
+    assert!(!buf.is_empty());
+}
+
+#[cfg(feature = "dictionary")]
+#[ntex::test]
+async fn test_client_dictionary() {
+    use ntex::http::encoding::dictionary::Dictionary;
+
+    let dict = Dictionary::new(STR);
+    let d = dict.clone();
+    let srv = test::server(move || {
+        App::new()
+            .wrap(Compress::default().dictionary(d.clone()))
+            .service(
+                web::resource("/")
+                    .route(web::to(|| async { HttpResponse::Ok().body(STR) })),
+            )
+    });
+
+    let client = Client::build().dictionary(dict.clone()).finish();
+    let mut response = client.get(srv.url("/")).send().await.unwrap();
+    assert!(response.status().is_success());
+    assert!(response.headers().get(header::CONTENT_ENCODING).is_none());
+    let bytes = response.body().await.unwrap();
+    assert_eq!(bytes, Bytes::from_static(STR.as_ref()));
+
+    // raw response is encoded with dictionary
+    let mut response = client
+        .get(srv.url("/"))
+        .no_decompress()
+        .header(header::ACCEPT_ENCODING, "dcb")
+        .header("available-dictionary", dict.header_value())
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(
+        response.headers().get(header::CONTENT_ENCODING).unwrap(),
+        "dcb"
+    );
+    let bytes = response.body().await.unwrap();
+    assert_eq!(&bytes[4..36], dict.hash());
+    assert!(bytes.len() < 100);
+
+    // unknown dictionary
+    let client = Client::build()
+        .dictionary(Dictionary::new("other"))
+        .finish();
+    let mut response = client.get(srv.url("/")).send().await.unwrap();
+    let bytes = response.body().await.unwrap();
+    assert_eq!(bytes, Bytes::from_static(STR.as_ref()));
+}
@@ -4,7 +4,9 @@ use std::sync::Arc;
 use std::time::Duration;
 
 use futures::future::ok;
-use open_ssl::ssl::{SslAcceptor, SslConnector, SslFiletype, SslMethod, SslVerifyMode};
+use open_ssl::ssl::{
+    SslAcceptor, SslAcceptorBuilder, SslConnector, SslFiletype, SslMethod, SslVerifyMode,
+};
 
 use ntex::http::client::{Client, Connector};
 use ntex::http::test::server as test_server;
@@ -12,7 +14,7 @@ use ntex::http::{HttpService, Version};
 use ntex::service::{map_config, pipeline_factory, ServiceFactory};
 use ntex::web::{self, dev::AppConfig, App, HttpResponse};
 
-fn ssl_acceptor() -> SslAcceptor {
+fn ssl_acceptor_builder() -> SslAcceptorBuilder {
     // load ssl keys
     let mut builder = SslAcceptor::mozilla_intermediate(SslMethod::tls()).unwrap();
     builder
@@ -21,6 +23,11 @@ fn ssl_acceptor() -> SslAcceptor {
     builder
         .set_certificate_chain_file("./tests/cert.pem")
         .unwrap();
+    builder
+}
+
+fn ssl_acceptor() -> SslAcceptor {
+    let mut builder = ssl_acceptor_builder();
     builder.set_alpn_select_callback(|_, protos| {
         const H2: &[u8] = b"\x02h2";
         if protos.windows(3).any(|window| window == H2) {
@@ -86,3 +93,85 @@ async fn test_connection_reuse_h2() {
     // one connection
     assert_eq!(num.load(Ordering::Relaxed), 1);
 }
+
+#[ntex::test]
+async fn test_server_name() {
+    let names = Arc::new(std::sync::Mutex::new(Vec::new()));
+    let names2 = names.clone();
+    let num = Arc::new(AtomicUsize::new(0));
+    let num2 = num.clone();
+
+    let srv = test_server(move || {
+        let num2 = num2.clone();
+        let names2 = names2.clone();
+        let mut acceptor = ssl_acceptor_builder();
+        acceptor.set_servername_callback(move |ssl, _| {
+            names2.lock().unwrap().push(
+                ssl.servername(open_ssl::ssl::NameType::HOST_NAME)
+                    .map(|s| s.to_string()),
+            );
+            Ok(())
+        });
+
+        pipeline_factory(move |io| {
+            num2.fetch_add(1, Ordering::Relaxed);
+            ok(io)
+        })
+        .and_then(
+            HttpService::build()
+                .h1(map_config(
+                    App::new().service(
+                        web::resource("/")
+                            .route(web::to(|| async { HttpResponse::Ok() })),
+                    ),
+                    |_| AppConfig::default(),
+                ))
+                .openssl(acceptor.build())
+                .map_err(|_| ()),
+        )
+    });
+
+    // disable ssl verification
+    let mut builder = SslConnector::builder(SslMethod::tls()).unwrap();
+    builder.set_verify(SslVerifyMode::NONE);
+
+    let client = Client::build()
+        .connector(Connector::default().openssl(builder.build()).finish())
+        .finish();
+
+    let response = client
+        .get(srv.surl("/"))
+        .server_name("example.com")
+        .send()
+        .await
+        .unwrap();
+    assert!(response.status().is_success());
+
+    let response = client
+        .get(srv.surl("/"))
+        .server_name("example.org")
+        .send()
+        .await
+        .unwrap();
+    assert!(response.status().is_success());
+
+    let response = client
+        .get(srv.surl("/"))
+        .server_name("example.com")
+        .disable_sni()
+        .send()
+        .await
+        .unwrap();
+    assert!(response.status().is_success());
+
+    // connections are not shared between server names
+    assert_eq!(num.load(Ordering::Relaxed), 3);
+    assert_eq!(
+        *names.lock().unwrap(),
+        vec![
+            Some("example.com".to_string()),
+            Some("example.org".to_string()),
+            None
+        ]
+    );
+}
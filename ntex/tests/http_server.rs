@@ -668,3 +668,106 @@ async fn test_h1_on_connect() {
     let response = srv.request(Method::GET, "/").send().await.unwrap();
     assert!(response.status().is_success());
 }
+
+#[ntex::test]
+async fn test_h1_arrival() {
+    let srv = test_server(|| {
+        HttpService::build()
+            .h1(|req: Request| {
+                let arrival = *req.extensions().get::<ntex::http::Arrival>().unwrap();
+                assert!(arrival.time() <= std::time::Instant::now());
+                future::ok::<_, io::Error>(Response::Ok().finish())
+            })
+            .tcp()
+    });
+
+    let response = srv.request(Method::GET, "/").send().await.unwrap();
+    assert!(response.status().is_success());
+}
+
+#[cfg(target_os = "linux")]
+#[ntex::test]
+async fn test_h1_tcp_rtt() {
+    let srv = test_server(|| {
+        HttpService::build()
+            .on_connect(|io: &ntex::rt::net::TcpStream| ntex::http::tcp_rtt(io))
+            .h1(|req: Request| {
+                assert!(req
+                    .extensions()
+                    .get::<Option<Duration>>()
+                    .unwrap()
+                    .is_some());
+                future::ok::<_, io::Error>(Response::Ok().finish())
+            })
+            .tcp()
+    });
+
+    let response = srv.request(Method::GET, "/").send().await.unwrap();
+    assert!(response.status().is_success());
+}
+
+#[ntex::test]
+async fn test_h1_max_pending_per_connection() {
+    let srv = test_server(|| {
+        HttpService::build()
+            .max_pending_per_connection(1)
+            .h1(|req: Request| {
+                let pending = *req.extensions().get::<ntex::http::Pending>().unwrap();
+                assert_eq!(pending.connection(), 1);
+                assert_eq!(pending.worker(), 1);
+                delay_for(Duration::from_millis(50))
+                    .then(|_| ok::<_, io::Error>(Response::Ok().finish()))
+            })
+            .tcp()
+    });
+
+    let mut stream = net::TcpStream::connect(srv.addr()).unwrap();
+    stream
+        .set_read_timeout(Some(Duration::from_millis(500)))
+        .unwrap();
+    let _ = stream.write_all(b"GET /test HTTP/1.1\r\n\r\nGET /test HTTP/1.1\r\n\r\n");
+
+    let mut data = Vec::new();
+    let mut buf = [0u8; 1024];
+    while let Ok(n) = stream.read(&mut buf) {
+        if n == 0 {
+            break;
+        }
+        data.extend_from_slice(&buf[..n]);
+    }
+    let data = String::from_utf8(data).unwrap();
+    let ok = data.find("HTTP/1.1 200 OK\r\n").unwrap();
+    let rejected = data.find("HTTP/1.1 503 Service Unavailable\r\n").unwrap();
+    assert!(ok < rejected);
+
+    // pending request is finished, connection accepts new requests
+    let _ = stream.write_all(b"GET /test HTTP/1.1\r\nconnection: close\r\n\r\n");
+    let mut data = String::new();
+    let _ = stream.read_to_string(&mut data);
+    assert!(data.starts_with("HTTP/1.1 200 OK\r\n"));
+}
+
+#[ntex::test]
+async fn test_h1_max_pending() {
+    let srv = test_server(|| {
+        HttpService::build()
+            .max_pending(1)
+            .h1(|_: Request| {
+                delay_for(Duration::from_millis(100))
+                    .then(|_| ok::<_, io::Error>(Response::Ok().finish()))
+            })
+            .tcp()
+    });
+
+    let req = srv.request(Method::GET, "/").send();
+    let req2 = async {
+        delay_for(Duration::from_millis(30)).await;
+        srv.request(Method::GET, "/").send().await
+    };
+    let (res, res2) = futures::join!(req, req2);
+    assert_eq!(res.unwrap().status(), StatusCode::OK);
+    assert_eq!(res2.unwrap().status(), StatusCode::SERVICE_UNAVAILABLE);
+
+    let res = srv.request(Method::GET, "/").send().await.unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+}
@@ -1,15 +1,19 @@
-use std::io::Read;
-use std::sync::atomic::{AtomicUsize, Ordering::Relaxed};
-use std::sync::{mpsc, Arc};
+use std::io::{Read, Write};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering::Relaxed};
+use std::sync::{mpsc, Arc, Mutex};
 use std::{net, thread, time};
 
 use bytes::Bytes;
-use futures::future::{lazy, ok};
-use futures::SinkExt;
+use futures::future::{lazy, ok, ready};
+use futures::{SinkExt, StreamExt};
 
 use ntex::codec::{BytesCodec, Framed};
 use ntex::rt::net::TcpStream;
-use ntex::server::{Server, TestServer};
+use ntex::rt::time::delay_for;
+use ntex::server::udp::{self, Datagram};
+use ntex::server::{
+    balance::LeastConnections, RestartPolicy, Server, TestServer, WarmupPolicy,
+};
 use ntex::service::fn_service;
 
 #[test]
@@ -178,3 +182,467 @@ fn test_configure() {
     let _ = sys.stop();
     let _ = h.join();
 }
+
+#[test]
+fn test_warmup() {
+    let addr = TestServer::unused_addr();
+    let (tx, rx) = mpsc::channel();
+    let num = Arc::new(AtomicUsize::new(0));
+    let num2 = num.clone();
+
+    let h = thread::spawn(move || {
+        let mut sys = ntex::rt::System::new("test");
+        let srv = sys.exec(|| {
+            Server::build()
+                .workers(2)
+                .disable_signals()
+                .warmup(move || {
+                    let num = num2.clone();
+                    async move {
+                        delay_for(time::Duration::from_millis(300)).await;
+                        let _ = num.fetch_add(1, Relaxed);
+                        Ok::<_, ()>(())
+                    }
+                })
+                .bind("test", addr, move || {
+                    fn_service(|io: TcpStream| async move {
+                        let mut f = Framed::new(io, BytesCodec);
+                        f.send(Bytes::from_static(b"test")).await.unwrap();
+                        Ok::<_, ()>(())
+                    })
+                })
+                .unwrap()
+                .start()
+        });
+        let _ = tx.send((srv, ntex::rt::System::current()));
+        let _ = sys.run();
+    });
+    let (_, sys) = rx.recv().unwrap();
+
+    // connections are not handled during warm-up
+    let mut buf = [0u8; 4];
+    let mut conn = net::TcpStream::connect(addr).unwrap();
+    conn.set_read_timeout(Some(time::Duration::from_millis(100)))
+        .unwrap();
+    assert!(conn.read_exact(&mut buf).is_err());
+    assert_eq!(num.load(Relaxed), 0);
+
+    thread::sleep(time::Duration::from_millis(500));
+    assert_eq!(num.load(Relaxed), 2);
+
+    let mut conn = net::TcpStream::connect(addr).unwrap();
+    let _ = conn.read_exact(&mut buf);
+    assert_eq!(buf, b"test"[..]);
+
+    let _ = sys.stop();
+    let _ = h.join();
+}
+
+#[test]
+#[cfg(unix)]
+fn test_reuse_port() {
+    let addr = TestServer::unused_addr();
+    let (tx, rx) = mpsc::channel();
+    let num = Arc::new(AtomicUsize::new(0));
+    let num2 = num.clone();
+
+    let h = thread::spawn(move || {
+        let mut sys = ntex::rt::System::new("test");
+        let srv = sys.exec(|| {
+            Server::build()
+                .workers(2)
+                .reuse_port(true)
+                .cpu_affinity(true)
+                .disable_signals()
+                .bind("test", addr, move || {
+                    let num = num2.clone();
+                    fn_service(move |io: TcpStream| {
+                        let _ = num.fetch_add(1, Relaxed);
+                        async move {
+                            let mut f = Framed::new(io, BytesCodec);
+                            f.send(Bytes::from_static(b"test")).await.unwrap();
+                            Ok::<_, ()>(())
+                        }
+                    })
+                })
+                .unwrap()
+                .start()
+        });
+        let _ = tx.send((srv, ntex::rt::System::current()));
+        let _ = sys.run();
+    });
+    let (_, sys) = rx.recv().unwrap();
+    thread::sleep(time::Duration::from_millis(300));
+
+    for _ in 0..10 {
+        let mut buf = [0u8; 4];
+        let mut conn = net::TcpStream::connect(addr).unwrap();
+        let _ = conn.read_exact(&mut buf);
+        assert_eq!(buf, b"test"[..]);
+    }
+    assert_eq!(num.load(Relaxed), 10);
+
+    let _ = sys.stop();
+    let _ = h.join();
+}
+
+#[test]
+fn test_udp() {
+    let unused_addr = || {
+        net::UdpSocket::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+    };
+    let addr = unused_addr();
+    let addr2 = unused_addr();
+    let (tx, rx) = mpsc::channel();
+
+    let h = thread::spawn(move || {
+        let mut sys = ntex::rt::System::new("test");
+        let srv = sys.exec(|| {
+            Server::build()
+                .workers(2)
+                .disable_signals()
+                .bind_udp("echo", addr, || {
+                    fn_service(|dg: Datagram| ok::<_, ()>(Some(dg.into_data())))
+                })
+                .unwrap()
+                .bind_udp("counter", addr2, || {
+                    udp::demux(|_| {
+                        let num = Arc::new(AtomicUsize::new(0));
+                        fn_service(move |_: Datagram| {
+                            let n = num.fetch_add(1, Relaxed) + 1;
+                            ok::<_, ()>(Some(Bytes::from(n.to_string())))
+                        })
+                    })
+                })
+                .unwrap()
+                .start()
+        });
+        let _ = tx.send((srv, ntex::rt::System::current()));
+        let _ = sys.run();
+    });
+    let (_, sys) = rx.recv().unwrap();
+    thread::sleep(time::Duration::from_millis(300));
+
+    for _ in 0..5 {
+        let client = net::UdpSocket::bind("127.0.0.1:0").unwrap();
+        client
+            .set_read_timeout(Some(time::Duration::from_secs(1)))
+            .unwrap();
+        client.send_to(b"test", addr).unwrap();
+        let mut buf = [0u8; 16];
+        let (size, peer) = client.recv_from(&mut buf).unwrap();
+        assert_eq!(&buf[..size], b"test");
+        assert_eq!(peer, addr);
+    }
+
+    // per-peer services
+    for _ in 0..3 {
+        let client = net::UdpSocket::bind("127.0.0.1:0").unwrap();
+        client
+            .set_read_timeout(Some(time::Duration::from_secs(1)))
+            .unwrap();
+        for i in 1..4 {
+            client.send_to(b"test", addr2).unwrap();
+            let mut buf = [0u8; 16];
+            let (size, _) = client.recv_from(&mut buf).unwrap();
+            assert_eq!(&buf[..size], i.to_string().as_bytes());
+        }
+    }
+
+    let _ = sys.stop();
+    let _ = h.join();
+}
+
+#[test]
+fn test_balance() {
+    let addr = TestServer::unused_addr();
+    let (tx, rx) = mpsc::channel();
+    let threads = Arc::new(Mutex::new(Vec::new()));
+    let threads2 = threads.clone();
+
+    let h = thread::spawn(move || {
+        let mut sys = ntex::rt::System::new("test");
+        let srv = sys.exec(|| {
+            Server::build()
+                .workers(2)
+                .balance(LeastConnections)
+                .disable_signals()
+                .bind("test", addr, move || {
+                    let threads = threads2.clone();
+                    fn_service(move |io: TcpStream| {
+                        threads.lock().unwrap().push(thread::current().id());
+                        async move {
+                            let mut f = Framed::new(io, BytesCodec);
+                            f.send(Bytes::from_static(b"test")).await.unwrap();
+                            while let Some(Ok(_)) = f.next().await {}
+                            Ok::<_, ()>(())
+                        }
+                    })
+                })
+                .unwrap()
+                .start()
+        });
+        let _ = tx.send((srv, ntex::rt::System::current()));
+        let _ = sys.run();
+    });
+    let (_, sys) = rx.recv().unwrap();
+    thread::sleep(time::Duration::from_millis(300));
+
+    let connect = || {
+        let mut buf = [0u8; 4];
+        let mut conn = net::TcpStream::connect(addr).unwrap();
+        let _ = conn.read_exact(&mut buf);
+        assert_eq!(buf, b"test"[..]);
+        conn
+    };
+
+    let c1 = connect();
+    let _c2 = connect();
+    let c3 = connect();
+    drop(c1);
+    drop(c3);
+    thread::sleep(time::Duration::from_millis(200));
+
+    // first worker does not have connections
+    let _c4 = connect();
+    let threads = threads.lock().unwrap().clone();
+    assert_ne!(threads[0], threads[1]);
+    assert_eq!(threads[0], threads[2]);
+    assert_eq!(threads[0], threads[3]);
+
+    let _ = sys.stop();
+    let _ = h.join();
+}
+
+#[test]
+fn test_warmup_failed() {
+    let addr = TestServer::unused_addr();
+    let (tx, rx) = mpsc::channel();
+
+    let h = thread::spawn(move || {
+        let mut sys = ntex::rt::System::new("test");
+        let srv = sys.exec(|| {
+            Server::build()
+                .workers(1)
+                .disable_signals()
+                .warmup(|| ready(Err::<(), _>("cache is not available")))
+                .warmup_policy(WarmupPolicy::Continue)
+                .bind("test", addr, move || {
+                    fn_service(|io: TcpStream| async move {
+                        let mut f = Framed::new(io, BytesCodec);
+                        f.send(Bytes::from_static(b"test")).await.unwrap();
+                        Ok::<_, ()>(())
+                    })
+                })
+                .unwrap()
+                .start()
+        });
+        let _ = tx.send((srv, ntex::rt::System::current()));
+        let _ = sys.run();
+    });
+    let (_, sys) = rx.recv().unwrap();
+    thread::sleep(time::Duration::from_millis(300));
+
+    let mut buf = [0u8; 4];
+    let mut conn = net::TcpStream::connect(addr).unwrap();
+    let _ = conn.read_exact(&mut buf);
+    assert_eq!(buf, b"test"[..]);
+
+    let _ = sys.stop();
+    let _ = h.join();
+}
+
+fn panic_server(
+    addr: net::SocketAddr,
+    policy: RestartPolicy,
+    warmup: Option<Arc<AtomicUsize>>,
+) -> (Server, ntex::rt::System, thread::JoinHandle<()>) {
+    let (tx, rx) = mpsc::channel();
+    let panic = Arc::new(AtomicBool::new(true));
+
+    let h = thread::spawn(move || {
+        let mut sys = ntex::rt::System::new("test");
+        let srv = sys.exec(|| {
+            let mut builder = Server::build()
+                .workers(1)
+                .disable_signals()
+                .restart_policy(policy);
+            if let Some(num) = warmup {
+                builder = builder.warmup(move || {
+                    let num = num.clone();
+                    async move {
+                        let _ = num.fetch_add(1, Relaxed);
+                        Ok::<_, ()>(())
+                    }
+                });
+            }
+            builder
+                .bind("test", addr, move || {
+                    let panic = panic.clone();
+                    fn_service(move |io: TcpStream| {
+                        if panic.swap(false, Relaxed) {
+                            panic!("worker failure");
+                        }
+                        async move {
+                            let mut f = Framed::new(io, BytesCodec);
+                            f.send(Bytes::from_static(b"test")).await.unwrap();
+                            Ok::<_, ()>(())
+                        }
+                    })
+                })
+                .unwrap()
+                .start()
+        });
+        let _ = tx.send((srv, ntex::rt::System::current()));
+        let _ = sys.run();
+    });
+    let (srv, sys) = rx.recv().unwrap();
+    (srv, sys, h)
+}
+
+#[test]
+fn test_worker_restart() {
+    let addr = TestServer::unused_addr();
+    let (srv, sys, h) =
+        panic_server(addr, RestartPolicy::new().backoff(100, 1000), None);
+    let metrics = srv.metrics();
+    thread::sleep(time::Duration::from_millis(300));
+    assert_eq!(metrics.workers(), 1);
+
+    // first connection kills worker
+    let _ = net::TcpStream::connect(addr).unwrap();
+    thread::sleep(time::Duration::from_millis(500));
+    assert_eq!(metrics.faults(), 1);
+    assert_eq!(metrics.restarts(), 1);
+    assert_eq!(metrics.abandoned(), 0);
+    assert_eq!(metrics.workers(), 1);
+
+    let mut buf = [0u8; 4];
+    let mut conn = net::TcpStream::connect(addr).unwrap();
+    let _ = conn.read_exact(&mut buf);
+    assert_eq!(buf, b"test"[..]);
+
+    let _ = sys.stop();
+    let _ = h.join();
+}
+
+#[test]
+fn test_worker_restart_warmup() {
+    let addr = TestServer::unused_addr();
+    let num = Arc::new(AtomicUsize::new(0));
+    let (srv, sys, h) = panic_server(addr, RestartPolicy::new(), Some(num.clone()));
+    let metrics = srv.metrics();
+    thread::sleep(time::Duration::from_millis(300));
+    assert_eq!(num.load(Relaxed), 1);
+
+    // restarted worker runs warm-up
+    let _ = net::TcpStream::connect(addr).unwrap();
+    thread::sleep(time::Duration::from_millis(300));
+    assert_eq!(metrics.restarts(), 1);
+    assert_eq!(num.load(Relaxed), 2);
+
+    let mut buf = [0u8; 4];
+    let mut conn = net::TcpStream::connect(addr).unwrap();
+    let _ = conn.read_exact(&mut buf);
+    assert_eq!(buf, b"test"[..]);
+
+    let _ = sys.stop();
+    let _ = h.join();
+}
+
+#[test]
+fn test_worker_restart_limit() {
+    let addr = TestServer::unused_addr();
+    let (srv, sys, h) = panic_server(addr, RestartPolicy::never(), None);
+    let metrics = srv.metrics();
+    thread::sleep(time::Duration::from_millis(300));
+
+    let _ = net::TcpStream::connect(addr).unwrap();
+    thread::sleep(time::Duration::from_millis(300));
+    assert_eq!(metrics.faults(), 1);
+    assert_eq!(metrics.restarts(), 0);
+    assert_eq!(metrics.abandoned(), 1);
+    assert_eq!(metrics.workers(), 0);
+
+    let _ = sys.stop();
+    let _ = h.join();
+}
+
+#[test]
+#[cfg(unix)]
+fn test_processes() {
+    // worker processes re-execute this test, address is passed with env
+    let addr = match std::env::var("NTEX_TEST_PROCESSES_ADDR") {
+        Ok(addr) => addr.parse().unwrap(),
+        Err(_) => {
+            let addr = TestServer::unused_addr();
+            std::env::set_var("NTEX_TEST_PROCESSES_ADDR", addr.to_string());
+            addr
+        }
+    };
+    let (tx, rx) = mpsc::channel();
+
+    let h = thread::spawn(move || {
+        let mut sys = ntex::rt::System::new("test");
+        let srv = sys.exec(|| {
+            Server::build()
+                .workers(1)
+                .processes(2)
+                .process_args(vec!["test_processes", "--exact", "-q"])
+                .disable_signals()
+                .shutdown_timeout(1)
+                .bind("test", addr, move || {
+                    fn_service(|io: TcpStream| async move {
+                        let mut f = Framed::new(io, BytesCodec);
+                        if let Some(Ok(item)) = f.next().await {
+                            if item == b"exit"[..] {
+                                std::process::exit(1);
+                            }
+                            f.send(Bytes::from_static(b"test")).await.unwrap();
+                        }
+                        Ok::<_, ()>(())
+                    })
+                })
+                .unwrap()
+                .start()
+        });
+        let _ = tx.send((srv, ntex::rt::System::current()));
+        let _ = sys.run();
+    });
+    let (srv, sys) = rx.recv().unwrap();
+    let metrics = srv.metrics();
+    thread::sleep(time::Duration::from_millis(500));
+    assert_eq!(metrics.workers(), 2);
+
+    for _ in 0..4 {
+        let mut buf = [0u8; 4];
+        let mut conn = net::TcpStream::connect(addr).unwrap();
+        conn.write_all(b"ping").unwrap();
+        conn.read_exact(&mut buf).unwrap();
+        assert_eq!(buf, b"test"[..]);
+    }
+
+    // worker process exits, master starts new one
+    let mut conn = net::TcpStream::connect(addr).unwrap();
+    conn.write_all(b"exit").unwrap();
+    thread::sleep(time::Duration::from_millis(500));
+    assert_eq!(metrics.faults(), 1);
+    assert_eq!(metrics.restarts(), 1);
+    assert_eq!(metrics.workers(), 2);
+
+    let mut buf = [0u8; 4];
+    let mut conn = net::TcpStream::connect(addr).unwrap();
+    conn.write_all(b"ping").unwrap();
+    conn.read_exact(&mut buf).unwrap();
+    assert_eq!(buf, b"test"[..]);
+
+    futures::executor::block_on(srv.stop(true));
+    assert_eq!(metrics.workers(), 0);
+    assert_eq!(metrics.faults(), 1);
+
+    let _ = sys.stop();
+    let _ = h.join();
+}
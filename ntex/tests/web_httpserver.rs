@@ -5,7 +5,7 @@ use std::{thread, time::Duration};
 use open_ssl::ssl::SslAcceptorBuilder;
 
 use ntex::server::TestServer;
-use ntex::web::{self, App, HttpResponse, HttpServer};
+use ntex::web::{self, App, HttpRequest, HttpResponse, HttpServer, HttpServerConfig};
 
 #[cfg(unix)]
 #[ntex::test]
@@ -65,6 +65,131 @@ async fn test_run() {
     let _ = sys.stop();
 }
 
+#[cfg(unix)]
+#[ntex::test]
+async fn test_startup_report() {
+    let addr = TestServer::unused_addr();
+    let (tx, rx) = mpsc::channel();
+    let (report_tx, report_rx) = mpsc::channel();
+
+    thread::spawn(move || {
+        let mut sys = ntex::rt::System::new("test");
+
+        let srv = sys.exec(|| {
+            HttpServer::new(|| {
+                App::new()
+                    .service(
+                        web::resource("/")
+                            .name("index")
+                            .to(|| async { HttpResponse::Ok() }),
+                    )
+                    .service(web::scope("/api").service(
+                        web::resource("/users/{id}").to(|| async { HttpResponse::Ok() }),
+                    ))
+            })
+            .workers(2)
+            .system_exit()
+            .disable_signals()
+            .startup_report(move |report| {
+                let _ = report_tx.send(report.clone());
+            })
+            .bind(format!("{}", addr))
+            .unwrap()
+            .run()
+        });
+
+        let _ = tx.send((srv, ntex::rt::System::current()));
+        let _ = sys.run();
+    });
+    let (srv, sys) = rx.recv().unwrap();
+
+    let report = report_rx.recv_timeout(Duration::from_secs(5)).unwrap();
+    assert_eq!(report.workers(), 2);
+    assert_eq!(report.listeners().len(), 1);
+    assert_eq!(report.listeners()[0].addr(), addr.to_string());
+    assert!(!report.listeners()[0].secure());
+    assert_eq!(report.listeners()[0].protocols(), &["http/1.1"]);
+
+    let routes: Vec<_> = report
+        .routes()
+        .iter()
+        .map(|r| (r.pattern(), r.name()))
+        .collect();
+    assert_eq!(routes, vec![("/", "index"), ("/api/users/{id}", "")]);
+    assert!(report.to_string().contains("Route /api/users/{id}"));
+
+    // report is delivered once
+    assert!(report_rx.recv_timeout(Duration::from_millis(200)).is_err());
+
+    // stop
+    let _ = srv.stop(false);
+
+    thread::sleep(Duration::from_millis(100));
+    let _ = sys.stop();
+}
+
+#[cfg(unix)]
+#[ntex::test]
+async fn test_from_config() {
+    let addr = TestServer::unused_addr();
+    let (tx, rx) = mpsc::channel();
+
+    let config: HttpServerConfig = serde_json::from_str(&format!(
+        r#"{{
+            "bind": ["{}"],
+            "workers": 1,
+            "keep_alive": 0,
+            "client_timeout": 1000,
+            "shutdown_timeout": 1,
+            "hostname": "localhost"
+        }}"#,
+        addr
+    ))
+    .unwrap();
+    assert!(serde_json::from_str::<HttpServerConfig>(r#"{"bnd": []}"#).is_err());
+
+    thread::spawn(move || {
+        let mut sys = ntex::rt::System::new("test");
+
+        let srv = sys.exec(|| {
+            HttpServer::from_config(
+                || {
+                    App::new().service(web::resource("/").to(
+                        |req: HttpRequest| async move {
+                            HttpResponse::Ok().body(req.app_config().host().to_string())
+                        },
+                    ))
+                },
+                &config,
+            )
+            .unwrap()
+            .system_exit()
+            .disable_signals()
+            .run()
+        });
+
+        let _ = tx.send((srv, ntex::rt::System::current()));
+        let _ = sys.run();
+    });
+    let (srv, sys) = rx.recv().unwrap();
+
+    let client = ntex::http::client::Client::new();
+    let mut response = client
+        .get(format!("http://{}/", addr))
+        .send()
+        .await
+        .unwrap();
+    assert!(response.status().is_success());
+    let body = response.body().await.unwrap();
+    assert_eq!(&body[..], b"localhost");
+
+    // stop
+    let _ = srv.stop(false);
+
+    thread::sleep(Duration::from_millis(100));
+    let _ = sys.stop();
+}
+
 #[cfg(feature = "openssl")]
 fn ssl_acceptor() -> std::io::Result<SslAcceptorBuilder> {
     use open_ssl::ssl::{SslAcceptor, SslFiletype, SslMethod, SslVerifyMode};
@@ -103,7 +228,6 @@ fn client() -> ntex::http::client::Client {
 #[ntex::test]
 #[cfg(feature = "openssl")]
 async fn test_openssl() {
-    use ntex::web::HttpRequest;
 
     let addr = TestServer::unused_addr();
     let (tx, rx) = mpsc::channel();
@@ -153,7 +277,6 @@ async fn test_rustls() {
     use std::fs::File;
     use std::io::BufReader;
 
-    use ntex::web::HttpRequest;
     use rust_tls::{
         internal::pemfile::{certs, pkcs8_private_keys},
         NoClientAuth, ServerConfig as RustlsServerConfig,
@@ -126,6 +126,89 @@ async fn test_body_gzip() {
     assert_eq!(Bytes::from(dec), Bytes::from_static(STR.as_ref()));
 }
 
+#[ntex::test]
+async fn test_body_gzip_media_types() {
+    let srv = test::server_with(test::config().h1(), || {
+        App::new()
+            .wrap(
+                Compress::new(ContentEncoding::Gzip)
+                    .media_types(web::middleware::MediaTypePredicate::compressible()),
+            )
+            .service(web::resource("/text").route(web::to(|| async {
+                HttpResponse::Ok().content_type("text/plain").body(STR)
+            })))
+            .service(web::resource("/png").route(web::to(|| async {
+                HttpResponse::Ok().content_type("image/png").body(STR)
+            })))
+    });
+
+    let mut response = srv
+        .get("/text")
+        .no_decompress()
+        .header(ACCEPT_ENCODING, "gzip")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.headers().get(CONTENT_ENCODING).unwrap(), "gzip");
+    let bytes = response.body().await.unwrap();
+    let mut e = GzDecoder::new(&bytes[..]);
+    let mut dec = Vec::new();
+    e.read_to_end(&mut dec).unwrap();
+    assert_eq!(Bytes::from(dec), Bytes::from_static(STR.as_ref()));
+
+    let mut response = srv
+        .get("/png")
+        .no_decompress()
+        .header(ACCEPT_ENCODING, "gzip")
+        .send()
+        .await
+        .unwrap();
+    assert!(response.headers().get(CONTENT_ENCODING).is_none());
+    let bytes = response.body().await.unwrap();
+    assert_eq!(bytes, Bytes::from_static(STR.as_ref()));
+}
+
+#[ntex::test]
+async fn test_body_gzip_min_size() {
+    let srv = test::server_with(test::config().h1(), || {
+        App::new()
+            .wrap(Compress::new(ContentEncoding::Gzip).min_size(STR.len()))
+            .service(
+                web::resource("/large")
+                    .route(web::to(|| async { HttpResponse::Ok().body(STR) })),
+            )
+            .service(
+                web::resource("/small")
+                    .route(web::to(|| async { HttpResponse::Ok().body(&STR[..10]) })),
+            )
+    });
+
+    let mut response = srv
+        .get("/large")
+        .no_decompress()
+        .header(ACCEPT_ENCODING, "gzip")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.headers().get(CONTENT_ENCODING).unwrap(), "gzip");
+    let bytes = response.body().await.unwrap();
+    let mut e = GzDecoder::new(&bytes[..]);
+    let mut dec = Vec::new();
+    e.read_to_end(&mut dec).unwrap();
+    assert_eq!(Bytes::from(dec), Bytes::from_static(STR.as_ref()));
+
+    let mut response = srv
+        .get("/small")
+        .no_decompress()
+        .header(ACCEPT_ENCODING, "gzip")
+        .send()
+        .await
+        .unwrap();
+    assert!(response.headers().get(CONTENT_ENCODING).is_none());
+    let bytes = response.body().await.unwrap();
+    assert_eq!(bytes, Bytes::from_static(STR[..10].as_ref()));
+}
+
 #[ntex::test]
 async fn test_body_gzip2() {
     let srv = test::server_with(test::config().h1(), || {
@@ -377,8 +460,7 @@ async fn test_no_chunking() {
     let srv = test::server_with(test::config().h1(), || {
         App::new().service(web::resource("/").route(web::to(move || async {
             HttpResponse::Ok()
-                .no_chunking()
-                .content_length(STR.len() as u64)
+                .no_chunking(STR.len() as u64)
                 .streaming(TestBody::new(Bytes::from_static(STR.as_ref()), 24))
         })))
     });